@@ -0,0 +1,50 @@
+//! Times a small seeded render of the Cornell box, and how soon a
+//! full-size progressive render of it shows its first preview.
+//!
+//! Run with `cargo bench --bench cornell_box`.
+
+use std::time::Instant;
+
+use ray_tracer::scenes;
+
+const RUNS: u32 = 5;
+
+fn main() {
+    let mut scene = scenes::build("cornell_box").expect("cornell_box is a built-in scene");
+    scene
+        .camera
+        .set_image_width(100)
+        .set_aa_samples(100)
+        .set_seed(Some(1));
+
+    let best = (0..RUNS)
+        .map(|_| {
+            let (image, stats) = scene.render_timed();
+            std::hint::black_box(image);
+            stats
+        })
+        .min_by_key(|stats| stats.render_time)
+        .unwrap();
+    println!(
+        "cornell_box {:>8.3} s, {:.2} M rays/s (best of {})",
+        best.render_time.as_secs_f64(),
+        best.rays_per_second() / 1e6,
+        RUNS
+    );
+
+    // The first preview doesn't depend on the samples per pixel, so one is
+    // enough to get through the rest quickly.
+    let mut full = scenes::build("cornell_box").expect("cornell_box is a built-in scene");
+    full.camera.set_aa_samples(1).set_seed(Some(1));
+    let started = Instant::now();
+    let mut first = None;
+    full.render_progressive(&mut |_| {
+        first.get_or_insert(started.elapsed());
+    });
+    println!(
+        "cornell_box first preview at {} by {}: {:.3} s",
+        full.camera.image_width,
+        full.camera.image_height(),
+        first.unwrap().as_secs_f64()
+    );
+}
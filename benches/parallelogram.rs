@@ -0,0 +1,46 @@
+//! Times `Parallelogram::hit` for rays that hit, miss the quad, and miss the plane.
+//!
+//! Run with `cargo bench --bench parallelogram`.
+
+use std::{hint::black_box, sync::Arc, time::Instant};
+
+use ray_tracer::{
+    color, point, Hittable, IndependentSampler, Interval, Lambertian, Parallelogram, Ray, Vec3,
+};
+
+const ITERATIONS: u32 = 10_000_000;
+
+fn main() {
+    let quad = Parallelogram::new(
+        point(-1., -1., 0.),
+        (Vec3(2., 0., 0.), Vec3(0., 2., 0.)),
+        Arc::new(Lambertian::from(color(0.5, 0.5, 0.5))),
+    );
+    let cases = [
+        ("hit", Vec3(0.2, 0.3, -1.)),
+        ("miss quad", Vec3(3., 0., -1.)),
+        ("miss plane", Vec3(1., 0., 0.)),
+    ];
+    let mut rng = IndependentSampler::new(0);
+    for (name, direction) in cases {
+        let ray = Ray {
+            origin: point(0., 0., 5.),
+            direction,
+            time: 0.0,
+        };
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            black_box(quad.hit(
+                black_box(&ray),
+                Interval::new(0.001, f64::INFINITY),
+                &mut rng,
+            ));
+        }
+        let elapsed = start.elapsed();
+        println!(
+            "{:<10} {:>8.2} ns/hit",
+            name,
+            elapsed.as_nanos() as f64 / ITERATIONS as f64
+        );
+    }
+}
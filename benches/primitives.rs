@@ -0,0 +1,79 @@
+//! Renders the random spheres and a terrain mesh through BVHs whose leaves
+//! store the built-in shapes in place, then through the same trees with
+//! every leaf behind an `Arc<dyn Hittable>`, and compares their sizes.
+//!
+//! Run with `cargo bench --bench primitives`.
+
+use std::{mem, sync::Arc, time::Instant};
+
+use ray_tracer::{
+    color,
+    scenes::{self, Scene},
+    BoundNode, Hittable, HittableList, Lambertian, MeshTriangle, Primitive, Sphere,
+};
+
+const RUNS: u32 = 3;
+
+fn time(name: &str, scene: &Scene) {
+    let mut best = f64::INFINITY;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        std::hint::black_box(scene.render_image());
+        best = best.min(start.elapsed().as_secs_f64());
+    }
+    println!("{:<28} {:>8.3} s (best of {})", name, best, RUNS);
+}
+
+/// Renders `scene` with its world a BVH over `leaves`, stored in place and
+/// then each behind an `Arc`, whose contents `arc_bytes` counts.
+fn compare(name: &str, mut scene: Scene, leaves: Vec<Arc<dyn Hittable>>, arc_bytes: usize) {
+    let inline = BoundNode::from_objects(&leaves, 0..leaves.len());
+    let dynamic = BoundNode::from_primitives(leaves.into_iter().map(Primitive::Other).collect());
+    let handles = dynamic.estimated_bytes() + arc_bytes;
+    for (kind, bvh, bytes) in [
+        ("in place", inline, None),
+        ("behind Arcs", dynamic, Some(handles)),
+    ] {
+        let bytes = bytes.unwrap_or_else(|| bvh.estimated_bytes());
+        scene.world = HittableList::from(Arc::new(bvh));
+        time(&format!("{} ({})", name, kind), &scene);
+        println!("{:<28} {:>8.1} MB", "", bytes as f64 / 1e6);
+    }
+}
+
+fn main() {
+    let mut spheres = scenes::random_spheres(0);
+    spheres
+        .camera
+        .set_image_width(200)
+        .set_aa_samples(10)
+        .set_seed(Some(1));
+    let leaves = spheres.world.objects[0]
+        .bvh_leaves()
+        .expect("the spheres are under a BVH");
+    let arc_bytes = leaves.len() * (mem::size_of::<Sphere>() + 2 * mem::size_of::<usize>());
+    compare("random spheres", spheres, leaves, arc_bytes);
+
+    let material = Arc::new(Lambertian::from(color(0.45, 0.55, 0.3)));
+    let field = scenes::hills(161, material.clone());
+    let data = Arc::new(field.mesh().data().clone());
+    let triangles: Vec<Arc<dyn Hittable>> = (0..data.faces.len())
+        .map(|face| {
+            Arc::new(MeshTriangle::new(
+                data.clone(),
+                face,
+                material.clone(),
+                None,
+            )) as Arc<dyn Hittable>
+        })
+        .collect();
+    let arc_bytes =
+        triangles.len() * (mem::size_of::<MeshTriangle>() + 2 * mem::size_of::<usize>());
+    let mut terrain = scenes::terrain();
+    terrain
+        .camera
+        .set_image_width(200)
+        .set_aa_samples(10)
+        .set_seed(Some(1));
+    compare("terrain mesh", terrain, triangles, arc_bytes);
+}
@@ -0,0 +1,46 @@
+//! Renders the terrain scene with its heightfield found through a uniform
+//! grid, then through a BVH over the same triangles.
+//!
+//! Run with `cargo bench --bench terrain`.
+
+use std::{sync::Arc, time::Instant};
+
+use ray_tracer::{
+    color,
+    scenes::{self, Scene},
+    HittableList, Lambertian, MeshAccel, TriangleMesh,
+};
+
+const RUNS: u32 = 3;
+
+fn time(name: &str, scene: &Scene) {
+    let mut best = f64::INFINITY;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        std::hint::black_box(scene.render_image());
+        best = best.min(start.elapsed().as_secs_f64());
+    }
+    println!("terrain ({}) {:>8.3} s (best of {})", name, best, RUNS);
+}
+
+fn main() {
+    let material = Arc::new(Lambertian::from(color(0.45, 0.55, 0.3)));
+    let field = scenes::hills(161, material.clone());
+
+    for accel in [MeshAccel::Grid, MeshAccel::Bvh] {
+        let start = Instant::now();
+        let mesh = TriangleMesh::with_accel(field.mesh().data().clone(), material.clone(), accel);
+        let build = start.elapsed().as_secs_f64();
+        let mut scene = scenes::terrain();
+        let mut world = HittableList::new();
+        world.add(mesh);
+        scene.world = world;
+        scene
+            .camera
+            .set_image_width(200)
+            .set_aa_samples(10)
+            .set_seed(Some(1));
+        let name = format!("{:?}, built in {:.3} s", accel, build);
+        time(&name, &scene);
+    }
+}
@@ -0,0 +1,3 @@
+//! The renderer's JavaScript bindings, packaged for the page in index.html.
+
+pub use ray_tracer::wasm::*;
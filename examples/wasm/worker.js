@@ -0,0 +1,21 @@
+import init, { WebScene } from "./pkg/ray_tracer_wasm.js";
+
+await init();
+postMessage({ type: "scenes", names: WebScene.builtIn() });
+
+onmessage = ({ data }) => {
+  try {
+    const started = performance.now();
+    const scene = new WebScene(data.scene);
+    scene.setSize(data.width, data.samples);
+    if (data.sky) {
+      scene.setBackground(0.7, 0.8, 1.0);
+    }
+    const rgba = scene.renderRgba((done, rows) => postMessage({ type: "progress", done, rows }));
+    const message = { type: "done", rgba, width: scene.width, height: scene.height, ms: performance.now() - started };
+    scene.free();
+    postMessage(message, [rgba.buffer]);
+  } catch (error) {
+    postMessage({ type: "error", message: String(error) });
+  }
+};
@@ -1,5 +1,237 @@
-use crate::{HittableList, Point, Ray, Vec3};
+pub mod accumulation;
+pub mod adaptive;
+pub mod aovs;
+pub mod aperture;
+pub mod events;
+pub mod focus;
+pub mod fog;
+pub mod mattes;
+pub mod parallel;
+pub mod postprocess;
+pub mod progress;
+pub mod progressive;
+pub mod projection;
+pub mod region;
+pub mod snapshot;
+pub mod supersample;
 
+pub use accumulation::*;
+pub use adaptive::*;
+pub use aovs::*;
+pub use aperture::*;
+pub use events::*;
+pub use fog::*;
+pub use mattes::*;
+pub use postprocess::*;
+pub use progress::*;
+pub use progressive::*;
+pub use projection::*;
+pub use region::*;
+pub use snapshot::*;
+
+use std::{
+    f64::consts::PI, fmt, io, ops::Range, path::Path, str::FromStr, sync::Arc, time::Duration,
+};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    count_trace_stats, heat_color, log, rays_traced, scenes::RenderMetadata, take_stage_times,
+    take_trace_stats, Background, BounceKind, BounceLimits, Color, DownscaleFilter, Error,
+    Exposure, HaltonSampler, Hittable, HittableList, ImageBuffer, IndependentSampler, Interval,
+    LightList, PathDepth, Point, PrimaryHit, Ray, RaySample, Sampler, SamplerKind, SobolSampler,
+    SolidBackground, Stage, StageTime, TraceStats, TraversalStats, Vec3, Verbosity,
+    DEFAULT_RAY_EPSILON, DEFAULT_ROULETTE_DEPTH,
+};
+
+/// The aspect ratio of `Camera::default()`, and so of scene files that
+/// leave out the camera altogether.
+pub const DEFAULT_ASPECT_RATIO: f64 = 16.0 / 9.0;
+
+/// A camera setting that can't produce an image.
+#[derive(Debug, Clone)]
+pub enum CameraError {
+    InvalidWidth(i32),
+    InvalidHeight(i32),
+    InvalidAspectRatio(f64),
+    /// A scene file's camera gave both, which could disagree.
+    AspectRatioAndHeight {
+        aspect_ratio: f64,
+        image_height: i32,
+    },
+    /// A scene file's camera gave neither, leaving its shape unknown.
+    MissingAspectRatioOrHeight,
+    InvalidFov(f64),
+    InvalidOrthographicWidth(f64),
+    InvalidFisheyeFov(f64),
+    InvalidSamples(i32),
+    InvalidMaxDepth(i32),
+    InvalidRouletteDepth(i32),
+    InvalidBounceLimit {
+        kind: BounceKind,
+        limit: i32,
+    },
+    InvalidDefocusAngle(f64),
+    InvalidFocusDistance(f64),
+    InvalidApertureBlades(u32),
+    InvalidEyeSeparation(f64),
+    InvalidFog(FogSettings),
+    InvalidExposure(Exposure),
+    InvalidSupersample(u32),
+    InvalidClip {
+        near: f64,
+        far: f64,
+    },
+    InvalidShutter {
+        open: f64,
+        close: f64,
+    },
+    InvalidAdaptive(AdaptiveSampling),
+    NonFiniteView,
+    LookFromIsLookAt(Point),
+    UpParallelToView {
+        up: Vec3,
+        direction: Vec3,
+    },
+}
+
+impl fmt::Display for CameraError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CameraError::InvalidWidth(width) => {
+                write!(f, "image width must be positive, got {}", width)
+            }
+            CameraError::InvalidHeight(height) => {
+                write!(f, "image height must be positive, got {}", height)
+            }
+            CameraError::InvalidAspectRatio(ratio) => {
+                write!(f, "aspect ratio must be positive, got {}", ratio)
+            }
+            CameraError::AspectRatioAndHeight {
+                aspect_ratio,
+                image_height,
+            } => write!(
+                f,
+                "give either an aspect ratio or an image height, not both, got {} and {}",
+                aspect_ratio, image_height
+            ),
+            CameraError::MissingAspectRatioOrHeight => {
+                write!(f, "give either an aspect ratio or an image height")
+            }
+            CameraError::InvalidFov(fov) => write!(
+                f,
+                "vertical field of view must be between 0 and 180 degrees, got {}",
+                fov
+            ),
+            CameraError::InvalidOrthographicWidth(width) => {
+                write!(f, "orthographic view width must be positive, got {}", width)
+            }
+            CameraError::InvalidFisheyeFov(fov) => write!(
+                f,
+                "fisheye field of view must be more than 0 and at most 360 degrees, got {}",
+                fov
+            ),
+            CameraError::InvalidSamples(samples) => {
+                write!(f, "samples per pixel must be positive, got {}", samples)
+            }
+            CameraError::InvalidMaxDepth(depth) => {
+                write!(f, "max depth must be positive, got {}", depth)
+            }
+            CameraError::InvalidRouletteDepth(depth) => write!(
+                f,
+                "Russian roulette start depth must not be negative, got {}",
+                depth
+            ),
+            CameraError::InvalidBounceLimit { kind, limit } => write!(
+                f,
+                "{} bounce limit must not be negative, got {}",
+                kind, limit
+            ),
+            CameraError::InvalidDefocusAngle(angle) => write!(
+                f,
+                "defocus angle must be between 0 and 180 degrees, got {}",
+                angle
+            ),
+            CameraError::InvalidFocusDistance(distance) => {
+                write!(f, "focus distance must be positive, got {}", distance)
+            }
+            CameraError::InvalidApertureBlades(blades) => {
+                write!(f, "an aperture needs at least 3 blades, got {}", blades)
+            }
+            CameraError::InvalidEyeSeparation(ipd) => {
+                write!(f, "eye separation must be positive, got {}", ipd)
+            }
+            CameraError::InvalidFog(fog) => write!(
+                f,
+                "fog needs a finite color, a positive falloff, and a finite start, got {:?}",
+                fog
+            ),
+            CameraError::InvalidExposure(exposure) => write!(
+                f,
+                "exposure needs a positive multiplier or key, and to exclude under half the pixels, got {:?}",
+                exposure
+            ),
+            CameraError::InvalidSupersample(factor) => {
+                write!(f, "supersampling factor must be at least 1, got {}", factor)
+            }
+            CameraError::InvalidClip { near, far } => write!(
+                f,
+                "the clipping planes must be at least 0 and the far one past the near one, got {} to {}",
+                near, far
+            ),
+            CameraError::InvalidShutter { open, close } => write!(
+                f,
+                "the shutter must open and close between times 0 and 1, in that order, got {} to {}",
+                open, close
+            ),
+            CameraError::InvalidAdaptive(adaptive) => write!(
+                f,
+                "adaptive sampling needs a positive tolerance and at least 2 samples, no more than the most, got {:?}",
+                adaptive
+            ),
+            CameraError::NonFiniteView => {
+                write!(f, "look_from, look_at, and up must be finite")
+            }
+            CameraError::LookFromIsLookAt(point) => write!(
+                f,
+                "look_from and look_at are both {:?}, so there is no view direction",
+                point
+            ),
+            CameraError::UpParallelToView { up, direction } => write!(
+                f,
+                "up {:?} is parallel to the view direction {:?}",
+                up, direction
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CameraError {}
+
+/// The height of an image `width` wide at `aspect_ratio`, rounded rather
+/// than cut short, so 1920 at 16:9 comes out 1080 and not 1079, and at
+/// least 1.
+fn height_for(width: i32, aspect_ratio: f64) -> i32 {
+    ((width as f64 / aspect_ratio).round() as i32).max(1)
+}
+
+/// Checks that a view has a direction and that `up` picks a roll around it.
+fn check_view(look_from: Point, look_at: Point, up: Vec3) -> Result<(), CameraError> {
+    if !(look_from.is_finite() && look_at.is_finite() && up.is_finite()) {
+        return Err(CameraError::NonFiniteView);
+    }
+    let direction = look_at - look_from;
+    if direction.length_squared() == 0.0 {
+        return Err(CameraError::LookFromIsLookAt(look_from));
+    }
+    if Vec3::cross(&up, &direction.unit()).length() <= 1e-9 * up.length() {
+        return Err(CameraError::UpParallelToView { up, direction });
+    }
+    Ok(())
+}
+
+#[derive(Clone)]
 pub struct Camera {
     /* Image Dimensions */
     pub aspect_ratio: f64,
@@ -15,16 +247,111 @@ pub struct Camera {
     pub look_from: Point,
     pub look_at: Point,
     pub up: Vec3,
+    // Perspective, parallel rays for technical drawings, or a panorama.
+    pub projection: Projection,
+
+    /* Defocus */
+    // Angle of the cone of rays through each pixel, in degrees; 0 is a pinhole.
+    pub defocus_angle: f64,
+    // Distance to the plane in perfect focus; by default, that of look_at.
+    pub focus_dist: Option<f64>,
+    // Shape of the lens opening; round by default.
+    pub aperture: ApertureShape,
+    defocus_disk_u: Vec3,
+    defocus_disk_v: Vec3,
+    // From the center straight ahead to the plane in focus.
+    to_focus_plane: Vec3,
+    // Unit vectors to the camera's right and up, for panoramic rays.
+    right: Vec3,
+    upward: Vec3,
+
+    /* Motion Blur */
+    // The times the shutter is open for, between 0 and 1; rays are spread
+    // evenly over them. Open and close are equal for a still frame.
+    pub shutter_open: f64,
+    pub shutter_close: f64,
 
     /* Anti-Aliasing */
     pub aa_samples: i32,
     aa_scale: f64,
+    // Render this many times wider and taller, then shrink the image back
+    // down with the filter; see `render_supersampled`.
+    pub supersample: u32,
+    pub downscale_filter: DownscaleFilter,
 
     /* Ray Behavior */
     pub max_depth: i32,
+    // Caps on each kind of bounce within the max depth.
+    pub bounce_limits: BounceLimits,
+    // End paths at random once they have bounced `roulette_depth` times,
+    // the dimmer the likelier, rather than always following them to the
+    // max depth; see `PathDepth`.
+    pub russian_roulette: bool,
+    pub roulette_depth: i32,
+    pub background: Arc<dyn Background>,
+    // Leave out the background and environment seen directly, giving the
+    // image an alpha channel to composite it over something else.
+    pub transparent: bool,
+    // Fade what the camera sees toward a color with distance.
+    pub fog: Option<FogSettings>,
+    // How much to brighten or darken the image before it is written.
+    pub exposure: Exposure,
+    // Hits closer than this along a ray are ignored.
+    pub ray_epsilon: f64,
+    // The distances along the view direction between which the camera sees
+    // anything: nearer surfaces are cut away, and farther ones not there.
+    pub near_clip: f64,
+    pub far_clip: f64,
+
+    /* Sampling */
+    // The base of every sample's random stream; renders with the same seed are identical.
+    pub seed: Option<u64>,
+    pub sampler: SamplerKind,
+    // Offset each pixel's samples by a blue-noise mask so the noise left over is fine-grained.
+    pub blue_noise: bool,
+    // Sample each pixel until it converges rather than `aa_samples` times.
+    pub adaptive: Option<AdaptiveSampling>,
+
+    /* Stereo */
+    // Render a view for each eye and compose them into one image.
+    pub stereo: Option<StereoSettings>,
+
+    /* Threads */
+    // How many threads render the image; 0, the default, is one per core.
+    pub threads: usize,
+
+    /* Progress */
+    // Called after each row of a render; see `set_progress`.
+    pub progress: Option<ProgressHook>,
+
+    /* Post-processing */
+    // Filters for finished renders, such as a denoiser; see `post_process`.
+    pub post_process: Vec<Arc<dyn PostProcess>>,
+}
+
+/// The first book's camera: 16:9 and 400 pixels wide, looking down -Z from
+/// the origin with a 90° field of view, at 100 samples and 50 bounces.
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_ASPECT_RATIO,
+            400,
+            90.0,
+            Vec3(0., 0., 0.),
+            Vec3(0., 0., -1.),
+            Vec3(0., 1., 0.),
+            100,
+            50,
+        )
+    }
 }
 
 impl Camera {
+    /// Creates a camera, panicking if the settings can't produce an image.
+    /// See `try_new` for the checked version. The image's height is its
+    /// width over `aspect_ratio`, to the nearest pixel; `with_dimensions`
+    /// gives it exactly.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         aspect_ratio: f64,
         image_width: i32,
@@ -35,57 +362,238 @@ impl Camera {
         aa_samples: i32,
         max_depth: i32,
     ) -> Self {
-        let image_height = (image_width as f64 / aspect_ratio) as i32;
-        let image_height = if image_height >= 1 { image_height } else { 1 };
-
-        // let look_from = Vec3(0.0, 0.0, 0.0);
-        // let look_at = Vec3(0.0, 0.0, -1.0);
-        // let up = Vec3(0.0, 1.0, 0.0);
-        let center = look_from;
-
-        let focal_length = (look_from - look_at).length();
-        let theta = vfov.to_radians();
-        let h = f64::tan(theta / 2.0);
-        let viewport_height = 2.0 * h * focal_length;
-        let viewport_width = viewport_height * (image_width as f64 / image_height as f64);
-
-        let w = (look_from - look_at).unit();
-        let u = Vec3::cross(&up, &w).unit();
-        let v = Vec3::cross(&w, &u);
-        // let basis = (u, v, w);
-
-        let viewport_u = viewport_width * u;
-        let viewport_v = viewport_height * -v;
-
-        let pixel_delta_u = viewport_u / image_width as f64;
-        let pixel_delta_v = viewport_v / image_height as f64;
-
-        let viewport_upper_left =
-            center - (w * focal_length) - (viewport_u / 2.0) - (viewport_v / 2.0);
-        let pixel_00 = viewport_upper_left + ((pixel_delta_u + pixel_delta_v) / 2.0);
-
-        // let aa_samples = 10;
-        let aa_scale = 1.0 / aa_samples as f64;
+        Self::try_new(
+            aspect_ratio,
+            image_width,
+            vfov,
+            look_from,
+            look_at,
+            up,
+            aa_samples,
+            max_depth,
+        )
+        .unwrap_or_else(|e| panic!("invalid camera: {}", e))
+    }
 
-        // let max_depth = 10;
+    #[allow(clippy::too_many_arguments)]
+    pub fn try_new(
+        aspect_ratio: f64,
+        image_width: i32,
+        vfov: f64,
+        look_from: Point,
+        look_at: Point,
+        up: Vec3,
+        aa_samples: i32,
+        max_depth: i32,
+    ) -> Result<Self, CameraError> {
+        let image_height = height_for(image_width, aspect_ratio);
 
-        Self {
+        let mut camera = Self {
             aspect_ratio,
             image_width,
             image_height,
-            center,
-            pixel_00,
-            pixel_delta_u,
-            pixel_delta_v,
+            center: look_from,
+            pixel_00: Vec3(0.0, 0.0, 0.0),
+            pixel_delta_u: Vec3(0.0, 0.0, 0.0),
+            pixel_delta_v: Vec3(0.0, 0.0, 0.0),
             vfov,
+            projection: Projection::Perspective,
             look_from,
             look_at,
             up,
-            // basis,
+            defocus_angle: 0.0,
+            focus_dist: None,
+            aperture: ApertureShape::Circle,
+            defocus_disk_u: Vec3(0.0, 0.0, 0.0),
+            defocus_disk_v: Vec3(0.0, 0.0, 0.0),
+            to_focus_plane: Vec3(0.0, 0.0, 0.0),
+            right: Vec3(0.0, 0.0, 0.0),
+            upward: Vec3(0.0, 0.0, 0.0),
+            shutter_open: 0.0,
+            shutter_close: 0.0,
             aa_samples,
-            aa_scale,
+            aa_scale: 1.0 / aa_samples as f64,
+            supersample: 1,
+            downscale_filter: DownscaleFilter::default(),
             max_depth,
+            bounce_limits: BounceLimits::default(),
+            russian_roulette: false,
+            roulette_depth: DEFAULT_ROULETTE_DEPTH,
+            background: Arc::new(SolidBackground::default()),
+            transparent: false,
+            fog: None,
+            exposure: Exposure::default(),
+            ray_epsilon: DEFAULT_RAY_EPSILON,
+            near_clip: 0.0,
+            far_clip: f64::INFINITY,
+            seed: None,
+            sampler: SamplerKind::default(),
+            blue_noise: false,
+            adaptive: None,
+            stereo: None,
+            threads: 0,
+            progress: None,
+            post_process: Vec::new(),
+        };
+        camera.validate()?;
+        camera.update_viewport();
+        Ok(camera)
+    }
+
+    /// Creates a camera making images exactly `image_width` by
+    /// `image_height`, such as 1920 by 1080, its aspect ratio theirs,
+    /// panicking if the settings can't produce an image. See
+    /// `try_with_dimensions` for the checked version.
+    #[allow(clippy::too_many_arguments)]
+    pub fn with_dimensions(
+        image_width: i32,
+        image_height: i32,
+        vfov: f64,
+        look_from: Point,
+        look_at: Point,
+        up: Vec3,
+        aa_samples: i32,
+        max_depth: i32,
+    ) -> Self {
+        Self::try_with_dimensions(
+            image_width,
+            image_height,
+            vfov,
+            look_from,
+            look_at,
+            up,
+            aa_samples,
+            max_depth,
+        )
+        .unwrap_or_else(|e| panic!("invalid camera: {}", e))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn try_with_dimensions(
+        image_width: i32,
+        image_height: i32,
+        vfov: f64,
+        look_from: Point,
+        look_at: Point,
+        up: Vec3,
+        aa_samples: i32,
+        max_depth: i32,
+    ) -> Result<Self, CameraError> {
+        if image_width <= 0 {
+            return Err(CameraError::InvalidWidth(image_width));
+        }
+        if image_height <= 0 {
+            return Err(CameraError::InvalidHeight(image_height));
+        }
+        let mut camera = Self::try_new(
+            image_width as f64 / image_height as f64,
+            image_width,
+            vfov,
+            look_from,
+            look_at,
+            up,
+            aa_samples,
+            max_depth,
+        )?;
+        camera.set_image_size(image_width, image_height);
+        Ok(camera)
+    }
+
+    /// Checks every setting; the setters don't, so call this after changing them.
+    pub fn validate(&self) -> Result<(), CameraError> {
+        if self.image_width <= 0 {
+            return Err(CameraError::InvalidWidth(self.image_width));
+        }
+        if self.image_height <= 0 {
+            return Err(CameraError::InvalidHeight(self.image_height));
+        }
+        if !(self.aspect_ratio.is_finite() && self.aspect_ratio > 0.0) {
+            return Err(CameraError::InvalidAspectRatio(self.aspect_ratio));
+        }
+        if !(self.vfov > 0.0 && self.vfov < 180.0) {
+            return Err(CameraError::InvalidFov(self.vfov));
+        }
+        if !self.projection.is_valid() {
+            match self.projection {
+                Projection::Orthographic { width } => {
+                    return Err(CameraError::InvalidOrthographicWidth(width))
+                }
+                Projection::Fisheye { fov } => return Err(CameraError::InvalidFisheyeFov(fov)),
+                Projection::Perspective | Projection::Equirectangular => {}
+            }
+        }
+        if self.aa_samples <= 0 {
+            return Err(CameraError::InvalidSamples(self.aa_samples));
+        }
+        if self.max_depth <= 0 {
+            return Err(CameraError::InvalidMaxDepth(self.max_depth));
+        }
+        if self.russian_roulette && self.roulette_depth < 0 {
+            return Err(CameraError::InvalidRouletteDepth(self.roulette_depth));
+        }
+        for kind in [
+            BounceKind::Diffuse,
+            BounceKind::Glossy,
+            BounceKind::Specular,
+            BounceKind::Transmission,
+        ] {
+            if let Some(limit) = self.bounce_limits.limit(kind).filter(|&limit| limit < 0) {
+                return Err(CameraError::InvalidBounceLimit { kind, limit });
+            }
+        }
+        if !(self.defocus_angle >= 0.0 && self.defocus_angle < 180.0) {
+            return Err(CameraError::InvalidDefocusAngle(self.defocus_angle));
+        }
+        if let Some(distance) = self
+            .focus_dist
+            .filter(|distance| !(distance.is_finite() && *distance > 0.0))
+        {
+            return Err(CameraError::InvalidFocusDistance(distance));
         }
+        if let ApertureShape::Polygon { blades, .. } = self.aperture {
+            if blades < 3 {
+                return Err(CameraError::InvalidApertureBlades(blades));
+            }
+        }
+        if let Some(stereo) = self
+            .stereo
+            .filter(|stereo| !(stereo.ipd.is_finite() && stereo.ipd > 0.0))
+        {
+            return Err(CameraError::InvalidEyeSeparation(stereo.ipd));
+        }
+        if let Some(fog) = self
+            .fog
+            .filter(|fog| !(fog.color.is_finite() && fog.falloff > 0.0 && fog.start.is_finite()))
+        {
+            return Err(CameraError::InvalidFog(fog));
+        }
+        if self.supersample == 0 {
+            return Err(CameraError::InvalidSupersample(self.supersample));
+        }
+        if let Some(adaptive) = self.adaptive.filter(|adaptive| !adaptive.is_valid()) {
+            return Err(CameraError::InvalidAdaptive(adaptive));
+        }
+        if !(0.0 <= self.near_clip && self.near_clip < self.far_clip)
+            || self.near_clip.is_infinite()
+        {
+            return Err(CameraError::InvalidClip {
+                near: self.near_clip,
+                far: self.far_clip,
+            });
+        }
+        if !self.exposure.is_valid() {
+            return Err(CameraError::InvalidExposure(self.exposure));
+        }
+        if !(0.0 <= self.shutter_open
+            && self.shutter_open <= self.shutter_close
+            && self.shutter_close <= 1.0)
+        {
+            return Err(CameraError::InvalidShutter {
+                open: self.shutter_open,
+                close: self.shutter_close,
+            });
+        }
+        check_view(self.look_from, self.look_at, self.up)
     }
 
     pub fn set_aa_samples(&mut self, aa_samples: i32) -> &mut Self {
@@ -94,23 +602,226 @@ impl Camera {
         self
     }
 
+    pub fn set_ray_epsilon(&mut self, ray_epsilon: f64) -> &mut Self {
+        self.ray_epsilon = ray_epsilon;
+        self
+    }
+
+    pub fn set_clip(&mut self, near: f64, far: f64) -> &mut Self {
+        self.near_clip = near;
+        self.far_clip = far;
+        self
+    }
+
     pub fn set_max_depth(&mut self, max_depth: i32) -> &mut Self {
         self.max_depth = max_depth;
         self
     }
 
-    pub fn move_camera(&mut self, look_from: Point, look_at: Point, up: Vec3) -> &mut Self {
+    pub fn set_bounce_limits(&mut self, bounce_limits: BounceLimits) -> &mut Self {
+        self.bounce_limits = bounce_limits;
+        self
+    }
+
+    /// Turns Russian roulette on from `depth` bounces into each path, or off.
+    pub fn set_russian_roulette(&mut self, enabled: bool, depth: i32) -> &mut Self {
+        self.russian_roulette = enabled;
+        self.roulette_depth = depth;
+        self
+    }
+
+    /// Blurs everything off the plane `focus_dist` away (or through look_at)
+    /// with rays spreading `defocus_angle` degrees from each pixel.
+    pub fn set_defocus(&mut self, defocus_angle: f64, focus_dist: Option<f64>) -> &mut Self {
+        self.defocus_angle = defocus_angle;
+        self.focus_dist = focus_dist;
+        self.update_viewport();
+        self
+    }
+
+    /// Shapes the lens opening as a regular polygon with `blades` sides, turned
+    /// `rotation` degrees, or as a disk without blades.
+    pub fn set_aperture(&mut self, blades: Option<u32>, rotation: f64) -> &mut Self {
+        self.set_aperture_shape(ApertureShape::from_blades(blades, rotation))
+    }
+
+    /// Shapes the lens opening as `shape`, such as a mask image's.
+    pub fn set_aperture_shape(&mut self, shape: ApertureShape) -> &mut Self {
+        self.aperture = shape;
+        self
+    }
+
+    /// Keeps the shutter open from time `open` to `close`, so objects moving
+    /// between them blur.
+    pub fn set_shutter(&mut self, open: f64, close: f64) -> &mut Self {
+        self.shutter_open = open;
+        self.shutter_close = close;
+        self
+    }
+
+    /// Sets how the scene maps onto the image. An equirectangular panorama
+    /// needs a 2:1 image, so any other aspect ratio is changed to it, with a
+    /// warning.
+    pub fn set_projection(&mut self, projection: Projection) -> &mut Self {
+        self.projection = projection;
+        if projection == Projection::Equirectangular && self.aspect_ratio != 2.0 {
+            log(
+                Verbosity::Normal,
+                format_args!(
+                    "warning: equirectangular images are 2:1; aspect ratio {} changed to 2",
+                    self.aspect_ratio
+                ),
+            );
+            self.aspect_ratio = 2.0;
+            self.image_height = height_for(self.image_width, 2.0);
+        }
+        self.update_viewport();
+        self
+    }
+
+    /// Makes the image `image_width` wide, keeping the aspect ratio: its
+    /// height is the nearest whole number of pixels to it, at least 1.
+    pub fn set_image_width(&mut self, image_width: i32) -> &mut Self {
+        self.image_width = image_width;
+        self.image_height = height_for(image_width, self.aspect_ratio);
+        self.update_viewport();
+        self
+    }
+
+    /// Makes the image exactly `image_width` by `image_height`, the aspect
+    /// ratio following.
+    pub fn set_image_size(&mut self, image_width: i32, image_height: i32) -> &mut Self {
+        self.image_width = image_width;
+        self.image_height = image_height;
+        self.aspect_ratio = image_width as f64 / image_height as f64;
+        self.update_viewport();
+        self
+    }
+
+    pub fn set_supersample(&mut self, factor: u32, filter: DownscaleFilter) -> &mut Self {
+        self.supersample = factor;
+        self.downscale_filter = filter;
+        self
+    }
+
+    pub fn set_seed(&mut self, seed: Option<u64>) -> &mut Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn set_sampler(&mut self, sampler: SamplerKind) -> &mut Self {
+        self.sampler = sampler;
+        self
+    }
+
+    pub fn set_blue_noise(&mut self, blue_noise: bool) -> &mut Self {
+        self.blue_noise = blue_noise;
+        self
+    }
+
+    /// The samples each pixel takes, or with adaptive sampling, the most it
+    /// may.
+    pub fn samples_per_pixel(&self) -> i32 {
+        match &self.adaptive {
+            Some(adaptive) => adaptive.max_samples as i32,
+            None => self.aa_samples,
+        }
+    }
+
+    /// Samples each pixel until it converges, see `AdaptiveSampling`, or
+    /// with `None`, `aa_samples` times.
+    pub fn set_adaptive(&mut self, adaptive: Option<AdaptiveSampling>) -> &mut Self {
+        self.adaptive = adaptive;
+        self
+    }
+
+    /// Caps the threads a render uses; 0 uses one per core.
+    pub fn set_threads(&mut self, threads: usize) -> &mut Self {
+        self.threads = threads;
+        self
+    }
+
+    pub fn set_stereo(&mut self, stereo: Option<StereoSettings>) -> &mut Self {
+        self.stereo = stereo;
+        self
+    }
+
+    pub fn image_height(&self) -> i32 {
+        self.image_height
+    }
+
+    /// The width and height of the images the camera renders, twice as
+    /// wide as `image_width` for side-by-side stereo.
+    pub fn output_size(&self) -> (usize, usize) {
+        let eyes = match self.stereo {
+            Some(StereoSettings {
+                mode: StereoMode::SideBySide,
+                ..
+            }) => 2,
+            _ => 1,
+        };
+        (eyes * self.image_width as usize, self.image_height as usize)
+    }
+
+    pub fn set_background(&mut self, background: Color) -> &mut Self {
+        self.set_background_arc(Arc::new(SolidBackground(background)))
+    }
+
+    pub fn set_background_arc(&mut self, background: Arc<dyn Background>) -> &mut Self {
+        self.background = background;
+        self
+    }
+
+    pub fn set_transparent(&mut self, transparent: bool) -> &mut Self {
+        self.transparent = transparent;
+        self
+    }
+
+    pub fn set_fog(&mut self, fog: Option<FogSettings>) -> &mut Self {
+        self.fog = fog;
+        self
+    }
+
+    pub fn set_exposure(&mut self, exposure: Exposure) -> &mut Self {
+        self.exposure = exposure;
+        self
+    }
+
+    /// Points the camera from `look_from` at `look_at`. A view with no
+    /// direction or an `up` along it is rejected and leaves the camera as it was.
+    pub fn move_camera(
+        &mut self,
+        look_from: Point,
+        look_at: Point,
+        up: Vec3,
+    ) -> Result<&mut Self, CameraError> {
+        check_view(look_from, look_at, up)?;
         self.look_from = look_from;
         self.look_at = look_at;
         self.up = up;
+        self.update_viewport();
+        Ok(self)
+    }
 
+    /// Recomputes the pixel grid from the view and image size.
+    fn update_viewport(&mut self) {
+        let (look_from, look_at, up) = (self.look_from, self.look_at, self.up);
         self.center = look_from;
 
-        let focal_length = (look_from - look_at).length();
-        let theta = self.vfov.to_radians();
-        let h = f64::tan(theta / 2.0);
-        let viewport_height = 2.0 * h * focal_length;
-        let viewport_width = viewport_height * (self.image_width as f64 / self.image_height as f64);
+        let focus_dist = self
+            .focus_dist
+            .unwrap_or_else(|| (look_from - look_at).length());
+        let image_aspect = self.image_width as f64 / self.image_height as f64;
+        // Panoramic rays don't use the viewport, but get one all the same.
+        let (viewport_width, viewport_height) = match self.projection {
+            Projection::Orthographic { width } => (width, width / image_aspect),
+            _ => {
+                let theta = self.vfov.to_radians();
+                let h = f64::tan(theta / 2.0);
+                let viewport_height = 2.0 * h * focus_dist;
+                (viewport_height * image_aspect, viewport_height)
+            }
+        };
 
         let w = (look_from - look_at).unit();
         let u = Vec3::cross(&up, &w).unit();
@@ -122,45 +833,722 @@ impl Camera {
         self.pixel_delta_u = viewport_u / self.image_width as f64;
         self.pixel_delta_v = viewport_v / self.image_height as f64;
 
+        self.to_focus_plane = -(w * focus_dist);
+        self.right = u;
+        self.upward = v;
         let viewport_upper_left =
-            self.center - (w * focal_length) - (viewport_u / 2.0) - (viewport_v / 2.0);
+            self.center + self.to_focus_plane - (viewport_u / 2.0) - (viewport_v / 2.0);
         self.pixel_00 = viewport_upper_left + ((self.pixel_delta_u + self.pixel_delta_v) / 2.0);
 
-        self
+        let defocus_radius = focus_dist * (self.defocus_angle / 2.0).to_radians().tan();
+        self.defocus_disk_u = u * defocus_radius;
+        self.defocus_disk_v = v * defocus_radius;
     }
 
-    pub fn render(&self, world: &HittableList) {
-        println!("P3\n{} {}\n255", self.image_width, self.image_height);
+    pub fn render(&self, world: &HittableList, lights: &LightList) -> RenderStats {
+        self.render_to(world, lights, io::stdout().lock())
+            .expect("could not write the image to stdout")
+    }
 
-        for y in 0..self.image_height {
-            for x in 0..self.image_width {
-                // let pixel_center = self.pixel_00
-                //     + (self.pixel_delta_u * x as f64)
-                //     + (self.pixel_delta_v * y as f64);
-                // let ray = Ray {
-                //     origin: self.center,
-                //     direction: pixel_center - self.center,
-                // };
-                let mut color = Vec3(0.0, 0.0, 0.0);
-                for _ in 0..self.aa_samples {
-                    let ray = self.sample_ray(x, y);
-                    color += ray.send(world, self.max_depth);
+    /// Renders the world as a PPM image to `out`, through one buffer that
+    /// is flushed once the image is written.
+    pub fn render_to(
+        &self,
+        world: &HittableList,
+        lights: &LightList,
+        out: impl io::Write,
+    ) -> io::Result<RenderStats> {
+        let (image, stats) = self.render_timed(world, lights);
+        image.write_ppm(out)?;
+        Ok(stats)
+    }
+
+    /// Renders the world to an image file at `path` as the command line
+    /// writes it: finished by `finish_image`, with its `RenderMetadata`
+    /// embedded. The format is chosen from the extension: an 8-bit PNG or
+    /// PPM, gamma corrected and clamped as `write_ppm` does, or a Radiance
+    /// HDR. Missing parent directories are created.
+    pub fn render_to_file(
+        &self,
+        world: &HittableList,
+        lights: &LightList,
+        path: &Path,
+    ) -> Result<RenderStats, Error> {
+        let (image, stats) = self.render_timed(world, lights);
+        let (image, exposure) = self.finish_image(image, None);
+        let mut metadata = RenderMetadata::new(String::new(), self, &stats);
+        metadata.exposure = exposure;
+        metadata.save_image(&image, path)?;
+        Ok(stats)
+    }
+
+    /// `image`, rendered by this camera, run through the `post_process`
+    /// filters and exposed, with the exposure multiplier unless the exposure
+    /// leaves images as they are.
+    pub fn finish_image(
+        &self,
+        image: ImageBuffer,
+        aovs: Option<&Aovs>,
+    ) -> (ImageBuffer, Option<f64>) {
+        let mut image = self.post_process(image, aovs);
+        let exposure = (!self.exposure.is_identity()).then(|| image.expose(self.exposure));
+        (image, exposure)
+    }
+
+    /// `render_image`, also reporting how long the render and the stages this
+    /// thread ran before it took, and unless it is stereo, how converged the
+    /// image is. See `RenderStats`.
+    pub fn render_timed(
+        &self,
+        world: &HittableList,
+        lights: &LightList,
+    ) -> (ImageBuffer, RenderStats) {
+        if self.stereo.is_some() || self.supersample > 1 {
+            return self.time_render(world, || self.render_image(world, lights));
+        }
+        let mut samples = None;
+        let (image, mut stats) = self.time_render(world, || {
+            let accumulation = self
+                .render_accumulation(world, lights)
+                .expect("mono cameras accumulate");
+            let image = accumulation.image();
+            samples = Some(accumulation);
+            image
+        });
+        if let Some(samples) = samples {
+            stats.convergence = Some(samples.convergence());
+            if self.adaptive.is_some() {
+                stats.mean_samples = Some(samples.mean_samples());
+            }
+        }
+        (image, stats)
+    }
+
+    /// `render_timed`, also counting what the paths did, how deep they went
+    /// and how hard the BVH worked for them, into the stats' `trace`. The
+    /// counting slows the render down, so `render_timed` leaves it out.
+    pub fn render_with_stats(
+        &self,
+        world: &HittableList,
+        lights: &LightList,
+    ) -> (ImageBuffer, RenderStats) {
+        count_trace_stats();
+        self.render_timed(world, lights)
+    }
+
+    /// Runs `render`, an image of `world` from this camera, as the render
+    /// stage and reports on it as `render_timed` does; e.g. to time
+    /// `render_progressive`. If this thread is counting `TraceStats`, the
+    /// count ends with the render and goes in the stats.
+    pub fn time_render(
+        &self,
+        world: &HittableList,
+        render: impl FnOnce() -> ImageBuffer,
+    ) -> (ImageBuffer, RenderStats) {
+        let rays_before = rays_traced();
+        let stage = Stage::start("render");
+        let image = render();
+        drop(stage);
+        let stages = take_stage_times();
+        let stats = RenderStats {
+            width: image.width,
+            height: image.height,
+            samples: self.samples_per_pixel(),
+            mean_samples: None,
+            threads: self.thread_count(),
+            rays: rays_traced() - rays_before,
+            primitives: world.primitive_count(),
+            render_time: stages
+                .iter()
+                .find(|stage| stage.name == "render")
+                .map_or(Duration::ZERO, |stage| stage.elapsed),
+            stages,
+            convergence: None,
+            trace: take_trace_stats(),
+        };
+        (image, stats)
+    }
+
+    /// Renders the world, or with stereo settings, both eyes' views composed
+    /// into one image. The eyes share the world and its BVH.
+    pub fn render_image(&self, world: &HittableList, lights: &LightList) -> ImageBuffer {
+        self.render_image_with_progress(world, lights, &mut |_, _| {})
+    }
+
+    /// `render_image`, calling `progress` with the rows finished so far and
+    /// the rows in all after each row. Stereo renders count both eyes' rows.
+    pub fn render_image_with_progress(
+        &self,
+        world: &HittableList,
+        lights: &LightList,
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> ImageBuffer {
+        let Some(stereo) = self.stereo else {
+            let rows = self.image_height as usize;
+            return self.render_view(world, lights, &mut |done| progress(done, rows));
+        };
+        // Both eyes draw the same samples, so their noise matches too.
+        let mut mono = self.clone();
+        mono.set_seed(Some(self.seed.unwrap_or_else(rand::random)));
+        let (left_eye, right_eye) = (mono.eye(Eye::Left), mono.eye(Eye::Right));
+        let left_rows = left_eye.image_height as usize;
+        let rows = left_rows + right_eye.image_height as usize;
+        let left = left_eye.render_view(world, lights, &mut |done| progress(done, rows));
+        let right =
+            right_eye.render_view(world, lights, &mut |done| progress(left_rows + done, rows));
+        stereo.mode.compose(&left, &right)
+    }
+
+    fn render_view(
+        &self,
+        world: &HittableList,
+        lights: &LightList,
+        progress: &mut dyn FnMut(usize),
+    ) -> ImageBuffer {
+        if self.supersample > 1 {
+            return self.render_supersampled(world, lights, progress);
+        }
+        // Unseeded renders still draw every sample from its own stream, just
+        // from a base seed that differs between runs.
+        let seed = self.seed.unwrap_or_else(rand::random);
+        let (width, height) = (self.image_width as usize, self.image_height as usize);
+        let mut image = ImageBuffer::new(width, height);
+        let row = |y: usize| -> Vec<RaySample> {
+            (0..width)
+                .map(|x| self.pixel_average(world, lights, x as i32, y as i32, seed))
+                .collect()
+        };
+        let mut rows_done = 0;
+        self.for_each_row(
+            height,
+            width * self.aa_samples as usize,
+            &row,
+            &mut |y, pixels| {
+                for (x, pixel) in pixels.into_iter().enumerate() {
+                    image.set(x, y, pixel.color);
+                    if self.transparent {
+                        image.set_alpha(x, y, pixel.alpha);
+                    }
                 }
-                // ray.send(world).write_color();
-                // write_color(&ray.send(world));
-                (color * self.aa_scale).to_gamma().write_color();
+                rows_done += 1;
+                progress(rows_done);
+            },
+        );
+        image
+    }
+
+    /// The averaged color of pixel (x, y). Each sample draws from a sampler
+    /// derived from `seed`, the pixel, and the sample index, so the result
+    /// doesn't depend on which thread computes it or when.
+    pub fn pixel_color(
+        &self,
+        world: &HittableList,
+        lights: &LightList,
+        x: i32,
+        y: i32,
+        seed: u64,
+    ) -> Color {
+        self.pixel_average(world, lights, x, y, seed).color
+    }
+
+    /// `pixel_color`, with the pixel's averaged alpha.
+    pub fn pixel_average(
+        &self,
+        world: &HittableList,
+        lights: &LightList,
+        x: i32,
+        y: i32,
+        seed: u64,
+    ) -> RaySample {
+        let mut sum = RaySample {
+            color: Vec3(0.0, 0.0, 0.0),
+            alpha: 0.0,
+        };
+        if let Some(adaptive) = &self.adaptive {
+            let samples =
+                self.add_adaptive_samples(world, lights, x, y, seed, 0, adaptive, &mut |sample| {
+                    sum += sample
+                });
+            // As `Accumulation::image` scales them.
+            let scale = 1.0 / samples as f64;
+            return RaySample {
+                color: sum.color * scale,
+                alpha: sum.alpha * scale,
+            };
+        }
+        self.add_pixel_samples(
+            world,
+            lights,
+            x,
+            y,
+            seed,
+            0..self.aa_samples as u32,
+            &mut sum,
+        );
+        RaySample {
+            color: sum.color * self.aa_scale,
+            alpha: sum.alpha * self.aa_scale,
+        }
+    }
+
+    /// Adds samples `samples` of pixel (x, y) to `sum`, one by one in order,
+    /// so a pixel sampled in several calls sums to exactly what
+    /// `pixel_average` would.
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_pixel_samples(
+        &self,
+        world: &HittableList,
+        lights: &LightList,
+        x: i32,
+        y: i32,
+        seed: u64,
+        samples: Range<u32>,
+        sum: &mut RaySample,
+    ) {
+        for sample in samples {
+            *sum += self.pixel_sample_with_alpha(world, lights, x, y, seed, sample);
+        }
+    }
+
+    /// Sample number `sample` of pixel (x, y), the same whenever it is taken.
+    pub fn pixel_sample(
+        &self,
+        world: &HittableList,
+        lights: &LightList,
+        x: i32,
+        y: i32,
+        seed: u64,
+        sample: u32,
+    ) -> Color {
+        self.pixel_sample_with_alpha(world, lights, x, y, seed, sample)
+            .color
+    }
+
+    /// `pixel_sample`, with its alpha: 1 unless the camera is `transparent`.
+    pub fn pixel_sample_with_alpha(
+        &self,
+        world: &HittableList,
+        lights: &LightList,
+        x: i32,
+        y: i32,
+        seed: u64,
+        sample: u32,
+    ) -> RaySample {
+        self.with_pixel_sampler(seed, x, y, sample, &mut |sampler| {
+            self.sample_color(world, lights, x, y, sampler).0
+        })
+    }
+
+    /// Calls `f` with the sampler for sample number `sample` of pixel
+    /// (x, y), so passes besides the color can draw the very same rays.
+    pub(crate) fn with_pixel_sampler<T>(
+        &self,
+        seed: u64,
+        x: i32,
+        y: i32,
+        sample: u32,
+        f: &mut dyn FnMut(&mut dyn Sampler) -> T,
+    ) -> T {
+        match (self.sampler, self.blue_noise) {
+            (SamplerKind::Independent, false) => {
+                f(&mut IndependentSampler::for_sample(seed, x, y, sample))
+            }
+            (SamplerKind::Independent, true) => {
+                f(&mut IndependentSampler::dithered(seed, x, y, sample))
             }
+            (SamplerKind::Halton, false) => f(&mut HaltonSampler::for_sample(seed, x, y, sample)),
+            (SamplerKind::Halton, true) => f(&mut HaltonSampler::dithered(seed, x, y, sample)),
+            (SamplerKind::Sobol, false) => f(&mut SobolSampler::for_sample(seed, x, y, sample)),
+            (SamplerKind::Sobol, true) => f(&mut SobolSampler::dithered(seed, x, y, sample)),
         }
     }
 
-    pub fn sample_ray(&self, x: i32, y: i32) -> Ray {
-        let offset = Vec3::sample_square();
-        let pixel_sample = self.pixel_00
-            + (self.pixel_delta_u * (x as f64 + offset.0))
-            + (self.pixel_delta_v * (y as f64 + offset.1));
+    /// A sample of pixel (x, y) drawn from `sampler`, and what its ray hit
+    /// first.
+    pub(crate) fn sample_color(
+        &self,
+        world: &HittableList,
+        lights: &LightList,
+        x: i32,
+        y: i32,
+        sampler: &mut dyn Sampler,
+    ) -> (RaySample, Option<PrimaryHit>) {
+        let ray = self.sample_ray(x, y, sampler);
+        let (sample, hit) = ray.trace_from_camera(
+            world,
+            lights,
+            PathDepth {
+                max_depth: self.max_depth,
+                bounces: self.bounce_limits,
+                roulette_depth: self.russian_roulette.then_some(self.roulette_depth),
+            },
+            self.background.as_ref(),
+            self.ray_epsilon,
+            self.clip_interval(&ray),
+            self.transparent,
+            sampler,
+        );
+        let sample = match &self.fog {
+            Some(fog) => fog.apply(sample, hit.map(|hit| hit.distance)),
+            None => sample,
+        };
+        (sample, hit)
+    }
+
+    /// A ray through pixel (x, y), drawing the pixel jitter, the lens, and
+    /// the time from the first five dimensions of `rng`. Cameras without a
+    /// lens or a shutter skip theirs, so tracing always starts on the sixth.
+    pub fn sample_ray(&self, x: i32, y: i32, rng: &mut dyn Sampler) -> Ray {
+        let mut ray = self.pixel_ray(x, y, Vec3::sample_square(rng));
+        if self.defocus_angle > 0.0 {
+            let lens = self.aperture.random(rng);
+            let origin = ray.origin + self.defocus_disk_u * lens.0 + self.defocus_disk_v * lens.1;
+            ray.direction = ray.at(1.0) - origin;
+            ray.origin = origin;
+        } else {
+            rng.skip(2);
+        }
+        if self.shutter_close > self.shutter_open {
+            ray.time = rng.gen_range(self.shutter_open..self.shutter_close);
+        } else {
+            rng.skip(1);
+        }
+        ray
+    }
+
+    /// The stretch of `ray`, leaving the camera, between the clipping planes
+    /// and past the ray epsilon. Every ray through the lens travels the
+    /// focus distance along the view direction to reach the focus plane, so
+    /// the planes are a fixed fraction of the way along each. Panoramas look
+    /// every way, so for them the planes are spheres around the camera.
+    pub(crate) fn clip_interval(&self, ray: &Ray) -> Interval {
+        let (near, far) = (self.near_clip, self.far_clip);
+        if near == 0.0 && far == f64::INFINITY {
+            return Interval::from_range(self.ray_epsilon..f64::INFINITY);
+        }
+        let depth = if self.projection.is_panoramic() {
+            ray.direction.length()
+        } else {
+            let forward = (self.look_at - self.look_from).unit();
+            Vec3::dot(&ray.direction, &forward)
+        };
+        Interval::new((near / depth).max(self.ray_epsilon), far / depth)
+    }
+
+    /// The ray through pixel (x, y), `offset` from its center in pixel units.
+    /// Every kind reaches the focus plane at 1, and orthographic rays start
+    /// from the pixel's spot on the plane through `center`. Panoramic rays
+    /// are aimed by angle, with the offset taken in angle too, and reach the
+    /// focus distance at 1 whichever way they go.
+    pub(crate) fn pixel_ray(&self, x: i32, y: i32, offset: Vec3) -> Ray {
+        let pixel_sample = || {
+            self.pixel_00
+                + (self.pixel_delta_u * (x as f64 + offset.0))
+                + (self.pixel_delta_v * (y as f64 + offset.1))
+        };
+        // Where the sample falls in the image, from 0 to 1 each way.
+        let (width, height) = (self.image_width as f64, self.image_height as f64);
+        let (s, t) = (
+            (x as f64 + 0.5 + offset.0) / width,
+            (y as f64 + 0.5 + offset.1) / height,
+        );
+        let (origin, direction) = match self.projection {
+            Projection::Perspective => (self.center, pixel_sample() - self.center),
+            Projection::Orthographic { .. } => {
+                (pixel_sample() - self.to_focus_plane, self.to_focus_plane)
+            }
+            Projection::Equirectangular => {
+                // Around from behind on the left, and down from straight up.
+                let (azimuth, polar) = ((s - 0.5) * 2.0 * PI, t * PI);
+                let direction = self.panoramic_direction(
+                    polar.sin() * azimuth.sin(),
+                    polar.cos(),
+                    polar.sin() * azimuth.cos(),
+                );
+                (self.center, direction)
+            }
+            Projection::Fisheye { fov } => {
+                // Measured from the center, in radii of the image circle.
+                let radius = width.min(height) / 2.0;
+                let (dx, dy) = ((s - 0.5) * width / radius, (0.5 - t) * height / radius);
+                let r = (dx * dx + dy * dy).sqrt();
+                let angle = r * fov.to_radians() / 2.0;
+                let lean = if r > 0.0 { angle.sin() / r } else { 0.0 };
+                let direction = self.panoramic_direction(dx * lean, dy * lean, angle.cos());
+                (self.center, direction)
+            }
+        };
         Ray {
-            origin: self.center,
-            direction: pixel_sample - self.center,
+            origin,
+            direction,
+            time: self.shutter_open,
+        }
+    }
+
+    /// The direction with the given parts to the camera's right, up, and
+    /// ahead, scaled to the focus distance.
+    fn panoramic_direction(&self, right: f64, up: f64, ahead: f64) -> Vec3 {
+        let focus_dist = self.to_focus_plane.length();
+        (self.right * right + self.upward * up) * focus_dist + self.to_focus_plane * ahead
+    }
+}
+
+/* === Stereo === */
+
+/// How the two eyes' views are combined into one image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StereoMode {
+    /// The left eye's view beside the right's, in an image twice as wide.
+    SideBySide,
+    /// The left eye's view in the red channel and the right's in green and
+    /// blue, for red/cyan glasses.
+    Anaglyph,
+}
+
+impl StereoMode {
+    pub fn compose(&self, left: &ImageBuffer, right: &ImageBuffer) -> ImageBuffer {
+        match self {
+            StereoMode::SideBySide => {
+                let mut image = ImageBuffer::new(left.width + right.width, left.height);
+                for y in 0..left.height {
+                    for x in 0..left.width {
+                        image.set(x, y, left.get(x, y));
+                    }
+                    for x in 0..right.width {
+                        image.set(left.width + x, y, right.get(x, y));
+                    }
+                }
+                image
+            }
+            StereoMode::Anaglyph => {
+                let mut image = left.clone();
+                for (pixel, r) in image.pixels.iter_mut().zip(&right.pixels) {
+                    *pixel = Vec3(pixel.0, r.1, r.2);
+                }
+                image
+            }
+        }
+    }
+}
+
+impl fmt::Display for StereoMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StereoMode::SideBySide => write!(f, "side_by_side"),
+            StereoMode::Anaglyph => write!(f, "anaglyph"),
+        }
+    }
+}
+
+impl FromStr for StereoMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "side_by_side" => Ok(StereoMode::SideBySide),
+            "anaglyph" => Ok(StereoMode::Anaglyph),
+            _ => Err(format!(
+                "unknown stereo mode '{}' (expected side_by_side or anaglyph)",
+                s
+            )),
+        }
+    }
+}
+
+/// A pair of eyes `ipd` apart, in scene units, centered on the camera.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct StereoSettings {
+    pub ipd: f64,
+    pub mode: StereoMode,
+    /// Turn both eyes toward look_at instead of looking straight ahead.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub converge: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Eye {
+    Left,
+    Right,
+}
+
+impl Camera {
+    /// The camera for one eye: moved half the eye separation to that side,
+    /// and either turned toward look_at or looking parallel to the center.
+    /// Without stereo settings, the camera itself.
+    pub fn eye(&self, eye: Eye) -> Camera {
+        let mut camera = self.clone();
+        camera.stereo = None;
+        let Some(stereo) = self.stereo else {
+            return camera;
+        };
+        let w = (self.look_from - self.look_at).unit();
+        let u = Vec3::cross(&self.up, &w).unit();
+        let offset = match eye {
+            Eye::Left => u * (-stereo.ipd / 2.0),
+            Eye::Right => u * (stereo.ipd / 2.0),
+        };
+        camera.look_from = self.look_from + offset;
+        if !stereo.converge {
+            camera.look_at = self.look_at + offset;
+        }
+        camera.update_viewport();
+        camera
+    }
+}
+
+/* === Render Stats === */
+
+/// What a render did and how long it and the work before it took.
+#[derive(Clone, Debug)]
+pub struct RenderStats {
+    /// The size of the image, both eyes together for stereo renders.
+    pub width: usize,
+    pub height: usize,
+    /// Samples per pixel, or with adaptive sampling, the most a pixel may
+    /// take.
+    pub samples: i32,
+    /// The samples the pixels took on average, for adaptive renders that
+    /// kept them, like `convergence`.
+    pub mean_samples: Option<f64>,
+    pub threads: usize,
+    /// Every bounce and shadow ray traced, see `rays_traced`.
+    pub rays: u64,
+    /// The primitives in the world, see `Hittable::primitive_count`.
+    pub primitives: usize,
+    pub render_time: Duration,
+    /// The stages run on the rendering thread since the last render, such as
+    /// building the scene, loading its assets, building BVHs, and the render.
+    pub stages: Vec<StageTime>,
+    /// How converged the image is, for renders that kept their samples'
+    /// variance, such as `render_timed`'s of one eye.
+    pub convergence: Option<Convergence>,
+    /// What the paths did, for renders that counted it, such as
+    /// `render_with_stats`.
+    pub trace: Option<TraceStats>,
+}
+
+impl RenderStats {
+    pub fn rays_per_second(&self) -> f64 {
+        self.rays as f64 / self.render_time.as_secs_f64().max(1e-9)
+    }
+
+    /// How long the stage called `name` took, however many times it ran,
+    /// e.g. "build BVH" to weigh against the render's.
+    pub fn stage_time(&self, name: &str) -> Option<Duration> {
+        self.stages
+            .iter()
+            .find(|stage| stage.name == name)
+            .map(|stage| stage.elapsed)
+    }
+}
+
+impl fmt::Display for RenderStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "render summary:")?;
+        writeln!(
+            f,
+            "  {} by {} pixels, {} samples per pixel, {} thread{}",
+            self.width,
+            self.height,
+            self.samples,
+            self.threads,
+            if self.threads == 1 { "" } else { "s" }
+        )?;
+        if let Some(mean) = self.mean_samples {
+            writeln!(f, "  {:.1} samples per pixel on average", mean)?;
+        }
+        writeln!(
+            f,
+            "  {} rays in {:.2?}, {:.2} M rays/s",
+            self.rays,
+            self.render_time,
+            self.rays_per_second() / 1e6
+        )?;
+        write!(f, "  {} primitives", self.primitives)?;
+        for stage in &self.stages {
+            write!(
+                f,
+                "\n  {:indent$}{:<width$} {:>9.1?}",
+                "",
+                stage.name,
+                stage.elapsed,
+                indent = stage.depth * 2,
+                width = 20usize.saturating_sub(stage.depth * 2)
+            )?;
+            if stage.count > 1 {
+                write!(f, " ({} times)", stage.count)?;
+            }
+        }
+        if let Some(trace) = &self.trace {
+            write!(
+                f,
+                "\n  {} primary rays, {} path rays, {:.2} per path on average",
+                trace.primary_rays,
+                trace.path_rays,
+                trace.mean_path_depth()
+            )?;
+            write!(
+                f,
+                "\n  {} BVH nodes visited, {:.1} per path ray",
+                trace.node_visits,
+                trace.node_visits as f64 / trace.path_rays.max(1) as f64
+            )?;
+            write!(f, "\n  {} primitives intersected", trace.total_hit_tests())?;
+            for (kind, tests) in &trace.hit_tests {
+                write!(f, "\n    {:<18} {:>12}", kind, tests)?;
+            }
+        }
+        if let Some(convergence) = &self.convergence {
+            write!(f, "\n  {}", convergence)?;
+        }
+        Ok(())
+    }
+}
+
+/* === BVH Heat Map === */
+
+/// A false-color picture of how hard the BVH worked for each primary ray.
+pub struct HeatMap {
+    pub image: ImageBuffer,
+    /// The work per pixel, row by row.
+    pub stats: Vec<TraversalStats>,
+    /// The node-visit count drawn in the hottest color.
+    pub max: u32,
+}
+
+impl HeatMap {
+    pub fn mean_node_visits(&self) -> f64 {
+        let total: u64 = self.stats.iter().map(|s| s.node_visits as u64).sum();
+        total as f64 / self.stats.len().max(1) as f64
+    }
+}
+
+impl Camera {
+    /// Traces one ray through the center of each pixel and colors the pixel by
+    /// the BVH nodes it visited, from black through blue, green, and yellow to
+    /// red at `max` visits. Without `max`, the busiest pixel sets the scale.
+    pub fn render_heat_map(&self, world: &HittableList, max: Option<u32>) -> HeatMap {
+        let seed = self.seed.unwrap_or(0);
+        let mut stats = Vec::with_capacity((self.image_width * self.image_height) as usize);
+        for y in 0..self.image_height {
+            for x in 0..self.image_width {
+                let ray = self.pixel_ray(x, y, Vec3(0.0, 0.0, 0.0));
+                let mut sampler = IndependentSampler::for_sample(seed, x, y, 0);
+                let mut pixel = TraversalStats::default();
+                world.hit_with_stats(&ray, self.clip_interval(&ray), &mut sampler, &mut pixel);
+                stats.push(pixel);
+            }
+        }
+
+        let max = max
+            .unwrap_or_else(|| stats.iter().map(|s| s.node_visits).max().unwrap_or(0))
+            .max(1);
+        let mut image = ImageBuffer::new(self.image_width as usize, self.image_height as usize);
+        for (pixel, s) in image.pixels.iter_mut().zip(&stats) {
+            *pixel = heat_color(s.node_visits as f64 / max as f64);
         }
+        HeatMap { image, stats, max }
     }
 }
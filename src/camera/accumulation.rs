@@ -0,0 +1,498 @@
+use std::{
+    fmt, fs,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use crate::{Camera, Color, HittableList, ImageBuffer, LightList, Vec3};
+
+/// The first bytes of an accumulation file.
+const MAGIC: &[u8; 8] = b"RTACC02\n";
+/// The first bytes of an accumulation file that sums alphas too.
+const MAGIC_WITH_ALPHA: &[u8; 8] = b"RTACC03\n";
+
+/// The unaveraged samples of a render: each pixel's linear color summed over
+/// its samples, the sum of their squares for the variance, and how many
+/// samples that is. Renders of the same scene
+/// with different seeds add up to one with all their samples, so they can
+/// be spread across machines or nights and merged.
+///
+/// The file format is little-endian: `MAGIC`, the width and height as u32,
+/// the fingerprint as u64, then per pixel row by row, the red, green, and
+/// blue sums and sums of squares as f64 and the sample count as u32. Files
+/// that sum alphas start with `MAGIC_WITH_ALPHA` instead and follow each
+/// pixel's count with its alpha sum as f64.
+#[derive(Clone, Debug)]
+pub struct Accumulation {
+    pub width: usize,
+    pub height: usize,
+    /// Identifies the scene and settings; see `Scene::fingerprint`.
+    pub fingerprint: u64,
+    pub sums: Vec<Color>,
+    /// The sums of the samples' squares, channel by channel.
+    pub squares: Vec<Color>,
+    pub samples: Vec<u32>,
+    /// The sums of the samples' alphas, for renders with a transparent
+    /// background; see `Camera::transparent`.
+    pub alphas: Option<Vec<f64>>,
+}
+
+#[derive(Debug)]
+pub enum AccumulationError {
+    Io(io::Error),
+    NotAnAccumulation,
+    SizeMismatch {
+        first: (usize, usize),
+        other: (usize, usize),
+    },
+    FingerprintMismatch {
+        first: u64,
+        other: u64,
+    },
+    NothingToMerge,
+    Stereo,
+    Supersampled,
+}
+
+impl fmt::Display for AccumulationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AccumulationError::Io(e) => write!(f, "{}", e),
+            AccumulationError::NotAnAccumulation => write!(f, "not an accumulation file"),
+            AccumulationError::SizeMismatch { first, other } => write!(
+                f,
+                "can't merge a {} by {} render with a {} by {} one",
+                first.0, first.1, other.0, other.1
+            ),
+            AccumulationError::FingerprintMismatch { first, other } => write!(
+                f,
+                "the renders are of different scenes or settings (fingerprints {:016x} and {:016x})",
+                first, other
+            ),
+            AccumulationError::NothingToMerge => write!(f, "no renders to merge"),
+            AccumulationError::Stereo => write!(f, "stereo renders can't be accumulated"),
+            AccumulationError::Supersampled => {
+                write!(f, "supersampled renders can't be accumulated")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AccumulationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AccumulationError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for AccumulationError {
+    fn from(e: io::Error) -> Self {
+        AccumulationError::Io(e)
+    }
+}
+
+impl Accumulation {
+    /// An accumulation with no samples yet.
+    pub fn new(width: usize, height: usize, fingerprint: u64) -> Self {
+        Self {
+            width,
+            height,
+            fingerprint,
+            sums: vec![Vec3(0.0, 0.0, 0.0); width * height],
+            squares: vec![Vec3(0.0, 0.0, 0.0); width * height],
+            samples: vec![0; width * height],
+            alphas: None,
+        }
+    }
+
+    /// Adds `other`'s samples to these, if it is of the same scene and size.
+    pub fn add(&mut self, other: &Accumulation) -> Result<(), AccumulationError> {
+        if (self.width, self.height) != (other.width, other.height) {
+            return Err(AccumulationError::SizeMismatch {
+                first: (self.width, self.height),
+                other: (other.width, other.height),
+            });
+        }
+        if self.fingerprint != other.fingerprint {
+            return Err(AccumulationError::FingerprintMismatch {
+                first: self.fingerprint,
+                other: other.fingerprint,
+            });
+        }
+        for (sum, other) in self.sums.iter_mut().zip(&other.sums) {
+            *sum += *other;
+        }
+        for (squares, other) in self.squares.iter_mut().zip(&other.squares) {
+            *squares += *other;
+        }
+        for (samples, other) in self.samples.iter_mut().zip(&other.samples) {
+            *samples += other;
+        }
+        if let Some(others) = &other.alphas {
+            let len = self.samples.len();
+            let alphas = self.alphas.get_or_insert_with(|| vec![0.0; len]);
+            for (alpha, other) in alphas.iter_mut().zip(others) {
+                *alpha += other;
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds one sample of pixel `i`, counting the pixels row by row.
+    pub fn add_sample(&mut self, i: usize, color: Color) {
+        self.sums[i] += color;
+        self.squares[i] += color * color;
+        self.samples[i] += 1;
+    }
+
+    /// Adds the alpha of the sample of pixel `i` just added.
+    pub fn add_alpha(&mut self, i: usize, alpha: f64) {
+        let len = self.samples.len();
+        self.alphas.get_or_insert_with(|| vec![0.0; len])[i] += alpha;
+    }
+
+    /// All of `parts` added together.
+    pub fn merge(parts: &[Accumulation]) -> Result<Accumulation, AccumulationError> {
+        let (first, rest) = parts
+            .split_first()
+            .ok_or(AccumulationError::NothingToMerge)?;
+        let mut merged = first.clone();
+        for part in rest {
+            merged.add(part)?;
+        }
+        Ok(merged)
+    }
+
+    /// The mean of each pixel's samples, black where there are none.
+    pub fn image(&self) -> ImageBuffer {
+        ImageBuffer {
+            width: self.width,
+            height: self.height,
+            pixels: self
+                .sums
+                .iter()
+                .zip(&self.samples)
+                // The same scaling as `Camera::pixel_color`, so an image of
+                // one render's samples matches `render_image`'s.
+                .map(|(&sum, &samples)| match samples {
+                    0 => Vec3(0.0, 0.0, 0.0),
+                    n => sum * (1.0 / n as f64),
+                })
+                .collect(),
+            alpha: self.alphas.as_ref().map(|alphas| {
+                alphas
+                    .iter()
+                    .zip(&self.samples)
+                    .map(|(&sum, &samples)| match samples {
+                        0 => 0.0,
+                        n => sum * (1.0 / n as f64),
+                    })
+                    .collect()
+            }),
+        }
+    }
+
+    /// The fewest samples any pixel has.
+    pub fn min_samples(&self) -> u32 {
+        self.samples.iter().copied().min().unwrap_or(0)
+    }
+
+    /// The variance of each pixel's samples, channel by channel: zero for
+    /// a pixel every sample agrees on, and infinite for one with fewer than
+    /// two samples.
+    pub fn variance(&self) -> ImageBuffer {
+        ImageBuffer {
+            width: self.width,
+            height: self.height,
+            pixels: (0..self.sums.len())
+                .map(|i| self.pixel_variance(i))
+                .collect(),
+            alpha: None,
+        }
+    }
+
+    /// The standard error of each pixel's mean, channel by channel: how far
+    /// it is likely to be from what more samples would converge to.
+    pub fn standard_error(&self) -> ImageBuffer {
+        ImageBuffer {
+            width: self.width,
+            height: self.height,
+            pixels: (0..self.sums.len())
+                .map(|i| {
+                    let v = self.pixel_variance(i) / self.samples[i] as f64;
+                    Vec3(v.0.sqrt(), v.1.sqrt(), v.2.sqrt())
+                })
+                .collect(),
+            alpha: None,
+        }
+    }
+
+    /// Each pixel's standard error relative to its brightness, row by row.
+    /// Pixels darker than `DARK` are measured against `DARK` instead, so
+    /// that faint noise in the shadows doesn't count as much as it divides.
+    pub fn relative_errors(&self) -> Vec<f64> {
+        (0..self.sums.len())
+            .map(|i| {
+                let n = self.samples[i] as f64;
+                let error = (self.pixel_variance(i) / n).length();
+                let mean = (self.sums[i] / n).length();
+                if error == 0.0 {
+                    0.0
+                } else {
+                    error / mean.max(DARK)
+                }
+            })
+            .collect()
+    }
+
+    /// The samples the pixels hold on average.
+    pub fn mean_samples(&self) -> f64 {
+        let total: u64 = self.samples.iter().map(|&n| n as u64).sum();
+        total as f64 / self.samples.len().max(1) as f64
+    }
+
+    /// How converged the render is, from the pixels' `relative_errors`.
+    pub fn convergence(&self) -> Convergence {
+        let mut errors = self.relative_errors();
+        errors.sort_by(f64::total_cmp);
+        let percentile = |p: f64| {
+            let rank = (p * errors.len() as f64).ceil() as usize;
+            errors.get(rank.max(1) - 1).copied().unwrap_or(0.0)
+        };
+        Convergence {
+            mean_relative_error: errors.iter().sum::<f64>() / errors.len().max(1) as f64,
+            p95_relative_error: percentile(0.95),
+            max_relative_error: percentile(1.0),
+        }
+    }
+
+    fn pixel_variance(&self, i: usize) -> Color {
+        let n = self.samples[i] as f64;
+        if n < 2.0 {
+            return Vec3(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        }
+        let (sum, squares) = (self.sums[i], self.squares[i]);
+        // Rounding can leave a constant pixel a hair below zero.
+        let variance = |sum: f64, squares: f64| ((squares - sum * sum / n) / (n - 1.0)).max(0.0);
+        Vec3(
+            variance(sum.0, squares.0),
+            variance(sum.1, squares.1),
+            variance(sum.2, squares.2),
+        )
+    }
+
+    pub fn write(&self, out: impl Write) -> io::Result<()> {
+        let mut out = BufWriter::new(out);
+        out.write_all(match self.alphas {
+            Some(_) => MAGIC_WITH_ALPHA,
+            None => MAGIC,
+        })?;
+        out.write_all(&(self.width as u32).to_le_bytes())?;
+        out.write_all(&(self.height as u32).to_le_bytes())?;
+        out.write_all(&self.fingerprint.to_le_bytes())?;
+        for (i, (sum, squares)) in self.sums.iter().zip(&self.squares).enumerate() {
+            for channel in [sum.0, sum.1, sum.2, squares.0, squares.1, squares.2] {
+                out.write_all(&channel.to_le_bytes())?;
+            }
+            out.write_all(&self.samples[i].to_le_bytes())?;
+            if let Some(alphas) = &self.alphas {
+                out.write_all(&alphas[i].to_le_bytes())?;
+            }
+        }
+        out.flush()
+    }
+
+    pub fn read(input: impl Read) -> Result<Self, AccumulationError> {
+        let mut input = BufReader::new(input);
+        let mut magic = [0; 8];
+        input.read_exact(&mut magic)?;
+        let with_alpha = match &magic {
+            MAGIC => false,
+            MAGIC_WITH_ALPHA => true,
+            _ => return Err(AccumulationError::NotAnAccumulation),
+        };
+        let width = read_u32(&mut input)? as usize;
+        let height = read_u32(&mut input)? as usize;
+        let mut bytes = [0; 8];
+        input.read_exact(&mut bytes)?;
+        let mut accumulation = Self::new(width, height, u64::from_le_bytes(bytes));
+        if with_alpha {
+            accumulation.alphas = Some(vec![0.0; width * height]);
+        }
+        for i in 0..width * height {
+            let mut color = || -> io::Result<Color> {
+                Ok(Vec3(
+                    read_f64(&mut input)?,
+                    read_f64(&mut input)?,
+                    read_f64(&mut input)?,
+                ))
+            };
+            accumulation.sums[i] = color()?;
+            accumulation.squares[i] = color()?;
+            accumulation.samples[i] = read_u32(&mut input)?;
+            if let Some(alphas) = &mut accumulation.alphas {
+                alphas[i] = read_f64(&mut input)?;
+            }
+        }
+        Ok(accumulation)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        self.write(fs::File::create(path)?)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, AccumulationError> {
+        Self::read(fs::File::open(path)?)
+    }
+}
+
+/// The brightness below which a pixel's standard error counts as if the
+/// pixel were this bright, in `Accumulation::relative_errors`.
+pub const DARK: f64 = 0.01;
+
+/// How far a render is from converged, in standard errors relative to the
+/// pixels' brightness: 0.01 means a pixel is likely within 1% of where more
+/// samples would take it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Convergence {
+    pub mean_relative_error: f64,
+    /// 95% of the pixels are at least this close.
+    pub p95_relative_error: f64,
+    pub max_relative_error: f64,
+}
+
+impl fmt::Display for Convergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "relative standard error {:.2}% at the 95th percentile, {:.2}% on average, {:.2}% at most",
+            self.p95_relative_error * 100.0,
+            self.mean_relative_error * 100.0,
+            self.max_relative_error * 100.0
+        )
+    }
+}
+
+impl Camera {
+    /// All of the camera's samples of every pixel, for their variance as
+    /// well as the image. `image()` of it is exactly `render_image`'s.
+    pub fn render_accumulation(
+        &self,
+        world: &HittableList,
+        lights: &LightList,
+    ) -> Result<Accumulation, AccumulationError> {
+        let mut accumulation =
+            Accumulation::new(self.image_width as usize, self.image_height as usize, 0);
+        self.accumulate(world, lights, &mut accumulation)?;
+        Ok(accumulation)
+    }
+
+    /// Adds `aa_samples` more samples of every pixel to `into`, continuing
+    /// each pixel's sequence from the samples it already has, so that
+    /// accumulating twice with one seed matches one render with twice the
+    /// samples, and different seeds give independent samples.
+    pub fn accumulate(
+        &self,
+        world: &HittableList,
+        lights: &LightList,
+        into: &mut Accumulation,
+    ) -> Result<(), AccumulationError> {
+        if self.stereo.is_some() {
+            return Err(AccumulationError::Stereo);
+        }
+        if self.supersample > 1 {
+            return Err(AccumulationError::Supersampled);
+        }
+        let size = (self.image_width as usize, self.image_height as usize);
+        if size != (into.width, into.height) {
+            return Err(AccumulationError::SizeMismatch {
+                first: (into.width, into.height),
+                other: size,
+            });
+        }
+        let seed = self.seed.unwrap_or_else(rand::random);
+        let width = into.width;
+        // Each row is summed onto a copy of what `into` holds, sample by
+        // sample as one thread alone would, so the sums come out the same
+        // however many threads there are.
+        let start = &*into;
+        let row = |y: usize| -> Accumulation {
+            let mut part = Accumulation::new(width, 1, 0);
+            let range = y * width..(y + 1) * width;
+            part.sums.copy_from_slice(&start.sums[range.clone()]);
+            part.squares.copy_from_slice(&start.squares[range.clone()]);
+            part.samples.copy_from_slice(&start.samples[range.clone()]);
+            part.alphas = start.alphas.as_ref().map(|alphas| alphas[range].to_vec());
+            for x in 0..width {
+                let taken = part.samples[x];
+                if let Some(adaptive) = &self.adaptive {
+                    let (px, py) = (x as i32, y as i32);
+                    self.add_adaptive_samples(
+                        world,
+                        lights,
+                        px,
+                        py,
+                        seed,
+                        taken,
+                        adaptive,
+                        &mut |sample| {
+                            part.add_sample(x, sample.color);
+                            if self.transparent {
+                                part.add_alpha(x, sample.alpha);
+                            }
+                        },
+                    );
+                    continue;
+                }
+                for sample in taken..taken + self.aa_samples as u32 {
+                    let (px, py) = (x as i32, y as i32);
+                    let sample = self.pixel_sample_with_alpha(world, lights, px, py, seed, sample);
+                    part.add_sample(x, sample.color);
+                    if self.transparent {
+                        part.add_alpha(x, sample.alpha);
+                    }
+                }
+            }
+            part
+        };
+        let mut rows = vec![None; into.height];
+        let samples_per_row = width * self.aa_samples as usize;
+        self.for_each_row(into.height, samples_per_row, &row, &mut |y, part| {
+            rows[y] = Some(part)
+        });
+        for (y, part) in rows.into_iter().enumerate() {
+            let part = part.expect("every row is rendered");
+            let range = y * width..(y + 1) * width;
+            into.sums[range.clone()].copy_from_slice(&part.sums);
+            into.squares[range.clone()].copy_from_slice(&part.squares);
+            into.samples[range.clone()].copy_from_slice(&part.samples);
+            if let Some(alphas) = part.alphas {
+                let len = into.samples.len();
+                into.alphas.get_or_insert_with(|| vec![0.0; len])[range].copy_from_slice(&alphas);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_u32(input: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_f64(input: &mut impl Read) -> io::Result<f64> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(f64::from_le_bytes(bytes))
+}
+
+/// A 64-bit FNV-1a hash, which unlike std's hashers is the same on every
+/// machine and release, for fingerprints that are compared across them.
+pub fn stable_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Camera, HittableList, LightList, RaySample, DARK};
+
+/// Sampling each pixel only until it has converged: after `min_samples`,
+/// a pixel stops once the 95% confidence interval of its luminance is
+/// within `tolerance` of it either way, relative to its brightness as in
+/// `Accumulation::relative_errors`, or at `max_samples` if it never is.
+/// Flat walls stop early and leave the samples to edges, shadows, and
+/// caustics.
+///
+/// It takes the place of `aa_samples` in `render_image`, `render_timed`,
+/// `render_with`, and accumulations, which continue each pixel from the
+/// samples it holds; progressive and snapshot renders still take
+/// `aa_samples` of every pixel.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct AdaptiveSampling {
+    pub tolerance: f64,
+    pub min_samples: u32,
+    pub max_samples: u32,
+}
+
+impl AdaptiveSampling {
+    pub fn new(tolerance: f64, min_samples: u32, max_samples: u32) -> Self {
+        Self {
+            tolerance,
+            min_samples,
+            max_samples,
+        }
+    }
+
+    pub(crate) fn is_valid(&self) -> bool {
+        self.tolerance.is_finite()
+            && self.tolerance > 0.0
+            && self.min_samples >= 2
+            && self.min_samples <= self.max_samples
+    }
+}
+
+/// The running mean and variance of a pixel's luminance, by Welford's
+/// method.
+#[derive(Default)]
+struct LuminanceStats {
+    samples: u32,
+    mean: f64,
+    m2: f64,
+}
+
+impl LuminanceStats {
+    fn add(&mut self, luminance: f64) {
+        self.samples += 1;
+        let delta = luminance - self.mean;
+        self.mean += delta / self.samples as f64;
+        self.m2 += delta * (luminance - self.mean);
+    }
+
+    fn converged(&self, adaptive: &AdaptiveSampling) -> bool {
+        if self.samples < adaptive.min_samples {
+            return false;
+        }
+        let n = self.samples as f64;
+        let half_width = 1.96 * (self.m2 / (n - 1.0) / n).max(0.0).sqrt();
+        half_width <= adaptive.tolerance * self.mean.abs().max(DARK)
+    }
+}
+
+impl Camera {
+    /// Takes samples of pixel (x, y) in order from number `first`, handing
+    /// each to `add`, until the pixel converges as `adaptive` says, and
+    /// returns how many it took.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn add_adaptive_samples(
+        &self,
+        world: &HittableList,
+        lights: &LightList,
+        x: i32,
+        y: i32,
+        seed: u64,
+        first: u32,
+        adaptive: &AdaptiveSampling,
+        add: &mut dyn FnMut(RaySample),
+    ) -> u32 {
+        let mut stats = LuminanceStats::default();
+        while stats.samples < adaptive.max_samples && !stats.converged(adaptive) {
+            let sample = first + stats.samples;
+            let sample = self.pixel_sample_with_alpha(world, lights, x, y, seed, sample);
+            stats.add(sample.color.luminance());
+            add(sample);
+        }
+        stats.samples
+    }
+}
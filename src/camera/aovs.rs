@@ -0,0 +1,191 @@
+use std::{
+    io,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    color, sidecar_path, Camera, Color, HittableList, ImageBuffer, LightList, RaySample, Vec3,
+};
+
+/// Passes rendered alongside the image from what each pixel's camera rays
+/// hit first, for compositing and denoising. Each is an average over the
+/// pixel's samples, so edges are antialiased as in the image.
+pub struct Aovs {
+    pub width: usize,
+    pub height: usize,
+    /// The world-space normal of the surface hit, facing the camera, row by
+    /// row; samples that hit nothing count as zero.
+    pub normal: Vec<Vec3>,
+    /// The distance to the surface hit, averaged over the samples that hit
+    /// one; infinite where none did.
+    pub depth: Vec<f64>,
+    /// The fraction of the samples that hit anything.
+    pub coverage: Vec<f64>,
+    /// The color of the surface hit, see `Material::albedo`; samples that
+    /// hit nothing count as black.
+    pub albedo: Vec<Color>,
+    /// The camera's clipping planes, between which `depth_image` maps the
+    /// depth by default.
+    pub near_clip: f64,
+    pub far_clip: f64,
+}
+
+/// One pixel's share of the `Aovs`.
+struct PixelAovs {
+    normal: Vec3,
+    depth: f64,
+    coverage: f64,
+    albedo: Color,
+}
+
+impl Aovs {
+    /// Passes `width` by `height` of nothing, to fill in, e.g. with another
+    /// renderer's to guide a `PostProcess`.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            normal: vec![Vec3(0.0, 0.0, 0.0); width * height],
+            depth: vec![0.0; width * height],
+            coverage: vec![0.0; width * height],
+            albedo: vec![color(0.0, 0.0, 0.0); width * height],
+            near_clip: 0.0,
+            far_clip: f64::INFINITY,
+        }
+    }
+
+    /// The normals with each component mapped from -1..1 to 0..1. Like
+    /// `Mattes::image`, the values are stored squared, so that gamma
+    /// correction leaves the saved values proportional to them.
+    pub fn normal_image(&self) -> ImageBuffer {
+        let mut image = ImageBuffer::new(self.width, self.height);
+        for (pixel, normal) in image.pixels.iter_mut().zip(&self.normal) {
+            let Vec3(x, y, z) = (*normal + Vec3(1.0, 1.0, 1.0)) / 2.0;
+            *pixel = Vec3(x * x, y * y, z * z);
+        }
+        image
+    }
+
+    /// The depth mapped from `range` to black through white, nothing hit
+    /// being white, and stored squared as for `normal_image`. Without a
+    /// range, from the near clipping plane to the farthest hit or the far
+    /// plane, whichever is nearer.
+    pub fn depth_image(&self, range: Option<Range<f64>>) -> ImageBuffer {
+        let range = range.unwrap_or_else(|| {
+            let farthest = self
+                .depth
+                .iter()
+                .zip(&self.coverage)
+                .filter(|(_, &coverage)| coverage > 0.0)
+                .map(|(&depth, _)| depth)
+                .fold(self.near_clip, f64::max);
+            self.near_clip..farthest.min(self.far_clip)
+        });
+        let mut image = ImageBuffer::new(self.width, self.height);
+        for ((pixel, &depth), &coverage) in
+            image.pixels.iter_mut().zip(&self.depth).zip(&self.coverage)
+        {
+            let near = (depth - range.start) / (range.end - range.start);
+            let d = if near.is_finite() {
+                near.clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let d = coverage * d + (1.0 - coverage);
+            *pixel = Vec3(d * d, d * d, d * d);
+        }
+        image
+    }
+
+    pub fn albedo_image(&self) -> ImageBuffer {
+        let mut image = ImageBuffer::new(self.width, self.height);
+        image.pixels.clone_from(&self.albedo);
+        image
+    }
+
+    /// Writes each pass beside `output`, the image they go with, as
+    /// OUTPUT.normal, OUTPUT.depth, and OUTPUT.albedo in its format, the
+    /// depth mapped from `depth_range` as `depth_image` says, and returns
+    /// their paths.
+    pub fn save(&self, output: &Path, depth_range: Option<Range<f64>>) -> io::Result<Vec<PathBuf>> {
+        let passes = [
+            (sidecar_path(output, "normal"), self.normal_image()),
+            (sidecar_path(output, "depth"), self.depth_image(depth_range)),
+            (sidecar_path(output, "albedo"), self.albedo_image()),
+        ];
+        let mut paths = Vec::new();
+        for (path, image) in passes {
+            image.save(&path)?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+}
+
+impl Camera {
+    /// `render_image`, also filling in the `Aovs` from the first hits of the
+    /// very rays it traces. Every pixel takes `aa_samples` samples, even
+    /// with adaptive sampling, and stereo and supersampled cameras render
+    /// as a plain mono camera of their size would.
+    pub fn render_with_aovs(
+        &self,
+        world: &HittableList,
+        lights: &LightList,
+    ) -> (ImageBuffer, Aovs) {
+        let seed = self.seed.unwrap_or_else(rand::random);
+        let (width, height) = (self.image_width as usize, self.image_height as usize);
+        let samples = self.aa_samples as u32;
+        let pixel = |x: i32, y: i32| {
+            let mut sum = RaySample::TRANSPARENT;
+            let (mut normal, mut albedo) = (Vec3(0.0, 0.0, 0.0), color(0.0, 0.0, 0.0));
+            let (mut depth, mut hits) = (0.0, 0);
+            for sample in 0..samples {
+                let (color, hit) = self.with_pixel_sampler(seed, x, y, sample, &mut |sampler| {
+                    self.sample_color(world, lights, x, y, sampler)
+                });
+                sum += color;
+                if let Some(hit) = hit {
+                    normal += hit.normal;
+                    albedo += hit.albedo;
+                    depth += hit.distance;
+                    hits += 1;
+                }
+            }
+            let sample = RaySample {
+                color: sum.color * self.aa_scale,
+                alpha: sum.alpha * self.aa_scale,
+            };
+            let aovs = PixelAovs {
+                normal: normal * self.aa_scale,
+                depth: if hits > 0 {
+                    depth / hits as f64
+                } else {
+                    f64::INFINITY
+                },
+                coverage: hits as f64 / samples as f64,
+                albedo: albedo * self.aa_scale,
+            };
+            (sample, aovs)
+        };
+        let row = |y: usize| -> Vec<_> { (0..width).map(|x| pixel(x as i32, y as i32)).collect() };
+
+        let mut image = ImageBuffer::new(width, height);
+        let mut aovs = Aovs::new(width, height);
+        (aovs.near_clip, aovs.far_clip) = (self.near_clip, self.far_clip);
+        self.for_each_row(height, width * samples as usize, &row, &mut |y, pixels| {
+            for (x, (sample, pixel)) in pixels.into_iter().enumerate() {
+                image.set(x, y, sample.color);
+                if self.transparent {
+                    image.set_alpha(x, y, sample.alpha);
+                }
+                let i = y * width + x;
+                aovs.normal[i] = pixel.normal;
+                aovs.depth[i] = pixel.depth;
+                aovs.coverage[i] = pixel.coverage;
+                aovs.albedo[i] = pixel.albedo;
+            }
+        });
+        (image, aovs)
+    }
+}
@@ -0,0 +1,112 @@
+use std::{fmt, sync::Arc};
+
+use rand::Rng;
+
+use crate::{point, Distribution2D, RegularPolygon, Texture, Vec3};
+
+/// How many texels a side an image aperture's mask is tabulated at.
+const MASK_RESOLUTION: usize = 256;
+
+/// The shape of the lens opening, which out-of-focus highlights take.
+#[derive(Clone, Default)]
+pub enum ApertureShape {
+    /// A round opening.
+    #[default]
+    Circle,
+    /// A regular polygon of `blades` sides, its first corner `rotation`
+    /// degrees counterclockwise from the camera's right.
+    Polygon { blades: u32, rotation: f64 },
+    /// Open where the mask is bright; see `ApertureMask`.
+    Image(ApertureMask),
+}
+
+/// A texture open where it is bright, across the square around the lens:
+/// (0, 0) of the texture at its lower left and (1, 1) at its upper right.
+/// Grays let part of the light through. Tabulated once, `MASK_RESOLUTION`
+/// texels a side, into the distribution lens points are drawn from.
+#[derive(Clone)]
+pub struct ApertureMask {
+    pub texture: Arc<dyn Texture>,
+    /// `None` if the mask is closed everywhere.
+    distribution: Option<Distribution2D>,
+}
+
+impl ApertureMask {
+    pub fn new(texture: Arc<dyn Texture>) -> Self {
+        const N: usize = MASK_RESOLUTION;
+        // Row by row from the top, as `Distribution2D` counts v down them.
+        let weights: Vec<f64> = (0..N * N)
+            .map(|i| {
+                let s = ((i % N) as f64 + 0.5) / N as f64;
+                let t = 1.0 - ((i / N) as f64 + 0.5) / N as f64;
+                texture
+                    .value(s, t, &point(s, t, 0.0))
+                    .luminance()
+                    .clamp(0.0, 1.0)
+            })
+            .collect();
+        let distribution = Distribution2D::new(&weights, N);
+        let closed = distribution.sample((0.5, 0.5)).is_none();
+        Self {
+            texture,
+            distribution: (!closed).then_some(distribution),
+        }
+    }
+
+    /// Maps `(u, v)` in [0, 1)² to a point of the square around the lens,
+    /// spread over it as the mask is open, or the center of the lens if the
+    /// mask is closed.
+    pub fn sample(&self, u: f64, v: f64) -> Vec3 {
+        match self.distribution.as_ref().and_then(|d| d.sample((u, v))) {
+            Some(((s, down), _)) => Vec3(2.0 * s - 1.0, 1.0 - 2.0 * down, 0.0),
+            None => Vec3(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+impl fmt::Debug for ApertureShape {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ApertureShape::Circle => write!(f, "Circle"),
+            ApertureShape::Polygon { blades, rotation } => f
+                .debug_struct("Polygon")
+                .field("blades", blades)
+                .field("rotation", rotation)
+                .finish(),
+            ApertureShape::Image(_) => write!(f, "Image(..)"),
+        }
+    }
+}
+
+impl ApertureShape {
+    /// A round opening without blades, or a polygon with them.
+    pub fn from_blades(blades: Option<u32>, rotation: f64) -> Self {
+        match blades {
+            Some(blades) => ApertureShape::Polygon { blades, rotation },
+            None => ApertureShape::Circle,
+        }
+    }
+
+    /// An opening the shape of `texture`'s bright parts; see `ApertureMask`.
+    pub fn image(texture: Arc<dyn Texture>) -> Self {
+        ApertureShape::Image(ApertureMask::new(texture))
+    }
+
+    /// Maps `(u, v)` in [0, 1)² to a point of the opening, within the unit
+    /// square of the xy plane, spread evenly over it as `(u, v)` is over
+    /// theirs.
+    pub fn sample(&self, u: f64, v: f64) -> Vec3 {
+        match self {
+            ApertureShape::Circle => Vec3::in_unit_disk(u, v),
+            ApertureShape::Polygon { blades, rotation } => {
+                RegularPolygon::new(*blades, *rotation).sample(u, v)
+            }
+            ApertureShape::Image(mask) => mask.sample(u, v),
+        }
+    }
+
+    /// A random point of the opening. Always draws exactly two numbers.
+    pub fn random(&self, rng: &mut (impl Rng + ?Sized)) -> Vec3 {
+        self.sample(rng.gen(), rng.gen())
+    }
+}
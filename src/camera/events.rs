@@ -0,0 +1,284 @@
+use std::{
+    ops::ControlFlow,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use crate::{
+    rays_traced, Camera, Color, Hittable, HittableList, ImageBuffer, Instant, LightList, RaySample,
+    RenderStats,
+};
+
+/// How `Camera::render_with` splits up a render.
+#[derive(Clone, Debug)]
+pub struct RenderSettings {
+    /// The width and height of the square tiles the image is rendered in,
+    /// handed out to the camera's `thread_count` threads. Each checks for
+    /// cancellation before taking another.
+    pub tile_size: usize,
+    /// How many passes over the image the samples are spread across, each
+    /// adding its share of every pixel's samples. At most one per sample.
+    pub passes: u32,
+    /// Set from anywhere, e.g. a GUI's cancel button on another thread, to
+    /// stop the render after the tiles it is on.
+    pub cancel: Arc<AtomicBool>,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            tile_size: 32,
+            passes: 1,
+            cancel: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+/// A finished tile of a pass, reported as it finishes, in no particular
+/// order with several threads: the pixels from (`x`, `y`), `width` by
+/// `height`, row by row, each averaged over its samples so far.
+pub struct Tile<'a> {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    /// The pass it was finished in, counting from 0.
+    pub pass: u32,
+    /// Samples taken of each of its pixels.
+    pub samples: u32,
+    pub pixels: &'a [Color],
+}
+
+/// What `Camera::render_with` reports as it goes.
+pub enum RenderEvent<'a> {
+    /// The tiles finished so far, each pass's counted anew, out of all the
+    /// tiles of every pass.
+    Progress {
+        done: usize,
+        total: usize,
+    },
+    Tile(Tile<'a>),
+    /// A pass over the whole image has finished, leaving `image` with
+    /// `samples` samples of every pixel.
+    Pass {
+        pass: u32,
+        passes: u32,
+        samples: u32,
+        image: &'a ImageBuffer,
+    },
+    /// The render has finished; the last event of one that wasn't cancelled,
+    /// and only sent if nothing broke or cancelled it before.
+    Finished(&'a RenderStats),
+}
+
+/// What `Camera::render_with` leaves: the image, finished or as far as it
+/// got, and the statistics of the render.
+pub struct RenderOutcome {
+    /// If cancelled, each pixel averaged over the samples it got, and black
+    /// where it got none.
+    pub image: ImageBuffer,
+    pub stats: RenderStats,
+    /// Whether `events` returned `Break` or `settings.cancel` was set before
+    /// `Finished`, even if every tile was done by then.
+    pub cancelled: bool,
+}
+
+impl Camera {
+    /// Renders the world tile by tile for a program embedding the tracer,
+    /// reporting each tile, pass, and the progress to `events` as it goes
+    /// and printing nothing. Returning `Break` from `events`, or setting
+    /// `settings.cancel`, stops the render after the tiles it is on, and
+    /// no more events are sent.
+    ///
+    /// Every pixel sums its samples in the order `pixel_color` does, so a
+    /// finished image is exactly `render_image`'s. Stereo, supersampled, and
+    /// adaptive renders skip straight to it, and can only be cancelled
+    /// before they start.
+    pub fn render_with(
+        &self,
+        world: &HittableList,
+        lights: &LightList,
+        settings: &RenderSettings,
+        events: &mut dyn FnMut(RenderEvent) -> ControlFlow<()>,
+    ) -> RenderOutcome {
+        let start = Instant::now();
+        let rays_before = rays_traced();
+        // Breaking stops this render without setting `settings.cancel`, so
+        // the settings can start another.
+        let broke = AtomicBool::new(false);
+        let cancelled = || broke.load(Ordering::Relaxed) || settings.cancel.load(Ordering::Relaxed);
+        let stats = |image: &ImageBuffer, render_time: Duration| RenderStats {
+            width: image.width,
+            height: image.height,
+            samples: self.samples_per_pixel(),
+            mean_samples: None,
+            threads: self.thread_count(),
+            rays: rays_traced() - rays_before,
+            primitives: world.primitive_count(),
+            render_time,
+            stages: Vec::new(),
+            convergence: None,
+            trace: None,
+        };
+        let send = |events: &mut dyn FnMut(RenderEvent) -> ControlFlow<()>, event: RenderEvent| {
+            if !cancelled() && events(event).is_break() {
+                broke.store(true, Ordering::Relaxed);
+            }
+        };
+        let stopped = |image: ImageBuffer| {
+            let stats = stats(&image, start.elapsed());
+            RenderOutcome {
+                image,
+                stats,
+                cancelled: true,
+            }
+        };
+
+        if self.stereo.is_some() || self.supersample > 1 || self.adaptive.is_some() {
+            let (width, height) = self.output_size();
+            if cancelled() {
+                return stopped(ImageBuffer::new(width, height));
+            }
+            let image = self.render_image(world, lights);
+            send(
+                events,
+                RenderEvent::Pass {
+                    pass: 0,
+                    passes: 1,
+                    samples: self.aa_samples as u32,
+                    image: &image,
+                },
+            );
+            if cancelled() {
+                return stopped(image);
+            }
+            let stats = stats(&image, start.elapsed());
+            send(events, RenderEvent::Finished(&stats));
+            return RenderOutcome {
+                image,
+                stats,
+                cancelled: false,
+            };
+        }
+
+        let seed = self.seed.unwrap_or_else(rand::random);
+        let (width, height) = (self.image_width as usize, self.image_height as usize);
+        let total = self.aa_samples as u32;
+        let passes = settings.passes.clamp(1, total.max(1));
+        let size = settings.tile_size.max(1);
+        let tiles: Vec<(usize, usize)> = (0..height)
+            .step_by(size)
+            .flat_map(|y| (0..width).step_by(size).map(move |x| (x, y)))
+            .collect();
+        let corners = |t: usize| {
+            let (x0, y0) = tiles[t];
+            (x0, y0, (x0 + size).min(width), (y0 + size).min(height))
+        };
+        // Each tile's sums, row by row, kept between passes.
+        let sums: Vec<Mutex<Vec<RaySample>>> = (0..tiles.len())
+            .map(|t| {
+                let (x0, y0, x1, y1) = corners(t);
+                Mutex::new(vec![RaySample::TRANSPARENT; (x1 - x0) * (y1 - y0)])
+            })
+            .collect();
+        let mut image = ImageBuffer::new(width, height);
+        let mut pixels = Vec::with_capacity(size * size);
+        let mut done = 0;
+
+        for pass in 0..passes {
+            let from = total * pass / passes;
+            let upto = total * (pass + 1) / passes;
+            // The same scaling as `pixel_average` once every sample is in,
+            // so the image matches it.
+            let scale = if pass + 1 == passes {
+                self.aa_scale
+            } else {
+                1.0 / upto as f64
+            };
+            let render_tile = |t: usize| {
+                let (x0, y0, x1, y1) = corners(t);
+                let mut sums = sums[t].lock().expect("no tile panics holding its sums");
+                let mut i = 0;
+                for y in y0..y1 {
+                    for x in x0..x1 {
+                        let (px, py) = (x as i32, y as i32);
+                        self.add_pixel_samples(
+                            world,
+                            lights,
+                            px,
+                            py,
+                            seed,
+                            from..upto,
+                            &mut sums[i],
+                        );
+                        i += 1;
+                    }
+                }
+                sums.iter()
+                    .map(|sum| (sum.color * scale, sum.alpha * scale))
+                    .collect::<Vec<_>>()
+            };
+            self.for_each_job(tiles.len(), &cancelled, &render_tile, &mut |t, tile| {
+                let (x0, y0, x1, y1) = corners(t);
+                pixels.clear();
+                let mut tile = tile.into_iter();
+                for y in y0..y1 {
+                    for x in x0..x1 {
+                        let (color, alpha) = tile.next().expect("a value for each pixel");
+                        image.set(x, y, color);
+                        if self.transparent {
+                            image.set_alpha(x, y, alpha);
+                        }
+                        pixels.push(image.get(x, y));
+                    }
+                }
+                done += 1;
+                send(
+                    events,
+                    RenderEvent::Tile(Tile {
+                        x: x0,
+                        y: y0,
+                        width: x1 - x0,
+                        height: y1 - y0,
+                        pass,
+                        samples: upto,
+                        pixels: &pixels,
+                    }),
+                );
+                send(
+                    events,
+                    RenderEvent::Progress {
+                        done,
+                        total: tiles.len() * passes as usize,
+                    },
+                );
+            });
+            if cancelled() {
+                return stopped(image);
+            }
+            send(
+                events,
+                RenderEvent::Pass {
+                    pass,
+                    passes,
+                    samples: upto,
+                    image: &image,
+                },
+            );
+        }
+
+        if cancelled() {
+            return stopped(image);
+        }
+        let stats = stats(&image, start.elapsed());
+        send(events, RenderEvent::Finished(&stats));
+        RenderOutcome {
+            image,
+            stats,
+            cancelled: false,
+        }
+    }
+}
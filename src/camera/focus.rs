@@ -0,0 +1,51 @@
+use crate::{Camera, Hittable, HittableList, IndependentSampler, Point, Ray, Vec3};
+
+impl Camera {
+    /// Focuses `point`'s distance away, which puts it in perfect focus if it
+    /// is on the line of sight. Leaves the focus as it was for `look_from`
+    /// itself.
+    pub fn focus_on_point(&mut self, point: Point) -> &mut Self {
+        let distance = (point - self.look_from).length();
+        if distance > 0.0 && distance.is_finite() {
+            self.focus_dist = Some(distance);
+            self.update_viewport();
+        }
+        self
+    }
+
+    /// Focuses on whatever in `world` is at the center of the image,
+    /// returning the new focus distance, or `None`, leaving the focus as it
+    /// was, if nothing is there.
+    pub fn focus_on(&mut self, world: &HittableList) -> Option<f64> {
+        let ray = Ray {
+            origin: self.look_from,
+            direction: (self.look_at - self.look_from).unit(),
+            time: self.shutter_open,
+        };
+        self.focus_along(world, ray)
+    }
+
+    /// Focuses on whatever in `world` is seen through the center of pixel
+    /// (x, y), as for a click on a preview, returning the new focus distance,
+    /// or `None`, leaving the focus as it was, if nothing is there. The
+    /// distance is along the line of sight, the plane in focus being square
+    /// to it, so off-center objects are in focus too.
+    pub fn focus_on_pixel(&mut self, world: &HittableList, x: i32, y: i32) -> Option<f64> {
+        let ray = self.pixel_ray(x, y, Vec3(0.0, 0.0, 0.0));
+        self.focus_along(world, ray)
+    }
+
+    fn focus_along(&mut self, world: &HittableList, ray: Ray) -> Option<f64> {
+        // Volumes decide where a ray scatters at random; any seed will do.
+        let mut rng = IndependentSampler::new(0);
+        let hit = world.hit(&ray, self.clip_interval(&ray), &mut rng)?;
+        let forward = (self.look_at - self.look_from).unit();
+        let distance = Vec3::dot(&(hit.point - self.look_from), &forward);
+        if !(distance > 0.0 && distance.is_finite()) {
+            return None;
+        }
+        self.focus_dist = Some(distance);
+        self.update_viewport();
+        Some(distance)
+    }
+}
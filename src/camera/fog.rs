@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Color, RaySample};
+
+/// Atmospheric perspective: what the camera sees fades toward `color` the
+/// farther away it is, by `exp(-(distance - start) / falloff)`. Only the
+/// light reaching the camera is fogged, not the light bouncing around the
+/// scene, so it costs nothing but the fading itself; an infinite `falloff`
+/// leaves the render as it was.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct FogSettings {
+    pub color: Color,
+    pub falloff: f64,
+    /// How far from the camera the fog begins.
+    #[serde(default)]
+    pub start: f64,
+    /// Show rays that hit nothing the background unfogged, instead of
+    /// lost in the fog entirely.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub keep_background: bool,
+}
+
+impl FogSettings {
+    pub fn new(color: Color, falloff: f64) -> Self {
+        Self {
+            color,
+            falloff,
+            start: 0.0,
+            keep_background: false,
+        }
+    }
+
+    /// How much of what is `distance` away shows through the fog.
+    pub fn transmittance(&self, distance: f64) -> f64 {
+        if self.falloff == f64::INFINITY {
+            return 1.0;
+        }
+        (-(distance - self.start).max(0.0) / self.falloff).exp()
+    }
+
+    /// `sample` seen through the fog, with `distance` to what it hit first,
+    /// or `None` if it hit nothing. Where the sample is partly transparent,
+    /// only the part of it covered fades.
+    pub fn apply(&self, sample: RaySample, distance: Option<f64>) -> RaySample {
+        let transmittance = match distance {
+            Some(distance) => self.transmittance(distance),
+            None if self.keep_background => 1.0,
+            None => self.transmittance(f64::INFINITY),
+        };
+        if transmittance == 1.0 {
+            return sample;
+        }
+        RaySample {
+            color: sample.color * transmittance
+                + self.color * (sample.alpha * (1.0 - transmittance)),
+            alpha: sample.alpha,
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{Camera, Hittable, HittableList, ImageBuffer, Vec3};
+
+/// How much of each pixel the objects tagged with each of a few `Matte`
+/// names cover, for masking them out of a render when compositing.
+pub struct Mattes {
+    pub width: usize,
+    pub height: usize,
+    /// The names asked for, each with its coverage row by row.
+    pub mattes: Vec<(String, Vec<f64>)>,
+    /// The coverage of everything else: the background and any object
+    /// tagged with none of the names. With the mattes, it sums to 1.
+    pub background: Vec<f64>,
+}
+
+impl Mattes {
+    /// The coverage of the matte `name`, row by row, if it was rendered.
+    pub fn coverage(&self, name: &str) -> Option<&[f64]> {
+        self.mattes
+            .iter()
+            .find(|(matte, _)| matte == name)
+            .map(|(_, coverage)| coverage.as_slice())
+    }
+
+    /// The matte `name` as a grayscale image, or the background's without a
+    /// name. Coverage is stored squared, so that gamma correction leaves the
+    /// saved values proportional to it.
+    pub fn image(&self, name: Option<&str>) -> Option<ImageBuffer> {
+        let coverage = match name {
+            Some(name) => self.coverage(name)?,
+            None => &self.background,
+        };
+        let mut image = ImageBuffer::new(self.width, self.height);
+        for (pixel, &c) in image.pixels.iter_mut().zip(coverage) {
+            *pixel = Vec3(c * c, c * c, c * c);
+        }
+        Some(image)
+    }
+
+    /// Up to three mattes in one image, in its red, green, and blue
+    /// channels, stored squared as for `image`. `None` if any of `names`
+    /// wasn't rendered or there are more than three.
+    pub fn packed(&self, names: &[&str]) -> Option<ImageBuffer> {
+        if names.len() > 3 {
+            return None;
+        }
+        let channels = names
+            .iter()
+            .map(|name| self.coverage(name))
+            .collect::<Option<Vec<_>>>()?;
+        let mut image = ImageBuffer::new(self.width, self.height);
+        for (i, pixel) in image.pixels.iter_mut().enumerate() {
+            let mut rgb = [0.0; 3];
+            for (channel, coverage) in rgb.iter_mut().zip(&channels) {
+                *channel = coverage[i] * coverage[i];
+            }
+            *pixel = Vec3(rgb[0], rgb[1], rgb[2]);
+        }
+        Some(image)
+    }
+}
+
+impl Camera {
+    /// Renders a matte for each of `names`: the fraction of each pixel's
+    /// samples whose first hit was inside a `Matte` of that name. The rays
+    /// are the ones `render_image` traces, so edges are antialiased to match
+    /// it exactly in seeded renders; unseeded ones use seed 0. A glass or
+    /// shadow-catching object covers what it is in front of all the same.
+    /// Stereo cameras render the view between the eyes.
+    pub fn render_mattes(&self, world: &HittableList, names: &[&str]) -> Mattes {
+        let seed = self.seed.unwrap_or(0);
+        let (width, height) = (self.image_width as usize, self.image_height as usize);
+        let mut mattes: Vec<(String, Vec<f64>)> = names
+            .iter()
+            .map(|name| (name.to_string(), vec![0.0; width * height]))
+            .collect();
+        let mut background = vec![0.0; width * height];
+        for y in 0..self.image_height {
+            for x in 0..self.image_width {
+                let i = y as usize * width + x as usize;
+                for sample in 0..self.aa_samples as u32 {
+                    let matte = self.with_pixel_sampler(seed, x, y, sample, &mut |sampler| {
+                        let ray = self.sample_ray(x, y, sampler);
+                        let record = world.hit(&ray, self.clip_interval(&ray), sampler)?;
+                        names.iter().position(|&name| record.matte == Some(name))
+                    });
+                    match matte {
+                        Some(m) => mattes[m].1[i] += self.aa_scale,
+                        None => background[i] += self.aa_scale,
+                    }
+                }
+            }
+        }
+        Mattes {
+            width,
+            height,
+            mattes,
+            background,
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::{
+    panic,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+};
+
+use crate::{
+    add_rays_traced, add_trace_stats, count_trace_stats, counting_trace_stats, rays_traced,
+    take_trace_stats, Camera, Instant,
+};
+
+impl Camera {
+    /// How many threads a render uses: `threads`, or if that's 0, one for
+    /// each core. The browser has no threads to spare, so always one there.
+    pub fn thread_count(&self) -> usize {
+        if cfg!(all(target_arch = "wasm32", target_os = "unknown")) {
+            return 1;
+        }
+        match self.threads {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            threads => threads,
+        }
+    }
+
+    /// Runs `row` for each of `rows` rows across `thread_count` threads,
+    /// and hands each result to `done` on this thread as it arrives, in no
+    /// particular order; see `for_each_job`. Progress is reported after
+    /// each, taking `samples_per_row` camera samples.
+    pub(crate) fn for_each_row<T: Send>(
+        &self,
+        rows: usize,
+        samples_per_row: usize,
+        row: &(dyn Fn(usize) -> T + Sync),
+        done: &mut dyn FnMut(usize, T),
+    ) {
+        let start = Instant::now();
+        let mut rows_done = 0;
+        self.for_each_job(rows, &|| false, row, &mut |y, result| {
+            done(y, result);
+            rows_done += 1;
+            self.report_progress(start, rows_done, rows, samples_per_row);
+        });
+    }
+
+    /// Runs `job` for each of `jobs` jobs across `thread_count` threads,
+    /// each taking the next job no thread has yet until `stop` says to take
+    /// no more, and hands each result to `done` on this thread as it
+    /// arrives, in no particular order. The rays the threads trace count
+    /// toward this thread's `rays_traced`, and its `TraceStats` if it is
+    /// counting them.
+    pub(crate) fn for_each_job<T: Send>(
+        &self,
+        jobs: usize,
+        stop: &(dyn Fn() -> bool + Sync),
+        job: &(dyn Fn(usize) -> T + Sync),
+        done: &mut dyn FnMut(usize, T),
+    ) {
+        let threads = self.thread_count().min(jobs);
+        if threads <= 1 {
+            for i in 0..jobs {
+                if stop() {
+                    return;
+                }
+                done(i, job(i));
+            }
+            return;
+        }
+        let next = AtomicUsize::new(0);
+        let counting = counting_trace_stats();
+        let (sender, receiver) = mpsc::channel();
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|_| {
+                    let sender = sender.clone();
+                    let next = &next;
+                    scope.spawn(move || {
+                        if counting {
+                            count_trace_stats();
+                        }
+                        while !stop() {
+                            let i = next.fetch_add(1, Ordering::Relaxed);
+                            if i >= jobs {
+                                break;
+                            }
+                            let before = rays_traced();
+                            let result = job(i);
+                            if sender.send((i, result, rays_traced() - before)).is_err() {
+                                break;
+                            }
+                        }
+                        take_trace_stats()
+                    })
+                })
+                .collect();
+            drop(sender);
+            for (i, result, rays) in receiver {
+                add_rays_traced(rays);
+                done(i, result);
+            }
+            for worker in workers {
+                match worker.join() {
+                    Ok(Some(stats)) => add_trace_stats(stats),
+                    Ok(None) => {}
+                    Err(panic) => panic::resume_unwind(panic),
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use crate::{color, Aovs, Camera, Color, ImageBuffer, Vec3};
+
+/// A filter run over a finished render in linear color, before exposure,
+/// gamma correction, and compositing, such as a denoiser. Cameras run a
+/// chain of them, see `Camera::add_post_process`.
+pub trait PostProcess: Send + Sync {
+    /// The filtered image, the same size as `image`. `aovs`, when the
+    /// render made them, describe what each pixel shows, for filters that
+    /// want to keep to its edges.
+    fn apply(&self, image: &ImageBuffer, aovs: Option<&Aovs>) -> ImageBuffer;
+}
+
+/// A joint bilateral filter: each pixel becomes a weighted average of its
+/// neighbors, the weights falling off with distance and with how much a
+/// neighbor's color, and if there are `Aovs`, its normal and albedo,
+/// differ from the pixel's. Noise on a flat wall is smoothed away while
+/// the edges of objects, of shadows, and of textures are kept.
+///
+/// Each falloff is a Gaussian with the given standard deviation. An
+/// infinite one ignores that difference, and 0 only blends pixels where
+/// it is the same. Pixels past the edges of the image are left out of the
+/// average rather than read.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BilateralFilter {
+    /// Of the distance to a neighbor, in pixels; neighbors up to twice it
+    /// away, rounded up, are averaged.
+    pub spatial_sigma: f64,
+    /// Of the difference in color, see `compressed`.
+    pub range_sigma: f64,
+    /// Of the difference in the normal pass.
+    pub normal_sigma: f64,
+    /// Of the difference in the albedo pass.
+    pub albedo_sigma: f64,
+}
+
+impl BilateralFilter {
+    pub fn new(spatial_sigma: f64, range_sigma: f64) -> Self {
+        Self {
+            spatial_sigma,
+            range_sigma,
+            ..Self::default()
+        }
+    }
+
+    /// Whether every sigma is at least 0, as the falloffs need.
+    pub fn is_valid(&self) -> bool {
+        [
+            self.spatial_sigma,
+            self.range_sigma,
+            self.normal_sigma,
+            self.albedo_sigma,
+        ]
+        .iter()
+        .all(|&sigma| sigma >= 0.0)
+    }
+}
+
+/// Two pixels of falloff, a light touch on color so that only fireflies
+/// and strong edges hold against it, and tight guides.
+impl Default for BilateralFilter {
+    fn default() -> Self {
+        Self {
+            spatial_sigma: 2.0,
+            range_sigma: 1.0,
+            normal_sigma: 0.2,
+            albedo_sigma: 0.1,
+        }
+    }
+}
+
+/// `c` with each component x made x / (1 + x), so that colors are told
+/// apart by how different they look rather than by how much light they
+/// carry: a firefly differs from a wall about as much as white does, not
+/// a hundred times as much. Only the weights use it; the average is taken
+/// of the linear colors.
+fn compressed(c: Color) -> Color {
+    Vec3(c.0 / (1.0 + c.0), c.1 / (1.0 + c.1), c.2 / (1.0 + c.2))
+}
+
+/// The Gaussian weight of a squared difference `d2` with deviation
+/// `sigma`: 1 for no difference whatever `sigma` is.
+fn falloff(d2: f64, sigma: f64) -> f64 {
+    if d2 == 0.0 {
+        1.0
+    } else {
+        (-d2 / (2.0 * sigma * sigma)).exp()
+    }
+}
+
+impl PostProcess for BilateralFilter {
+    fn apply(&self, image: &ImageBuffer, aovs: Option<&Aovs>) -> ImageBuffer {
+        let (width, height) = (image.width, image.height);
+        // Passes of another size, e.g. a supersampled render's, can't guide it.
+        let aovs = aovs.filter(|aovs| (aovs.width, aovs.height) == (width, height));
+        let radius = (2.0 * self.spatial_sigma).ceil() as usize;
+
+        let mut filtered = ImageBuffer::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let i = y * width + x;
+                let center = compressed(image.pixels[i]);
+                let (mut sum, mut alpha, mut total) = (color(0.0, 0.0, 0.0), 0.0, 0.0);
+                for ny in y.saturating_sub(radius)..(y + radius + 1).min(height) {
+                    for nx in x.saturating_sub(radius)..(x + radius + 1).min(width) {
+                        let j = ny * width + nx;
+                        let (dx, dy) = (nx as f64 - x as f64, ny as f64 - y as f64);
+                        let mut weight = falloff(dx * dx + dy * dy, self.spatial_sigma)
+                            * falloff(
+                                (compressed(image.pixels[j]) - center).length_squared(),
+                                self.range_sigma,
+                            );
+                        if let Some(aovs) = aovs {
+                            weight *= falloff(
+                                (aovs.normal[j] - aovs.normal[i]).length_squared(),
+                                self.normal_sigma,
+                            ) * falloff(
+                                (aovs.albedo[j] - aovs.albedo[i]).length_squared(),
+                                self.albedo_sigma,
+                            );
+                        }
+                        sum += image.pixels[j] * weight;
+                        alpha += image.get_alpha(nx, ny) * weight;
+                        total += weight;
+                    }
+                }
+                // The pixel itself always counts fully, so `total` is at least 1.
+                filtered.pixels[i] = sum / total;
+                if image.alpha.is_some() {
+                    filtered.set_alpha(x, y, alpha / total);
+                }
+            }
+        }
+        filtered
+    }
+}
+
+impl Camera {
+    /// Adds `filter` to the end of the filters `post_process` runs.
+    pub fn add_post_process(&mut self, filter: Arc<dyn PostProcess>) -> &mut Self {
+        self.post_process.push(filter);
+        self
+    }
+
+    /// Runs `image`, a render from this camera, through its post-process
+    /// filters in the order they were added, each guided by `aovs` if
+    /// given. Like exposure, the render methods leave this to the caller,
+    /// to do before exposing the image.
+    pub fn post_process(&self, image: ImageBuffer, aovs: Option<&Aovs>) -> ImageBuffer {
+        self.post_process
+            .iter()
+            .fold(image, |image, filter| filter.apply(&image, aovs))
+    }
+}
@@ -0,0 +1,82 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+use crate::{verbosity, Camera, Instant, Verbosity};
+
+/// How far a render has got, as reported to a camera's `progress` hook.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderProgress {
+    /// Rows finished so far, in no particular order once there are threads.
+    pub rows_done: usize,
+    pub rows: usize,
+    pub elapsed: Duration,
+    /// Camera samples taken per second of the render so far.
+    pub samples_per_second: f64,
+}
+
+impl RenderProgress {
+    pub fn rows_remaining(&self) -> usize {
+        self.rows - self.rows_done
+    }
+}
+
+impl fmt::Display for RenderProgress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Scanlines remaining: {} ({:.0} samples/s)",
+            self.rows_remaining(),
+            self.samples_per_second
+        )
+    }
+}
+
+/// Called with each row a render finishes. It is called on the thread that
+/// started the render, but shared with the threads rendering rows along
+/// with the rest of the camera, so it must be `Send` and `Sync`.
+pub type ProgressHook = Arc<dyn Fn(RenderProgress) + Send + Sync>;
+
+impl Camera {
+    /// Calls `progress` after each row of every render, e.g. to drive a
+    /// progress bar. Stereo renders report each eye's rows in turn, and
+    /// supersampled ones the rows of the larger image. Set the `progress`
+    /// field to `None` to stop.
+    pub fn set_progress(
+        &mut self,
+        progress: impl Fn(RenderProgress) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Reports `rows_done` of `rows` to the `progress` hook, and with
+    /// verbose logging, on stderr.
+    pub(crate) fn report_progress(
+        &self,
+        start: Instant,
+        rows_done: usize,
+        rows: usize,
+        samples_per_row: usize,
+    ) {
+        let verbose = verbosity() >= Verbosity::Verbose;
+        if self.progress.is_none() && !verbose {
+            return;
+        }
+        let elapsed = start.elapsed();
+        let samples = (rows_done * samples_per_row) as f64;
+        let progress = RenderProgress {
+            rows_done,
+            rows,
+            elapsed,
+            samples_per_second: samples / elapsed.as_secs_f64().max(1e-9),
+        };
+        if let Some(hook) = &self.progress {
+            hook(progress);
+        }
+        if verbose {
+            eprint!("\r{}   ", progress);
+            if rows_done == rows {
+                eprintln!();
+            }
+        }
+    }
+}
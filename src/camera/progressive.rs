@@ -0,0 +1,146 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{Camera, HittableList, ImageBuffer, LightList, RaySample};
+
+/// The block sizes of the coarse passes: each samples one pixel in every
+/// block and fills the block with it.
+pub const PREVIEW_BLOCKS: [usize; 3] = [8, 4, 2];
+
+/// One image of a progressive render, see `Camera::render_progressive`.
+pub struct Preview<'a> {
+    pub image: &'a ImageBuffer,
+    /// The size of the blocks of pixels that share a color: 8, 4, and 2 in
+    /// the coarse passes, then 1 at full resolution.
+    pub block: usize,
+    /// Samples taken of each pixel that has been sampled.
+    pub samples: u32,
+    /// Whether this is the finished image, the same as `render_image`'s.
+    pub done: bool,
+}
+
+impl Camera {
+    /// Renders the world coarse to fine, calling `on_pass` with an image
+    /// after each pass: one sample each of every 8th, 4th, and 2nd pixel in
+    /// both directions, filling the blocks between them, then every pixel at
+    /// full resolution with the samples doubling each pass.
+    ///
+    /// Coarse samples are kept, not thrown away: every pixel sums its
+    /// samples in the order `pixel_color` does, so the finished image is
+    /// exactly `render_image`'s. Stereo and supersampled renders skip
+    /// straight to it.
+    pub fn render_progressive(
+        &self,
+        world: &HittableList,
+        lights: &LightList,
+        on_pass: &mut dyn FnMut(&Preview),
+    ) -> ImageBuffer {
+        self.render_progressive_until(world, lights, &AtomicBool::new(false), on_pass)
+            .expect("nothing stops the render")
+    }
+
+    /// Renders as `render_progressive` does until `stop` is set, checking
+    /// after every row, e.g. to start over when a preview is clicked to
+    /// refocus. Returns `None` if stopped.
+    pub fn render_progressive_until(
+        &self,
+        world: &HittableList,
+        lights: &LightList,
+        stop: &AtomicBool,
+        on_pass: &mut dyn FnMut(&Preview),
+    ) -> Option<ImageBuffer> {
+        let stopped = || stop.load(Ordering::Relaxed);
+        if self.stereo.is_some() || self.supersample > 1 {
+            let image = self.render_image(world, lights);
+            on_pass(&Preview {
+                image: &image,
+                block: 1,
+                samples: self.aa_samples as u32,
+                done: true,
+            });
+            return Some(image);
+        }
+
+        let seed = self.seed.unwrap_or_else(rand::random);
+        let (width, height) = (self.image_width as usize, self.image_height as usize);
+        let total = self.aa_samples as u32;
+        let mut sums = vec![RaySample::TRANSPARENT; width * height];
+        let mut taken = vec![0u32; width * height];
+        let mut image = ImageBuffer::new(width, height);
+        let sample = |sums: &mut [RaySample], taken: &mut [u32], x: usize, y: usize, upto: u32| {
+            let i = y * width + x;
+            if taken[i] < upto {
+                let (x, y) = (x as i32, y as i32);
+                self.add_pixel_samples(world, lights, x, y, seed, taken[i]..upto, &mut sums[i]);
+                taken[i] = upto;
+            }
+        };
+
+        for block in PREVIEW_BLOCKS {
+            for y in (0..height).step_by(block) {
+                for x in (0..width).step_by(block) {
+                    sample(&mut sums, &mut taken, x, y, 1);
+                }
+                if stopped() {
+                    return None;
+                }
+            }
+            for y in 0..height {
+                for x in 0..width {
+                    let corner = (y - y % block) * width + (x - x % block);
+                    let sum = sums[corner];
+                    image.set(x, y, sum.color / taken[corner] as f64);
+                    if self.transparent {
+                        image.set_alpha(x, y, sum.alpha / taken[corner] as f64);
+                    }
+                }
+            }
+            on_pass(&Preview {
+                image: &image,
+                block,
+                samples: 1,
+                done: false,
+            });
+        }
+
+        let mut samples = 1;
+        loop {
+            samples = (samples * 2).min(total);
+            for y in 0..height {
+                for x in 0..width {
+                    sample(&mut sums, &mut taken, x, y, samples);
+                }
+                if stopped() {
+                    return None;
+                }
+            }
+            let done = samples == total;
+            for (i, sum) in sums.iter().enumerate() {
+                // The same scaling as `pixel_average`, so the last pass matches it.
+                let scale = |value: f64| {
+                    if done {
+                        value * self.aa_scale
+                    } else {
+                        value / samples as f64
+                    }
+                };
+                image.pixels[i] = if done {
+                    sum.color * self.aa_scale
+                } else {
+                    sum.color / samples as f64
+                };
+                if self.transparent {
+                    image.set_alpha(i % width, i / width, scale(sum.alpha));
+                }
+            }
+            on_pass(&Preview {
+                image: &image,
+                block: 1,
+                samples,
+                done,
+            });
+            if done {
+                return Some(image);
+            }
+        }
+    }
+}
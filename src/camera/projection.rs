@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+/// How the camera maps the scene onto the image.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Projection {
+    /// Rays fan out from `look_from` through the viewport, `vfov` degrees
+    /// tall, so farther things look smaller.
+    #[default]
+    Perspective,
+    /// Parallel rays along the view direction, each starting from its pixel
+    /// on the plane through `look_from`, for technical drawings in which
+    /// sizes don't shrink with distance. The view is `width` scene units
+    /// wide, its height following the image's, and `vfov` is unused.
+    Orthographic { width: f64 },
+    /// A full 360° panorama: across the image is all the way around the
+    /// camera, starting and ending behind it, and down it from straight up
+    /// to straight down. The image is made 2:1 to keep the angles square,
+    /// and `vfov` is unused.
+    Equirectangular,
+    /// An equidistant fisheye, in which distance from the image's center is
+    /// proportional to the angle from the view direction: the circle
+    /// touching the image's shorter sides spans `fov` degrees, up to 360,
+    /// and the corners carry on past it. `vfov` is unused.
+    Fisheye { fov: f64 },
+}
+
+impl Projection {
+    pub fn is_default(&self) -> bool {
+        *self == Projection::default()
+    }
+
+    pub(crate) fn is_valid(&self) -> bool {
+        match self {
+            Projection::Perspective | Projection::Equirectangular => true,
+            Projection::Orthographic { width } => width.is_finite() && *width > 0.0,
+            Projection::Fisheye { fov } => *fov > 0.0 && *fov <= 360.0,
+        }
+    }
+
+    /// Whether rays leave the camera in every direction by angle rather
+    /// than through a flat viewport.
+    pub(crate) fn is_panoramic(&self) -> bool {
+        matches!(
+            self,
+            Projection::Equirectangular | Projection::Fisheye { .. }
+        )
+    }
+}
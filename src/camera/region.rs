@@ -0,0 +1,72 @@
+use std::ops::Range;
+
+use crate::{log, Camera, HittableList, ImageBuffer, LightList, RaySample, Verbosity};
+
+/// What `Camera::render_region` returns.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RegionOutput {
+    /// An image of just the region.
+    #[default]
+    Cropped,
+    /// An image the size of the full render, black outside the region.
+    FullFrame,
+}
+
+impl Camera {
+    /// Renders only the pixels in columns `x` and rows `y`, each exactly as
+    /// the full render would, so a seeded crop lines up with it pixel for
+    /// pixel. A window reaching past the image is clamped to it with a
+    /// warning. Stereo and supersampled cameras render the region as a
+    /// plain mono camera of their size would.
+    pub fn render_region(
+        &self,
+        world: &HittableList,
+        lights: &LightList,
+        x: Range<usize>,
+        y: Range<usize>,
+        output: RegionOutput,
+    ) -> ImageBuffer {
+        let (width, height) = (self.image_width as usize, self.image_height as usize);
+        let clamp = |range: &Range<usize>, size: usize| {
+            let start = range.start.min(size);
+            start..range.end.clamp(start, size)
+        };
+        let (columns, rows) = (clamp(&x, width), clamp(&y, height));
+        if (&columns, &rows) != (&x, &y) {
+            log(
+                Verbosity::Normal,
+                format_args!(
+                    "warning: crop window {:?} by {:?} clamped to the {} by {} image: {:?} by {:?}",
+                    x, y, width, height, columns, rows
+                ),
+            );
+        }
+
+        let seed = self.seed.unwrap_or_else(rand::random);
+        let (origin, mut image) = match output {
+            RegionOutput::Cropped => (
+                (columns.start, rows.start),
+                ImageBuffer::new(columns.len(), rows.len()),
+            ),
+            RegionOutput::FullFrame => ((0, 0), ImageBuffer::new(width, height)),
+        };
+        let row = |i: usize| -> Vec<RaySample> {
+            let y = rows.start + i;
+            columns
+                .clone()
+                .map(|x| self.pixel_average(world, lights, x as i32, y as i32, seed))
+                .collect()
+        };
+        let samples_per_row = columns.len() * self.samples_per_pixel() as usize;
+        self.for_each_row(rows.len(), samples_per_row, &row, &mut |i, pixels| {
+            let y = rows.start + i - origin.1;
+            for (pixel, x) in pixels.into_iter().zip(columns.clone()) {
+                image.set(x - origin.0, y, pixel.color);
+                if self.transparent {
+                    image.set_alpha(x - origin.0, y, pixel.alpha);
+                }
+            }
+        });
+        image
+    }
+}
@@ -0,0 +1,110 @@
+use std::{str::FromStr, time::Duration};
+
+use crate::{Accumulation, Camera, HittableList, ImageBuffer, Instant, LightList};
+
+/// How often a long render hands out a snapshot of its progress: after so
+/// much time, or after so many passes of one sample of every pixel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SnapshotEvery {
+    Interval(Duration),
+    Passes(u32),
+}
+
+impl FromStr for SnapshotEvery {
+    type Err = String;
+
+    /// Parses `90s`, `500ms`, `5m`, `1h`, or a number of passes such as
+    /// `8passes` or `1pass`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (number, unit) = s.split_at(split);
+        let error = || {
+            format!(
+                "bad snapshot interval '{}' (expected e.g. 60s, 500ms, 5m, 1h, or 8passes)",
+                s
+            )
+        };
+        let n: u64 = number.parse().map_err(|_| error())?;
+        let every = match unit.trim() {
+            "ms" => SnapshotEvery::Interval(Duration::from_millis(n)),
+            "s" => SnapshotEvery::Interval(Duration::from_secs(n)),
+            "m" => SnapshotEvery::Interval(Duration::from_secs(n * 60)),
+            "h" => SnapshotEvery::Interval(Duration::from_secs(n * 3600)),
+            "pass" | "passes" => SnapshotEvery::Passes(u32::try_from(n).map_err(|_| error())?),
+            _ => return Err(error()),
+        };
+        match every {
+            SnapshotEvery::Interval(Duration::ZERO) | SnapshotEvery::Passes(0) => Err(error()),
+            every => Ok(every),
+        }
+    }
+}
+
+/// The progress of a render, see `Camera::render_with_snapshots`.
+pub struct Snapshot<'a> {
+    /// The samples so far. Pixels in rows the current pass has reached have
+    /// one more sample than the rest; `Accumulation::image` averages each
+    /// pixel over its own.
+    pub accumulation: &'a Accumulation,
+    /// Passes finished, each one sample of every pixel.
+    pub passes: u32,
+    pub elapsed: Duration,
+}
+
+impl Camera {
+    /// Renders the world one sample of every pixel at a time, calling
+    /// `on_snapshot` with the samples so far as often as `every` says,
+    /// never after the last pass. The time is checked after every row, so
+    /// an interval is kept to within a row's work.
+    ///
+    /// `on_snapshot` holds up the render for as long as it takes, so it
+    /// should do no more than copy the accumulation and hand it off. Every
+    /// pixel sums its samples in the order `pixel_color` does, so the
+    /// finished image is exactly `render_image`'s. Stereo and supersampled
+    /// renders skip straight to it.
+    pub fn render_with_snapshots(
+        &self,
+        world: &HittableList,
+        lights: &LightList,
+        every: SnapshotEvery,
+        on_snapshot: &mut dyn FnMut(&Snapshot),
+    ) -> ImageBuffer {
+        if self.stereo.is_some() || self.supersample > 1 {
+            return self.render_image(world, lights);
+        }
+
+        let seed = self.seed.unwrap_or_else(rand::random);
+        let (width, height) = (self.image_width as usize, self.image_height as usize);
+        let total = self.aa_samples as u32;
+        let mut accumulation = Accumulation::new(width, height, 0);
+        let start = Instant::now();
+        let mut last = start;
+        for pass in 0..total {
+            for y in 0..height {
+                for x in 0..width {
+                    let (i, x, y) = (y * width + x, x as i32, y as i32);
+                    let sample = self.pixel_sample_with_alpha(world, lights, x, y, seed, pass);
+                    accumulation.add_sample(i, sample.color);
+                    if self.transparent {
+                        accumulation.add_alpha(i, sample.alpha);
+                    }
+                }
+                let due = match every {
+                    SnapshotEvery::Interval(interval) => last.elapsed() >= interval,
+                    SnapshotEvery::Passes(passes) => y + 1 == height && (pass + 1) % passes == 0,
+                };
+                let finished = pass + 1 == total && y + 1 == height;
+                if due && !finished {
+                    let passes = if y + 1 == height { pass + 1 } else { pass };
+                    on_snapshot(&Snapshot {
+                        accumulation: &accumulation,
+                        passes,
+                        elapsed: start.elapsed(),
+                    });
+                    last = Instant::now();
+                }
+            }
+        }
+        accumulation.image()
+    }
+}
@@ -0,0 +1,54 @@
+use crate::{Camera, HittableList, ImageBuffer, LightList};
+
+impl Camera {
+    /// Renders `supersample` times wider and taller and shrinks the image
+    /// back down with `downscale_filter`, which smooths edges that more
+    /// samples of the same pixels would only average. Each row is shrunk
+    /// across as soon as it is done, so only an image as wide as the output
+    /// is kept; `progress` is called with the output rows done.
+    pub(super) fn render_supersampled(
+        &self,
+        world: &HittableList,
+        lights: &LightList,
+        progress: &mut dyn FnMut(usize),
+    ) -> ImageBuffer {
+        let factor = self.supersample as usize;
+        let mut large = self.clone();
+        large.supersample = 1;
+        large.image_width = self.image_width * self.supersample as i32;
+        large.image_height = self.image_height * self.supersample as i32;
+        large.update_viewport();
+
+        let seed = self.seed.unwrap_or_else(rand::random);
+        let mut rows = ImageBuffer::new(self.image_width as usize, large.image_height as usize);
+        let large_width = large.image_width as usize;
+        let row = |y: usize| -> ImageBuffer {
+            let mut row = ImageBuffer::new(large_width, 1);
+            for x in 0..large_width {
+                let pixel = large.pixel_average(world, lights, x as i32, y as i32, seed);
+                row.set(x, 0, pixel.color);
+                if self.transparent {
+                    row.set_alpha(x, 0, pixel.alpha);
+                }
+            }
+            row.downscale_axes(factor, 1, self.downscale_filter)
+        };
+        let mut rows_done: usize = 0;
+        let samples_per_row = large_width * self.aa_samples as usize;
+        let large_rows = large.image_height as usize;
+        self.for_each_row(large_rows, samples_per_row, &row, &mut |y, shrunk| {
+            let start = y * rows.width;
+            rows.pixels[start..start + rows.width].copy_from_slice(&shrunk.pixels);
+            if let Some(alpha) = &shrunk.alpha {
+                let len = rows.pixels.len();
+                rows.alpha.get_or_insert_with(|| vec![1.0; len])[start..start + rows.width]
+                    .copy_from_slice(alpha);
+            }
+            rows_done += 1;
+            if rows_done.is_multiple_of(factor) {
+                progress(rows_done / factor);
+            }
+        });
+        rows.downscale_axes(1, factor, self.downscale_filter)
+    }
+}
@@ -1,7 +1,35 @@
+pub mod assets;
+pub mod diff;
+pub mod exposure;
+pub mod hdr;
+pub mod image;
 pub mod interval;
+pub mod log;
+pub mod mat4;
+pub mod pdf;
+pub mod polygon;
+pub mod quat;
+pub mod random;
 pub mod rays;
+pub mod resample;
+pub mod sampler;
+pub mod transform;
 pub mod vec3;
+pub mod video;
 
+pub use assets::*;
+pub use diff::*;
+pub use exposure::*;
+pub use image::*;
 pub use interval::*;
+pub use log::*;
+pub use mat4::*;
+pub use pdf::*;
+pub use polygon::*;
+pub use quat::*;
 pub use rays::*;
+pub use resample::*;
+pub use sampler::*;
+pub use transform::*;
 pub use vec3::*;
+pub use video::*;
@@ -0,0 +1,101 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+/// Extra directories searched for assets after the scene's own directory. The
+/// crate root is always searched last, so the bundled resources resolve from anywhere.
+static SEARCH_PATH: RwLock<Vec<PathBuf>> = RwLock::new(Vec::new());
+
+/// Prepends `dirs` to the search path used by new resolvers.
+pub fn add_search_path(dirs: impl IntoIterator<Item = PathBuf>) {
+    let mut search_path = SEARCH_PATH.write().unwrap();
+    let mut dirs: Vec<PathBuf> = dirs.into_iter().collect();
+    dirs.append(&mut search_path);
+    *search_path = dirs;
+}
+
+fn search_path() -> Vec<PathBuf> {
+    let mut dirs = SEARCH_PATH.read().unwrap().clone();
+    dirs.push(PathBuf::from(env!("CARGO_MANIFEST_DIR")));
+    dirs
+}
+
+/// An asset reference that matched no file.
+#[derive(Debug, Clone)]
+pub struct AssetError {
+    pub reference: PathBuf,
+    pub tried: Vec<PathBuf>,
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "file not found: {} (tried ", self.reference.display())?;
+        for (i, path) in self.tried.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", path.display())?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl std::error::Error for AssetError {}
+
+/// Turns the file references in scenes into paths: absolute references are
+/// used as is, relative ones are tried against the scene's directory (the
+/// working directory for built-in scenes) and then each search path entry.
+#[derive(Debug, Clone)]
+pub struct AssetResolver {
+    pub base_dir: Option<PathBuf>,
+    pub search_path: Vec<PathBuf>,
+}
+
+impl AssetResolver {
+    pub fn new() -> Self {
+        Self {
+            base_dir: None,
+            search_path: search_path(),
+        }
+    }
+
+    /// A resolver for references made by the scene file at `path`.
+    pub fn for_scene_file(path: &Path) -> Self {
+        Self {
+            base_dir: Some(path.parent().unwrap_or(Path::new("")).to_path_buf()),
+            search_path: search_path(),
+        }
+    }
+
+    /// The paths `reference` may refer to, in the order they are tried.
+    pub fn candidates(&self, reference: &Path) -> Vec<PathBuf> {
+        if reference.is_absolute() {
+            return vec![reference.to_path_buf()];
+        }
+        let mut candidates = vec![match &self.base_dir {
+            Some(dir) => dir.join(reference),
+            None => reference.to_path_buf(),
+        }];
+        candidates.extend(self.search_path.iter().map(|dir| dir.join(reference)));
+        candidates
+    }
+
+    pub fn resolve(&self, reference: &Path) -> Result<PathBuf, AssetError> {
+        let tried = self.candidates(reference);
+        match tried.iter().find(|path| path.is_file()) {
+            Some(path) => Ok(path.clone()),
+            None => Err(AssetError {
+                reference: reference.to_path_buf(),
+                tried,
+            }),
+        }
+    }
+}
+
+impl Default for AssetResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,163 @@
+use std::fmt;
+
+use crate::{Color, ImageBuffer, Vec3};
+
+/// How far apart two images are, channel by channel, in the units of their
+/// pixels: linear color for rendered buffers, or [0, 1] for `encoded` ones.
+#[derive(Clone, Debug)]
+pub struct DiffReport {
+    pub width: usize,
+    pub height: usize,
+    /// The mean absolute difference over every channel of every pixel.
+    pub mean_error: f64,
+    /// The root mean square difference over every channel of every pixel.
+    pub rms_error: f64,
+    /// The largest difference of any one channel, and the pixel it is in.
+    pub max_error: f64,
+    pub max_at: (usize, usize),
+    /// The largest channel difference of each pixel, row by row.
+    pub errors: Vec<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffError {
+    SizeMismatch {
+        left: (usize, usize),
+        right: (usize, usize),
+    },
+}
+
+impl fmt::Display for DiffError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiffError::SizeMismatch { left, right } => write!(
+                f,
+                "can't compare a {} by {} image with a {} by {} one",
+                left.0, left.1, right.0, right.1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DiffError {}
+
+impl ImageBuffer {
+    /// Compares this image with `other` pixel by pixel. Rendered buffers are
+    /// compared in linear color, so bright HDR pixels count in full; compare
+    /// their `encoded` versions to measure what a viewer of the files sees.
+    pub fn diff(&self, other: &ImageBuffer) -> Result<DiffReport, DiffError> {
+        if (self.width, self.height) != (other.width, other.height) {
+            return Err(DiffError::SizeMismatch {
+                left: (self.width, self.height),
+                right: (other.width, other.height),
+            });
+        }
+        let (mut sum, mut sum_squares) = (0.0, 0.0);
+        let (mut max_error, mut max_index) = (0.0, 0);
+        let errors: Vec<f64> = self
+            .pixels
+            .iter()
+            .zip(&other.pixels)
+            .enumerate()
+            .map(|(i, (a, b))| {
+                let d = *a - *b;
+                let d = [d.0.abs(), d.1.abs(), d.2.abs()];
+                sum += d.iter().sum::<f64>();
+                sum_squares += d.iter().map(|d| d * d).sum::<f64>();
+                let error = d.into_iter().fold(0.0, f64::max);
+                if error > max_error {
+                    (max_error, max_index) = (error, i);
+                }
+                error
+            })
+            .collect();
+        let channels = (errors.len() * 3).max(1) as f64;
+        Ok(DiffReport {
+            width: self.width,
+            height: self.height,
+            mean_error: sum / channels,
+            rms_error: (sum_squares / channels).sqrt(),
+            max_error,
+            max_at: (max_index % self.width.max(1), max_index / self.width.max(1)),
+            errors,
+        })
+    }
+
+    /// The image as it is written out: gamma-corrected and quantized to
+    /// 8 bits, with each channel in [0, 1].
+    pub fn encoded(&self) -> ImageBuffer {
+        ImageBuffer {
+            width: self.width,
+            height: self.height,
+            pixels: self
+                .pixels
+                .iter()
+                .map(|pixel| {
+                    let [r, g, b] = pixel.to_gamma().to_bytes();
+                    Vec3(r as f64, g as f64, b as f64) / 255.0
+                })
+                .collect(),
+            alpha: self.alpha.clone(),
+        }
+    }
+}
+
+impl DiffReport {
+    /// How many pixels have a channel differing by more than `threshold`.
+    pub fn pixels_over(&self, threshold: f64) -> usize {
+        self.errors
+            .iter()
+            .filter(|&&error| error > threshold)
+            .count()
+    }
+
+    /// The differences in false color, from black where the images agree
+    /// through blue, green, and yellow to red at `max`. Without `max`, the
+    /// largest difference sets the scale.
+    pub fn image(&self, max: Option<f64>) -> ImageBuffer {
+        let max = max.unwrap_or(self.max_error);
+        ImageBuffer {
+            width: self.width,
+            height: self.height,
+            pixels: self
+                .errors
+                .iter()
+                .map(|&error| heat_color(if max > 0.0 { error / max } else { 0.0 }))
+                .collect(),
+            alpha: None,
+        }
+    }
+}
+
+impl fmt::Display for DiffReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "mean absolute error: {:.6}", self.mean_error)?;
+        writeln!(f, "root mean square error: {:.6}", self.rms_error)?;
+        write!(
+            f,
+            "max error: {:.6} at ({}, {})",
+            self.max_error, self.max_at.0, self.max_at.1
+        )
+    }
+}
+
+/* === False Color === */
+
+/// The colors of the heat ramp, from nothing to the most.
+const HEAT_RAMP: [Color; 5] = [
+    Vec3(0.0, 0.0, 0.0),
+    Vec3(0.0, 0.0, 1.0),
+    Vec3(0.0, 1.0, 0.0),
+    Vec3(1.0, 1.0, 0.0),
+    Vec3(1.0, 0.0, 0.0),
+];
+
+/// The ramp color at `t` in [0, 1], as linear color that writes out as the
+/// ramp's gamma-encoded color. Used by the BVH heat map and image diffs.
+pub fn heat_color(t: f64) -> Color {
+    let scaled = t.clamp(0.0, 1.0) * (HEAT_RAMP.len() - 1) as f64;
+    let i = (scaled as usize).min(HEAT_RAMP.len() - 2);
+    let f = scaled - i as f64;
+    let c = HEAT_RAMP[i] * (1.0 - f) + HEAT_RAMP[i + 1] * f;
+    c * c
+}
@@ -0,0 +1,161 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ImageBuffer;
+
+/// The luminance an automatic exposure brings the image's log average to:
+/// middle gray.
+pub const MIDDLE_GRAY: f64 = 0.18;
+
+/// The fraction of the darkest and of the brightest pixels `Exposure::auto`
+/// leaves out.
+const DEFAULT_EXCLUDE: f64 = 0.01;
+
+/// Keeps pixels of no light at all from sending the log average to zero.
+const LOG_EPSILON: f64 = 1e-4;
+
+/// How much a render is brightened or darkened before it is written.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Exposure {
+    /// Multiply every pixel by this.
+    Manual(f64),
+    /// Choose the multiplier that brings the log-average luminance of the
+    /// image to `key`, leaving out the fraction `exclude` of the darkest and
+    /// of the brightest pixels, such as a light seen directly.
+    Auto {
+        key: f64,
+        #[serde(default)]
+        exclude: f64,
+    },
+}
+
+impl Default for Exposure {
+    fn default() -> Self {
+        Exposure::Manual(1.0)
+    }
+}
+
+impl Exposure {
+    /// Middle gray, leaving out the darkest and brightest 1% of pixels.
+    pub fn auto() -> Self {
+        Exposure::Auto {
+            key: MIDDLE_GRAY,
+            exclude: DEFAULT_EXCLUDE,
+        }
+    }
+
+    /// Whether the exposure leaves images as they are.
+    pub fn is_identity(&self) -> bool {
+        *self == Exposure::Manual(1.0)
+    }
+
+    /// Whether the exposure can brighten or darken anything.
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            Exposure::Manual(multiplier) => multiplier.is_finite() && multiplier > 0.0,
+            Exposure::Auto { key, exclude } => {
+                key.is_finite() && key > 0.0 && (0.0..0.5).contains(&exclude)
+            }
+        }
+    }
+
+    /// The multiplier for `image`. An automatic exposure brightens the
+    /// image only as far as the brightest pixel it keeps stays below white,
+    /// so that scenes mostly in shadow don't wash out what is lit. An image
+    /// with nothing in it is left as it is.
+    pub fn multiplier(&self, image: &ImageBuffer) -> f64 {
+        let (key, exclude) = match *self {
+            Exposure::Manual(multiplier) => return multiplier,
+            Exposure::Auto { key, exclude } => (key, exclude),
+        };
+        let kept = kept_luminances(image, exclude);
+        let (Some(average), Some(&brightest)) = (log_average(&kept), kept.last()) else {
+            return 1.0;
+        };
+        if brightest <= 0.0 {
+            return 1.0;
+        }
+        (key / average).min(1.0 / brightest)
+    }
+}
+
+impl FromStr for Exposure {
+    type Err = String;
+
+    /// A multiplier, `auto`, or `auto:KEY`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let exposure = match s.strip_prefix("auto") {
+            Some("") => Some(Exposure::auto()),
+            Some(key) => key
+                .strip_prefix(':')
+                .and_then(|key| key.parse().ok())
+                .map(|key| Exposure::Auto {
+                    key,
+                    exclude: DEFAULT_EXCLUDE,
+                }),
+            None => s.parse().ok().map(Exposure::Manual),
+        };
+        match exposure {
+            Some(exposure) if exposure.is_valid() => Ok(exposure),
+            _ => Err(format!(
+                "expected a positive multiplier, auto, or auto:KEY, got '{}'",
+                s
+            )),
+        }
+    }
+}
+
+/// The geometric mean of the luminance of the pixels of `image`, after
+/// leaving out the fraction `exclude` of the darkest and of the brightest.
+/// Pixels an alpha channel leaves uncovered don't count, and partly covered
+/// ones count as if they were whole. `None` if no pixels are left.
+pub fn log_average_luminance(image: &ImageBuffer, exclude: f64) -> Option<f64> {
+    log_average(&kept_luminances(image, exclude))
+}
+
+fn log_average(luminances: &[f64]) -> Option<f64> {
+    if luminances.is_empty() {
+        return None;
+    }
+    let log_sum: f64 = luminances.iter().map(|l| (LOG_EPSILON + l).ln()).sum();
+    Some((log_sum / luminances.len() as f64).exp())
+}
+
+/// The luminances of the covered pixels of `image` from darkest to
+/// brightest, less the fraction `exclude` at each end.
+fn kept_luminances(image: &ImageBuffer, exclude: f64) -> Vec<f64> {
+    let mut luminances: Vec<f64> = image
+        .pixels
+        .iter()
+        .enumerate()
+        .filter_map(|(i, pixel)| {
+            let alpha = match &image.alpha {
+                Some(alpha) => alpha[i],
+                None => 1.0,
+            };
+            (alpha > 0.0).then(|| (pixel.luminance() / alpha).max(0.0))
+        })
+        .filter(|luminance| luminance.is_finite())
+        .collect();
+    luminances.sort_by(f64::total_cmp);
+    let cut = (luminances.len() as f64 * exclude) as usize;
+    luminances.truncate(luminances.len() - cut);
+    luminances.drain(..cut);
+    luminances
+}
+
+impl ImageBuffer {
+    /// Applies `exposure` to the image, returning the multiplier chosen, so
+    /// that an automatic exposure can be repeated by hand.
+    pub fn expose(&mut self, exposure: Exposure) -> f64 {
+        let multiplier = exposure.multiplier(self);
+        if multiplier != 1.0 {
+            for pixel in &mut self.pixels {
+                *pixel = *pixel * multiplier;
+            }
+        }
+        multiplier
+    }
+}
@@ -0,0 +1,182 @@
+use std::io::{self, BufRead, BufWriter, Read, Write};
+
+use crate::{color, Color, ImageBuffer};
+
+/// The first line of a Radiance HDR file.
+const HDR_MAGIC: &str = "#?RADIANCE";
+
+impl ImageBuffer {
+    /// Writes a Radiance HDR (RGBE) image, which keeps linear color above 1
+    /// to about 1% precision, with `metadata` in comments in its header.
+    pub fn write_hdr(&self, out: impl Write, metadata: &str) -> io::Result<()> {
+        let mut out = BufWriter::new(out);
+        writeln!(out, "{}", HDR_MAGIC)?;
+        for line in metadata.lines() {
+            writeln!(out, "# {}", line)?;
+        }
+        writeln!(out, "FORMAT=32-bit_rle_rgbe\n")?;
+        writeln!(out, "-Y {} +X {}", self.height, self.width)?;
+        for pixel in &self.pixels {
+            out.write_all(&to_rgbe(*pixel))?;
+        }
+        out.flush()
+    }
+
+    /// Reads a Radiance HDR image, flat or run-length encoded, into linear
+    /// color. Only the usual orientation, rows top to bottom, is supported.
+    pub fn read_hdr(mut input: impl BufRead) -> io::Result<ImageBuffer> {
+        let invalid =
+            |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+        let mut line = String::new();
+        input.read_line(&mut line)?;
+        if !line.starts_with("#?") {
+            return Err(invalid("not a Radiance HDR image"));
+        }
+        loop {
+            line.clear();
+            if input.read_line(&mut line)? == 0 {
+                return Err(invalid("truncated Radiance HDR header"));
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(format) = line.strip_prefix("FORMAT=") {
+                if format != "32-bit_rle_rgbe" {
+                    return Err(invalid("only RGBE Radiance HDR images are supported"));
+                }
+            }
+        }
+        line.clear();
+        input.read_line(&mut line)?;
+        let size: Vec<_> = line.split_ascii_whitespace().collect();
+        let (height, width) = match size.as_slice() {
+            ["-Y", height, "+X", width] => (height.parse(), width.parse()),
+            _ => {
+                return Err(invalid(
+                    "only top-to-bottom Radiance HDR images are supported",
+                ))
+            }
+        };
+        let (Ok(height), Ok(width)): (Result<usize, _>, Result<usize, _>) = (height, width) else {
+            return Err(invalid("malformed Radiance HDR image size"));
+        };
+
+        let mut image = ImageBuffer::new(width, height);
+        let mut row = vec![[0u8; 4]; width];
+        for y in 0..height {
+            read_scanline(&mut input, &mut row)?;
+            for (x, rgbe) in row.iter().enumerate() {
+                image.set(x, y, from_rgbe(*rgbe));
+            }
+        }
+        Ok(image)
+    }
+}
+
+/// Reads one row of RGBE pixels, in either the flat or the run-length
+/// encoding of each channel in turn.
+fn read_scanline(input: &mut impl Read, row: &mut [[u8; 4]]) -> io::Result<()> {
+    let width = row.len();
+    if width == 0 {
+        return Ok(());
+    }
+    let mut first = [0u8; 4];
+    input.read_exact(&mut first)?;
+    row[0] = first;
+    let encoded = (8..0x8000).contains(&width)
+        && first[0] == 2
+        && first[1] == 2
+        && usize::from(first[2]) << 8 | usize::from(first[3]) == width;
+    if !encoded {
+        for pixel in &mut row[1..] {
+            input.read_exact(pixel)?;
+        }
+        return Ok(());
+    }
+    let corrupt = || io::Error::new(io::ErrorKind::InvalidData, "corrupt Radiance HDR run");
+    let mut byte = [0u8; 1];
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            input.read_exact(&mut byte)?;
+            let count = byte[0] as usize;
+            if count > 128 {
+                let count = count - 128;
+                if x + count > width {
+                    return Err(corrupt());
+                }
+                input.read_exact(&mut byte)?;
+                for pixel in &mut row[x..x + count] {
+                    pixel[channel] = byte[0];
+                }
+                x += count;
+            } else {
+                if count == 0 || x + count > width {
+                    return Err(corrupt());
+                }
+                for pixel in &mut row[x..x + count] {
+                    input.read_exact(&mut byte)?;
+                    pixel[channel] = byte[0];
+                }
+                x += count;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A color as three mantissas sharing the exponent of the brightest channel.
+fn to_rgbe(pixel: Color) -> [u8; 4] {
+    let (r, g, b) = (pixel.0.max(0.0), pixel.1.max(0.0), pixel.2.max(0.0));
+    let brightest = r.max(g).max(b);
+    if brightest < 1e-32 || !brightest.is_finite() {
+        return [0, 0, 0, 0];
+    }
+    // brightest = m · 2^exponent with m in [0.5, 1).
+    let exponent = brightest.log2().floor() as i32 + 1;
+    let scale = 256.0 / 2f64.powi(exponent);
+    let byte = |c: f64| (c * scale).min(255.0) as u8;
+    [
+        byte(r),
+        byte(g),
+        byte(b),
+        (exponent + 128).clamp(0, 255) as u8,
+    ]
+}
+
+fn from_rgbe([r, g, b, e]: [u8; 4]) -> Color {
+    if e == 0 {
+        return color(0.0, 0.0, 0.0);
+    }
+    let scale = 2f64.powi(e as i32 - 128 - 8);
+    color(
+        (r as f64 + 0.5) * scale,
+        (g as f64 + 0.5) * scale,
+        (b as f64 + 0.5) * scale,
+    )
+}
+
+/// The comments in a Radiance HDR header, which is where `write_hdr` keeps
+/// metadata.
+pub(crate) fn read_hdr_comments(mut file: impl BufRead) -> io::Result<String> {
+    let mut line = String::new();
+    file.read_line(&mut line)?;
+    if !line.starts_with("#?") {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a Radiance HDR image",
+        ));
+    }
+    let mut metadata = String::new();
+    loop {
+        line.clear();
+        if file.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+            return Ok(metadata);
+        }
+        if let Some(comment) = line.trim_end().strip_prefix('#') {
+            metadata += comment.strip_prefix(' ').unwrap_or(comment);
+            metadata.push('\n');
+        }
+    }
+}
@@ -0,0 +1,377 @@
+use std::{
+    ffi::OsString,
+    fs,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{color, core::hdr::read_hdr_comments, Color};
+
+/// A rendered image in linear color, before gamma correction.
+#[derive(Clone)]
+pub struct ImageBuffer {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Color>,
+    /// How much of each pixel the image covers, row by row, if it isn't
+    /// opaque throughout: 0 where nothing is drawn. The pixels are
+    /// premultiplied by it.
+    pub alpha: Option<Vec<f64>>,
+}
+
+impl ImageBuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![color(0., 0., 0.); width * height],
+            alpha: None,
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+    pub fn set(&mut self, x: usize, y: usize, pixel: Color) {
+        self.pixels[y * self.width + x] = pixel;
+    }
+
+    /// The alpha of pixel (x, y): 1 in an opaque image.
+    pub fn get_alpha(&self, x: usize, y: usize) -> f64 {
+        self.alpha
+            .as_ref()
+            .map_or(1.0, |alpha| alpha[y * self.width + x])
+    }
+    /// Sets the alpha of pixel (x, y), making the rest of an opaque image
+    /// opaque in its alpha channel.
+    pub fn set_alpha(&mut self, x: usize, y: usize, alpha: f64) {
+        let len = self.pixels.len();
+        self.alpha.get_or_insert_with(|| vec![1.0; len])[y * self.width + x] = alpha;
+    }
+
+    /// The image composited over `plate`, which is stretched to its size:
+    /// each pixel is its own premultiplied color plus as much of the plate's
+    /// as its alpha leaves uncovered. Opaque images come back unchanged.
+    pub fn over(&self, plate: &ImageBuffer) -> ImageBuffer {
+        let Some(alpha) = &self.alpha else {
+            return self.clone();
+        };
+        let mut image = ImageBuffer::new(self.width, self.height);
+        if plate.width == 0 || plate.height == 0 {
+            image.pixels.clone_from(&self.pixels);
+            return image;
+        }
+        for y in 0..self.height {
+            // The plate's pixel under the middle of this one.
+            let plate_y = (y * plate.height + plate.height / 2) / self.height;
+            for x in 0..self.width {
+                let plate_x = (x * plate.width + plate.width / 2) / self.width;
+                let i = y * self.width + x;
+                image.pixels[i] =
+                    self.pixels[i] + plate.get(plate_x, plate_y) * (1.0 - alpha[i].clamp(0.0, 1.0));
+            }
+        }
+        image
+    }
+
+    /// The gamma-corrected pixels as 8-bit RGBA, row by row from the top.
+    /// The color is divided back out of the alpha, as PNG and a canvas's
+    /// `ImageData` store it.
+    fn rgba_bytes(&self) -> impl Iterator<Item = [u8; 4]> + '_ {
+        self.pixels.iter().enumerate().map(|(i, pixel)| {
+            let alpha = self
+                .alpha
+                .as_ref()
+                .map_or(1.0, |alpha| alpha[i].clamp(0.0, 1.0));
+            let straight = if alpha > 0.0 { *pixel / alpha } else { *pixel };
+            let [r, g, b] = straight.to_gamma().to_bytes();
+            [r, g, b, (alpha * 255.0).round() as u8]
+        })
+    }
+
+    pub fn write_ppm(&self, out: impl Write) -> io::Result<()> {
+        self.write_ppm_with_metadata(out, "")
+    }
+
+    /// `write_ppm`, with each line of `metadata` as a comment after the
+    /// magic number.
+    pub fn write_ppm_with_metadata(&self, out: impl Write, metadata: &str) -> io::Result<()> {
+        let mut out = BufWriter::new(out);
+        writeln!(out, "P3")?;
+        for line in metadata.lines() {
+            writeln!(out, "# {}", line)?;
+        }
+        writeln!(out, "{} {}\n255", self.width, self.height)?;
+        for pixel in &self.pixels {
+            pixel.to_gamma().write_color(&mut out)?;
+        }
+        out.flush()
+    }
+
+    /// Writes an 8-bit PNG, naming this crate as its software and keeping
+    /// `metadata`, if any, in a compressed text chunk. An image with an
+    /// alpha channel is written as RGBA, its color divided back out of the
+    /// alpha as PNG stores it.
+    #[cfg(feature = "image")]
+    pub fn write_png(&self, out: impl Write, metadata: &str) -> io::Result<()> {
+        let mut encoder =
+            png::Encoder::new(BufWriter::new(out), self.width as u32, self.height as u32);
+        encoder.set_color(match self.alpha {
+            Some(_) => png::ColorType::Rgba,
+            None => png::ColorType::Rgb,
+        });
+        encoder.set_depth(png::BitDepth::Eight);
+        let text_error = |e: png::EncodingError| io::Error::new(io::ErrorKind::InvalidInput, e);
+        encoder
+            .add_text_chunk("Software".to_string(), software())
+            .map_err(text_error)?;
+        if !metadata.is_empty() {
+            // zTXt only holds Latin-1; anything else goes in an iTXt chunk.
+            let keyword = METADATA_KEYWORD.to_string();
+            if metadata.chars().all(|c| (c as u32) < 0x100) {
+                encoder.add_ztxt_chunk(keyword, metadata.to_string())
+            } else {
+                encoder.add_itxt_chunk(keyword, metadata.to_string())
+            }
+            .map_err(text_error)?;
+        }
+        let data: Vec<u8> = match self.alpha {
+            Some(_) => self.rgba_bytes().flatten().collect(),
+            None => self
+                .pixels
+                .iter()
+                .flat_map(|pixel| pixel.to_gamma().to_bytes())
+                .collect(),
+        };
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer.write_image_data(&data).map_err(io::Error::other)?;
+        writer.finish().map_err(io::Error::other)
+    }
+
+    /// The gamma-corrected pixels as 8-bit RGBA, row by row from the top,
+    /// the layout of a canvas's `ImageData`. Opaque images are fully opaque.
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        self.rgba_bytes().flatten().collect()
+    }
+
+    /// Reads a PPM or, with the `image` feature, a PNG, undoing the gamma
+    /// correction so that saving the image again writes the same bytes. A
+    /// Radiance HDR image is read as the linear color it holds.
+    pub fn load(path: &Path) -> io::Result<ImageBuffer> {
+        let (width, height, bytes) = match path.extension().and_then(|e| e.to_str()) {
+            Some("ppm") => read_ppm(&fs::read_to_string(path)?)?,
+            Some("hdr") => return Self::read_hdr(BufReader::new(fs::File::open(path)?)),
+            #[cfg(feature = "image")]
+            Some("png") => {
+                let image = image::open(path)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+                    .into_rgb8();
+                let (width, height) = image.dimensions();
+                (width as usize, height as usize, image.into_raw())
+            }
+            #[cfg(not(feature = "image"))]
+            Some("png") => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "this build can't read PNGs; enable the `image` feature",
+                ))
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("unsupported image format: {}", path.display()),
+                ))
+            }
+        };
+        // The middle of each byte's range, which `to_bytes` maps back to it.
+        let linear = |byte: u8| ((byte as f64 + 0.5) / 256.0).powi(2);
+        Ok(ImageBuffer {
+            width,
+            height,
+            pixels: bytes
+                .chunks_exact(3)
+                .map(|p| color(linear(p[0]), linear(p[1]), linear(p[2])))
+                .collect(),
+            alpha: None,
+        })
+    }
+
+    /// Writes the image to `path`, choosing the format from the extension
+    /// and creating any directories missing on the way to it.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        self.save_with_metadata(path, "")
+    }
+
+    /// `save`, but written to a temporary file beside `path` and renamed
+    /// over it, so that a viewer watching `path` never reads half an image.
+    pub fn save_atomically(&self, path: &Path) -> io::Result<()> {
+        let name = path.file_name().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("not a file path: {}", path.display()),
+            )
+        })?;
+        // Keep the extension, which picks the format.
+        let mut temporary = OsString::from(".partial-");
+        temporary.push(name);
+        let temporary = path.with_file_name(temporary);
+        self.save(&temporary)
+            .and_then(|()| fs::rename(&temporary, path))
+            .inspect_err(|_| {
+                let _ = fs::remove_file(&temporary);
+            })
+    }
+
+    /// `save`, keeping `metadata` in the file: in a text chunk of a PNG or
+    /// in comments in a PPM or Radiance HDR image. See `read_image_metadata`.
+    pub fn save_with_metadata(&self, path: &Path, metadata: &str) -> io::Result<()> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("ppm") => self.write_ppm_with_metadata(create_file(path)?, metadata),
+            Some("hdr") => self.write_hdr(create_file(path)?, metadata),
+            #[cfg(feature = "image")]
+            Some("png") => self.write_png(create_file(path)?, metadata),
+            #[cfg(not(feature = "image"))]
+            Some("png") => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "this build can't write PNGs; enable the `image` feature",
+            )),
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("unsupported image format: {}", path.display()),
+            )),
+        }
+    }
+}
+
+/// Creates the file at `path`, and the directories it goes in if missing.
+fn create_file(path: &Path) -> io::Result<fs::File> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    fs::File::create(path)
+}
+
+/// The path of an image written alongside the one at `path`, such as an
+/// AOV pass or a matte: OUTPUT.SUFFIX.EXT, in the same directory and format.
+pub fn sidecar_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_stem().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    if let Some(extension) = path.extension() {
+        name.push(".");
+        name.push(extension);
+    }
+    path.with_file_name(name)
+}
+
+/// The keyword of the PNG text chunk holding the metadata.
+#[cfg(feature = "image")]
+const METADATA_KEYWORD: &str = "ray-tracer";
+
+#[cfg(feature = "image")]
+fn software() -> String {
+    format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+}
+
+/// The metadata an image was saved with by `save_with_metadata`: the text
+/// chunk of a PNG, or the header comments of a PPM or Radiance HDR image.
+/// Empty if it has none.
+pub fn read_image_metadata(path: &Path) -> io::Result<String> {
+    let file = BufReader::new(fs::File::open(path)?);
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("ppm") => read_ppm_comments(file),
+        Some("hdr") => read_hdr_comments(file),
+        #[cfg(feature = "image")]
+        Some("png") => read_png_text(file),
+        #[cfg(not(feature = "image"))]
+        Some("png") => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "this build can't read PNGs; enable the `image` feature",
+        )),
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("unsupported image format: {}", path.display()),
+        )),
+    }
+}
+
+/// The size and 8-bit RGB values of a plain (P3) PPM.
+fn read_ppm(text: &str) -> io::Result<(usize, usize, Vec<u8>)> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let mut values = text.lines().flat_map(|line| {
+        line.split('#')
+            .next()
+            .unwrap_or("")
+            .split_ascii_whitespace()
+    });
+    if values.next() != Some("P3") {
+        return Err(invalid("not a plain (P3) PPM image"));
+    }
+    let mut next = || -> io::Result<usize> {
+        values
+            .next()
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| invalid("truncated or malformed PPM image"))
+    };
+    let (width, height, max) = (next()?, next()?, next()?);
+    if max == 0 || max > 255 {
+        return Err(invalid("only 8-bit PPM images are supported"));
+    }
+    let bytes = (0..width * height * 3)
+        .map(|_| Ok((next()?.min(max) * 255 / max) as u8))
+        .collect::<io::Result<_>>()?;
+    Ok((width, height, bytes))
+}
+
+/// The comment lines between the magic number and the size.
+fn read_ppm_comments(file: impl BufRead) -> io::Result<String> {
+    let mut lines = file.lines();
+    let magic = lines.next().transpose()?;
+    if !matches!(magic.as_deref(), Some("P3" | "P6")) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a PPM image",
+        ));
+    }
+    let mut metadata = String::new();
+    for line in lines {
+        let line = line?;
+        let Some(comment) = line.strip_prefix('#') else {
+            break;
+        };
+        metadata += comment.strip_prefix(' ').unwrap_or(comment);
+        metadata.push('\n');
+    }
+    Ok(metadata)
+}
+
+#[cfg(feature = "image")]
+fn read_png_text(file: impl BufRead + io::Seek) -> io::Result<String> {
+    let reader = png::Decoder::new(file)
+        .read_info()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let info = reader.info();
+    let text = |result: Result<String, png::DecodingError>| {
+        result.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    };
+    if let Some(chunk) = info
+        .compressed_latin1_text
+        .iter()
+        .find(|chunk| chunk.keyword == METADATA_KEYWORD)
+    {
+        return text(chunk.get_text());
+    }
+    if let Some(chunk) = info
+        .utf8_text
+        .iter()
+        .find(|chunk| chunk.keyword == METADATA_KEYWORD)
+    {
+        return text(chunk.get_text());
+    }
+    Ok(info
+        .uncompressed_latin1_text
+        .iter()
+        .find(|chunk| chunk.keyword == METADATA_KEYWORD)
+        .map(|chunk| chunk.text.clone())
+        .unwrap_or_default())
+}
@@ -15,6 +15,13 @@ impl Add<f64> for Interval {
     }
 }
 
+/// The empty interval.
+impl Default for Interval {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
 impl Interval {
     pub fn empty() -> Self {
         Self {
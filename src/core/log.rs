@@ -0,0 +1,161 @@
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+    str::FromStr,
+    sync::atomic::{AtomicU8, Ordering},
+    time::Duration,
+};
+
+/// The clock for timing stages and loads. `std::time::Instant` panics in the
+/// browser, so wasm builds read `performance.now()` through `web_time`.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub(crate) use std::time::Instant;
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub(crate) use web_time::Instant;
+
+/// How much the renderer reports on stderr. Nothing is ever logged to
+/// stdout, which may be carrying the image.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Only errors.
+    Quiet,
+    /// Warnings, loaded assets, and the summary after a render.
+    #[default]
+    Normal,
+    /// Also each stage's time as it finishes, and load progress.
+    Verbose,
+}
+
+impl fmt::Display for Verbosity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Verbosity::Quiet => write!(f, "quiet"),
+            Verbosity::Normal => write!(f, "normal"),
+            Verbosity::Verbose => write!(f, "verbose"),
+        }
+    }
+}
+
+impl FromStr for Verbosity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "quiet" => Ok(Verbosity::Quiet),
+            "normal" => Ok(Verbosity::Normal),
+            "verbose" => Ok(Verbosity::Verbose),
+            _ => Err(format!(
+                "unknown verbosity '{}' (expected quiet, normal, or verbose)",
+                s
+            )),
+        }
+    }
+}
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+pub fn set_verbosity(verbosity: Verbosity) {
+    VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+}
+
+pub fn verbosity() -> Verbosity {
+    match VERBOSITY.load(Ordering::Relaxed) {
+        0 => Verbosity::Quiet,
+        1 => Verbosity::Normal,
+        _ => Verbosity::Verbose,
+    }
+}
+
+/// Writes `message` to stderr if the verbosity is at least `level`.
+pub fn log(level: Verbosity, message: impl fmt::Display) {
+    if verbosity() >= level {
+        eprintln!("{}", message);
+    }
+}
+
+/* === Stages === */
+
+/// The total time spent in one named stage, e.g. every BVH build of a scene.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StageTime {
+    pub name: &'static str,
+    /// How many stages this one is nested in, e.g. 1 for a BVH built while
+    /// a scene is being constructed.
+    pub depth: usize,
+    /// How many times the stage ran.
+    pub count: usize,
+    pub elapsed: Duration,
+}
+
+thread_local! {
+    static STAGES: RefCell<Vec<StageTime>> = const { RefCell::new(Vec::new()) };
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Times a stage of the work from `Stage::start` until it is dropped, adding
+/// the time to this thread's `stage_times` and logging it when verbose.
+#[must_use = "the stage ends when it is dropped"]
+pub struct Stage {
+    name: &'static str,
+    depth: usize,
+    started: Instant,
+}
+
+impl Stage {
+    pub fn start(name: &'static str) -> Self {
+        let depth = DEPTH.replace(DEPTH.get() + 1);
+        STAGES.with(|stages| {
+            let mut stages = stages.borrow_mut();
+            match stages.iter_mut().find(|stage| stage.name == name) {
+                Some(stage) => stage.depth = stage.depth.min(depth),
+                None => stages.push(StageTime {
+                    name,
+                    depth,
+                    count: 0,
+                    elapsed: Duration::ZERO,
+                }),
+            }
+        });
+        Self {
+            name,
+            depth,
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Drop for Stage {
+    fn drop(&mut self) {
+        let elapsed = self.started.elapsed();
+        DEPTH.set(self.depth);
+        STAGES.with(|stages| {
+            let mut stages = stages.borrow_mut();
+            // A `take_stage_times` while the stage ran drops its entry.
+            if let Some(stage) = stages.iter_mut().find(|stage| stage.name == self.name) {
+                stage.count += 1;
+                stage.elapsed += elapsed;
+            }
+        });
+        log(
+            Verbosity::Verbose,
+            format_args!(
+                "{}{} took {:.1?}",
+                "  ".repeat(self.depth),
+                self.name,
+                elapsed
+            ),
+        );
+    }
+}
+
+/// The stages this thread has run since the last `take_stage_times`, in the
+/// order each first started, so stages come before the ones nested in them.
+/// A stage still running has a count of 0.
+pub fn stage_times() -> Vec<StageTime> {
+    STAGES.with(|stages| stages.borrow().clone())
+}
+
+/// Like `stage_times`, but also starts over, so the next call only sees later stages.
+pub fn take_stage_times() -> Vec<StageTime> {
+    STAGES.with(|stages| stages.take())
+}
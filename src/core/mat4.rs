@@ -0,0 +1,268 @@
+use crate::{Point, Quat, Transform, Vec3};
+
+use std::ops::Mul;
+
+/// A 4×4 affine or projective matrix, stored by rows and applied to column
+/// vectors: `a * b` applies `b` first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat4 {
+    pub m: [[f64; 4]; 4],
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Mat4 {
+    pub fn new(m: [[f64; 4]; 4]) -> Self {
+        Self { m }
+    }
+    pub fn identity() -> Self {
+        Self::scale(Vec3(1., 1., 1.))
+    }
+    pub fn translation(offset: Vec3) -> Self {
+        let mut matrix = Self::identity();
+        matrix.m[0][3] = offset.0;
+        matrix.m[1][3] = offset.1;
+        matrix.m[2][3] = offset.2;
+        matrix
+    }
+    pub fn scale(factors: Vec3) -> Self {
+        Self::new([
+            [factors.0, 0., 0., 0.],
+            [0., factors.1, 0., 0.],
+            [0., 0., factors.2, 0.],
+            [0., 0., 0., 1.],
+        ])
+    }
+    /// A rotation of `angle` degrees about the X axis.
+    pub fn rotation_x(angle: f64) -> Self {
+        Self::from(Quat::from_axis_angle(Vec3(1., 0., 0.), angle))
+    }
+    pub fn rotation_y(angle: f64) -> Self {
+        Self::from(Quat::from_axis_angle(Vec3(0., 1., 0.), angle))
+    }
+    pub fn rotation_z(angle: f64) -> Self {
+        Self::from(Quat::from_axis_angle(Vec3(0., 0., 1.), angle))
+    }
+    pub fn rotation(axis: Vec3, angle: f64) -> Self {
+        Self::from(Quat::from_axis_angle(axis, angle))
+    }
+    /// The camera-to-world matrix of a camera at `from` looking at `at`: the
+    /// camera looks down its -Z axis with +Y toward `up`, as in `Camera`.
+    pub fn look_at(from: Point, at: Point, up: Vec3) -> Self {
+        let w = (from - at).unit();
+        let u = Vec3::cross(&up, &w).unit();
+        let v = Vec3::cross(&w, &u);
+        Self::new([
+            [u.0, v.0, w.0, from.0],
+            [u.1, v.1, w.1, from.1],
+            [u.2, v.2, w.2, from.2],
+            [0., 0., 0., 1.],
+        ])
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut t = [[0.; 4]; 4];
+        for (i, row) in t.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.m[j][i];
+            }
+        }
+        Self::new(t)
+    }
+
+    pub fn determinant(&self) -> f64 {
+        // Laplace expansion along the first row.
+        (0..4)
+            .map(|j| {
+                let sign = if j % 2 == 0 { 1.0 } else { -1.0 };
+                sign * self.m[0][j] * self.minor(0, j)
+            })
+            .sum()
+    }
+    /// The determinant of the 3×3 matrix left without `row` and `column`.
+    fn minor(&self, row: usize, column: usize) -> f64 {
+        let mut rest = [[0.; 3]; 3];
+        let rows = (0..4).filter(|&i| i != row);
+        for (r, i) in rows.enumerate() {
+            let columns = (0..4).filter(|&j| j != column);
+            for (c, j) in columns.enumerate() {
+                rest[r][c] = self.m[i][j];
+            }
+        }
+        rest[0][0] * (rest[1][1] * rest[2][2] - rest[1][2] * rest[2][1])
+            - rest[0][1] * (rest[1][0] * rest[2][2] - rest[1][2] * rest[2][0])
+            + rest[0][2] * (rest[1][0] * rest[2][1] - rest[1][1] * rest[2][0])
+    }
+
+    /// The inverse by Gauss-Jordan elimination with partial pivoting, or
+    /// `None` if the matrix is singular.
+    pub fn inverse(&self) -> Option<Self> {
+        let mut a = self.m;
+        let mut inverse = Self::identity().m;
+        // Pivots this small relative to the largest entry mean the matrix is
+        // singular up to rounding.
+        let scale = a.iter().flatten().fold(0.0f64, |max, v| max.max(v.abs()));
+        if scale == 0.0 || !scale.is_finite() {
+            return None;
+        }
+        for column in 0..4 {
+            let pivot = (column..4)
+                .max_by(|&i, &j| a[i][column].abs().total_cmp(&a[j][column].abs()))
+                .unwrap();
+            if a[pivot][column].abs() <= 1e-12 * scale {
+                return None;
+            }
+            a.swap(column, pivot);
+            inverse.swap(column, pivot);
+            let divisor = a[column][column];
+            for j in 0..4 {
+                a[column][j] /= divisor;
+                inverse[column][j] /= divisor;
+            }
+            for i in (0..4).filter(|&i| i != column) {
+                let factor = a[i][column];
+                for j in 0..4 {
+                    a[i][j] -= factor * a[column][j];
+                    inverse[i][j] -= factor * inverse[column][j];
+                }
+            }
+        }
+        Some(Self::new(inverse))
+    }
+
+    pub fn transform_point(&self, p: Point) -> Point {
+        let m = &self.m;
+        let x = m[0][0] * p.0 + m[0][1] * p.1 + m[0][2] * p.2 + m[0][3];
+        let y = m[1][0] * p.0 + m[1][1] * p.1 + m[1][2] * p.2 + m[1][3];
+        let z = m[2][0] * p.0 + m[2][1] * p.1 + m[2][2] * p.2 + m[2][3];
+        let w = m[3][0] * p.0 + m[3][1] * p.1 + m[3][2] * p.2 + m[3][3];
+        if w == 1.0 {
+            Vec3(x, y, z)
+        } else {
+            Vec3(x, y, z) / w
+        }
+    }
+    /// Transforms a direction, ignoring the translation.
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3(
+            m[0][0] * v.0 + m[0][1] * v.1 + m[0][2] * v.2,
+            m[1][0] * v.0 + m[1][1] * v.1 + m[1][2] * v.2,
+            m[2][0] * v.0 + m[2][1] * v.1 + m[2][2] * v.2,
+        )
+    }
+    /// Transforms a surface normal by the inverse transpose, so it stays
+    /// perpendicular to transformed tangents. Returns a unit vector, or the
+    /// normal unchanged if the matrix is singular.
+    pub fn transform_normal(&self, n: Vec3) -> Vec3 {
+        match self.inverse() {
+            Some(inverse) => inverse.transpose().transform_vector(n).unit(),
+            None => n,
+        }
+    }
+
+    /// Splits an affine matrix into a translation, a rotation, and a scale
+    /// applied in the order scale, rotate, translate. Returns `None` for
+    /// singular or projective matrices; shear is lost.
+    pub fn decompose(&self) -> Option<(Vec3, Quat, Vec3)> {
+        let m = &self.m;
+        if m[3] != [0., 0., 0., 1.] {
+            return None;
+        }
+        let column = |j: usize| Vec3(m[0][j], m[1][j], m[2][j]);
+        let mut scale = Vec3(column(0).length(), column(1).length(), column(2).length());
+        if scale.0 == 0.0 || scale.1 == 0.0 || scale.2 == 0.0 {
+            return None;
+        }
+        // A mirror image can't be a rotation, so put the flip in the scale.
+        if self.determinant() < 0.0 {
+            scale.0 = -scale.0;
+        }
+        let [x, y, z] = [
+            column(0) / scale.0,
+            column(1) / scale.1,
+            column(2) / scale.2,
+        ];
+        Some((column(3), quat_from_basis(x, y, z).normalized(), scale))
+    }
+}
+
+/// The rotation taking the X, Y, and Z axes to the orthonormal `x`, `y`,
+/// and `z` (Shepperd's method).
+fn quat_from_basis(x: Vec3, y: Vec3, z: Vec3) -> Quat {
+    let trace = x.0 + y.1 + z.2;
+    if trace > 0.0 {
+        let s = 2.0 * (trace + 1.0).sqrt();
+        Quat {
+            w: s / 4.0,
+            x: (y.2 - z.1) / s,
+            y: (z.0 - x.2) / s,
+            z: (x.1 - y.0) / s,
+        }
+    } else if x.0 > y.1 && x.0 > z.2 {
+        let s = 2.0 * (1.0 + x.0 - y.1 - z.2).sqrt();
+        Quat {
+            w: (y.2 - z.1) / s,
+            x: s / 4.0,
+            y: (y.0 + x.1) / s,
+            z: (z.0 + x.2) / s,
+        }
+    } else if y.1 > z.2 {
+        let s = 2.0 * (1.0 + y.1 - x.0 - z.2).sqrt();
+        Quat {
+            w: (z.0 - x.2) / s,
+            x: (y.0 + x.1) / s,
+            y: s / 4.0,
+            z: (z.1 + y.2) / s,
+        }
+    } else {
+        let s = 2.0 * (1.0 + z.2 - x.0 - y.1).sqrt();
+        Quat {
+            w: (x.1 - y.0) / s,
+            x: (z.0 + x.2) / s,
+            y: (z.1 + y.2) / s,
+            z: s / 4.0,
+        }
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+    fn mul(self, b: Mat4) -> Mat4 {
+        let mut product = [[0.; 4]; 4];
+        for (i, row) in product.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * b.m[k][j]).sum();
+            }
+        }
+        Mat4::new(product)
+    }
+}
+
+impl From<Quat> for Mat4 {
+    fn from(q: Quat) -> Self {
+        let [x, y, z] = [
+            q.rotate(Vec3(1., 0., 0.)),
+            q.rotate(Vec3(0., 1., 0.)),
+            q.rotate(Vec3(0., 0., 1.)),
+        ];
+        Self::new([
+            [x.0, y.0, z.0, 0.],
+            [x.1, y.1, z.1, 0.],
+            [x.2, y.2, z.2, 0.],
+            [0., 0., 0., 1.],
+        ])
+    }
+}
+
+impl From<Transform> for Mat4 {
+    fn from(transform: Transform) -> Self {
+        Self::translation(transform.translation)
+            * Self::rotation_y(transform.rotation_y)
+            * Self::scale(transform.scale)
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{Sampler, Vec3};
+
+/// A distribution of directions to sample light from, and its density, so
+/// that a sample can be weighted by how likely it was.
+pub trait Pdf {
+    /// The probability density of `generate` picking unit `direction`, per
+    /// steradian.
+    fn value(&self, direction: Vec3) -> f64;
+
+    /// A unit direction drawn from the distribution.
+    fn generate(&self, rng: &mut dyn Sampler) -> Vec3;
+}
+
+/// The power heuristic for multiple importance sampling: the weight of a
+/// sample drawn with density `chosen` when `other` could also have drawn it.
+pub fn power_heuristic(chosen: f64, other: f64) -> f64 {
+    let (chosen, other) = (chosen * chosen, other * other);
+    if chosen == 0.0 {
+        0.0
+    } else {
+        chosen / (chosen + other)
+    }
+}
+
+/// A piecewise-constant distribution over [0, 1), one piece per weight.
+#[derive(Clone, Debug)]
+pub struct Distribution1D {
+    weights: Vec<f64>,
+    /// `cdf[i]` is the probability of landing before piece `i`; the last
+    /// entry is 1.
+    cdf: Vec<f64>,
+    total: f64,
+}
+
+impl Distribution1D {
+    /// A distribution proportional to `weights`, which must not be negative.
+    /// All zeros gives a distribution that `sample` can't draw from.
+    pub fn new(weights: Vec<f64>) -> Self {
+        let mut cdf = Vec::with_capacity(weights.len() + 1);
+        let mut total = 0.0;
+        cdf.push(0.0);
+        for &weight in &weights {
+            total += weight;
+            cdf.push(total);
+        }
+        if total > 0.0 {
+            for value in &mut cdf {
+                *value /= total;
+            }
+        }
+        Self {
+            weights,
+            cdf,
+            total,
+        }
+    }
+
+    /// The sum of the weights.
+    pub fn total(&self) -> f64 {
+        self.total
+    }
+
+    pub fn len(&self) -> usize {
+        self.weights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.weights.is_empty()
+    }
+
+    /// The piece `xi` in [0, 1) falls in, and where in it, from 0 to 1, so
+    /// the one number serves for both. `None` if every weight is zero.
+    pub fn sample(&self, xi: f64) -> Option<(usize, f64)> {
+        if self.total <= 0.0 {
+            return None;
+        }
+        // The last piece with cdf[i] <= xi, skipping pieces of no weight.
+        let i = self
+            .cdf
+            .partition_point(|&c| c <= xi)
+            .clamp(1, self.weights.len())
+            - 1;
+        let width = self.cdf[i + 1] - self.cdf[i];
+        let within = if width > 0.0 {
+            ((xi - self.cdf[i]) / width).clamp(0.0, 1.0 - f64::EPSILON)
+        } else {
+            0.5
+        };
+        Some((i, within))
+    }
+
+    /// The density over [0, 1) of piece `i`.
+    pub fn density(&self, i: usize) -> f64 {
+        if self.total <= 0.0 {
+            return 0.0;
+        }
+        self.weights[i] * self.weights.len() as f64 / self.total
+    }
+}
+
+/// A piecewise-constant distribution over [0, 1)², drawn as a row from the
+/// rows' totals and then a column from that row's weights.
+#[derive(Clone, Debug)]
+pub struct Distribution2D {
+    rows: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    /// A distribution proportional to `weights`, `width` to a row, row by row.
+    pub fn new(weights: &[f64], width: usize) -> Self {
+        let rows: Vec<_> = weights
+            .chunks(width.max(1))
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect();
+        let marginal = Distribution1D::new(rows.iter().map(Distribution1D::total).collect());
+        Self { rows, marginal }
+    }
+
+    /// A point (u, v), u along the rows and v down them, drawn with two
+    /// numbers in [0, 1), and its density. `None` if every weight is zero.
+    pub fn sample(&self, (xi_u, xi_v): (f64, f64)) -> Option<((f64, f64), f64)> {
+        let (row, v) = self.marginal.sample(xi_v)?;
+        let (column, u) = self.rows[row].sample(xi_u)?;
+        let width = self.rows[row].len() as f64;
+        let point = (
+            (column as f64 + u) / width,
+            (row as f64 + v) / self.rows.len() as f64,
+        );
+        Some((point, self.density(point)))
+    }
+
+    /// The density at (u, v) in [0, 1)².
+    pub fn density(&self, (u, v): (f64, f64)) -> f64 {
+        let height = self.rows.len();
+        if height == 0 {
+            return 0.0;
+        }
+        let row = ((v * height as f64) as usize).min(height - 1);
+        let width = self.rows[row].len();
+        if width == 0 {
+            return 0.0;
+        }
+        let column = ((u * width as f64) as usize).min(width - 1);
+        self.marginal.density(row) * self.rows[row].density(column)
+    }
+}
@@ -0,0 +1,53 @@
+use std::f64::consts::PI;
+
+use rand::Rng;
+
+use crate::Vec3;
+
+/// A regular polygon inscribed in the unit circle of the xy plane, with its
+/// first vertex `rotation` degrees counterclockwise from +x.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RegularPolygon {
+    pub sides: u32,
+    pub rotation: f64,
+}
+
+impl RegularPolygon {
+    pub fn new(sides: u32, rotation: f64) -> Self {
+        Self { sides, rotation }
+    }
+
+    pub fn vertex(&self, i: u32) -> Vec3 {
+        let angle =
+            self.rotation.to_radians() + 2.0 * PI * (i % self.sides) as f64 / self.sides as f64;
+        Vec3(angle.cos(), angle.sin(), 0.0)
+    }
+
+    pub fn area(&self) -> f64 {
+        let n = self.sides as f64;
+        0.5 * n * (2.0 * PI / n).sin()
+    }
+
+    /// Whether `p` lies inside the polygon or on its edge.
+    pub fn contains(&self, p: Vec3) -> bool {
+        (0..self.sides).all(|i| {
+            let (a, b) = (self.vertex(i), self.vertex(i + 1));
+            (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0) >= -1e-12
+        })
+    }
+
+    /// Maps `(u, v)` in [0, 1)² to a uniformly distributed point of the
+    /// polygon. `u` picks one of the equal triangles fanning out from the
+    /// center and how far out in it to go; `v` picks where across.
+    pub fn sample(&self, u: f64, v: f64) -> Vec3 {
+        let scaled = u * self.sides as f64;
+        let i = (scaled as u32).min(self.sides - 1);
+        let distance = (scaled - i as f64).sqrt();
+        (self.vertex(i) * (1.0 - v) + self.vertex(i + 1) * v) * distance
+    }
+
+    /// A random point of the polygon. Always draws exactly two numbers.
+    pub fn random(&self, rng: &mut (impl Rng + ?Sized)) -> Vec3 {
+        self.sample(rng.gen(), rng.gen())
+    }
+}
@@ -0,0 +1,142 @@
+use crate::Vec3;
+
+use std::ops::Mul;
+
+/// A rotation stored as a unit quaternion `w + xi + yj + zk`. Angles are in
+/// degrees, like the rest of the crate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quat {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Default for Quat {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Quat {
+    pub fn identity() -> Self {
+        Self {
+            w: 1.,
+            x: 0.,
+            y: 0.,
+            z: 0.,
+        }
+    }
+    /// A rotation of `angle` degrees counterclockwise about `axis`, looking
+    /// down the axis toward the origin. `axis` need not be a unit vector.
+    pub fn from_axis_angle(axis: Vec3, angle: f64) -> Self {
+        let axis = axis.unit();
+        let (sin, cos) = (angle.to_radians() / 2.0).sin_cos();
+        Self {
+            w: cos,
+            x: axis.0 * sin,
+            y: axis.1 * sin,
+            z: axis.2 * sin,
+        }
+    }
+    /// Rotates `x` degrees about the X axis, then `y` about Y, then `z` about Z.
+    pub fn from_euler(x: f64, y: f64, z: f64) -> Self {
+        Self::from_axis_angle(Vec3(0., 0., 1.), z)
+            * Self::from_axis_angle(Vec3(0., 1., 0.), y)
+            * Self::from_axis_angle(Vec3(1., 0., 0.), x)
+    }
+
+    pub fn length(&self) -> f64 {
+        Self::dot(self, self).sqrt()
+    }
+    pub fn normalized(&self) -> Self {
+        let length = self.length();
+        Self {
+            w: self.w / length,
+            x: self.x / length,
+            y: self.y / length,
+            z: self.z / length,
+        }
+    }
+    pub fn is_finite(&self) -> bool {
+        self.w.is_finite() && self.x.is_finite() && self.y.is_finite() && self.z.is_finite()
+    }
+    pub fn dot(a: &Quat, b: &Quat) -> f64 {
+        a.w * b.w + a.x * b.x + a.y * b.y + a.z * b.z
+    }
+    /// The opposite rotation, for unit quaternions.
+    pub fn inverse(&self) -> Self {
+        Self {
+            w: self.w,
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+
+    /// The axis and the angle in degrees of the rotation, turning about +Y
+    /// for the identity.
+    pub fn to_axis_angle(&self) -> (Vec3, f64) {
+        let q = self.normalized();
+        let sin = Vec3(q.x, q.y, q.z).length();
+        if sin < 1e-12 {
+            return (Vec3(0., 1., 0.), 0.0);
+        }
+        let angle = 2.0 * sin.atan2(q.w);
+        (Vec3(q.x, q.y, q.z) / sin, angle.to_degrees())
+    }
+
+    pub fn rotate(&self, v: Vec3) -> Vec3 {
+        // v + 2w(u × v) + 2u × (u × v), with u the vector part.
+        let u = Vec3(self.x, self.y, self.z);
+        let t = Vec3::cross(&u, &v) * 2.0;
+        v + t * self.w + Vec3::cross(&u, &t)
+    }
+
+    /// Interpolates along the shortest arc from `self` at `t = 0` to `other`
+    /// at `t = 1` at a constant angular speed.
+    pub fn slerp(&self, other: &Quat, t: f64) -> Self {
+        let mut cos = Self::dot(self, other);
+        // q and -q are the same rotation; take the one that is closer.
+        let other = if cos < 0.0 {
+            cos = -cos;
+            Self {
+                w: -other.w,
+                x: -other.x,
+                y: -other.y,
+                z: -other.z,
+            }
+        } else {
+            *other
+        };
+        let (a, b) = if cos > 1.0 - 1e-9 {
+            // Nearly parallel: the arc is a straight line.
+            (1.0 - t, t)
+        } else {
+            let theta = cos.acos();
+            let sin = theta.sin();
+            (((1.0 - t) * theta).sin() / sin, (t * theta).sin() / sin)
+        };
+        Self {
+            w: a * self.w + b * other.w,
+            x: a * self.x + b * other.x,
+            y: a * self.y + b * other.y,
+            z: a * self.z + b * other.z,
+        }
+        .normalized()
+    }
+}
+
+/// Composes rotations: `a * b` rotates by `b`, then by `a`.
+impl Mul for Quat {
+    type Output = Quat;
+    fn mul(self, b: Quat) -> Quat {
+        let a = self;
+        Quat {
+            w: a.w * b.w - a.x * b.x - a.y * b.y - a.z * b.z,
+            x: a.w * b.x + a.x * b.w + a.y * b.z - a.z * b.y,
+            y: a.w * b.y - a.x * b.z + a.y * b.w + a.z * b.x,
+            z: a.w * b.z + a.x * b.y - a.y * b.x + a.z * b.w,
+        }
+    }
+}
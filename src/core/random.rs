@@ -0,0 +1,29 @@
+pub use rand::rngs::SmallRng;
+
+use rand::SeedableRng;
+
+/// One round of SplitMix64: scrambles `z` so nearby inputs give unrelated outputs.
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Mixes a render seed with a pixel position into an independent seed
+/// (SplitMix64), so each pixel's samples don't depend on render order.
+pub fn pixel_seed(seed: u64, x: i32, y: i32) -> u64 {
+    let position = ((y as u32 as u64) << 32) | x as u32 as u64;
+    mix(seed ^ position.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+}
+
+/// The seed of one sample's stream within a pixel.
+pub fn sample_seed(seed: u64, x: i32, y: i32, sample: u32) -> u64 {
+    mix(pixel_seed(seed, x, y) ^ (sample as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+}
+
+/// The generator for one sample of one pixel. Everything the sample draws
+/// comes from it, so a pixel's color depends only on the render seed, not on
+/// which thread renders it or in what order.
+pub fn sample_rng(seed: u64, x: i32, y: i32, sample: u32) -> SmallRng {
+    SmallRng::seed_from_u64(sample_seed(seed, x, y, sample))
+}
@@ -1,9 +1,225 @@
-use crate::{hittable::*, vec3::*, Interval, Point, Vec3};
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    ops::AddAssign,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    hittable::*, power_heuristic, vec3::*, Background, BounceKind, DeltaLight, Environment,
+    Interval, LightList, Pdf, Point, Sampler, TraversalStats, Vec3,
+};
+
+/// The default for the closest hit a ray accepts. Spawned rays start off the
+/// surface (see `HitRecord::spawn_ray`), so this only needs to skip rounding noise.
+pub const DEFAULT_RAY_EPSILON: f64 = 1e-9;
+
+/// The bounces a path takes before Russian roulette may end it, unless
+/// told otherwise: enough for the light most of the image gets.
+pub const DEFAULT_ROULETTE_DEPTH: i32 = 3;
+
+thread_local! {
+    static RAYS_TRACED: Cell<u64> = const { Cell::new(0) };
+    static TRACE_STATS: RefCell<Option<TraceStats>> = const { RefCell::new(None) };
+}
+
+/// How many rays `Ray::send` has traced through a world on this thread,
+/// counting each bounce and shadow ray, and those a render's threads traced
+/// for it.
+pub fn rays_traced() -> u64 {
+    RAYS_TRACED.get()
+}
+
+/// Counts `rays` traced on another thread on behalf of this one.
+pub(crate) fn add_rays_traced(rays: u64) {
+    RAYS_TRACED.set(RAYS_TRACED.get() + rays);
+}
+
+fn count_ray() {
+    RAYS_TRACED.set(RAYS_TRACED.get() + 1);
+}
+
+/// What the paths traced on a thread did, from `count_trace_stats` on.
+/// Counting sends each ray down `Hittable::hit_with_stats` instead of
+/// `hit`, so it is only done when asked for, see
+/// `Camera::render_with_stats`; otherwise a path only checks once whether
+/// to.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TraceStats {
+    /// The first ray of each path, one per camera sample.
+    pub primary_rays: u64,
+    /// Every ray along the paths, the primary rays among them, but not
+    /// shadow rays.
+    pub path_rays: u64,
+    /// The BVH and TLAS nodes and grid cells the path rays were tested
+    /// against.
+    pub node_visits: u64,
+    /// The primitives the path rays were intersected with, by type.
+    pub hit_tests: BTreeMap<&'static str, u64>,
+}
+
+impl TraceStats {
+    /// The rays a path took on average: 1 for one that left the scene or
+    /// ended at the first surface it hit, and so on.
+    pub fn mean_path_depth(&self) -> f64 {
+        self.path_rays as f64 / self.primary_rays.max(1) as f64
+    }
+
+    /// The primitives intersected, of every type.
+    pub fn total_hit_tests(&self) -> u64 {
+        self.hit_tests.values().sum()
+    }
+}
+
+impl AddAssign for TraceStats {
+    fn add_assign(&mut self, other: Self) {
+        self.primary_rays += other.primary_rays;
+        self.path_rays += other.path_rays;
+        self.node_visits += other.node_visits;
+        for (kind, tests) in other.hit_tests {
+            *self.hit_tests.entry(kind).or_default() += tests;
+        }
+    }
+}
+
+/// Starts counting `TraceStats` on this thread, from zero. The threads a
+/// render runs on count for it too.
+pub fn count_trace_stats() {
+    TRACE_STATS.set(Some(TraceStats::default()));
+}
+
+/// The `TraceStats` counted since `count_trace_stats`, which stops
+/// counting, or `None` if this thread wasn't.
+pub fn take_trace_stats() -> Option<TraceStats> {
+    TRACE_STATS.take()
+}
+
+pub(crate) fn counting_trace_stats() -> bool {
+    TRACE_STATS.with_borrow(Option::is_some)
+}
+
+/// Counts `stats` counted on another thread on behalf of this one, if this
+/// one is counting.
+pub(crate) fn add_trace_stats(stats: TraceStats) {
+    TRACE_STATS.with_borrow_mut(|counted| {
+        if let Some(counted) = counted {
+            *counted += stats;
+        }
+    });
+}
+
+/// Counts a primitive of type `kind` intersected, if this thread is
+/// counting.
+pub(crate) fn count_hit_test(kind: &'static str) {
+    TRACE_STATS.with_borrow_mut(|counted| {
+        if let Some(counted) = counted {
+            *counted.hit_tests.entry(kind).or_default() += 1;
+        }
+    });
+}
+
+/// The most bounces of each kind a path may take. Kinds without a limit are
+/// only bounded by the max depth.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct BounceLimits {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diffuse: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glossy: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub specular: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transmission: Option<i32>,
+}
+
+impl BounceLimits {
+    pub fn is_default(&self) -> bool {
+        *self == BounceLimits::default()
+    }
+
+    pub fn limit(&self, kind: BounceKind) -> Option<i32> {
+        match kind {
+            BounceKind::Diffuse => self.diffuse,
+            BounceKind::Glossy => self.glossy,
+            BounceKind::Specular => self.specular,
+            BounceKind::Transmission => self.transmission,
+        }
+    }
+}
+
+/// How far a path may go: `max_depth` surfaces in all, and no more bounces of
+/// any kind than `bounces` allows. With `roulette_depth`, paths that have
+/// bounced that many times are ended at random by Russian roulette, the
+/// dimmer they have grown the likelier, and those that go on count for
+/// more to make up for the rest, so the image comes out the same on
+/// average in less time.
+#[derive(Clone, Copy, Debug)]
+pub struct PathDepth {
+    pub max_depth: i32,
+    pub bounces: BounceLimits,
+    pub roulette_depth: Option<i32>,
+}
+
+impl From<i32> for PathDepth {
+    fn from(max_depth: i32) -> Self {
+        Self {
+            max_depth,
+            bounces: BounceLimits::default(),
+            roulette_depth: None,
+        }
+    }
+}
 
 #[derive(Clone, Copy, Debug)]
 pub struct Ray {
     pub origin: Point,
     pub direction: Vec3,
+    /// When the ray was sent, for motion blur: 0 when the shutter opens, 1 when it closes.
+    pub time: f64,
+}
+
+/// What a ray from the camera brings back: the light along it, and its
+/// alpha, how much of it the scene covers rather than what lies behind.
+/// The light is premultiplied by the alpha, so averaging samples averages
+/// both alike.
+#[derive(Clone, Copy, Debug)]
+pub struct RaySample {
+    pub color: Color,
+    pub alpha: f64,
+}
+
+impl RaySample {
+    /// Nothing: black and fully transparent.
+    pub const TRANSPARENT: RaySample = RaySample {
+        color: Vec3(0.0, 0.0, 0.0),
+        alpha: 0.0,
+    };
+
+    /// `color`, covering the ray entirely.
+    pub fn opaque(color: Color) -> Self {
+        Self { color, alpha: 1.0 }
+    }
+}
+
+/// What a ray from the camera hit first, for passes besides the color such
+/// as a denoiser's, and for effects like fog that depend on it.
+#[derive(Clone, Copy, Debug)]
+pub struct PrimaryHit {
+    /// How far along the ray the hit is, in scene units.
+    pub distance: f64,
+    /// The surface's unit normal there, facing back along the ray.
+    pub normal: Vec3,
+    /// The surface's color there; see `Material::albedo`.
+    pub albedo: Color,
+}
+
+impl AddAssign for RaySample {
+    fn add_assign(&mut self, other: Self) {
+        self.color += other.color;
+        self.alpha += other.alpha;
+    }
 }
 
 impl Ray {
@@ -11,30 +227,394 @@ impl Ray {
         self.origin + self.direction * t
     }
 
-    pub fn background(&self) -> Color {
-        // let unit_direction = self.direction.unit();
-        // let t = 0.5 * (unit_direction.y() + 1.0);
-        // Vec3(1.0, 1.0, 1.0) * (1.0 - t) + Vec3(0.5, 0.7, 1.0) * t
-        color(0., 0., 0.)
+    pub fn hit<'a, T: Hittable>(
+        &self,
+        object: &'a T,
+        t: Interval,
+        rng: &mut dyn Sampler,
+    ) -> Option<HitRecord<'a>> {
+        object.hit(self, t, rng)
+    }
+
+    /// `hit`, counting the ray and the work it took toward this thread's
+    /// `TraceStats`, and as a path's first ray if `primary`.
+    fn hit_counted<'a, T: Hittable>(
+        &self,
+        object: &'a T,
+        t: Interval,
+        primary: bool,
+        rng: &mut dyn Sampler,
+    ) -> Option<HitRecord<'a>> {
+        let mut traversal = TraversalStats::default();
+        let record = object.hit_with_stats(self, t, rng, &mut traversal);
+        TRACE_STATS.with_borrow_mut(|counted| {
+            if let Some(counted) = counted {
+                counted.primary_rays += primary as u64;
+                counted.path_rays += 1;
+                counted.node_visits += traversal.node_visits as u64;
+            }
+        });
+        record
+    }
+
+    /// Traces the ray through `world`, ignoring hits closer than `epsilon`,
+    /// until the path leaves the scene, is absorbed, or reaches `depth`.
+    /// Every random choice along the path is drawn from `rng`. A shadow
+    /// catcher the ray hits first shows the environment or `background`
+    /// behind it, darkened by the shadows falling on it.
+    pub fn send(
+        &self,
+        world: &HittableList,
+        lights: &LightList,
+        depth: impl Into<PathDepth>,
+        background: &dyn Background,
+        epsilon: f64,
+        rng: &mut dyn Sampler,
+    ) -> Color {
+        self.send_from_camera(world, lights, depth, background, epsilon, false, rng)
+            .color
     }
 
-    pub fn hit<T: Hittable>(&self, object: &T, t: Interval) -> Option<HitRecord> {
-        object.hit(self, t)
+    /// `send`, also finding how much of the scene the ray covers. If
+    /// `transparent`, the environment or `background` the ray would see
+    /// directly is left out, so the ray comes back black and transparent,
+    /// and a shadow catcher it hits first comes back black with the
+    /// darkness of the shadows on it as its alpha: the scene's part of an
+    /// image to composite over a photograph.
+    #[allow(clippy::too_many_arguments)]
+    pub fn send_from_camera(
+        &self,
+        world: &HittableList,
+        lights: &LightList,
+        depth: impl Into<PathDepth>,
+        background: &dyn Background,
+        epsilon: f64,
+        transparent: bool,
+        rng: &mut dyn Sampler,
+    ) -> RaySample {
+        let first = Interval::from_range(epsilon..f64::INFINITY);
+        self.trace_from_camera(
+            world,
+            lights,
+            depth,
+            background,
+            epsilon,
+            first,
+            transparent,
+            rng,
+        )
+        .0
     }
 
-    pub fn send(&self, world: &HittableList, depth: i32) -> Color {
-        if depth <= 0 {
-            return color(0.0, 0.0, 0.0);
+    /// `send`, also describing what the ray hit first, if anything.
+    pub fn send_with_aux(
+        &self,
+        world: &HittableList,
+        lights: &LightList,
+        depth: impl Into<PathDepth>,
+        background: &dyn Background,
+        epsilon: f64,
+        rng: &mut dyn Sampler,
+    ) -> (Color, Option<PrimaryHit>) {
+        let first = Interval::from_range(epsilon..f64::INFINITY);
+        let (sample, hit) =
+            self.trace_from_camera(world, lights, depth, background, epsilon, first, false, rng);
+        (sample.color, hit)
+    }
+
+    /// `send_from_camera`, with what the ray hit first, if anything. The
+    /// ray itself only finds hits within `first`, such as between a
+    /// camera's clipping planes; the rays it scatters into, beyond
+    /// `epsilon`.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn trace_from_camera(
+        &self,
+        world: &HittableList,
+        lights: &LightList,
+        depth: impl Into<PathDepth>,
+        background: &dyn Background,
+        epsilon: f64,
+        first: Interval,
+        transparent: bool,
+        rng: &mut dyn Sampler,
+    ) -> (RaySample, Option<PrimaryHit>) {
+        let depth = depth.into();
+        let mut radiance = color(0.0, 0.0, 0.0);
+        // The fraction of light reaching the current ray's origin that makes it
+        // back along the path.
+        let mut throughput = color(1.0, 1.0, 1.0);
+        let mut bounces = [0; 4];
+        let mut ray = *self;
+        // The densities of the diffuse bounce that sent `ray` and of the
+        // environment's direct sampling drawing its direction, while an
+        // environment is sampled directly too.
+        let mut bounce_pdfs = None;
+        let mut primary = None;
+        let counting = counting_trace_stats();
+
+        for bounce in 0..=depth.max_depth {
+            // Past the last surface, a path is only followed to see whether it
+            // leaves for the environment, whose sampling at that surface
+            // counted on the bounce finding it too.
+            let last = bounce == depth.max_depth;
+            if last && bounce_pdfs.is_none() {
+                break;
+            }
+            count_ray();
+            let t = match bounce {
+                0 => first,
+                _ => Interval::from_range(epsilon..f64::INFINITY),
+            };
+            let hit = match counting {
+                true => ray.hit_counted(world, t, bounce == 0, rng),
+                false => ray.hit(world, t, rng),
+            };
+            let Some(record) = hit else {
+                if bounce == 0 && transparent {
+                    return (RaySample::TRANSPARENT, primary);
+                }
+                let escaped = radiance + throughput * ray.escape(lights, background, bounce_pdfs);
+                return (RaySample::opaque(escaped), primary);
+            };
+            if bounce == 0 {
+                primary = Some(PrimaryHit {
+                    distance: record.t * ray.direction.length(),
+                    normal: record.normal.unit(),
+                    albedo: record.material.albedo(&record),
+                });
+            }
+            if last {
+                break;
+            }
+            if bounce == 0 && record.material.is_shadow_catcher() {
+                let shadow = ray.catcher_shadow(&record, world, lights, epsilon, rng);
+                if transparent {
+                    let sample = RaySample {
+                        color: color(0.0, 0.0, 0.0),
+                        alpha: shadow,
+                    };
+                    return (sample, primary);
+                }
+                let behind = ray.escape(lights, background, None);
+                return (RaySample::opaque(behind * (1.0 - shadow)), primary);
+            }
+            radiance += throughput
+                * (record.material.emitted(record.u, record.v, &record.point)
+                    + ray.direct_light(&record, world, lights, epsilon, rng));
+            let Some(scatter) = record.material.scatter(&ray, &record, rng) else {
+                return (RaySample::opaque(radiance), primary);
+            };
+            let count = &mut bounces[scatter.kind as usize];
+            *count += 1;
+            if depth
+                .bounces
+                .limit(scatter.kind)
+                .is_some_and(|limit| *count > limit)
+            {
+                return (RaySample::opaque(radiance), primary);
+            }
+            throughput = throughput * scatter.attenuation;
+            if depth
+                .roulette_depth
+                .is_some_and(|start| bounce + 1 >= start)
+            {
+                let survival = throughput.max_component().min(1.0);
+                if rng.next_1d() >= survival {
+                    return (RaySample::opaque(radiance), primary);
+                }
+                throughput = throughput / survival;
+            }
+            bounce_pdfs = match lights.environment_pdf(record.point) {
+                Some(light_pdf) if scatter.kind == BounceKind::Diffuse => {
+                    let direction = scatter.ray.direction.unit();
+                    record
+                        .material
+                        .scatter_pdf(&ray, &record, direction)
+                        .map(|bounce_pdf| (bounce_pdf, light_pdf.value(direction)))
+                }
+                _ => None,
+            };
+            ray = scatter.ray;
         }
-        if let Some(record) = self.hit(world, Interval::from_range(0.0001..f64::INFINITY)) {
-            let emitted = record.material.emitted(record.u, record.v, &record.point);
-            if let Some((scattered, attenuation)) = record.material.scatter(self, &record) {
-                emitted + attenuation * scattered.send(world, depth - 1)
-            } else {
-                emitted
+        (RaySample::opaque(radiance), primary)
+    }
+
+    /// The light seen by a ray leaving the scene: the environment if there is
+    /// one, weighed against its direct sampling if a diffuse bounce sent the
+    /// ray, with `bounce_pdfs` the densities of the two drawing it, or else
+    /// `background`.
+    fn escape(
+        &self,
+        lights: &LightList,
+        background: &dyn Background,
+        bounce_pdfs: Option<(f64, f64)>,
+    ) -> Color {
+        let Some(environment) = &lights.environment else {
+            return background.value(self);
+        };
+        let radiance = environment.radiance(self.direction.unit());
+        match bounce_pdfs {
+            Some((bounce_pdf, light_pdf)) => radiance * power_heuristic(bounce_pdf, light_pdf),
+            None => radiance,
+        }
+    }
+
+    /// The light from `lights.delta` and the environment reaching `record` and
+    /// leaving along this ray. Rays can't hit delta lights, so they are
+    /// sampled with shadow rays instead, toward each one or toward one
+    /// picked as `lights.sampling()` says, its light divided by the odds of
+    /// picking it; specular surfaces don't respond to them at all.
+    fn direct_light(
+        &self,
+        record: &HitRecord,
+        world: &HittableList,
+        lights: &LightList,
+        epsilon: f64,
+        rng: &mut dyn Sampler,
+    ) -> Color {
+        let mut total = color(0.0, 0.0, 0.0);
+        match lights.selector(world) {
+            None => {
+                for light in &lights.delta {
+                    total += self.delta_light(record, world, light.as_ref(), epsilon, rng);
+                }
+            }
+            Some(selector) if !selector.is_empty() => {
+                if let Some((i, probability)) = selector.pick(record.point, rng.next_1d()) {
+                    let light = lights.delta[i].as_ref();
+                    total += self.delta_light(record, world, light, epsilon, rng) / probability;
+                }
             }
+            Some(_) => {}
+        }
+        if let Some(environment) = &lights.environment {
+            total += self.environment_light(record, world, lights, environment, epsilon, rng);
+        }
+        total
+    }
+
+    /// The light from `light` reaching `record` and leaving along this ray,
+    /// if a shadow ray toward it gets through.
+    fn delta_light(
+        &self,
+        record: &HitRecord,
+        world: &HittableList,
+        light: &dyn DeltaLight,
+        epsilon: f64,
+        rng: &mut dyn Sampler,
+    ) -> Color {
+        let black = color(0.0, 0.0, 0.0);
+        let Some(sample) = light.sample(record.point) else {
+            return black;
+        };
+        let response = record.material.evaluate(self, record, sample.direction);
+        if response.near_zero() {
+            return black;
+        }
+        let shadow = record.spawn_ray(sample.direction);
+        count_ray();
+        if world.hit_any(&shadow, Interval::new(epsilon, sample.distance), rng) {
+            return black;
+        }
+        response * sample.radiance
+    }
+
+    /// One shadow ray toward `environment` in a direction drawn from its
+    /// brightness, or through the portals `record` is inside, weighed
+    /// against the material's own bounces finding the same light.
+    fn environment_light(
+        &self,
+        record: &HitRecord,
+        world: &HittableList,
+        lights: &LightList,
+        environment: &Environment,
+        epsilon: f64,
+        rng: &mut dyn Sampler,
+    ) -> Color {
+        let black = color(0.0, 0.0, 0.0);
+        let Some(pdf) = lights.environment_pdf(record.point) else {
+            return black;
+        };
+        let direction = pdf.generate(rng);
+        let Some(bounce_pdf) = record.material.scatter_pdf(self, record, direction) else {
+            return black;
+        };
+        let light_pdf = pdf.value(direction);
+        let response = record.material.evaluate(self, record, direction);
+        if light_pdf <= 0.0 || response.near_zero() {
+            return black;
+        }
+        let shadow = record.spawn_ray(direction);
+        count_ray();
+        if world.hit_any(&shadow, Interval::new(epsilon, f64::INFINITY), rng) {
+            return black;
+        }
+        response
+            * environment.radiance(direction)
+            * (power_heuristic(light_pdf, bounce_pdf) / light_pdf)
+    }
+    /// How much of the direct light reaching the shadow catcher at `record`
+    /// the rest of the world blocks: 0 where it is fully lit and 1 in full
+    /// shadow. Each delta light counts for the light it would bring, and
+    /// the environment for one direction drawn from its sampling, or from
+    /// the catcher's own bounce if it isn't sampled directly.
+    fn catcher_shadow(
+        &self,
+        record: &HitRecord,
+        world: &HittableList,
+        lights: &LightList,
+        epsilon: f64,
+        rng: &mut dyn Sampler,
+    ) -> f64 {
+        // The direction, distance, and brightness of each light.
+        let mut samples = Vec::new();
+        for light in &lights.delta {
+            if let Some(sample) = light.sample(record.point) {
+                let response = record.material.evaluate(self, record, sample.direction);
+                samples.push((
+                    sample.direction,
+                    sample.distance,
+                    (response * sample.radiance).luminance(),
+                ));
+            }
+        }
+        if let Some(environment) = &lights.environment {
+            let sample = match lights.environment_pdf(record.point) {
+                Some(pdf) => {
+                    let direction = pdf.generate(rng);
+                    let light_pdf = pdf.value(direction);
+                    (light_pdf > 0.0).then(|| {
+                        let response = record.material.evaluate(self, record, direction);
+                        (response / light_pdf, direction)
+                    })
+                }
+                None => record
+                    .material
+                    .scatter(self, record, rng)
+                    .map(|scatter| (scatter.attenuation, scatter.ray.direction.unit())),
+            };
+            if let Some((weight, direction)) = sample {
+                let brightness = (weight * environment.radiance(direction)).luminance();
+                samples.push((direction, f64::INFINITY, brightness));
+            }
+        }
+
+        let (mut lit, mut total) = (0.0, 0.0);
+        for (direction, distance, brightness) in samples {
+            if !(brightness > 0.0 && brightness.is_finite()) {
+                continue;
+            }
+            total += brightness;
+            let shadow = record.spawn_ray(direction);
+            count_ray();
+            if !world.hit_any(&shadow, Interval::new(epsilon, distance), rng) {
+                lit += brightness;
+            }
+        }
+        if total > 0.0 {
+            1.0 - lit / total
         } else {
-            self.background()
+            0.0
         }
     }
 }
@@ -0,0 +1,131 @@
+use std::{f64::consts::PI, fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{color, ImageBuffer};
+
+/// How the pixels of an image are weighed when it shrinks by a whole
+/// factor, as a supersampled render does.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DownscaleFilter {
+    /// The plain average of the pixels each one covers.
+    Box,
+    /// A windowed sinc three pixels wide each way, sharper than the box but
+    /// ringing a little at hard edges.
+    #[default]
+    Lanczos3,
+}
+
+impl DownscaleFilter {
+    /// How far from its center the filter reaches, in output pixels.
+    fn radius(self) -> f64 {
+        match self {
+            DownscaleFilter::Box => 0.5,
+            DownscaleFilter::Lanczos3 => 3.0,
+        }
+    }
+
+    /// The weight of an input pixel `x` output pixels from the center.
+    fn weight(self, x: f64) -> f64 {
+        let x = x.abs();
+        match self {
+            DownscaleFilter::Box => (x < 0.5) as u8 as f64,
+            DownscaleFilter::Lanczos3 if x < 1e-12 => 1.0,
+            DownscaleFilter::Lanczos3 if x < 3.0 => {
+                let (a, b) = (PI * x, PI * x / 3.0);
+                (a.sin() / a) * (b.sin() / b)
+            }
+            DownscaleFilter::Lanczos3 => 0.0,
+        }
+    }
+
+    /// The input pixels making up each output pixel, and their weights, when
+    /// `len` pixels shrink by `factor`. Near the ends, the weights of the
+    /// pixels there are scaled back up to sum to 1.
+    fn taps(self, len: usize, factor: usize) -> Vec<Vec<(usize, f64)>> {
+        let scale = factor as f64;
+        (0..len / factor)
+            .map(|out| {
+                let center = (out as f64 + 0.5) * scale;
+                let reach = self.radius() * scale;
+                let first = (center - reach).floor().max(0.0) as usize;
+                let last = ((center + reach).ceil() as usize).min(len);
+                let mut taps: Vec<(usize, f64)> = (first..last)
+                    .map(|i| (i, self.weight((i as f64 + 0.5 - center) / scale)))
+                    .filter(|&(_, weight)| weight != 0.0)
+                    .collect();
+                let total: f64 = taps.iter().map(|(_, weight)| weight).sum();
+                for (_, weight) in &mut taps {
+                    *weight /= total;
+                }
+                taps
+            })
+            .collect()
+    }
+}
+
+impl FromStr for DownscaleFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "box" => Ok(DownscaleFilter::Box),
+            "lanczos3" => Ok(DownscaleFilter::Lanczos3),
+            _ => Err(format!(
+                "unknown downscale filter '{}' (expected box or lanczos3)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DownscaleFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DownscaleFilter::Box => write!(f, "box"),
+            DownscaleFilter::Lanczos3 => write!(f, "lanczos3"),
+        }
+    }
+}
+
+impl ImageBuffer {
+    /// The image shrunk by `factor` each way with `filter`, in linear color,
+    /// its alpha channel along with it. A size that isn't a multiple of the
+    /// factor loses the pixels left over at the right and bottom.
+    pub fn downscale(&self, factor: usize, filter: DownscaleFilter) -> ImageBuffer {
+        self.downscale_axes(factor, factor, filter)
+    }
+
+    /// `downscale`, shrinking by `x` across and `y` down, so that a render
+    /// can shrink each row as it finishes and only the columns at the end.
+    /// The filter's negative lobes can't take a pixel below black, nor its
+    /// alpha outside 0 to 1.
+    pub fn downscale_axes(&self, x: usize, y: usize, filter: DownscaleFilter) -> ImageBuffer {
+        let (x, y) = (x.max(1), y.max(1));
+        let (columns, rows) = (filter.taps(self.width, x), filter.taps(self.height, y));
+        let mut image = ImageBuffer::new(columns.len(), rows.len());
+        let mut alpha = self.alpha.as_ref().map(|_| vec![0.0; image.pixels.len()]);
+        for (out_y, row_taps) in rows.iter().enumerate() {
+            for (out_x, column_taps) in columns.iter().enumerate() {
+                let (mut sum, mut coverage) = (color(0.0, 0.0, 0.0), 0.0);
+                for &(in_y, wy) in row_taps {
+                    for &(in_x, wx) in column_taps {
+                        let i = in_y * self.width + in_x;
+                        sum += self.pixels[i] * (wx * wy);
+                        if let Some(alpha) = &self.alpha {
+                            coverage += alpha[i] * wx * wy;
+                        }
+                    }
+                }
+                let i = out_y * image.width + out_x;
+                image.pixels[i] = color(sum.0.max(0.0), sum.1.max(0.0), sum.2.max(0.0));
+                if let Some(alpha) = &mut alpha {
+                    alpha[i] = coverage.clamp(0.0, 1.0);
+                }
+            }
+        }
+        image.alpha = alpha;
+        image
+    }
+}
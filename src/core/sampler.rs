@@ -0,0 +1,605 @@
+use std::{fmt, str::FromStr, sync::OnceLock};
+
+use rand::{Rng, RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::random::{self, SmallRng};
+
+/// The source of every random number one camera sample draws. Each draw is
+/// one dimension of the sample; samplers that spread their points evenly do so
+/// per dimension, so the same decision should use the same dimension in every
+/// sample of a pixel.
+///
+/// Samplers are also `RngCore`s, each `next_u32`/`next_u64` drawing one
+/// dimension, so anything taking an `Rng` can draw from them.
+pub trait Sampler: RngCore {
+    /// How many dimensions have been drawn so far.
+    fn dimension(&self) -> u32;
+
+    /// A number in [0, 1) for the next dimension.
+    fn next_1d(&mut self) -> f64;
+
+    /// A point in [0, 1)² for the next two dimensions.
+    fn next_2d(&mut self) -> (f64, f64) {
+        (self.next_1d(), self.next_1d())
+    }
+
+    /// Passes over the next `count` dimensions without using them, so that
+    /// the draws after them keep their places.
+    fn skip(&mut self, count: u32) {
+        for _ in 0..count {
+            self.next_1d();
+        }
+    }
+}
+
+/// Which sampler a render draws its samples from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SamplerKind {
+    /// Independent uniform random numbers.
+    #[default]
+    Independent,
+    /// A scrambled Halton sequence for the first dimensions of each sample.
+    Halton,
+    /// An Owen-scrambled Sobol sequence for the first dimensions of each
+    /// sample.
+    Sobol,
+}
+
+impl SamplerKind {
+    pub fn is_default(&self) -> bool {
+        *self == SamplerKind::default()
+    }
+}
+
+impl fmt::Display for SamplerKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SamplerKind::Independent => write!(f, "independent"),
+            SamplerKind::Halton => write!(f, "halton"),
+            SamplerKind::Sobol => write!(f, "sobol"),
+        }
+    }
+}
+
+impl FromStr for SamplerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "independent" => Ok(SamplerKind::Independent),
+            "halton" => Ok(SamplerKind::Halton),
+            "sobol" => Ok(SamplerKind::Sobol),
+            _ => Err(format!(
+                "unknown sampler '{}' (expected independent, halton, or sobol)",
+                s
+            )),
+        }
+    }
+}
+
+/// Converts a number in [0, 1) to the `u64` that `Rng::gen::<f64>()` and
+/// `gen_range` map back to (nearly) the same number.
+fn to_bits(u: f64) -> u64 {
+    (u * 2f64.powi(64)) as u64
+}
+
+/// Fills `dest` from `next_u64`, for samplers whose every draw is a dimension.
+fn fill_bytes_via_next(rng: &mut impl RngCore, dest: &mut [u8]) {
+    for chunk in dest.chunks_mut(8) {
+        let bytes = rng.next_u64().to_le_bytes();
+        chunk.copy_from_slice(&bytes[..chunk.len()]);
+    }
+}
+
+/* === Rotation === */
+
+/// How many leading dimensions of a sample are rotated per pixel.
+const ROTATED_DIMENSIONS: usize = 8;
+
+/// `u` shifted by `offset` around [0, 1) (a Cranley–Patterson rotation).
+fn rotate(u: f64, offset: f64) -> f64 {
+    let u = u + offset;
+    if u >= 1.0 {
+        u - 1.0
+    } else {
+        u
+    }
+}
+
+/// The per-pixel offsets of a sample's leading dimensions, drawn in order so
+/// every sample of the pixel gets the same offset for the same dimension.
+enum Rotation {
+    /// Independent random offsets for every pixel: white noise across the image.
+    White(SmallRng),
+    /// The blue-noise mask at the pixel, read at a different place for each
+    /// dimension so the dimensions don't share one offset.
+    Blue { x: i32, y: i32, shifts: SmallRng },
+}
+
+impl Rotation {
+    fn white_noise(seed: u64, x: i32, y: i32) -> Self {
+        Rotation::White(SmallRng::seed_from_u64(random::pixel_seed(seed, x, y)))
+    }
+
+    fn blue_noise(seed: u64, x: i32, y: i32) -> Self {
+        // The shifts are the same for every pixel, so each dimension's offsets
+        // keep the mask's spectrum across the image.
+        Rotation::Blue {
+            x,
+            y,
+            shifts: SmallRng::seed_from_u64(seed),
+        }
+    }
+
+    fn next_offset(&mut self) -> f64 {
+        match self {
+            Rotation::White(rng) => rng.gen(),
+            Rotation::Blue { x, y, shifts } => {
+                let size = BLUE_NOISE_SIZE as i32;
+                blue_noise(
+                    *x + shifts.gen_range(0..size),
+                    *y + shifts.gen_range(0..size),
+                )
+            }
+        }
+    }
+}
+
+/* === Independent === */
+
+/// Independent uniform random numbers from a `SmallRng`.
+pub struct IndependentSampler {
+    rng: SmallRng,
+    dimension: u32,
+    dither: Option<Dither>,
+}
+
+/// The first dimensions of a dithered independent sample: one random point
+/// shared by every pixel, rotated by each pixel's blue-noise offsets.
+struct Dither {
+    pattern: SmallRng,
+    rotation: Rotation,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64) -> Self {
+        Self::from_rng(SmallRng::seed_from_u64(seed))
+    }
+
+    /// The sampler for one sample of one pixel; see `random::sample_rng`.
+    pub fn for_sample(seed: u64, x: i32, y: i32, sample: u32) -> Self {
+        Self::from_rng(random::sample_rng(seed, x, y, sample))
+    }
+
+    /// Like `for_sample`, but every pixel's sample starts from the same point,
+    /// offset by the blue-noise mask so neighboring pixels err differently.
+    pub fn dithered(seed: u64, x: i32, y: i32, sample: u32) -> Self {
+        Self {
+            dither: Some(Dither {
+                // The stream of a pixel outside the image, shared by all of them.
+                pattern: random::sample_rng(seed, -1, -1, sample),
+                rotation: Rotation::blue_noise(seed, x, y),
+            }),
+            ..Self::for_sample(seed, x, y, sample)
+        }
+    }
+
+    fn from_rng(rng: SmallRng) -> Self {
+        Self {
+            rng,
+            dimension: 0,
+            dither: None,
+        }
+    }
+
+    /// The dither of the next dimension, if it's one of the rotated ones.
+    fn dither(&mut self) -> Option<&mut Dither> {
+        self.dither
+            .as_mut()
+            .filter(|_| (self.dimension as usize) < ROTATED_DIMENSIONS)
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn dimension(&self) -> u32 {
+        self.dimension
+    }
+    fn next_1d(&mut self) -> f64 {
+        let u = match self.dither() {
+            Some(dither) => rotate(dither.pattern.gen(), dither.rotation.next_offset()),
+            None => self.rng.gen(),
+        };
+        self.dimension += 1;
+        u
+    }
+    fn skip(&mut self, count: u32) {
+        // Undithered draws are independent of their dimension, so there's
+        // nothing to pass over but the count.
+        if self.dither.is_none() {
+            self.dimension += count;
+            return;
+        }
+        for _ in 0..count {
+            self.next_1d();
+        }
+    }
+}
+
+impl RngCore for IndependentSampler {
+    fn next_u32(&mut self) -> u32 {
+        if self.dither().is_some() {
+            return (to_bits(self.next_1d()) >> 32) as u32;
+        }
+        self.dimension += 1;
+        self.rng.next_u32()
+    }
+    fn next_u64(&mut self) -> u64 {
+        if self.dither().is_some() {
+            return to_bits(self.next_1d());
+        }
+        self.dimension += 1;
+        self.rng.next_u64()
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        fill_bytes_via_next(self, dest)
+    }
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/* === Halton === */
+
+/// The bases of the Halton dimensions: pixel jitter, lens, time, and the
+/// first scatter decisions. Larger bases need more samples than a pixel gets
+/// to fill their dimension evenly, so later draws are independent instead.
+const HALTON_BASES: [u32; ROTATED_DIMENSIONS] = [2, 3, 5, 7, 11, 13, 17, 19];
+
+/// The digits of `index` in `base`, mirrored around the radix point.
+pub fn radical_inverse(base: u32, mut index: u64) -> f64 {
+    let base = base as u64;
+    let inverse_base = 1.0 / base as f64;
+    let mut reversed = 0u64;
+    let mut scale = 1.0;
+    while index > 0 {
+        reversed = reversed * base + index % base;
+        scale *= inverse_base;
+        index /= base;
+    }
+    (reversed as f64 * scale).min(1.0 - f64::EPSILON / 2.0)
+}
+
+/// The `sample`th point of the Halton sequence, rotated per pixel so
+/// neighboring pixels don't share one pattern. Past the Halton dimensions,
+/// draws are independent.
+pub struct HaltonSampler {
+    index: u64,
+    dimension: u32,
+    rotation: Rotation,
+    rng: SmallRng,
+}
+
+impl HaltonSampler {
+    /// The sampler for one sample of one pixel, rotated by random offsets.
+    pub fn for_sample(seed: u64, x: i32, y: i32, sample: u32) -> Self {
+        Self::with_rotation(seed, x, y, sample, Rotation::white_noise(seed, x, y))
+    }
+
+    /// Like `for_sample`, but rotated by the blue-noise mask so neighboring
+    /// pixels err differently.
+    pub fn dithered(seed: u64, x: i32, y: i32, sample: u32) -> Self {
+        Self::with_rotation(seed, x, y, sample, Rotation::blue_noise(seed, x, y))
+    }
+
+    fn with_rotation(seed: u64, x: i32, y: i32, sample: u32, rotation: Rotation) -> Self {
+        Self {
+            index: sample as u64,
+            dimension: 0,
+            rotation,
+            rng: random::sample_rng(seed, x, y, sample),
+        }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn dimension(&self) -> u32 {
+        self.dimension
+    }
+    fn next_1d(&mut self) -> f64 {
+        let dimension = self.dimension as usize;
+        self.dimension += 1;
+        match HALTON_BASES.get(dimension) {
+            Some(&base) => rotate(
+                radical_inverse(base, self.index),
+                self.rotation.next_offset(),
+            ),
+            None => self.rng.gen(),
+        }
+    }
+}
+
+impl RngCore for HaltonSampler {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+    fn next_u64(&mut self) -> u64 {
+        to_bits(self.next_1d())
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        fill_bytes_via_next(self, dest)
+    }
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/* === Sobol === */
+
+/// How many dimensions of a sample come from the Sobol sequence, in the
+/// order a camera sample draws them: pixel jitter x and y, lens u and v
+/// for defocus, and time for motion blur, then the first scatter
+/// decisions. A camera without a lens or a shutter skips their dimensions
+/// rather than leaving them to the scatter decisions, so each decision
+/// draws from the same dimension whatever the camera. Later draws are
+/// independent.
+pub const SOBOL_DIMENSIONS: usize = ROTATED_DIMENSIONS;
+
+/// The degree, coefficients, and initial direction numbers of each Sobol
+/// dimension's primitive polynomial, after the first's, from Joe and
+/// Kuo's new-joe-kuo-6.21201 table.
+const SOBOL_POLYNOMIALS: [(usize, u32, [u32; 5]); SOBOL_DIMENSIONS - 1] = [
+    (1, 0, [1, 0, 0, 0, 0]),
+    (2, 1, [1, 3, 0, 0, 0]),
+    (3, 1, [1, 3, 1, 0, 0]),
+    (3, 2, [1, 1, 1, 0, 0]),
+    (4, 1, [1, 1, 3, 3, 0]),
+    (4, 4, [1, 3, 5, 13, 0]),
+    (5, 2, [1, 1, 5, 5, 17]),
+];
+
+/// Each dimension's direction numbers, one for each bit of the index, as
+/// the 32 bits after the binary point.
+static SOBOL_DIRECTIONS: [[u32; 32]; SOBOL_DIMENSIONS] = sobol_directions();
+
+const fn sobol_directions() -> [[u32; 32]; SOBOL_DIMENSIONS] {
+    let mut directions = [[0; 32]; SOBOL_DIMENSIONS];
+    // The first dimension is the van der Corput sequence.
+    let mut i = 0;
+    while i < 32 {
+        directions[0][i] = 1 << (31 - i);
+        i += 1;
+    }
+    let mut d = 1;
+    while d < SOBOL_DIMENSIONS {
+        let (degree, coefficients, initial) = SOBOL_POLYNOMIALS[d - 1];
+        let v = &mut directions[d];
+        let mut i = 0;
+        while i < 32 {
+            v[i] = if i < degree {
+                initial[i] << (31 - i)
+            } else {
+                let mut x = v[i - degree] ^ (v[i - degree] >> degree);
+                let mut k = 1;
+                while k < degree {
+                    if (coefficients >> (degree - 1 - k)) & 1 == 1 {
+                        x ^= v[i - k];
+                    }
+                    k += 1;
+                }
+                x
+            };
+            i += 1;
+        }
+        d += 1;
+    }
+    directions
+}
+
+fn sobol_bits(dimension: usize, mut index: u32) -> u32 {
+    let mut bits = 0;
+    for direction in SOBOL_DIRECTIONS[dimension] {
+        if index == 0 {
+            break;
+        }
+        if index & 1 == 1 {
+            bits ^= direction;
+        }
+        index >>= 1;
+    }
+    bits
+}
+
+/// Point number `index` of the unscrambled Sobol sequence in `dimension`,
+/// which must be below `SOBOL_DIMENSIONS`.
+pub fn sobol(dimension: usize, index: u32) -> f64 {
+    sobol_bits(dimension, index) as f64 / 2f64.powi(32)
+}
+
+/// `bits` Owen-scrambled by `seed`: each digit flipped or not at random,
+/// depending on the digits before it, by Burley's hash of the reversed
+/// bits ("Practical Hash-based Owen Scrambling", 2020). Points that split
+/// [0, 1) evenly still do after it.
+fn owen_scramble(bits: u32, seed: u32) -> u32 {
+    let mut x = bits.reverse_bits().wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50_b47c);
+    x ^= x.wrapping_mul(0xb82f_1e52);
+    x ^= x.wrapping_mul(0xc7af_e638);
+    x ^= x.wrapping_mul(0x8d22_f6e6);
+    x.reverse_bits()
+}
+
+/// The `sample`th point of the Sobol sequence, Owen-scrambled per pixel so
+/// neighboring pixels don't share one pattern. Past the Sobol dimensions,
+/// draws are independent.
+pub struct SobolSampler {
+    index: u32,
+    dimension: u32,
+    /// Draws each dimension's scrambling seed in order, the same in every
+    /// sample of the pixel.
+    scrambles: SmallRng,
+    /// For dithered samplers, the blue-noise offsets of each pixel.
+    rotation: Option<Rotation>,
+    rng: SmallRng,
+}
+
+impl SobolSampler {
+    /// The sampler for one sample of one pixel, scrambled by a seed of the
+    /// pixel's own.
+    pub fn for_sample(seed: u64, x: i32, y: i32, sample: u32) -> Self {
+        Self {
+            index: sample,
+            dimension: 0,
+            scrambles: SmallRng::seed_from_u64(random::pixel_seed(seed, x, y)),
+            rotation: None,
+            rng: random::sample_rng(seed, x, y, sample),
+        }
+    }
+
+    /// Like `for_sample`, but every pixel is scrambled alike and rotated by
+    /// the blue-noise mask instead, so neighboring pixels err differently.
+    pub fn dithered(seed: u64, x: i32, y: i32, sample: u32) -> Self {
+        Self {
+            scrambles: SmallRng::seed_from_u64(seed),
+            rotation: Some(Rotation::blue_noise(seed, x, y)),
+            ..Self::for_sample(seed, x, y, sample)
+        }
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn dimension(&self) -> u32 {
+        self.dimension
+    }
+    fn next_1d(&mut self) -> f64 {
+        let dimension = self.dimension as usize;
+        self.dimension += 1;
+        if dimension >= SOBOL_DIMENSIONS {
+            return self.rng.gen();
+        }
+        let bits = owen_scramble(sobol_bits(dimension, self.index), self.scrambles.gen());
+        let u = bits as f64 / 2f64.powi(32);
+        match &mut self.rotation {
+            Some(rotation) => rotate(u, rotation.next_offset()),
+            None => u,
+        }
+    }
+}
+
+impl RngCore for SobolSampler {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+    fn next_u64(&mut self) -> u64 {
+        to_bits(self.next_1d())
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        fill_bytes_via_next(self, dest)
+    }
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/* === Blue Noise === */
+
+/// The side of the tileable blue-noise mask, in pixels.
+pub const BLUE_NOISE_SIZE: usize = 64;
+
+/// The blue-noise mask at pixel (x, y), repeating every `BLUE_NOISE_SIZE`
+/// pixels. Each value in [0, 1) appears once per tile, and nearby pixels
+/// have values far apart, so the mask has almost no low frequencies.
+pub fn blue_noise(x: i32, y: i32) -> f64 {
+    static MASK: OnceLock<Vec<f64>> = OnceLock::new();
+    let mask = MASK.get_or_init(|| void_and_cluster(BLUE_NOISE_SIZE));
+    let size = BLUE_NOISE_SIZE as i32;
+    mask[(y.rem_euclid(size) * size + x.rem_euclid(size)) as usize]
+}
+
+/// Ulichney's void-and-cluster method: ranks the pixels of a `size`×`size`
+/// torus so that the first n of them are evenly spread for every n, and
+/// returns each pixel's rank scaled to [0, 1).
+fn void_and_cluster(size: usize) -> Vec<f64> {
+    let count = size * size;
+    // A Gaussian of deviation 1.5, cut off where it falls below 1e-5.
+    let sigma: f64 = 1.5;
+    let radius = ((3.0 * sigma).ceil() as usize).min((size - 1) / 2);
+    let kernel = |dx: usize, dy: usize| {
+        let (dx, dy) = (dx as f64 - radius as f64, dy as f64 - radius as f64);
+        (-(dx * dx + dy * dy) / (2.0 * sigma * sigma)).exp()
+    };
+    let mut ones = vec![false; count];
+    let mut energy = vec![0.0; count];
+    // Sets or clears pixel i, updating the energy of the pixels around it.
+    let toggle = |ones: &mut [bool], energy: &mut [f64], i: usize| {
+        ones[i] = !ones[i];
+        let sign = if ones[i] { 1.0 } else { -1.0 };
+        let (ix, iy) = (i % size, i / size);
+        for dy in 0..=2 * radius {
+            let y = (iy + size + dy - radius) % size;
+            for dx in 0..=2 * radius {
+                let x = (ix + size + dx - radius) % size;
+                energy[y * size + x] += sign * kernel(dx, dy);
+            }
+        }
+    };
+    // The densest set pixel and the emptiest unset one.
+    let tightest_cluster = |ones: &[bool], energy: &[f64]| {
+        (0..count)
+            .filter(|&i| ones[i])
+            .max_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+            .unwrap()
+    };
+    let largest_void = |ones: &[bool], energy: &[f64]| {
+        (0..count)
+            .filter(|&i| !ones[i])
+            .min_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+            .unwrap()
+    };
+
+    // A random tenth of the pixels, moved from clusters into voids until
+    // they're evenly spread.
+    let mut rng = SmallRng::seed_from_u64(0);
+    let initial = count / 10;
+    while ones.iter().filter(|&&one| one).count() < initial {
+        let i = rng.gen_range(0..count);
+        if !ones[i] {
+            toggle(&mut ones, &mut energy, i);
+        }
+    }
+    loop {
+        let cluster = tightest_cluster(&ones, &energy);
+        toggle(&mut ones, &mut energy, cluster);
+        let void = largest_void(&ones, &energy);
+        if void == cluster {
+            toggle(&mut ones, &mut energy, cluster);
+            break;
+        }
+        toggle(&mut ones, &mut energy, void);
+    }
+    let (prototype, prototype_energy) = (ones.clone(), energy.clone());
+
+    // Rank the initial pixels by removing the tightest cluster each time,
+    // then the rest by filling the largest void each time.
+    let mut ranks = vec![0; count];
+    for rank in (0..initial).rev() {
+        let cluster = tightest_cluster(&ones, &energy);
+        toggle(&mut ones, &mut energy, cluster);
+        ranks[cluster] = rank;
+    }
+    let (mut ones, mut energy) = (prototype, prototype_energy);
+    for rank in initial..count {
+        let void = largest_void(&ones, &energy);
+        toggle(&mut ones, &mut energy, void);
+        ranks[void] = rank;
+    }
+
+    ranks
+        .into_iter()
+        .map(|rank| (rank as f64 + 0.5) / count as f64)
+        .collect()
+}
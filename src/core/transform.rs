@@ -0,0 +1,139 @@
+use crate::{Mat4, Point, Quat, Vec3};
+
+use serde::{Deserialize, Serialize};
+
+/// Scale, then rotate about the Y axis, then translate.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation_y: f64,
+    pub scale: Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Self {
+            translation: Vec3(0., 0., 0.),
+            rotation_y: 0.,
+            scale: Vec3(1., 1., 1.),
+        }
+    }
+    pub fn translate(offset: Vec3) -> Self {
+        Self {
+            translation: offset,
+            ..Self::identity()
+        }
+    }
+    pub fn rotate_y(angle: f64) -> Self {
+        Self {
+            rotation_y: angle,
+            ..Self::identity()
+        }
+    }
+    pub fn scale(factor: f64) -> Self {
+        Self {
+            scale: Vec3(factor, factor, factor),
+            ..Self::identity()
+        }
+    }
+
+    /// A transform that only rotates by `rotation`, or `None` unless it turns
+    /// about the Y axis, the only rotation a `Transform` holds.
+    pub fn from_rotation(rotation: Quat) -> Option<Self> {
+        let (axis, angle) = rotation.to_axis_angle();
+        if angle.abs() < 1e-9 {
+            return Some(Self::identity());
+        }
+        if axis.0.abs() > 1e-9 || axis.2.abs() > 1e-9 {
+            return None;
+        }
+        Some(Self::rotate_y(angle * axis.1.signum()))
+    }
+    /// The rotation part of the transform.
+    pub fn rotation(&self) -> Quat {
+        Quat::from_axis_angle(Vec3(0., 1., 0.), self.rotation_y)
+    }
+
+    fn rotate(&self, v: Vec3) -> Vec3 {
+        let (sin_theta, cos_theta) = self.rotation_y.to_radians().sin_cos();
+        Vec3(
+            cos_theta * v.0 + sin_theta * v.2,
+            v.1,
+            -sin_theta * v.0 + cos_theta * v.2,
+        )
+    }
+
+    pub fn point(&self, p: Point) -> Point {
+        self.rotate(p * self.scale) + self.translation
+    }
+    pub fn vector(&self, v: Vec3) -> Vec3 {
+        self.rotate(v * self.scale)
+    }
+    pub fn normal(&self, n: Vec3) -> Vec3 {
+        // Normals transform by the inverse transpose, which for a scale
+        // followed by a rotation is the inverse scale followed by the rotation.
+        self.rotate(n / self.scale).unit()
+    }
+}
+
+/// Scale, then rotate about any axis, then translate: the parts of an affine
+/// transform that interpolate smoothly.
+#[derive(Debug, Clone, Copy)]
+pub struct Pose {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Default for Pose {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Pose {
+    pub fn identity() -> Self {
+        Self::from(Transform::identity())
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::translation(self.translation) * Mat4::from(self.rotation) * Mat4::scale(self.scale)
+    }
+    /// The inverse of `matrix()`, built from the inverted parts.
+    pub fn inverse_matrix(&self) -> Mat4 {
+        Mat4::scale(Vec3(1., 1., 1.) / self.scale)
+            * Mat4::from(self.rotation.inverse())
+            * Mat4::translation(-self.translation)
+    }
+
+    /// The pose `t` of the way from `self` to `other`: translation and scale
+    /// move in a straight line, and the rotation turns at a constant rate.
+    pub fn lerp(&self, other: &Pose, t: f64) -> Self {
+        Self {
+            translation: self.translation + (other.translation - self.translation) * t,
+            rotation: self.rotation.slerp(&other.rotation, t),
+            scale: self.scale + (other.scale - self.scale) * t,
+        }
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.translation.is_finite() && self.rotation.is_finite() && self.scale.is_finite()
+    }
+}
+
+impl From<Transform> for Pose {
+    fn from(transform: Transform) -> Self {
+        Self {
+            translation: transform.translation,
+            rotation: transform.rotation(),
+            scale: transform.scale,
+        }
+    }
+}
@@ -1,10 +1,15 @@
 use crate::Interval;
 
-use rand::{random, thread_rng, Rng};
-use serde::Deserialize;
-use std::ops::{Add, AddAssign, Div, Index, Mul, Neg, Sub};
-
-#[derive(Debug, Copy, Clone, Deserialize)]
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::{
+    f64::consts::PI,
+    fmt,
+    io::{self, Write},
+    ops::{Add, AddAssign, Div, Index, Mul, Neg, Sub},
+};
+
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
 pub struct Vec3(pub f64, pub f64, pub f64);
 
 impl Vec3 {
@@ -50,6 +55,16 @@ impl Vec3 {
     pub fn unit(&self) -> Vec3 {
         *self / self.length()
     }
+    pub fn is_finite(&self) -> bool {
+        self.0.is_finite() && self.1.is_finite() && self.2.is_finite()
+    }
+    /// Perceived brightness, taking the components as linear Rec. 709 color.
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.0 + 0.7152 * self.1 + 0.0722 * self.2
+    }
+    pub fn max_component(&self) -> f64 {
+        self.0.max(self.1).max(self.2)
+    }
     pub fn near_zero(&self) -> bool {
         let s = 1e-8;
         self.0.abs() < s && self.1.abs() < s && self.2.abs() < s
@@ -78,23 +93,28 @@ impl Vec3 {
         Vec3(r, g, b)
     }
 
-    pub fn write_color(&self) {
+    pub fn to_bytes(&self) -> [u8; 3] {
         let intensity = Interval::new(0.0, 0.999);
-        println!(
-            "{} {} {}",
-            (256.0 * intensity.clamp(self.0)) as i32,
-            (256.0 * intensity.clamp(self.1)) as i32,
-            (256.0 * intensity.clamp(self.2)) as i32,
-        );
+        [
+            (256.0 * intensity.clamp(self.0)) as u8,
+            (256.0 * intensity.clamp(self.1)) as u8,
+            (256.0 * intensity.clamp(self.2)) as u8,
+        ]
+    }
+
+    /// Writes the color as a line of a plain PPM, its channels clamped to
+    /// bytes. `out` should be buffered; a line is a single small write.
+    pub fn write_color(&self, out: &mut impl Write) -> io::Result<()> {
+        let [r, g, b] = self.to_bytes();
+        writeln!(out, "{} {} {}", r, g, b)
     }
 
     /* -- Random -- */
-    pub fn random() -> Vec3 {
-        Vec3(random(), random(), random())
+    pub fn random(rng: &mut (impl Rng + ?Sized)) -> Vec3 {
+        Vec3(rng.gen(), rng.gen(), rng.gen())
     }
 
-    pub fn random_range(min: f64, max: f64) -> Vec3 {
-        let mut rng = thread_rng();
+    pub fn random_range(rng: &mut (impl Rng + ?Sized), min: f64, max: f64) -> Vec3 {
         Vec3(
             rng.gen_range(min..max),
             rng.gen_range(min..max),
@@ -102,23 +122,35 @@ impl Vec3 {
         )
     }
 
-    pub fn sample_square() -> Vec3 {
-        let mut rng = thread_rng();
+    pub fn sample_square(rng: &mut (impl Rng + ?Sized)) -> Vec3 {
         Vec3(rng.gen_range(-0.5..0.5), rng.gen_range(-0.5..0.5), 0.0)
     }
 
-    pub fn random_unit() -> Vec3 {
-        loop {
-            let v = Vec3::random_range(-1.0, 1.0);
-            let l = v.length_squared();
-            if l < 1.0 && l > 1e-60 {
-                return v / f64::sqrt(l);
-            }
-        }
+    /// A uniformly distributed point of the unit disk in the xy plane. Always
+    /// draws exactly two numbers.
+    pub fn random_in_unit_disk(rng: &mut (impl Rng + ?Sized)) -> Vec3 {
+        let u = rng.gen::<f64>();
+        Vec3::in_unit_disk(u, rng.gen())
     }
 
-    pub fn random_on_hemisphere(normal: Vec3) -> Vec3 {
-        let on_unit_sphere = Vec3::random_unit();
+    /// Maps `(u, v)` in [0, 1)² to a point of the unit disk in the xy plane,
+    /// spread evenly over it as `(u, v)` is over theirs.
+    pub fn in_unit_disk(u: f64, v: f64) -> Vec3 {
+        let (r, theta) = (u.sqrt(), 2.0 * PI * v);
+        Vec3(r * theta.cos(), r * theta.sin(), 0.0)
+    }
+
+    /// A uniformly distributed unit vector. Always draws exactly two numbers,
+    /// so each bounce uses the same sampler dimensions in every sample.
+    pub fn random_unit(rng: &mut (impl Rng + ?Sized)) -> Vec3 {
+        let z: f64 = 1.0 - 2.0 * rng.gen::<f64>();
+        let phi = 2.0 * PI * rng.gen::<f64>();
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        Vec3(r * phi.cos(), r * phi.sin(), z)
+    }
+
+    pub fn random_on_hemisphere(rng: &mut (impl Rng + ?Sized), normal: Vec3) -> Vec3 {
+        let on_unit_sphere = Vec3::random_unit(rng);
         if Vec3::dot(&on_unit_sphere, &normal) > 0.0 {
             on_unit_sphere
         } else {
@@ -127,6 +159,12 @@ impl Vec3 {
     }
 }
 
+impl fmt::Display for Vec3 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {}, {})", self.0, self.1, self.2)
+    }
+}
+
 impl Add for Vec3 {
     type Output = Self;
     fn add(self, other: Self) -> Self {
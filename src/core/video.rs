@@ -0,0 +1,407 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, Command, Stdio},
+};
+
+use crate::ImageBuffer;
+
+/// Where the frames of an animation go, in order.
+pub trait VideoSink {
+    /// Adds the next frame. Every frame must be the size of the first.
+    fn write_frame(&mut self, frame: &ImageBuffer) -> Result<(), VideoError>;
+
+    /// Finishes the video after the last frame.
+    fn finish(self: Box<Self>) -> Result<(), VideoError>;
+}
+
+/// Something that went wrong writing a video.
+#[derive(Debug)]
+pub enum VideoError {
+    /// `ffmpeg` couldn't be started, most likely because it isn't installed.
+    FfmpegMissing {
+        program: PathBuf,
+        source: io::Error,
+    },
+    /// `ffmpeg` gave up, with what it printed to stderr.
+    Ffmpeg {
+        status: String,
+        message: String,
+    },
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    FrameSize {
+        expected: (usize, usize),
+        found: (usize, usize),
+    },
+    /// The format of the output can't be written by this build.
+    Unsupported(String),
+}
+
+impl fmt::Display for VideoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VideoError::FfmpegMissing { program, source } => write!(
+                f,
+                "could not run {} ({}); install ffmpeg, or write a .gif instead",
+                program.display(),
+                source
+            ),
+            VideoError::Ffmpeg { status, message } if message.is_empty() => {
+                write!(f, "ffmpeg failed ({})", status)
+            }
+            VideoError::Ffmpeg { status, message } => {
+                write!(f, "ffmpeg failed ({}): {}", status, message.trim_end())
+            }
+            VideoError::Io { path, source } => {
+                write!(f, "could not write {}: {}", path.display(), source)
+            }
+            VideoError::FrameSize { expected, found } => write!(
+                f,
+                "frames must all be {}x{}, got one {}x{}",
+                expected.0, expected.1, found.0, found.1
+            ),
+            VideoError::Unsupported(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for VideoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            VideoError::FfmpegMissing { source, .. } | VideoError::Io { source, .. } => {
+                Some(source)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn check_size(expected: (usize, usize), frame: &ImageBuffer) -> Result<(), VideoError> {
+    let found = (frame.width, frame.height);
+    if found != expected {
+        return Err(VideoError::FrameSize { expected, found });
+    }
+    Ok(())
+}
+
+/// How `FfmpegSink` encodes a video.
+#[derive(Clone, Debug)]
+pub struct FfmpegSettings {
+    /// The ffmpeg executable, found on the `PATH` if it's a bare name.
+    pub program: PathBuf,
+    pub fps: u32,
+    /// The video codec, e.g. libx264 for an mp4 that plays anywhere.
+    pub codec: String,
+}
+
+impl Default for FfmpegSettings {
+    fn default() -> Self {
+        Self {
+            program: PathBuf::from("ffmpeg"),
+            fps: 24,
+            codec: "libx264".to_string(),
+        }
+    }
+}
+
+/// Streams frames as raw RGBA into an `ffmpeg` process that encodes them
+/// into a video file, whatever its extension says.
+pub struct FfmpegSink {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    output: PathBuf,
+    size: (usize, usize),
+}
+
+impl FfmpegSink {
+    /// Starts ffmpeg writing `output` from frames `width` by `height`.
+    pub fn spawn(
+        settings: &FfmpegSettings,
+        output: &Path,
+        width: usize,
+        height: usize,
+    ) -> Result<Self, VideoError> {
+        let mut child = Command::new(&settings.program)
+            .args([
+                "-y",
+                "-loglevel",
+                "error",
+                "-f",
+                "rawvideo",
+                "-pix_fmt",
+                "rgba",
+            ])
+            .args(["-s", &format!("{}x{}", width, height)])
+            .args(["-r", &settings.fps.to_string(), "-i", "-"])
+            .args(["-c:v", &settings.codec])
+            // Most players only take 4:2:0 video, which needs even sizes.
+            .args([
+                "-pix_fmt",
+                "yuv420p",
+                "-vf",
+                "pad=ceil(iw/2)*2:ceil(ih/2)*2",
+            ])
+            .arg(output)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|source| VideoError::FfmpegMissing {
+                program: settings.program.clone(),
+                source,
+            })?;
+        Ok(Self {
+            stdin: child.stdin.take(),
+            child,
+            output: output.to_path_buf(),
+            size: (width, height),
+        })
+    }
+
+    /// Waits for ffmpeg to exit, reporting its complaints if it failed.
+    fn wait(&mut self) -> Result<(), VideoError> {
+        drop(self.stdin.take());
+        let mut message = String::new();
+        if let Some(mut stderr) = self.child.stderr.take() {
+            let _ = stderr.read_to_string(&mut message);
+        }
+        let status = self.child.wait().map_err(|source| VideoError::Io {
+            path: self.output.clone(),
+            source,
+        })?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(VideoError::Ffmpeg {
+                status: status.to_string(),
+                message,
+            })
+        }
+    }
+}
+
+impl VideoSink for FfmpegSink {
+    fn write_frame(&mut self, frame: &ImageBuffer) -> Result<(), VideoError> {
+        check_size(self.size, frame)?;
+        let result = match &mut self.stdin {
+            Some(stdin) => stdin.write_all(&frame.to_rgba8()),
+            None => Err(io::ErrorKind::BrokenPipe.into()),
+        };
+        result.or_else(|source| {
+            // A closed pipe means ffmpeg quit, and it will have said why.
+            self.wait()?;
+            Err(VideoError::Io {
+                path: self.output.clone(),
+                source,
+            })
+        })
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), VideoError> {
+        self.wait()
+    }
+}
+
+/// Writes frames into an endlessly looping animated GIF, each quantized to
+/// a palette of its own. Fine for short clips; ffmpeg makes far smaller
+/// files of longer ones.
+#[cfg(feature = "image")]
+pub struct GifSink {
+    encoder: gif::Encoder<io::BufWriter<std::fs::File>>,
+    path: PathBuf,
+    size: (usize, usize),
+    /// How long each frame shows, in hundredths of a second.
+    delay: u16,
+}
+
+#[cfg(feature = "image")]
+impl GifSink {
+    pub fn create(path: &Path, width: usize, height: usize, fps: u32) -> Result<Self, VideoError> {
+        let io_error = |source| VideoError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        let (Ok(w), Ok(h)) = (u16::try_from(width), u16::try_from(height)) else {
+            return Err(VideoError::Unsupported(format!(
+                "GIFs can't be {}x{}; they are at most 65535 pixels across",
+                width, height
+            )));
+        };
+        let file = std::fs::File::create(path).map_err(io_error)?;
+        let mut encoder = gif::Encoder::new(io::BufWriter::new(file), w, h, &[])
+            .map_err(|e| io_error(io::Error::other(e)))?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(|e| io_error(io::Error::other(e)))?;
+        Ok(Self {
+            encoder,
+            path: path.to_path_buf(),
+            size: (width, height),
+            delay: (100 / fps.max(1)).clamp(1, u16::MAX as u32) as u16,
+        })
+    }
+}
+
+#[cfg(feature = "image")]
+impl VideoSink for GifSink {
+    fn write_frame(&mut self, frame: &ImageBuffer) -> Result<(), VideoError> {
+        check_size(self.size, frame)?;
+        let mut rgba = frame.to_rgba8();
+        let (w, h) = (self.size.0 as u16, self.size.1 as u16);
+        let mut gif_frame = gif::Frame::from_rgba_speed(w, h, &mut rgba, 10);
+        gif_frame.delay = self.delay;
+        self.encoder
+            .write_frame(&gif_frame)
+            .map_err(|e| VideoError::Io {
+                path: self.path.clone(),
+                source: io::Error::other(e),
+            })
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), VideoError> {
+        let path = self.path;
+        let io_error = |source| VideoError::Io {
+            path: path.clone(),
+            source,
+        };
+        let mut out = self
+            .encoder
+            .into_inner()
+            .map_err(|e| io_error(io::Error::other(e)))?;
+        out.flush().map_err(io_error)
+    }
+}
+
+/// Writes each frame to its own image in a directory, `frame_0001.png`,
+/// `frame_0002.png`, and so on, for editing or encoding elsewhere.
+pub struct FrameSequence {
+    dir: PathBuf,
+    extension: String,
+    size: Option<(usize, usize)>,
+    written: usize,
+}
+
+impl FrameSequence {
+    /// A sequence of images in `dir`, created if it doesn't exist, of the
+    /// format `extension` names, as for `ImageBuffer::save`.
+    pub fn create(dir: &Path, extension: &str) -> Result<Self, VideoError> {
+        std::fs::create_dir_all(dir).map_err(|source| VideoError::Io {
+            path: dir.to_path_buf(),
+            source,
+        })?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            extension: extension.to_string(),
+            size: None,
+            written: 0,
+        })
+    }
+
+    /// The file frame `index`, counting from 0, is written to.
+    pub fn frame_path(&self, index: usize) -> PathBuf {
+        self.dir
+            .join(format!("frame_{:04}.{}", index + 1, self.extension))
+    }
+}
+
+impl VideoSink for FrameSequence {
+    fn write_frame(&mut self, frame: &ImageBuffer) -> Result<(), VideoError> {
+        check_size(*self.size.get_or_insert((frame.width, frame.height)), frame)?;
+        let path = self.frame_path(self.written);
+        frame
+            .save(&path)
+            .map_err(|source| VideoError::Io { path, source })?;
+        self.written += 1;
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), VideoError> {
+        Ok(())
+    }
+}
+
+/// A sink for `path` by its extension: an animated GIF for .gif, numbered
+/// PNGs (PPMs without the `image` feature) in the directory `path` without
+/// one, and otherwise whatever ffmpeg makes of it.
+pub fn open_video(
+    path: &Path,
+    width: usize,
+    height: usize,
+    settings: &FfmpegSettings,
+) -> Result<Box<dyn VideoSink>, VideoError> {
+    match path.extension().and_then(|e| e.to_str()) {
+        #[cfg(feature = "image")]
+        Some("gif") => Ok(Box::new(GifSink::create(
+            path,
+            width,
+            height,
+            settings.fps,
+        )?)),
+        #[cfg(not(feature = "image"))]
+        Some("gif") => Err(VideoError::Unsupported(
+            "this build can't write GIFs; enable the `image` feature".to_string(),
+        )),
+        None => {
+            let extension = if cfg!(feature = "image") {
+                "png"
+            } else {
+                "ppm"
+            };
+            Ok(Box::new(FrameSequence::create(path, extension)?))
+        }
+        _ => Ok(Box::new(FfmpegSink::spawn(settings, path, width, height)?)),
+    }
+}
+
+/// Puts items numbered 0, 1, 2, ... back in order when they arrive out of
+/// it, e.g. frames rendered in parallel, holding each until those before it
+/// have come.
+#[derive(Debug)]
+pub struct ReorderBuffer<T> {
+    next: usize,
+    waiting: BTreeMap<usize, T>,
+}
+
+impl<T> Default for ReorderBuffer<T> {
+    fn default() -> Self {
+        Self {
+            next: 0,
+            waiting: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T> ReorderBuffer<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds item `index`, returning it and any held after it that are now
+    /// in order. Items already released or already held are ignored.
+    pub fn push(&mut self, index: usize, item: T) -> Vec<T> {
+        if index >= self.next {
+            self.waiting.entry(index).or_insert(item);
+        }
+        let mut ready = Vec::new();
+        while let Some(item) = self.waiting.remove(&self.next) {
+            ready.push(item);
+            self.next += 1;
+        }
+        ready
+    }
+
+    /// The index of the next item to release.
+    pub fn next_index(&self) -> usize {
+        self.next
+    }
+
+    /// How many items are held waiting for one before them.
+    pub fn held(&self) -> usize {
+        self.waiting.len()
+    }
+}
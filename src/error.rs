@@ -0,0 +1,74 @@
+use std::{fmt, io, path::PathBuf};
+
+use crate::{scenes::SceneError, AssetError, CameraError, LoadError};
+
+/// Everything that can go wrong loading assets and building scenes.
+#[derive(Debug)]
+pub enum Error {
+    Io { path: PathBuf, source: io::Error },
+    Decode { path: PathBuf, message: String },
+    UnsupportedFormat(PathBuf),
+    MissingAsset(AssetError),
+    UnknownScene(String),
+    Camera(CameraError),
+    Model(LoadError),
+    Scene(SceneError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => {
+                write!(f, "could not access {}: {}", path.display(), source)
+            }
+            Error::Decode { path, message } => {
+                write!(f, "could not decode {}: {}", path.display(), message)
+            }
+            Error::UnsupportedFormat(path) => {
+                write!(f, "unsupported file format: {}", path.display())
+            }
+            Error::MissingAsset(e) => write!(f, "{}", e),
+            Error::UnknownScene(name) => write!(f, "unknown scene '{}'", name),
+            Error::Camera(e) => write!(f, "camera: {}", e),
+            Error::Model(e) => write!(f, "{}", e),
+            Error::Scene(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::MissingAsset(e) => Some(e),
+            Error::Camera(e) => Some(e),
+            Error::Model(e) => Some(e),
+            Error::Scene(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<AssetError> for Error {
+    fn from(e: AssetError) -> Self {
+        Error::MissingAsset(e)
+    }
+}
+
+impl From<CameraError> for Error {
+    fn from(e: CameraError) -> Self {
+        Error::Camera(e)
+    }
+}
+
+impl From<LoadError> for Error {
+    fn from(e: LoadError) -> Self {
+        Error::Model(e)
+    }
+}
+
+impl From<SceneError> for Error {
+    fn from(e: SceneError) -> Self {
+        Error::Scene(e)
+    }
+}
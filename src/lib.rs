@@ -0,0 +1,16 @@
+pub mod camera;
+pub mod core;
+pub mod error;
+pub mod models;
+pub mod surfaces;
+
+pub mod scenes;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use camera::*;
+pub use core::*;
+pub use error::*;
+pub use models::*;
+pub use surfaces::*;
@@ -1,28 +1,1139 @@
-pub mod camera;
-pub mod core;
-pub mod models;
-pub mod surfaces;
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process,
+    sync::{mpsc, Arc},
+    thread,
+    time::Duration,
+};
 
-pub mod scenes;
+use clap::{Parser, Subcommand};
+use ray_tracer::{
+    add_search_path, count_trace_stats, log, open_video, scenes, set_load_listener, set_verbosity,
+    sidecar_path, Accumulation, AdaptiveSampling, Aovs, BilateralFilter, DownscaleFilter,
+    Environment, Error, Exposure, FfmpegSettings, ImageBuffer, LightSampling, LoadListener,
+    LoadReport, Projection, RegionOutput, RenderStats, SamplerKind, Sky, SnapshotEvery, StereoMode,
+    StereoSettings, Verbosity,
+};
 
-pub use camera::*;
-pub use core::*;
-pub use models::*;
-pub use surfaces::*;
+#[derive(Parser)]
+#[command(
+    about = "A simple ray tracer writing PPM images to stdout, or PNG and PPM files",
+    args_conflicts_with_subcommands = true
+)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
 
-use std::panic;
+    /// Name of the built-in scene to render (see --list)
+    #[arg(default_value = "cornell_smoke")]
+    scene: String,
+
+    /// List the built-in scenes and exit
+    #[arg(long)]
+    list: bool,
+
+    /// Load the scene from a JSON or TOML description file
+    #[arg(long)]
+    scene_file: Option<PathBuf>,
+
+    /// Refuse to render scenes with validation warnings, not just errors
+    #[arg(long)]
+    deny_warnings: bool,
+
+    /// Also look for textures and meshes in this directory (repeatable)
+    #[arg(long = "asset-path", value_name = "DIR")]
+    asset_paths: Vec<PathBuf>,
+
+    /// Write the image to this PNG or PPM file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Also write the render's settings to this JSON file
+    #[arg(long, value_name = "FILE", num_args = 0..=1, default_missing_value = "render.json")]
+    sidecar: Option<PathBuf>,
+
+    /// Seed the samples, so the render can be repeated exactly (default: a random seed)
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Add the render's samples to this accumulation file, starting it if it doesn't exist
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["preview", "heat_map"])]
+    accumulate: Option<PathBuf>,
+
+    /// Also write each pixel's standard error to this image, showing where the render is noisiest
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["preview", "heat_map", "snapshot_every"])]
+    error_image: Option<PathBuf>,
+
+    /// Write the render so far to FILE every INTERVAL: a time such as 60s, 5m, or 500ms, or a
+    /// number of passes of one sample per pixel such as 8passes
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["INTERVAL", "FILE"],
+        conflicts_with_all = ["preview", "heat_map", "accumulate"]
+    )]
+    snapshot_every: Option<Vec<String>>,
+
+    /// Render only the pixels from column X0 and row Y0 up to X1 and Y1, as an image of just
+    /// that window; it lines up exactly with the full render at the same --seed
+    #[arg(
+        long,
+        num_args = 4,
+        value_names = ["X0", "Y0", "X1", "Y1"],
+        conflicts_with_all = ["preview", "heat_map", "accumulate", "snapshot_every", "error_image", "mattes"]
+    )]
+    crop: Option<Vec<usize>>,
+
+    /// Write a --crop at the size of the full image, black outside the window
+    #[arg(long, requires = "crop")]
+    crop_full_frame: bool,
+
+    /// Render a turntable of this many frames, the camera circling what it looks at, into --video
+    #[arg(
+        long,
+        value_name = "FRAMES",
+        requires = "video",
+        conflicts_with_all = ["preview", "heat_map", "accumulate", "snapshot_every", "error_image", "crop"]
+    )]
+    turntable: Option<usize>,
+
+    /// Write the animation to this file: an animated GIF for .gif, numbered PNGs in this
+    /// directory without an extension (frame_0001.png, ...), anything else through ffmpeg
+    #[arg(long, value_name = "FILE", requires = "turntable")]
+    video: Option<PathBuf>,
+
+    /// Frames per second of the --video
+    #[arg(long, default_value_t = 24, requires = "video")]
+    fps: u32,
+
+    /// Codec ffmpeg encodes the --video with
+    #[arg(long, default_value = "libx264", requires = "video")]
+    codec: String,
+
+    /// Show the render in a window as it goes, coarse to fine (needs the macroquad feature)
+    #[arg(long)]
+    preview: bool,
+
+    /// Write the scene as a JSON or TOML description file instead of rendering it
+    #[arg(long)]
+    export: Option<PathBuf>,
+
+    /// Print the scene's objects as an indented tree instead of rendering it
+    #[arg(long)]
+    describe: bool,
+
+    /// Render every job in a JSON or TOML manifest to its output file
+    #[arg(long)]
+    manifest: Option<PathBuf>,
+
+    /// Draw samples from this sampler: independent, halton, or sobol
+    #[arg(long)]
+    sampler: Option<SamplerKind>,
+
+    /// Render on this many threads (default, or 0: one per core)
+    #[arg(long, value_name = "N")]
+    threads: Option<u32>,
+
+    /// How hits pick the point, spot, and sun lights they send shadow rays toward: all, uniform, power, or tree
+    #[arg(long, value_name = "STRATEGY")]
+    light_sampling: Option<LightSampling>,
+
+    /// Offset each pixel's samples by a blue-noise mask, leaving fine-grained rather than blotchy noise
+    #[arg(long)]
+    blue_noise: bool,
+
+    /// Sample each pixel until the 95% confidence interval of its brightness is within TOLERANCE of it, e.g. 0.05
+    #[arg(long, value_name = "TOLERANCE")]
+    adaptive: Option<f64>,
+
+    /// Fewest samples an --adaptive render takes of each pixel
+    #[arg(long, value_name = "N", default_value_t = 16, requires = "adaptive")]
+    min_samples: u32,
+
+    /// Most samples an --adaptive render takes of each pixel (default: the scene's samples per pixel)
+    #[arg(long, value_name = "N", requires = "adaptive")]
+    max_samples: Option<u32>,
+
+    /// Draw the scene in parallel projection, the view WIDTH scene units across
+    #[arg(long, value_name = "WIDTH")]
+    orthographic: Option<f64>,
+
+    /// Render a 2:1 panorama of everything around the camera
+    #[arg(long, conflicts_with_all = ["orthographic", "fisheye"])]
+    equirectangular: bool,
+
+    /// Render through an equidistant fisheye lens FOV degrees across
+    #[arg(long, value_name = "FOV", conflicts_with = "orthographic")]
+    fisheye: Option<f64>,
+
+    /// Render FACTOR times wider and taller, then shrink the image back down
+    #[arg(long, value_name = "FACTOR", value_parser = clap::value_parser!(u32).range(1..))]
+    supersample: Option<u32>,
+
+    /// Shrink supersampled renders with this filter: box or lanczos3
+    #[arg(long, value_name = "FILTER", requires = "supersample")]
+    downscale_filter: Option<DownscaleFilter>,
+
+    /// Most diffuse bounces a path may take (default: up to the max depth)
+    #[arg(long, value_name = "BOUNCES")]
+    diffuse_depth: Option<i32>,
+
+    /// Most glossy bounces a path may take
+    #[arg(long, value_name = "BOUNCES")]
+    glossy_depth: Option<i32>,
+
+    /// Most mirror reflections a path may take
+    #[arg(long, value_name = "BOUNCES")]
+    specular_depth: Option<i32>,
+
+    /// Most refractions a path may take
+    #[arg(long, value_name = "BOUNCES")]
+    transmission_depth: Option<i32>,
+
+    /// End paths at random once they have bounced DEPTH times (default: 3), the dimmer the likelier
+    #[arg(long, value_name = "DEPTH", num_args = 0..=1, default_missing_value = "3")]
+    russian_roulette: Option<i32>,
+
+    /// Light the scene with this equirectangular Radiance HDR image, seen in place of the background
+    #[arg(long, value_name = "FILE", conflicts_with = "sky")]
+    environment: Option<PathBuf>,
+
+    /// Multiply the --environment image's radiance by this
+    #[arg(
+        long,
+        value_name = "SCALE",
+        default_value_t = 1.0,
+        requires = "environment"
+    )]
+    environment_strength: f64,
+
+    /// Light the scene with a procedural sun and sky at this hour of the day, from 0 to 24
+    #[arg(long, value_name = "HOUR")]
+    sky: Option<f64>,
+
+    /// How hazy the --sky is, from 2 for clear mountain air to 10 for a hazy summer day
+    #[arg(long, default_value_t = 3.0, requires = "sky")]
+    turbidity: f64,
+
+    /// Leave out the background and environment seen directly, writing a PNG with an alpha
+    /// channel rather than compositing the render over the scene's backplate
+    #[arg(long, conflicts_with = "backplate")]
+    transparent: bool,
+
+    /// Composite the render over this image, stretched to fit, with shadow catchers darkening it
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["heat_map", "turntable"])]
+    backplate: Option<PathBuf>,
+
+    /// Brighten or darken the image before writing it: a multiplier, or auto to bring its
+    /// log-average luminance to middle gray (auto:KEY for another), recording the multiplier
+    #[arg(long, value_name = "EXPOSURE", conflicts_with_all = ["heat_map", "turntable"])]
+    exposure: Option<Exposure>,
+
+    /// Also write the coverage of the objects tagged NAME beside --output, as
+    /// OUTPUT.matte-NAME.png, with everything else in OUTPUT.matte-background.png
+    #[arg(
+        long = "matte",
+        value_name = "NAME",
+        requires = "output",
+        conflicts_with_all = ["heat_map", "turntable", "accumulate"]
+    )]
+    mattes: Vec<String>,
+
+    /// Pack up to three --matte names into the channels of OUTPUT.mattes.png instead
+    #[arg(long, requires = "mattes")]
+    pack_mattes: bool,
+
+    /// Also write the normal, distance, and albedo of what each pixel sees first beside
+    /// --output, as OUTPUT.normal.png, OUTPUT.depth.png, and OUTPUT.albedo.png
+    #[arg(
+        long,
+        requires = "output",
+        conflicts_with_all = ["preview", "heat_map", "accumulate", "snapshot_every", "error_image", "crop", "turntable"]
+    )]
+    aovs: bool,
+
+    /// Map distances from NEAR to FAR onto black through white in the --aovs depth pass
+    /// (default: 0 to the farthest hit)
+    #[arg(long, num_args = 2, value_names = ["NEAR", "FAR"], requires = "aovs")]
+    depth_range: Option<Vec<f64>>,
+
+    /// Smooth the noise out of the image with a bilateral filter guided by the normal and
+    /// albedo passes, which are rendered along with it
+    #[arg(
+        long,
+        conflicts_with_all = ["preview", "heat_map", "accumulate", "snapshot_every", "error_image", "crop", "turntable"]
+    )]
+    denoise: bool,
+
+    /// The standard deviations of --denoise's falloff with distance, in pixels, and with
+    /// difference in color (default: 2 and 1)
+    #[arg(long, num_args = 2, value_names = ["SPATIAL", "RANGE"], requires = "denoise")]
+    denoise_sigmas: Option<Vec<f64>>,
+
+    /// Render a view for each eye, composed side_by_side or as an anaglyph
+    #[arg(long, value_name = "MODE", requires = "ipd")]
+    stereo: Option<StereoMode>,
+
+    /// Distance between the eyes of a stereo render, in scene units
+    #[arg(long, value_name = "DISTANCE", requires = "stereo")]
+    ipd: Option<f64>,
+
+    /// Turn the stereo eyes toward the camera's look-at point
+    #[arg(long, requires = "stereo")]
+    converge: bool,
+
+    /// Render a false-color map of BVH nodes visited per primary ray instead
+    #[arg(long)]
+    heat_map: bool,
+
+    /// Node visits drawn in the hottest heat-map color (default: the busiest pixel)
+    #[arg(long, value_name = "VISITS", requires = "heat_map")]
+    heat_map_max: Option<u32>,
+
+    /// Also count primary and path rays, BVH node visits, and primitives
+    /// intersected by type for the render summary, at some cost in speed
+    #[arg(long, conflicts_with_all = ["preview", "heat_map", "turntable"])]
+    trace_stats: bool,
+
+    /// Number of manifest jobs or animation frames to render at once
+    #[arg(long, default_value_t = 1)]
+    parallel_jobs: usize,
+
+    /// How much to report on stderr: quiet, normal, or verbose
+    #[arg(long, value_name = "LEVEL", conflicts_with_all = ["verbose", "quiet"])]
+    verbosity: Option<Verbosity>,
+
+    /// Same as --verbosity verbose: also time each stage, show progress while
+    /// meshes load and rows render, and report what each mesh holds
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Same as --verbosity quiet: only report errors
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the settings a render was made with, from the image or its JSON sidecar
+    Info { file: PathBuf },
+    /// Compare two PNG or PPM renders, exiting with 1 if any pixel differs by more than the threshold
+    Diff {
+        first: PathBuf,
+        second: PathBuf,
+        /// Write the differences in false color to this image
+        #[arg(long, value_name = "FILE")]
+        out: Option<PathBuf>,
+        /// Largest channel difference, on a 0-1 scale, a pixel may have and still match
+        #[arg(long, default_value_t = 2.0 / 255.0)]
+        threshold: f64,
+        /// Difference drawn in the hottest color (default: the largest difference)
+        #[arg(long, value_name = "ERROR", requires = "out")]
+        scale: Option<f64>,
+        /// Compare linear color instead of the gamma-corrected values in the files
+        #[arg(long)]
+        linear: bool,
+    },
+    /// Add up accumulation files of the same scene and write the image they make
+    Merge {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Write the merged image to this PNG or PPM file, or the merged samples to a .acc file
+        #[arg(long, value_name = "FILE")]
+        out: PathBuf,
+    },
+}
+
+/// Compares two image files for the `diff` command, returning whether they match.
+fn diff_images(
+    first: &Path,
+    second: &Path,
+    out: Option<&Path>,
+    threshold: f64,
+    scale: Option<f64>,
+    linear: bool,
+) -> Result<bool, String> {
+    let load = |path: &Path| {
+        ImageBuffer::load(path)
+            .map(|image| if linear { image } else { image.encoded() })
+            .map_err(|e| format!("could not read {}: {}", path.display(), e))
+    };
+    let report = load(first)?
+        .diff(&load(second)?)
+        .map_err(|e| e.to_string())?;
+    let over = report.pixels_over(threshold);
+    println!("{}", report);
+    println!(
+        "pixels over {:.4}: {} of {}",
+        threshold,
+        over,
+        report.errors.len()
+    );
+    if let Some(out) = out {
+        report
+            .image(scale)
+            .save(out)
+            .map_err(|e| format!("could not write {}: {}", out.display(), e))?;
+    }
+    Ok(over == 0)
+}
+
+/// Adds up accumulation files for the `merge` command and writes the result.
+fn merge_accumulations(files: &[PathBuf], out: &Path) -> Result<(), String> {
+    let parts = files
+        .iter()
+        .map(|path| {
+            Accumulation::load(path)
+                .map_err(|e| format!("could not read {}: {}", path.display(), e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let merged = Accumulation::merge(&parts).map_err(|e| e.to_string())?;
+    let saved = if out.extension().is_some_and(|e| e == "acc") {
+        merged.save(out)
+    } else {
+        merged.image().save(out)
+    };
+    saved.map_err(|e| format!("could not write {}: {}", out.display(), e))?;
+    log(
+        Verbosity::Normal,
+        format_args!(
+            "merged {} files: at least {} samples per pixel, {}",
+            parts.len(),
+            merged.min_samples(),
+            merged.convergence()
+        ),
+    );
+    Ok(())
+}
+
+/// Adds a render of `scene` to the accumulation file at `path`, or starts
+/// one there, returning the image of all its samples.
+fn render_accumulation(
+    scene: &scenes::Scene,
+    path: &Path,
+) -> Result<(ImageBuffer, RenderStats, Accumulation), String> {
+    let mut accumulation = if path.exists() {
+        let existing = Accumulation::load(path)
+            .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+        if existing.fingerprint != scene.fingerprint() {
+            return Err(format!(
+                "{} holds samples of a different scene or settings",
+                path.display()
+            ));
+        }
+        existing
+    } else {
+        scene.new_accumulation()
+    };
+    let mut result = Ok(());
+    let (image, stats) = scene.camera.time_render(&scene.world, || {
+        result = scene.accumulate(&mut accumulation);
+        accumulation.image()
+    });
+    result.map_err(|e| e.to_string())?;
+    accumulation
+        .save(path)
+        .map_err(|e| format!("could not write {}: {}", path.display(), e))?;
+    Ok((image, stats, accumulation))
+}
+
+/// Writes the standard error of each pixel of `accumulation` to `path`.
+fn save_error_image(accumulation: &Accumulation, path: &Path) -> Result<(), String> {
+    accumulation
+        .standard_error()
+        .save(path)
+        .map_err(|e| format!("could not write {}: {}", path.display(), e))
+}
+
+/// Renders the mattes `names` and writes them beside `output`, one per
+/// name and one for the background, or packed into one image's channels.
+fn save_mattes(
+    scene: &scenes::Scene,
+    names: &[String],
+    output: &Path,
+    packed: bool,
+) -> Result<(), String> {
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    let mattes = scene.render_mattes(&names);
+    let images = if packed {
+        let image = mattes
+            .packed(&names)
+            .ok_or("at most three mattes can be packed into one image")?;
+        vec![(sidecar_path(output, "mattes"), image)]
+    } else {
+        names
+            .iter()
+            .map(|&name| Some(name))
+            .chain([None])
+            .filter_map(|name| {
+                let suffix = format!("matte-{}", name.unwrap_or("background"));
+                Some((sidecar_path(output, &suffix), mattes.image(name)?))
+            })
+            .collect()
+    };
+    for (path, image) in images {
+        image
+            .save(&path)
+            .map_err(|e| format!("could not write {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Draws a progress bar on stderr while meshes load, then prints their reports.
+struct VerboseLoading;
+
+impl LoadListener for VerboseLoading {
+    fn progress(&self, path: &Path, faces: usize, total: usize) {
+        const WIDTH: usize = 30;
+        let filled = WIDTH * faces / total.max(1);
+        eprint!(
+            "\rloading {} [{}{}] {}/{} faces",
+            path.display(),
+            "#".repeat(filled),
+            " ".repeat(WIDTH - filled),
+            faces,
+            total
+        );
+        if faces == total {
+            eprintln!();
+        }
+    }
+    fn loaded(&self, report: &LoadReport) {
+        eprintln!("{}", report);
+    }
+}
+
+/// Renders progressively on another thread, showing each pass in a window
+/// until it is closed. The render carries on if the window closes first.
+/// Clicking the window focuses the camera on what was clicked, and dragging
+/// with the right button moves the object under the cursor; either starts
+/// the render over.
+#[cfg(feature = "macroquad")]
+fn render_with_preview(scene: &mut scenes::Scene) -> (ImageBuffer, RenderStats) {
+    use macroquad::prelude::*;
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Mutex,
+    };
+
+    /// What the window asks of the render, in pixels of the image.
+    enum Input {
+        Focus(i32, i32),
+        Grab(i32, i32),
+        Drag(i32, i32),
+        Release,
+    }
+
+    let latest: Arc<Mutex<Option<ImageBuffer>>> = Arc::default();
+    let (input, inputs) = mpsc::channel();
+    let restart = Arc::new(AtomicBool::new(false));
+    let (width, height) = (scene.camera.image_width, scene.camera.image_height());
+    thread::scope(|scope| {
+        let render = {
+            let (latest, restart) = (latest.clone(), restart.clone());
+            scope.spawn(move || {
+                let mut grab = None;
+                loop {
+                    restart.store(false, Ordering::Relaxed);
+                    for input in inputs.try_iter() {
+                        match input {
+                            Input::Focus(x, y) => {
+                                match scene.camera.focus_on_pixel(&scene.world, x, y) {
+                                    Some(distance) => log(
+                                        Verbosity::Normal,
+                                        format_args!("focusing {:.3} away", distance),
+                                    ),
+                                    None => log(Verbosity::Normal, "nothing there to focus on"),
+                                }
+                            }
+                            Input::Grab(x, y) => {
+                                // Only worlds being edited pay for instancing.
+                                scene.instance_world();
+                                grab = scene.grab_instance(x, y);
+                                if grab.is_none() {
+                                    log(Verbosity::Normal, "nothing there to move");
+                                }
+                            }
+                            Input::Drag(x, y) => {
+                                if let Some(grab) = &grab {
+                                    if let Some(update) = scene.drag_instance(grab, x, y) {
+                                        log(Verbosity::Verbose, format_args!("{:?}", update));
+                                    }
+                                }
+                            }
+                            Input::Release => grab = None,
+                        }
+                    }
+                    let mut finished = false;
+                    let (image, stats) = scene.camera.time_render(&scene.world, || {
+                        let image = scene.camera.render_progressive_until(
+                            &scene.world,
+                            &scene.lights,
+                            &restart,
+                            &mut |preview| *latest.lock().unwrap() = Some(preview.image.clone()),
+                        );
+                        finished = image.is_some();
+                        image.unwrap_or_else(|| ImageBuffer::new(0, 0))
+                    });
+                    if finished {
+                        return (image, stats);
+                    }
+                }
+            })
+        };
+
+        let conf = macroquad::window::Conf {
+            window_title: "ray tracer preview".to_string(),
+            window_width: width,
+            window_height: height,
+            ..Default::default()
+        };
+        macroquad::Window::from_config(conf, async move {
+            let mut shown: Option<Texture2D> = None;
+            let mut dragged_to = None;
+            while !is_quit_requested() {
+                if let Some(image) = latest.lock().unwrap().take() {
+                    let texture = Texture2D::from_rgba8(
+                        image.width as u16,
+                        image.height as u16,
+                        &image.to_rgba8(),
+                    );
+                    texture.set_filter(FilterMode::Nearest);
+                    shown = Some(texture);
+                }
+                let (x, y) = mouse_position();
+                let x = (x / screen_width() * width as f32) as i32;
+                let y = (y / screen_height() * height as f32) as i32;
+                // A render that has ended has nothing to start over.
+                let send = |message| {
+                    if input.send(message).is_ok() {
+                        restart.store(true, Ordering::Relaxed);
+                    }
+                };
+                if is_mouse_button_pressed(MouseButton::Left) {
+                    send(Input::Focus(x, y));
+                }
+                if is_mouse_button_pressed(MouseButton::Right) {
+                    // Picking up changes nothing to render yet.
+                    let _ = input.send(Input::Grab(x, y));
+                    dragged_to = Some((x, y));
+                } else if is_mouse_button_down(MouseButton::Right) {
+                    if dragged_to != Some((x, y)) {
+                        send(Input::Drag(x, y));
+                        dragged_to = Some((x, y));
+                    }
+                } else if dragged_to.take().is_some() {
+                    let _ = input.send(Input::Release);
+                }
+                clear_background(BLACK);
+                if let Some(texture) = &shown {
+                    let size = vec2(screen_width(), screen_height());
+                    let params = DrawTextureParams {
+                        dest_size: Some(size),
+                        ..Default::default()
+                    };
+                    draw_texture_ex(texture, 0.0, 0.0, WHITE, params);
+                }
+                next_frame().await;
+            }
+        });
+        if !render.is_finished() {
+            log(Verbosity::Normal, "preview closed; still rendering");
+        }
+        // The image's metadata records the focus clicked on and the
+        // objects moved.
+        render.join().unwrap()
+    })
+}
+
+#[cfg(not(feature = "macroquad"))]
+fn render_with_preview(_: &mut scenes::Scene) -> (ImageBuffer, RenderStats) {
+    eprintln!("error: this build has no preview window; enable the `macroquad` feature");
+    process::exit(2);
+}
+
+/// Renders `scene`, writing snapshots of its progress to `path` from a
+/// thread of their own, so the render only stops to copy its samples. A
+/// snapshot due while the last is still being written is skipped.
+fn render_with_snapshots(
+    scene: &scenes::Scene,
+    every: SnapshotEvery,
+    path: &Path,
+) -> (ImageBuffer, RenderStats) {
+    let (sender, receiver) = mpsc::sync_channel::<(Accumulation, u32)>(1);
+    thread::scope(|scope| {
+        scope.spawn(move || {
+            for (accumulation, passes) in receiver {
+                match finish(scene, accumulation.image(), None)
+                    .0
+                    .save_atomically(path)
+                {
+                    Ok(()) => log(
+                        Verbosity::Verbose,
+                        format_args!("snapshot after {} passes: {}", passes, path.display()),
+                    ),
+                    Err(e) => log(
+                        Verbosity::Quiet,
+                        format_args!("error: could not write {}: {}", path.display(), e),
+                    ),
+                }
+            }
+        });
+        scene.camera.time_render(&scene.world, || {
+            let image = scene.render_with_snapshots(every, &mut |snapshot| {
+                let _ = sender.try_send((snapshot.accumulation.clone(), snapshot.passes));
+            });
+            drop(sender);
+            image
+        })
+    })
+}
+
+fn scene_list() -> String {
+    let mut text = String::from("Available scenes:");
+    for entry in scenes::list() {
+        text += &format!("\n  {:<20} {}", entry.name, entry.description);
+    }
+    text
+}
+
+/// `image` finished as `Scene::finish_image` does, logging the exposure
+/// multiplier if it chose one.
+fn finish(
+    scene: &scenes::Scene,
+    image: ImageBuffer,
+    aovs: Option<&Aovs>,
+) -> (ImageBuffer, Option<f64>) {
+    let (image, exposure) = scene.finish_image(image, aovs);
+    if let Some(multiplier) = exposure {
+        log(
+            Verbosity::Verbose,
+            format_args!("exposure: multiplied by {}", multiplier),
+        );
+    }
+    (image, exposure)
+}
 
 fn main() {
-    match 8 {
-        0 => scenes::material_spheres(),
-        1 => scenes::checkered_spheres(),
-        2 => scenes::earthmap(),
-        3 => scenes::quads(),
-        4 => scenes::planars(),
-        5 => scenes::obj_mesh(),
-        6 => scenes::simple_light(),
-        7 => scenes::cornell_box(),
-        8 => scenes::cornell_smoke(),
-        _ => panic!("Invalid scene number"),
+    let args = Args::parse();
+
+    if let Some(Command::Info { file }) = &args.command {
+        match scenes::RenderMetadata::read(file) {
+            Ok(metadata) => println!("{}", metadata),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(Command::Diff {
+        first,
+        second,
+        out,
+        threshold,
+        scale,
+        linear,
+    }) = &args.command
+    {
+        match diff_images(first, second, out.as_deref(), *threshold, *scale, *linear) {
+            Ok(true) => return,
+            Ok(false) => process::exit(1),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                process::exit(2);
+            }
+        }
+    }
+
+    if let Some(Command::Merge { files, out }) = &args.command {
+        if let Err(e) = merge_accumulations(files, out) {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    if args.list {
+        println!("{}", scene_list());
+        return;
+    }
+
+    add_search_path(args.asset_paths);
+    let verbosity = match (args.verbosity, args.verbose, args.quiet) {
+        (Some(verbosity), _, _) => verbosity,
+        (None, true, _) => Verbosity::Verbose,
+        (None, _, true) => Verbosity::Quiet,
+        (None, false, false) => Verbosity::Normal,
+    };
+    set_verbosity(verbosity);
+    if verbosity == Verbosity::Verbose {
+        set_load_listener(Some(Arc::new(VerboseLoading)));
+    }
+
+    if let Some(path) = args.manifest {
+        let manifest = match scenes::RenderManifest::from_path(&path) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+        };
+        let reports = manifest.run(args.parallel_jobs);
+        // Failures are shown even when quiet.
+        let failed = reports.iter().any(|report| report.result.is_err());
+        let level = if failed {
+            Verbosity::Quiet
+        } else {
+            Verbosity::Normal
+        };
+        log(level, scenes::summary_table(&reports));
+        if failed {
+            process::exit(1);
+        }
+        return;
+    }
+
+    let scene = match &args.scene_file {
+        Some(path) => scenes::Scene::from_path(path).map_err(Error::from),
+        None => scenes::build(&args.scene),
+    };
+    let mut scene = match scene {
+        Ok(scene) => scene,
+        Err(e @ Error::UnknownScene(_)) => {
+            eprintln!("error: {}", e);
+            eprintln!("{}", scene_list());
+            process::exit(2);
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    };
+
+    if let Some(sampler) = args.sampler {
+        scene.camera.set_sampler(sampler);
+    }
+    if let Some(seed) = args.seed {
+        scene.camera.set_seed(Some(seed));
+    }
+    if let Some(threads) = args.threads {
+        scene.camera.set_threads(threads as usize);
+    }
+    if let Some(sampling) = args.light_sampling {
+        scene.lights.set_sampling(sampling);
+    }
+    if args.blue_noise {
+        scene.camera.set_blue_noise(true);
+    }
+    if let Some(width) = args.orthographic {
+        scene
+            .camera
+            .set_projection(Projection::Orthographic { width });
+    }
+    if args.equirectangular {
+        scene.camera.set_projection(Projection::Equirectangular);
+    }
+    if let Some(fov) = args.fisheye {
+        scene.camera.set_projection(Projection::Fisheye { fov });
+    }
+    if let Some(tolerance) = args.adaptive {
+        let max_samples = args
+            .max_samples
+            .unwrap_or(scene.camera.aa_samples.max(0) as u32);
+        scene.camera.set_adaptive(Some(AdaptiveSampling::new(
+            tolerance,
+            args.min_samples,
+            max_samples,
+        )));
+    }
+    let mut bounces = scene.camera.bounce_limits;
+    bounces.diffuse = args.diffuse_depth.or(bounces.diffuse);
+    bounces.glossy = args.glossy_depth.or(bounces.glossy);
+    bounces.specular = args.specular_depth.or(bounces.specular);
+    bounces.transmission = args.transmission_depth.or(bounces.transmission);
+    scene.camera.set_bounce_limits(bounces);
+    if let Some(depth) = args.russian_roulette {
+        scene.camera.set_russian_roulette(true, depth);
+    }
+    if let Some(path) = &args.environment {
+        match Environment::load(path) {
+            Ok(mut environment) => {
+                environment.strength = args.environment_strength;
+                scene.set_environment(environment);
+            }
+            Err(e) => {
+                eprintln!("error: could not load {}: {}", path.display(), e);
+                process::exit(1);
+            }
+        }
+    }
+    if let Some(hour) = args.sky {
+        scene.set_sky(&Sky::from_time_of_day(hour, args.turbidity));
+    }
+    if args.transparent {
+        scene.camera.set_transparent(true);
+        scene.backplate = None;
+    }
+    if let Some(path) = &args.backplate {
+        match ImageBuffer::load(path) {
+            Ok(plate) => {
+                scene.camera.set_transparent(true);
+                scene.backplate = Some(plate);
+            }
+            Err(e) => {
+                eprintln!("error: could not load {}: {}", path.display(), e);
+                process::exit(1);
+            }
+        }
+    }
+    if let Some(exposure) = args.exposure {
+        scene.camera.set_exposure(exposure);
+    }
+    if let Some(factor) = args.supersample {
+        let filter = args.downscale_filter.unwrap_or_default();
+        scene.camera.set_supersample(factor, filter);
+    }
+    if let (Some(mode), Some(ipd)) = (args.stereo, args.ipd) {
+        scene.camera.set_stereo(Some(StereoSettings {
+            ipd,
+            mode,
+            converge: args.converge,
+        }));
+    }
+
+    if args.describe {
+        scene.print_hierarchy();
+        return;
+    }
+
+    if let Some(path) = args.export {
+        if let Err(e) = scene.describe().and_then(|desc| desc.save(&path)) {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    let issues = scene.validate();
+    for issue in &issues {
+        let level = match issue.severity {
+            scenes::Severity::Error => Verbosity::Quiet,
+            scenes::Severity::Warning => Verbosity::Normal,
+        };
+        log(level, issue);
+    }
+    if issues
+        .iter()
+        .any(|issue| issue.severity == scenes::Severity::Error || args.deny_warnings)
+    {
+        process::exit(1);
+    }
+
+    if args.heat_map {
+        let heat_map = scene.render_heat_map(args.heat_map_max);
+        log(
+            Verbosity::Normal,
+            format_args!(
+                "heat map: {:.1} node visits per ray on average, red at {}",
+                heat_map.mean_node_visits(),
+                heat_map.max
+            ),
+        );
+        heat_map
+            .image
+            .write_ppm(io::stdout().lock())
+            .expect("could not write the image to stdout");
+        return;
+    }
+
+    // Pick the seed up front, so the metadata can record it.
+    if scene.camera.seed.is_none() {
+        scene.camera.set_seed(Some(rand::random()));
+    }
+    let source = match &args.scene_file {
+        Some(path) => path.display().to_string(),
+        None => args.scene.clone(),
+    };
+    if args.trace_stats {
+        count_trace_stats();
+    }
+    if let Some(path) = &args.accumulate {
+        let (image, mut stats, accumulation) = match render_accumulation(&scene, path) {
+            Ok(rendered) => rendered,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+        };
+        let samples = accumulation.min_samples();
+        stats.convergence = Some(accumulation.convergence());
+        if let Some(error_image) = &args.error_image {
+            if let Err(e) = save_error_image(&accumulation, error_image) {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+        }
+        log(
+            Verbosity::Normal,
+            format_args!("{} now holds {} samples per pixel", path.display(), samples),
+        );
+        if let Some(output) = &args.output {
+            let (image, exposure) = finish(&scene, image, None);
+            let mut camera = scene.camera.clone();
+            camera.set_aa_samples(samples as i32);
+            let mut metadata = scenes::RenderMetadata::new(source, &camera, &stats);
+            metadata.exposure = exposure;
+            if let Err(e) = metadata.save_image(&image, output) {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+        }
+        log(Verbosity::Normal, stats);
+        return;
+    }
+
+    if let (Some(frames), Some(path)) = (args.turntable, &args.video) {
+        let settings = FfmpegSettings {
+            fps: args.fps,
+            codec: args.codec.clone(),
+            ..FfmpegSettings::default()
+        };
+        let (width, height) = scene.camera.output_size();
+        let turntable = scenes::Turntable::new(frames);
+        let result = open_video(path, width, height, &settings).and_then(|sink| {
+            scene.render_animation(
+                frames,
+                args.parallel_jobs,
+                &|frame| turntable.camera_at(&scene.camera, frame),
+                sink,
+            )
+        });
+        let times = result.unwrap_or_else(|e| {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        });
+        let total: Duration = times.iter().sum();
+        log(
+            Verbosity::Normal,
+            format_args!(
+                "{} frames written to {}, rendered in {:.2?} each on average",
+                frames,
+                path.display(),
+                total / frames.max(1) as u32
+            ),
+        );
+        return;
+    }
+
+    let snapshots = args.snapshot_every.as_deref().map(|snapshot| {
+        let every: SnapshotEvery = snapshot[0].parse().unwrap_or_else(|e| {
+            eprintln!("error: {}", e);
+            process::exit(2);
+        });
+        (every, PathBuf::from(&snapshot[1]))
+    });
+    if args.pack_mattes && args.mattes.len() > 3 {
+        eprintln!("error: at most three mattes can be packed into one image");
+        process::exit(2);
+    }
+    if args.error_image.is_some() && scene.camera.stereo.is_some() {
+        eprintln!("error: stereo renders have no error image");
+        process::exit(2);
+    }
+    if args.error_image.is_some() && scene.camera.supersample > 1 {
+        eprintln!("error: supersampled renders have no error image");
+        process::exit(2);
+    }
+    if args.denoise {
+        let filter = match args.denoise_sigmas.as_deref() {
+            Some(&[spatial, range]) => BilateralFilter::new(spatial, range),
+            _ => BilateralFilter::default(),
+        };
+        if !filter.is_valid() {
+            eprintln!("error: --denoise-sigmas must not be negative");
+            process::exit(2);
+        }
+        scene.camera.add_post_process(Arc::new(filter));
+    }
+    let mut aovs = None;
+    let (image, stats) = if args.preview {
+        render_with_preview(&mut scene)
+    } else if let Some((every, path)) = &snapshots {
+        render_with_snapshots(&scene, *every, path)
+    } else if let Some([x0, y0, x1, y1]) = args.crop.as_deref() {
+        let output = match args.crop_full_frame {
+            true => RegionOutput::FullFrame,
+            false => RegionOutput::Cropped,
+        };
+        scene.camera.time_render(&scene.world, || {
+            scene.render_region(*x0..*x1, *y0..*y1, output)
+        })
+    } else if args.aovs || args.denoise {
+        let mut passes = None;
+        let rendered = scene.camera.time_render(&scene.world, || {
+            let (image, aovs) = scene.render_with_aovs();
+            passes = Some(aovs);
+            image
+        });
+        aovs = passes;
+        rendered
+    } else if let Some(path) = &args.error_image {
+        let mut samples = None;
+        let (image, mut stats) = scene.camera.time_render(&scene.world, || {
+            let accumulation = scene
+                .render_accumulation()
+                .expect("mono cameras accumulate");
+            let image = accumulation.image();
+            samples = Some(accumulation);
+            image
+        });
+        let samples = samples.expect("the render ran");
+        stats.convergence = Some(samples.convergence());
+        if scene.camera.adaptive.is_some() {
+            stats.mean_samples = Some(samples.mean_samples());
+        }
+        if let Err(e) = save_error_image(&samples, path) {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+        (image, stats)
+    } else {
+        scene.render_timed()
+    };
+    let (image, exposure) = finish(&scene, image, aovs.as_ref());
+    let mut metadata = scenes::RenderMetadata::new(source, &scene.camera, &stats);
+    metadata.exposure = exposure;
+    let saved = match &args.output {
+        Some(path) => metadata.save_image(&image, path),
+        None => {
+            image
+                .write_ppm_with_metadata(io::stdout().lock(), &metadata.to_json())
+                .expect("could not write the image to stdout");
+            Ok(())
+        }
+    };
+    if let Err(e) = saved.and_then(|()| match &args.sidecar {
+        Some(path) => metadata.save_sidecar(path),
+        None => Ok(()),
+    }) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+    if let (false, Some(output)) = (args.mattes.is_empty(), &args.output) {
+        if let Err(e) = save_mattes(&scene, &args.mattes, output, args.pack_mattes) {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    }
+    if let (true, Some(aovs), Some(output)) = (args.aovs, &aovs, &args.output) {
+        let depth_range = args.depth_range.as_deref().map(|range| range[0]..range[1]);
+        if let Err(e) = aovs.save(output, depth_range) {
+            eprintln!("error: could not write the AOV passes: {}", e);
+            process::exit(1);
+        }
     }
+    log(Verbosity::Normal, stats);
 }
@@ -1,10 +1,36 @@
 pub mod hittable;
 pub mod shapes;
 
+pub mod background;
 pub mod bounds;
+pub mod environment;
+pub mod grid;
+pub mod heightfield;
+pub mod light_selection;
+pub mod lights;
+pub mod loaders;
+pub mod matte;
+pub mod mesh;
+pub mod portal;
+pub mod primitive;
+pub mod sky;
+pub mod tlas;
 pub mod volumes;
 
+pub use background::*;
 pub use bounds::*;
+pub use environment::*;
+pub use grid::*;
+pub use heightfield::*;
 pub use hittable::*;
+pub use light_selection::*;
+pub use lights::*;
+pub use loaders::*;
+pub use matte::*;
+pub use mesh::*;
+pub use portal::*;
+pub use primitive::*;
 pub use shapes::*;
+pub use sky::*;
+pub use tlas::*;
 pub use volumes::*;
@@ -0,0 +1,60 @@
+use crate::{color, scenes::BackgroundDesc, Color, Ray};
+
+/// What a ray that leaves the scene sees when there is no `Environment` to
+/// light it: a backdrop, evaluated per ray, that lights nothing but the
+/// paths that escape toward it.
+pub trait Background: Send + Sync {
+    /// The light seen looking along `ray` out of the scene.
+    fn value(&self, ray: &Ray) -> Color;
+
+    /// Describes the background for scene export, or `None` if it has no description.
+    fn describe(&self) -> Option<BackgroundDesc> {
+        None
+    }
+}
+
+/// The same color in every direction. Black, the default, keeps closed
+/// scenes such as the Cornell box lit by their own lights alone.
+pub struct SolidBackground(pub Color);
+
+impl Default for SolidBackground {
+    fn default() -> Self {
+        Self(color(0.0, 0.0, 0.0))
+    }
+}
+
+impl Background for SolidBackground {
+    fn value(&self, _ray: &Ray) -> Color {
+        self.0
+    }
+    fn describe(&self) -> Option<BackgroundDesc> {
+        Some(BackgroundDesc::Solid(self.0))
+    }
+}
+
+/// A sky blending from `bottom` looking straight down to `top` looking
+/// straight up, by height alone, like the first book's background: e.g.
+/// white to `color(0.5, 0.7, 1.0)`.
+pub struct GradientSky {
+    pub bottom: Color,
+    pub top: Color,
+}
+
+impl GradientSky {
+    pub fn new(bottom: Color, top: Color) -> Self {
+        Self { bottom, top }
+    }
+}
+
+impl Background for GradientSky {
+    fn value(&self, ray: &Ray) -> Color {
+        let a = 0.5 * (ray.direction.unit().y() + 1.0);
+        self.bottom * (1.0 - a) + self.top * a
+    }
+    fn describe(&self) -> Option<BackgroundDesc> {
+        Some(BackgroundDesc::Gradient {
+            bottom: self.bottom,
+            top: self.top,
+        })
+    }
+}
@@ -1,9 +1,14 @@
 use std::{
+    fmt, mem,
     ops::{Add, Range},
     sync::Arc,
 };
 
-use crate::{hittable::*, Interval, Point, Ray, Vec3};
+use crate::{
+    hittable::*,
+    scenes::{validate_duplicates, Describer, ObjectDesc, ValidationIssue},
+    Interval, Mat4, Point, Primitive, Ray, Sampler, Stage, Vec3,
+};
 
 #[derive(Clone, Copy, Debug)]
 pub struct BoundingBox {
@@ -22,6 +27,20 @@ impl Add<Vec3> for BoundingBox {
     }
 }
 
+impl fmt::Display for BoundingBox {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.intervals.iter().any(|i| i.start > i.end) {
+            return write!(f, "empty");
+        }
+        let [x, y, z] = self.intervals;
+        write!(
+            f,
+            "[{:.3}, {:.3}] x [{:.3}, {:.3}] x [{:.3}, {:.3}]",
+            x.start, x.end, y.start, y.end, z.start, z.end
+        )
+    }
+}
+
 impl BoundingBox {
     pub fn empty() -> Self {
         Self {
@@ -40,6 +59,22 @@ impl BoundingBox {
         let z = Interval::new(a.z().min(b.z()), a.z().max(b.z()));
         Self::new(x, y, z)
     }
+    /// The smallest box holding every point, or an empty box if there are none.
+    pub fn around(points: impl IntoIterator<Item = Point>) -> Self {
+        let mut intervals = [Interval::empty(); 3];
+        let mut any = false;
+        for p in points {
+            any = true;
+            for (axis, interval) in intervals.iter_mut().enumerate() {
+                *interval = Interval::from_pair(*interval, Interval::new(p[axis], p[axis]));
+            }
+        }
+        if !any {
+            return Self::empty();
+        }
+        let [x, y, z] = intervals;
+        Self::new(x, y, z)
+    }
     pub fn from_boxes(a: BoundingBox, b: BoundingBox) -> Self {
         let x = Interval::from_pair(a.intervals[0], b.intervals[0]);
         let y = Interval::from_pair(a.intervals[1], b.intervals[1]);
@@ -47,6 +82,35 @@ impl BoundingBox {
         Self::new(x, y, z)
     }
 
+    pub fn corners(&self) -> [Point; 8] {
+        std::array::from_fn(|corner| {
+            let pick = |axis: usize| {
+                let interval = self.intervals[axis];
+                if corner >> axis & 1 == 1 {
+                    interval.end
+                } else {
+                    interval.start
+                }
+            };
+            Vec3(pick(0), pick(1), pick(2))
+        })
+    }
+    /// The box around this one's corners placed by `transform`. It holds
+    /// whatever this box held, but can be much looser than the object.
+    pub fn transformed(&self, transform: &Mat4) -> Self {
+        if self.intervals.iter().any(|i| i.start > i.end) {
+            return Self::empty();
+        }
+        Self::around(self.corners().map(|p| transform.transform_point(p)))
+    }
+
+    /// The area of the box's faces, or 0 if it is empty: how likely a ray
+    /// through the scene is to pass through it.
+    pub fn surface_area(&self) -> f64 {
+        let [x, y, z] = self.intervals.map(|i| i.size().max(0.0));
+        2.0 * (x * y + y * z + z * x)
+    }
+
     pub fn longest_axis(&self) -> usize {
         if self.intervals[0].size() > self.intervals[1].size() {
             if self.intervals[0].size() > self.intervals[2].size() {
@@ -74,7 +138,7 @@ impl BoundingBox {
         if self.intervals[2].size() < delta {
             self.intervals[2] = self.intervals[2].expand(delta);
         }
-        self.clone()
+        *self
     }
 }
 
@@ -83,7 +147,7 @@ pub trait Bounds {
 }
 
 impl Bounds for BoundingBox {
-    fn hit(&self, ray: &Ray, t: Interval) -> bool {
+    fn hit(&self, ray: &Ray, mut t: Interval) -> bool {
         for i in 0..3 {
             let ax = self.intervals[i];
             let adinv = 1.0 / ray.direction[i];
@@ -92,7 +156,7 @@ impl Bounds for BoundingBox {
             let t1 = (ax.end - ray.origin[i]) * adinv;
 
             let (t0, t1) = (t0.min(t1), t0.max(t1));
-            let t = Interval::new(t.start.max(t0), t.end.min(t1));
+            t = Interval::new(t.start.max(t0), t.end.min(t1));
             if t.size() <= 0.0 {
                 return false;
             }
@@ -101,85 +165,280 @@ impl Bounds for BoundingBox {
     }
 }
 
+/// What one ray's traversal cost: the BVH nodes whose boxes it was tested
+/// against and the primitives it was intersected with.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TraversalStats {
+    pub node_visits: u32,
+    pub primitive_tests: u32,
+}
+
+/// The closer of two optional hits.
+fn closest<'a>(a: Option<HitRecord<'a>>, b: Option<HitRecord<'a>>) -> Option<HitRecord<'a>> {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            if a.t < b.t {
+                Some(a)
+            } else {
+                Some(b)
+            }
+        }
+        (Some(a), None) => Some(a),
+        (None, b) => b,
+    }
+}
+
+/// A BVH: boxes around pairs of boxes down to leaves of one or two
+/// primitives. The nodes are kept in one array, each before its children,
+/// and the primitives in another in the order the leaves reach them, so a
+/// ray walks two arrays instead of chasing a pointer per node and per
+/// primitive.
 pub struct BoundNode {
+    nodes: Vec<Node>,
+    primitives: Vec<Primitive>,
+    /// Where each object the tree was built over went in `primitives`, in
+    /// the order they were given.
+    order: Vec<u32>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Children {
+    /// The indices of two nodes.
+    Nodes(u32, u32),
+    /// The first of the leaf's primitives and how many it has.
+    Primitives(u32, u32),
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Node {
     bounds: BoundingBox,
-    left: Arc<dyn Hittable>,
-    right: Arc<dyn Hittable>,
+    children: Children,
 }
 
 impl BoundNode {
-    // pub fn new(bounds: BoundingBox) -> Self {
-    //     Self {
-    //         bounds,
-    //         left: None,
-    //         right: None,
-    //     }
-    // }
-    pub fn from_objects(objects: &Vec<Arc<dyn Hittable>>, range: Range<usize>) -> Self {
-        let mut bounds = BoundingBox::empty();
-        for i in range.clone() {
-            bounds = BoundingBox::from_boxes(bounds, objects[i].bound());
-        }
-        let axis = bounds.longest_axis();
-
-        let span = range.len();
-        match span {
-            0 => panic!("No objects in range"),
-            1 => Self {
-                bounds,
-                left: objects[range.start].clone(),
-                right: objects[range.start].clone(),
-            },
-            2 => Self {
-                bounds,
-                left: objects[range.start].clone(),
-                right: objects[range.start + 1].clone(),
-            },
-            _ => {
-                let mut objects = objects.clone();
-                objects[range.clone()].sort_by(|a, b| {
-                    let a = a.bound().intervals[axis].start;
-                    let b = b.bound().intervals[axis].start;
-                    a.partial_cmp(&b).unwrap()
-                });
-                let mid = range.start + span / 2;
-                let left = Self::from_objects(&objects, range.start..mid);
-                let right = Self::from_objects(&objects, mid..range.end);
-                Self {
-                    bounds,
-                    left: Arc::new(left),
-                    right: Arc::new(right),
-                }
-            }
+    pub fn from_objects(objects: &[Arc<dyn Hittable>], range: Range<usize>) -> Self {
+        Self::from_primitives(
+            objects[range]
+                .iter()
+                .map(|object| Primitive::from_arc(object.clone()))
+                .collect(),
+        )
+    }
+    pub fn from_primitives(primitives: Vec<Primitive>) -> Self {
+        let _stage = Stage::start("build BVH");
+        if primitives.is_empty() {
+            panic!("No objects in range");
+        }
+        let bounds: Vec<BoundingBox> = primitives.iter().map(|p| p.bound()).collect();
+        let mut indices: Vec<usize> = (0..primitives.len()).collect();
+        let mut tree = Self {
+            nodes: Vec::with_capacity(primitives.len()),
+            primitives: Vec::with_capacity(primitives.len()),
+            order: vec![0; primitives.len()],
+        };
+        let mut leaf_order = Vec::with_capacity(primitives.len());
+        tree.split(&bounds, &mut indices, &mut leaf_order);
+
+        let mut primitives: Vec<Option<Primitive>> = primitives.into_iter().map(Some).collect();
+        for index in leaf_order {
+            tree.order[index] = tree.primitives.len() as u32;
+            tree.primitives.push(
+                primitives[index]
+                    .take()
+                    .expect("each object is in one leaf"),
+            );
+        }
+        tree
+    }
+    /// Adds the nodes over `indices`, sorting them by where their boxes
+    /// start along the longest axis and halving them, and returns the
+    /// index of the first. `leaf_order` collects the objects as the leaves
+    /// reach them.
+    fn split(
+        &mut self,
+        bounds: &[BoundingBox],
+        indices: &mut [usize],
+        leaf_order: &mut Vec<usize>,
+    ) -> u32 {
+        let mut node_bounds = BoundingBox::empty();
+        for &i in indices.iter() {
+            node_bounds = BoundingBox::from_boxes(node_bounds, bounds[i]);
         }
+        let axis = node_bounds.longest_axis();
+
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            bounds: node_bounds,
+            children: Children::Primitives(leaf_order.len() as u32, indices.len() as u32),
+        });
+        if indices.len() <= 2 {
+            leaf_order.extend_from_slice(indices);
+        } else {
+            indices.sort_by(|&a, &b| {
+                let a = bounds[a].intervals[axis].start;
+                let b = bounds[b].intervals[axis].start;
+                a.partial_cmp(&b).unwrap()
+            });
+            let (left, right) = indices.split_at_mut(indices.len() / 2);
+            let left = self.split(bounds, left, leaf_order);
+            let right = self.split(bounds, right, leaf_order);
+            self.nodes[index].children = Children::Nodes(left, right);
+        }
+        index as u32
     }
     pub fn from_list(list: HittableList) -> Self {
         let objects = list.objects.clone();
         let len = objects.len();
         Self::from_objects(&objects, 0..len)
     }
-}
 
-impl Hittable for BoundNode {
-    fn hit(&self, ray: &Ray, t: Interval) -> Option<HitRecord> {
-        if !self.bounds.hit(ray, t) {
+    /// The objects the tree was built over, in the order they were given.
+    pub fn leaves(&self) -> impl Iterator<Item = &Primitive> + '_ {
+        self.order
+            .iter()
+            .map(|&index| &self.primitives[index as usize])
+    }
+
+    /// A rough count of the bytes held by the nodes and the primitives,
+    /// not counting what objects kept behind an `Arc` hold.
+    pub fn estimated_bytes(&self) -> usize {
+        self.nodes.len() * mem::size_of::<Node>()
+            + self.primitives.len() * (mem::size_of::<Primitive>() + mem::size_of::<u32>())
+            + self
+                .primitives
+                .iter()
+                .map(Primitive::boxed_bytes)
+                .sum::<usize>()
+    }
+
+    fn hit_node(
+        &self,
+        node: u32,
+        ray: &Ray,
+        t: Interval,
+        rng: &mut dyn Sampler,
+        stats: &mut TraversalStats,
+    ) -> Option<HitRecord<'_>> {
+        let node = &self.nodes[node as usize];
+        stats.node_visits += 1;
+        if !node.bounds.hit(ray, t) {
             return None;
         }
-        match (self.left.hit(ray, t), self.right.hit(ray, t)) {
-            (Some(a), Some(b)) => {
-                if a.t < b.t {
-                    Some(a)
-                } else {
-                    Some(b)
+        match node.children {
+            Children::Nodes(left, right) => closest(
+                self.hit_node(left, ray, t, rng, stats),
+                self.hit_node(right, ray, t, rng, stats),
+            ),
+            Children::Primitives(first, count) => self.primitives
+                [first as usize..(first + count) as usize]
+                .iter()
+                .fold(None, |hit, primitive| {
+                    closest(hit, primitive.hit_with_stats(ray, t, rng, stats))
+                }),
+        }
+    }
+
+    fn hit_any_node(&self, node: u32, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> bool {
+        let node = &self.nodes[node as usize];
+        node.bounds.hit(ray, t)
+            && match node.children {
+                Children::Nodes(left, right) => {
+                    self.hit_any_node(left, ray, t, rng) || self.hit_any_node(right, ray, t, rng)
                 }
+                Children::Primitives(first, count) => self.primitives
+                    [first as usize..(first + count) as usize]
+                    .iter()
+                    .any(|primitive| primitive.hit_any(ray, t, rng)),
             }
-            (Some(a), None) => Some(a),
-            (None, Some(b)) => Some(b),
-            (None, None) => None,
-        }
+    }
+}
+
+impl Hittable for BoundNode {
+    fn hit(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> Option<HitRecord<'_>> {
+        self.hit_node(0, ray, t, rng, &mut TraversalStats::default())
+    }
+
+    fn hit_with_stats(
+        &self,
+        ray: &Ray,
+        t: Interval,
+        rng: &mut dyn Sampler,
+        stats: &mut TraversalStats,
+    ) -> Option<HitRecord<'_>> {
+        self.hit_node(0, ray, t, rng, stats)
+    }
+
+    fn hit_any(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> bool {
+        self.hit_any_node(0, ray, t, rng)
     }
 
     fn bound(&self) -> BoundingBox {
-        self.bounds
+        self.nodes[0].bounds
+    }
+    fn bound_transformed(&self, transform: &Mat4) -> BoundingBox {
+        self.primitives
+            .iter()
+            .fold(BoundingBox::empty(), |bounds, primitive| {
+                BoundingBox::from_boxes(bounds, primitive.bound_transformed(transform))
+            })
+    }
+
+    fn collect_lights(&self, lights: &mut Vec<Arc<dyn Hittable>>) {
+        for primitive in self.leaves() {
+            if primitive.is_emissive() {
+                lights.push(primitive.to_arc());
+            } else {
+                primitive.collect_lights(lights);
+            }
+        }
+    }
+
+    fn describe(&self, describer: &mut Describer) -> Option<ObjectDesc> {
+        Some(ObjectDesc::Group {
+            bvh: true,
+            objects: self
+                .leaves()
+                .map(|primitive| describer.object(primitive))
+                .collect::<Option<_>>()?,
+        })
+    }
+
+    fn validate(&self, path: &str, issues: &mut Vec<ValidationIssue>) {
+        let leaves: Vec<(String, Arc<dyn Hittable>)> = self
+            .leaves()
+            .enumerate()
+            .map(|(i, primitive)| (format!("{}.objects[{}]", path, i), primitive.to_arc()))
+            .collect();
+        for (path, object) in &leaves {
+            let bounded = object
+                .bound()
+                .intervals
+                .iter()
+                .all(|i| i.start.is_finite() && i.end.is_finite());
+            if bounded {
+                object.validate(path, issues);
+            } else {
+                issues.push(ValidationIssue::error(
+                    path,
+                    "unbounded object inside a BVH can never be hit",
+                ));
+            }
+        }
+        validate_duplicates(&leaves, issues);
+    }
+
+    /// Lists the objects the tree was built over, not its nodes.
+    fn label(&self) -> String {
+        format!("BVH over {} objects", self.primitives.len())
+    }
+    fn bvh_leaves(&self) -> Option<Vec<Arc<dyn Hittable>>> {
+        Some(self.leaves().map(Primitive::to_arc).collect())
+    }
+    fn children(&self) -> Vec<&dyn Hittable> {
+        self.leaves()
+            .map(|primitive| primitive as &dyn Hittable)
+            .collect()
     }
 }
+
+debug_as_hierarchy!(BoundNode);
@@ -0,0 +1,157 @@
+use std::{f64::consts::PI, io, path::Path};
+
+use crate::{
+    camera::stable_hash, color, Color, Distribution2D, ImageBuffer, Pdf, Sampler, Texture, Vec3,
+};
+
+/// Light arriving from infinitely far away in every direction, from an
+/// equirectangular image wrapped around the scene: across its width once
+/// around the horizon, from straight up at its top row to straight down at
+/// its bottom one. Rays that leave the scene see it instead of the camera's
+/// background.
+///
+/// Bright parts of the image, such as a small sun, would rarely be found by
+/// rays bouncing off at random, so unless `importance_sampled` is off, every
+/// diffuse hit also sends a shadow ray in a direction drawn in proportion
+/// to the image's brightness, see `EnvironmentPdf`, weighing the two ways
+/// of finding the light by multiple importance sampling.
+pub struct Environment {
+    /// Linear radiance, row by row from the top.
+    pub image: ImageBuffer,
+    /// Scales the radiance of the image.
+    pub strength: f64,
+    pub importance_sampled: bool,
+    distribution: Option<Distribution2D>,
+}
+
+impl Environment {
+    pub fn new(image: ImageBuffer) -> Self {
+        // Rows near the poles cover less of the sphere than those at the
+        // horizon, by sin θ.
+        let weights: Vec<f64> = image
+            .pixels
+            .iter()
+            .enumerate()
+            .map(|(i, pixel)| {
+                let theta = ((i / image.width.max(1)) as f64 + 0.5) / image.height as f64 * PI;
+                pixel.luminance().max(0.0) * theta.sin()
+            })
+            .collect();
+        let distribution = Distribution2D::new(&weights, image.width);
+        let black = distribution.sample((0.5, 0.5)).is_none();
+        Self {
+            image,
+            strength: 1.0,
+            importance_sampled: true,
+            distribution: (!black).then_some(distribution),
+        }
+    }
+
+    /// `texture` wrapped around the scene as `Sphere` wraps it around
+    /// itself, so a globe's texture seen from inside, looked up at the
+    /// centers of an image `width` by half as high. Procedural textures
+    /// are looked up at the unit direction of each pixel.
+    pub fn from_texture(texture: &dyn Texture, width: usize) -> Self {
+        let (width, height) = (width.max(1), (width / 2).max(1));
+        let mut image = ImageBuffer::new(width, height);
+        for y in 0..height {
+            let v = (y as f64 + 0.5) / height as f64;
+            for x in 0..width {
+                let u = (x as f64 + 0.5) / width as f64;
+                // `Sphere` counts v up from its bottom.
+                image.set(x, y, texture.value(u, 1.0 - v, &uv_to_direction(u, v)));
+            }
+        }
+        Self::new(image)
+    }
+
+    /// Loads the image from a Radiance HDR file, or any format
+    /// `ImageBuffer::load` reads.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Ok(Self::new(ImageBuffer::load(path)?))
+    }
+
+    /// The radiance arriving along the reverse of unit `direction`, i.e.
+    /// seen looking along `direction`.
+    pub fn radiance(&self, direction: Vec3) -> Color {
+        let (width, height) = (self.image.width, self.image.height);
+        if width == 0 || height == 0 {
+            return color(0.0, 0.0, 0.0);
+        }
+        let (u, v) = direction_to_uv(direction);
+        let x = ((u * width as f64) as usize).min(width - 1);
+        let y = ((v * height as f64) as usize).min(height - 1);
+        self.image.get(x, y) * self.strength
+    }
+
+    /// The distribution shadow rays toward the environment are drawn from,
+    /// or `None` if it isn't importance sampled or is black throughout.
+    pub fn pdf(&self) -> Option<EnvironmentPdf<'_>> {
+        let distribution = self.distribution.as_ref()?;
+        self.importance_sampled
+            .then_some(EnvironmentPdf { distribution })
+    }
+
+    /// A hash of the image and its strength, for `Scene::fingerprint`.
+    pub fn fingerprint(&self) -> u64 {
+        let mut bytes = Vec::with_capacity(self.image.pixels.len() * 24 + 24);
+        for value in [
+            self.image.width as f64,
+            self.image.height as f64,
+            self.strength,
+        ] {
+            bytes.extend(value.to_le_bytes());
+        }
+        for pixel in &self.image.pixels {
+            for channel in [pixel.0, pixel.1, pixel.2] {
+                bytes.extend(channel.to_le_bytes());
+            }
+        }
+        stable_hash(&bytes)
+    }
+}
+
+/// Directions toward an `Environment` in proportion to its brightness, by
+/// way of a piecewise-constant distribution over its pixels.
+pub struct EnvironmentPdf<'a> {
+    distribution: &'a Distribution2D,
+}
+
+impl Pdf for EnvironmentPdf<'_> {
+    fn value(&self, direction: Vec3) -> f64 {
+        let (u, v) = direction_to_uv(direction);
+        let sin_theta = (v * PI).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        // The image spans 2π by π radians, squeezed by sin θ toward the poles.
+        self.distribution.density((u, v)) / (2.0 * PI * PI * sin_theta)
+    }
+
+    fn generate(&self, rng: &mut dyn Sampler) -> Vec3 {
+        match self.distribution.sample(rng.next_2d()) {
+            Some(((u, v), _)) => uv_to_direction(u, v),
+            None => Vec3(0.0, 1.0, 0.0),
+        }
+    }
+}
+
+/// Where unit `direction` falls on an equirectangular image: u around the
+/// horizon as `Sphere` maps its texture, v from 0 straight up to 1 straight
+/// down.
+pub fn direction_to_uv(direction: Vec3) -> (f64, f64) {
+    let theta = direction.y().clamp(-1.0, 1.0).acos();
+    let phi = (-direction.z()).atan2(direction.x()) + PI;
+    ((phi / (2.0 * PI)).clamp(0.0, 1.0), theta / PI)
+}
+
+/// The unit direction at (u, v) on an equirectangular image, undoing
+/// `direction_to_uv`.
+pub fn uv_to_direction(u: f64, v: f64) -> Vec3 {
+    let (phi, theta) = (u * 2.0 * PI, v * PI);
+    Vec3(
+        -phi.cos() * theta.sin(),
+        theta.cos(),
+        phi.sin() * theta.sin(),
+    )
+}
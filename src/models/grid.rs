@@ -0,0 +1,309 @@
+use crate::{
+    hittable::*,
+    scenes::{validate_duplicates, ValidationIssue},
+    BoundingBox, Interval, Mat4, Ray, Sampler, Stage, TraversalStats, Vec3,
+};
+
+use std::{mem, sync::Arc};
+
+/// How many primitives the grid aims to put in each cell.
+pub const PRIMITIVES_PER_CELL: f64 = 2.0;
+/// The most cells along any one axis.
+const MAX_RESOLUTION: usize = 256;
+
+/// A uniform grid of cells, each listing the objects whose bounds overlap it.
+/// Rays walk the cells they cross in order (a 3D DDA), so for evenly spread
+/// geometry like terrain the first hit is found after a few cells.
+pub struct GridAccel {
+    objects: Vec<Arc<dyn Hittable>>,
+    bounds: BoundingBox,
+    resolution: [usize; 3],
+    cell_size: Vec3,
+    // Cell `c` holds `indices[starts[c]..starts[c + 1]]`.
+    starts: Vec<u32>,
+    indices: Vec<u32>,
+}
+
+impl GridAccel {
+    pub fn new(objects: Vec<Arc<dyn Hittable>>) -> Self {
+        let _stage = Stage::start("build grid");
+        // Objects without finite bounds can't be placed in a cell.
+        let bounded = |object: &Arc<dyn Hittable>| {
+            object
+                .bound()
+                .intervals
+                .iter()
+                .all(|i| i.start.is_finite() && i.end.is_finite() && i.start <= i.end)
+        };
+        let bounds = objects
+            .iter()
+            .filter(|object| bounded(object))
+            .fold(BoundingBox::empty(), |bounds, object| {
+                BoundingBox::from_boxes(bounds, object.bound())
+            });
+        let count = objects.iter().filter(|object| bounded(object)).count();
+        let resolution = Self::resolution_for(&bounds, count);
+        let cell_size = Vec3(
+            bounds.intervals[0].size() / resolution[0] as f64,
+            bounds.intervals[1].size() / resolution[1] as f64,
+            bounds.intervals[2].size() / resolution[2] as f64,
+        );
+        let mut grid = Self {
+            objects,
+            bounds,
+            resolution,
+            cell_size,
+            starts: Vec::new(),
+            indices: Vec::new(),
+        };
+
+        // Count the objects in each cell, then fill the cells in a second pass.
+        let cells = resolution.iter().product::<usize>();
+        let ranges: Vec<Option<[(usize, usize); 3]>> = grid
+            .objects
+            .iter()
+            .map(|object| bounded(object).then(|| grid.cell_range(&object.bound())))
+            .collect();
+        let mut counts = vec![0u32; cells + 1];
+        for range in ranges.iter().flatten() {
+            grid.for_each_cell(range, |cell| counts[cell + 1] += 1);
+        }
+        for cell in 0..cells {
+            counts[cell + 1] += counts[cell];
+        }
+        let mut next = counts.clone();
+        let mut indices = vec![0u32; counts[cells] as usize];
+        for (object, range) in ranges.iter().enumerate() {
+            if let Some(range) = range {
+                grid.for_each_cell(range, |cell| {
+                    indices[next[cell] as usize] = object as u32;
+                    next[cell] += 1;
+                });
+            }
+        }
+        grid.starts = counts;
+        grid.indices = indices;
+        grid
+    }
+
+    /// Cells along each axis: about `PRIMITIVES_PER_CELL` objects per cell,
+    /// with cells as close to cubes as the bounds allow. Axes the objects
+    /// barely extend along, like the height of flat terrain, get one cell.
+    fn resolution_for(bounds: &BoundingBox, count: usize) -> [usize; 3] {
+        let extents = bounds.intervals.map(|i| i.size().max(0.0));
+        let longest = extents.iter().cloned().fold(0.0, f64::max);
+        if count == 0 || longest <= 0.0 {
+            return [1; 3];
+        }
+        let spread: Vec<f64> = extents
+            .iter()
+            .cloned()
+            .filter(|&e| e > longest * 1e-3)
+            .collect();
+        let cells = (count as f64 / PRIMITIVES_PER_CELL).max(1.0);
+        let per_unit = (cells / spread.iter().product::<f64>()).powf(1.0 / spread.len() as f64);
+        extents.map(|e| {
+            if e > longest * 1e-3 {
+                ((e * per_unit).round() as usize).clamp(1, MAX_RESOLUTION)
+            } else {
+                1
+            }
+        })
+    }
+
+    pub fn resolution(&self) -> [usize; 3] {
+        self.resolution
+    }
+    pub fn objects(&self) -> &[Arc<dyn Hittable>] {
+        &self.objects
+    }
+    /// The bytes held by the cell lists, not counting the objects.
+    pub fn estimated_bytes(&self) -> usize {
+        (self.starts.len() + self.indices.len()) * mem::size_of::<u32>()
+    }
+
+    fn cell_index(&self, cell: [usize; 3]) -> usize {
+        (cell[2] * self.resolution[1] + cell[1]) * self.resolution[0] + cell[0]
+    }
+    /// The cell holding `value` along `axis`, clamped to the grid.
+    fn cell_along(&self, axis: usize, value: f64) -> usize {
+        let offset = (value - self.bounds.intervals[axis].start) / self.cell_size[axis];
+        if offset.is_nan() || offset < 0.0 {
+            0
+        } else {
+            (offset as usize).min(self.resolution[axis] - 1)
+        }
+    }
+    /// The first and last cells along each axis that `bounds` overlaps.
+    fn cell_range(&self, bounds: &BoundingBox) -> [(usize, usize); 3] {
+        std::array::from_fn(|axis| {
+            // Widen slightly, so rounding in the walk can't skip a cell an
+            // object touches.
+            let interval = bounds.intervals[axis].expand(self.cell_size[axis] * 1e-6);
+            (
+                self.cell_along(axis, interval.start),
+                self.cell_along(axis, interval.end),
+            )
+        })
+    }
+    fn for_each_cell(&self, range: &[(usize, usize); 3], mut f: impl FnMut(usize)) {
+        for z in range[2].0..=range[2].1 {
+            for y in range[1].0..=range[1].1 {
+                for x in range[0].0..=range[0].1 {
+                    f(self.cell_index([x, y, z]));
+                }
+            }
+        }
+    }
+    fn cell(&self, cell: usize) -> &[u32] {
+        &self.indices[self.starts[cell] as usize..self.starts[cell + 1] as usize]
+    }
+
+    /// Calls `visit` on each cell `ray` crosses within `t`, in order, with the
+    /// distance at which the ray leaves the cell. Stops when `visit` returns
+    /// true. Returns the number of cells visited.
+    fn walk(&self, ray: &Ray, t: Interval, mut visit: impl FnMut(&[u32], f64) -> bool) -> u32 {
+        // Clip the ray to the grid's box.
+        let mut span = t;
+        for axis in 0..3 {
+            let interval = self.bounds.intervals[axis];
+            let inverse = 1.0 / ray.direction[axis];
+            let t0 = (interval.start - ray.origin[axis]) * inverse;
+            let t1 = (interval.end - ray.origin[axis]) * inverse;
+            let (t0, t1) = if t0 <= t1 { (t0, t1) } else { (t1, t0) };
+            // NaN, from a ray in the plane of a face, leaves the span alone.
+            span.start = if t0 > span.start { t0 } else { span.start };
+            span.end = if t1 < span.end { t1 } else { span.end };
+            if span.start > span.end {
+                return 0;
+            }
+        }
+
+        let entry = ray.at(span.start);
+        let mut cell = [0; 3];
+        let mut step = [0isize; 3];
+        let mut next = [f64::INFINITY; 3];
+        let mut delta = [f64::INFINITY; 3];
+        for axis in 0..3 {
+            cell[axis] = self.cell_along(axis, entry[axis]);
+            let direction = ray.direction[axis];
+            let start = self.bounds.intervals[axis].start;
+            if direction > 0.0 {
+                step[axis] = 1;
+                let boundary = start + (cell[axis] + 1) as f64 * self.cell_size[axis];
+                next[axis] = (boundary - ray.origin[axis]) / direction;
+                delta[axis] = self.cell_size[axis] / direction;
+            } else if direction < 0.0 {
+                step[axis] = -1;
+                let boundary = start + cell[axis] as f64 * self.cell_size[axis];
+                next[axis] = (boundary - ray.origin[axis]) / direction;
+                delta[axis] = -self.cell_size[axis] / direction;
+            }
+        }
+
+        let mut visited = 0;
+        loop {
+            let axis = (0..3).min_by(|&a, &b| next[a].total_cmp(&next[b])).unwrap();
+            visited += 1;
+            if visit(self.cell(self.cell_index(cell)), next[axis]) || next[axis] > span.end {
+                return visited;
+            }
+            let moved = cell[axis] as isize + step[axis];
+            if moved < 0 || moved as usize >= self.resolution[axis] {
+                return visited;
+            }
+            cell[axis] = moved as usize;
+            next[axis] += delta[axis];
+        }
+    }
+
+    /// The closest hit among the objects in the cells along `ray`, testing
+    /// each with `test`, and the number of cells walked to find it.
+    fn closest<'a>(
+        &'a self,
+        ray: &Ray,
+        t: Interval,
+        mut test: impl FnMut(&'a dyn Hittable, Interval) -> Option<HitRecord<'a>>,
+    ) -> (Option<HitRecord<'a>>, u32) {
+        let mut closest: Option<HitRecord<'a>> = None;
+        let cells = self.walk(ray, t, |cell, exit| {
+            for &index in cell {
+                let end = closest.as_ref().map_or(t.end, |record| record.t);
+                let object = self.objects[index as usize].as_ref();
+                if let Some(record) = test(object, Interval::new(t.start, end)) {
+                    closest = Some(record);
+                }
+            }
+            // A hit before the ray leaves the cell can't be beaten by anything
+            // in the cells further on.
+            closest.as_ref().is_some_and(|record| record.t <= exit)
+        });
+        (closest, cells)
+    }
+}
+
+impl Hittable for GridAccel {
+    fn hit(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> Option<HitRecord<'_>> {
+        self.closest(ray, t, |object, t| object.hit(ray, t, rng)).0
+    }
+    fn hit_with_stats(
+        &self,
+        ray: &Ray,
+        t: Interval,
+        rng: &mut dyn Sampler,
+        stats: &mut TraversalStats,
+    ) -> Option<HitRecord<'_>> {
+        // Each cell walked counts as a node visit.
+        let (record, cells) = self.closest(ray, t, |object, t| {
+            object.hit_with_stats(ray, t, rng, stats)
+        });
+        stats.node_visits += cells;
+        record
+    }
+    fn hit_any(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> bool {
+        let mut found = false;
+        self.walk(ray, t, |cell, _| {
+            found = cell
+                .iter()
+                .any(|&index| self.objects[index as usize].hit_any(ray, t, rng));
+            found
+        });
+        found
+    }
+    fn bound(&self) -> BoundingBox {
+        self.bounds
+    }
+    fn bound_transformed(&self, transform: &Mat4) -> BoundingBox {
+        self.objects
+            .iter()
+            .fold(BoundingBox::empty(), |bounds, object| {
+                BoundingBox::from_boxes(bounds, object.bound_transformed(transform))
+            })
+    }
+    fn collect_lights(&self, lights: &mut Vec<Arc<dyn Hittable>>) {
+        for object in &self.objects {
+            collect_light(object, lights);
+        }
+    }
+    fn validate(&self, path: &str, issues: &mut Vec<ValidationIssue>) {
+        let objects: Vec<(String, Arc<dyn Hittable>)> = self
+            .objects
+            .iter()
+            .enumerate()
+            .map(|(i, object)| (format!("{}.objects[{}]", path, i), object.clone()))
+            .collect();
+        for (path, object) in &objects {
+            object.validate(path, issues);
+        }
+        validate_duplicates(&objects, issues);
+    }
+    fn label(&self) -> String {
+        let [x, y, z] = self.resolution;
+        format!("GridAccel ({} by {} by {} cells)", x, y, z)
+    }
+    fn children(&self) -> Vec<&dyn Hittable> {
+        self.objects.iter().map(|object| object.as_ref()).collect()
+    }
+}
+
+debug_as_hierarchy!(GridAccel);
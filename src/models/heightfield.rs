@@ -0,0 +1,165 @@
+use crate::{
+    hittable::*,
+    point,
+    scenes::{Describer, ObjectDesc, ValidationIssue},
+    BoundingBox, Interval, Mat4, Material, MeshAccel, MeshData, Ray, Sampler, TraversalStats,
+    TriangleMesh, DEFAULT_SMOOTHING_ANGLE,
+};
+
+use std::sync::Arc;
+
+/// Terrain: heights sampled on a regular grid over the XZ plane, starting at
+/// the origin. Each square of samples becomes two triangles, found through a
+/// uniform grid rather than a BVH.
+pub struct Heightfield {
+    pub columns: usize,
+    pub rows: usize,
+    /// The distance between neighboring samples along X and Z.
+    pub spacing: f64,
+    /// The height of each sample, row by row along Z.
+    pub heights: Vec<f64>,
+    mesh: TriangleMesh,
+}
+
+impl Heightfield {
+    pub fn new(
+        columns: usize,
+        spacing: f64,
+        heights: Vec<f64>,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        let rows = heights.len().checked_div(columns).unwrap_or(0);
+        let mut data = MeshData::default();
+        // Ragged or single-line grids have no squares; `validate` reports them.
+        if columns >= 2 && rows >= 2 && rows * columns == heights.len() {
+            let last = ((columns - 1) as f64, (rows - 1) as f64);
+            let mut uvs = Vec::with_capacity(heights.len());
+            for row in 0..rows {
+                for column in 0..columns {
+                    let height = heights[row * columns + column];
+                    let (x, z) = (column as f64 * spacing, row as f64 * spacing);
+                    data.positions.push(point(x, height, z));
+                    uvs.push((column as f64 / last.0, row as f64 / last.1));
+                }
+            }
+            data.uvs = Some(uvs);
+            for row in 0..rows - 1 {
+                for column in 0..columns - 1 {
+                    let corner = row * columns + column;
+                    let (right, below) = (corner + 1, corner + columns);
+                    data.faces.push([corner, below, right]);
+                    data.faces.push([right, below, below + 1]);
+                }
+            }
+            data.compute_vertex_normals(DEFAULT_SMOOTHING_ANGLE);
+        }
+        let mesh = TriangleMesh::with_accel(data, material, MeshAccel::Grid);
+        Self {
+            columns,
+            rows,
+            spacing,
+            heights,
+            mesh,
+        }
+    }
+    /// Samples `height(x, z)` on a `columns` by `rows` grid.
+    pub fn from_fn(
+        columns: usize,
+        rows: usize,
+        spacing: f64,
+        height: impl Fn(f64, f64) -> f64,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        let heights = (0..rows)
+            .flat_map(|row| {
+                let height = &height;
+                (0..columns)
+                    .map(move |column| height(column as f64 * spacing, row as f64 * spacing))
+            })
+            .collect();
+        Self::new(columns, spacing, heights, material)
+    }
+
+    pub fn height(&self, column: usize, row: usize) -> f64 {
+        self.heights[row * self.columns + column]
+    }
+    pub fn mesh(&self) -> &TriangleMesh {
+        &self.mesh
+    }
+}
+
+impl Hittable for Heightfield {
+    fn hit(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> Option<HitRecord<'_>> {
+        self.mesh.hit(ray, t, rng)
+    }
+    fn hit_with_stats(
+        &self,
+        ray: &Ray,
+        t: Interval,
+        rng: &mut dyn Sampler,
+        stats: &mut TraversalStats,
+    ) -> Option<HitRecord<'_>> {
+        self.mesh.hit_with_stats(ray, t, rng, stats)
+    }
+    fn hit_any(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> bool {
+        self.mesh.hit_any(ray, t, rng)
+    }
+    fn bound(&self) -> BoundingBox {
+        self.mesh.bound()
+    }
+    fn bound_transformed(&self, transform: &Mat4) -> BoundingBox {
+        self.mesh.bound_transformed(transform)
+    }
+    fn is_emissive(&self) -> bool {
+        self.mesh.is_emissive()
+    }
+    fn describe(&self, describer: &mut Describer) -> Option<ObjectDesc> {
+        Some(ObjectDesc::Heightfield {
+            columns: self.columns,
+            spacing: self.spacing,
+            heights: self.heights.clone(),
+            material: describer.material(&self.mesh.material)?.into(),
+        })
+    }
+    fn validate(&self, path: &str, issues: &mut Vec<ValidationIssue>) {
+        if self.columns < 2 || self.rows < 2 {
+            issues.push(ValidationIssue::error(
+                path,
+                format!(
+                    "needs at least 2 by 2 heights, got {} by {}",
+                    self.columns, self.rows
+                ),
+            ));
+        } else if self.rows * self.columns != self.heights.len() {
+            issues.push(ValidationIssue::error(
+                path,
+                format!(
+                    "{} heights don't fill rows of {}",
+                    self.heights.len(),
+                    self.columns
+                ),
+            ));
+        }
+        if !(self.spacing.is_finite() && self.spacing > 0.0) {
+            issues.push(ValidationIssue::error(
+                path,
+                format!("spacing must be positive, got {}", self.spacing),
+            ));
+        }
+        self.mesh.validate(path, issues);
+    }
+    fn label(&self) -> String {
+        format!(
+            "Heightfield ({} by {} heights, spacing {}, {})",
+            self.columns,
+            self.rows,
+            self.spacing,
+            self.mesh.material.kind()
+        )
+    }
+    fn primitive_count(&self) -> usize {
+        self.mesh.triangle_count()
+    }
+}
+
+debug_as_hierarchy!(Heightfield);
@@ -1,23 +1,40 @@
-use crate::{vec3::*, BoundingBox, Interval, Material, Point, Ray};
+use crate::{
+    count_hit_test,
+    scenes::{validate_bounds, validate_duplicates, Describer, ObjectDesc, ValidationIssue},
+    vec3::*,
+    BoundingBox, Interval, Mat4, Material, Point, Pose, Primitive, Quat, Ray, Sampler,
+    TraversalStats,
+};
 
-use std::sync::Arc;
+use std::{fmt, sync::Arc};
 
 pub use transformation::*;
 
+/// Distance, relative to the hit point's magnitude, that spawned rays start off
+/// the surface. Well above f64 rounding, well below any visible gap.
+const SPAWN_OFFSET: f64 = 1e-9;
+
+/// A hit on a surface. The material is borrowed from the object that was hit,
+/// so testing candidate hits never touches a reference count.
 #[derive(Clone)]
-pub struct HitRecord {
+pub struct HitRecord<'a> {
     pub point: Point,
     pub normal: Vec3,
     pub t: f64,
     pub front_face: bool,
     pub u: f64,
     pub v: f64,
-    pub material: Arc<dyn Material>,
+    pub material: &'a dyn Material,
     pub emitted: Color,
+    pub vertex_color: Option<Color>,
+    /// The time of the ray that hit, carried on to the rays that leave.
+    pub time: f64,
+    /// The name of the innermost `Matte` around the object hit, if any.
+    pub matte: Option<&'a str>,
 }
 
-impl HitRecord {
-    pub fn new(ray: &Ray, t: f64, point: Point, normal: Vec3, material: Arc<dyn Material>) -> Self {
+impl<'a> HitRecord<'a> {
+    pub fn new(ray: &Ray, t: f64, point: Point, normal: Vec3, material: &'a dyn Material) -> Self {
         let front_face = Vec3::dot(&ray.direction, &normal) < 0.0;
         let normal = if front_face { normal } else { -normal };
         Self {
@@ -29,23 +46,197 @@ impl HitRecord {
             v: 0.0,
             material,
             emitted: color(0., 0., 0.),
+            vertex_color: None,
+            time: ray.time,
+            matte: None,
+        }
+    }
+    /// Starts a ray leaving the hit point in `direction`. The origin is nudged off
+    /// the surface along the normal, by a distance scaled to the point's magnitude,
+    /// so rounding in the hit point can't make the ray hit the same surface again.
+    pub fn spawn_ray(&self, direction: Vec3) -> Ray {
+        let p = self.point;
+        let magnitude = p.0.abs().max(p.1.abs()).max(p.2.abs());
+        let offset = SPAWN_OFFSET * (1.0 + magnitude);
+        let side = if Vec3::dot(&direction, &self.normal) < 0.0 {
+            -offset
+        } else {
+            offset
+        };
+        Ray {
+            origin: p + self.normal * side,
+            direction,
+            time: self.time,
         }
     }
+
     pub fn set_uv(&mut self, u: f64, v: f64) -> Self {
         self.u = u;
         self.v = v;
         self.clone()
     }
-    pub fn set_material(&mut self, material: Arc<dyn Material>) -> Self {
+    pub fn set_material(&mut self, material: &'a dyn Material) -> Self {
         self.material = material;
         self.clone()
     }
 }
 
-pub trait Hittable {
-    fn hit(&self, ray: &Ray, t: Interval) -> Option<HitRecord>;
+pub trait Hittable: Send + Sync {
+    fn hit(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> Option<HitRecord<'_>>;
 
     fn bound(&self) -> BoundingBox;
+
+    /// The box around the object once placed by `transform`. By default this
+    /// boxes the placed corners of `bound`; shapes that know their geometry
+    /// can bound it exactly, which matters once rotations tilt long objects.
+    fn bound_transformed(&self, transform: &Mat4) -> BoundingBox {
+        self.bound().transformed(transform)
+    }
+
+    /// Whether anything in the object blocks `ray` within `t`. Shadow rays only
+    /// need a yes or no, so groups can stop at the first hit they find.
+    fn hit_any(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> bool {
+        self.hit(ray, t, rng).is_some()
+    }
+
+    /// `hit`, also counting the work done in `stats`. Groups and wrappers pass
+    /// `stats` on to their children; anything else counts as one primitive.
+    fn hit_with_stats(
+        &self,
+        ray: &Ray,
+        t: Interval,
+        rng: &mut dyn Sampler,
+        stats: &mut TraversalStats,
+    ) -> Option<HitRecord<'_>> {
+        stats.primitive_tests += 1;
+        count_hit_test(short_type_name::<Self>());
+        self.hit(ray, t, rng)
+    }
+
+    /// Whether the object emits light and belongs in a scene's light registry.
+    fn is_emissive(&self) -> bool {
+        false
+    }
+
+    /// Adds the lights inside a group or wrapper to `lights`. Emissive objects
+    /// themselves are registered by whoever holds them; see `collect_light`.
+    fn collect_lights(&self, _lights: &mut Vec<Arc<dyn Hittable>>) {}
+
+    /// Describes the object for scene export, or `None` if it has no description.
+    fn describe(&self, _describer: &mut Describer) -> Option<ObjectDesc> {
+        None
+    }
+
+    /// Reports problems with the object, recursing into any children. `path`
+    /// names the object in the reported issues.
+    fn validate(&self, path: &str, issues: &mut Vec<ValidationIssue>) {
+        validate_bounds(&self.bound(), path, issues);
+    }
+
+    /// One line about the object for `Hierarchy`: its type and settings, but
+    /// not the objects inside it.
+    fn label(&self) -> String {
+        short_type_name::<Self>().to_string()
+    }
+
+    /// The objects inside a group or wrapper, for `Hierarchy`.
+    fn children(&self) -> Vec<&dyn Hittable> {
+        Vec::new()
+    }
+
+    /// The objects a BVH was built over, so they can be regrouped some other
+    /// way, as the instances of a `Tlas`.
+    fn bvh_leaves(&self) -> Option<Vec<Arc<dyn Hittable>>> {
+        None
+    }
+
+    /// The object as a `Primitive` a BVH can store in place, for the
+    /// built-in shapes.
+    fn to_primitive(&self) -> Option<Primitive> {
+        None
+    }
+
+    /// How many primitives the object is made of: those of its children, or
+    /// 1 for a shape. Instances of one object each count.
+    fn primitive_count(&self) -> usize {
+        match self.children() {
+            children if children.is_empty() => 1,
+            children => children.iter().map(|child| child.primitive_count()).sum(),
+        }
+    }
+}
+
+/// The last segment of a type's path, e.g. `Sphere`.
+pub(crate) fn short_type_name<T: ?Sized>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+/// Groups list at most this many children in a `Hierarchy`, then a count of the rest.
+const HIERARCHY_CHILDREN: usize = 20;
+
+/// Displays an object and everything inside it as an indented tree, one line
+/// per object with its bounds.
+pub struct Hierarchy<'a>(pub &'a dyn Hittable);
+
+impl Hierarchy<'_> {
+    fn write(f: &mut fmt::Formatter, object: &dyn Hittable, depth: usize) -> fmt::Result {
+        writeln!(
+            f,
+            "{:indent$}{}  {}",
+            "",
+            object.label(),
+            object.bound(),
+            indent = depth * 2
+        )?;
+        let children = object.children();
+        for child in children.iter().take(HIERARCHY_CHILDREN) {
+            Self::write(f, *child, depth + 1)?;
+        }
+        if children.len() > HIERARCHY_CHILDREN {
+            writeln!(
+                f,
+                "{:indent$}... {} more",
+                "",
+                children.len() - HIERARCHY_CHILDREN,
+                indent = (depth + 1) * 2
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Hierarchy<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Self::write(f, self.0, 0)
+    }
+}
+
+impl fmt::Debug for dyn Hittable + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Hierarchy(self))
+    }
+}
+
+/// Implements `Debug` for objects as their `Hierarchy`.
+macro_rules! debug_as_hierarchy {
+    ($($object:ty),*) => {$(
+        impl std::fmt::Debug for $object {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "{}", Hierarchy(self))
+            }
+        }
+    )*};
+}
+pub(crate) use debug_as_hierarchy;
+
+/// Registers `object` if it emits light, or else the lights inside it.
+pub fn collect_light(object: &Arc<dyn Hittable>, lights: &mut Vec<Arc<dyn Hittable>>) {
+    if object.is_emissive() {
+        lights.push(object.clone());
+    } else {
+        object.collect_lights(lights);
+    }
 }
 
 pub struct HittableList {
@@ -75,13 +266,21 @@ impl HittableList {
     }
 }
 
+impl Default for HittableList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Hittable for HittableList {
-    fn hit(&self, ray: &Ray, t: Interval) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> Option<HitRecord<'_>> {
         let mut closest_so_far = t.end;
         let mut hit_record = None;
 
         for object in self.objects.iter() {
-            if let Some(record) = object.hit(ray, Interval::from_range(t.start..closest_so_far)) {
+            if let Some(record) =
+                object.hit(ray, Interval::from_range(t.start..closest_so_far), rng)
+            {
                 closest_so_far = record.t;
                 hit_record = Some(record);
             }
@@ -89,14 +288,92 @@ impl Hittable for HittableList {
 
         hit_record
     }
+    fn hit_with_stats(
+        &self,
+        ray: &Ray,
+        t: Interval,
+        rng: &mut dyn Sampler,
+        stats: &mut TraversalStats,
+    ) -> Option<HitRecord<'_>> {
+        let mut closest_so_far = t.end;
+        let mut hit_record = None;
+
+        for object in self.objects.iter() {
+            if let Some(record) = object.hit_with_stats(
+                ray,
+                Interval::from_range(t.start..closest_so_far),
+                rng,
+                stats,
+            ) {
+                closest_so_far = record.t;
+                hit_record = Some(record);
+            }
+        }
+
+        hit_record
+    }
+    fn hit_any(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> bool {
+        self.objects
+            .iter()
+            .any(|object| object.hit_any(ray, t, rng))
+    }
     fn bound(&self) -> BoundingBox {
         self.bounds
     }
+    fn bound_transformed(&self, transform: &Mat4) -> BoundingBox {
+        self.objects
+            .iter()
+            .fold(BoundingBox::empty(), |bounds, object| {
+                BoundingBox::from_boxes(bounds, object.bound_transformed(transform))
+            })
+    }
+    fn collect_lights(&self, lights: &mut Vec<Arc<dyn Hittable>>) {
+        for object in &self.objects {
+            collect_light(object, lights);
+        }
+    }
+    fn describe(&self, describer: &mut Describer) -> Option<ObjectDesc> {
+        Some(ObjectDesc::Group {
+            bvh: false,
+            objects: self
+                .objects
+                .iter()
+                .map(|object| describer.object(object.as_ref()))
+                .collect::<Option<_>>()?,
+        })
+    }
+    fn label(&self) -> String {
+        format!("HittableList ({} objects)", self.objects.len())
+    }
+    fn children(&self) -> Vec<&dyn Hittable> {
+        self.objects.iter().map(|object| object.as_ref()).collect()
+    }
+    fn validate(&self, path: &str, issues: &mut Vec<ValidationIssue>) {
+        if self.objects.is_empty() {
+            issues.push(ValidationIssue::warning(path, "group is empty"));
+        }
+        let objects: Vec<(String, Arc<dyn Hittable>)> = self
+            .objects
+            .iter()
+            .enumerate()
+            .map(|(i, object)| (format!("{}.objects[{}]", path, i), object.clone()))
+            .collect();
+        for (path, object) in &objects {
+            object.validate(path, issues);
+        }
+        validate_duplicates(&objects, issues);
+    }
 }
 
 pub mod transformation {
     use super::*;
 
+    /// A rotation as an angle about an axis, for labels.
+    fn rotation_text(rotation: Quat) -> String {
+        let (axis, angle) = rotation.to_axis_angle();
+        format!("{}° about {}", angle, axis)
+    }
+
     pub struct Translation {
         pub object: Arc<dyn Hittable>,
         pub offset: Vec3,
@@ -112,28 +389,80 @@ pub mod transformation {
                 bounds,
             }
         }
-    }
 
-    impl Hittable for Translation {
-        fn hit(&self, ray: &Ray, t: Interval) -> Option<HitRecord> {
-            let moved_ray = Ray {
+        /// `ray` in the object's own space.
+        fn moved(&self, ray: &Ray) -> Ray {
+            Ray {
                 origin: ray.origin - self.offset,
                 direction: ray.direction,
-            };
-            if let Some(mut record) = self.object.hit(&moved_ray, t) {
-                record.point += self.offset;
-                Some(record)
-            } else {
-                None
+                time: ray.time,
             }
         }
+    }
+
+    impl Hittable for Translation {
+        fn hit(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> Option<HitRecord<'_>> {
+            let mut record = self.object.hit(&self.moved(ray), t, rng)?;
+            record.point += self.offset;
+            Some(record)
+        }
+        fn hit_with_stats(
+            &self,
+            ray: &Ray,
+            t: Interval,
+            rng: &mut dyn Sampler,
+            stats: &mut TraversalStats,
+        ) -> Option<HitRecord<'_>> {
+            let mut record = self
+                .object
+                .hit_with_stats(&self.moved(ray), t, rng, stats)?;
+            record.point += self.offset;
+            Some(record)
+        }
+        fn hit_any(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> bool {
+            self.object.hit_any(&self.moved(ray), t, rng)
+        }
         fn bound(&self) -> BoundingBox {
             self.bounds
         }
+        fn bound_transformed(&self, transform: &Mat4) -> BoundingBox {
+            self.object
+                .bound_transformed(&(*transform * Mat4::translation(self.offset)))
+        }
+        fn is_emissive(&self) -> bool {
+            self.object.is_emissive()
+        }
+        /// Lights nested deeper are moved along with the rest of the object.
+        fn collect_lights(&self, lights: &mut Vec<Arc<dyn Hittable>>) {
+            let mut inner = Vec::new();
+            self.object.collect_lights(&mut inner);
+            for light in inner {
+                lights.push(Arc::new(Translation::new(light, self.offset)));
+            }
+        }
+        fn describe(&self, describer: &mut Describer) -> Option<ObjectDesc> {
+            Some(ObjectDesc::Translate {
+                offset: self.offset,
+                object: Box::new(describer.object(self.object.as_ref())?),
+            })
+        }
+        fn validate(&self, path: &str, issues: &mut Vec<ValidationIssue>) {
+            if !self.offset.is_finite() {
+                issues.push(ValidationIssue::error(path, "offset is not finite"));
+            }
+            self.object.validate(&format!("{}.object", path), issues);
+        }
+        fn label(&self) -> String {
+            format!("Translation by {}", self.offset)
+        }
+        fn children(&self) -> Vec<&dyn Hittable> {
+            vec![self.object.as_ref()]
+        }
     }
 
     pub struct RotateY {
         object: Arc<dyn Hittable>,
+        pub angle: f64,
         sin_theta: f64,
         cos_theta: f64,
         bounds: BoundingBox,
@@ -144,35 +473,10 @@ pub mod transformation {
             let radians = angle.to_radians();
             let sin_theta = radians.sin();
             let cos_theta = radians.cos();
-            let mut bounds = object.bound();
-
-            for i in 0..2 {
-                for j in 0..2 {
-                    for k in 0..2 {
-                        let x = i as f64 * bounds.intervals[0].end
-                            + (1 - i) as f64 * bounds.intervals[0].start;
-                        let y = j as f64 * bounds.intervals[1].end
-                            + (1 - j) as f64 * bounds.intervals[1].start;
-                        let z = k as f64 * bounds.intervals[2].end
-                            + (1 - k) as f64 * bounds.intervals[2].start;
-
-                        let new_x = cos_theta * x + sin_theta * z;
-                        let new_z = -sin_theta * x + cos_theta * z;
-
-                        let tester = Vec3(new_x, y, new_z);
-
-                        for c in 0..3 {
-                            bounds.intervals[c] = Interval::from_pair(
-                                bounds.intervals[c],
-                                Interval::new(tester[c], tester[c]),
-                            );
-                        }
-                    }
-                }
-            }
-
+            let bounds = object.bound_transformed(&Mat4::rotation_y(angle));
             Self {
                 object,
+                angle,
                 sin_theta,
                 cos_theta,
                 bounds,
@@ -180,8 +484,9 @@ pub mod transformation {
         }
     }
 
-    impl Hittable for RotateY {
-        fn hit(&self, ray: &Ray, t: Interval) -> Option<HitRecord> {
+    impl RotateY {
+        /// `ray` in the object's own, unrotated space.
+        fn rotated(&self, ray: &Ray) -> Ray {
             let mut origin = ray.origin;
             let mut direction = ray.direction;
 
@@ -191,29 +496,484 @@ pub mod transformation {
             direction.0 = self.cos_theta * ray.direction.0 - self.sin_theta * ray.direction.2;
             direction.2 = self.sin_theta * ray.direction.0 + self.cos_theta * ray.direction.2;
 
-            let rotated_ray = Ray { origin, direction };
+            Ray {
+                origin,
+                direction,
+                time: ray.time,
+            }
+        }
 
-            if let Some(mut record) = self.object.hit(&rotated_ray, t) {
-                let mut point = record.point;
-                let mut normal = record.normal;
+        /// Turns a hit in the object's space back into world space.
+        fn unrotate<'a>(&self, mut record: HitRecord<'a>) -> HitRecord<'a> {
+            let mut point = record.point;
+            let mut normal = record.normal;
 
-                point.0 = self.cos_theta * record.point.0 + self.sin_theta * record.point.2;
-                point.2 = -self.sin_theta * record.point.0 + self.cos_theta * record.point.2;
+            point.0 = self.cos_theta * record.point.0 + self.sin_theta * record.point.2;
+            point.2 = -self.sin_theta * record.point.0 + self.cos_theta * record.point.2;
+
+            normal.0 = self.cos_theta * record.normal.0 + self.sin_theta * record.normal.2;
+            normal.2 = -self.sin_theta * record.normal.0 + self.cos_theta * record.normal.2;
+
+            record.point = point;
+            record.normal = normal;
+            record
+        }
+    }
 
-                normal.0 = self.cos_theta * record.normal.0 + self.sin_theta * record.normal.2;
-                normal.2 = -self.sin_theta * record.normal.0 + self.cos_theta * record.normal.2;
+    impl Hittable for RotateY {
+        fn hit(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> Option<HitRecord<'_>> {
+            let record = self.object.hit(&self.rotated(ray), t, rng)?;
+            Some(self.unrotate(record))
+        }
 
-                record.point = point;
-                record.normal = normal;
+        fn hit_with_stats(
+            &self,
+            ray: &Ray,
+            t: Interval,
+            rng: &mut dyn Sampler,
+            stats: &mut TraversalStats,
+        ) -> Option<HitRecord<'_>> {
+            let record = self
+                .object
+                .hit_with_stats(&self.rotated(ray), t, rng, stats)?;
+            Some(self.unrotate(record))
+        }
 
-                Some(record)
-            } else {
-                None
+        fn bound(&self) -> BoundingBox {
+            self.bounds
+        }
+        fn bound_transformed(&self, transform: &Mat4) -> BoundingBox {
+            self.object
+                .bound_transformed(&(*transform * Mat4::rotation_y(self.angle)))
+        }
+        fn is_emissive(&self) -> bool {
+            self.object.is_emissive()
+        }
+        fn collect_lights(&self, lights: &mut Vec<Arc<dyn Hittable>>) {
+            let mut inner = Vec::new();
+            self.object.collect_lights(&mut inner);
+            for light in inner {
+                lights.push(Arc::new(RotateY::new(light, self.angle)));
             }
         }
+        fn describe(&self, describer: &mut Describer) -> Option<ObjectDesc> {
+            Some(ObjectDesc::RotateY {
+                angle: self.angle,
+                object: Box::new(describer.object(self.object.as_ref())?),
+            })
+        }
+        fn validate(&self, path: &str, issues: &mut Vec<ValidationIssue>) {
+            if !self.angle.is_finite() {
+                issues.push(ValidationIssue::error(path, "angle is not finite"));
+            }
+            self.object.validate(&format!("{}.object", path), issues);
+        }
+        fn label(&self) -> String {
+            format!("RotateY by {}°", self.angle)
+        }
+        fn children(&self) -> Vec<&dyn Hittable> {
+            vec![self.object.as_ref()]
+        }
+    }
+
+    /// An object turned by an arbitrary rotation about the origin.
+    pub struct Rotate {
+        object: Arc<dyn Hittable>,
+        pub rotation: Quat,
+        inverse: Quat,
+        bounds: BoundingBox,
+    }
+
+    impl Rotate {
+        pub fn new(object: Arc<dyn Hittable>, rotation: Quat) -> Self {
+            let rotation = rotation.normalized();
+            let bounds = object.bound_transformed(&Mat4::from(rotation));
+            Self {
+                object,
+                rotation,
+                inverse: rotation.inverse(),
+                bounds,
+            }
+        }
+        pub fn from_axis_angle(object: Arc<dyn Hittable>, axis: Vec3, angle: f64) -> Self {
+            Self::new(object, Quat::from_axis_angle(axis, angle))
+        }
+
+        /// `ray` in the object's own, unrotated space.
+        fn rotated(&self, ray: &Ray) -> Ray {
+            Ray {
+                origin: self.inverse.rotate(ray.origin),
+                direction: self.inverse.rotate(ray.direction),
+                time: ray.time,
+            }
+        }
+
+        /// Turns a hit in the object's space back into world space.
+        fn unrotate<'a>(&self, mut record: HitRecord<'a>) -> HitRecord<'a> {
+            record.point = self.rotation.rotate(record.point);
+            record.normal = self.rotation.rotate(record.normal);
+            record
+        }
+    }
+
+    impl Hittable for Rotate {
+        fn hit(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> Option<HitRecord<'_>> {
+            let record = self.object.hit(&self.rotated(ray), t, rng)?;
+            Some(self.unrotate(record))
+        }
+        fn hit_with_stats(
+            &self,
+            ray: &Ray,
+            t: Interval,
+            rng: &mut dyn Sampler,
+            stats: &mut TraversalStats,
+        ) -> Option<HitRecord<'_>> {
+            let record = self
+                .object
+                .hit_with_stats(&self.rotated(ray), t, rng, stats)?;
+            Some(self.unrotate(record))
+        }
+        fn hit_any(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> bool {
+            self.object.hit_any(&self.rotated(ray), t, rng)
+        }
+        fn bound(&self) -> BoundingBox {
+            self.bounds
+        }
+        fn bound_transformed(&self, transform: &Mat4) -> BoundingBox {
+            self.object
+                .bound_transformed(&(*transform * Mat4::from(self.rotation)))
+        }
+        fn is_emissive(&self) -> bool {
+            self.object.is_emissive()
+        }
+        fn collect_lights(&self, lights: &mut Vec<Arc<dyn Hittable>>) {
+            let mut inner = Vec::new();
+            self.object.collect_lights(&mut inner);
+            for light in inner {
+                lights.push(Arc::new(Rotate::new(light, self.rotation)));
+            }
+        }
+        fn describe(&self, describer: &mut Describer) -> Option<ObjectDesc> {
+            let (axis, angle) = self.rotation.to_axis_angle();
+            Some(ObjectDesc::Rotate {
+                axis,
+                angle,
+                object: Box::new(describer.object(self.object.as_ref())?),
+            })
+        }
+        fn validate(&self, path: &str, issues: &mut Vec<ValidationIssue>) {
+            if !self.rotation.is_finite() {
+                issues.push(ValidationIssue::error(path, "rotation is not finite"));
+            }
+            self.object.validate(&format!("{}.object", path), issues);
+        }
+        fn label(&self) -> String {
+            format!("Rotate by {}", rotation_text(self.rotation))
+        }
+        fn children(&self) -> Vec<&dyn Hittable> {
+            vec![self.object.as_ref()]
+        }
+    }
+
+    /// A matrix, its inverse, and the inverse transpose that carries normals.
+    #[derive(Clone, Copy)]
+    struct Placement {
+        matrix: Mat4,
+        inverse: Mat4,
+        normal_matrix: Mat4,
+    }
+
+    impl Placement {
+        fn new(matrix: Mat4, inverse: Mat4) -> Self {
+            Self {
+                matrix,
+                inverse,
+                normal_matrix: inverse.transpose(),
+            }
+        }
+
+        /// `ray` in the object's own space. Its direction isn't renormalized,
+        /// so hit distances carry over unchanged.
+        fn object_ray(&self, ray: &Ray) -> Ray {
+            Ray {
+                origin: self.inverse.transform_point(ray.origin),
+                direction: self.inverse.transform_vector(ray.direction),
+                time: ray.time,
+            }
+        }
+
+        /// Turns a hit in the object's space back into world space.
+        fn world_record<'a>(&self, mut record: HitRecord<'a>) -> HitRecord<'a> {
+            record.point = self.matrix.transform_point(record.point);
+            record.normal = self.normal_matrix.transform_vector(record.normal).unit();
+            record
+        }
+    }
+
+    /// An object placed by an affine matrix, for transforms that `Translation`
+    /// and the rotation wrappers can't express, like non-uniform scales.
+    pub struct Transformed {
+        object: Arc<dyn Hittable>,
+        pub matrix: Mat4,
+        // None for a singular matrix, which flattens the object to nothing.
+        placement: Option<Placement>,
+        bounds: BoundingBox,
+    }
+
+    impl Transformed {
+        pub fn new(object: Arc<dyn Hittable>, matrix: Mat4) -> Self {
+            let placement = matrix
+                .inverse()
+                .map(|inverse| Placement::new(matrix, inverse));
+            let bounds = match &placement {
+                Some(_) => object.bound_transformed(&matrix),
+                None => BoundingBox::empty(),
+            };
+            Self {
+                object,
+                matrix,
+                placement,
+                bounds,
+            }
+        }
+    }
 
+    impl Hittable for Transformed {
+        fn hit(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> Option<HitRecord<'_>> {
+            let placement = self.placement.as_ref()?;
+            let record = self.object.hit(&placement.object_ray(ray), t, rng)?;
+            Some(placement.world_record(record))
+        }
+        fn hit_with_stats(
+            &self,
+            ray: &Ray,
+            t: Interval,
+            rng: &mut dyn Sampler,
+            stats: &mut TraversalStats,
+        ) -> Option<HitRecord<'_>> {
+            let placement = self.placement.as_ref()?;
+            let record = self
+                .object
+                .hit_with_stats(&placement.object_ray(ray), t, rng, stats)?;
+            Some(placement.world_record(record))
+        }
+        fn hit_any(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> bool {
+            self.placement
+                .as_ref()
+                .is_some_and(|placement| self.object.hit_any(&placement.object_ray(ray), t, rng))
+        }
         fn bound(&self) -> BoundingBox {
             self.bounds
         }
+        fn bound_transformed(&self, transform: &Mat4) -> BoundingBox {
+            match self.placement {
+                Some(_) => self.object.bound_transformed(&(*transform * self.matrix)),
+                None => BoundingBox::empty(),
+            }
+        }
+        fn is_emissive(&self) -> bool {
+            self.object.is_emissive()
+        }
+        fn collect_lights(&self, lights: &mut Vec<Arc<dyn Hittable>>) {
+            let mut inner = Vec::new();
+            self.object.collect_lights(&mut inner);
+            for light in inner {
+                lights.push(Arc::new(Transformed::new(light, self.matrix)));
+            }
+        }
+        fn validate(&self, path: &str, issues: &mut Vec<ValidationIssue>) {
+            if self.placement.is_none() {
+                issues.push(ValidationIssue::error(path, "matrix is singular"));
+            }
+            self.object.validate(&format!("{}.object", path), issues);
+        }
+        fn label(&self) -> String {
+            match self.matrix.decompose() {
+                Some((translation, rotation, scale)) => format!(
+                    "Transformed (translation {}, rotation {}, scale {})",
+                    translation,
+                    rotation_text(rotation),
+                    scale
+                ),
+                None => "Transformed (projective)".to_string(),
+            }
+        }
+        fn children(&self) -> Vec<&dyn Hittable> {
+            vec![self.object.as_ref()]
+        }
+    }
+
+    /// Poses the bounding box is sampled at across the shutter.
+    const SWEEP_STEPS: usize = 16;
+
+    /// An object moving from one pose to another while the shutter is open:
+    /// `start` at time 0 and `end` at time 1, in between at the ray's time.
+    pub struct AnimatedTransform {
+        object: Arc<dyn Hittable>,
+        pub start: Pose,
+        pub end: Pose,
+        // Objects that don't move are placed exactly like a `Transformed`.
+        still: Option<Transformed>,
+        bounds: BoundingBox,
+    }
+
+    impl AnimatedTransform {
+        pub fn new(object: Arc<dyn Hittable>, start: Pose, end: Pose) -> Self {
+            let same = |a: Vec3, b: Vec3| a.0 == b.0 && a.1 == b.1 && a.2 == b.2;
+            let moves = !same(start.translation, end.translation)
+                || start.rotation != end.rotation
+                || !same(start.scale, end.scale);
+            if !moves {
+                let still = Transformed::new(object.clone(), start.matrix());
+                return Self {
+                    bounds: still.bound(),
+                    object,
+                    start,
+                    end,
+                    still: Some(still),
+                };
+            }
+
+            // Box the poses at evenly spaced times. Between them, rotating
+            // corners bulge off the straight line by at most r(1 - cos(step / 2)).
+            let original = object.bound();
+            let mut bounds = BoundingBox::empty();
+            for step in 0..=SWEEP_STEPS {
+                let pose = start.lerp(&end, step as f64 / SWEEP_STEPS as f64);
+                bounds = BoundingBox::from_boxes(bounds, object.bound_transformed(&pose.matrix()));
+            }
+            let turn = 2.0
+                * Quat::dot(&start.rotation, &end.rotation)
+                    .abs()
+                    .min(1.0)
+                    .acos();
+            let [x, y, z] = original.intervals;
+            let farthest = |i: Interval| i.start.abs().max(i.end.abs());
+            let radius = Vec3(farthest(x), farthest(y), farthest(z)).length();
+            let largest_scale = [start.scale, end.scale]
+                .iter()
+                .flat_map(|s| [s.0.abs(), s.1.abs(), s.2.abs()])
+                .fold(0.0f64, f64::max);
+            let bulge = radius * largest_scale * (1.0 - (turn / SWEEP_STEPS as f64 / 2.0).cos());
+            for interval in &mut bounds.intervals {
+                *interval = interval.expand(bulge);
+            }
+            Self {
+                object,
+                start,
+                end,
+                still: None,
+                bounds,
+            }
+        }
+
+        /// The pose at `time`, holding still outside the shutter interval.
+        pub fn pose(&self, time: f64) -> Pose {
+            self.start.lerp(&self.end, time.clamp(0.0, 1.0))
+        }
+        fn placement(&self, time: f64) -> Placement {
+            let pose = self.pose(time);
+            Placement::new(pose.matrix(), pose.inverse_matrix())
+        }
+    }
+
+    impl Hittable for AnimatedTransform {
+        fn hit(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> Option<HitRecord<'_>> {
+            if let Some(still) = &self.still {
+                return still.hit(ray, t, rng);
+            }
+            let placement = self.placement(ray.time);
+            let record = self.object.hit(&placement.object_ray(ray), t, rng)?;
+            Some(placement.world_record(record))
+        }
+        fn hit_with_stats(
+            &self,
+            ray: &Ray,
+            t: Interval,
+            rng: &mut dyn Sampler,
+            stats: &mut TraversalStats,
+        ) -> Option<HitRecord<'_>> {
+            if let Some(still) = &self.still {
+                return still.hit_with_stats(ray, t, rng, stats);
+            }
+            let placement = self.placement(ray.time);
+            let record = self
+                .object
+                .hit_with_stats(&placement.object_ray(ray), t, rng, stats)?;
+            Some(placement.world_record(record))
+        }
+        fn hit_any(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> bool {
+            if let Some(still) = &self.still {
+                return still.hit_any(ray, t, rng);
+            }
+            let placement = self.placement(ray.time);
+            self.object.hit_any(&placement.object_ray(ray), t, rng)
+        }
+        fn bound(&self) -> BoundingBox {
+            self.bounds
+        }
+        fn bound_transformed(&self, transform: &Mat4) -> BoundingBox {
+            match &self.still {
+                Some(still) => still.bound_transformed(transform),
+                None => self.bound().transformed(transform),
+            }
+        }
+        fn is_emissive(&self) -> bool {
+            self.object.is_emissive()
+        }
+        fn collect_lights(&self, lights: &mut Vec<Arc<dyn Hittable>>) {
+            let mut inner = Vec::new();
+            self.object.collect_lights(&mut inner);
+            for light in inner {
+                lights.push(Arc::new(AnimatedTransform::new(
+                    light, self.start, self.end,
+                )));
+            }
+        }
+        fn describe(&self, describer: &mut Describer) -> Option<ObjectDesc> {
+            let object = describer.object(self.object.as_ref())?;
+            Some(ObjectDesc::Animate {
+                start: self.start.into(),
+                end: self.end.into(),
+                object: Box::new(object),
+            })
+        }
+        fn validate(&self, path: &str, issues: &mut Vec<ValidationIssue>) {
+            for (name, pose) in [("start", &self.start), ("end", &self.end)] {
+                if !pose.is_finite() {
+                    issues.push(ValidationIssue::error(
+                        path,
+                        format!("{} pose is not finite", name),
+                    ));
+                } else if pose.scale.0 * pose.scale.1 * pose.scale.2 == 0.0 {
+                    issues.push(ValidationIssue::error(
+                        path,
+                        format!("{} pose has a zero scale", name),
+                    ));
+                }
+            }
+            self.object.validate(&format!("{}.object", path), issues);
+        }
+        fn label(&self) -> String {
+            format!(
+                "AnimatedTransform (translation {} to {}, rotation {} to {})",
+                self.start.translation,
+                self.end.translation,
+                rotation_text(self.start.rotation),
+                rotation_text(self.end.rotation)
+            )
+        }
+        fn children(&self) -> Vec<&dyn Hittable> {
+            vec![self.object.as_ref()]
+        }
     }
 }
+
+debug_as_hierarchy!(
+    HittableList,
+    Translation,
+    RotateY,
+    Rotate,
+    Transformed,
+    AnimatedTransform
+);
@@ -0,0 +1,320 @@
+use std::{f64::consts::PI, fmt, str::FromStr, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{BoundingBox, DeltaLight, Distribution1D, LightEstimate, Point, Vec3};
+
+/// How each diffuse hit chooses the delta lights it sends shadow rays toward.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LightSampling {
+    /// A shadow ray toward every light at every hit.
+    #[default]
+    All,
+    /// One shadow ray, toward a light chosen uniformly.
+    Uniform,
+    /// One shadow ray, toward a light chosen in proportion to its power.
+    Power,
+    /// One shadow ray, toward a light found by descending a tree of clusters
+    /// of lights, choosing each step by the clusters' power over their
+    /// distance squared from the hit.
+    Tree,
+}
+
+impl LightSampling {
+    pub fn is_default(&self) -> bool {
+        *self == LightSampling::default()
+    }
+}
+
+impl fmt::Display for LightSampling {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LightSampling::All => write!(f, "all"),
+            LightSampling::Uniform => write!(f, "uniform"),
+            LightSampling::Power => write!(f, "power"),
+            LightSampling::Tree => write!(f, "tree"),
+        }
+    }
+}
+
+impl FromStr for LightSampling {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(LightSampling::All),
+            "uniform" => Ok(LightSampling::Uniform),
+            "power" => Ok(LightSampling::Power),
+            "tree" => Ok(LightSampling::Tree),
+            _ => Err(format!(
+                "unknown light sampling '{}' (expected all, uniform, power, or tree)",
+                s
+            )),
+        }
+    }
+}
+
+/// Picks one of a scene's delta lights for a shadow ray, with the
+/// probability of picking it, so the light it brings can be divided by that
+/// probability and the estimate stays unbiased.
+#[derive(Clone, Debug)]
+pub enum LightSelector {
+    Uniform(usize),
+    Power(Distribution1D),
+    Tree(LightTree),
+}
+
+impl LightSelector {
+    /// A selector over `lights` for `sampling`, or `None` for
+    /// `LightSampling::All`, which picks none. Lights infinitely far away
+    /// count as lighting a disk of radius `scene_radius`.
+    pub fn new(
+        sampling: LightSampling,
+        lights: &[Arc<dyn DeltaLight>],
+        scene_radius: f64,
+    ) -> Option<Self> {
+        Some(match sampling {
+            LightSampling::All => return None,
+            LightSampling::Uniform => LightSelector::Uniform(lights.len()),
+            LightSampling::Power => LightSelector::Power(Distribution1D::new(
+                lights
+                    .iter()
+                    .map(|light| light.estimate().power(scene_radius))
+                    .collect(),
+            )),
+            LightSampling::Tree => LightSelector::Tree(LightTree::new(lights)),
+        })
+    }
+
+    /// How many lights it picks among.
+    pub fn len(&self) -> usize {
+        match self {
+            LightSelector::Uniform(count) => *count,
+            LightSelector::Power(distribution) => distribution.len(),
+            LightSelector::Tree(tree) => tree.count,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The index of the light `xi` in [0, 1) picks for a hit at `point`, and
+    /// the probability of picking it. `None` if no light has any power.
+    pub fn pick(&self, point: Point, xi: f64) -> Option<(usize, f64)> {
+        match self {
+            LightSelector::Uniform(0) => None,
+            LightSelector::Uniform(count) => {
+                let i = ((xi * *count as f64) as usize).min(count - 1);
+                Some((i, 1.0 / *count as f64))
+            }
+            LightSelector::Power(distribution) => {
+                let (i, _) = distribution.sample(xi)?;
+                Some((i, distribution.density(i) / distribution.len() as f64))
+            }
+            LightSelector::Tree(tree) => tree.pick(point, xi),
+        }
+    }
+
+    /// The probability of `pick` choosing light `index` for a hit at `point`.
+    pub fn probability(&self, point: Point, index: usize) -> f64 {
+        match self {
+            LightSelector::Uniform(count) => {
+                if index < *count {
+                    1.0 / *count as f64
+                } else {
+                    0.0
+                }
+            }
+            LightSelector::Power(distribution) => {
+                distribution.density(index) / distribution.len() as f64
+            }
+            LightSelector::Tree(tree) => tree.probability(point, index),
+        }
+    }
+}
+
+impl LightEstimate {
+    /// The light's power, counting a light infinitely far away as lighting
+    /// a disk of radius `scene_radius`.
+    pub fn power(&self, scene_radius: f64) -> f64 {
+        match *self {
+            LightEstimate::Local { power, .. } => power,
+            LightEstimate::Distant { irradiance } => irradiance * PI * scene_radius * scene_radius,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Children {
+    Leaf(usize),
+    Inner(usize, usize),
+}
+
+/// A cluster of lights: the box around them, their power, and either the
+/// one light or the two clusters it splits into.
+#[derive(Clone, Copy, Debug)]
+struct LightNode {
+    bounds: BoundingBox,
+    power: f64,
+    children: Children,
+}
+
+/// A binary tree over the lights with positions, split down the middle of
+/// the longest axis of each cluster, alongside the lights infinitely far
+/// away, which light every point alike.
+#[derive(Clone, Debug)]
+pub struct LightTree {
+    /// The root first, each inner node's children after it.
+    nodes: Vec<LightNode>,
+    /// The index and irradiance of each light infinitely far away.
+    distant: Vec<(usize, f64)>,
+    /// The parent of each node.
+    parents: Vec<Option<usize>>,
+    /// The leaf holding each light with a position.
+    leaves: Vec<Option<usize>>,
+    count: usize,
+}
+
+impl LightTree {
+    pub fn new(lights: &[Arc<dyn DeltaLight>]) -> Self {
+        let mut local = Vec::new();
+        let mut distant = Vec::new();
+        for (i, light) in lights.iter().enumerate() {
+            match light.estimate() {
+                LightEstimate::Local { position, power } => local.push((i, position, power)),
+                LightEstimate::Distant { irradiance } => distant.push((i, irradiance)),
+            }
+        }
+        let mut tree = Self {
+            nodes: Vec::new(),
+            distant,
+            parents: Vec::new(),
+            leaves: vec![None; lights.len()],
+            count: lights.len(),
+        };
+        if !local.is_empty() {
+            tree.build(&mut local, None);
+        }
+        tree
+    }
+
+    /// Adds the node over `lights` under `parent`, returning its index.
+    fn build(&mut self, lights: &mut [(usize, Point, f64)], parent: Option<usize>) -> usize {
+        let index = self.nodes.len();
+        let bounds = BoundingBox::around(lights.iter().map(|&(_, position, _)| position));
+        let power = lights.iter().map(|&(_, _, power)| power).sum();
+        self.nodes.push(LightNode {
+            bounds,
+            power,
+            children: Children::Leaf(lights[0].0),
+        });
+        self.parents.push(parent);
+        if let [(light, _, _)] = lights {
+            self.leaves[*light] = Some(index);
+            return index;
+        }
+        let axis = bounds.longest_axis();
+        lights.sort_by(|a, b| a.1[axis].total_cmp(&b.1[axis]));
+        let (near, far) = lights.split_at_mut(lights.len() / 2);
+        let left = self.build(near, Some(index));
+        let right = self.build(far, Some(index));
+        self.nodes[index].children = Children::Inner(left, right);
+        index
+    }
+
+    /// How much light the cluster at `node` might bring to `point`: its
+    /// power over the distance squared to its center, but never more than
+    /// from as near as its own extent.
+    fn importance(&self, node: usize, point: Point) -> f64 {
+        let LightNode { bounds, power, .. } = self.nodes[node];
+        let [x, y, z] = bounds.intervals;
+        let center = Vec3(
+            (x.start + x.end) / 2.0,
+            (y.start + y.end) / 2.0,
+            (z.start + z.end) / 2.0,
+        );
+        let extent = Vec3(x.size(), y.size(), z.size()).length_squared() / 4.0;
+        let distance = (point - center).length_squared().max(extent).max(1e-12);
+        power / (4.0 * PI * distance)
+    }
+
+    /// The weights of the choices at the top: the tree's root, if any
+    /// light has a position, then each light infinitely far away.
+    fn top(&self, point: Point) -> Vec<f64> {
+        let root = (!self.nodes.is_empty()).then(|| self.importance(0, point));
+        root.into_iter()
+            .chain(self.distant.iter().map(|&(_, irradiance)| irradiance))
+            .collect()
+    }
+
+    fn pick(&self, point: Point, xi: f64) -> Option<(usize, f64)> {
+        let top = self.top(point);
+        let (mut choice, mut xi, mut probability) = choose(&top, xi)?;
+        if self.nodes.is_empty() || choice > 0 {
+            if !self.nodes.is_empty() {
+                choice -= 1;
+            }
+            return Some((self.distant[choice].0, probability));
+        }
+        let mut node = 0;
+        while let Children::Inner(left, right) = self.nodes[node].children {
+            let weights = [self.importance(left, point), self.importance(right, point)];
+            let (side, rest, odds) = choose(&weights, xi)?;
+            node = if side == 0 { left } else { right };
+            xi = rest;
+            probability *= odds;
+        }
+        match self.nodes[node].children {
+            Children::Leaf(light) => Some((light, probability)),
+            Children::Inner(..) => None,
+        }
+    }
+
+    fn probability(&self, point: Point, index: usize) -> f64 {
+        let top = self.top(point);
+        let total: f64 = top.iter().sum();
+        if total.is_nan() || total <= 0.0 {
+            return 0.0;
+        }
+        if let Some(at) = self.distant.iter().position(|&(light, _)| light == index) {
+            let offset = usize::from(!self.nodes.is_empty());
+            return top[at + offset] / total;
+        }
+        let Some(Some(mut node)) = self.leaves.get(index).copied() else {
+            return 0.0;
+        };
+        let mut probability = 1.0;
+        while let Some(parent) = self.parents[node] {
+            let Children::Inner(left, right) = self.nodes[parent].children else {
+                return 0.0;
+            };
+            let weights = (self.importance(left, point), self.importance(right, point));
+            let chosen = if left == node { weights.0 } else { weights.1 };
+            probability *= chosen / (weights.0 + weights.1);
+            node = parent;
+        }
+        probability * top[0] / total
+    }
+}
+
+/// The choice `xi` makes among `weights`, what's left of `xi` for the next
+/// choice, rescaled to [0, 1), and the probability of the choice.
+fn choose(weights: &[f64], xi: f64) -> Option<(usize, f64, f64)> {
+    let total: f64 = weights.iter().sum();
+    if !total.is_finite() || total <= 0.0 {
+        return None;
+    }
+    let mut start = 0.0;
+    for (i, &weight) in weights.iter().enumerate() {
+        let probability = weight / total;
+        let end = start + probability;
+        if probability > 0.0 && (xi < end || i == weights.len() - 1) {
+            let rest = ((xi - start) / probability).clamp(0.0, 1.0 - f64::EPSILON);
+            return Some((i, rest, probability));
+        }
+        start = end;
+    }
+    None
+}
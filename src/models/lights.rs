@@ -0,0 +1,337 @@
+use crate::{
+    scenes::{LightDesc, ValidationIssue},
+    vec3::*,
+    Environment, EnvironmentSampling, Hittable, HittableList, LightSampling, LightSelector, Point,
+    Portal, PortalPdf,
+};
+
+use std::{
+    borrow::Cow,
+    f64::consts::PI,
+    sync::{Arc, OnceLock},
+};
+
+/// Light arriving at a point from a delta light.
+#[derive(Clone, Copy, Debug)]
+pub struct LightSample {
+    /// Unit direction from the point toward the light.
+    pub direction: Vec3,
+    /// Distance to the light along `direction`; infinite for distant lights.
+    pub distance: f64,
+    /// Irradiance at the point from a surface facing the light head on.
+    pub radiance: Color,
+}
+
+/// A light with no area. Rays can never hit one, so the integrator samples it
+/// directly with a shadow ray from every diffuse hit instead.
+pub trait DeltaLight: Send + Sync {
+    /// The light reaching `point`, or `None` if none can.
+    fn sample(&self, point: Point) -> Option<LightSample>;
+
+    /// Roughly how much light it gives off, for choosing which lights to
+    /// sample when there are many.
+    fn estimate(&self) -> LightEstimate;
+
+    /// Describes the light for scene export.
+    fn describe(&self) -> LightDesc;
+
+    /// Reports problems with the light. `path` names it in the reported issues.
+    fn validate(&self, _path: &str, _issues: &mut Vec<ValidationIssue>) {}
+}
+
+/// How much light a delta light gives off, as luminance.
+#[derive(Clone, Copy, Debug)]
+pub enum LightEstimate {
+    /// A light at `position` radiating `power` in all.
+    Local { position: Point, power: f64 },
+    /// A light infinitely far away, delivering `irradiance` everywhere.
+    Distant { irradiance: f64 },
+}
+
+/// A light radiating `intensity` equally in every direction from `position`,
+/// falling off with the square of the distance.
+pub struct PointLight {
+    pub position: Point,
+    pub intensity: Color,
+}
+
+impl PointLight {
+    pub fn new(position: Point, intensity: Color) -> Self {
+        Self {
+            position,
+            intensity,
+        }
+    }
+}
+
+impl DeltaLight for PointLight {
+    fn sample(&self, point: Point) -> Option<LightSample> {
+        let offset = self.position - point;
+        let distance = offset.length();
+        if distance <= 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            direction: offset / distance,
+            distance,
+            radiance: self.intensity / (distance * distance),
+        })
+    }
+    fn estimate(&self) -> LightEstimate {
+        LightEstimate::Local {
+            position: self.position,
+            power: 4.0 * PI * self.intensity.luminance(),
+        }
+    }
+    fn describe(&self) -> LightDesc {
+        LightDesc::Point {
+            position: self.position,
+            intensity: self.intensity,
+        }
+    }
+    fn validate(&self, path: &str, issues: &mut Vec<ValidationIssue>) {
+        if !self.position.is_finite() {
+            issues.push(ValidationIssue::error(path, "position is not finite"));
+        }
+        validate_power(self.intensity, "intensity", path, issues);
+    }
+}
+
+/// A light infinitely far away, like the sun: parallel rays travelling along
+/// `direction` deliver the same `irradiance` everywhere.
+pub struct DirectionalLight {
+    pub direction: Vec3,
+    pub irradiance: Color,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vec3, irradiance: Color) -> Self {
+        Self {
+            direction,
+            irradiance,
+        }
+    }
+}
+
+impl DeltaLight for DirectionalLight {
+    fn sample(&self, _point: Point) -> Option<LightSample> {
+        if self.direction.near_zero() {
+            return None;
+        }
+        Some(LightSample {
+            direction: -self.direction.unit(),
+            distance: f64::INFINITY,
+            radiance: self.irradiance,
+        })
+    }
+    fn estimate(&self) -> LightEstimate {
+        LightEstimate::Distant {
+            irradiance: self.irradiance.luminance(),
+        }
+    }
+    fn describe(&self) -> LightDesc {
+        LightDesc::Directional {
+            direction: self.direction,
+            irradiance: self.irradiance,
+        }
+    }
+    fn validate(&self, path: &str, issues: &mut Vec<ValidationIssue>) {
+        if !self.direction.is_finite() || self.direction.near_zero() {
+            issues.push(ValidationIssue::error(
+                path,
+                "direction must be a finite, nonzero vector",
+            ));
+        }
+        validate_power(self.irradiance, "irradiance", path, issues);
+    }
+}
+
+/// A point light shining along `direction`: at full `intensity` within
+/// `inner_angle` of its axis, fading smoothly to nothing at `outer_angle`.
+/// Angles are in degrees from the axis.
+pub struct SpotLight {
+    pub position: Point,
+    pub direction: Vec3,
+    pub inner_angle: f64,
+    pub outer_angle: f64,
+    pub intensity: Color,
+    cos_inner: f64,
+    cos_outer: f64,
+}
+
+impl SpotLight {
+    pub fn new(
+        position: Point,
+        direction: Vec3,
+        inner_angle: f64,
+        outer_angle: f64,
+        intensity: Color,
+    ) -> Self {
+        Self {
+            position,
+            direction,
+            inner_angle,
+            outer_angle,
+            intensity,
+            cos_inner: inner_angle.to_radians().cos(),
+            cos_outer: outer_angle.to_radians().cos(),
+        }
+    }
+
+    /// The fraction of `intensity` sent along unit `direction` out of the light.
+    pub fn falloff(&self, direction: Vec3) -> f64 {
+        let cosine = Vec3::dot(&direction, &self.direction.unit());
+        if cosine >= self.cos_inner {
+            1.0
+        } else if cosine <= self.cos_outer {
+            0.0
+        } else {
+            let t = (cosine - self.cos_outer) / (self.cos_inner - self.cos_outer);
+            t * t * (3.0 - 2.0 * t)
+        }
+    }
+}
+
+impl DeltaLight for SpotLight {
+    fn sample(&self, point: Point) -> Option<LightSample> {
+        let offset = self.position - point;
+        let distance = offset.length();
+        if distance <= 0.0 || self.direction.near_zero() {
+            return None;
+        }
+        let direction = offset / distance;
+        let falloff = self.falloff(-direction);
+        if falloff <= 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: self.intensity * (falloff / (distance * distance)),
+        })
+    }
+    fn estimate(&self) -> LightEstimate {
+        // The solid angle of a cone halfway between the inner and outer ones.
+        let cosine = (self.cos_inner + self.cos_outer) / 2.0;
+        LightEstimate::Local {
+            position: self.position,
+            power: 2.0 * PI * (1.0 - cosine) * self.intensity.luminance(),
+        }
+    }
+    fn describe(&self) -> LightDesc {
+        LightDesc::Spot {
+            position: self.position,
+            direction: self.direction,
+            inner_angle: self.inner_angle,
+            outer_angle: self.outer_angle,
+            intensity: self.intensity,
+        }
+    }
+    fn validate(&self, path: &str, issues: &mut Vec<ValidationIssue>) {
+        if !self.position.is_finite() {
+            issues.push(ValidationIssue::error(path, "position is not finite"));
+        }
+        if !self.direction.is_finite() || self.direction.near_zero() {
+            issues.push(ValidationIssue::error(
+                path,
+                "direction must be a finite, nonzero vector",
+            ));
+        }
+        if !(self.outer_angle > 0.0 && self.outer_angle <= 180.0) {
+            issues.push(ValidationIssue::error(
+                path,
+                format!(
+                    "outer angle must be in (0, 180] degrees, got {}",
+                    self.outer_angle
+                ),
+            ));
+        } else if !(self.inner_angle >= 0.0 && self.inner_angle <= self.outer_angle) {
+            issues.push(ValidationIssue::error(
+                path,
+                format!(
+                    "inner angle must be between 0 and the outer angle, got {}",
+                    self.inner_angle
+                ),
+            ));
+        }
+        validate_power(self.intensity, "intensity", path, issues);
+    }
+}
+
+fn validate_power(power: Color, name: &str, path: &str, issues: &mut Vec<ValidationIssue>) {
+    if !power.is_finite() || power.0 < 0.0 || power.1 < 0.0 || power.2 < 0.0 {
+        issues.push(ValidationIssue::error(
+            path,
+            format!("{} must be finite and non-negative", name),
+        ));
+    }
+}
+
+/// The lights of a scene: emissive objects, which rays can also hit, delta
+/// lights, which only direct sampling can reach, and the environment around
+/// it all, which both can, through `portals` from inside them.
+#[derive(Default)]
+pub struct LightList {
+    pub area: HittableList,
+    pub delta: Vec<Arc<dyn DeltaLight>>,
+    pub environment: Option<Arc<Environment>>,
+    pub portals: Vec<Portal>,
+    sampling: LightSampling,
+    /// Built for `sampling` by the first shadow ray that needs it.
+    selector: OnceLock<Option<LightSelector>>,
+}
+
+impl LightList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.area.objects.is_empty() && self.delta.is_empty() && self.environment.is_none()
+    }
+
+    pub fn sampling(&self) -> LightSampling {
+        self.sampling
+    }
+
+    /// Chooses how hits pick the delta lights they send shadow rays toward.
+    pub fn set_sampling(&mut self, sampling: LightSampling) {
+        self.sampling = sampling;
+        self.delta_changed();
+    }
+
+    /// Forgets the choice of lights built for the delta lights there were,
+    /// after lights are replaced rather than just added or removed.
+    pub fn delta_changed(&mut self) {
+        self.selector = OnceLock::new();
+    }
+
+    /// What picks one delta light for each shadow ray, or `None` if every
+    /// light gets one. Lights infinitely far away are weighed as lighting
+    /// all of `world`.
+    pub fn selector(&self, world: &HittableList) -> Option<Cow<'_, LightSelector>> {
+        let build = || {
+            let [x, y, z] = world.bound().intervals;
+            let radius = Vec3(x.size(), y.size(), z.size()).length() / 2.0;
+            let radius = if radius.is_finite() { radius } else { 1.0 };
+            LightSelector::new(self.sampling, &self.delta, radius)
+        };
+        let selector = self.selector.get_or_init(build).as_ref()?;
+        // Lights added or removed since it was built need another.
+        if selector.len() == self.delta.len() {
+            Some(Cow::Borrowed(selector))
+        } else {
+            build().map(Cow::Owned)
+        }
+    }
+
+    /// How shadow rays toward the environment are drawn from `point`, or
+    /// `None` if the environment isn't sampled directly.
+    pub fn environment_pdf(&self, point: Point) -> Option<EnvironmentSampling<'_>> {
+        let pdf = self.environment.as_ref()?.pdf()?;
+        Some(match PortalPdf::new(&self.portals, point) {
+            Some(portals) => EnvironmentSampling::Portals(portals),
+            None => EnvironmentSampling::Image(pdf),
+        })
+    }
+}
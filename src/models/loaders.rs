@@ -0,0 +1,190 @@
+use crate::{
+    AssetError, AssetResolver, Dielectric, Instant, Lambertian, Material, MeshSource, Plastic,
+    Stage, Texture, Transform, TriangleMesh, VoxelGrid,
+};
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+#[cfg(not(feature = "obj"))]
+mod obj;
+mod ply;
+mod report;
+mod stl;
+#[cfg(feature = "obj")]
+mod three_d_obj;
+mod vol;
+
+#[cfg(not(feature = "obj"))]
+pub use obj::*;
+pub use ply::*;
+pub use report::*;
+pub use stl::*;
+#[cfg(feature = "obj")]
+pub use three_d_obj::*;
+pub use vol::*;
+
+#[derive(Debug)]
+pub enum LoadError {
+    NotFound(PathBuf),
+    Missing(AssetError),
+    Decode { path: PathBuf, message: String },
+    UnsupportedGeometry { path: PathBuf, kind: &'static str },
+    Empty(PathBuf),
+    UnsupportedFormat(PathBuf),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::NotFound(path) => write!(f, "file not found: {}", path.display()),
+            LoadError::Missing(e) => write!(f, "{}", e),
+            LoadError::Decode { path, message } => {
+                write!(f, "could not load {}: {}", path.display(), message)
+            }
+            LoadError::UnsupportedGeometry { path, kind } => write!(
+                f,
+                "{} contains {} geometry, expected a triangle mesh",
+                path.display(),
+                kind
+            ),
+            LoadError::Empty(path) => write!(f, "{} contains no triangles", path.display()),
+            LoadError::UnsupportedFormat(path) => {
+                write!(f, "unsupported model format: {}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+/// Finds the mesh `reference` points to with `resolver` and loads it, choosing
+/// the format from the file extension.
+pub fn load_model(
+    reference: &Path,
+    resolver: &AssetResolver,
+    default_material: Arc<dyn Material>,
+    transform: Option<Transform>,
+) -> Result<TriangleMesh, LoadError> {
+    let _stage = Stage::start("load assets");
+    let mesh = load_resolved(reference, resolver, default_material, transform)?;
+    if let Some(report) = &mesh.report {
+        notify_loaded(report);
+    }
+    Ok(mesh)
+}
+
+fn load_resolved(
+    reference: &Path,
+    resolver: &AssetResolver,
+    default_material: Arc<dyn Material>,
+    transform: Option<Transform>,
+) -> Result<TriangleMesh, LoadError> {
+    let path = &resolver.resolve(reference).map_err(LoadError::Missing)?;
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    let mut mesh = match extension.as_deref() {
+        Some("obj") => load_obj(path, default_material, transform),
+        Some("ply") => load_ply(path, default_material, transform),
+        Some("stl") => load_stl(path, default_material, transform),
+        _ => Err(LoadError::UnsupportedFormat(path.to_path_buf())),
+    }?;
+    mesh.source = Some(MeshSource {
+        path: path.to_path_buf(),
+        transform,
+        normalize: None,
+        displacement: None,
+    });
+    Ok(mesh)
+}
+
+/// Finds the density grid `reference` points to with `resolver` and loads
+/// it; only Mitsuba's `.vol` format is read.
+pub fn load_volume(reference: &Path, resolver: &AssetResolver) -> Result<VoxelGrid, LoadError> {
+    let _stage = Stage::start("load assets");
+    let path = &resolver.resolve(reference).map_err(LoadError::Missing)?;
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    let mut grid = match extension.as_deref() {
+        Some("vol") => load_vol(path),
+        _ => Err(LoadError::UnsupportedFormat(path.to_path_buf())),
+    }?;
+    grid.path = Some(path.to_path_buf());
+    Ok(grid)
+}
+
+/// Loads a mesh like `load_model`, but first normalizes it to `target_extent`
+/// (see `TriangleMesh::normalize`) and only then applies `transform`, so the
+/// transform places a model of known size. Also returns the normalizing transform.
+pub fn load_model_normalized(
+    reference: &Path,
+    resolver: &AssetResolver,
+    default_material: Arc<dyn Material>,
+    target_extent: f64,
+    transform: Option<Transform>,
+) -> Result<(TriangleMesh, Transform), LoadError> {
+    let _stage = Stage::start("load assets");
+    let mut mesh = load_resolved(reference, resolver, default_material, None)?;
+    let path = mesh
+        .source
+        .take()
+        .map(|source| source.path)
+        .unwrap_or_default();
+    let mut bvh_time = mesh.build_time();
+    let normalization = mesh.normalize(target_extent);
+    bvh_time += mesh.build_time();
+    if let Some(transform) = &transform {
+        mesh.apply_transform(transform);
+        bvh_time += mesh.build_time();
+    }
+    mesh.source = Some(MeshSource {
+        path,
+        transform,
+        normalize: Some(target_extent),
+        displacement: None,
+    });
+    let bounds = mesh.bounds();
+    if let Some(report) = &mut mesh.report {
+        report.bounds = bounds;
+        report.bvh_time = bvh_time;
+        notify_loaded(report);
+    }
+    Ok((mesh, normalization))
+}
+
+/// Records the report of a mesh loaded from `path` since `started`.
+fn finish_load(
+    path: &Path,
+    mut mesh: TriangleMesh,
+    normals: bool,
+    started: Instant,
+) -> Result<TriangleMesh, LoadError> {
+    let parse_time = started.elapsed().saturating_sub(mesh.build_time());
+    mesh.report = Some(LoadReport::new(path, &mesh, normals, parse_time));
+    Ok(mesh)
+}
+
+/// The closest material here to an MTL material: translucent surfaces
+/// become glass, specular ones plastic, the rest diffuse.
+fn mtl_material(
+    texture: Arc<dyn Texture>,
+    translucent: bool,
+    refraction_index: f64,
+    metallic: f64,
+    roughness: f64,
+) -> Arc<dyn Material> {
+    if translucent {
+        Arc::new(Dielectric::new(refraction_index))
+    } else if metallic > 0.0 {
+        Arc::new(Plastic::new(texture, metallic, roughness))
+    } else {
+        Arc::new(Lambertian::new(texture))
+    }
+}
@@ -0,0 +1,352 @@
+use crate::{
+    color, point, AssetResolver, Color, Instant, LoadError, Material, MeshData, Point, SolidColor,
+    Texture, TextureRegistry, Transform, TriangleMesh, Vec3, DEFAULT_SMOOTHING_ANGLE,
+};
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use super::{finish_load, mtl_material, progress_for};
+
+/// A face corner: indices of its position, uv, and normal.
+type Corner = (usize, Option<usize>, Option<usize>);
+
+/// What the parser keeps of an OBJ file. Objects and groups are merged.
+#[derive(Default)]
+struct Obj {
+    positions: Vec<Point>,
+    /// The colors some exporters append to vertices (`v x y z r g b`).
+    colors: Vec<Option<Color>>,
+    uvs: Vec<(f64, f64)>,
+    normals: Vec<Vec3>,
+    /// Faces split into triangles, fanning out from their first corner.
+    triangles: Vec<[Corner; 3]>,
+    /// The `usemtl` name in effect for each triangle, if any.
+    triangle_materials: Vec<Option<usize>>,
+    material_names: Vec<String>,
+    library: Option<String>,
+}
+
+enum ObjError {
+    /// Point or line elements, named like `LoadError::UnsupportedGeometry`.
+    Geometry(&'static str),
+    Syntax(String),
+}
+
+fn numbers(tokens: std::str::SplitWhitespace, line: usize) -> Result<Vec<f64>, ObjError> {
+    tokens
+        .map(|token| {
+            token
+                .parse()
+                .map_err(|_| ObjError::Syntax(format!("line {}: bad number '{}'", line, token)))
+        })
+        .collect()
+}
+
+/// Resolves a 1-based OBJ index, or a negative one counting back from the
+/// last of `count` elements, to a 0-based one.
+fn index(token: &str, count: usize, line: usize) -> Result<usize, ObjError> {
+    let bad = || ObjError::Syntax(format!("line {}: bad index '{}'", line, token));
+    let i: i64 = token.parse().map_err(|_| bad())?;
+    let resolved = match i {
+        i if i > 0 => i - 1,
+        i if i < 0 => count as i64 + i,
+        _ => return Err(bad()),
+    };
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(bad());
+    }
+    Ok(resolved as usize)
+}
+
+fn parse_obj(text: &str) -> Result<Obj, ObjError> {
+    let mut obj = Obj::default();
+    let mut material = None;
+    for (number, line) in text.lines().enumerate() {
+        let number = number + 1;
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        match keyword {
+            "v" => match numbers(tokens, number)?[..] {
+                [x, y, z] => {
+                    obj.positions.push(point(x, y, z));
+                    obj.colors.push(None);
+                }
+                [x, y, z, r, g, b] => {
+                    obj.positions.push(point(x, y, z));
+                    obj.colors.push(Some(color(r, g, b)));
+                }
+                // A weight, which only matters for rational curves.
+                [x, y, z, _] => {
+                    obj.positions.push(point(x, y, z));
+                    obj.colors.push(None);
+                }
+                _ => return Err(ObjError::Syntax(format!("line {}: bad vertex", number))),
+            },
+            "vt" => match numbers(tokens, number)?[..] {
+                [u] => obj.uvs.push((u, 0.0)),
+                [u, v, ..] => obj.uvs.push((u, v)),
+                _ => return Err(ObjError::Syntax(format!("line {}: bad uv", number))),
+            },
+            "vn" => match numbers(tokens, number)?[..] {
+                [x, y, z] => obj.normals.push(Vec3(x, y, z)),
+                _ => return Err(ObjError::Syntax(format!("line {}: bad normal", number))),
+            },
+            "f" => {
+                let corners = tokens
+                    .map(|corner| {
+                        let mut parts = corner.split('/');
+                        let position =
+                            index(parts.next().unwrap_or(""), obj.positions.len(), number)?;
+                        let uv = match parts.next() {
+                            Some("") | None => None,
+                            Some(uv) => Some(index(uv, obj.uvs.len(), number)?),
+                        };
+                        let normal = match parts.next() {
+                            Some("") | None => None,
+                            Some(normal) => Some(index(normal, obj.normals.len(), number)?),
+                        };
+                        Ok((position, uv, normal))
+                    })
+                    .collect::<Result<Vec<Corner>, _>>()?;
+                match corners.len() {
+                    1 => return Err(ObjError::Geometry("point")),
+                    2 => return Err(ObjError::Geometry("line")),
+                    _ => {}
+                }
+                for pair in corners[1..].windows(2) {
+                    obj.triangles.push([corners[0], pair[0], pair[1]]);
+                    obj.triangle_materials.push(material);
+                }
+            }
+            "p" => return Err(ObjError::Geometry("point")),
+            "l" => return Err(ObjError::Geometry("line")),
+            "usemtl" => {
+                let name = tokens.collect::<Vec<_>>().join(" ");
+                material = Some(
+                    match obj.material_names.iter().position(|known| *known == name) {
+                        Some(i) => i,
+                        None => {
+                            obj.material_names.push(name);
+                            obj.material_names.len() - 1
+                        }
+                    },
+                );
+            }
+            "mtllib" => obj.library = Some(tokens.collect::<Vec<_>>().join(" ")),
+            // Objects, groups, smoothing groups, and anything else.
+            _ => {}
+        }
+    }
+    Ok(obj)
+}
+
+/// The parts of an MTL material this loader uses.
+struct Mtl {
+    name: String,
+    diffuse: Color,
+    specular: Color,
+    shininess: f64,
+    alpha: f64,
+    refraction_index: f64,
+    diffuse_map: Option<String>,
+}
+
+fn parse_mtl(text: &str, path: &Path) -> Result<Vec<Mtl>, LoadError> {
+    let error = |number: usize, message: &str| LoadError::Decode {
+        path: path.to_path_buf(),
+        message: format!("line {}: {}", number, message),
+    };
+    let mut materials: Vec<Mtl> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let number = number + 1;
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        if keyword == "newmtl" {
+            materials.push(Mtl {
+                name: tokens.collect::<Vec<_>>().join(" "),
+                diffuse: color(1., 1., 1.),
+                specular: color(0., 0., 0.),
+                shininess: 0.0,
+                alpha: 1.0,
+                refraction_index: 1.5,
+                diffuse_map: None,
+            });
+            continue;
+        }
+        let Some(material) = materials.last_mut() else {
+            continue;
+        };
+        let rest: Vec<&str> = tokens.collect();
+        let values: Vec<f64> = rest.iter().filter_map(|token| token.parse().ok()).collect();
+        match (keyword, &values[..]) {
+            ("Kd", [r, g, b]) => material.diffuse = color(*r, *g, *b),
+            ("Ks", [r, g, b]) => material.specular = color(*r, *g, *b),
+            ("Ns", [n]) => material.shininess = *n,
+            ("d", [d]) => material.alpha = *d,
+            ("Tr", [t]) => material.alpha = 1.0 - t,
+            ("Ni", [n]) => material.refraction_index = *n,
+            // Options such as `-s 1 1 1` come before the file name.
+            ("map_Kd", _) => material.diffuse_map = rest.last().map(|name| name.to_string()),
+            ("Kd" | "Ks" | "Ns" | "d" | "Tr" | "Ni", _) => {
+                return Err(error(number, &format!("bad {} values", keyword)))
+            }
+            _ => {}
+        }
+    }
+    Ok(materials)
+}
+
+impl Mtl {
+    fn to_material(&self, directory: &Path) -> Result<Arc<dyn Material>, LoadError> {
+        let texture: Arc<dyn Texture> = match &self.diffuse_map {
+            Some(name) => {
+                let path = directory.join(name);
+                TextureRegistry::global()
+                    .load(&path, &AssetResolver::new())
+                    .map_err(|e| LoadError::Decode {
+                        path,
+                        message: e.to_string(),
+                    })?
+            }
+            None => Arc::new(SolidColor::new(self.diffuse)),
+        };
+        let metallic = (self.specular.0 + self.specular.1 + self.specular.2) / 3.0;
+        let roughness = if self.shininess > 0.1 {
+            (1.999 / self.shininess).sqrt().min(1.0)
+        } else {
+            1.0
+        };
+        Ok(mtl_material(
+            texture,
+            self.alpha < 1.0,
+            self.refraction_index,
+            metallic,
+            roughness,
+        ))
+    }
+}
+
+fn read(path: &Path) -> Result<String, LoadError> {
+    fs::read_to_string(path).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => LoadError::NotFound(path.to_path_buf()),
+        _ => LoadError::Decode {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        },
+    })
+}
+
+/// Loads every face of an OBJ file into one mesh, applying `transform` to
+/// the vertex positions. Faces after a `usemtl` naming a material of the
+/// OBJ's MTL library index it in the mesh's palette; the rest fall back to
+/// `default_material`.
+///
+/// This is a minimal parser: it reads positions (with optional colors), uvs,
+/// normals, polygonal faces, and the diffuse, specular, and transparency
+/// settings of materials. The `obj` feature swaps in three_d_asset's loader.
+pub fn load_obj(
+    path: &Path,
+    default_material: Arc<dyn Material>,
+    transform: Option<Transform>,
+) -> Result<TriangleMesh, LoadError> {
+    let started = Instant::now();
+    let obj = parse_obj(&read(path)?).map_err(|e| match e {
+        ObjError::Geometry(kind) => LoadError::UnsupportedGeometry {
+            path: path.to_path_buf(),
+            kind,
+        },
+        ObjError::Syntax(message) => LoadError::Decode {
+            path: path.to_path_buf(),
+            message,
+        },
+    })?;
+    if obj.triangles.is_empty() {
+        return Err(LoadError::Empty(path.to_path_buf()));
+    }
+
+    let directory = path.parent().unwrap_or(Path::new(""));
+    let library = match &obj.library {
+        Some(name) => {
+            let library: PathBuf = directory.join(name);
+            parse_mtl(&read(&library)?, &library)?
+        }
+        None => Vec::new(),
+    };
+    let mut materials = library
+        .iter()
+        .map(|mtl| mtl.to_material(directory))
+        .collect::<Result<Vec<_>, _>>()?;
+    // The palette slot of the default material, added once a face needs it.
+    let mut default_id = None;
+    let mut palette_id = |name: Option<usize>| {
+        let found = name.and_then(|name| {
+            let name = &obj.material_names[name];
+            library.iter().position(|mtl| mtl.name == *name)
+        });
+        let index = found.unwrap_or_else(|| {
+            *default_id.get_or_insert_with(|| {
+                materials.push(default_material.clone());
+                materials.len() - 1
+            })
+        });
+        u16::try_from(index).map_err(|_| LoadError::Decode {
+            path: path.to_path_buf(),
+            message: format!("more than {} materials", u16::MAX),
+        })
+    };
+
+    // A vertex per distinct position, uv, and normal triple.
+    let transform = transform.unwrap_or_default();
+    let mut vertices: HashMap<Corner, usize> = HashMap::new();
+    let mut corners: Vec<Corner> = Vec::new();
+    let mut data = MeshData::default();
+    let mut material_ids = Vec::with_capacity(obj.triangles.len());
+    let mut progress = progress_for(path);
+    let total = obj.triangles.len();
+    for (triangle, &name) in obj.triangles.iter().zip(&obj.triangle_materials) {
+        let face = triangle.map(|corner| {
+            *vertices.entry(corner).or_insert_with(|| {
+                corners.push(corner);
+                corners.len() - 1
+            })
+        });
+        data.faces.push(face);
+        material_ids.push(palette_id(name)?);
+        progress(data.faces.len(), total);
+    }
+    data.positions = corners
+        .iter()
+        .map(|&(p, _, _)| transform.point(obj.positions[p]))
+        .collect();
+    // Attributes are only usable if every corner has them.
+    data.normals = corners
+        .iter()
+        .map(|&(_, _, n)| n.map(|n| transform.normal(obj.normals[n])))
+        .collect();
+    data.uvs = corners
+        .iter()
+        .map(|&(_, uv, _)| uv.map(|uv| obj.uvs[uv]))
+        .collect();
+    data.colors = corners.iter().map(|&(p, _, _)| obj.colors[p]).collect();
+
+    let normals = data.normals.is_some();
+    if !normals {
+        data.compute_vertex_normals(DEFAULT_SMOOTHING_ANGLE);
+    }
+    // Files without any materials don't need a palette.
+    let mesh = if default_id.is_some() && materials.len() == 1 {
+        TriangleMesh::new(data, default_material)
+    } else {
+        TriangleMesh::with_materials(data, default_material, materials, material_ids)
+    };
+    finish_load(path, mesh, normals, started)
+}
@@ -0,0 +1,327 @@
+use crate::{
+    color, point, Color, Instant, LoadError, Material, MeshData, Transform, TriangleMesh, Vec3,
+    DEFAULT_SMOOTHING_ANGLE,
+};
+
+use std::{fs, io, path::Path, str, sync::Arc};
+
+use super::{finish_load, progress_for};
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return None,
+        })
+    }
+    fn size(&self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+}
+
+enum Property {
+    Scalar {
+        name: String,
+        ty: Scalar,
+    },
+    List {
+        name: String,
+        count: Scalar,
+        item: Scalar,
+    },
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+/// Reads scalars from the body of the file in either encoding.
+struct Body<'a> {
+    format: Format,
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Body<'_> {
+    fn read(&mut self, ty: Scalar) -> Result<f64, String> {
+        if self.format == Format::Ascii {
+            return self.read_token();
+        }
+        let size = ty.size();
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + size)
+            .ok_or("unexpected end of file")?;
+        self.position += size;
+        let mut buffer = [0u8; 8];
+        buffer[..size].copy_from_slice(bytes);
+        if self.format == Format::BinaryBigEndian {
+            buffer[..size].reverse();
+        }
+        Ok(match ty {
+            Scalar::I8 => buffer[0] as i8 as f64,
+            Scalar::U8 => buffer[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes(buffer[..4].try_into().unwrap()) as f64,
+            Scalar::U32 => u32::from_le_bytes(buffer[..4].try_into().unwrap()) as f64,
+            Scalar::F32 => f32::from_le_bytes(buffer[..4].try_into().unwrap()) as f64,
+            Scalar::F64 => f64::from_le_bytes(buffer),
+        })
+    }
+
+    fn read_token(&mut self) -> Result<f64, String> {
+        let rest = &self.bytes[self.position..];
+        let start = rest
+            .iter()
+            .position(|b| !b.is_ascii_whitespace())
+            .ok_or("unexpected end of file")?;
+        let length = rest[start..]
+            .iter()
+            .position(|b| b.is_ascii_whitespace())
+            .unwrap_or(rest.len() - start);
+        self.position += start + length;
+        let token = str::from_utf8(&rest[start..start + length]).map_err(|e| e.to_string())?;
+        token
+            .parse()
+            .map_err(|_| format!("invalid number '{}'", token))
+    }
+}
+
+fn parse_header(bytes: &[u8]) -> Result<(Format, Vec<Element>, usize), String> {
+    const END: &[u8] = b"end_header";
+    let end = bytes
+        .windows(END.len())
+        .position(|w| w == END)
+        .ok_or("missing end_header")?;
+    let body_start = bytes[end..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map(|i| end + i + 1)
+        .ok_or("missing newline after end_header")?;
+    let header = str::from_utf8(&bytes[..end]).map_err(|_| "header is not valid text")?;
+
+    let mut lines = header.lines().map(str::trim);
+    if lines.next() != Some("ply") {
+        return Err("missing 'ply' magic number".to_string());
+    }
+
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in lines {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] | ["comment", ..] | ["obj_info", ..] => {}
+            ["format", kind, _version] => {
+                format = Some(match *kind {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => return Err(format!("unknown format '{}'", kind)),
+                })
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| format!("invalid element count '{}'", count))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => {
+                let property = Property::List {
+                    name: name.to_string(),
+                    count: Scalar::parse(count).ok_or(format!("unknown type '{}'", count))?,
+                    item: Scalar::parse(item).ok_or(format!("unknown type '{}'", item))?,
+                };
+                elements
+                    .last_mut()
+                    .ok_or("property declared before any element")?
+                    .properties
+                    .push(property);
+            }
+            ["property", ty, name] => {
+                let property = Property::Scalar {
+                    name: name.to_string(),
+                    ty: Scalar::parse(ty).ok_or(format!("unknown type '{}'", ty))?,
+                };
+                elements
+                    .last_mut()
+                    .ok_or("property declared before any element")?
+                    .properties
+                    .push(property);
+            }
+            _ => return Err(format!("unrecognized header line '{}'", line)),
+        }
+    }
+
+    Ok((format.ok_or("missing format line")?, elements, body_start))
+}
+
+/// Loads an ASCII or binary PLY file, keeping normals and vertex colors when present
+/// and computing smooth normals otherwise.
+/// Polygonal faces are triangulated as fans.
+pub fn load_ply(
+    path: &Path,
+    default_material: Arc<dyn Material>,
+    transform: Option<Transform>,
+) -> Result<TriangleMesh, LoadError> {
+    let started = Instant::now();
+    let bytes = fs::read(path).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => LoadError::NotFound(path.to_path_buf()),
+        _ => LoadError::Decode {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        },
+    })?;
+    let mut data = parse_ply(&bytes, transform.unwrap_or_default(), progress_for(path)).map_err(
+        |message| LoadError::Decode {
+            path: path.to_path_buf(),
+            message,
+        },
+    )?;
+    if data.faces.is_empty() {
+        return Err(LoadError::Empty(path.to_path_buf()));
+    }
+    let normals = data.normals.is_some();
+    if !normals {
+        data.compute_vertex_normals(DEFAULT_SMOOTHING_ANGLE);
+    }
+    finish_load(
+        path,
+        TriangleMesh::new(data, default_material),
+        normals,
+        started,
+    )
+}
+
+/// Calls `progress` with the number of faces read so far and the total.
+fn parse_ply(
+    bytes: &[u8],
+    transform: Transform,
+    mut progress: impl FnMut(usize, usize),
+) -> Result<MeshData, String> {
+    let (format, elements, body_start) = parse_header(bytes)?;
+    let mut body = Body {
+        format,
+        bytes,
+        position: body_start,
+    };
+
+    let mut data = MeshData::default();
+    let mut normals: Vec<Vec3> = Vec::new();
+    let mut colors: Vec<Color> = Vec::new();
+    for element in &elements {
+        for read in 1..=element.count {
+            let mut values = [None; 9];
+            let mut polygon = Vec::new();
+            for property in &element.properties {
+                match property {
+                    Property::Scalar { name, ty } => {
+                        let value = body.read(*ty)?;
+                        // Integer color channels are 0–255, float ones 0–1.
+                        let color = |v: f64| match ty {
+                            Scalar::F32 | Scalar::F64 => v,
+                            _ => v / 255.,
+                        };
+                        let slot = match name.as_str() {
+                            "x" => Some((0, value)),
+                            "y" => Some((1, value)),
+                            "z" => Some((2, value)),
+                            "nx" => Some((3, value)),
+                            "ny" => Some((4, value)),
+                            "nz" => Some((5, value)),
+                            "red" | "r" => Some((6, color(value))),
+                            "green" | "g" => Some((7, color(value))),
+                            "blue" | "b" => Some((8, color(value))),
+                            _ => None,
+                        };
+                        if let Some((i, v)) = slot {
+                            values[i] = Some(v);
+                        }
+                    }
+                    Property::List { name, count, item } => {
+                        let n = body.read(*count)? as usize;
+                        for _ in 0..n {
+                            let index = body.read(*item)?;
+                            if name == "vertex_indices" || name == "vertex_index" {
+                                polygon.push(index as usize);
+                            }
+                        }
+                    }
+                }
+            }
+
+            match element.name.as_str() {
+                "vertex" => {
+                    let [x, y, z, nx, ny, nz, r, g, b] = values;
+                    let (x, y, z) = match (x, y, z) {
+                        (Some(x), Some(y), Some(z)) => (x, y, z),
+                        _ => return Err("vertex is missing x, y, or z".to_string()),
+                    };
+                    data.positions.push(transform.point(point(x, y, z)));
+                    if let (Some(nx), Some(ny), Some(nz)) = (nx, ny, nz) {
+                        normals.push(transform.normal(Vec3(nx, ny, nz)));
+                    }
+                    if let (Some(r), Some(g), Some(b)) = (r, g, b) {
+                        colors.push(color(r, g, b));
+                    }
+                }
+                "face" => {
+                    for i in 1..polygon.len().saturating_sub(1) {
+                        data.faces.push([polygon[0], polygon[i], polygon[i + 1]]);
+                    }
+                    progress(read, element.count);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let vertex_count = data.positions.len();
+    if let Some(face) = data.faces.iter().flatten().find(|&&i| i >= vertex_count) {
+        return Err(format!(
+            "face references vertex {} but only {} vertices exist",
+            face, vertex_count
+        ));
+    }
+    if normals.len() == vertex_count {
+        data.normals = Some(normals);
+    }
+    if colors.len() == vertex_count {
+        data.colors = Some(colors);
+    }
+    Ok(data)
+}
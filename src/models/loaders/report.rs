@@ -0,0 +1,127 @@
+use crate::{BoundingBox, TriangleMesh};
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+/// How many faces loaders read between progress callbacks.
+pub const PROGRESS_INTERVAL: usize = 10_000;
+
+/// What loading a mesh found and what it cost.
+#[derive(Debug, Clone)]
+pub struct LoadReport {
+    pub path: PathBuf,
+    pub triangles: usize,
+    pub vertices: usize,
+    /// Whether the file provided normals, rather than them being computed.
+    pub normals: bool,
+    pub uvs: bool,
+    pub colors: bool,
+    /// Size of the mesh's material palette, 0 if every face uses the default.
+    pub materials: usize,
+    pub bounds: BoundingBox,
+    /// Time spent reading the file and building the mesh data.
+    pub parse_time: Duration,
+    pub bvh_time: Duration,
+    /// Rough memory held by the mesh data, its triangles, and the BVH.
+    pub bytes: usize,
+}
+
+impl LoadReport {
+    pub(crate) fn new(
+        path: &Path,
+        mesh: &TriangleMesh,
+        normals: bool,
+        parse_time: Duration,
+    ) -> Self {
+        let data = mesh.data();
+        Self {
+            path: path.to_path_buf(),
+            triangles: mesh.triangle_count(),
+            vertices: mesh.vertex_count(),
+            normals,
+            uvs: data.uvs.is_some(),
+            colors: data.colors.is_some(),
+            materials: mesh.materials().len(),
+            bounds: mesh.bounds(),
+            parse_time,
+            bvh_time: mesh.build_time(),
+            bytes: mesh.estimated_bytes(),
+        }
+    }
+}
+
+impl fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let yes_no = |found: bool| if found { "yes" } else { "no" };
+        let [x, y, z] = self.bounds.intervals;
+        writeln!(f, "{}:", self.path.display())?;
+        writeln!(
+            f,
+            "  {} triangles, {} vertices",
+            self.triangles, self.vertices
+        )?;
+        writeln!(
+            f,
+            "  normals: {}, uvs: {}, colors: {}, materials: {}",
+            if self.normals { "file" } else { "computed" },
+            yes_no(self.uvs),
+            yes_no(self.colors),
+            self.materials
+        )?;
+        writeln!(
+            f,
+            "  bounds [{:.3}, {:.3}] x [{:.3}, {:.3}] x [{:.3}, {:.3}]",
+            x.start, x.end, y.start, y.end, z.start, z.end
+        )?;
+        write!(
+            f,
+            "  parsed in {:.1?}, BVH built in {:.1?}, about ",
+            self.parse_time, self.bvh_time
+        )?;
+        match self.bytes as f64 / 1024.0 {
+            kib if kib < 1024.0 => write!(f, "{:.1} KiB", kib),
+            kib => write!(f, "{:.1} MiB", kib / 1024.0),
+        }
+    }
+}
+
+/// Receives progress and results from every model loader, e.g. to draw a
+/// progress bar while large meshes load.
+pub trait LoadListener: Send + Sync {
+    /// Called every `PROGRESS_INTERVAL` faces and once all `total` are read.
+    fn progress(&self, _path: &Path, _faces: usize, _total: usize) {}
+    fn loaded(&self, _report: &LoadReport) {}
+}
+
+static LOAD_LISTENER: RwLock<Option<Arc<dyn LoadListener>>> = RwLock::new(None);
+
+/// Sends the progress and reports of all later loads to `listener`.
+pub fn set_load_listener(listener: Option<Arc<dyn LoadListener>>) {
+    *LOAD_LISTENER.write().unwrap() = listener;
+}
+
+fn load_listener() -> Option<Arc<dyn LoadListener>> {
+    LOAD_LISTENER.read().unwrap().clone()
+}
+
+/// A progress callback for loading `path` that forwards to the listener, if any.
+pub(crate) fn progress_for(path: &Path) -> impl FnMut(usize, usize) + '_ {
+    let listener = load_listener();
+    move |faces, total| {
+        if let Some(listener) = &listener {
+            if faces % PROGRESS_INTERVAL == 0 || faces == total {
+                listener.progress(path, faces, total);
+            }
+        }
+    }
+}
+
+pub(crate) fn notify_loaded(report: &LoadReport) {
+    if let Some(listener) = load_listener() {
+        listener.loaded(report);
+    }
+}
@@ -0,0 +1,183 @@
+use crate::{
+    Instant, LoadError, Material, MeshData, Point, Transform, TriangleMesh, Vec3,
+    DEFAULT_SMOOTHING_ANGLE,
+};
+
+use std::{collections::HashMap, fs, io, path::Path, str, sync::Arc};
+
+use super::{finish_load, progress_for};
+
+/// Vertices closer than this (per axis) are merged into one.
+const WELD_EPSILON: f64 = 1e-5;
+
+struct Facet {
+    normal: Vec3,
+    vertices: [Point; 3],
+}
+
+/// Loads a binary or ASCII STL file, welding the shared corners of the facets
+/// back together. STL facet normals are often unreliable, so they are only used
+/// to fix the winding of facets that disagree with them; shading uses smooth
+/// normals computed from the welded faces.
+pub fn load_stl(
+    path: &Path,
+    default_material: Arc<dyn Material>,
+    transform: Option<Transform>,
+) -> Result<TriangleMesh, LoadError> {
+    let started = Instant::now();
+    let bytes = fs::read(path).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => LoadError::NotFound(path.to_path_buf()),
+        _ => LoadError::Decode {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        },
+    })?;
+    let decode_error = |message: String| LoadError::Decode {
+        path: path.to_path_buf(),
+        message,
+    };
+    let facets = if is_binary(&bytes) {
+        parse_binary(&bytes).map_err(decode_error)?
+    } else {
+        parse_ascii(&bytes).map_err(decode_error)?
+    };
+
+    let mut data = weld(&facets, transform.unwrap_or_default(), progress_for(path));
+    if data.faces.is_empty() {
+        return Err(LoadError::Empty(path.to_path_buf()));
+    }
+    data.compute_vertex_normals(DEFAULT_SMOOTHING_ANGLE);
+    finish_load(
+        path,
+        TriangleMesh::new(data, default_material),
+        false,
+        started,
+    )
+}
+
+/// Binary files may also start with "solid", so trust the size implied by
+/// the facet count instead.
+fn is_binary(bytes: &[u8]) -> bool {
+    if bytes.len() < 84 {
+        return false;
+    }
+    let count = u32::from_le_bytes(bytes[80..84].try_into().unwrap()) as usize;
+    bytes.len() == 84 + count * 50 || !bytes.starts_with(b"solid")
+}
+
+fn parse_binary(bytes: &[u8]) -> Result<Vec<Facet>, String> {
+    let count = u32::from_le_bytes(bytes[80..84].try_into().unwrap()) as usize;
+    if bytes.len() < 84 + count * 50 {
+        return Err(format!(
+            "header declares {} facets but the file holds {}",
+            count,
+            (bytes.len() - 84) / 50
+        ));
+    }
+    let vector = |chunk: &[u8]| {
+        let float = |i: usize| f32::from_le_bytes(chunk[i..i + 4].try_into().unwrap()) as f64;
+        Vec3(float(0), float(4), float(8))
+    };
+    Ok(bytes[84..84 + count * 50]
+        .chunks_exact(50)
+        .map(|facet| Facet {
+            normal: vector(&facet[0..12]),
+            vertices: [
+                vector(&facet[12..24]),
+                vector(&facet[24..36]),
+                vector(&facet[36..48]),
+            ],
+        })
+        .collect())
+}
+
+fn parse_ascii(bytes: &[u8]) -> Result<Vec<Facet>, String> {
+    let text = str::from_utf8(bytes).map_err(|_| "file is neither binary nor valid text")?;
+    let mut words = text.split_whitespace();
+    if words.next() != Some("solid") {
+        return Err("missing 'solid' header".to_string());
+    }
+
+    let next_vector = |words: &mut str::SplitWhitespace| -> Result<Vec3, String> {
+        let mut component = || {
+            let word = words.next().ok_or("unexpected end of file")?;
+            word.parse::<f64>()
+                .map_err(|_| format!("invalid number '{}'", word))
+        };
+        Ok(Vec3(component()?, component()?, component()?))
+    };
+
+    let mut facets = Vec::new();
+    while let Some(word) = words.next() {
+        match word {
+            "facet" => {
+                if words.next() != Some("normal") {
+                    return Err("expected 'normal' after 'facet'".to_string());
+                }
+                let normal = next_vector(&mut words)?;
+                let mut vertices = Vec::with_capacity(3);
+                loop {
+                    match words.next() {
+                        Some("vertex") => vertices.push(next_vector(&mut words)?),
+                        Some("outer") | Some("loop") | Some("endloop") => {}
+                        Some("endfacet") => break,
+                        Some(other) => return Err(format!("unexpected '{}' in facet", other)),
+                        None => return Err("unexpected end of file".to_string()),
+                    }
+                }
+                let vertices: [Point; 3] = vertices
+                    .try_into()
+                    .map_err(|v: Vec<Point>| format!("facet has {} vertices", v.len()))?;
+                facets.push(Facet { normal, vertices });
+            }
+            "endsolid" => break,
+            // The solid's name may be several words long.
+            _ => {}
+        }
+    }
+    Ok(facets)
+}
+
+/// Calls `progress` with the number of facets welded so far and the total.
+fn weld(
+    facets: &[Facet],
+    transform: Transform,
+    mut progress: impl FnMut(usize, usize),
+) -> MeshData {
+    let mut data = MeshData::default();
+    let mut indices: HashMap<(i64, i64, i64), usize> = HashMap::new();
+    let mut index_of = |p: Point, data: &mut MeshData| {
+        let key = (
+            (p.0 / WELD_EPSILON).round() as i64,
+            (p.1 / WELD_EPSILON).round() as i64,
+            (p.2 / WELD_EPSILON).round() as i64,
+        );
+        *indices.entry(key).or_insert_with(|| {
+            data.positions.push(p);
+            data.positions.len() - 1
+        })
+    };
+
+    for (done, facet) in facets.iter().enumerate() {
+        progress(done + 1, facets.len());
+        let [a, b, c] = facet.vertices.map(|v| transform.point(v));
+        let geometric = Vec3::cross(&(b - a), &(c - a));
+        let flipped = !facet.normal.near_zero()
+            && Vec3::dot(&geometric, &transform.normal(facet.normal)) < 0.0;
+        let face = [
+            index_of(a, &mut data),
+            index_of(b, &mut data),
+            index_of(c, &mut data),
+        ];
+        // Welding can collapse sliver facets to a line or a point.
+        if face[0] == face[1] || face[1] == face[2] || face[0] == face[2] {
+            continue;
+        }
+        data.faces.push(if flipped {
+            [face[0], face[2], face[1]]
+        } else {
+            face
+        });
+    }
+    data
+}
@@ -0,0 +1,257 @@
+use crate::{
+    color, point, Color, ColorTexture, Instant, LoadError, Material, MeshData, SolidColor, Texture,
+    TextureRegistry, Transform, TriangleMesh, Vec3, DEFAULT_SMOOTHING_ANGLE,
+};
+
+use std::{collections::HashMap, fs, path::Path, sync::Arc};
+
+use three_d_asset::{Geometry, PbrMaterial, Texture2D, TextureData};
+
+use super::{finish_load, mtl_material, progress_for};
+
+/// Loads every geometry in an OBJ file into one mesh, applying `transform`
+/// to the vertex positions. Faces of geometries with a material from the
+/// OBJ's MTL library index it in the mesh's palette; the rest fall back to
+/// `default_material`.
+pub fn load_obj(
+    path: &Path,
+    default_material: Arc<dyn Material>,
+    transform: Option<Transform>,
+) -> Result<TriangleMesh, LoadError> {
+    let started = Instant::now();
+    if !path.exists() {
+        return Err(LoadError::NotFound(path.to_path_buf()));
+    }
+    let vertex_colors = fs::read_to_string(path)
+        .ok()
+        .and_then(|text| split_obj_vertex_colors(&text));
+    let model: three_d_asset::Model = match &vertex_colors {
+        Some((_, stripped)) => load_stripped_obj(path, stripped),
+        None => three_d_asset::io::load_and_deserialize(path),
+    }
+    .map_err(|e| match obj_primitive_kind(path) {
+        Some(kind) => LoadError::UnsupportedGeometry {
+            path: path.to_path_buf(),
+            kind,
+        },
+        None => LoadError::Decode {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        },
+    })?;
+    let transform = transform.unwrap_or_default();
+    let mut materials: Vec<Arc<dyn Material>> =
+        model.materials.iter().map(convert_material).collect();
+    let mut material_ids: Vec<u16> = Vec::new();
+    // The palette slot of the default material, added once a geometry needs it.
+    let mut default_id = None;
+
+    let mut data = MeshData::default();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    let mut colors = Vec::new();
+    let (mut has_normals, mut has_uvs) = (true, true);
+    let mut progress = progress_for(path);
+    let total = model
+        .geometries
+        .iter()
+        .map(|part| match &part.geometry {
+            Geometry::Triangles(mesh) => mesh.triangle_count(),
+            Geometry::Points(_) => 0,
+        })
+        .sum();
+    for part in &model.geometries {
+        let mesh = match &part.geometry {
+            Geometry::Points(_) => {
+                return Err(LoadError::UnsupportedGeometry {
+                    path: path.to_path_buf(),
+                    kind: "point",
+                })
+            }
+            Geometry::Triangles(mesh) => mesh,
+        };
+        let offset = data.positions.len();
+        let positions = mesh.positions.to_f64();
+        if let Some((by_position, _)) = &vertex_colors {
+            // The parser renumbers vertices, so find their colors by position.
+            colors.extend(positions.iter().filter_map(|p| {
+                by_position
+                    .get(&[p.x.to_bits(), p.y.to_bits(), p.z.to_bits()])
+                    .copied()
+            }));
+        }
+        data.positions.extend(
+            positions
+                .iter()
+                .map(|p| transform.point(point(p.x, p.y, p.z))),
+        );
+        match &mesh.normals {
+            Some(n) => normals.extend(
+                n.iter()
+                    .map(|n| transform.normal(Vec3(n.x as f64, n.y as f64, n.z as f64))),
+            ),
+            None => has_normals = false,
+        }
+        // three_d_asset flips V to a top-down convention; textures here expect V up.
+        match &mesh.uvs {
+            Some(uv) => uvs.extend(uv.iter().map(|uv| (uv.x as f64, 1.0 - uv.y as f64))),
+            None => has_uvs = false,
+        }
+        mesh.for_each_triangle(|a, b, c| {
+            data.faces.push([offset + a, offset + b, offset + c]);
+            progress(data.faces.len(), total);
+        });
+        let index = match part.material_index {
+            Some(index) => index,
+            None => *default_id.get_or_insert_with(|| {
+                materials.push(default_material.clone());
+                materials.len() - 1
+            }),
+        };
+        let id = u16::try_from(index).map_err(|_| LoadError::Decode {
+            path: path.to_path_buf(),
+            message: format!("more than {} materials", u16::MAX),
+        })?;
+        material_ids.resize(data.faces.len(), id);
+    }
+    if data.faces.is_empty() {
+        return Err(match obj_primitive_kind(path) {
+            Some(kind) => LoadError::UnsupportedGeometry {
+                path: path.to_path_buf(),
+                kind,
+            },
+            None => LoadError::Empty(path.to_path_buf()),
+        });
+    }
+    // Attributes are only usable if every geometry in the file provided them.
+    if has_normals && normals.len() == data.positions.len() {
+        data.normals = Some(normals);
+    }
+    if has_uvs && uvs.len() == data.positions.len() {
+        data.uvs = Some(uvs);
+    }
+    if vertex_colors.is_some() && colors.len() == data.positions.len() {
+        data.colors = Some(colors);
+    }
+    let normals = data.normals.is_some();
+    if !normals {
+        data.compute_vertex_normals(DEFAULT_SMOOTHING_ANGLE);
+    }
+
+    // Files without any materials don't need a palette.
+    let mesh = if default_id.is_some() && materials.len() == 1 {
+        TriangleMesh::new(data, default_material)
+    } else {
+        TriangleMesh::with_materials(data, default_material, materials, material_ids)
+    };
+    finish_load(path, mesh, normals, started)
+}
+
+/// Splits off the colors some exporters append to OBJ vertices
+/// (`v x y z r g b`), which the OBJ parser rejects. Returns each color keyed
+/// by the bits of its vertex position, and the file without them; `None` if
+/// no vertex has a color. Vertices at the same position share the first color.
+fn split_obj_vertex_colors(text: &str) -> Option<(HashMap<[u64; 3], Color>, String)> {
+    let mut colors = HashMap::new();
+    let mut stripped = String::with_capacity(text.len());
+    for line in text.lines() {
+        let mut tokens = line.split_whitespace();
+        if tokens.next() == Some("v") {
+            let values: Vec<f64> = tokens.filter_map(|token| token.parse().ok()).collect();
+            if let [x, y, z, r, g, b] = values[..] {
+                colors
+                    .entry([x.to_bits(), y.to_bits(), z.to_bits()])
+                    .or_insert(color(r, g, b));
+                stripped += &format!("v {} {} {}\n", x, y, z);
+                continue;
+            }
+        }
+        stripped += line;
+        stripped.push('\n');
+    }
+    (!colors.is_empty()).then_some((colors, stripped))
+}
+
+/// Loads an OBJ from `text` in place of the file at `path`, with the material
+/// library the file names next to it.
+fn load_stripped_obj(path: &Path, text: &str) -> three_d_asset::Result<three_d_asset::Model> {
+    let library = text
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("mtllib "));
+    let mut raw_assets = match library {
+        Some(library) => {
+            let directory = path.parent().unwrap_or(Path::new(""));
+            three_d_asset::io::load(&[directory.join(library.trim())])?
+        }
+        None => three_d_asset::io::RawAssets::new(),
+    };
+    raw_assets.insert(path, text.as_bytes().to_vec());
+    raw_assets.deserialize(path)
+}
+
+/// Finds point or line elements in an OBJ file. The OBJ parser rejects `p`
+/// elements and silently drops short faces, so this explains what went wrong.
+fn obj_primitive_kind(path: &Path) -> Option<&'static str> {
+    let text = fs::read_to_string(path).ok()?;
+    text.lines().find_map(|line| {
+        let mut tokens = line.split_whitespace();
+        match (tokens.next(), tokens.count()) {
+            (Some("p"), _) | (Some("f"), 1) => Some("point"),
+            (Some("l"), _) | (Some("f"), 2) => Some("line"),
+            _ => None,
+        }
+    })
+}
+
+/// Maps an MTL material, as parsed by three_d_asset, onto the closest material here.
+fn convert_material(material: &PbrMaterial) -> Arc<dyn Material> {
+    let albedo = material.albedo;
+
+    let texture: Arc<dyn Texture> = match &material.albedo_texture {
+        Some(texture) => TextureRegistry::global()
+            .get_or_insert_with(Path::new(&texture.name), || {
+                Arc::new(convert_texture(texture))
+            }),
+        None => Arc::new(SolidColor::new(color(
+            albedo.r as f64 / 255.,
+            albedo.g as f64 / 255.,
+            albedo.b as f64 / 255.,
+        ))),
+    };
+
+    mtl_material(
+        texture,
+        albedo.a < 255,
+        material.index_of_refraction as f64,
+        material.metallic as f64,
+        material.roughness as f64,
+    )
+}
+
+fn convert_texture(texture: &Texture2D) -> ColorTexture {
+    let byte = |c: u8| c as f64 / 255.;
+    let data = match &texture.data {
+        TextureData::RU8(d) => d
+            .iter()
+            .map(|&r| color(byte(r), byte(r), byte(r)))
+            .collect(),
+        TextureData::RgbU8(d) => d
+            .iter()
+            .map(|&[r, g, b]| color(byte(r), byte(g), byte(b)))
+            .collect(),
+        TextureData::RgbaU8(d) => d
+            .iter()
+            .map(|&[r, g, b, _]| color(byte(r), byte(g), byte(b)))
+            .collect(),
+        TextureData::RgbF32(d) => d
+            .iter()
+            .map(|&[r, g, b]| color(r as f64, g as f64, b as f64))
+            .collect(),
+        TextureData::RgbaF32(d) => d
+            .iter()
+            .map(|&[r, g, b, _]| color(r as f64, g as f64, b as f64))
+            .collect(),
+        _ => vec![color(1., 1., 1.); (texture.width * texture.height) as usize],
+    };
+    ColorTexture::new(data, texture.width as usize, texture.height as usize)
+}
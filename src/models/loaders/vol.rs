@@ -0,0 +1,96 @@
+use crate::{BoundingBox, Interval, LoadError, VoxelGrid};
+
+use std::{fs, io, path::Path};
+
+/// The magic bytes and version Mitsuba's grid volumes start with.
+const MAGIC: &[u8; 4] = b"VOL\x03";
+/// The only encoding read: 32-bit floats.
+const FLOAT32: i32 = 1;
+const HEADER_LEN: usize = 48;
+
+/// Loads a density grid in Mitsuba's `.vol` format: a 48-byte header of the
+/// magic bytes, the encoding, the x, y, and z resolution, the channel count,
+/// and the bounding box, then one little-endian float per voxel with x
+/// varying fastest. Only single-channel float grids are read.
+pub fn load_vol(path: &Path) -> Result<VoxelGrid, LoadError> {
+    let bytes = fs::read(path).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => LoadError::NotFound(path.to_path_buf()),
+        _ => LoadError::Decode {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        },
+    })?;
+    parse_vol(&bytes).map_err(|message| LoadError::Decode {
+        path: path.to_path_buf(),
+        message,
+    })
+}
+
+fn parse_vol(bytes: &[u8]) -> Result<VoxelGrid, String> {
+    if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC {
+        return Err("not a version 3 grid volume".to_string());
+    }
+    let int = |i: usize| i32::from_le_bytes(bytes[i..i + 4].try_into().expect("4 bytes"));
+    let float = |i: usize| f32::from_le_bytes(bytes[i..i + 4].try_into().expect("4 bytes"));
+    if int(4) != FLOAT32 {
+        return Err(format!(
+            "unsupported encoding {}, expected 32-bit floats",
+            int(4)
+        ));
+    }
+    if int(20) != 1 {
+        return Err(format!("{} channels, expected one of density", int(20)));
+    }
+    let resolution = [int(8), int(12), int(16)];
+    if resolution.iter().any(|&n| n < 1) {
+        return Err(format!("bad resolution {:?}", resolution));
+    }
+    let resolution = resolution.map(|n| n as usize);
+    // Four bytes a voxel, checked as it's multiplied out so that a huge
+    // resolution is an error rather than an overflow.
+    let expected = resolution
+        .iter()
+        .try_fold(4usize, |n, &r| n.checked_mul(r))
+        .ok_or_else(|| format!("resolution {:?} is too large", resolution))?;
+    let data = &bytes[HEADER_LEN..];
+    if data.len() != expected {
+        return Err(format!(
+            "{} bytes of voxels, expected {} for {} by {} by {}",
+            data.len(),
+            expected,
+            resolution[0],
+            resolution[1],
+            resolution[2]
+        ));
+    }
+    let axis = |i: usize| Interval::new(float(24 + 4 * i) as f64, float(36 + 4 * i) as f64);
+    let bounds = BoundingBox::new(axis(0), axis(1), axis(2));
+    let densities = data
+        .chunks_exact(4)
+        .map(|v| f32::from_le_bytes(v.try_into().expect("4 bytes")))
+        .collect();
+    VoxelGrid::new(resolution, bounds, densities)
+}
+
+impl VoxelGrid {
+    /// The grid in the `.vol` format `load_vol` reads.
+    pub fn to_vol_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.densities.len() * 4);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&FLOAT32.to_le_bytes());
+        for n in self.resolution {
+            bytes.extend_from_slice(&(n as i32).to_le_bytes());
+        }
+        bytes.extend_from_slice(&1i32.to_le_bytes());
+        for end in [false, true] {
+            for interval in self.bounds.intervals {
+                let value = if end { interval.end } else { interval.start };
+                bytes.extend_from_slice(&(value as f32).to_le_bytes());
+            }
+        }
+        for density in &self.densities {
+            bytes.extend_from_slice(&density.to_le_bytes());
+        }
+        bytes
+    }
+}
@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use crate::{
+    core::*,
+    models::*,
+    scenes::{Describer, ObjectDesc, ValidationIssue},
+};
+
+/// Tags every hit on `object` with `name`, so that `Camera::render_mattes`
+/// can tell how much of each pixel it covers. An object or group can be
+/// tagged once; a tag nested deeper wins over the ones around it.
+pub struct Matte {
+    pub name: Arc<str>,
+    pub object: Arc<dyn Hittable>,
+}
+
+impl Matte {
+    pub fn new(name: &str, object: Arc<dyn Hittable>) -> Self {
+        Self {
+            name: name.into(),
+            object,
+        }
+    }
+
+    fn tag<'a>(&'a self, mut record: HitRecord<'a>) -> HitRecord<'a> {
+        record.matte.get_or_insert(&self.name);
+        record
+    }
+}
+
+impl Hittable for Matte {
+    fn hit(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> Option<HitRecord<'_>> {
+        Some(self.tag(self.object.hit(ray, t, rng)?))
+    }
+    fn hit_with_stats(
+        &self,
+        ray: &Ray,
+        t: Interval,
+        rng: &mut dyn Sampler,
+        stats: &mut TraversalStats,
+    ) -> Option<HitRecord<'_>> {
+        Some(self.tag(self.object.hit_with_stats(ray, t, rng, stats)?))
+    }
+    fn hit_any(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> bool {
+        self.object.hit_any(ray, t, rng)
+    }
+    fn bound(&self) -> BoundingBox {
+        self.object.bound()
+    }
+    fn bound_transformed(&self, transform: &Mat4) -> BoundingBox {
+        self.object.bound_transformed(transform)
+    }
+    fn is_emissive(&self) -> bool {
+        self.object.is_emissive()
+    }
+    fn collect_lights(&self, lights: &mut Vec<Arc<dyn Hittable>>) {
+        self.object.collect_lights(lights);
+    }
+    fn describe(&self, describer: &mut Describer) -> Option<ObjectDesc> {
+        Some(ObjectDesc::Matte {
+            name: self.name.to_string(),
+            object: Box::new(describer.object(self.object.as_ref())?),
+        })
+    }
+    fn validate(&self, path: &str, issues: &mut Vec<ValidationIssue>) {
+        if self.name.is_empty() {
+            issues.push(ValidationIssue::warning(path, "matte has no name"));
+        }
+        self.object.validate(&format!("{}.object", path), issues);
+    }
+    fn label(&self) -> String {
+        format!("Matte {:?}", self.name)
+    }
+    fn children(&self) -> Vec<&dyn Hittable> {
+        vec![self.object.as_ref()]
+    }
+}
+
+debug_as_hierarchy!(Matte);
@@ -0,0 +1,836 @@
+use crate::{
+    hittable::*,
+    point,
+    scenes::{Describer, DisplaceDesc, ObjectDesc, ValidationIssue},
+    BoundNode, BoundingBox, Color, GridAccel, Instant, Interval, LoadReport, Mat4, Material, Point,
+    Primitive, Ray, Sampler, Texture, Transform, TraversalStats, Vec3,
+};
+
+use serde::{Deserialize, Serialize};
+
+use std::{collections::HashMap, fmt, mem, path::PathBuf, sync::Arc, time::Duration};
+
+/// Faces meeting at more than this many degrees keep a hard edge when the
+/// loaders compute the normals a file left out.
+pub const DEFAULT_SMOOTHING_ANGLE: f64 = 60.0;
+
+#[derive(Clone, Default)]
+pub struct MeshData {
+    pub positions: Vec<Point>,
+    pub faces: Vec<[usize; 3]>,
+    /* Optional per-vertex attributes, indexed like `positions` */
+    pub normals: Option<Vec<Vec3>>,
+    pub uvs: Option<Vec<(f64, f64)>>,
+    pub colors: Option<Vec<Color>>,
+}
+
+impl MeshData {
+    /// The parallelogram spanned by `u` and `v` from `corner`, as two
+    /// triangles with UVs running from 0 to 1 along each side.
+    pub fn quad(corner: Point, (u, v): (Vec3, Vec3)) -> Self {
+        let normal = Vec3::cross(&u, &v);
+        let normal = if normal.near_zero() {
+            normal
+        } else {
+            normal.unit()
+        };
+        Self {
+            positions: vec![corner, corner + u, corner + u + v, corner + v],
+            faces: vec![[0, 1, 2], [0, 2, 3]],
+            normals: Some(vec![normal; 4]),
+            uvs: Some(vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]),
+            colors: None,
+        }
+    }
+
+    pub fn vertices(&self, face: usize) -> (Point, Point, Point) {
+        let [a, b, c] = self.faces[face];
+        (self.positions[a], self.positions[b], self.positions[c])
+    }
+
+    /// Interpolates the vertex normals at barycentric (u, v), if the mesh has any.
+    pub fn shading_normal(&self, face: usize, u: f64, v: f64) -> Option<Vec3> {
+        let normals = self.normals.as_ref()?;
+        let [a, b, c] = self.faces[face];
+        let n = normals[a] * (1.0 - u - v) + normals[b] * u + normals[c] * v;
+        if n.near_zero() {
+            None
+        } else {
+            Some(n.unit())
+        }
+    }
+
+    /// Sets each vertex normal to the area-weighted average of the normals of
+    /// the faces around it. Faces meeting at more than `angle_threshold`
+    /// degrees don't smooth into each other; the vertices they share are split
+    /// so the edge between them stays sharp.
+    pub fn compute_vertex_normals(&mut self, angle_threshold: f64) {
+        // Each face's normal scaled by twice its area, and its direction.
+        let weighted: Vec<Vec3> = (0..self.faces.len())
+            .map(|face| {
+                let (a, b, c) = self.vertices(face);
+                Vec3::cross(&(b - a), &(c - a))
+            })
+            .collect();
+        let directions: Vec<Vec3> = weighted
+            .iter()
+            .map(|&n| if n.near_zero() { n } else { n.unit() })
+            .collect();
+        let mut around = vec![Vec::new(); self.positions.len()];
+        for (face, corners) in self.faces.iter().enumerate() {
+            for &vertex in corners {
+                around[vertex].push(face);
+            }
+        }
+        let min_cos = angle_threshold.to_radians().cos() - 1e-9;
+
+        let mut normals = vec![Vec3(0.0, 0.0, 0.0); self.positions.len()];
+        for (vertex, faces) in around.iter().enumerate() {
+            // Corners that smooth with the same faces share a vertex.
+            let mut groups: Vec<(Vec<usize>, usize)> = Vec::new();
+            for &face in faces {
+                let smooth: Vec<usize> = faces
+                    .iter()
+                    .copied()
+                    .filter(|&other| {
+                        other == face || Vec3::dot(&directions[face], &directions[other]) >= min_cos
+                    })
+                    .collect();
+                let index = match groups.iter().find(|(group, _)| *group == smooth) {
+                    Some(&(_, index)) => index,
+                    None => {
+                        let index = if groups.is_empty() {
+                            vertex
+                        } else {
+                            self.split_vertex(vertex)
+                        };
+                        let normal = smooth
+                            .iter()
+                            .fold(Vec3(0.0, 0.0, 0.0), |sum, &other| sum + weighted[other]);
+                        if index == normals.len() {
+                            normals.push(normal);
+                        } else {
+                            normals[index] = normal;
+                        }
+                        groups.push((smooth, index));
+                        index
+                    }
+                };
+                for corner in &mut self.faces[face] {
+                    if *corner == vertex {
+                        *corner = index;
+                    }
+                }
+            }
+        }
+        self.normals = Some(
+            normals
+                .into_iter()
+                .map(|n| if n.near_zero() { n } else { n.unit() })
+                .collect(),
+        );
+    }
+
+    /// Appends a copy of `vertex` and its attributes, returning the copy's index.
+    fn split_vertex(&mut self, vertex: usize) -> usize {
+        self.positions.push(self.positions[vertex]);
+        if let Some(uvs) = &mut self.uvs {
+            uvs.push(uvs[vertex]);
+        }
+        if let Some(colors) = &mut self.colors {
+            colors.push(colors[vertex]);
+        }
+        self.positions.len() - 1
+    }
+
+    /// Splits every face into four at the midpoints of its edges, which
+    /// neighboring faces share. Faces `4 * i..4 * i + 4` replace face `i`.
+    pub fn subdivide(&mut self) {
+        let mut midpoints: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut faces = Vec::with_capacity(self.faces.len() * 4);
+        for face in mem::take(&mut self.faces) {
+            let [a, b, c] = face;
+            let [ab, bc, ca] = [(a, b), (b, c), (c, a)].map(|(from, to)| {
+                let key = (from.min(to), from.max(to));
+                *midpoints
+                    .entry(key)
+                    .or_insert_with(|| self.push_midpoint(from, to))
+            });
+            faces.extend([[a, ab, ca], [ab, b, bc], [ca, bc, c], [ab, bc, ca]]);
+        }
+        self.faces = faces;
+    }
+
+    /// Appends the vertex halfway between `a` and `b`, returning its index.
+    fn push_midpoint(&mut self, a: usize, b: usize) -> usize {
+        self.positions
+            .push((self.positions[a] + self.positions[b]) / 2.0);
+        if let Some(normals) = &mut self.normals {
+            let n = normals[a] + normals[b];
+            normals.push(if n.near_zero() { normals[a] } else { n.unit() });
+        }
+        if let Some(uvs) = &mut self.uvs {
+            uvs.push(((uvs[a].0 + uvs[b].0) / 2.0, (uvs[a].1 + uvs[b].1) / 2.0));
+        }
+        if let Some(colors) = &mut self.colors {
+            colors.push((colors[a] + colors[b]) / 2.0);
+        }
+        self.positions.len() - 1
+    }
+
+    /// Interpolates the vertex colors at barycentric (u, v), if the mesh has any.
+    pub fn vertex_color(&self, face: usize, u: f64, v: f64) -> Option<Color> {
+        let colors = self.colors.as_ref()?;
+        let [a, b, c] = self.faces[face];
+        Some(colors[a] * (1.0 - u - v) + colors[b] * u + colors[c] * v)
+    }
+
+    /// Interpolates the vertex texture coordinates at barycentric (u, v),
+    /// falling back to the barycentric coordinates themselves.
+    pub fn texture_uv(&self, face: usize, u: f64, v: f64) -> (f64, f64) {
+        match &self.uvs {
+            Some(uvs) => {
+                let [a, b, c] = self.faces[face];
+                let w = 1.0 - u - v;
+                (
+                    uvs[a].0 * w + uvs[b].0 * u + uvs[c].0 * v,
+                    uvs[a].1 * w + uvs[b].1 * u + uvs[c].1 * v,
+                )
+            }
+            None => (u, v),
+        }
+    }
+}
+
+/// A palette of materials and the index into it of each face, for meshes
+/// whose faces don't all share one material.
+#[derive(Clone, Default)]
+pub struct FaceMaterials {
+    pub materials: Vec<Arc<dyn Material>>,
+    pub ids: Vec<u16>,
+}
+
+impl FaceMaterials {
+    /// The material of `face`, unless it has no index or one past the palette.
+    pub fn get(&self, face: usize) -> Option<&Arc<dyn Material>> {
+        self.materials.get(*self.ids.get(face)? as usize)
+    }
+}
+
+/// The file a mesh was loaded from, kept so the mesh can be described for export.
+#[derive(Clone)]
+pub struct MeshSource {
+    pub path: PathBuf,
+    pub transform: Option<Transform>,
+    pub normalize: Option<f64>,
+    pub displacement: Option<Displacement>,
+}
+
+/// The height map a loaded mesh was displaced by, kept for export.
+#[derive(Clone)]
+pub struct Displacement {
+    pub texture: Arc<dyn Texture>,
+    pub scale: f64,
+    pub max_subdivision: u32,
+}
+
+/// What `TriangleMesh::displace` did to a mesh.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DisplaceReport {
+    pub triangles_before: usize,
+    pub triangles_after: usize,
+    /// How many times each face was split into four.
+    pub subdivisions: u32,
+}
+
+impl fmt::Display for DisplaceReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "displaced {} triangles into {} ({} subdivisions)",
+            self.triangles_before, self.triangles_after, self.subdivisions
+        )
+    }
+}
+
+/// How a mesh finds the triangles a ray hits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MeshAccel {
+    #[default]
+    Bvh,
+    /// A uniform grid: faster for evenly spread triangles, like terrain.
+    Grid,
+}
+
+enum TriangleIndex {
+    Bvh(BoundNode),
+    Grid(GridAccel),
+}
+
+impl TriangleIndex {
+    fn get(&self) -> &dyn Hittable {
+        match self {
+            TriangleIndex::Bvh(bvh) => bvh,
+            TriangleIndex::Grid(grid) => grid,
+        }
+    }
+}
+
+pub struct TriangleMesh {
+    data: Arc<MeshData>,
+    pub material: Arc<dyn Material>,
+    face_materials: Option<Arc<FaceMaterials>>,
+    accel: MeshAccel,
+    index: Option<TriangleIndex>,
+    build_time: Duration,
+    pub source: Option<MeshSource>,
+    /// Set by the model loaders.
+    pub report: Option<LoadReport>,
+}
+
+impl TriangleMesh {
+    pub fn new(data: MeshData, material: Arc<dyn Material>) -> Self {
+        Self::build_with(data, material, None, MeshAccel::default())
+    }
+    /// Creates a mesh whose triangles are found through `accel`.
+    pub fn with_accel(data: MeshData, material: Arc<dyn Material>, accel: MeshAccel) -> Self {
+        Self::build_with(data, material, None, accel)
+    }
+    /// Creates a mesh whose faces each use `materials[material_ids[face]]`.
+    /// Faces without a valid index use `material`.
+    pub fn with_materials(
+        data: MeshData,
+        material: Arc<dyn Material>,
+        materials: Vec<Arc<dyn Material>>,
+        material_ids: Vec<u16>,
+    ) -> Self {
+        let face_materials = FaceMaterials {
+            materials,
+            ids: material_ids,
+        };
+        Self::build_with(
+            data,
+            material,
+            Some(Arc::new(face_materials)),
+            MeshAccel::default(),
+        )
+    }
+    fn build_with(
+        data: MeshData,
+        material: Arc<dyn Material>,
+        face_materials: Option<Arc<FaceMaterials>>,
+        accel: MeshAccel,
+    ) -> Self {
+        let mut mesh = Self {
+            data: Arc::new(data),
+            material,
+            face_materials,
+            accel,
+            index: None,
+            build_time: Duration::ZERO,
+            source: None,
+            report: None,
+        };
+        mesh.build();
+        mesh
+    }
+
+    pub fn face_material(&self, face: usize) -> &Arc<dyn Material> {
+        self.face_materials
+            .as_ref()
+            .and_then(|face_materials| face_materials.get(face))
+            .unwrap_or(&self.material)
+    }
+    /// The palette faces pick their materials from, empty if they all use the default.
+    pub fn materials(&self) -> &[Arc<dyn Material>] {
+        self.face_materials
+            .as_ref()
+            .map_or(&[], |face_materials| &face_materials.materials)
+    }
+    pub fn material_ids(&self) -> Option<&[u16]> {
+        self.face_materials
+            .as_ref()
+            .map(|face_materials| face_materials.ids.as_slice())
+    }
+
+    pub fn accel(&self) -> MeshAccel {
+        self.accel
+    }
+    /// Switches the structure rays find triangles through, rebuilding it.
+    pub fn set_accel(&mut self, accel: MeshAccel) -> &mut Self {
+        if accel != self.accel {
+            self.accel = accel;
+            self.index = None;
+            self.build();
+        }
+        self
+    }
+
+    pub fn data(&self) -> &MeshData {
+        &self.data
+    }
+    pub fn triangle_count(&self) -> usize {
+        self.data.faces.len()
+    }
+    pub fn vertex_count(&self) -> usize {
+        self.data.positions.len()
+    }
+
+    /// The exact bounds of the vertices, without the padding of `bound()`.
+    pub fn bounds(&self) -> BoundingBox {
+        self.data
+            .positions
+            .iter()
+            .fold(BoundingBox::empty(), |bounds, &p| {
+                BoundingBox::from_boxes(bounds, BoundingBox::from_points(p, p))
+            })
+    }
+    /// The center of the mesh's bounding box.
+    pub fn centroid(&self) -> Point {
+        let [x, y, z] = self.bounds().intervals;
+        point(
+            (x.start + x.end) / 2.0,
+            (y.start + y.end) / 2.0,
+            (z.start + z.end) / 2.0,
+        )
+    }
+
+    /// Moves and rescales the mesh in place.
+    pub fn apply_transform(&mut self, transform: &Transform) {
+        // Drop the index first so its triangles release their handles on the data.
+        self.index = None;
+        let data = Arc::make_mut(&mut self.data);
+        for position in &mut data.positions {
+            *position = transform.point(*position);
+        }
+        if let Some(normals) = &mut data.normals {
+            for normal in normals {
+                *normal = transform.normal(*normal);
+            }
+        }
+        self.build();
+    }
+
+    /// Replaces the vertex normals with smooth ones computed from the faces,
+    /// keeping edges sharper than `angle_threshold` degrees hard.
+    /// See `MeshData::compute_vertex_normals`.
+    pub fn compute_vertex_normals(&mut self, angle_threshold: f64) {
+        self.index = None;
+        Arc::make_mut(&mut self.data).compute_vertex_normals(angle_threshold);
+        self.build();
+    }
+
+    /// Centers the mesh on the origin and scales it uniformly so its longest
+    /// side is `target_extent`. Returns the transform that was applied.
+    pub fn normalize(&mut self, target_extent: f64) -> Transform {
+        let bounds = self.bounds();
+        let extent = bounds.intervals[bounds.longest_axis()].size();
+        let scale = if extent > 0.0 {
+            target_extent / extent
+        } else {
+            1.0
+        };
+        let transform = Transform {
+            translation: -self.centroid() * scale,
+            rotation_y: 0.0,
+            scale: Vec3(scale, scale, scale),
+        };
+        self.apply_transform(&transform);
+        transform
+    }
+
+    /// Moves the surface along its normals by a height map, so the detail
+    /// shows in silhouettes and shadows, not just in shading.
+    ///
+    /// Faces are first split into four, up to `max_subdivision` times, until
+    /// no edge is longer than the diagonal of the mesh's box over
+    /// `2^max_subdivision`, so meshes that are already fine stay as they are. Each vertex then moves by `scale` times the
+    /// brightness of `texture` at its UV (or at (0, 0) for meshes without
+    /// any), and the normals and BVH or grid are rebuilt. Vertices split
+    /// along hard edges move apart, so creases can open.
+    pub fn displace(
+        &mut self,
+        texture: &dyn Texture,
+        scale: f64,
+        max_subdivision: u32,
+    ) -> DisplaceReport {
+        let triangles_before = self.triangle_count();
+        let extent = self.bounds().intervals.map(|i| i.size().max(0.0));
+        let diagonal = Vec3(extent[0], extent[1], extent[2]).length();
+        let target = diagonal / 2f64.powi(max_subdivision as i32);
+        let longest_edge = (0..self.triangle_count())
+            .map(|face| {
+                let (a, b, c) = self.data.vertices(face);
+                [(b - a), (c - b), (a - c)]
+                    .iter()
+                    .map(|edge| edge.length())
+                    .fold(0.0, f64::max)
+            })
+            .fold(0.0, f64::max);
+        let mut subdivisions = 0;
+        while subdivisions < max_subdivision
+            && longest_edge / 2f64.powi(subdivisions as i32) > target * (1.0 + 1e-9)
+        {
+            subdivisions += 1;
+        }
+
+        self.index = None;
+        let data = Arc::make_mut(&mut self.data);
+        if data.normals.is_none() {
+            data.compute_vertex_normals(DEFAULT_SMOOTHING_ANGLE);
+        }
+        for _ in 0..subdivisions {
+            data.subdivide();
+        }
+        let normals = data.normals.take().unwrap_or_default();
+        for (vertex, position) in data.positions.iter_mut().enumerate() {
+            let (u, v) = data.uvs.as_ref().map_or((0.0, 0.0), |uvs| uvs[vertex]);
+            let value = texture.value(u, v, position);
+            let height = (value.0 + value.1 + value.2) / 3.0;
+            *position += normals[vertex] * (height * scale);
+        }
+        data.compute_vertex_normals(DEFAULT_SMOOTHING_ANGLE);
+        if let Some(face_materials) = &mut self.face_materials {
+            let children = 4usize.pow(subdivisions);
+            let ids = &mut Arc::make_mut(face_materials).ids;
+            *ids = ids
+                .iter()
+                .flat_map(|&id| std::iter::repeat_n(id, children))
+                .collect();
+        }
+        self.build();
+        DisplaceReport {
+            triangles_before,
+            triangles_after: self.triangle_count(),
+            subdivisions,
+        }
+    }
+
+    /// How long the latest build of the BVH or grid took.
+    pub fn build_time(&self) -> Duration {
+        self.build_time
+    }
+    /// A rough count of the bytes held by the vertex data, the triangles,
+    /// and the BVH or grid over them.
+    pub fn estimated_bytes(&self) -> usize {
+        let data = &self.data;
+        let vertices = data.positions.len()
+            * (mem::size_of::<Point>()
+                + data.normals.as_ref().map_or(0, |_| mem::size_of::<Vec3>())
+                + data
+                    .uvs
+                    .as_ref()
+                    .map_or(0, |_| mem::size_of::<(f64, f64)>())
+                + data.colors.as_ref().map_or(0, |_| mem::size_of::<Color>()));
+        let faces = data.faces.len() * mem::size_of::<[usize; 3]>();
+        let ids = self.material_ids().map_or(0, mem::size_of_val);
+        let index = match &self.index {
+            // The BVH keeps the triangles in its leaves.
+            Some(TriangleIndex::Bvh(bvh)) => bvh.estimated_bytes(),
+            // Each triangle sits behind an Arc (two counters) and a fat
+            // pointer in the grid's object list.
+            Some(TriangleIndex::Grid(grid)) => {
+                let arc = 2 * mem::size_of::<usize>();
+                let handle = mem::size_of::<Arc<dyn Hittable>>();
+                data.faces.len() * (mem::size_of::<MeshTriangle>() + arc + handle)
+                    + grid.estimated_bytes()
+            }
+            None => 0,
+        };
+        vertices + faces + ids + index
+    }
+
+    fn build(&mut self) {
+        let started = Instant::now();
+        let triangles = (0..self.data.faces.len()).map(|face| {
+            MeshTriangle::new(
+                self.data.clone(),
+                face,
+                self.material.clone(),
+                self.face_materials.clone(),
+            )
+        });
+        self.index = if self.data.faces.is_empty() {
+            None
+        } else {
+            Some(match self.accel {
+                MeshAccel::Bvh => TriangleIndex::Bvh(BoundNode::from_primitives(
+                    triangles.map(Primitive::MeshTriangle).collect(),
+                )),
+                MeshAccel::Grid => TriangleIndex::Grid(GridAccel::new(
+                    triangles
+                        .map(|triangle| Arc::new(triangle) as Arc<dyn Hittable>)
+                        .collect(),
+                )),
+            })
+        };
+        self.build_time = started.elapsed();
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn hit(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> Option<HitRecord<'_>> {
+        self.index
+            .as_ref()
+            .and_then(|index| index.get().hit(ray, t, rng))
+    }
+
+    fn hit_with_stats(
+        &self,
+        ray: &Ray,
+        t: Interval,
+        rng: &mut dyn Sampler,
+        stats: &mut TraversalStats,
+    ) -> Option<HitRecord<'_>> {
+        self.index
+            .as_ref()
+            .and_then(|index| index.get().hit_with_stats(ray, t, rng, stats))
+    }
+
+    fn hit_any(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> bool {
+        self.index
+            .as_ref()
+            .is_some_and(|index| index.get().hit_any(ray, t, rng))
+    }
+
+    fn bound(&self) -> BoundingBox {
+        match &self.index {
+            Some(index) => index.get().bound(),
+            None => BoundingBox::empty(),
+        }
+    }
+    fn bound_transformed(&self, transform: &Mat4) -> BoundingBox {
+        let positions = &self.data.positions;
+        let corners = self.data.faces.iter().flatten();
+        BoundingBox::around(corners.map(|&i| transform.transform_point(positions[i])))
+    }
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive() || self.materials().iter().any(|m| m.is_emissive())
+    }
+
+    fn describe(&self, describer: &mut Describer) -> Option<ObjectDesc> {
+        let source = self.source.as_ref()?;
+        Some(ObjectDesc::Mesh {
+            path: source.path.clone(),
+            material: describer.material(&self.material)?.into(),
+            transform: source.transform,
+            normalize: source.normalize,
+            accel: (self.accel != MeshAccel::default()).then_some(self.accel),
+            displace: match &source.displacement {
+                Some(displacement) => Some(DisplaceDesc {
+                    texture: describer.texture(&displacement.texture)?.into(),
+                    scale: displacement.scale,
+                    max_subdivision: displacement.max_subdivision,
+                }),
+                None => None,
+            },
+        })
+    }
+
+    fn validate(&self, path: &str, issues: &mut Vec<ValidationIssue>) {
+        if let Some(face_materials) = &self.face_materials {
+            if face_materials.ids.len() != self.triangle_count() {
+                issues.push(ValidationIssue::error(
+                    path,
+                    format!(
+                        "{} material indices for {} triangles",
+                        face_materials.ids.len(),
+                        self.triangle_count()
+                    ),
+                ));
+            }
+            let unknown = face_materials
+                .ids
+                .iter()
+                .filter(|&&id| id as usize >= face_materials.materials.len())
+                .count();
+            if unknown > 0 {
+                issues.push(ValidationIssue::error(
+                    path,
+                    format!(
+                        "{} triangles index past the {} mesh materials",
+                        unknown,
+                        face_materials.materials.len()
+                    ),
+                ));
+            }
+        }
+        let invalid = self
+            .data
+            .positions
+            .iter()
+            .filter(|p| !p.is_finite())
+            .count();
+        if invalid > 0 {
+            issues.push(ValidationIssue::error(
+                path,
+                format!("{} vertices have non-finite coordinates", invalid),
+            ));
+        }
+        let degenerate = (0..self.triangle_count())
+            .filter(|&face| {
+                let (a, b, c) = self.data.vertices(face);
+                Vec3::cross(&(b - a), &(c - a)).length() < 1e-12
+            })
+            .count();
+        if degenerate > 0 {
+            issues.push(ValidationIssue::warning(
+                path,
+                format!(
+                    "{} of {} triangles have zero area",
+                    degenerate,
+                    self.triangle_count()
+                ),
+            ));
+        }
+    }
+
+    /// A summary: listing every triangle would bury everything else.
+    fn label(&self) -> String {
+        let accel = match self.accel {
+            MeshAccel::Bvh => "BVH",
+            MeshAccel::Grid => "grid",
+        };
+        let materials = match self.materials().len() {
+            0 => self.material.kind().to_string(),
+            n => format!("{} materials", n),
+        };
+        format!(
+            "TriangleMesh ({} tris, {} vertices, {}, {})",
+            self.triangle_count(),
+            self.vertex_count(),
+            accel,
+            materials
+        )
+    }
+    fn primitive_count(&self) -> usize {
+        self.triangle_count()
+    }
+}
+
+impl fmt::Display for TriangleMesh {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} triangles, {} vertices, bounds {}",
+            self.triangle_count(),
+            self.vertex_count(),
+            self.bound()
+        )
+    }
+}
+
+/// A single face of a `TriangleMesh`, sharing the mesh's vertex data and
+/// material palette.
+#[derive(Clone)]
+pub struct MeshTriangle {
+    mesh: Arc<MeshData>,
+    face: usize,
+    material: Arc<dyn Material>,
+    face_materials: Option<Arc<FaceMaterials>>,
+    bounds: BoundingBox,
+}
+
+impl MeshTriangle {
+    pub fn new(
+        mesh: Arc<MeshData>,
+        face: usize,
+        material: Arc<dyn Material>,
+        face_materials: Option<Arc<FaceMaterials>>,
+    ) -> Self {
+        let (a, b, c) = mesh.vertices(face);
+        let bounds = BoundingBox::from_boxes(
+            BoundingBox::from_points(a, b),
+            BoundingBox::from_points(c, c),
+        );
+        Self {
+            mesh,
+            face,
+            material,
+            face_materials,
+            bounds,
+        }
+    }
+
+    fn material(&self) -> &dyn Material {
+        self.face_materials
+            .as_ref()
+            .and_then(|face_materials| face_materials.get(self.face))
+            .unwrap_or(&self.material)
+            .as_ref()
+    }
+}
+
+impl Hittable for MeshTriangle {
+    fn hit(&self, ray: &Ray, t_range: Interval, _rng: &mut dyn Sampler) -> Option<HitRecord<'_>> {
+        // Möller–Trumbore: solve for t and the barycentric (u, v) directly.
+        let (a, b, c) = self.mesh.vertices(self.face);
+        let edge_1 = b - a;
+        let edge_2 = c - a;
+
+        let p = Vec3::cross(&ray.direction, &edge_2);
+        let determinant = Vec3::dot(&edge_1, &p);
+        if determinant.abs() < 1e-12 {
+            return None;
+        }
+        let inv_determinant = 1.0 / determinant;
+
+        let s = ray.origin - a;
+        let u = Vec3::dot(&s, &p) * inv_determinant;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = Vec3::cross(&s, &edge_1);
+        let v = Vec3::dot(&ray.direction, &q) * inv_determinant;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let t = Vec3::dot(&edge_2, &q) * inv_determinant;
+        if !t_range.surrounds(t) {
+            return None;
+        }
+
+        let normal = Vec3::cross(&edge_1, &edge_2).unit();
+        let (tex_u, tex_v) = self.mesh.texture_uv(self.face, u, v);
+        let mut record =
+            HitRecord::new(ray, t, ray.at(t), normal, self.material()).set_uv(tex_u, tex_v);
+        record.vertex_color = self.mesh.vertex_color(self.face, u, v);
+
+        // Face orientation comes from the geometric normal; the interpolated
+        // normal only changes shading, kept on the same side as the face.
+        if let Some(shading) = self.mesh.shading_normal(self.face, u, v) {
+            let shading = if Vec3::dot(&shading, &normal) < 0.0 {
+                -shading
+            } else {
+                shading
+            };
+            record.normal = if record.front_face { shading } else { -shading };
+        }
+        Some(record)
+    }
+
+    fn bound(&self) -> BoundingBox {
+        self.bounds
+    }
+    fn bound_transformed(&self, transform: &Mat4) -> BoundingBox {
+        let (a, b, c) = self.mesh.vertices(self.face);
+        BoundingBox::around([a, b, c].map(|p| transform.transform_point(p)))
+    }
+    fn is_emissive(&self) -> bool {
+        self.material().is_emissive()
+    }
+    fn to_primitive(&self) -> Option<Primitive> {
+        Some(Primitive::MeshTriangle(self.clone()))
+    }
+    fn label(&self) -> String {
+        format!(
+            "MeshTriangle (face {}, {})",
+            self.face,
+            self.material().kind()
+        )
+    }
+}
+
+debug_as_hierarchy!(TriangleMesh, MeshTriangle);
@@ -0,0 +1,143 @@
+use crate::{EnvironmentPdf, Pdf, Point, Sampler, Vec3};
+
+/// An opening, such as a window, through which the environment lights an
+/// interior: a parallelogram from `corner` spanned by `sides`, whose
+/// inside is the side `sides.0 × sides.1` points to. A portal is never
+/// drawn and blocks nothing; it only tells the integrator where to send
+/// shadow rays toward the environment from points inside, which would
+/// otherwise mostly end on the walls.
+///
+/// Light only arrives through the portals from a point inside them, so a
+/// room needs one over every opening the environment shines through;
+/// light through others is still found, but only by bounces.
+#[derive(Clone, Debug)]
+pub struct Portal {
+    pub corner: Point,
+    pub sides: (Vec3, Vec3),
+    normal: Vec3,
+    /// Takes a point on the plane to its coordinates along the sides.
+    w: Vec3,
+    area: f64,
+}
+
+impl Portal {
+    pub fn new(corner: Point, sides: (Vec3, Vec3)) -> Self {
+        let n = Vec3::cross(&sides.0, &sides.1);
+        let area = n.length();
+        Self {
+            corner,
+            sides,
+            normal: n.unit(),
+            w: n / Vec3::dot(&n, &n),
+            area,
+        }
+    }
+
+    /// The unit normal, pointing inside.
+    pub fn normal(&self) -> Vec3 {
+        self.normal
+    }
+
+    pub fn area(&self) -> f64 {
+        self.area
+    }
+
+    /// Whether `point` is inside the portal, so light can come through it.
+    pub fn faces(&self, point: Point) -> bool {
+        self.area > 0.0 && Vec3::dot(&(point - self.corner), &self.normal) > 0.0
+    }
+
+    /// The unit direction from `origin` to the point at (s, t) along the
+    /// sides.
+    fn direction_to(&self, origin: Point, (s, t): (f64, f64)) -> Vec3 {
+        (self.corner + self.sides.0 * s + self.sides.1 * t - origin).unit()
+    }
+
+    /// The density per steradian of directions from `origin` drawn
+    /// uniformly over the portal's area, for unit `direction`: zero unless
+    /// it passes through the portal.
+    fn density(&self, origin: Point, direction: Vec3) -> f64 {
+        let cosine = Vec3::dot(&direction, &self.normal);
+        if cosine >= 0.0 {
+            return 0.0;
+        }
+        let distance = Vec3::dot(&(self.corner - origin), &self.normal) / cosine;
+        if !(distance > 0.0 && distance.is_finite()) {
+            return 0.0;
+        }
+        let offset = origin + direction * distance - self.corner;
+        let s = Vec3::dot(&self.w, &Vec3::cross(&offset, &self.sides.1));
+        let t = Vec3::dot(&self.w, &Vec3::cross(&self.sides.0, &offset));
+        if !((0.0..=1.0).contains(&s) && (0.0..=1.0).contains(&t)) {
+            return 0.0;
+        }
+        distance * distance / (self.area * -cosine)
+    }
+}
+
+/// Directions from `origin` toward the environment through the portals it
+/// is inside, each of those equally likely and its area uniformly so.
+pub struct PortalPdf<'a> {
+    portals: &'a [Portal],
+    origin: Point,
+    facing: usize,
+}
+
+impl<'a> PortalPdf<'a> {
+    /// The distribution for `origin`, or `None` if it is inside none of
+    /// `portals`.
+    pub fn new(portals: &'a [Portal], origin: Point) -> Option<Self> {
+        let facing = portals.iter().filter(|p| p.faces(origin)).count();
+        (facing > 0).then_some(Self {
+            portals,
+            origin,
+            facing,
+        })
+    }
+
+    fn facing(&self) -> impl Iterator<Item = &'a Portal> + '_ {
+        self.portals.iter().filter(|p| p.faces(self.origin))
+    }
+}
+
+impl Pdf for PortalPdf<'_> {
+    fn value(&self, direction: Vec3) -> f64 {
+        self.facing()
+            .map(|portal| portal.density(self.origin, direction))
+            .sum::<f64>()
+            / self.facing as f64
+    }
+
+    fn generate(&self, rng: &mut dyn Sampler) -> Vec3 {
+        // The first coordinate picks the portal as well as the point on it.
+        let (s, t) = rng.next_2d();
+        let scaled = s * self.facing as f64;
+        let index = (scaled as usize).min(self.facing - 1);
+        let portal = self.facing().nth(index).expect("index is below the count");
+        portal.direction_to(self.origin, (scaled - index as f64, t))
+    }
+}
+
+/// How shadow rays toward the environment are drawn from a point: in
+/// proportion to its brightness, or through the portals the point is
+/// inside.
+pub enum EnvironmentSampling<'a> {
+    Image(EnvironmentPdf<'a>),
+    Portals(PortalPdf<'a>),
+}
+
+impl Pdf for EnvironmentSampling<'_> {
+    fn value(&self, direction: Vec3) -> f64 {
+        match self {
+            EnvironmentSampling::Image(pdf) => pdf.value(direction),
+            EnvironmentSampling::Portals(pdf) => pdf.value(direction),
+        }
+    }
+
+    fn generate(&self, rng: &mut dyn Sampler) -> Vec3 {
+        match self {
+            EnvironmentSampling::Image(pdf) => pdf.generate(rng),
+            EnvironmentSampling::Portals(pdf) => pdf.generate(rng),
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use std::{mem, sync::Arc};
+
+use crate::{
+    hittable::*,
+    scenes::{Describer, ObjectDesc, ValidationIssue},
+    BoundingBox, Interval, Mat4, MeshTriangle, Parallelogram, Ray, Sampler, Sphere, TraversalStats,
+    Triangle,
+};
+
+/// An object a BVH keeps in its leaves: one of the built-in shapes, stored
+/// in place and hit by matching on it instead of through a pointer and a
+/// vtable, or anything else behind its `Arc`. Spheres and mesh triangles,
+/// by far the most numerous, are stored whole; the larger flat shapes are
+/// boxed so they don't pad every leaf to their size.
+#[derive(Clone)]
+pub enum Primitive {
+    Sphere(Sphere),
+    Parallelogram(Box<Parallelogram>),
+    Triangle(Box<Triangle>),
+    MeshTriangle(MeshTriangle),
+    Other(Arc<dyn Hittable>),
+}
+
+/// Evaluates `$body` with `$object` bound to whatever the primitive holds.
+macro_rules! dispatch {
+    ($primitive:expr, $object:ident => $body:expr) => {
+        match $primitive {
+            Primitive::Sphere($object) => $body,
+            Primitive::Parallelogram($object) => $body,
+            Primitive::Triangle($object) => $body,
+            Primitive::MeshTriangle($object) => $body,
+            Primitive::Other($object) => $body,
+        }
+    };
+}
+
+impl Primitive {
+    /// `object` stored in place if it is a built-in shape, or kept behind
+    /// its `Arc` if not.
+    pub fn from_arc(object: Arc<dyn Hittable>) -> Self {
+        object.to_primitive().unwrap_or(Primitive::Other(object))
+    }
+
+    /// The object on its own again, sharing it if it was never stored in
+    /// place.
+    pub fn to_arc(&self) -> Arc<dyn Hittable> {
+        match self {
+            Primitive::Sphere(sphere) => Arc::new(sphere.clone()),
+            Primitive::Parallelogram(parallelogram) => Arc::new((**parallelogram).clone()),
+            Primitive::Triangle(triangle) => Arc::new((**triangle).clone()),
+            Primitive::MeshTriangle(triangle) => Arc::new(triangle.clone()),
+            Primitive::Other(object) => object.clone(),
+        }
+    }
+
+    /// The bytes the primitive holds beyond its own size, in a box.
+    pub(crate) fn boxed_bytes(&self) -> usize {
+        match self {
+            Primitive::Parallelogram(_) => mem::size_of::<Parallelogram>(),
+            Primitive::Triangle(_) => mem::size_of::<Triangle>(),
+            _ => 0,
+        }
+    }
+}
+
+impl Hittable for Primitive {
+    fn hit(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> Option<HitRecord<'_>> {
+        dispatch!(self, object => object.hit(ray, t, rng))
+    }
+
+    fn hit_with_stats(
+        &self,
+        ray: &Ray,
+        t: Interval,
+        rng: &mut dyn Sampler,
+        stats: &mut TraversalStats,
+    ) -> Option<HitRecord<'_>> {
+        dispatch!(self, object => object.hit_with_stats(ray, t, rng, stats))
+    }
+
+    fn hit_any(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> bool {
+        dispatch!(self, object => object.hit_any(ray, t, rng))
+    }
+
+    fn bound(&self) -> BoundingBox {
+        dispatch!(self, object => object.bound())
+    }
+
+    fn bound_transformed(&self, transform: &Mat4) -> BoundingBox {
+        dispatch!(self, object => object.bound_transformed(transform))
+    }
+
+    fn is_emissive(&self) -> bool {
+        dispatch!(self, object => object.is_emissive())
+    }
+
+    fn collect_lights(&self, lights: &mut Vec<Arc<dyn Hittable>>) {
+        dispatch!(self, object => object.collect_lights(lights))
+    }
+
+    fn describe(&self, describer: &mut Describer) -> Option<ObjectDesc> {
+        dispatch!(self, object => object.describe(describer))
+    }
+
+    fn validate(&self, path: &str, issues: &mut Vec<ValidationIssue>) {
+        dispatch!(self, object => object.validate(path, issues))
+    }
+
+    fn label(&self) -> String {
+        dispatch!(self, object => object.label())
+    }
+
+    fn children(&self) -> Vec<&dyn Hittable> {
+        dispatch!(self, object => object.children())
+    }
+
+    fn primitive_count(&self) -> usize {
+        dispatch!(self, object => object.primitive_count())
+    }
+}
+
+debug_as_hierarchy!(Primitive);
@@ -1,7 +1,13 @@
-use crate::{hittable::*, point, BoundingBox, Interval, Invisible, Material, Point, Ray, Vec3};
+use crate::{
+    hittable::*,
+    point,
+    scenes::{Describer, ObjectDesc, ValidationIssue},
+    BoundingBox, Interval, Invisible, Mat4, Material, Point, Primitive, Ray, Sampler, Vec3,
+};
 
 use std::{f64::consts::PI, sync::Arc};
 
+#[derive(Clone)]
 pub struct Sphere {
     pub center: Vec3,
     pub radius: f64,
@@ -31,7 +37,7 @@ impl Sphere {
 }
 
 impl Hittable for Sphere {
-    fn hit(&self, ray: &Ray, t_range: Interval) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_range: Interval, _rng: &mut dyn Sampler) -> Option<HitRecord<'_>> {
         let oc = self.center - ray.origin;
         let a = ray.direction.length_squared();
         let h = Vec3::dot(&ray.direction, &oc);
@@ -57,24 +63,73 @@ impl Hittable for Sphere {
         let normal = (point - self.center) / self.radius;
         let (u, v) = self.get_uv(&normal);
 
-        Some(HitRecord::new(ray, root, point, normal, self.material.clone()).set_uv(u, v))
+        Some(HitRecord::new(ray, root, point, normal, self.material.as_ref()).set_uv(u, v))
     }
 
     fn bound(&self) -> BoundingBox {
         self.bounds
     }
+    /// An affine map turns the sphere into an ellipsoid whose half-width
+    /// along each axis is the radius times the length of that row.
+    fn bound_transformed(&self, transform: &Mat4) -> BoundingBox {
+        let m = &transform.m;
+        if m[3] != [0., 0., 0., 1.] {
+            return self.bound().transformed(transform);
+        }
+        let center = transform.transform_point(self.center);
+        let reach = |row: usize| self.radius * Vec3(m[row][0], m[row][1], m[row][2]).length();
+        let extent = Vec3(reach(0), reach(1), reach(2));
+        BoundingBox::from_points(center - extent, center + extent)
+    }
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn describe(&self, describer: &mut Describer) -> Option<ObjectDesc> {
+        Some(ObjectDesc::Sphere {
+            center: self.center,
+            radius: self.radius,
+            material: describer.material(&self.material)?.into(),
+        })
+    }
+    fn validate(&self, path: &str, issues: &mut Vec<ValidationIssue>) {
+        if !self.center.is_finite() {
+            issues.push(ValidationIssue::error(path, "center is not finite"));
+        }
+        if !(self.radius.is_finite() && self.radius > 0.0) {
+            issues.push(ValidationIssue::error(
+                path,
+                format!("radius must be positive, got {}", self.radius),
+            ));
+        }
+    }
+    fn to_primitive(&self) -> Option<Primitive> {
+        Some(Primitive::Sphere(self.clone()))
+    }
+    fn label(&self) -> String {
+        format!(
+            "Sphere at {}, radius {} ({})",
+            self.center,
+            self.radius,
+            self.material.kind()
+        )
+    }
 }
 
+#[derive(Clone)]
 pub struct Triangle {
     pub vertex: (Vec3, Vec3, Vec3),
-    normal: Vec3,
+    plane: Plane,
+    w: Vec3,
     pub material: Arc<dyn Material>,
     bounds: BoundingBox,
 }
 
 impl Triangle {
     pub fn new(vertex: (Vec3, Vec3, Vec3), material: Arc<dyn Material>) -> Self {
-        let normal = Vec3::cross(&(vertex.1 - vertex.0), &(vertex.2 - vertex.0));
+        let n = Vec3::cross(&(vertex.1 - vertex.0), &(vertex.2 - vertex.0));
+        let normal = n.unit();
+        let w = n / Vec3::dot(&n, &n);
 
         let min_x = vertex.0.x().min(vertex.1.x()).min(vertex.2.x());
         let min_y = vertex.0.y().min(vertex.1.y()).min(vertex.2.y());
@@ -85,7 +140,8 @@ impl Triangle {
         let bounds = BoundingBox::from_points(Vec3(min_x, min_y, min_z), Vec3(max_x, max_y, max_z));
         Self {
             vertex,
-            normal,
+            plane: Plane::new(vertex.0, normal),
+            w,
             material,
             bounds,
         }
@@ -93,7 +149,7 @@ impl Triangle {
 
     pub fn is_interior(alpha: f64, beta: f64) -> Option<(f64, f64)> {
         if alpha < 0.0 || beta < 0.0 || alpha + beta > 1.0 {
-            return None;
+            None
         } else {
             Some((alpha, beta))
         }
@@ -101,39 +157,50 @@ impl Triangle {
 }
 
 impl Hittable for Triangle {
-    fn hit(&self, ray: &Ray, _t_range: Interval) -> Option<HitRecord> {
-        let normal = Vec3::cross(
-            &(self.vertex.1 - self.vertex.0),
-            &(self.vertex.2 - self.vertex.0),
-        );
-
-        let p = ray.direction + ray.origin;
-        let normal_a = Vec3::cross(&(self.vertex.2 - self.vertex.1), &(p - self.vertex.1));
-        let normal_b = Vec3::cross(&(self.vertex.0 - self.vertex.2), &(p - self.vertex.2));
-        let normal_c = Vec3::cross(&(self.vertex.1 - self.vertex.0), &(p - self.vertex.0));
-
-        let bary = Vec3(
-            Vec3::dot(&normal, &normal_a),
-            Vec3::dot(&normal, &normal_b),
-            Vec3::dot(&normal, &normal_c),
-        ) / normal.length_squared();
-
-        if bary.0 > 0.0 && bary.1 > 0.0 && bary.2 > 0.0 {
-            Some(HitRecord::new(ray, 0.0, p, normal, self.material.clone()))
-        } else {
-            None
-        }
+    fn hit(&self, ray: &Ray, t_range: Interval, _rng: &mut dyn Sampler) -> Option<HitRecord<'_>> {
+        let (t, point) = self.plane.intersect(ray, t_range)?;
+        let p = point - self.vertex.0;
+        let alpha = Vec3::dot(&self.w, &Vec3::cross(&p, &(self.vertex.2 - self.vertex.0)));
+        let beta = Vec3::dot(&self.w, &Vec3::cross(&(self.vertex.1 - self.vertex.0), &p));
+        let (u, v) = Self::is_interior(alpha, beta)?;
+        Some(HitRecord::new(ray, t, point, self.plane.normal, self.material.as_ref()).set_uv(u, v))
     }
 
     fn bound(&self) -> BoundingBox {
         self.bounds
     }
+    fn bound_transformed(&self, transform: &Mat4) -> BoundingBox {
+        let (a, b, c) = self.vertex;
+        BoundingBox::around([a, b, c].map(|p| transform.transform_point(p)))
+    }
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn describe(&self, describer: &mut Describer) -> Option<ObjectDesc> {
+        Some(ObjectDesc::Triangle {
+            vertices: [self.vertex.0, self.vertex.1, self.vertex.2],
+            material: describer.material(&self.material)?.into(),
+        })
+    }
+    fn validate(&self, path: &str, issues: &mut Vec<ValidationIssue>) {
+        let (a, b, c) = self.vertex;
+        validate_planar(path, [a, b, c], Vec3::cross(&(b - a), &(c - a)), issues);
+    }
+    fn to_primitive(&self) -> Option<Primitive> {
+        Some(Primitive::Triangle(Box::new(self.clone())))
+    }
+    fn label(&self) -> String {
+        let (a, b, c) = self.vertex;
+        format!("Triangle {}, {}, {} ({})", a, b, c, self.material.kind())
+    }
 }
 
+#[derive(Clone)]
 pub struct Parallelogram {
     pub corner: Point,
     pub sides: (Vec3, Vec3),
-    normal: Vec3,
+    plane: Plane,
     w: Vec3,
     pub material: Arc<dyn Material>,
     bounds: BoundingBox,
@@ -143,7 +210,6 @@ impl Parallelogram {
     pub fn new(corner: Point, sides: (Vec3, Vec3), material: Arc<dyn Material>) -> Self {
         let n = Vec3::cross(&sides.0, &sides.1);
         let normal = n.unit();
-        // let d = Vec3::dot(&normal, &corner);
         let w = n / Vec3::dot(&n, &n);
         let diagonal_bound_1 = BoundingBox::from_points(corner, corner + sides.0 + sides.1);
         let diagonal_bound_2 = BoundingBox::from_points(corner + sides.0, corner + sides.1);
@@ -151,7 +217,7 @@ impl Parallelogram {
         Self {
             corner,
             sides,
-            normal,
+            plane: Plane::new(corner, normal),
             w,
             material,
             bounds,
@@ -177,32 +243,63 @@ impl Parallelogram {
 }
 
 impl Hittable for Parallelogram {
-    fn hit(&self, ray: &Ray, t_range: Interval) -> Option<HitRecord> {
-        if let Some(record) = Plane::new(self.corner, self.normal).hit(ray, t_range) {
-            let p = record.point - self.corner;
-            let alpha = Vec3::dot(&self.w, &Vec3::cross(&p, &self.sides.1));
-            let beta = Vec3::dot(&self.w, &Vec3::cross(&self.sides.0, &p));
-            if let Some((u, v)) = Self::is_interior(alpha, beta) {
-                Some(
-                    HitRecord::new(
-                        ray,
-                        record.t,
-                        record.point,
-                        self.normal,
-                        self.material.clone(),
-                    )
-                    .set_uv(u, v),
-                )
-            } else {
-                None
-            }
-        } else {
-            None
-        }
+    fn hit(&self, ray: &Ray, t_range: Interval, _rng: &mut dyn Sampler) -> Option<HitRecord<'_>> {
+        let (t, point) = self.plane.intersect(ray, t_range)?;
+        let p = point - self.corner;
+        let alpha = Vec3::dot(&self.w, &Vec3::cross(&p, &self.sides.1));
+        let beta = Vec3::dot(&self.w, &Vec3::cross(&self.sides.0, &p));
+        let (u, v) = Self::is_interior(alpha, beta)?;
+        Some(HitRecord::new(ray, t, point, self.plane.normal, self.material.as_ref()).set_uv(u, v))
     }
     fn bound(&self) -> BoundingBox {
         self.bounds
     }
+    fn bound_transformed(&self, transform: &Mat4) -> BoundingBox {
+        let (u, v) = self.sides;
+        let q = self.corner;
+        BoundingBox::around([q, q + u, q + v, q + u + v].map(|p| transform.transform_point(p)))
+    }
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
+    fn describe(&self, describer: &mut Describer) -> Option<ObjectDesc> {
+        Some(ObjectDesc::Quad {
+            corner: self.corner,
+            u: self.sides.0,
+            v: self.sides.1,
+            material: describer.material(&self.material)?.into(),
+        })
+    }
+    fn validate(&self, path: &str, issues: &mut Vec<ValidationIssue>) {
+        let (u, v) = self.sides;
+        validate_planar(path, [self.corner, u, v], Vec3::cross(&u, &v), issues);
+    }
+    fn to_primitive(&self) -> Option<Primitive> {
+        Some(Primitive::Parallelogram(Box::new(self.clone())))
+    }
+    fn label(&self) -> String {
+        format!(
+            "Parallelogram at {}, sides {} and {} ({})",
+            self.corner,
+            self.sides.0,
+            self.sides.1,
+            self.material.kind()
+        )
+    }
+}
+
+/// Planar shapes with no area have no normal, and every hit on them shades as NaN.
+fn validate_planar(
+    path: &str,
+    vectors: [Vec3; 3],
+    normal: Vec3,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    if vectors.iter().any(|v| !v.is_finite()) {
+        issues.push(ValidationIssue::error(path, "coordinates are not finite"));
+    } else if normal.length() < 1e-12 {
+        issues.push(ValidationIssue::error(path, "shape has zero area"));
+    }
 }
 
 pub fn parallelepiped(a: Point, b: Point, material: Arc<dyn Material>) -> Arc<HittableList> {
@@ -249,40 +346,47 @@ pub fn parallelepiped(a: Point, b: Point, material: Arc<dyn Material>) -> Arc<Hi
     Arc::new(sides)
 }
 
+#[derive(Clone, Copy)]
 pub struct Plane {
     pub point: Vec3,
     pub normal: Vec3,
+    d: f64,
 }
 
 impl Plane {
     pub fn new(point: Vec3, normal: Vec3) -> Self {
-        Self { point, normal }
+        let d = Vec3::dot(&point, &normal);
+        Self { point, normal, d }
     }
-}
-
-impl Hittable for Plane {
-    fn hit(&self, ray: &Ray, t_range: Interval) -> Option<HitRecord> {
-        let d = Vec3::dot(&self.point, &self.normal);
 
+    /// The distance along `ray` to the plane and the point it crosses at.
+    pub fn intersect(&self, ray: &Ray, t_range: Interval) -> Option<(f64, Point)> {
         let denominator = Vec3::dot(&ray.direction, &self.normal);
         if denominator.abs() < 1e-8 {
             return None;
         }
-        let t = (d - Vec3::dot(&ray.origin, &self.normal)) / denominator;
+        let t = (self.d - Vec3::dot(&ray.origin, &self.normal)) / denominator;
         if !t_range.contains(t) {
             return None;
         }
-        Some(HitRecord::new(
-            ray,
-            t,
-            ray.at(t),
-            self.normal,
-            Arc::new(Invisible),
-        ))
+        Some((t, ray.at(t)))
+    }
+}
+
+impl Hittable for Plane {
+    fn hit(&self, ray: &Ray, t_range: Interval, _rng: &mut dyn Sampler) -> Option<HitRecord<'_>> {
+        let (t, point) = self.intersect(ray, t_range)?;
+        Some(HitRecord::new(ray, t, point, self.normal, &Invisible))
     }
     fn bound(&self) -> BoundingBox {
         BoundingBox::empty()
     }
+    fn bound_transformed(&self, _transform: &Mat4) -> BoundingBox {
+        BoundingBox::empty()
+    }
+    fn label(&self) -> String {
+        format!("Plane through {}, facing {}", self.point, self.normal)
+    }
 }
 
 pub enum Planar {
@@ -291,42 +395,10 @@ pub enum Planar {
 }
 
 impl Hittable for Planar {
-    fn hit(&self, ray: &Ray, t_range: Interval) -> Option<HitRecord> {
-        let (point, normal, material) = match self {
-            Planar::Triangle(triangle) => (
-                triangle.vertex.0,
-                triangle.normal,
-                triangle.material.clone(),
-            ),
-            Planar::Parallelogram(parallelogram) => (
-                parallelogram.corner,
-                parallelogram.normal,
-                parallelogram.material.clone(),
-            ),
-        };
-        if let Some(record) = Plane::new(point, normal).hit(ray, t_range) {
-            let p = record.point - point;
-            let w = normal / Vec3::dot(&normal, &normal);
-            if let Some((u, v)) = match self {
-                Planar::Triangle(triangle) => {
-                    let u = triangle.vertex.1 - triangle.vertex.0;
-                    let v = triangle.vertex.2 - triangle.vertex.0;
-                    let alpha = Vec3::dot(&w, &Vec3::cross(&p, &v));
-                    let beta = Vec3::dot(&w, &Vec3::cross(&u, &p));
-                    Triangle::is_interior(alpha, beta)
-                }
-                Planar::Parallelogram(quad) => {
-                    let alpha = Vec3::dot(&quad.w, &Vec3::cross(&p, &quad.sides.1));
-                    let beta = Vec3::dot(&quad.w, &Vec3::cross(&quad.sides.0, &p));
-                    Parallelogram::is_interior(alpha, beta)
-                }
-            } {
-                Some(HitRecord::new(ray, record.t, record.point, normal, material).set_uv(u, v))
-            } else {
-                None
-            }
-        } else {
-            None
+    fn hit(&self, ray: &Ray, t_range: Interval, rng: &mut dyn Sampler) -> Option<HitRecord<'_>> {
+        match self {
+            Planar::Triangle(triangle) => triangle.hit(ray, t_range, rng),
+            Planar::Parallelogram(quad) => quad.hit(ray, t_range, rng),
         }
     }
 
@@ -336,4 +408,37 @@ impl Hittable for Planar {
             Planar::Parallelogram(quad) => quad.bound(),
         }
     }
+    fn bound_transformed(&self, transform: &Mat4) -> BoundingBox {
+        match self {
+            Planar::Triangle(triangle) => triangle.bound_transformed(transform),
+            Planar::Parallelogram(quad) => quad.bound_transformed(transform),
+        }
+    }
+    fn is_emissive(&self) -> bool {
+        match self {
+            Planar::Triangle(triangle) => triangle.is_emissive(),
+            Planar::Parallelogram(quad) => quad.is_emissive(),
+        }
+    }
+
+    fn describe(&self, describer: &mut Describer) -> Option<ObjectDesc> {
+        match self {
+            Planar::Triangle(triangle) => triangle.describe(describer),
+            Planar::Parallelogram(quad) => quad.describe(describer),
+        }
+    }
+    fn validate(&self, path: &str, issues: &mut Vec<ValidationIssue>) {
+        match self {
+            Planar::Triangle(triangle) => triangle.validate(path, issues),
+            Planar::Parallelogram(quad) => quad.validate(path, issues),
+        }
+    }
+    fn label(&self) -> String {
+        match self {
+            Planar::Triangle(triangle) => triangle.label(),
+            Planar::Parallelogram(quad) => quad.label(),
+        }
+    }
 }
+
+debug_as_hierarchy!(Sphere, Triangle, Parallelogram, Plane, Planar);
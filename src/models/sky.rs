@@ -0,0 +1,199 @@
+use std::f64::consts::PI;
+
+use crate::{color, uv_to_direction, Color, DirectionalLight, Environment, ImageBuffer, Vec3};
+
+/// Converts the sky's kilocandelas per square metre and the sun's kilolux to
+/// the renderer's units, so a noon sun lights a white surface facing it to
+/// about 1.
+const SCALE: f64 = 1.0 / 30.0;
+
+/// The sun's illuminance above the atmosphere, in kilolux.
+const SOLAR_ILLUMINANCE: f64 = 128.0;
+
+/// Wavelengths standing in for the red, green, and blue channels, in μm.
+const WAVELENGTHS: [f64; 3] = [0.680, 0.550, 0.440];
+
+/// A clear daytime sky after Preetham, Shirley, and Smits' analytic model,
+/// with the sun at `elevation` degrees above the horizon and `azimuth`
+/// degrees clockwise from north seen from above: north is -z, east +x, and
+/// up +y. `turbidity` is how hazy the air is, from about 2 for a crisp
+/// mountain sky to 10 for a hazy summer one.
+///
+/// The sun itself isn't in the sky's radiance but is a `DirectionalLight`,
+/// see `sun`, so that its shadows are sampled directly; below the horizon
+/// the sky is black, the ground being the scene's to draw.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sky {
+    pub elevation: f64,
+    pub azimuth: f64,
+    pub turbidity: f64,
+    /// Scales the radiance of the sky and the irradiance of the sun.
+    pub strength: f64,
+}
+
+impl Sky {
+    pub fn new(elevation: f64, azimuth: f64, turbidity: f64) -> Self {
+        Self {
+            elevation,
+            azimuth,
+            turbidity,
+            strength: 1.0,
+        }
+    }
+
+    /// The sky at `hour`, from 0 to 24, on a mid-latitude day near the
+    /// equinox: the sun rises in the east at 6, is highest, 60° up in the
+    /// south, at noon, and sets in the west at 18.
+    pub fn from_time_of_day(hour: f64, turbidity: f64) -> Self {
+        let day = (hour - 6.0) / 12.0;
+        Self::new(60.0 * (PI * day).sin(), 90.0 + 180.0 * day, turbidity)
+    }
+
+    /// The unit direction toward the sun.
+    pub fn sun_direction(&self) -> Vec3 {
+        let (elevation, azimuth) = (self.elevation.to_radians(), self.azimuth.to_radians());
+        Vec3(
+            azimuth.sin() * elevation.cos(),
+            elevation.sin(),
+            -azimuth.cos() * elevation.cos(),
+        )
+    }
+
+    /// The radiance of the sky seen looking along unit `direction`.
+    pub fn radiance(&self, direction: Vec3) -> Color {
+        if direction.y() <= 0.0 {
+            return color(0.0, 0.0, 0.0);
+        }
+        // After sunset the sky keeps its colors at the horizon, fading out
+        // over the six degrees of civil twilight.
+        let fade = ((self.elevation + 6.0) / 6.0).clamp(0.0, 1.0);
+        if fade == 0.0 {
+            return color(0.0, 0.0, 0.0);
+        }
+        let mut sun = self.sun_direction();
+        sun.1 = sun.1.max(0.0);
+        let sun = sun.unit();
+
+        let turbidity = self.turbidity.clamp(1.7, 10.0);
+        let theta_sun = sun.y().clamp(-1.0, 1.0).acos();
+        let cos_theta = direction.y().max(0.01);
+        let gamma = Vec3::dot(&direction, &sun).clamp(-1.0, 1.0).acos();
+        let relative = |[a, b, c, d, e]: [f64; 5]| {
+            let perez = |cos_theta: f64, gamma: f64| {
+                (1.0 + a * (b / cos_theta).exp())
+                    * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+            };
+            perez(cos_theta, gamma) / perez(1.0, theta_sun)
+        };
+        let t = turbidity;
+        let luminance = zenith_luminance(t, theta_sun)
+            * relative([
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ]);
+        let (zenith_x, zenith_y) = zenith_chromaticity(t, theta_sun);
+        let x = zenith_x
+            * relative([
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ]);
+        let y = zenith_y
+            * relative([
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ]);
+        xyy_to_rgb(x, y, luminance.max(0.0)) * (SCALE * self.strength * fade)
+    }
+
+    /// The sun as a light, its color and brightness what is left of
+    /// sunlight after Rayleigh and aerosol scattering on its way through
+    /// the air, or `None` once it has set.
+    pub fn sun(&self) -> Option<DirectionalLight> {
+        if self.elevation <= 0.0 {
+            return None;
+        }
+        let zenith = 90.0 - self.elevation;
+        // Kasten and Young's relative air mass.
+        let air_mass =
+            1.0 / (zenith.to_radians().cos() + 0.50572 * (96.07995 - zenith).powf(-1.6364));
+        let beta = 0.04608 * self.turbidity.clamp(1.7, 10.0) - 0.04586;
+        let [r, g, b] = WAVELENGTHS.map(|lambda| {
+            let rayleigh = 0.008735 * lambda.powf(-4.08);
+            let aerosol = beta * lambda.powf(-1.3);
+            (-(rayleigh + aerosol) * air_mass).exp()
+        });
+        Some(DirectionalLight::new(
+            -self.sun_direction(),
+            color(r, g, b) * (SOLAR_ILLUMINANCE * SCALE * self.strength),
+        ))
+    }
+
+    /// The sky baked into an equirectangular `Environment` `width` pixels
+    /// around, so shadow rays toward it are importance sampled.
+    pub fn environment(&self, width: usize) -> Environment {
+        let height = (width / 2).max(1);
+        let mut image = ImageBuffer::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let direction = uv_to_direction(
+                    (x as f64 + 0.5) / width as f64,
+                    (y as f64 + 0.5) / height as f64,
+                );
+                image.set(x, y, self.radiance(direction));
+            }
+        }
+        Environment::new(image)
+    }
+}
+
+/// The luminance straight up, in kilocandelas per square metre, for the sun
+/// `theta_sun` radians from the zenith.
+fn zenith_luminance(turbidity: f64, theta_sun: f64) -> f64 {
+    let chi = (4.0 / 9.0 - turbidity / 120.0) * (PI - 2.0 * theta_sun);
+    (4.0453 * turbidity - 4.9710) * chi.tan() - 0.2155 * turbidity + 2.4192
+}
+
+/// The CIE xy chromaticity straight up.
+fn zenith_chromaticity(turbidity: f64, theta_sun: f64) -> (f64, f64) {
+    let t = [turbidity * turbidity, turbidity, 1.0];
+    let theta = [theta_sun.powi(3), theta_sun.powi(2), theta_sun, 1.0];
+    let apply = |matrix: [[f64; 4]; 3]| -> f64 {
+        (0..3)
+            .map(|i| t[i] * (0..4).map(|j| matrix[i][j] * theta[j]).sum::<f64>())
+            .sum()
+    };
+    (
+        apply([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]),
+        apply([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]),
+    )
+}
+
+/// Linear Rec. 709 color for CIE xyY, clipped to the gamut.
+fn xyy_to_rgb(x: f64, y: f64, luminance: f64) -> Color {
+    if y <= 0.0 {
+        return color(0.0, 0.0, 0.0);
+    }
+    let (cx, cz) = (x / y * luminance, (1.0 - x - y) / y * luminance);
+    color(
+        (3.2406 * cx - 1.5372 * luminance - 0.4986 * cz).max(0.0),
+        (-0.9689 * cx + 1.8758 * luminance + 0.0415 * cz).max(0.0),
+        (0.0557 * cx - 0.2040 * luminance + 1.0570 * cz).max(0.0),
+    )
+}
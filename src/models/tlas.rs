@@ -0,0 +1,371 @@
+use std::sync::Arc;
+
+use crate::{
+    hittable::*,
+    scenes::{validate_duplicates, Describer, ObjectDesc, ValidationIssue},
+    BoundingBox, Bounds, Interval, Mat4, Ray, Sampler, Stage, TraversalStats, Vec3,
+};
+
+/// One object of a `Tlas`, placed in the world by `transform`.
+#[derive(Clone)]
+pub struct Instance {
+    pub object: Arc<dyn Hittable>,
+    pub transform: Mat4,
+    /// The object where `transform` puts it; the object itself for the
+    /// identity, so unmoved instances cost nothing extra to hit.
+    placed: Arc<dyn Hittable>,
+}
+
+impl Instance {
+    pub fn new(object: Arc<dyn Hittable>, transform: Mat4) -> Self {
+        let placed = if transform == Mat4::identity() {
+            object.clone()
+        } else {
+            Arc::new(Transformed::new(object.clone(), transform))
+        };
+        Self {
+            object,
+            transform,
+            placed,
+        }
+    }
+
+    /// The object as placed in the world.
+    pub fn placed(&self) -> &Arc<dyn Hittable> {
+        &self.placed
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Children {
+    /// The index of an instance.
+    Leaf(usize),
+    /// The indices of two nodes.
+    Inner(usize, usize),
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    bounds: BoundingBox,
+    children: Children,
+}
+
+/// What `Tlas::update` did to keep the tree fit to its instances.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TlasUpdate {
+    /// Nothing had moved.
+    Unchanged,
+    /// The boxes were recomputed around the moved instances, keeping the
+    /// tree's shape.
+    Refit,
+    /// The tree had grown too loose to refit and was built again.
+    Rebuilt,
+}
+
+/// The top level of a two-level BVH: a tree over instances of objects,
+/// each with a transform and usually a BVH of its own, such as a mesh's.
+/// Moving an instance only needs the few top-level boxes above it
+/// recomputed, see `update`, not the whole world's BVH rebuilt, which is
+/// what makes dragging objects around a preview interactive.
+///
+/// Refitting keeps the tree's shape, which suits the instances less and
+/// less as they move apart from where it was built; once the boxes have
+/// grown by `rebuild_threshold` times as a ray sees them, the tree is
+/// built again. The nodes are kept in an array with parents before
+/// children, so cloning a `Tlas` to edit it while renders still hold the
+/// old one is cheap.
+#[derive(Clone)]
+pub struct Tlas {
+    instances: Vec<Instance>,
+    nodes: Vec<Node>,
+    /// `cost` when the tree was last built.
+    built_cost: f64,
+    dirty: bool,
+    pub rebuild_threshold: f64,
+}
+
+impl Tlas {
+    /// The ratio of boxes' surface areas past which `update` rebuilds.
+    pub const DEFAULT_REBUILD_THRESHOLD: f64 = 1.5;
+
+    pub fn new(instances: Vec<Instance>) -> Self {
+        let mut tlas = Self {
+            instances,
+            nodes: Vec::new(),
+            built_cost: 0.0,
+            dirty: false,
+            rebuild_threshold: Self::DEFAULT_REBUILD_THRESHOLD,
+        };
+        tlas.rebuild();
+        tlas
+    }
+
+    /// A tree with each of `objects` an instance where it is.
+    pub fn from_objects(objects: impl IntoIterator<Item = Arc<dyn Hittable>>) -> Self {
+        Self::new(
+            objects
+                .into_iter()
+                .map(|object| Instance::new(object, Mat4::identity()))
+                .collect(),
+        )
+    }
+
+    pub fn instances(&self) -> &[Instance] {
+        &self.instances
+    }
+
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    /// Places instance `index` by `transform` instead, leaving the tree to
+    /// be fit to it by `update`, which renders need done first.
+    pub fn set_transform(&mut self, index: usize, transform: Mat4) {
+        let instance = &mut self.instances[index];
+        *instance = Instance::new(instance.object.clone(), transform);
+        self.dirty = true;
+    }
+
+    /// Fits the tree to instances moved since the last update: refits it,
+    /// or rebuilds it if refitting has left it too loose.
+    pub fn update(&mut self) -> TlasUpdate {
+        if !self.dirty {
+            return TlasUpdate::Unchanged;
+        }
+        self.refit();
+        if self.cost() > self.built_cost * self.rebuild_threshold {
+            self.rebuild();
+            TlasUpdate::Rebuilt
+        } else {
+            TlasUpdate::Refit
+        }
+    }
+
+    /// Recomputes every box from its children, bottom up, keeping the
+    /// tree's shape.
+    pub fn refit(&mut self) {
+        for i in (0..self.nodes.len()).rev() {
+            self.nodes[i].bounds = match self.nodes[i].children {
+                Children::Leaf(instance) => self.instances[instance].placed.bound(),
+                Children::Inner(left, right) => {
+                    BoundingBox::from_boxes(self.nodes[left].bounds, self.nodes[right].bounds)
+                }
+            };
+        }
+        self.dirty = false;
+    }
+
+    /// Builds the tree again from where the instances are now.
+    pub fn rebuild(&mut self) {
+        let _stage = Stage::start("build TLAS");
+        self.nodes.clear();
+        let mut order: Vec<usize> = (0..self.instances.len()).collect();
+        if !order.is_empty() {
+            self.build(&mut order);
+        }
+        self.built_cost = self.cost();
+        self.dirty = false;
+    }
+
+    /// Adds the nodes over `instances`, splitting them at the median
+    /// along the axis their centers spread furthest, and returns the
+    /// index of the first.
+    fn build(&mut self, instances: &mut [usize]) -> usize {
+        let index = self.nodes.len();
+        let bounds = instances.iter().fold(BoundingBox::empty(), |bounds, &i| {
+            BoundingBox::from_boxes(bounds, self.instances[i].placed.bound())
+        });
+        self.nodes.push(Node {
+            bounds,
+            children: Children::Leaf(instances[0]),
+        });
+        if instances.len() > 1 {
+            let center = |i: usize| {
+                let bounds = self.instances[i].placed.bound();
+                bounds
+                    .intervals
+                    .map(|interval| 0.5 * (interval.start + interval.end))
+            };
+            let centers = BoundingBox::around(
+                instances
+                    .iter()
+                    .map(|&i| center(i))
+                    .map(|[x, y, z]| Vec3(x, y, z)),
+            );
+            let axis = centers.longest_axis();
+            instances.sort_by(|&a, &b| center(a)[axis].total_cmp(&center(b)[axis]));
+            let (left, right) = instances.split_at_mut(instances.len() / 2);
+            let left = self.build(left);
+            let right = self.build(right);
+            self.nodes[index].children = Children::Inner(left, right);
+        }
+        index
+    }
+
+    /// The summed surface areas of the inner boxes, relative to the
+    /// instances' own: about how many boxes a ray that hits an instance is
+    /// tested against on its way. Moving instances leaves their own boxes
+    /// as they were, so only the inner boxes they stretch raise it.
+    pub fn cost(&self) -> f64 {
+        let (inner, leaves) = self.nodes.iter().fold((0.0, 0.0), |(inner, leaves), node| {
+            let area = node.bounds.surface_area();
+            match node.children {
+                Children::Leaf(_) => (inner, leaves + area),
+                Children::Inner(..) => (inner + area, leaves),
+            }
+        });
+        if leaves > 0.0 {
+            inner / leaves
+        } else {
+            0.0
+        }
+    }
+
+    /// The closest hit along `ray` and the index of the instance hit.
+    pub fn hit_instance(
+        &self,
+        ray: &Ray,
+        t: Interval,
+        rng: &mut dyn Sampler,
+    ) -> Option<(usize, HitRecord<'_>)> {
+        self.hit_node(0, ray, t, rng, &mut TraversalStats::default())
+    }
+
+    fn hit_node(
+        &self,
+        node: usize,
+        ray: &Ray,
+        t: Interval,
+        rng: &mut dyn Sampler,
+        stats: &mut TraversalStats,
+    ) -> Option<(usize, HitRecord<'_>)> {
+        let node = self.nodes.get(node)?;
+        stats.node_visits += 1;
+        if !node.bounds.hit(ray, t) {
+            return None;
+        }
+        match node.children {
+            Children::Leaf(instance) => {
+                let record = self.instances[instance]
+                    .placed
+                    .hit_with_stats(ray, t, rng, stats)?;
+                Some((instance, record))
+            }
+            Children::Inner(left, right) => {
+                let left = self.hit_node(left, ray, t, rng, stats);
+                // Only what's closer than the left's hit can replace it.
+                let closer = match &left {
+                    Some((_, record)) => Interval::new(t.start, record.t),
+                    None => t,
+                };
+                self.hit_node(right, ray, closer, rng, stats).or(left)
+            }
+        }
+    }
+
+    fn hit_any_node(&self, node: usize, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> bool {
+        let Some(node) = self.nodes.get(node) else {
+            return false;
+        };
+        node.bounds.hit(ray, t)
+            && match node.children {
+                Children::Leaf(instance) => self.instances[instance].placed.hit_any(ray, t, rng),
+                Children::Inner(left, right) => {
+                    self.hit_any_node(left, ray, t, rng) || self.hit_any_node(right, ray, t, rng)
+                }
+            }
+    }
+}
+
+impl Hittable for Tlas {
+    fn hit(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> Option<HitRecord<'_>> {
+        let (_, record) = self.hit_instance(ray, t, rng)?;
+        Some(record)
+    }
+
+    fn hit_with_stats(
+        &self,
+        ray: &Ray,
+        t: Interval,
+        rng: &mut dyn Sampler,
+        stats: &mut TraversalStats,
+    ) -> Option<HitRecord<'_>> {
+        let (_, record) = self.hit_node(0, ray, t, rng, stats)?;
+        Some(record)
+    }
+
+    fn hit_any(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> bool {
+        self.hit_any_node(0, ray, t, rng)
+    }
+
+    fn bound(&self) -> BoundingBox {
+        self.nodes
+            .first()
+            .map_or(BoundingBox::empty(), |root| root.bounds)
+    }
+
+    fn collect_lights(&self, lights: &mut Vec<Arc<dyn Hittable>>) {
+        for instance in &self.instances {
+            collect_light(&instance.placed, lights);
+        }
+    }
+
+    /// Instances that have only been moved are described as translations.
+    fn describe(&self, describer: &mut Describer) -> Option<ObjectDesc> {
+        let objects = self
+            .instances
+            .iter()
+            .map(|instance| {
+                let object = describer.object(instance.object.as_ref())?;
+                let m = instance.transform.m;
+                let offset = Vec3(m[0][3], m[1][3], m[2][3]);
+                if instance.transform == Mat4::identity() {
+                    Some(object)
+                } else if instance.transform != Mat4::translation(offset) {
+                    None
+                } else {
+                    Some(ObjectDesc::Translate {
+                        offset,
+                        object: Box::new(object),
+                    })
+                }
+            })
+            .collect::<Option<_>>()?;
+        Some(ObjectDesc::Group { bvh: true, objects })
+    }
+
+    fn validate(&self, path: &str, issues: &mut Vec<ValidationIssue>) {
+        let instances: Vec<(String, Arc<dyn Hittable>)> = self
+            .instances
+            .iter()
+            .enumerate()
+            .map(|(i, instance)| {
+                (
+                    format!("{}.instances[{}]", path, i),
+                    instance.placed.clone(),
+                )
+            })
+            .collect();
+        for (path, object) in &instances {
+            object.validate(path, issues);
+        }
+        validate_duplicates(&instances, issues);
+    }
+
+    fn label(&self) -> String {
+        format!("TLAS over {} instances", self.instances.len())
+    }
+
+    fn children(&self) -> Vec<&dyn Hittable> {
+        self.instances
+            .iter()
+            .map(|instance| instance.placed.as_ref())
+            .collect()
+    }
+}
+
+debug_as_hierarchy!(Tlas);
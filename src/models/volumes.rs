@@ -1,9 +1,12 @@
-use crate::{core::*, models::*, surfaces::*};
+use crate::{core::*, models::*, scenes::*, surfaces::*, Sampler};
 
-use std::sync::Arc;
+use rand::Rng;
+use std::{path::PathBuf, sync::Arc};
 
 pub struct ConstantMedium {
     boundary: Arc<dyn Hittable>,
+    density: f64,
+    texture: Arc<dyn Texture>,
     neg_inv_density: f64,
     phase_function: Arc<dyn Material>,
 }
@@ -12,6 +15,8 @@ impl ConstantMedium {
     pub fn new(boundary: Arc<dyn Hittable>, density: f64, texture: Arc<dyn Texture>) -> Self {
         Self {
             boundary,
+            density,
+            texture: texture.clone(),
             neg_inv_density: -1.0 / density,
             phase_function: Arc::new(Isotropic::new(texture)),
         }
@@ -22,11 +27,12 @@ impl ConstantMedium {
 }
 
 impl Hittable for ConstantMedium {
-    fn hit(&self, ray: &Ray, t: Interval) -> Option<HitRecord> {
-        if let Some(mut rec1) = self.boundary.hit(ray, Interval::universe()) {
+    fn hit(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> Option<HitRecord<'_>> {
+        if let Some(mut rec1) = self.boundary.hit(ray, Interval::universe(), rng) {
             if let Some(mut rec2) = self.boundary.hit(
                 ray,
-                Interval::from_range(rec1.t + 0.0001..std::f64::INFINITY),
+                Interval::from_range(rec1.t + 0.0001..f64::INFINITY),
+                rng,
             ) {
                 rec1.t = rec1.t.max(t.start);
                 rec2.t = rec2.t.min(t.end);
@@ -36,7 +42,7 @@ impl Hittable for ConstantMedium {
                 rec1.t = rec1.t.max(0.0);
                 let ray_length = ray.direction.length();
                 let distance_inside_boundary = (rec2.t - rec1.t) * ray_length;
-                let hit_distance = self.neg_inv_density * rand::random::<f64>().ln();
+                let hit_distance = self.neg_inv_density * rng.gen::<f64>().ln();
                 if hit_distance > distance_inside_boundary {
                     return None;
                 }
@@ -47,7 +53,7 @@ impl Hittable for ConstantMedium {
                     t,
                     point,
                     Vec3(1.0, 0.0, 0.0),
-                    self.phase_function.clone(),
+                    self.phase_function.as_ref(),
                 ))
             } else {
                 None
@@ -60,4 +66,282 @@ impl Hittable for ConstantMedium {
     fn bound(&self) -> BoundingBox {
         self.boundary.bound()
     }
+    fn bound_transformed(&self, transform: &Mat4) -> BoundingBox {
+        self.boundary.bound_transformed(transform)
+    }
+
+    fn describe(&self, describer: &mut Describer) -> Option<ObjectDesc> {
+        let (color, texture) = describer.color_or_texture(&self.texture)?;
+        Some(ObjectDesc::Medium {
+            density: self.density,
+            color,
+            texture,
+            boundary: Box::new(describer.object(self.boundary.as_ref())?),
+        })
+    }
+
+    fn validate(&self, path: &str, issues: &mut Vec<ValidationIssue>) {
+        if !(self.density.is_finite() && self.density > 0.0) {
+            issues.push(ValidationIssue::error(
+                path,
+                format!("density must be positive, got {}", self.density),
+            ));
+        }
+        self.boundary
+            .validate(&format!("{}.boundary", path), issues);
+    }
+    fn label(&self) -> String {
+        format!(
+            "ConstantMedium, density {} ({})",
+            self.density,
+            self.phase_function.kind()
+        )
+    }
+    fn children(&self) -> Vec<&dyn Hittable> {
+        vec![self.boundary.as_ref()]
+    }
+}
+
+debug_as_hierarchy!(ConstantMedium);
+
+/// Densities sampled on a regular 3D grid spanning `bounds`, the first at
+/// its minimum corner and the last at its maximum, with x varying fastest,
+/// then y, then z. Between samples the density is interpolated; outside the
+/// bounds it is zero.
+pub struct VoxelGrid {
+    pub resolution: [usize; 3],
+    pub bounds: BoundingBox,
+    pub densities: Vec<f32>,
+    /// The file the grid was loaded from, to describe it again.
+    pub path: Option<PathBuf>,
+    max_density: f64,
+}
+
+impl VoxelGrid {
+    pub fn new(
+        resolution: [usize; 3],
+        bounds: BoundingBox,
+        densities: Vec<f32>,
+    ) -> Result<Self, String> {
+        // One sample an axis is enough, its density holding throughout.
+        if resolution.contains(&0) {
+            return Err(format!(
+                "a grid needs at least one sample along each axis, got {} by {} by {}",
+                resolution[0], resolution[1], resolution[2]
+            ));
+        }
+        let count = resolution.iter().try_fold(1usize, |n, &r| n.checked_mul(r));
+        if count != Some(densities.len()) {
+            return Err(format!(
+                "{} densities for a {} by {} by {} grid",
+                densities.len(),
+                resolution[0],
+                resolution[1],
+                resolution[2]
+            ));
+        }
+        if let Some(bad) = densities.iter().find(|d| !(d.is_finite() && **d >= 0.0)) {
+            return Err(format!(
+                "densities must be finite and not negative, got {}",
+                bad
+            ));
+        }
+        if bounds
+            .intervals
+            .iter()
+            .any(|axis| axis.size().is_nan() || axis.size() <= 0.0)
+        {
+            return Err("the grid's bounds are empty".to_string());
+        }
+        let max_density = densities.iter().fold(0.0f32, |a, &b| a.max(b)) as f64;
+        Ok(Self {
+            resolution,
+            bounds,
+            densities,
+            path: None,
+            max_density,
+        })
+    }
+
+    /// The densest sample, which no interpolated density exceeds.
+    pub fn max_density(&self) -> f64 {
+        self.max_density
+    }
+
+    /// The sample at grid point (x, y, z).
+    pub fn voxel(&self, x: usize, y: usize, z: usize) -> f64 {
+        let [nx, ny, _] = self.resolution;
+        self.densities[(z * ny + y) * nx + x] as f64
+    }
+
+    /// The density at `point`, in the grid's own space, interpolated
+    /// trilinearly between the eight samples around it.
+    pub fn density_at(&self, point: Point) -> f64 {
+        let mut cell = [0; 3];
+        let mut fraction = [0.0; 3];
+        for axis in 0..3 {
+            let interval = self.bounds.intervals[axis];
+            if !interval.contains(point[axis]) {
+                return 0.0;
+            }
+            let last = self.resolution[axis] - 1;
+            let at = (point[axis] - interval.start) / interval.size() * last as f64;
+            let i = (at.floor() as usize).min(last.saturating_sub(1));
+            cell[axis] = i;
+            fraction[axis] = (at - i as f64).min(1.0);
+        }
+        let [x, y, z] = cell;
+        let step = |axis: usize, i: usize| (i + 1).min(self.resolution[axis] - 1);
+        let (x1, y1, z1) = (step(0, x), step(1, y), step(2, z));
+        let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+        let [fx, fy, fz] = fraction;
+        let near = lerp(
+            lerp(self.voxel(x, y, z), self.voxel(x1, y, z), fx),
+            lerp(self.voxel(x, y1, z), self.voxel(x1, y1, z), fx),
+            fy,
+        );
+        let far = lerp(
+            lerp(self.voxel(x, y, z1), self.voxel(x1, y, z1), fx),
+            lerp(self.voxel(x, y1, z1), self.voxel(x1, y1, z1), fx),
+            fy,
+        );
+        lerp(near, far, fz)
+    }
 }
+
+/// A medium whose density varies through a `VoxelGrid`, such as simulated
+/// smoke, scaled by `density` and placed in the scene by `transform`. Rays
+/// find where they scatter by delta tracking: stepping through the grid's
+/// box as if it were as dense as its densest sample throughout, and
+/// scattering at each step with the odds of the density there to that.
+pub struct VoxelGridMedium {
+    pub grid: Arc<VoxelGrid>,
+    pub density: f64,
+    pub transform: Mat4,
+    inverse: Mat4,
+    texture: Arc<dyn Texture>,
+    phase_function: Arc<dyn Material>,
+    bounds: BoundingBox,
+}
+
+impl VoxelGridMedium {
+    /// `None` if `transform` can't be undone.
+    pub fn new(
+        grid: Arc<VoxelGrid>,
+        density: f64,
+        texture: Arc<dyn Texture>,
+        transform: Mat4,
+    ) -> Option<Self> {
+        let inverse = transform.inverse()?;
+        Some(Self {
+            bounds: grid.bounds.transformed(&transform),
+            grid,
+            density,
+            transform,
+            inverse,
+            texture: texture.clone(),
+            phase_function: Arc::new(Isotropic::new(texture)),
+        })
+    }
+
+    pub fn from_color(
+        grid: Arc<VoxelGrid>,
+        density: f64,
+        color: Color,
+        transform: Mat4,
+    ) -> Option<Self> {
+        Self::new(grid, density, Arc::new(SolidColor::new(color)), transform)
+    }
+}
+
+impl Hittable for VoxelGridMedium {
+    fn hit(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> Option<HitRecord<'_>> {
+        let majorant = self.grid.max_density * self.density;
+        if majorant.is_nan() || majorant <= 0.0 {
+            return None;
+        }
+        // An affine map keeps the ray's parameter, so the grid's box can be
+        // crossed in the grid's space and the hit placed in the world's.
+        let local = Ray {
+            origin: self.inverse.transform_point(ray.origin),
+            direction: self.inverse.transform_vector(ray.direction),
+            time: ray.time,
+        };
+        let mut span = t;
+        for axis in 0..3 {
+            let interval = self.grid.bounds.intervals[axis];
+            let inverse = 1.0 / local.direction[axis];
+            let t0 = (interval.start - local.origin[axis]) * inverse;
+            let t1 = (interval.end - local.origin[axis]) * inverse;
+            span = Interval::new(span.start.max(t0.min(t1)), span.end.min(t0.max(t1)));
+        }
+        if span.size().is_nan() || span.size() <= 0.0 {
+            return None;
+        }
+
+        let rate = majorant * ray.direction.length();
+        let mut t = span.start;
+        loop {
+            t -= (1.0 - rng.gen::<f64>()).ln() / rate;
+            if t >= span.end {
+                return None;
+            }
+            let density = self.density * self.grid.density_at(local.at(t));
+            if rng.gen::<f64>() * majorant < density {
+                return Some(HitRecord::new(
+                    ray,
+                    t,
+                    ray.at(t),
+                    Vec3(1.0, 0.0, 0.0),
+                    self.phase_function.as_ref(),
+                ));
+            }
+        }
+    }
+
+    fn bound(&self) -> BoundingBox {
+        self.bounds
+    }
+    fn bound_transformed(&self, transform: &Mat4) -> BoundingBox {
+        self.grid.bounds.transformed(&(*transform * self.transform))
+    }
+
+    fn describe(&self, describer: &mut Describer) -> Option<ObjectDesc> {
+        let (translation, rotation, scale) = self.transform.decompose()?;
+        let transform = Transform {
+            translation,
+            scale,
+            ..Transform::from_rotation(rotation)?
+        };
+        let (color, texture) = describer.color_or_texture(&self.texture)?;
+        Some(ObjectDesc::VoxelMedium {
+            path: self.grid.path.clone()?,
+            density: self.density,
+            color,
+            texture,
+            transform: Some(transform),
+        })
+    }
+
+    fn validate(&self, path: &str, issues: &mut Vec<ValidationIssue>) {
+        if !(self.density.is_finite() && self.density > 0.0) {
+            issues.push(ValidationIssue::error(
+                path,
+                format!("density must be positive, got {}", self.density),
+            ));
+        }
+    }
+    fn label(&self) -> String {
+        let [x, y, z] = self.grid.resolution;
+        format!(
+            "VoxelGridMedium, {} by {} by {}, density {} ({})",
+            x,
+            y,
+            z,
+            self.density,
+            self.phase_function.kind()
+        )
+    }
+}
+
+debug_as_hierarchy!(VoxelGridMedium);
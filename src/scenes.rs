@@ -1,38 +1,850 @@
-use std::{path::Path, sync::Arc};
+pub mod animation;
+pub mod builder;
+pub mod cornell;
+pub mod editing;
+pub mod export;
+pub mod file;
+pub mod manifest;
+pub mod metadata;
+pub mod placement;
+pub mod validate;
 
-use crate::{camera::*, core::*, models::*, surfaces::*};
+pub use animation::*;
+pub use builder::*;
+pub use editing::*;
+pub use export::*;
+pub use file::*;
+pub use manifest::*;
+pub use metadata::*;
+pub use placement::*;
+pub use validate::*;
 
-use macroquad::prelude::ImageFormat;
-use serde::Deserialize;
-use three_d_asset::Geometry;
+use std::{
+    io,
+    ops::{ControlFlow, Range},
+    path::Path,
+    sync::Arc,
+};
 
-#[derive(Deserialize)]
+use crate::{camera::*, core::*, models::*, surfaces::*, Error};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+/// A camera as scene files describe it. Fields left out take the values of
+/// `Camera::default()`.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
 pub struct CameraBuilder {
-    pub aspect_ratio: f64,
+    /// The image's width over its height, or else its `image_height` in
+    /// pixels: exactly one of the two. A scene file that leaves out the
+    /// camera altogether gets `Camera::default()`'s 16:9.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aspect_ratio: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_height: Option<i32>,
     pub image_width: i32,
     pub vfov: f64,
     pub look_from: Point,
     pub look_at: Point,
     pub up: Vec3,
+    /// `"perspective"`, `{ "orthographic": { "width": 10 } }`,
+    /// `"equirectangular"`, or `{ "fisheye": { "fov": 180 } }`.
+    #[serde(default, skip_serializing_if = "Projection::is_default")]
+    pub projection: Projection,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub defocus_angle: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub focus_dist: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aperture_blades: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aperture_rotation: Option<f64>,
+    /// Open and close times of the shutter, for motion blur.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shutter: Option<(f64, f64)>,
     pub aa_samples: i32,
+    /// Renders this many times wider and taller and shrinks the image back
+    /// down; see `Camera::supersample`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supersample: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub downscale_filter: Option<DownscaleFilter>,
     pub max_depth: i32,
+    #[serde(default, skip_serializing_if = "BounceLimits::is_default")]
+    pub bounces: BounceLimits,
+    /// The bounce from which Russian roulette ends paths, if it does; see
+    /// `Camera::russian_roulette`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub russian_roulette: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ray_epsilon: Option<f64>,
+    /// Distances along the view direction nearer than which surfaces are
+    /// cut away; see `Camera::near_clip`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub near_clip: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub far_clip: Option<f64>,
+    #[serde(default, skip_serializing_if = "SamplerKind::is_default")]
+    pub sampler: SamplerKind,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub blue_noise: bool,
+    /// Samples each pixel until it converges; see `AdaptiveSampling`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub adaptive: Option<AdaptiveSampling>,
+    /// Renders with an alpha channel; see `Camera::transparent`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub transparent: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fog: Option<FogSettings>,
+    #[serde(default, skip_serializing_if = "Exposure::is_identity")]
+    pub exposure: Exposure,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stereo: Option<StereoSettings>,
+}
+impl Default for CameraBuilder {
+    fn default() -> Self {
+        Self::from(&Camera::default())
+    }
+}
+
+impl From<&Camera> for CameraBuilder {
+    fn from(camera: &Camera) -> Self {
+        Self {
+            aspect_ratio: Some(camera.aspect_ratio),
+            image_height: None,
+            image_width: camera.image_width,
+            vfov: camera.vfov,
+            look_from: camera.look_from,
+            look_at: camera.look_at,
+            up: camera.up,
+            projection: camera.projection,
+            defocus_angle: (camera.defocus_angle != 0.0).then_some(camera.defocus_angle),
+            focus_dist: camera.focus_dist,
+            aperture_blades: match camera.aperture {
+                ApertureShape::Polygon { blades, .. } => Some(blades),
+                _ => None,
+            },
+            aperture_rotation: match camera.aperture {
+                ApertureShape::Polygon { rotation, .. } => (rotation != 0.0).then_some(rotation),
+                _ => None,
+            },
+            shutter: ((camera.shutter_open, camera.shutter_close) != (0.0, 0.0))
+                .then_some((camera.shutter_open, camera.shutter_close)),
+            aa_samples: camera.aa_samples,
+            supersample: (camera.supersample != 1).then_some(camera.supersample),
+            downscale_filter: (camera.downscale_filter != DownscaleFilter::default())
+                .then_some(camera.downscale_filter),
+            max_depth: camera.max_depth,
+            bounces: camera.bounce_limits,
+            russian_roulette: camera.russian_roulette.then_some(camera.roulette_depth),
+            ray_epsilon: (camera.ray_epsilon != DEFAULT_RAY_EPSILON).then_some(camera.ray_epsilon),
+            near_clip: (camera.near_clip != 0.0).then_some(camera.near_clip),
+            far_clip: (camera.far_clip != f64::INFINITY).then_some(camera.far_clip),
+            sampler: camera.sampler,
+            blue_noise: camera.blue_noise,
+            adaptive: camera.adaptive,
+            transparent: camera.transparent,
+            fog: camera.fog,
+            exposure: camera.exposure,
+            stereo: camera.stereo,
+        }
+    }
 }
+
+/// Chainable setters, so cameras can be built in code by name rather than
+/// through `Camera::new`'s eight positional arguments, starting from
+/// `CameraBuilder::default()` and ending with `build`. Settings left unset
+/// keep `Camera::default()`'s: 16:9 and 400 pixels wide, looking down -Z
+/// from the origin with a 90° field of view, up +Y, at 100 samples and 50
+/// bounces.
 impl CameraBuilder {
-    pub fn build(&self) -> Camera {
-        Camera::new(
-            self.aspect_ratio,
-            self.image_width,
-            self.vfov,
-            self.look_from,
-            self.look_at,
-            self.up,
-            self.aa_samples,
-            self.max_depth,
+    /// The image's width over its height, in place of any `image_height`.
+    pub fn aspect_ratio(mut self, aspect_ratio: f64) -> Self {
+        self.aspect_ratio = Some(aspect_ratio);
+        self.image_height = None;
+        self
+    }
+
+    /// The image's height in pixels, in place of any `aspect_ratio`, which
+    /// becomes the width's over it.
+    pub fn image_height(mut self, image_height: i32) -> Self {
+        self.image_height = Some(image_height);
+        self.aspect_ratio = None;
+        self
+    }
+
+    pub fn image_width(mut self, image_width: i32) -> Self {
+        self.image_width = image_width;
+        self
+    }
+
+    /// The vertical field of view, in degrees.
+    pub fn vfov(mut self, vfov: f64) -> Self {
+        self.vfov = vfov;
+        self
+    }
+
+    pub fn look_from(mut self, look_from: Point) -> Self {
+        self.look_from = look_from;
+        self
+    }
+
+    pub fn look_at(mut self, look_at: Point) -> Self {
+        self.look_at = look_at;
+        self
+    }
+
+    pub fn up(mut self, up: Vec3) -> Self {
+        self.up = up;
+        self
+    }
+
+    pub fn projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    /// Samples per pixel.
+    pub fn samples(mut self, samples: i32) -> Self {
+        self.aa_samples = samples;
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: i32) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// See `Camera::set_defocus`.
+    pub fn defocus(mut self, angle: f64, focus_dist: Option<f64>) -> Self {
+        self.defocus_angle = Some(angle);
+        self.focus_dist = focus_dist;
+        self
+    }
+
+    /// See `Camera::set_aperture`.
+    pub fn aperture(mut self, blades: Option<u32>, rotation: f64) -> Self {
+        self.aperture_blades = blades;
+        self.aperture_rotation = Some(rotation);
+        self
+    }
+
+    pub fn shutter(mut self, open: f64, close: f64) -> Self {
+        self.shutter = Some((open, close));
+        self
+    }
+
+    pub fn supersample(mut self, factor: u32, filter: DownscaleFilter) -> Self {
+        self.supersample = Some(factor);
+        self.downscale_filter = Some(filter);
+        self
+    }
+
+    pub fn bounces(mut self, bounces: BounceLimits) -> Self {
+        self.bounces = bounces;
+        self
+    }
+
+    pub fn ray_epsilon(mut self, ray_epsilon: f64) -> Self {
+        self.ray_epsilon = Some(ray_epsilon);
+        self
+    }
+
+    /// See `Camera::set_clip`.
+    pub fn clip(mut self, near: f64, far: f64) -> Self {
+        self.near_clip = Some(near);
+        self.far_clip = Some(far);
+        self
+    }
+
+    pub fn sampler(mut self, sampler: SamplerKind) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    pub fn blue_noise(mut self, blue_noise: bool) -> Self {
+        self.blue_noise = blue_noise;
+        self
+    }
+
+    pub fn russian_roulette(mut self, depth: Option<i32>) -> Self {
+        self.russian_roulette = depth;
+        self
+    }
+
+    pub fn adaptive(mut self, adaptive: Option<AdaptiveSampling>) -> Self {
+        self.adaptive = adaptive;
+        self
+    }
+
+    pub fn transparent(mut self, transparent: bool) -> Self {
+        self.transparent = transparent;
+        self
+    }
+
+    pub fn fog(mut self, fog: Option<FogSettings>) -> Self {
+        self.fog = fog;
+        self
+    }
+
+    pub fn exposure(mut self, exposure: Exposure) -> Self {
+        self.exposure = exposure;
+        self
+    }
+
+    pub fn stereo(mut self, stereo: Option<StereoSettings>) -> Self {
+        self.stereo = stereo;
+        self
+    }
+
+    /// The camera, or the first setting that can't produce an image, such
+    /// as a width of 0, an up vector along the view direction, or both an
+    /// aspect ratio and an image height, or neither.
+    pub fn build(&self) -> Result<Camera, CameraError> {
+        let mut camera = match (self.aspect_ratio, self.image_height) {
+            (Some(aspect_ratio), Some(image_height)) => {
+                return Err(CameraError::AspectRatioAndHeight {
+                    aspect_ratio,
+                    image_height,
+                })
+            }
+            (None, Some(image_height)) => Camera::try_with_dimensions(
+                self.image_width,
+                image_height,
+                self.vfov,
+                self.look_from,
+                self.look_at,
+                self.up,
+                self.aa_samples,
+                self.max_depth,
+            ),
+            (None, None) => return Err(CameraError::MissingAspectRatioOrHeight),
+            (Some(aspect_ratio), None) => Camera::try_new(
+                aspect_ratio,
+                self.image_width,
+                self.vfov,
+                self.look_from,
+                self.look_at,
+                self.up,
+                self.aa_samples,
+                self.max_depth,
+            ),
+        }?;
+        if let Some(ray_epsilon) = self.ray_epsilon {
+            camera.set_ray_epsilon(ray_epsilon);
+        }
+        camera.set_clip(
+            self.near_clip.unwrap_or(0.0),
+            self.far_clip.unwrap_or(f64::INFINITY),
+        );
+        if let Some((open, close)) = self.shutter {
+            camera.set_shutter(open, close);
+        }
+        camera
+            .set_projection(self.projection)
+            .set_defocus(self.defocus_angle.unwrap_or(0.0), self.focus_dist)
+            .set_aperture(self.aperture_blades, self.aperture_rotation.unwrap_or(0.0))
+            .set_supersample(
+                self.supersample.unwrap_or(1),
+                self.downscale_filter.unwrap_or_default(),
+            )
+            .set_bounce_limits(self.bounces)
+            .set_russian_roulette(
+                self.russian_roulette.is_some(),
+                self.russian_roulette.unwrap_or(DEFAULT_ROULETTE_DEPTH),
+            )
+            .set_sampler(self.sampler)
+            .set_blue_noise(self.blue_noise)
+            .set_adaptive(self.adaptive)
+            .set_transparent(self.transparent)
+            .set_fog(self.fog)
+            .set_exposure(self.exposure)
+            .set_stereo(self.stereo);
+        camera.validate()?;
+        Ok(camera)
+    }
+}
+
+pub struct Scene {
+    pub camera: Camera,
+    pub world: HittableList,
+    /// The emissive objects in the world, for sampling lights directly, and
+    /// the delta lights that can only be sampled.
+    pub lights: LightList,
+    /// The sun `set_sky` added, which another sky replaces.
+    sky_sun: Option<Arc<dyn DeltaLight>>,
+    /// The lights `add_light` registered, which aren't in the world.
+    added_lights: Vec<Arc<dyn Hittable>>,
+    /// The world's instances, once `instance_world` has made it editable.
+    instances: Option<Arc<Tlas>>,
+    /// The photograph a transparent render of the scene is meant to be
+    /// composited over, see `ImageBuffer::over`.
+    pub backplate: Option<ImageBuffer>,
+}
+
+impl Scene {
+    /// Creates a scene, registering every emissive object in `world` as a light.
+    pub fn new(camera: Camera, world: HittableList) -> Self {
+        let mut found = Vec::new();
+        world.collect_lights(&mut found);
+        let mut lights = LightList::new();
+        for light in found {
+            lights.area.add_arc(light);
+        }
+        Self {
+            camera,
+            world,
+            lights,
+            sky_sun: None,
+            added_lights: Vec::new(),
+            instances: None,
+            backplate: None,
+        }
+    }
+
+    /// Adds an object to the world, registering it or the lights inside it.
+    pub fn add(&mut self, object: Arc<dyn Hittable>) {
+        let mut found = Vec::new();
+        collect_light(&object, &mut found);
+        for light in found {
+            self.lights.area.add_arc(light);
+        }
+        self.world.add_arc(object);
+    }
+
+    /// Registers `light` without adding it to the world, e.g. a shape that
+    /// stands in for a light the world draws some other way.
+    pub fn add_light(&mut self, light: Arc<dyn Hittable>) {
+        self.added_lights.push(light.clone());
+        self.lights.area.add_arc(light);
+    }
+
+    /// Adds a point, directional, or other light that has no surface.
+    pub fn add_delta_light(&mut self, light: Arc<dyn DeltaLight>) {
+        self.lights.delta.push(light);
+        self.lights.delta_changed();
+    }
+
+    /// Lights the scene with `environment`, which rays leaving it see instead
+    /// of the camera's background.
+    pub fn set_environment(&mut self, environment: Environment) {
+        self.lights.environment = Some(Arc::new(environment));
+    }
+
+    /// Lights the scene with `sky`: its sun as a directional light, and the
+    /// rest of it as the environment. Replaces any sky set before.
+    pub fn set_sky(&mut self, sky: &Sky) {
+        if let Some(sun) = self.sky_sun.take() {
+            self.lights.delta.retain(|light| !Arc::ptr_eq(light, &sun));
+            self.lights.delta_changed();
+        }
+        self.set_environment(sky.environment(512));
+        if let Some(sun) = sky.sun() {
+            let sun: Arc<dyn DeltaLight> = Arc::new(sun);
+            self.add_delta_light(sun.clone());
+            self.sky_sun = Some(sun);
+        }
+    }
+
+    /// Declares an opening the environment lights the scene through, see
+    /// `Portal`.
+    pub fn add_portal(&mut self, portal: Portal) {
+        self.lights.portals.push(portal);
+    }
+
+    /// Renders the world to stdout as a PPM image.
+    pub fn render(&self) -> RenderStats {
+        self.camera.render(&self.world, &self.lights)
+    }
+
+    /// Renders the world as a PPM image to `out`, e.g. a file or socket.
+    pub fn render_to(&self, out: impl io::Write) -> io::Result<RenderStats> {
+        self.camera.render_to(&self.world, &self.lights, out)
+    }
+
+    /// Renders the world to an image file as the command line writes it,
+    /// finished by `finish_image`. See `Camera::render_to_file`.
+    pub fn render_to_file(&self, path: &Path) -> Result<RenderStats, Error> {
+        let (image, stats) = self.render_timed();
+        let (image, exposure) = self.finish_image(image, None);
+        let mut metadata = RenderMetadata::new(String::new(), &self.camera, &stats);
+        metadata.exposure = exposure;
+        metadata.save_image(&image, path)?;
+        Ok(stats)
+    }
+
+    /// `image`, a render of the scene, finished as the camera says (see
+    /// `Camera::finish_image`) and composited over the backplate, with the
+    /// exposure multiplier if one was chosen.
+    pub fn finish_image(
+        &self,
+        image: ImageBuffer,
+        aovs: Option<&Aovs>,
+    ) -> (ImageBuffer, Option<f64>) {
+        let (image, exposure) = self.camera.finish_image(image, aovs);
+        let image = match &self.backplate {
+            Some(plate) => image.over(plate),
+            None => image,
+        };
+        (image, exposure)
+    }
+
+    /// Renders the world into a buffer, also reporting how long it and the
+    /// scene's construction took.
+    pub fn render_timed(&self) -> (ImageBuffer, RenderStats) {
+        self.camera.render_timed(&self.world, &self.lights)
+    }
+
+    /// `render_timed`, also counting what the paths did. See
+    /// `Camera::render_with_stats`.
+    pub fn render_with_stats(&self) -> (ImageBuffer, RenderStats) {
+        self.camera.render_with_stats(&self.world, &self.lights)
+    }
+
+    /// Renders the world into a buffer instead of stdout.
+    pub fn render_image(&self) -> ImageBuffer {
+        self.camera.render_image(&self.world, &self.lights)
+    }
+
+    /// Renders the world into a buffer, calling `progress` with the rows
+    /// finished and the rows in all after each row.
+    pub fn render_image_with_progress(
+        &self,
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> ImageBuffer {
+        self.camera
+            .render_image_with_progress(&self.world, &self.lights, progress)
+    }
+
+    /// Renders only the pixels in columns `x` and rows `y`. See
+    /// `Camera::render_region`.
+    pub fn render_region(
+        &self,
+        x: Range<usize>,
+        y: Range<usize>,
+        output: RegionOutput,
+    ) -> ImageBuffer {
+        self.camera
+            .render_region(&self.world, &self.lights, x, y, output)
+    }
+
+    /// Renders the world coarse to fine, calling `on_pass` with each
+    /// preview. See `Camera::render_progressive`.
+    pub fn render_progressive(&self, on_pass: &mut dyn FnMut(&Preview)) -> ImageBuffer {
+        self.camera
+            .render_progressive(&self.world, &self.lights, on_pass)
+    }
+
+    /// Renders the world tile by tile, reporting to `events` as it goes
+    /// until it finishes or is cancelled. See `Camera::render_with`.
+    pub fn render_with(
+        &self,
+        settings: &RenderSettings,
+        mut events: impl FnMut(RenderEvent) -> ControlFlow<()>,
+    ) -> RenderOutcome {
+        self.camera
+            .render_with(&self.world, &self.lights, settings, &mut events)
+    }
+
+    /// Renders the world a sample of every pixel at a time, calling
+    /// `on_snapshot` with the progress as often as `every` says. See
+    /// `Camera::render_with_snapshots`.
+    pub fn render_with_snapshots(
+        &self,
+        every: SnapshotEvery,
+        on_snapshot: &mut dyn FnMut(&Snapshot),
+    ) -> ImageBuffer {
+        self.camera
+            .render_with_snapshots(&self.world, &self.lights, every, on_snapshot)
+    }
+
+    /// Adds the camera's `aa_samples` more samples of every pixel to `into`.
+    /// See `Camera::accumulate`.
+    pub fn accumulate(&self, into: &mut Accumulation) -> Result<(), AccumulationError> {
+        self.camera.accumulate(&self.world, &self.lights, into)
+    }
+
+    /// All of the camera's samples of every pixel, for their variance as
+    /// well as the image. See `Camera::render_accumulation`.
+    pub fn render_accumulation(&self) -> Result<Accumulation, AccumulationError> {
+        let mut accumulation = self.new_accumulation();
+        self.accumulate(&mut accumulation)?;
+        Ok(accumulation)
+    }
+
+    /// An empty accumulation for this scene, to `accumulate` into.
+    pub fn new_accumulation(&self) -> Accumulation {
+        Accumulation::new(
+            self.camera.image_width as usize,
+            self.camera.image_height() as usize,
+            self.fingerprint(),
+        )
+    }
+
+    /// A hash of everything that decides what a render of the scene converges
+    /// to: the camera but for its samples and seed, the background, the
+    /// lights, the environment, and the world. Renders can only be merged if theirs match.
+    /// Scenes that can't be described are hashed by their `hierarchy`, which
+    /// misses changes of material.
+    pub fn fingerprint(&self) -> u64 {
+        let mut camera = CameraBuilder::from(&self.camera);
+        camera.aa_samples = 0;
+        let mut text = match self.describe() {
+            Ok(mut desc) => {
+                desc.camera = camera;
+                serde_json::to_string(&desc).expect("scene descriptions are always serializable")
+            }
+            Err(_) => format!(
+                "{}\n{:?}\n{}\n{}",
+                serde_json::to_string(&camera).expect("cameras are always serializable"),
+                self.camera.background.describe(),
+                serde_json::to_string(
+                    &self
+                        .lights
+                        .delta
+                        .iter()
+                        .map(|light| light.describe())
+                        .collect::<Vec<_>>()
+                )
+                .expect("lights are always serializable"),
+                Hierarchy(&self.world)
+            ),
+        };
+        if let Some(environment) = &self.lights.environment {
+            text += &format!("\nenvironment {:016x}", environment.fingerprint());
+        }
+        stable_hash(text.as_bytes())
+    }
+
+    /// Renders the BVH heat map of the world instead of an image of it.
+    pub fn render_heat_map(&self, max: Option<u32>) -> HeatMap {
+        self.camera.render_heat_map(&self.world, max)
+    }
+
+    /// Applies the camera's exposure to `image`, a render of the scene,
+    /// returning the multiplier it chose unless the exposure leaves images
+    /// as they are.
+    pub fn expose(&self, image: &mut ImageBuffer) -> Option<f64> {
+        let exposure = self.camera.exposure;
+        (!exposure.is_identity()).then(|| image.expose(exposure))
+    }
+
+    /// Renders the coverage of the objects tagged with each of `names`. See
+    /// `Camera::render_mattes`.
+    pub fn render_mattes(&self, names: &[&str]) -> Mattes {
+        self.camera.render_mattes(&self.world, names)
+    }
+
+    pub fn render_with_aovs(&self) -> (ImageBuffer, Aovs) {
+        self.camera.render_with_aovs(&self.world, &self.lights)
+    }
+
+    /// The bounds of everything in the world.
+    pub fn bounds(&self) -> BoundingBox {
+        self.world.bound()
+    }
+
+    /// The camera, the lights, and the world as an indented tree of objects,
+    /// one per line with its bounds. See `Hierarchy`.
+    pub fn hierarchy(&self) -> String {
+        let camera = &self.camera;
+        format!(
+            "camera: {} by {}, {} samples, from {} toward {}\nlights: {} area, {} delta\n{}",
+            camera.image_width,
+            camera.image_height(),
+            camera.aa_samples,
+            camera.look_from,
+            camera.look_at,
+            self.lights.area.objects.len(),
+            self.lights.delta.len(),
+            Hierarchy(&self.world)
         )
     }
+    pub fn print_hierarchy(&self) {
+        print!("{}", self.hierarchy());
+    }
+}
+
+/// An empty world seen through `Camera::default()`.
+impl Default for Scene {
+    fn default() -> Self {
+        Self::new(Camera::default(), HittableList::new())
+    }
+}
+
+pub struct SceneEntry {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub build: fn() -> Result<Scene, Error>,
+}
+
+impl SceneEntry {
+    pub fn build(&self) -> Result<Scene, Error> {
+        (self.build)()
+    }
 }
 
-pub fn material_spheres() {
+static SCENES: &[SceneEntry] = &[
+    SceneEntry {
+        name: "material_spheres",
+        description: "Diffuse, glass, hollow glass, and fuzzy metal spheres on a ground sphere",
+        build: || Ok(material_spheres()),
+    },
+    SceneEntry {
+        name: "book2_final",
+        description: "The book-2 final scene combining every feature in one image",
+        build: || Ok(book2_final()),
+    },
+    SceneEntry {
+        name: "random_spheres",
+        description: "The book-1 cover: hundreds of random small spheres around three large ones",
+        build: || Ok(random_spheres(0)),
+    },
+    SceneEntry {
+        name: "asteroid_field",
+        description: "A ringed planet beside a seeded field of scattered asteroids",
+        build: || Ok(asteroid_field()),
+    },
+    SceneEntry {
+        name: "checkered_spheres",
+        description: "Two large spheres sharing a spatial checker texture",
+        build: || Ok(checkered_spheres()),
+    },
+    SceneEntry {
+        name: "earthmap",
+        description: "A globe textured with the bundled earth map image",
+        build: || Ok(earthmap()),
+    },
+    SceneEntry {
+        name: "earth_environment",
+        description: "Mirror, glass, and matte spheres lit by the earth map wrapped around them",
+        build: || Ok(earth_environment()),
+    },
+    SceneEntry {
+        name: "backplate",
+        description: "A sphere and its soft contact shadow composited over the bundled earth map",
+        build: || Ok(backplate()),
+    },
+    SceneEntry {
+        name: "quads",
+        description: "Five colored parallelograms forming an open box",
+        build: || Ok(quads()),
+    },
+    SceneEntry {
+        name: "quads_orthographic",
+        description: "The open box of quads drawn from above and aside in parallel projection",
+        build: || Ok(quads_orthographic()),
+    },
+    SceneEntry {
+        name: "planars",
+        description: "The quads scene rebuilt from a parallelogram and triangles",
+        build: || Ok(planars()),
+    },
+    SceneEntry {
+        name: "obj_mesh",
+        description: "The SpaceShip-Fighter model loaded from an OBJ file",
+        build: obj_mesh,
+    },
+    SceneEntry {
+        name: "simple_light",
+        description: "A sphere lit by a single rectangular area light",
+        build: || Ok(simple_light()),
+    },
+    SceneEntry {
+        name: "sunlit_spheres",
+        description: "Three spheres casting crisp parallel shadows under a warm low sun",
+        build: || Ok(sunlit_spheres()),
+    },
+    SceneEntry {
+        name: "terrain",
+        description: "Rolling hills built from a heightfield under a low sun",
+        build: || Ok(terrain()),
+    },
+    SceneEntry {
+        name: "sky_boxes",
+        description: "Boxes on a ground plane under a procedural sun and sky at 8 am (see --sky)",
+        build: || Ok(sky_boxes(8.0)),
+    },
+    SceneEntry {
+        name: "window_room",
+        description: "A room lit only through its window by the afternoon sun and sky, sampled through a light portal",
+        build: || Ok(window_room()),
+    },
+    SceneEntry {
+        name: "spotlight_fog",
+        description: "A spotlight in a dark, foggy room, its cone aimed at a sphere",
+        build: || Ok(spotlight_fog()),
+    },
+    SceneEntry {
+        name: "ceiling_lights",
+        description: "Spheres under a ceiling of 200 small lights of wildly varying brightness, picked by a light tree",
+        build: || Ok(ceiling_lights()),
+    },
+    SceneEntry {
+        name: "distance_fog",
+        description: "Two rows of spheres receding into a hazy distance, faded by distance fog",
+        build: || Ok(distance_fog()),
+    },
+    SceneEntry {
+        name: "bokeh",
+        description: "A sphere in focus before far-off lights blurred into hexagons",
+        build: || Ok(bokeh()),
+    },
+    SceneEntry {
+        name: "motion_blur",
+        description: "The Cornell box with its tall block sliding and a textured cube spinning",
+        build: || Ok(motion_blur()),
+    },
+    SceneEntry {
+        name: "cornell_box",
+        description: "The classic Cornell box with two rotated blocks",
+        build: || Ok(cornell_box()),
+    },
+    SceneEntry {
+        name: "cornell_cutaway",
+        description: "The Cornell box seen from inside its tall block, the near faces clipped away",
+        build: || Ok(cornell_cutaway()),
+    },
+    SceneEntry {
+        name: "cornell_panorama",
+        description: "The Cornell box from between its blocks as a 360-degree panorama",
+        build: || Ok(cornell_panorama()),
+    },
+    SceneEntry {
+        name: "cornell_glass",
+        description: "The Cornell box with two glass spheres, capped at four diffuse bounces",
+        build: || Ok(cornell_glass()),
+    },
+    SceneEntry {
+        name: "cornell_plume",
+        description: "A plume of smoke from a voxel grid file rising in the empty Cornell box",
+        build: || cornell_plume(),
+    },
+    SceneEntry {
+        name: "cornell_smoke",
+        description: "The Cornell box with its blocks replaced by smoke and fog volumes",
+        build: || Ok(cornell_smoke()),
+    },
+];
+
+pub fn list() -> &'static [SceneEntry] {
+    SCENES
+}
+
+pub fn by_name(name: &str) -> Option<&'static SceneEntry> {
+    SCENES.iter().find(|entry| entry.name == name)
+}
+
+/// Builds the built-in scene called `name`.
+pub fn build(name: &str) -> Result<Scene, Error> {
+    let _stage = Stage::start("build scene");
+    by_name(name)
+        .ok_or_else(|| Error::UnknownScene(name.to_string()))?
+        .build()
+}
+
+pub fn material_spheres() -> Scene {
     /* === World === */
     let mut world = HittableList::new();
 
@@ -73,20 +885,93 @@ pub fn material_spheres() {
 
     let world = HittableList::from(Arc::new(BoundNode::from_list(world)));
 
-    Camera::new(
-        16.0 / 9.0,
-        400,
-        90.0,
-        point(0.0, 0.0, 0.0),
-        point(0.0, 0.0, -1.0),
-        Vec3(0.0, 1.0, 0.0),
-        20,
-        20,
-    )
-    .render(&world);
+    let mut scene = Scene::new(
+        CameraBuilder::default()
+            .samples(20)
+            .max_depth(20)
+            .build()
+            .expect("a valid camera"),
+        world,
+    );
+    scene.camera.set_background_arc(Arc::new(GradientSky::new(
+        color(1.0, 1.0, 1.0),
+        color(0.5, 0.7, 1.0),
+    )));
+    scene
 }
 
-pub fn checkered_spheres() {
+pub fn random_spheres(seed: u64) -> Scene {
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    /* === World === */
+    let mut world = HittableList::new();
+
+    /* === Materials === */
+    let material_ground = Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)));
+    let material_glass = Arc::new(Dielectric::new(1.5));
+    let material_diffuse = Arc::new(Lambertian::from(color(0.4, 0.2, 0.1)));
+    let material_metal = Arc::new(Metal::new(color(0.7, 0.6, 0.5), 0.0));
+
+    /* === Objects === */
+    world.add(Sphere::new(point(0., -1000., 0.), 1000., material_ground));
+
+    let heroes = [point(0., 1., 0.), point(-4., 1., 0.), point(4., 1., 0.)];
+    for a in -11..11 {
+        for b in -11..11 {
+            let center = point(
+                a as f64 + 0.9 * rng.gen::<f64>(),
+                0.2,
+                b as f64 + 0.9 * rng.gen::<f64>(),
+            );
+            // Reject small spheres that would intersect one of the large ones.
+            if heroes
+                .iter()
+                .any(|&hero| (center - hero).length() < 1.0 + 0.2)
+            {
+                continue;
+            }
+
+            let choose_material = rng.gen::<f64>();
+            let material: Arc<dyn Material> = if choose_material < 0.8 {
+                let a = Vec3(rng.gen(), rng.gen(), rng.gen());
+                let b = Vec3(rng.gen(), rng.gen(), rng.gen());
+                Arc::new(Lambertian::from(a * b))
+            } else if choose_material < 0.95 {
+                let albedo = Vec3(
+                    rng.gen_range(0.5..1.0),
+                    rng.gen_range(0.5..1.0),
+                    rng.gen_range(0.5..1.0),
+                );
+                Arc::new(Metal::new(albedo, rng.gen_range(0.0..0.5)))
+            } else {
+                material_glass.clone()
+            };
+            world.add(Sphere::new(center, 0.2, material));
+        }
+    }
+
+    world.add(Sphere::new(heroes[0], 1.0, material_glass));
+    world.add(Sphere::new(heroes[1], 1.0, material_diffuse));
+    world.add(Sphere::new(heroes[2], 1.0, material_metal));
+
+    let world = HittableList::from(Arc::new(BoundNode::from_list(world)));
+
+    let mut camera = CameraBuilder::default()
+        .aspect_ratio(3.0 / 2.0)
+        .image_width(600)
+        .vfov(20.0)
+        .look_from(point(13., 2., 3.))
+        .look_at(point(0., 0., 0.))
+        .defocus(0.6, Some(10.0))
+        .samples(50)
+        .build()
+        .expect("a valid camera");
+    camera.set_background(color(0.70, 0.80, 1.00));
+
+    Scene::new(camera, world)
+}
+
+pub fn checkered_spheres() -> Scene {
     /* === World === */
     let mut world = HittableList::new();
 
@@ -109,28 +994,45 @@ pub fn checkered_spheres() {
         Arc::new(Lambertian::new(checker.clone())),
     )));
 
-    Camera::new(
-        16.0 / 9.0,
-        400,
-        20.0,
-        point(13.0, 2.0, 3.0),
-        point(0.0, 0.0, 0.0),
-        Vec3(0.0, 1.0, 0.0),
-        20,
-        20,
+    Scene::new(
+        CameraBuilder::default()
+            .vfov(20.0)
+            .look_from(point(13.0, 2.0, 3.0))
+            .look_at(point(0.0, 0.0, 0.0))
+            .samples(20)
+            .max_depth(20)
+            .build()
+            .expect("a valid camera"),
+        world,
     )
-    .render(&world);
 }
 
-pub fn earthmap() {
+const EARTHMAP_PNG: &[u8] = include_bytes!("../resources/earthmap.png");
+
+/// The bundled earth map, registered under its resource path so exported
+/// scenes can refer to it. Builds that can't decode images draw the earth
+/// in a flat ocean blue instead.
+fn earthmap_texture() -> Arc<dyn Texture> {
+    TextureRegistry::global().get_or_insert_with(Path::new("resources/earthmap.png"), || {
+        match ColorTexture::from_file(EARTHMAP_PNG) {
+            Ok(texture) => Arc::new(texture),
+            Err(e) => {
+                log(
+                    Verbosity::Normal,
+                    format_args!("warning: earth map: {}; using a flat color", e),
+                );
+                Arc::new(SolidColor::new(color(0.1, 0.2, 0.45)))
+            }
+        }
+    })
+}
+
+pub fn earthmap() -> Scene {
     /* === World === */
     let mut world = HittableList::new();
 
     /* === Materials === */
-    let earthmap = Arc::new(ColorTexture::from_file(
-        include_bytes!("../resources/earthmap.png"),
-        Some(ImageFormat::Png),
-    ));
+    let earthmap = earthmap_texture();
 
     /* === Objects === */
     world.add_arc(Arc::new(Sphere::new(
@@ -139,20 +1041,119 @@ pub fn earthmap() {
         Arc::new(Lambertian::new(earthmap.clone())),
     )));
 
-    Camera::new(
-        16.0 / 9.0,
-        400,
-        20.0,
-        point(0., 0., 12.),
-        point(0., 0., 0.),
-        Vec3(0.0, 1.0, 0.0),
-        20,
-        20,
+    Scene::new(
+        CameraBuilder::default()
+            .vfov(20.0)
+            .look_from(point(0., 0., 12.))
+            .look_at(point(0., 0., 0.))
+            .samples(20)
+            .max_depth(20)
+            .build()
+            .expect("a valid camera"),
+        world,
     )
-    .render(&world);
 }
 
-pub fn quads() {
+/// The bundled earth map wrapped around the scene as its environment, seen
+/// behind and reflected in a mirror, a glass, and a matte sphere, which it
+/// lights as well.
+pub fn earth_environment() -> Scene {
+    /* === World === */
+    let mut world = HittableList::new();
+
+    /* === Materials === */
+    let material_mirror = Arc::new(Metal::new(color(0.9, 0.9, 0.9), 0.0));
+    let material_glass = Arc::new(Dielectric::new(1.5));
+    let material_matte = Arc::new(Lambertian::from(color(0.7, 0.7, 0.7)));
+
+    /* === Objects === */
+    world.add(Sphere::new(point(-2.2, 0., 0.), 1., material_mirror));
+    world.add(Sphere::new(point(0., 0., 0.), 1., material_glass));
+    world.add(Sphere::new(point(2.2, 0., 0.), 1., material_matte));
+
+    /* === Camera === */
+    let camera = CameraBuilder::default()
+        .vfov(45.0)
+        .look_from(point(0., 1., 7.))
+        .look_at(point(0., 0., 0.))
+        .samples(50)
+        .max_depth(20)
+        .build()
+        .expect("a valid camera");
+
+    /* === Lights === */
+    let mut scene = Scene::new(camera, world);
+    let mut environment = Environment::from_texture(earthmap_texture().as_ref(), 1024);
+    environment.strength = 1.5;
+    scene.set_environment(environment);
+    scene
+}
+
+/// A sphere resting on a shadow catcher under an even white sky, rendered
+/// transparent and composited over the bundled earth map, which only its
+/// soft contact shadow darkens. Builds that can't decode images leave the
+/// render transparent.
+pub fn backplate() -> Scene {
+    /* === World === */
+    let mut world = HittableList::new();
+
+    /* === Materials === */
+    let material_sphere = Arc::new(Plastic::new(
+        Arc::new(SolidColor::new(color(0.7, 0.15, 0.1))),
+        0.3,
+        0.1,
+    ));
+    let material_ground = Arc::new(ShadowCatcher::from(color(0.5, 0.5, 0.5)));
+
+    /* === Objects === */
+    world.add(Sphere::new(point(0., 1., 0.), 1., material_sphere));
+    world.add(Parallelogram::new(
+        point(-20., 0., -20.),
+        (Vec3(40., 0., 0.), Vec3(0., 0., 40.)),
+        material_ground,
+    ));
+
+    /* === Camera === */
+    let mut camera = CameraBuilder::default()
+        .vfov(40.)
+        .look_from(point(0., 5., 7.))
+        .look_at(point(0., 2., -0.5))
+        .max_depth(20)
+        .build()
+        .expect("a valid camera");
+    camera.set_transparent(true);
+
+    /* === Lights === */
+    let mut scene = Scene::new(camera, world);
+    // Brightest overhead, so the shadow gathers where the sphere meets the
+    // ground.
+    let mut sky = ImageBuffer::new(1, 32);
+    for y in 0..16 {
+        let up = ((y as f64 + 0.5) / 32.0 * std::f64::consts::PI).cos();
+        sky.set(0, y, color(1., 1., 1.) * (3.0 * up * up));
+    }
+    scene.set_environment(Environment::new(sky));
+    scene.backplate = match ColorTexture::from_file(EARTHMAP_PNG) {
+        // Undoing the gamma correction as `ImageBuffer::load` does, so the
+        // plate comes out as it went in wherever nothing covers it.
+        Ok(texture) => Some(ImageBuffer {
+            width: texture.width,
+            height: texture.height,
+            pixels: texture.data.iter().map(|&pixel| pixel * pixel).collect(),
+            alpha: None,
+        }),
+        Err(e) => {
+            log(
+                Verbosity::Normal,
+                format_args!("warning: backplate: {}; leaving the render transparent", e),
+            );
+            None
+        }
+    };
+    scene
+}
+
+pub fn quads() -> Scene {
     /* === World === */
     let mut world = HittableList::new();
 
@@ -190,20 +1191,38 @@ pub fn quads() {
         bottom_teal,
     )));
 
-    Camera::new(
-        1.0,
-        400,
-        80.,
-        point(0., 0., 9.),
-        point(0., 0., 0.),
-        Vec3(0., 1., 0.),
-        20,
-        20,
+    Scene::new(
+        CameraBuilder::default()
+            .aspect_ratio(1.0)
+            .vfov(80.)
+            .look_from(point(0., 0., 9.))
+            .look_at(point(0., 0., 0.))
+            .samples(20)
+            .max_depth(20)
+            .build()
+            .expect("a valid camera"),
+        world,
     )
-    .render(&world);
 }
 
-pub fn planars() {
+/// `quads` in parallel projection from above and to one side, so the box's
+/// parallel edges stay parallel, under a flat sky to light it.
+pub fn quads_orthographic() -> Scene {
+    let mut scene = quads();
+    scene.camera = CameraBuilder::default()
+        .aspect_ratio(1.0)
+        .look_from(point(7., 5., 12.))
+        .look_at(point(0., 0., 3.))
+        .projection(Projection::Orthographic { width: 11. })
+        .samples(20)
+        .max_depth(20)
+        .build()
+        .expect("a valid camera");
+    scene.camera.set_background(color(0.7, 0.8, 1.0));
+    scene
+}
+
+pub fn planars() -> Scene {
     /* === World === */
     let mut world = HittableList::new();
 
@@ -237,63 +1256,50 @@ pub fn planars() {
         bottom_teal,
     )));
 
-    Camera::new(
-        1.0,
-        400,
-        80.,
-        point(0., 0., 9.),
-        point(0., 0., 0.),
-        Vec3(0., 1., 0.),
-        20,
-        20,
+    Scene::new(
+        CameraBuilder::default()
+            .aspect_ratio(1.0)
+            .vfov(80.)
+            .look_from(point(0., 0., 9.))
+            .look_at(point(0., 0., 0.))
+            .samples(20)
+            .max_depth(20)
+            .build()
+            .expect("a valid camera"),
+        world,
     )
-    .render(&world);
 }
 
-pub fn obj_mesh() {
+pub fn obj_mesh() -> Result<Scene, Error> {
     let mut world = HittableList::new();
 
     let material = Arc::new(Lambertian::from(color(0.8, 0.8, 0.8)));
 
-    let model: three_d_asset::Model = three_d_asset::io::load_and_deserialize(Path::new(
-        "./resources/SpaceShip-Fighter/SpaceShip-Fighter.obj",
-    ))
-    .unwrap();
-
-    let mesh = match &model.geometries[0].geometry {
-        Geometry::Points(_) => panic!("Expected a triangle mesh"),
-        Geometry::Triangles(mesh) => mesh,
-    };
-    mesh.for_each_triangle(|a, b, c| {
-        let va = mesh.positions.to_f64()[a];
-        let vb = mesh.positions.to_f64()[b];
-        let vc = mesh.positions.to_f64()[c];
-        world.add(Planar::Triangle(Triangle::new(
-            (
-                point(va.x, va.y, va.z),
-                point(vb.x, vb.y, vb.z),
-                point(vc.x, vc.y, vc.z),
-            ),
-            material.clone(),
-        )));
-    });
-
-    let world = HittableList::from(Arc::new(BoundNode::from_list(world)));
+    // Normalized so the fixed camera below frames any model.
+    let (mesh, _) = load_model_normalized(
+        Path::new("resources/SpaceShip-Fighter/SpaceShip-Fighter.obj"),
+        &AssetResolver::new(),
+        material,
+        8.0,
+        None,
+    )?;
+    world.add(mesh);
 
-    Camera::new(
-        1.0,
-        400,
-        80.,
-        point(0., 0., 9.),
-        point(0., 0., 0.),
-        Vec3(0., 1., 0.),
-        20,
-        20,
-    )
-    .render(&world);
+    Ok(Scene::new(
+        CameraBuilder::default()
+            .aspect_ratio(1.0)
+            .vfov(50.)
+            .look_from(point(7., 4., 7.))
+            .look_at(point(0., 0., 0.))
+            .samples(20)
+            .max_depth(20)
+            .build()
+            .expect("a valid camera"),
+        world,
+    ))
 }
 
-pub fn simple_light() {
+pub fn simple_light() -> Scene {
     let mut world = HittableList::new();
 
     let material_ground = Arc::new(Lambertian::from(color(0.8, 0.8, 0.0)));
@@ -308,140 +1314,754 @@ pub fn simple_light() {
         diffuse_light,
     )));
 
-    Camera::new(
-        16.0 / 9.0,
-        400,
-        20.,
-        point(26., 3., 6.),
-        point(0., 2., 0.),
-        Vec3(0., 1., 0.),
-        20,
-        20,
+    Scene::new(
+        CameraBuilder::default()
+            .vfov(20.)
+            .look_from(point(26., 3., 6.))
+            .look_at(point(0., 2., 0.))
+            .samples(20)
+            .max_depth(20)
+            .build()
+            .expect("a valid camera"),
+        world,
     )
-    .render(&world);
 }
 
-pub fn cornell_box() {
+pub fn sunlit_spheres() -> Scene {
+    /* === World === */
     let mut world = HittableList::new();
 
-    let red = Arc::new(Lambertian::from(color(0.65, 0.05, 0.05)));
-    let white = Arc::new(Lambertian::from(color(0.73, 0.73, 0.73)));
-    let green = Arc::new(Lambertian::from(color(0.12, 0.45, 0.15)));
-    let light = Arc::new(DiffuseLight::from(color(15., 15., 15.)));
+    /* === Materials === */
+    let material_ground = Arc::new(Lambertian::from(color(0.6, 0.6, 0.6)));
+    let material_left = Arc::new(Lambertian::from(color(0.7, 0.2, 0.2)));
+    let material_center = Arc::new(Plastic::new(
+        Arc::new(SolidColor::new(color(0.2, 0.4, 0.7))),
+        0.1,
+        0.2,
+    ));
+    let material_right = Arc::new(Metal::new(color(0.8, 0.8, 0.8), 0.05));
 
-    world.add(Planar::Parallelogram(Parallelogram::new(
-        point(555., 0., 0.),
-        (Vec3(0., 555., 0.), Vec3(0., 0., 555.)),
-        green.clone(),
-    )));
-    world.add(Planar::Parallelogram(Parallelogram::new(
-        point(0., 0., 0.),
-        (Vec3(555., 0., 0.), Vec3(0., 0., 555.)),
-        red.clone(),
-    )));
-    world.add(Planar::Parallelogram(Parallelogram::new(
-        point(343., 554., 332.),
-        (Vec3(-130., 0., 0.), Vec3(0., 0., -105.)),
-        light.clone(),
+    /* === Objects === */
+    world.add(Sphere::new(point(0., -1000., 0.), 1000., material_ground));
+    world.add(Sphere::new(point(-2.2, 1., 0.), 1., material_left));
+    world.add(Sphere::new(point(0., 1., 0.), 1., material_center));
+    world.add(Sphere::new(point(2.2, 1., 0.), 1., material_right));
+
+    /* === Camera === */
+    let mut camera = CameraBuilder::default()
+        .vfov(30.)
+        .look_from(point(0., 3., 11.))
+        .look_at(point(0., 0.8, 0.))
+        .samples(20)
+        .max_depth(20)
+        .build()
+        .expect("a valid camera");
+    camera.set_background(color(0.10, 0.12, 0.16));
+
+    /* === Lights === */
+    let mut scene = Scene::new(camera, world);
+    scene.add_delta_light(Arc::new(DirectionalLight::new(
+        Vec3(-1., -0.8, -0.6),
+        color(3.0, 2.5, 1.8),
     )));
-    world.add(Planar::Parallelogram(Parallelogram::new(
-        point(0., 0., 0.),
-        (Vec3(555., 0., 0.), Vec3(0., 0., 555.)),
-        white.clone(),
+    scene
+}
+
+/// Rolling hills `columns` samples across, spanning 20 units along X and Z.
+pub fn hills(columns: usize, material: Arc<dyn Material>) -> Heightfield {
+    let height = |x: f64, z: f64| {
+        1.2 * (0.6 * x).sin() * (0.5 * z).cos()
+            + 0.4 * (1.7 * x + 1.3 * z).sin()
+            + 0.15 * (3.1 * x - 2.3 * z).sin()
+    };
+    let spacing = 20.0 / (columns.max(2) - 1) as f64;
+    Heightfield::from_fn(columns, columns, spacing, height, material)
+}
+
+pub fn terrain() -> Scene {
+    /* === World === */
+    let mut world = HittableList::new();
+
+    /* === Materials === */
+    let material_ground = Arc::new(Lambertian::from(color(0.45, 0.55, 0.3)));
+
+    /* === Objects === */
+    world.add(hills(161, material_ground));
+
+    /* === Camera === */
+    let mut camera = CameraBuilder::default()
+        .vfov(45.)
+        .look_from(point(10., 5., -5.))
+        .look_at(point(10., 0., 9.))
+        .samples(20)
+        .max_depth(20)
+        .build()
+        .expect("a valid camera");
+    camera.set_background(color(0.55, 0.7, 0.9));
+
+    /* === Lights === */
+    let mut scene = Scene::new(camera, world);
+    scene.add_delta_light(Arc::new(DirectionalLight::new(
+        Vec3(-1., -0.6, 0.8),
+        color(2.5, 2.2, 1.8),
     )));
-    world.add(Planar::Parallelogram(Parallelogram::new(
-        point(555., 555., 555.),
-        (Vec3(555., 0., 0.), Vec3(0., 0., 555.)),
-        white.clone(),
+    scene
+}
+
+/// Boxes on a ground plane under the sky at `hour`, see
+/// `Sky::from_time_of_day`: long shadows and warm light in the morning and
+/// evening, short ones and a blue sky at noon.
+pub fn sky_boxes(hour: f64) -> Scene {
+    let ground = lambertian(color(0.5, 0.5, 0.5));
+    let white = lambertian(color(0.75, 0.75, 0.75));
+    let terracotta = lambertian(color(0.7, 0.35, 0.2));
+
+    let mut scene = WorldBuilder::new()
+        .quad(
+            point(-5000., 0., -5000.),
+            Vec3(0., 0., 10000.),
+            Vec3(10000., 0., 0.),
+            ground,
+        )
+        .object(
+            cuboid(point(-1., 0., -1.), point(1., 3., 1.), white.clone())
+                .rotate_y(20.)
+                .translate(Vec3(-2.5, 0., -1.)),
+        )
+        .object(cuboid(point(-0.8, 0., -0.8), point(0.8, 1.6, 0.8), terracotta).rotate_y(-15.))
+        .object(
+            cuboid(point(-0.6, 0., -0.6), point(0.6, 1.2, 0.6), white)
+                .rotate_y(40.)
+                .translate(Vec3(2.6, 0., 0.8)),
+        )
+        .scene(
+            CameraBuilder::default()
+                .vfov(40.)
+                .look_from(point(3., 2.5, 14.))
+                .look_at(point(0., 2., 0.))
+                .samples(20)
+                .max_depth(8)
+                .build()
+                .expect("a valid camera"),
+        );
+    scene.set_sky(&Sky::from_time_of_day(hour, 3.0));
+    scene
+}
+
+/// A room, 4 units wide and deep and 3 high, lit only through a window in
+/// its west wall by the sun and sky at 3 pm. The window is declared a
+/// `Portal`, so shadow rays toward the sky leave through it rather than
+/// mostly ending on the walls.
+pub fn window_room() -> Scene {
+    let white = lambertian(color(0.73, 0.73, 0.73));
+    let floor = lambertian(color(0.55, 0.4, 0.3));
+    let blue = lambertian(color(0.2, 0.3, 0.6));
+    let (x, y, z) = (Vec3(4., 0., 0.), Vec3(0., 3., 0.), Vec3(0., 0., 4.));
+
+    let mut scene = WorldBuilder::new()
+        // The ground outside.
+        .quad(
+            point(-5000., 0., -5000.),
+            Vec3(0., 0., 10000.),
+            Vec3(5000., 0., 0.),
+            lambertian(color(0.35, 0.45, 0.25)),
+        )
+        .quad(point(0., 0., 0.), z, x, floor)
+        .quad(point(0., 3., 0.), x, z, white.clone())
+        .quad(point(0., 0., 0.), x, y, white.clone())
+        .quad(point(0., 0., 4.), y, x, white.clone())
+        .quad(point(4., 0., 0.), y, z, white.clone())
+        // The west wall, around a window 2 wide and 1.5 high.
+        .quad(point(0., 0., 0.), z, Vec3(0., 1., 0.), white.clone())
+        .quad(point(0., 2.5, 0.), z, Vec3(0., 0.5, 0.), white.clone())
+        .quad(
+            point(0., 1., 0.),
+            Vec3(0., 0., 1.),
+            Vec3(0., 1.5, 0.),
+            white.clone(),
+        )
+        .quad(
+            point(0., 1., 3.),
+            Vec3(0., 0., 1.),
+            Vec3(0., 1.5, 0.),
+            white.clone(),
+        )
+        .object(cuboid(point(1.6, 0., 1.4), point(2.4, 0.8, 2.2), blue).rotate_y(25.))
+        .object(cuboid(point(2.8, 0., 0.4), point(3.5, 1.6, 1.1), white))
+        .scene(
+            CameraBuilder::default()
+                .vfov(70.)
+                .look_from(point(3.8, 1.6, 3.8))
+                .look_at(point(0.5, 1.1, 0.8))
+                .samples(64)
+                .max_depth(8)
+                .build()
+                .expect("a valid camera"),
+        );
+    scene.set_sky(&Sky::from_time_of_day(15.0, 3.0));
+    scene.add_portal(Portal::new(
+        point(0., 1., 1.),
+        (Vec3(0., 1.5, 0.), Vec3(0., 0., 2.)),
+    ));
+    scene
+}
+
+pub fn spotlight_fog() -> Scene {
+    /* === World === */
+    let mut world = HittableList::new();
+
+    /* === Materials === */
+    let material_walls = Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)));
+    let material_sphere = Arc::new(Lambertian::from(color(0.7, 0.3, 0.2)));
+
+    /* === Objects === */
+    world.add(Parallelogram::new(
+        point(-10., 0., -10.),
+        (Vec3(20., 0., 0.), Vec3(0., 0., 20.)),
+        material_walls.clone(),
+    ));
+    world.add(Parallelogram::new(
+        point(-10., 0., -4.),
+        (Vec3(20., 0., 0.), Vec3(0., 10., 0.)),
+        material_walls,
+    ));
+    world.add(Sphere::new(point(0., 1., 0.), 1., material_sphere));
+
+    /* === Fog === */
+    let room = parallelepiped(
+        point(-10., 0., -4.),
+        point(10., 10., 10.),
+        Arc::new(Dielectric::new(1.5)),
+    );
+    world.add(ConstantMedium::from_color(room, 0.05, color(1., 1., 1.)));
+
+    /* === Camera === */
+    let camera = CameraBuilder::default()
+        .vfov(40.)
+        .look_from(point(0., 2.5, 9.))
+        .look_at(point(0., 2., 0.))
+        .samples(50)
+        .max_depth(20)
+        .build()
+        .expect("a valid camera");
+
+    /* === Lights === */
+    let mut scene = Scene::new(camera, world);
+    let light = point(-2.5, 7., 1.);
+    scene.add_delta_light(Arc::new(SpotLight::new(
+        light,
+        point(0., 1., 0.) - light,
+        15.,
+        25.,
+        color(60., 55., 45.),
     )));
-    world.add(Planar::Parallelogram(Parallelogram::new(
-        point(0., 0., 555.),
-        (Vec3(555., 0., 0.), Vec3(0., 555., 0.)),
-        white.clone(),
+    scene
+}
+
+/// A room lit by a 20 by 10 grid of point lights just under its ceiling,
+/// their brightness spread over four orders of magnitude, so a few of them
+/// light most of it. Each hit sends one shadow ray toward a light picked
+/// from a light tree rather than one toward all 200.
+pub fn ceiling_lights() -> Scene {
+    let mut rng = StdRng::seed_from_u64(3);
+
+    /* === World === */
+    let mut world = HittableList::new();
+
+    /* === Materials === */
+    let material_walls = Arc::new(Lambertian::from(color(0.6, 0.6, 0.6)));
+
+    /* === Objects === */
+    world.add(Parallelogram::new(
+        point(-10., 0., -5.),
+        (Vec3(20., 0., 0.), Vec3(0., 0., 10.)),
+        material_walls.clone(),
+    ));
+    world.add(Parallelogram::new(
+        point(-10., 0., -5.),
+        (Vec3(20., 0., 0.), Vec3(0., 6., 0.)),
+        material_walls,
+    ));
+    for i in 0..5 {
+        let albedo = color(
+            rng.gen_range(0.2..0.9),
+            rng.gen_range(0.2..0.9),
+            rng.gen_range(0.2..0.9),
+        );
+        world.add(Sphere::new(
+            point(-6. + 3. * i as f64, 1., 0.),
+            1.,
+            Arc::new(Lambertian::from(albedo)),
+        ));
+    }
+
+    /* === Camera === */
+    let camera = CameraBuilder::default()
+        .vfov(50.)
+        .look_from(point(0., 3., 12.))
+        .look_at(point(0., 2., 0.))
+        .samples(16)
+        .max_depth(10)
+        .build()
+        .expect("a valid camera");
+
+    /* === Lights === */
+    let mut scene = Scene::new(camera, world);
+    for row in 0..10 {
+        for column in 0..20 {
+            let position = point(-9.5 + column as f64, 5.8, -4.5 + row as f64);
+            let intensity = 10f64.powf(rng.gen_range(-2.0..2.0));
+            scene.add_delta_light(Arc::new(PointLight::new(
+                position,
+                color(1.0, 0.9, 0.75) * intensity,
+            )));
+        }
+    }
+    scene.lights.set_sampling(LightSampling::Tree);
+    scene
+}
+
+/// A row of spheres receding along a ground plane into a pale sky, fading
+/// into it with distance.
+pub fn distance_fog() -> Scene {
+    /* === World === */
+    let mut world = HittableList::new();
+    let haze = color(0.7, 0.8, 0.9);
+
+    /* === Materials === */
+    let material_ground = Arc::new(Lambertian::from(color(0.4, 0.5, 0.3)));
+    let material_sphere = Arc::new(Lambertian::from(color(0.7, 0.3, 0.2)));
+
+    /* === Objects === */
+    world.add(Parallelogram::new(
+        point(-50., 0., -200.),
+        (Vec3(100., 0., 0.), Vec3(0., 0., 210.)),
+        material_ground,
+    ));
+    for i in 0..12 {
+        let z = -4.0 * i as f64;
+        world.add(Sphere::new(point(1.5, 1., z), 1., material_sphere.clone()));
+        world.add(Sphere::new(point(-1.5, 1., z), 1., material_sphere.clone()));
+    }
+
+    /* === Camera === */
+    let mut camera = CameraBuilder::default()
+        .vfov(30.)
+        .look_from(point(0., 2., 6.))
+        .look_at(point(0., 1., -20.))
+        .samples(50)
+        .max_depth(20)
+        .build()
+        .expect("a valid camera");
+    camera.set_background(haze);
+    camera.set_fog(Some(FogSettings {
+        start: 4.0,
+        ..FogSettings::new(haze, 15.0)
+    }));
+
+    /* === Lights === */
+    let mut scene = Scene::new(camera, world);
+    scene.add_delta_light(Arc::new(DirectionalLight::new(
+        Vec3(-1., -2., -1.),
+        color(2., 2., 2.),
     )));
+    scene
+}
 
-    let box1 = parallelepiped(Vec3(0., 0., 0.), Vec3(165., 330., 165.), white.clone());
-    let box1 = Arc::new(RotateY::new(box1, 15.));
-    let box1 = Arc::new(Translation::new(box1, Vec3(265., 0., 295.)));
-    world.add_arc(box1);
-
-    let box2 = parallelepiped(Vec3(0., 0., 0.), Vec3(165., 165., 165.), white.clone());
-    let box2 = Arc::new(RotateY::new(box2, -18.));
-    let box2 = Arc::new(Translation::new(box2, Vec3(130., 0., 65.)));
-    world.add_arc(box2);
-
-    Camera::new(
-        1.0,
-        600,
-        40.0,
-        point(278., 278., -800.),
-        point(278., 278., 0.),
-        Vec3(0., 1., 0.),
-        50,
-        20,
-    )
-    .render(&world);
+pub fn bokeh() -> Scene {
+    /* === World === */
+    let mut world = HittableList::new();
+    let mut rng = StdRng::seed_from_u64(7);
+
+    /* === Objects === */
+    let material_subject = Arc::new(Lambertian::from(color(0.7, 0.3, 0.2)));
+    world.add(Sphere::new(point(0., 0., 0.), 0.6, material_subject));
+
+    // Small, bright lights ten times farther away than the subject, so each
+    // spreads into the shape of the aperture.
+    for _ in 0..24 {
+        let light = Arc::new(DiffuseLight::from(color(
+            rng.gen_range(10.0..30.0),
+            rng.gen_range(8.0..24.0),
+            rng.gen_range(5.0..20.0),
+        )));
+        let center = point(
+            rng.gen_range(-18.0..18.0),
+            rng.gen_range(-10.0..10.0),
+            rng.gen_range(-44.0..-36.0),
+        );
+        world.add(Sphere::new(center, 0.2, light));
+    }
+
+    /* === Camera === */
+    let mut camera = CameraBuilder::default()
+        .vfov(30.)
+        .look_from(point(0., 0., 4.))
+        .look_at(point(0., 0., 0.))
+        .samples(400)
+        .max_depth(10)
+        .build()
+        .expect("a valid camera");
+    camera
+        .set_background(color(0.02, 0.02, 0.03))
+        .set_defocus(5.0, None)
+        .set_aperture(Some(6), 15.0);
+
+    Scene::new(camera, world)
 }
 
-pub fn cornell_smoke() {
+pub fn asteroid_field() -> Scene {
+    let mut rng = StdRng::seed_from_u64(7);
+
+    /* === Materials === */
+    let planet = Arc::new(Lambertian::new(Arc::new(NoiseTexture::new(0.4))));
+    let ice = Arc::new(Lambertian::from(color(0.8, 0.85, 0.9)));
+    let sun = Arc::new(DiffuseLight::from(color(20., 18., 15.)));
+
+    /* === Objects === */
     let mut world = HittableList::new();
+    world.add(Sphere::new(point(0., 0., 0.), 15., planet));
+    world.add(Sphere::new(point(20., 90., 150.), 30., sun));
+
+    let ring = orbit_ring(point(0., 0., 0.), 26., 90, |_, position| {
+        Arc::new(Sphere::new(position, rng.gen_range(0.4..1.0), ice.clone()))
+    });
+    world.add(BoundNode::from_list(ring));
+
+    let region = BoundingBox::from_points(point(45., -15., -40.), point(100., 15., 40.));
+    let asteroids = scatter_on_box(region, 150, 7, 5., |position, rng| {
+        let material: Arc<dyn Material> = if rng.gen::<f64>() < 0.85 {
+            let shade = rng.gen_range(0.25..0.5);
+            Arc::new(Lambertian::from(color(shade, shade * 0.9, shade * 0.8)))
+        } else {
+            Arc::new(Metal::new(color(0.7, 0.6, 0.5), rng.gen_range(0.1..0.5)))
+        };
+        Arc::new(Sphere::new(position, rng.gen_range(0.8..2.2), material))
+    })
+    .expect("a valid region");
+    world.add(BoundNode::from_list(asteroids));
+
+    let mut camera = CameraBuilder::default()
+        .vfov(45.)
+        .look_from(point(120., 40., 95.))
+        .look_at(point(40., 0., 0.))
+        .max_depth(20)
+        .build()
+        .expect("a valid camera");
+    camera.set_background(color(0.02, 0.02, 0.04));
+
+    Scene::new(camera, world)
+}
+
+pub fn cornell_box() -> Scene {
+    let red = lambertian(color(0.65, 0.05, 0.05));
+    let green = lambertian(color(0.12, 0.45, 0.15));
+    let white = lambertian(color(0.73, 0.73, 0.73));
+    let light = diffuse_light(color(15., 15., 15.));
+    let (x, y, z) = (Vec3(555., 0., 0.), Vec3(0., 555., 0.), Vec3(0., 0., 555.));
+
+    WorldBuilder::new()
+        .quad(point(555., 0., 0.), y, z, green)
+        .quad(point(0., 0., 0.), x, z, red)
+        .quad(point(0., 0., 0.), x, z, white.clone())
+        .quad(point(555., 555., 555.), x, z, white.clone())
+        .quad(point(0., 0., 555.), x, y, white.clone())
+        .quad(
+            point(343., 554., 332.),
+            Vec3(-130., 0., 0.),
+            Vec3(0., 0., -105.),
+            light,
+        )
+        .object(
+            cuboid(point(0., 0., 0.), point(165., 330., 165.), white.clone())
+                .rotate_y(15.)
+                .translate(Vec3(265., 0., 295.))
+                .matte("tall_box"),
+        )
+        .object(
+            cuboid(point(0., 0., 0.), point(165., 165., 165.), white)
+                .rotate_y(-18.)
+                .translate(Vec3(130., 0., 65.))
+                .matte("short_box"),
+        )
+        .scene(
+            CameraBuilder::default()
+                .aspect_ratio(1.0)
+                .image_width(600)
+                .vfov(40.0)
+                .look_from(point(278., 278., -800.))
+                .look_at(point(278., 278., 0.))
+                .samples(50)
+                .max_depth(20)
+                .build()
+                .expect("a valid camera"),
+        )
+}
+
+/// The Cornell box from inside the tall block, looking out toward the short
+/// one: the near clipping plane cuts away the block's own walls in front of
+/// the camera, as in an architectural cutaway.
+pub fn cornell_cutaway() -> Scene {
+    let mut scene = cornell_box();
+    scene.camera = CameraBuilder::default()
+        .aspect_ratio(1.0)
+        .image_width(600)
+        .vfov(70.0)
+        .look_from(point(366., 165., 353.))
+        .look_at(point(555., 200., 555.))
+        .samples(50)
+        .max_depth(20)
+        .build()
+        .expect("a valid camera");
+    scene.camera.set_clip(140.0, f64::INFINITY);
+    scene
+}
+
+/// The Cornell box all the way round from between its two blocks, as a 360°
+/// panorama; the open front of the box is the black band at either end.
+pub fn cornell_panorama() -> Scene {
+    let mut scene = cornell_box();
+    scene.camera = CameraBuilder::default()
+        .aspect_ratio(2.0)
+        .image_width(800)
+        .look_from(point(230., 200., 250.))
+        .look_at(point(230., 200., 555.))
+        .projection(Projection::Equirectangular)
+        .samples(50)
+        .max_depth(20)
+        .build()
+        .expect("a valid camera");
+    scene
+}
+
+/// Both blocks move while the shutter is open: the tall one slides toward the
+/// red wall, and a checkered cube in place of the short one turns on the spot.
+pub fn motion_blur() -> Scene {
+    let mut world = cornell::empty_box(cornell::STANDARD_SIZE);
+    world.add_arc(cornell::ceiling_light(15., cornell::STANDARD_SIZE));
 
-    let red = Arc::new(Lambertian::from(color(0.65, 0.05, 0.05)));
     let white = Arc::new(Lambertian::from(color(0.73, 0.73, 0.73)));
-    let green = Arc::new(Lambertian::from(color(0.12, 0.45, 0.15)));
-    let light = Arc::new(DiffuseLight::from(color(20., 20., 20.)));
+    let tall = parallelepiped(Vec3(0., 0., 0.), Vec3(165., 330., 165.), white);
+    let at = |translation: Vec3, angle: f64| Pose {
+        translation,
+        rotation: Quat::from_axis_angle(Vec3(0., 1., 0.), angle),
+        scale: Vec3(1., 1., 1.),
+    };
+    world.add(AnimatedTransform::new(
+        tall,
+        at(Vec3(265., 0., 295.), 15.),
+        at(Vec3(215., 0., 295.), 15.),
+    ));
 
+    // Solid textures stay put in world space, so the spinning cube wears an
+    // image mapped by its face coordinates, which turns with it.
+    let earth = Arc::new(Lambertian::new(earthmap_texture()));
+    let cube = parallelepiped(Vec3(-82.5, -82.5, -82.5), Vec3(82.5, 82.5, 82.5), earth);
+    world.add(AnimatedTransform::new(
+        cube,
+        at(Vec3(212., 82.5, 147.), -18.),
+        at(Vec3(212., 82.5, 147.), 27.),
+    ));
+
+    let mut camera = CameraBuilder::default()
+        .aspect_ratio(1.0)
+        .image_width(600)
+        .vfov(40.0)
+        .look_from(point(278., 278., -800.))
+        .look_at(point(278., 278., 0.))
+        .max_depth(20)
+        .build()
+        .expect("a valid camera");
+    camera.set_shutter(0.0, 1.0);
+    Scene::new(camera, world)
+}
+
+/// Glass needs many bounces to look right, but diffuse light between the walls
+/// fades after a few, so only the diffuse bounces are cut short.
+pub fn cornell_glass() -> Scene {
+    let mut world = cornell::empty_box(cornell::STANDARD_SIZE);
+    world.add_arc(cornell::ceiling_light(15., cornell::STANDARD_SIZE));
+    let glass = Arc::new(Dielectric::new(1.5));
+    world.add(Sphere::new(point(185., 100., 170.), 100., glass.clone()));
+    world.add(Sphere::new(point(380., 90., 360.), 90., glass));
+
+    let mut camera = CameraBuilder::default()
+        .aspect_ratio(1.0)
+        .image_width(600)
+        .vfov(40.0)
+        .look_from(point(278., 278., -800.))
+        .look_at(point(278., 278., 0.))
+        .samples(50)
+        .max_depth(30)
+        .build()
+        .expect("a valid camera");
+    camera.set_bounce_limits(BounceLimits {
+        diffuse: Some(4),
+        ..BounceLimits::default()
+    });
+    Scene::new(camera, world)
+}
+
+pub fn cornell_smoke() -> Scene {
+    let mut world = cornell::empty_box(cornell::STANDARD_SIZE);
+    world.add_arc(cornell::ceiling_light(20., cornell::STANDARD_SIZE));
+    let [tall, short] = cornell::standard_boxes();
+    world.add(ConstantMedium::from_color(tall, 0.01, color(0., 0., 0.)));
+    world.add(ConstantMedium::from_color(short, 0.01, color(1., 1., 1.)));
+
+    Scene::new(
+        CameraBuilder::default()
+            .aspect_ratio(1.0)
+            .image_width(900)
+            .vfov(40.0)
+            .look_from(point(278., 278., -800.))
+            .look_at(point(278., 278., 0.))
+            .samples(150)
+            .max_depth(75)
+            .build()
+            .expect("a valid camera"),
+        world,
+    )
+}
+
+/// The bundled smoke plume, a 24 by 40 by 24 grid, rising from the floor of
+/// the empty box and lit by its ceiling light.
+pub fn cornell_plume() -> Result<Scene, Error> {
+    let mut world = cornell::empty_box(cornell::STANDARD_SIZE);
+    world.add_arc(cornell::ceiling_light(15., cornell::STANDARD_SIZE));
+    let grid = load_volume(
+        Path::new("resources/volumes/smoke_plume.vol"),
+        &AssetResolver::new(),
+    )?;
+    let placement = Mat4::translation(Vec3(278., 0., 278.)) * Mat4::scale(Vec3(280., 280., 280.));
+    world.add(
+        VoxelGridMedium::from_color(Arc::new(grid), 0.06, color(0.8, 0.8, 0.8), placement)
+            .expect("the placement can be undone"),
+    );
+
+    Ok(Scene::new(
+        CameraBuilder::default()
+            .aspect_ratio(1.0)
+            .image_width(600)
+            .vfov(40.0)
+            .look_from(point(278., 278., -800.))
+            .look_at(point(278., 278., 0.))
+            .build()
+            .expect("a valid camera"),
+        world,
+    ))
+}
+
+pub fn book2_final() -> Scene {
+    let mut rng = StdRng::seed_from_u64(0);
+
+    /* === Ground === */
+    let ground = Arc::new(Lambertian::from(color(0.48, 0.83, 0.53)));
+    let w = 100.0;
+    let boxes = grid_place(point(-1000., 0., -1000.), 20, 20, w, |_, _, corner| {
+        let y1 = rng.gen_range(1.0..101.0);
+        parallelepiped(corner, corner + Vec3(w, y1, w), ground.clone())
+    });
+
+    let mut world = HittableList::new();
+    world.add(BoundNode::from_list(boxes));
+
+    /* === Light === */
+    let light = Arc::new(DiffuseLight::from(color(7., 7., 7.)));
     world.add(Planar::Parallelogram(Parallelogram::new(
-        point(555., 0., 0.),
-        (Vec3(0., 555., 0.), Vec3(0., 0., 555.)),
-        green.clone(),
-    )));
-    world.add(Planar::Parallelogram(Parallelogram::new(
-        point(0., 0., 0.),
-        (Vec3(555., 0., 0.), Vec3(0., 0., 555.)),
-        red.clone(),
-    )));
-    world.add(Planar::Parallelogram(Parallelogram::new(
-        point(343., 554., 332.),
-        (Vec3(-130., 0., 0.), Vec3(0., 0., -105.)),
-        light.clone(),
+        point(123., 554., 147.),
+        (Vec3(300., 0., 0.), Vec3(0., 0., 265.)),
+        light,
     )));
-    world.add(Planar::Parallelogram(Parallelogram::new(
+
+    /* === Spheres === */
+    // Moves 30 units to the right while the shutter is open.
+    let sphere_material = Arc::new(Lambertian::from(color(0.7, 0.3, 0.1)));
+    let moving = Arc::new(Sphere::new(point(0., 0., 0.), 50., sphere_material));
+    let at = |translation: Vec3| Pose {
+        translation,
+        ..Pose::identity()
+    };
+    world.add(AnimatedTransform::new(
+        moving,
+        at(Vec3(400., 400., 200.)),
+        at(Vec3(430., 400., 200.)),
+    ));
+
+    world.add(Sphere::new(
+        point(260., 150., 45.),
+        50.,
+        Arc::new(Dielectric::new(1.5)),
+    ));
+    world.add(Sphere::new(
+        point(0., 150., 145.),
+        50.,
+        Arc::new(Metal::new(color(0.8, 0.8, 0.9), 1.0)),
+    ));
+
+    /* === Volumes === */
+    let boundary = Arc::new(Sphere::new(
+        point(360., 150., 145.),
+        70.,
+        Arc::new(Dielectric::new(1.5)),
+    ));
+    world.add_arc(boundary.clone());
+    world.add(ConstantMedium::from_color(
+        boundary,
+        0.2,
+        color(0.2, 0.4, 0.9),
+    ));
+    let boundary = Arc::new(Sphere::new(
         point(0., 0., 0.),
-        (Vec3(555., 0., 0.), Vec3(0., 0., 555.)),
-        white.clone(),
-    )));
-    world.add(Planar::Parallelogram(Parallelogram::new(
-        point(555., 555., 555.),
-        (Vec3(555., 0., 0.), Vec3(0., 0., 555.)),
-        white.clone(),
-    )));
-    world.add(Planar::Parallelogram(Parallelogram::new(
-        point(0., 0., 555.),
-        (Vec3(555., 0., 0.), Vec3(0., 555., 0.)),
-        white.clone(),
-    )));
+        5000.,
+        Arc::new(Dielectric::new(1.5)),
+    ));
+    world.add(ConstantMedium::from_color(
+        boundary,
+        0.0001,
+        color(1., 1., 1.),
+    ));
+
+    /* === Textures === */
+    let earthmap = earthmap_texture();
+    world.add(Sphere::new(
+        point(400., 200., 400.),
+        100.,
+        Arc::new(Lambertian::new(earthmap)),
+    ));
+    let noise = Arc::new(NoiseTexture::new(0.2));
+    world.add(Sphere::new(
+        point(220., 280., 300.),
+        80.,
+        Arc::new(Lambertian::new(noise)),
+    ));
+
+    /* === Sphere Cluster === */
+    let white = Arc::new(Lambertian::from(color(0.73, 0.73, 0.73)));
+    let mut cluster = HittableList::new();
+    for _ in 0..1000 {
+        let center = Vec3(
+            rng.gen_range(0.0..165.0),
+            rng.gen_range(0.0..165.0),
+            rng.gen_range(0.0..165.0),
+        );
+        cluster.add(Sphere::new(center, 10., white.clone()));
+    }
+    let cluster = Arc::new(BoundNode::from_list(cluster));
+    let cluster = Arc::new(RotateY::new(cluster, 15.));
+    world.add(Translation::new(cluster, Vec3(-100., 270., 395.)));
 
-    let box1 = parallelepiped(Vec3(0., 0., 0.), Vec3(165., 330., 165.), white.clone());
-    let box1 = Arc::new(RotateY::new(box1, 15.));
-    let box1 = Arc::new(Translation::new(box1, Vec3(265., 0., 295.)));
-
-    let box2 = parallelepiped(Vec3(0., 0., 0.), Vec3(165., 165., 165.), white.clone());
-    let box2 = Arc::new(RotateY::new(box2, -18.));
-    let box2 = Arc::new(Translation::new(box2, Vec3(130., 0., 65.)));
-
-    world.add(ConstantMedium::from_color(box1, 0.01, color(0., 0., 0.)));
-    world.add(ConstantMedium::from_color(box2, 0.01, color(1., 1., 1.)));
-
-    Camera::new(
-        1.0,
-        900,
-        40.0,
-        point(278., 278., -800.),
-        point(278., 278., 0.),
-        Vec3(0., 1., 0.),
-        150,
-        75,
+    Scene::new(
+        CameraBuilder::default()
+            .aspect_ratio(1.0)
+            .vfov(40.0)
+            .look_from(point(478., 278., -600.))
+            .look_at(point(278., 278., 0.))
+            .samples(250)
+            .max_depth(4)
+            .shutter(0.0, 1.0)
+            .build()
+            .expect("a valid camera"),
+        world,
     )
-    .render(&world);
 }
@@ -0,0 +1,145 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{camera::*, core::*, scenes::Scene};
+
+/// A camera circling the point it looks at, about its up axis, over
+/// `frames` frames.
+#[derive(Clone, Copy, Debug)]
+pub struct Turntable {
+    pub frames: usize,
+    /// How far round the camera goes, 360 for a loop.
+    pub degrees: f64,
+}
+
+impl Turntable {
+    /// A full turn in `frames` frames, whose last frame runs seamlessly
+    /// into the first.
+    pub fn new(frames: usize) -> Self {
+        Self {
+            frames,
+            degrees: 360.0,
+        }
+    }
+
+    /// `camera` moved round to where it is at `frame`.
+    pub fn camera_at(&self, camera: &Camera, frame: usize) -> Camera {
+        let angle = (self.degrees * frame as f64 / self.frames.max(1) as f64).to_radians();
+        let axis = camera.up.unit();
+        let offset = camera.look_from - camera.look_at;
+        // Rodrigues' rotation of the offset about the axis.
+        let rotated = offset * angle.cos()
+            + Vec3::cross(&axis, &offset) * angle.sin()
+            + axis * (Vec3::dot(&axis, &offset) * (1.0 - angle.cos()));
+        let mut moved = camera.clone();
+        moved
+            .move_camera(camera.look_at + rotated, camera.look_at, camera.up)
+            .expect("turning keeps the view valid");
+        moved
+    }
+}
+
+/// How far through an animation of `frames` frames frame `frame` is, from
+/// 0 at the first to 1 at the last.
+pub fn frame_time(frame: usize, frames: usize) -> f64 {
+    if frames > 1 {
+        frame as f64 / (frames - 1) as f64
+    } else {
+        0.0
+    }
+}
+
+impl Scene {
+    /// Renders `frames` frames, each through the camera `camera_at` gives
+    /// for it, into `sink` in order, then finishes the video, returning how
+    /// long each frame took to render. The world and its BVH are shared by
+    /// every frame. Up to `parallel_jobs` frames render at once; those
+    /// finished early wait for the ones before them.
+    pub fn render_animation(
+        &self,
+        frames: usize,
+        parallel_jobs: usize,
+        camera_at: &(dyn Fn(usize) -> Camera + Sync),
+        sink: Box<dyn VideoSink>,
+    ) -> Result<Vec<Duration>, VideoError> {
+        render_frames(frames, parallel_jobs, sink, &|index| {
+            camera_at(index).render_image(&self.world, &self.lights)
+        })
+    }
+}
+
+/// `Scene::render_animation` for a scene that changes as well as its
+/// camera: frame by frame, the scene `scene_at` builds for the frame's
+/// `frame_time`, world, BVH, camera and all, is rendered into `sink`.
+pub fn render_sequence(
+    frames: usize,
+    parallel_jobs: usize,
+    scene_at: &(dyn Fn(f64) -> Scene + Sync),
+    sink: Box<dyn VideoSink>,
+) -> Result<Vec<Duration>, VideoError> {
+    render_frames(frames, parallel_jobs, sink, &|index| {
+        scene_at(frame_time(index, frames)).render_image()
+    })
+}
+
+/// Renders frames 0 to `frames` with `render`, up to `parallel_jobs` at
+/// once, writing them into `sink` in order.
+fn render_frames(
+    frames: usize,
+    parallel_jobs: usize,
+    mut sink: Box<dyn VideoSink>,
+    render: &(dyn Fn(usize) -> ImageBuffer + Sync),
+) -> Result<Vec<Duration>, VideoError> {
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
+    let mut times = Vec::with_capacity(frames);
+    thread::scope(|scope| {
+        for _ in 0..parallel_jobs.clamp(1, frames.max(1)) {
+            let sender = sender.clone();
+            let (next, stop) = (&next, &stop);
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                if index >= frames || stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                let start = Instant::now();
+                let image = render(index);
+                if sender.send((index, (image, start.elapsed()))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        let mut in_order = ReorderBuffer::new();
+        for (index, frame) in receiver {
+            for (image, time) in in_order.push(index, frame) {
+                if let Err(e) = sink.write_frame(&image) {
+                    // Let the frames being rendered finish, but start no more.
+                    stop.store(true, Ordering::Relaxed);
+                    return Err(e);
+                }
+                times.push(time);
+                log(
+                    Verbosity::Verbose,
+                    format_args!(
+                        "frame {}/{} written, rendered in {:.2?}",
+                        times.len(),
+                        frames,
+                        time
+                    ),
+                );
+            }
+        }
+        Ok(())
+    })?;
+    sink.finish()?;
+    Ok(times)
+}
@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use crate::{camera::*, core::*, models::*, scenes::Scene, surfaces::*};
+
+/* === Textures === */
+
+pub fn solid(color: Color) -> Arc<dyn Texture> {
+    Arc::new(SolidColor::new(color))
+}
+pub fn checker(scale: f64, odd: Color, even: Color) -> Arc<dyn Texture> {
+    Arc::new(CheckerTexture::from(scale, odd, even))
+}
+pub fn noise(scale: f64) -> Arc<dyn Texture> {
+    Arc::new(NoiseTexture::new(scale))
+}
+
+/* === Materials === */
+
+pub fn lambertian(albedo: Color) -> Arc<dyn Material> {
+    Arc::new(Lambertian::from(albedo))
+}
+/// A Lambertian surface colored by `texture`.
+pub fn textured(texture: Arc<dyn Texture>) -> Arc<dyn Material> {
+    Arc::new(Lambertian::new(texture))
+}
+pub fn metal(albedo: Color, fuzz: f64) -> Arc<dyn Material> {
+    Arc::new(Metal::new(albedo, fuzz))
+}
+pub fn dielectric(refraction_index: f64) -> Arc<dyn Material> {
+    Arc::new(Dielectric::new(refraction_index))
+}
+pub fn diffuse_light(color: Color) -> Arc<dyn Material> {
+    Arc::new(DiffuseLight::from(color))
+}
+
+/* === Shapes === */
+
+pub fn sphere(center: Point, radius: f64, material: Arc<dyn Material>) -> Arc<dyn Hittable> {
+    Arc::new(Sphere::new(center, radius, material))
+}
+/// The parallelogram spanned by `u` and `v` from `corner`.
+pub fn quad(corner: Point, u: Vec3, v: Vec3, material: Arc<dyn Material>) -> Arc<dyn Hittable> {
+    Arc::new(Planar::Parallelogram(Parallelogram::new(
+        corner,
+        (u, v),
+        material,
+    )))
+}
+pub fn triangle(a: Point, b: Point, c: Point, material: Arc<dyn Material>) -> Arc<dyn Hittable> {
+    Arc::new(Planar::Triangle(Triangle::new((a, b, c), material)))
+}
+/// The axis-aligned box with opposite corners `a` and `b`.
+pub fn cuboid(a: Point, b: Point, material: Arc<dyn Material>) -> Arc<dyn Hittable> {
+    parallelepiped(a, b, material)
+}
+
+/* === Placement === */
+
+/// Chains the transform wrappers onto an object, innermost first:
+/// `cuboid(..).rotate_y(15.).translate(offset)` turns the box, then moves it.
+pub trait Place {
+    fn translate(self, offset: Vec3) -> Arc<dyn Hittable>;
+    fn rotate_y(self, angle: f64) -> Arc<dyn Hittable>;
+    fn rotate(self, axis: Vec3, angle: f64) -> Arc<dyn Hittable>;
+    fn transform(self, matrix: Mat4) -> Arc<dyn Hittable>;
+    /// Tags the object for a matte named `name`.
+    fn matte(self, name: &str) -> Arc<dyn Hittable>;
+}
+
+impl Place for Arc<dyn Hittable> {
+    fn translate(self, offset: Vec3) -> Arc<dyn Hittable> {
+        Arc::new(Translation::new(self, offset))
+    }
+    fn rotate_y(self, angle: f64) -> Arc<dyn Hittable> {
+        Arc::new(RotateY::new(self, angle))
+    }
+    fn rotate(self, axis: Vec3, angle: f64) -> Arc<dyn Hittable> {
+        Arc::new(Rotate::from_axis_angle(self, axis, angle))
+    }
+    fn transform(self, matrix: Mat4) -> Arc<dyn Hittable> {
+        Arc::new(Transformed::new(self, matrix))
+    }
+    fn matte(self, name: &str) -> Arc<dyn Hittable> {
+        Arc::new(Matte::new(name, self))
+    }
+}
+
+/* === World === */
+
+/// Collects the objects of a world in order, so a scene can be written as
+/// one chain of shapes ending in `build()` or `scene(camera)`.
+#[derive(Default)]
+pub struct WorldBuilder {
+    world: HittableList,
+}
+
+impl WorldBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn object(mut self, object: Arc<dyn Hittable>) -> Self {
+        self.world.add_arc(object);
+        self
+    }
+    pub fn extend(mut self, objects: impl IntoIterator<Item = Arc<dyn Hittable>>) -> Self {
+        for object in objects {
+            self.world.add_arc(object);
+        }
+        self
+    }
+
+    pub fn sphere(self, center: Point, radius: f64, material: Arc<dyn Material>) -> Self {
+        self.object(sphere(center, radius, material))
+    }
+    pub fn quad(self, corner: Point, u: Vec3, v: Vec3, material: Arc<dyn Material>) -> Self {
+        self.object(quad(corner, u, v, material))
+    }
+    pub fn triangle(self, a: Point, b: Point, c: Point, material: Arc<dyn Material>) -> Self {
+        self.object(triangle(a, b, c, material))
+    }
+    pub fn cuboid(self, a: Point, b: Point, material: Arc<dyn Material>) -> Self {
+        self.object(cuboid(a, b, material))
+    }
+
+    pub fn build(self) -> HittableList {
+        self.world
+    }
+    /// The scene seeing the world through `camera`, with its lights registered.
+    pub fn scene(self, camera: Camera) -> Scene {
+        Scene::new(camera, self.world)
+    }
+}
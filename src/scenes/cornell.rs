@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use crate::{core::*, models::*, surfaces::*};
+
+/// The side length of the classic box; the helpers scale from it.
+pub const STANDARD_SIZE: f64 = 555.;
+
+fn white() -> Arc<dyn Material> {
+    Arc::new(Lambertian::from(color(0.73, 0.73, 0.73)))
+}
+
+fn wall(q: Point, edges: (Vec3, Vec3), material: Arc<dyn Material>) -> Arc<dyn Hittable> {
+    Arc::new(Planar::Parallelogram(Parallelogram::new(
+        q, edges, material,
+    )))
+}
+
+/// The walls, floor, and ceiling of a Cornell box `size` units across, with the
+/// green wall at +x, the red one at the origin, and the open side facing -z.
+pub fn empty_box(size: f64) -> HittableList {
+    let red: Arc<dyn Material> = Arc::new(Lambertian::from(color(0.65, 0.05, 0.05)));
+    let green: Arc<dyn Material> = Arc::new(Lambertian::from(color(0.12, 0.45, 0.15)));
+    let white = white();
+
+    let mut walls = HittableList::new();
+    walls.add_arc(wall(
+        point(size, 0., 0.),
+        (Vec3(0., size, 0.), Vec3(0., 0., size)),
+        green,
+    ));
+    walls.add_arc(wall(
+        point(0., 0., 0.),
+        (Vec3(size, 0., 0.), Vec3(0., 0., size)),
+        red,
+    ));
+    walls.add_arc(wall(
+        point(0., 0., 0.),
+        (Vec3(size, 0., 0.), Vec3(0., 0., size)),
+        white.clone(),
+    ));
+    walls.add_arc(wall(
+        point(size, size, size),
+        (Vec3(size, 0., 0.), Vec3(0., 0., size)),
+        white.clone(),
+    ));
+    walls.add_arc(wall(
+        point(0., 0., size),
+        (Vec3(size, 0., 0.), Vec3(0., size, 0.)),
+        white,
+    ));
+    walls
+}
+
+/// The rectangular light just below the ceiling of a box `size` units across.
+pub fn ceiling_light(intensity: f64, size: f64) -> Arc<dyn Hittable> {
+    let scale = size / STANDARD_SIZE;
+    let light = Arc::new(DiffuseLight::from(color(intensity, intensity, intensity)));
+    wall(
+        point(343., 554., 332.) * scale,
+        (Vec3(-130., 0., 0.) * scale, Vec3(0., 0., -105.) * scale),
+        light,
+    )
+}
+
+/// The tall and short white blocks of the standard box, rotated and in place.
+pub fn standard_boxes() -> [Arc<dyn Hittable>; 2] {
+    let white = white();
+
+    let tall = parallelepiped(Vec3(0., 0., 0.), Vec3(165., 330., 165.), white.clone());
+    let tall = Arc::new(RotateY::new(tall, 15.));
+    let tall = Arc::new(Translation::new(tall, Vec3(265., 0., 295.)));
+
+    let short = parallelepiped(Vec3(0., 0., 0.), Vec3(165., 165., 165.), white);
+    let short = Arc::new(RotateY::new(short, -18.));
+    let short = Arc::new(Translation::new(short, Vec3(130., 0., 65.)));
+
+    [tall, short]
+}
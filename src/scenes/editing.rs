@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+use crate::{core::*, models::*, scenes::Scene};
+
+/// An instance picked up at a pixel of the camera's image, for
+/// `Scene::drag_instance` to move.
+#[derive(Clone, Copy, Debug)]
+pub struct Grab {
+    /// The index of the instance in the scene's `Tlas`.
+    pub instance: usize,
+    /// Where the instance was when it was picked up.
+    pub transform: Mat4,
+    /// The point picked up, on the instance's surface.
+    pub point: Point,
+}
+
+impl Scene {
+    /// Regroups the world's objects as the instances of a `Tlas`, so they
+    /// can be moved without rebuilding the world's BVH. Objects the world
+    /// built a BVH over become instances of their own; anything else, such
+    /// as a mesh, stays one instance, whatever its size. Does nothing if the
+    /// world is already instanced.
+    pub fn instance_world(&mut self) {
+        if self.instances.is_some() {
+            return;
+        }
+        let mut objects = Vec::new();
+        for object in &self.world.objects {
+            match object.bvh_leaves() {
+                Some(leaves) => objects.extend(leaves),
+                None => objects.push(object.clone()),
+            }
+        }
+        let tlas = Arc::new(Tlas::from_objects(objects));
+        self.world = HittableList::from(tlas.clone());
+        self.instances = Some(tlas);
+    }
+
+    /// The world's instances, if `instance_world` has made them.
+    pub fn instances(&self) -> Option<&Tlas> {
+        self.instances.as_deref()
+    }
+
+    /// Places instance `index` by `transform`, refitting the instances'
+    /// tree around it or rebuilding it, and says which. `None` if the world
+    /// isn't instanced or has no such instance.
+    pub fn set_instance_transform(&mut self, index: usize, transform: Mat4) -> Option<TlasUpdate> {
+        let mut tlas = self.instances.take()?;
+        if index >= tlas.len() {
+            self.instances = Some(tlas);
+            return None;
+        }
+        // The world lets go of the tree while it's edited, so it's only
+        // copied if a render still holds it.
+        let same =
+            |object: &Arc<dyn Hittable>| std::ptr::addr_eq(Arc::as_ptr(object), Arc::as_ptr(&tlas));
+        let mut objects = std::mem::take(&mut self.world.objects);
+        let position = objects.iter().position(same);
+        if let Some(position) = position {
+            objects.remove(position);
+        }
+        let edited = Arc::make_mut(&mut tlas);
+        edited.set_transform(index, transform);
+        let update = edited.update();
+        objects.insert(position.unwrap_or(0), tlas.clone());
+
+        self.world = HittableList::new();
+        for object in objects {
+            self.world.add_arc(object);
+        }
+        // Lights moved with their instances.
+        let mut found = Vec::new();
+        self.world.collect_lights(&mut found);
+        self.lights.area = HittableList::new();
+        for light in found.into_iter().chain(self.added_lights.iter().cloned()) {
+            self.lights.area.add_arc(light);
+        }
+        self.instances = Some(tlas);
+        Some(update)
+    }
+
+    /// Picks up the instance seen through the center of pixel (x, y) of
+    /// the camera's image, as for a click on a preview, or `None` if no
+    /// instance is there.
+    pub fn grab_instance(&self, x: i32, y: i32) -> Option<Grab> {
+        let tlas = self.instances.as_ref()?;
+        let camera = &self.camera;
+        let ray = camera.pixel_ray(x, y, Vec3(0.0, 0.0, 0.0));
+        // Volumes decide where a ray scatters at random; any seed will do.
+        let mut rng = IndependentSampler::new(0);
+        let (instance, record) = tlas.hit_instance(&ray, camera.clip_interval(&ray), &mut rng)?;
+        Some(Grab {
+            instance,
+            transform: tlas.instances()[instance].transform,
+            point: record.point,
+        })
+    }
+
+    /// Moves the instance `grab` picked up so the point grabbed is under
+    /// pixel (x, y), sliding it over the plane through that point square
+    /// to the camera's up, as across a floor. `None`, leaving it where it
+    /// is, if the pixel doesn't look onto that plane. See
+    /// `set_instance_transform`.
+    pub fn drag_instance(&mut self, grab: &Grab, x: i32, y: i32) -> Option<TlasUpdate> {
+        let camera = &self.camera;
+        let ray = camera.pixel_ray(x, y, Vec3(0.0, 0.0, 0.0));
+        let up = camera.up.unit();
+        let distance = Vec3::dot(&(grab.point - ray.origin), &up) / Vec3::dot(&ray.direction, &up);
+        if !(distance > 0.0 && distance.is_finite()) {
+            return None;
+        }
+        let target = ray.origin + ray.direction * distance;
+        let transform = Mat4::translation(target - grab.point) * grab.transform;
+        self.set_instance_transform(grab.instance, transform)
+    }
+}
@@ -0,0 +1,145 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+    sync::Arc,
+};
+
+use crate::{core::*, models::*, scenes::*, surfaces::*};
+
+/// Collects the textures and materials shared by the objects being described,
+/// naming each distinct `Arc` once so shared materials stay shared on reload.
+#[derive(Default)]
+pub struct Describer {
+    textures: BTreeMap<String, DescRef<TextureDesc>>,
+    materials: BTreeMap<String, DescRef<MaterialDesc>>,
+    texture_names: HashMap<*const (), String>,
+    material_names: HashMap<*const (), String>,
+}
+
+impl Describer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn object(&mut self, object: &dyn Hittable) -> Option<ObjectDesc> {
+        object.describe(self)
+    }
+
+    /// Returns the name of the described texture, registering it on first use.
+    pub fn texture(&mut self, texture: &Arc<dyn Texture>) -> Option<String> {
+        let key = Arc::as_ptr(texture) as *const ();
+        if let Some(name) = self.texture_names.get(&key) {
+            return Some(name.clone());
+        }
+        let desc = match TextureRegistry::global().path_of(texture) {
+            Some(path) => TextureDesc::Image { path },
+            None => texture.describe()?,
+        };
+        let name = format!("texture_{}", self.textures.len());
+        self.textures.insert(name.clone(), DescRef::Inline(desc));
+        self.texture_names.insert(key, name.clone());
+        Some(name)
+    }
+
+    /// Returns the name of the described material, registering it on first use.
+    pub fn material(&mut self, material: &Arc<dyn Material>) -> Option<String> {
+        let key = Arc::as_ptr(material) as *const ();
+        if let Some(name) = self.material_names.get(&key) {
+            return Some(name.clone());
+        }
+        let desc = material.describe(self)?;
+        let name = format!("material_{}", self.materials.len());
+        self.materials.insert(name.clone(), DescRef::Inline(desc));
+        self.material_names.insert(key, name.clone());
+        Some(name)
+    }
+
+    /// Splits a texture into the `color` / `texture` pair used by the
+    /// descriptions, inlining solid colors.
+    pub fn color_or_texture(
+        &mut self,
+        texture: &Arc<dyn Texture>,
+    ) -> Option<(Option<Color>, Option<DescRef<TextureDesc>>)> {
+        match texture.describe() {
+            Some(TextureDesc::Solid { color }) => Some((Some(color), None)),
+            _ => Some((None, Some(self.texture(texture)?.into()))),
+        }
+    }
+}
+
+impl Scene {
+    /// Describes the scene so it can be saved and loaded again. Fails on an
+    /// image aperture, a background without a description, or the first
+    /// object, material, or texture that has none.
+    pub fn describe(&self) -> Result<SceneDesc, SceneError> {
+        if let ApertureShape::Image(_) = self.camera.aperture {
+            return Err(SceneError::Invalid {
+                entry: "camera".to_string(),
+                message: "an image aperture cannot be described".to_string(),
+            });
+        }
+        let background = self
+            .camera
+            .background
+            .describe()
+            .ok_or_else(|| SceneError::Invalid {
+                entry: "background".to_string(),
+                message: "background cannot be described".to_string(),
+            })?;
+        let mut describer = Describer::new();
+        let mut objects = Vec::new();
+        for (i, object) in self.world.objects.iter().enumerate() {
+            objects.push(
+                describer
+                    .object(object.as_ref())
+                    .ok_or_else(|| SceneError::Invalid {
+                        entry: format!("objects[{}]", i),
+                        message: "object cannot be described".to_string(),
+                    })?,
+            );
+        }
+
+        // A world built with `bvh = true` is a single BVH over the objects.
+        let bvh = matches!(objects.as_slice(), [ObjectDesc::Group { bvh: true, .. }]);
+        if bvh {
+            if let Some(ObjectDesc::Group { objects: inner, .. }) = objects.pop() {
+                objects = inner;
+            }
+        }
+
+        Ok(SceneDesc {
+            camera: CameraBuilder::from(&self.camera),
+            background: Some(background),
+            bvh,
+            textures: describer.textures,
+            materials: describer.materials,
+            objects,
+            lights: self
+                .lights
+                .delta
+                .iter()
+                .map(|light| light.describe())
+                .collect(),
+            light_sampling: self.lights.sampling(),
+        })
+    }
+}
+
+impl SceneDesc {
+    /// Writes the description as JSON or TOML, chosen by the file extension.
+    pub fn save(&self, path: &Path) -> Result<(), SceneError> {
+        let write_error = |message: String| SceneError::Write {
+            path: path.to_path_buf(),
+            message,
+        };
+        let text = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => {
+                serde_json::to_string_pretty(self).map_err(|e| write_error(e.to_string()))?
+            }
+            Some("toml") => toml::to_string(self).map_err(|e| write_error(e.to_string()))?,
+            _ => return Err(SceneError::UnsupportedFormat(path.to_path_buf())),
+        };
+        fs::write(path, text).map_err(|e| write_error(e.to_string()))
+    }
+}
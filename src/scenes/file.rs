@@ -0,0 +1,894 @@
+use std::{
+    collections::BTreeMap,
+    fmt, fs, io,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{core::*, models::*, scenes::*, surfaces::*, Error};
+
+use serde::{
+    de::{self, value::MapAccessDeserializer},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+/* === Description Types === */
+
+#[derive(Deserialize, Serialize)]
+pub struct SceneDesc {
+    #[serde(default)]
+    pub camera: CameraBuilder,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background: Option<BackgroundDesc>,
+    #[serde(default)]
+    pub bvh: bool,
+    /// Textures the materials and objects can refer to by name. An entry
+    /// may itself be the name of another, to give a texture a second name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub textures: BTreeMap<String, DescRef<TextureDesc>>,
+    /// Materials the objects can refer to by name, or give a second name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub materials: BTreeMap<String, DescRef<MaterialDesc>>,
+    pub objects: Vec<ObjectDesc>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lights: Vec<LightDesc>,
+    /// How hits pick the `lights` they send shadow rays toward.
+    #[serde(default, skip_serializing_if = "LightSampling::is_default")]
+    pub light_sampling: LightSampling,
+}
+
+/// The camera's background: a color, `[r, g, b]`, or a `GradientSky`,
+/// `{ "bottom": [r, g, b], "top": [r, g, b] }`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum BackgroundDesc {
+    Solid(Color),
+    Gradient { bottom: Color, top: Color },
+}
+
+impl BackgroundDesc {
+    pub fn build(&self) -> Arc<dyn Background> {
+        match *self {
+            BackgroundDesc::Solid(color) => Arc::new(SolidBackground(color)),
+            BackgroundDesc::Gradient { bottom, top } => Arc::new(GradientSky::new(bottom, top)),
+        }
+    }
+}
+
+/// A texture or material: the name of one of the scene's `textures` or
+/// `materials`, written as a string, or a one-off defined in place.
+#[derive(Clone)]
+pub enum DescRef<T> {
+    Named(String),
+    Inline(T),
+}
+
+impl<T> From<String> for DescRef<T> {
+    fn from(name: String) -> Self {
+        DescRef::Named(name)
+    }
+}
+
+impl<T> From<&str> for DescRef<T> {
+    fn from(name: &str) -> Self {
+        DescRef::Named(name.to_string())
+    }
+}
+
+impl<T: Serialize> Serialize for DescRef<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            DescRef::Named(name) => serializer.serialize_str(name),
+            DescRef::Inline(desc) => desc.serialize(serializer),
+        }
+    }
+}
+
+/// Tells names from definitions by whether they are strings or tables, so
+/// that a definition that is wrong still says why.
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for DescRef<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>> de::Visitor<'de> for Visitor<T> {
+            type Value = DescRef<T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a name or a definition")
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> Result<Self::Value, E> {
+                Ok(DescRef::Named(name.to_string()))
+            }
+
+            fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                T::deserialize(MapAccessDeserializer::new(map)).map(DescRef::Inline)
+            }
+        }
+
+        deserializer.deserialize_any(Visitor(PhantomData))
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TextureDesc {
+    Solid { color: Color },
+    Checker { scale: f64, odd: Color, even: Color },
+    Image { path: PathBuf },
+    VertexColor { fallback: Color },
+    Noise { scale: f64 },
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MaterialDesc {
+    Lambertian {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        albedo: Option<Color>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        texture: Option<DescRef<TextureDesc>>,
+    },
+    Metal {
+        albedo: Color,
+        #[serde(default)]
+        fuzz: f64,
+    },
+    Dielectric {
+        refraction_index: f64,
+    },
+    Plastic {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        albedo: Option<Color>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        texture: Option<DescRef<TextureDesc>>,
+        specular: f64,
+        roughness: f64,
+    },
+    DiffuseLight {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        color: Option<Color>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        texture: Option<DescRef<TextureDesc>>,
+    },
+    Isotropic {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        color: Option<Color>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        texture: Option<DescRef<TextureDesc>>,
+    },
+    ShadowCatcher {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        albedo: Option<Color>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        texture: Option<DescRef<TextureDesc>>,
+    },
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ObjectDesc {
+    Sphere {
+        center: Point,
+        radius: f64,
+        material: DescRef<MaterialDesc>,
+    },
+    Quad {
+        corner: Point,
+        u: Vec3,
+        v: Vec3,
+        material: DescRef<MaterialDesc>,
+    },
+    Triangle {
+        vertices: [Point; 3],
+        material: DescRef<MaterialDesc>,
+    },
+    Box {
+        min: Point,
+        max: Point,
+        material: DescRef<MaterialDesc>,
+    },
+    Mesh {
+        path: PathBuf,
+        material: DescRef<MaterialDesc>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        transform: Option<Transform>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        normalize: Option<f64>,
+        /// `grid` finds triangles through a uniform grid instead of a BVH.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        accel: Option<MeshAccel>,
+        /// Subdivides and displaces the loaded mesh by a height texture.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        displace: Option<DisplaceDesc>,
+    },
+    /// Terrain over rows of `columns` heights, `spacing` apart on the XZ plane.
+    Heightfield {
+        columns: usize,
+        spacing: f64,
+        heights: Vec<f64>,
+        material: DescRef<MaterialDesc>,
+    },
+    Translate {
+        offset: Vec3,
+        object: Box<ObjectDesc>,
+    },
+    RotateY {
+        angle: f64,
+        object: Box<ObjectDesc>,
+    },
+    /// Turns `object` `angle` degrees about `axis`.
+    Rotate {
+        axis: Vec3,
+        angle: f64,
+        object: Box<ObjectDesc>,
+    },
+    /// Moves `object` from the `start` pose when the shutter opens to `end`
+    /// when it closes.
+    Animate {
+        start: PoseDesc,
+        end: PoseDesc,
+        object: Box<ObjectDesc>,
+    },
+    /// Tags every hit on `object` with `name`, for `--matte`.
+    Matte {
+        name: String,
+        object: Box<ObjectDesc>,
+    },
+    Medium {
+        density: f64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        color: Option<Color>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        texture: Option<DescRef<TextureDesc>>,
+        boundary: Box<ObjectDesc>,
+    },
+    /// A medium whose density is read from a grid file, scaled by `density`
+    /// and placed by `transform`.
+    VoxelMedium {
+        path: PathBuf,
+        #[serde(default = "one")]
+        density: f64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        color: Option<Color>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        texture: Option<DescRef<TextureDesc>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        transform: Option<Transform>,
+    },
+    Group {
+        #[serde(default)]
+        bvh: bool,
+        objects: Vec<ObjectDesc>,
+    },
+}
+
+fn one() -> f64 {
+    1.0
+}
+
+/// The arguments of `TriangleMesh::displace`, naming a texture.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct DisplaceDesc {
+    pub texture: DescRef<TextureDesc>,
+    pub scale: f64,
+    pub max_subdivision: u32,
+}
+
+/// A `Pose` with its rotation as an angle in degrees about an axis.
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PoseDesc {
+    pub translation: Vec3,
+    pub axis: Vec3,
+    pub angle: f64,
+    pub scale: Vec3,
+}
+
+impl Default for PoseDesc {
+    fn default() -> Self {
+        Pose::identity().into()
+    }
+}
+
+impl From<Pose> for PoseDesc {
+    fn from(pose: Pose) -> Self {
+        let (axis, angle) = pose.rotation.to_axis_angle();
+        Self {
+            translation: pose.translation,
+            axis,
+            angle,
+            scale: pose.scale,
+        }
+    }
+}
+
+impl From<PoseDesc> for Pose {
+    fn from(desc: PoseDesc) -> Self {
+        Self {
+            translation: desc.translation,
+            rotation: Quat::from_axis_angle(desc.axis, desc.angle),
+            scale: desc.scale,
+        }
+    }
+}
+
+/// Lights without a surface. Emissive objects are described as objects.
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LightDesc {
+    Point {
+        position: Point,
+        intensity: Color,
+    },
+    Directional {
+        direction: Vec3,
+        irradiance: Color,
+    },
+    /// Angles are in degrees from `direction`.
+    Spot {
+        position: Point,
+        direction: Vec3,
+        inner_angle: f64,
+        outer_angle: f64,
+        intensity: Color,
+    },
+}
+
+/* === Errors === */
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        message: String,
+    },
+    UnsupportedFormat(PathBuf),
+    MissingFile {
+        entry: String,
+        source: AssetError,
+    },
+    UnknownTexture {
+        entry: String,
+        name: String,
+    },
+    UnknownMaterial {
+        entry: String,
+        name: String,
+    },
+    /// Names in `textures` or `materials` that lead back to themselves.
+    CyclicReference {
+        entry: String,
+        names: Vec<String>,
+    },
+    Load {
+        entry: String,
+        source: LoadError,
+    },
+    Invalid {
+        entry: String,
+        message: String,
+    },
+    Camera(CameraError),
+    Write {
+        path: PathBuf,
+        message: String,
+    },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io { path, source } => {
+                write!(f, "could not read {}: {}", path.display(), source)
+            }
+            SceneError::Parse { path, message } => {
+                write!(f, "could not parse {}: {}", path.display(), message)
+            }
+            SceneError::UnsupportedFormat(path) => write!(
+                f,
+                "unsupported scene format {} (expected .json or .toml)",
+                path.display()
+            ),
+            SceneError::MissingFile { entry, source } => write!(f, "{}: {}", entry, source),
+            SceneError::UnknownTexture { entry, name } => {
+                write!(f, "{}: unknown texture '{}'", entry, name)
+            }
+            SceneError::UnknownMaterial { entry, name } => {
+                write!(f, "{}: unknown material '{}'", entry, name)
+            }
+            SceneError::CyclicReference { entry, names } => {
+                write!(f, "{}: the names refer to each other in a cycle, ", entry)?;
+                write!(f, "{} -> {}", names.join(" -> "), names[0])
+            }
+            SceneError::Load { entry, source } => write!(f, "{}: {}", entry, source),
+            SceneError::Invalid { entry, message } => write!(f, "{}: {}", entry, message),
+            SceneError::Camera(e) => write!(f, "camera: {}", e),
+            SceneError::Write { path, message } => {
+                write!(f, "could not write {}: {}", path.display(), message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SceneError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SceneError::Io { source, .. } => Some(source),
+            SceneError::MissingFile { source, .. } => Some(source),
+            SceneError::Load { source, .. } => Some(source),
+            SceneError::Camera(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/* === Loading === */
+
+impl SceneDesc {
+    pub fn from_path(path: &Path) -> Result<Self, SceneError> {
+        let text = fs::read_to_string(path).map_err(|source| SceneError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let parse_error = |message: String| SceneError::Parse {
+            path: path.to_path_buf(),
+            message,
+        };
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => serde_json::from_str(&text).map_err(|e| parse_error(e.to_string())),
+            Some("toml") => toml::from_str(&text).map_err(|e| parse_error(e.to_string())),
+            _ => Err(SceneError::UnsupportedFormat(path.to_path_buf())),
+        }
+    }
+
+    /// Builds the scene, resolving asset paths against the working directory.
+    pub fn build(&self) -> Result<Scene, SceneError> {
+        self.build_with(&AssetResolver::new())
+    }
+
+    pub fn build_with(&self, resolver: &AssetResolver) -> Result<Scene, SceneError> {
+        let _stage = Stage::start("build scene");
+        let library = self.library(resolver)?;
+        let mut world = HittableList::new();
+        for (i, desc) in self.objects.iter().enumerate() {
+            let entry = format!("objects[{}]", i);
+            world.add_arc(library.build_object(&entry, desc)?);
+        }
+        if self.bvh && !world.objects.is_empty() {
+            world = HittableList::from(Arc::new(BoundNode::from_list(world)));
+        }
+
+        let mut camera = self.camera.build().map_err(SceneError::Camera)?;
+        if let Some(background) = &self.background {
+            camera.set_background_arc(background.build());
+        }
+
+        let mut scene = Scene::new(camera, world);
+        for desc in &self.lights {
+            scene.add_delta_light(build_light(desc));
+        }
+        scene.lights.set_sampling(self.light_sampling);
+        Ok(scene)
+    }
+}
+
+impl SceneDesc {
+    /// Builds the scene's named textures and materials, each once.
+    pub fn library<'a>(&self, resolver: &'a AssetResolver) -> Result<Library<'a>, SceneError> {
+        let textures = build_named("textures", &self.textures, |entry, desc| {
+            build_texture(entry, desc, resolver)
+        })?;
+        let materials = build_named("materials", &self.materials, |entry, desc| {
+            build_material(entry, desc, &textures, resolver)
+        })?;
+        Ok(Library {
+            textures,
+            materials,
+            resolver,
+        })
+    }
+}
+
+impl Scene {
+    pub fn from_path(path: &Path) -> Result<Self, SceneError> {
+        SceneDesc::from_path(path)?.build_with(&AssetResolver::for_scene_file(path))
+    }
+}
+
+/// Builds each entry of the scene's `textures` or `materials` once, however
+/// many names lead to it, so that everything using it shares one `Arc`.
+fn build_named<D, T: Clone>(
+    section: &str,
+    descs: &BTreeMap<String, DescRef<D>>,
+    build: impl Fn(&str, &D) -> Result<T, SceneError>,
+) -> Result<BTreeMap<String, T>, SceneError> {
+    let mut built: BTreeMap<String, T> = BTreeMap::new();
+    for first in descs.keys() {
+        // Follow the names to a definition or to an entry already built.
+        let mut names = vec![first.clone()];
+        let value = loop {
+            let name = names.last().expect("starts with a name");
+            if let Some(value) = built.get(name) {
+                break value.clone();
+            }
+            let entry = format!("{}.{}", section, name);
+            match descs.get(name) {
+                Some(DescRef::Inline(desc)) => break build(&entry, desc)?,
+                Some(DescRef::Named(next)) if names.contains(next) => {
+                    let start = names.iter().position(|name| name == next).unwrap_or(0);
+                    return Err(SceneError::CyclicReference {
+                        entry,
+                        names: names.split_off(start),
+                    });
+                }
+                Some(DescRef::Named(next)) => names.push(next.clone()),
+                None => {
+                    let entry = format!("{}.{}", section, names[names.len() - 2]);
+                    let name = name.clone();
+                    return Err(match section {
+                        "textures" => SceneError::UnknownTexture { entry, name },
+                        _ => SceneError::UnknownMaterial { entry, name },
+                    });
+                }
+            }
+        };
+        for name in names {
+            built.insert(name, value.clone());
+        }
+    }
+    Ok(built)
+}
+
+/// The texture `desc` names or defines in place.
+fn texture_ref(
+    entry: &str,
+    desc: &DescRef<TextureDesc>,
+    textures: &BTreeMap<String, Arc<dyn Texture>>,
+    resolver: &AssetResolver,
+) -> Result<Arc<dyn Texture>, SceneError> {
+    match desc {
+        DescRef::Named(name) => {
+            textures
+                .get(name)
+                .cloned()
+                .ok_or_else(|| SceneError::UnknownTexture {
+                    entry: entry.to_string(),
+                    name: name.clone(),
+                })
+        }
+        DescRef::Inline(desc) => build_texture(&format!("{}.texture", entry), desc, resolver),
+    }
+}
+
+fn build_light(desc: &LightDesc) -> Arc<dyn DeltaLight> {
+    match *desc {
+        LightDesc::Point {
+            position,
+            intensity,
+        } => Arc::new(PointLight::new(position, intensity)),
+        LightDesc::Directional {
+            direction,
+            irradiance,
+        } => Arc::new(DirectionalLight::new(direction, irradiance)),
+        LightDesc::Spot {
+            position,
+            direction,
+            inner_angle,
+            outer_angle,
+            intensity,
+        } => Arc::new(SpotLight::new(
+            position,
+            direction,
+            inner_angle,
+            outer_angle,
+            intensity,
+        )),
+    }
+}
+
+fn build_texture(
+    entry: &str,
+    desc: &TextureDesc,
+    resolver: &AssetResolver,
+) -> Result<Arc<dyn Texture>, SceneError> {
+    Ok(match desc {
+        TextureDesc::Solid { color } => Arc::new(SolidColor::new(*color)),
+        TextureDesc::Checker { scale, odd, even } => {
+            Arc::new(CheckerTexture::from(*scale, *odd, *even))
+        }
+        TextureDesc::VertexColor { fallback } => Arc::new(VertexColorTexture::new(*fallback)),
+        TextureDesc::Noise { scale } => Arc::new(NoiseTexture::new(*scale)),
+        TextureDesc::Image { path } => {
+            TextureRegistry::global()
+                .load(path, resolver)
+                .map_err(|e| match e {
+                    Error::MissingAsset(source) => SceneError::MissingFile {
+                        entry: entry.to_string(),
+                        source,
+                    },
+                    Error::Io { path, source } => SceneError::Io { path, source },
+                    e => SceneError::Invalid {
+                        entry: entry.to_string(),
+                        message: e.to_string(),
+                    },
+                })?
+        }
+    })
+}
+
+fn build_material(
+    entry: &str,
+    desc: &MaterialDesc,
+    textures: &BTreeMap<String, Arc<dyn Texture>>,
+    resolver: &AssetResolver,
+) -> Result<Arc<dyn Material>, SceneError> {
+    let texture =
+        |color: &Option<Color>, texture: &Option<DescRef<TextureDesc>>| match (color, texture) {
+            (Some(color), None) => Ok(Arc::new(SolidColor::new(*color)) as Arc<dyn Texture>),
+            (None, Some(desc)) => texture_ref(entry, desc, textures, resolver),
+            _ => Err(SceneError::Invalid {
+                entry: entry.to_string(),
+                message: "expected exactly one of a color or a texture".to_string(),
+            }),
+        };
+    Ok(match desc {
+        MaterialDesc::Lambertian {
+            albedo,
+            texture: name,
+        } => Arc::new(Lambertian::new(texture(albedo, name)?)),
+        MaterialDesc::Metal { albedo, fuzz } => Arc::new(Metal::new(*albedo, *fuzz)),
+        MaterialDesc::Dielectric { refraction_index } => {
+            Arc::new(Dielectric::new(*refraction_index))
+        }
+        MaterialDesc::Plastic {
+            albedo,
+            texture: name,
+            specular,
+            roughness,
+        } => Arc::new(Plastic::new(texture(albedo, name)?, *specular, *roughness)),
+        MaterialDesc::DiffuseLight {
+            color,
+            texture: name,
+        } => Arc::new(DiffuseLight::new(texture(color, name)?)),
+        MaterialDesc::Isotropic {
+            color,
+            texture: name,
+        } => Arc::new(Isotropic::new(texture(color, name)?)),
+        MaterialDesc::ShadowCatcher {
+            albedo,
+            texture: name,
+        } => Arc::new(ShadowCatcher::new(texture(albedo, name)?)),
+    })
+}
+
+/// A scene file's named textures and materials, built, which the objects
+/// built from it share.
+pub struct Library<'a> {
+    pub textures: BTreeMap<String, Arc<dyn Texture>>,
+    pub materials: BTreeMap<String, Arc<dyn Material>>,
+    resolver: &'a AssetResolver,
+}
+
+impl Library<'_> {
+    /// The material `desc` names, or a new one it defines in place.
+    fn material(
+        &self,
+        entry: &str,
+        desc: &DescRef<MaterialDesc>,
+    ) -> Result<Arc<dyn Material>, SceneError> {
+        match desc {
+            DescRef::Named(name) => {
+                self.materials
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| SceneError::UnknownMaterial {
+                        entry: entry.to_string(),
+                        name: name.clone(),
+                    })
+            }
+            DescRef::Inline(desc) => build_material(
+                &format!("{}.material", entry),
+                desc,
+                &self.textures,
+                self.resolver,
+            ),
+        }
+    }
+
+    fn texture(
+        &self,
+        entry: &str,
+        desc: &DescRef<TextureDesc>,
+    ) -> Result<Arc<dyn Texture>, SceneError> {
+        texture_ref(entry, desc, &self.textures, self.resolver)
+    }
+
+    /// Builds the object `desc`, naming it `entry` in errors.
+    pub fn build_object(
+        &self,
+        entry: &str,
+        desc: &ObjectDesc,
+    ) -> Result<Arc<dyn Hittable>, SceneError> {
+        Ok(match desc {
+            ObjectDesc::Sphere {
+                center,
+                radius,
+                material,
+            } => Arc::new(Sphere::new(
+                *center,
+                *radius,
+                self.material(entry, material)?,
+            )),
+            ObjectDesc::Quad {
+                corner,
+                u,
+                v,
+                material,
+            } => Arc::new(Planar::Parallelogram(Parallelogram::new(
+                *corner,
+                (*u, *v),
+                self.material(entry, material)?,
+            ))),
+            ObjectDesc::Triangle { vertices, material } => {
+                Arc::new(Planar::Triangle(Triangle::new(
+                    (vertices[0], vertices[1], vertices[2]),
+                    self.material(entry, material)?,
+                )))
+            }
+            ObjectDesc::Box { min, max, material } => {
+                parallelepiped(*min, *max, self.material(entry, material)?)
+            }
+            ObjectDesc::Mesh {
+                path,
+                material,
+                transform,
+                normalize,
+                accel,
+                displace,
+            } => {
+                let material = self.material(entry, material)?;
+                let mut mesh = match normalize {
+                    Some(extent) => {
+                        load_model_normalized(path, self.resolver, material, *extent, *transform)
+                            .map(|(mesh, _)| mesh)
+                    }
+                    None => load_model(path, self.resolver, material, *transform),
+                }
+                .map_err(|source| SceneError::Load {
+                    entry: entry.to_string(),
+                    source,
+                })?;
+                if let Some(accel) = accel {
+                    mesh.set_accel(*accel);
+                }
+                log(
+                    Verbosity::Normal,
+                    format_args!("{}: loaded {}: {}", entry, path.display(), mesh),
+                );
+                if let Some(displace) = displace {
+                    let texture = self.texture(entry, &displace.texture)?;
+                    let report =
+                        mesh.displace(texture.as_ref(), displace.scale, displace.max_subdivision);
+                    log(Verbosity::Normal, format_args!("{}: {}", entry, report));
+                    if let Some(source) = &mut mesh.source {
+                        source.displacement = Some(Displacement {
+                            texture,
+                            scale: displace.scale,
+                            max_subdivision: displace.max_subdivision,
+                        });
+                    }
+                }
+                Arc::new(mesh)
+            }
+            ObjectDesc::Heightfield {
+                columns,
+                spacing,
+                heights,
+                material,
+            } => Arc::new(Heightfield::new(
+                *columns,
+                *spacing,
+                heights.clone(),
+                self.material(entry, material)?,
+            )),
+            ObjectDesc::Translate { offset, object } => {
+                let object = self.build_object(&format!("{}.object", entry), object)?;
+                Arc::new(Translation::new(object, *offset))
+            }
+            ObjectDesc::RotateY { angle, object } => {
+                let object = self.build_object(&format!("{}.object", entry), object)?;
+                Arc::new(RotateY::new(object, *angle))
+            }
+            ObjectDesc::Rotate {
+                axis,
+                angle,
+                object,
+            } => {
+                let object = self.build_object(&format!("{}.object", entry), object)?;
+                Arc::new(Rotate::from_axis_angle(object, *axis, *angle))
+            }
+            ObjectDesc::Animate { start, end, object } => {
+                let object = self.build_object(&format!("{}.object", entry), object)?;
+                Arc::new(AnimatedTransform::new(
+                    object,
+                    (*start).into(),
+                    (*end).into(),
+                ))
+            }
+            ObjectDesc::Matte { name, object } => {
+                let object = self.build_object(&format!("{}.object", entry), object)?;
+                Arc::new(Matte::new(name, object))
+            }
+            ObjectDesc::Medium {
+                density,
+                color,
+                texture,
+                boundary,
+            } => {
+                let boundary = self.build_object(&format!("{}.boundary", entry), boundary)?;
+                match (color, texture) {
+                    (Some(color), None) => {
+                        Arc::new(ConstantMedium::from_color(boundary, *density, *color))
+                    }
+                    (None, Some(name)) => Arc::new(ConstantMedium::new(
+                        boundary,
+                        *density,
+                        self.texture(entry, name)?,
+                    )),
+                    _ => {
+                        return Err(SceneError::Invalid {
+                            entry: entry.to_string(),
+                            message: "expected exactly one of a color or a texture".to_string(),
+                        })
+                    }
+                }
+            }
+            ObjectDesc::VoxelMedium {
+                path,
+                density,
+                color,
+                texture,
+                transform,
+            } => {
+                let grid = load_volume(path, self.resolver).map_err(|source| SceneError::Load {
+                    entry: entry.to_string(),
+                    source,
+                })?;
+                let texture = match (color, texture) {
+                    (Some(color), None) => Arc::new(SolidColor::new(*color)),
+                    (None, Some(desc)) => self.texture(entry, desc)?,
+                    _ => {
+                        return Err(SceneError::Invalid {
+                            entry: entry.to_string(),
+                            message: "expected exactly one of a color or a texture".to_string(),
+                        })
+                    }
+                };
+                let transform = Pose::from(transform.unwrap_or_default()).matrix();
+                let medium = VoxelGridMedium::new(Arc::new(grid), *density, texture, transform)
+                    .ok_or_else(|| SceneError::Invalid {
+                        entry: entry.to_string(),
+                        message: "the transform flattens the grid to nothing".to_string(),
+                    })?;
+                Arc::new(medium)
+            }
+            ObjectDesc::Group { bvh, objects } => {
+                let mut group = HittableList::new();
+                for (i, desc) in objects.iter().enumerate() {
+                    group.add_arc(self.build_object(&format!("{}.objects[{}]", entry, i), desc)?);
+                }
+                if *bvh && !group.objects.is_empty() {
+                    Arc::new(BoundNode::from_list(group))
+                } else {
+                    Arc::new(group)
+                }
+            }
+        })
+    }
+}
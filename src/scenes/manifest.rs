@@ -0,0 +1,218 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{scenes::*, BounceLimits, Error, Instant, SamplerKind, StereoSettings};
+
+use serde::Deserialize;
+
+/// A batch of renders, loaded from a JSON or TOML file with a `jobs` list.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RenderManifest {
+    pub jobs: Vec<RenderJob>,
+}
+
+/// One render: a built-in scene or a scene file, optional camera overrides,
+/// and the image to write.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RenderJob {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub scene: Option<String>,
+    #[serde(default)]
+    pub scene_file: Option<PathBuf>,
+    #[serde(default)]
+    pub width: Option<i32>,
+    #[serde(default)]
+    pub samples: Option<i32>,
+    #[serde(default)]
+    pub max_depth: Option<i32>,
+    #[serde(default)]
+    pub bounces: Option<BounceLimits>,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub sampler: Option<SamplerKind>,
+    #[serde(default)]
+    pub blue_noise: Option<bool>,
+    #[serde(default)]
+    pub stereo: Option<StereoSettings>,
+    pub output: PathBuf,
+    /// Where to also write the render's settings as JSON.
+    #[serde(default)]
+    pub sidecar: Option<PathBuf>,
+}
+
+/// How a job went, for the summary printed after a batch.
+pub struct JobReport {
+    pub name: String,
+    pub output: PathBuf,
+    pub elapsed: Duration,
+    pub result: Result<(), Error>,
+}
+
+impl RenderJob {
+    pub fn label(&self, index: usize) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("jobs[{}]", index))
+    }
+
+    fn scene(&self, entry: &str) -> Result<Scene, Error> {
+        match (&self.scene, &self.scene_file) {
+            (Some(name), None) => build(name),
+            (None, Some(path)) => Ok(Scene::from_path(path)?),
+            _ => Err(Error::Scene(SceneError::Invalid {
+                entry: entry.to_string(),
+                message: "exactly one of `scene` or `scene_file` is required".to_string(),
+            })),
+        }
+    }
+
+    /// Builds the scene, applies the overrides, and writes the rendered image.
+    pub fn run(&self, entry: &str) -> Result<(), Error> {
+        let mut scene = self.scene(entry)?;
+        let camera = &mut scene.camera;
+        if let Some(width) = self.width {
+            camera.set_image_width(width);
+        }
+        if let Some(samples) = self.samples {
+            camera.set_aa_samples(samples);
+        }
+        if let Some(max_depth) = self.max_depth {
+            camera.set_max_depth(max_depth);
+        }
+        if let Some(bounces) = self.bounces {
+            camera.set_bounce_limits(bounces);
+        }
+        camera.set_seed(self.seed);
+        if let Some(sampler) = self.sampler {
+            camera.set_sampler(sampler);
+        }
+        if let Some(blue_noise) = self.blue_noise {
+            camera.set_blue_noise(blue_noise);
+        }
+        if let Some(stereo) = self.stereo {
+            camera.set_stereo(Some(stereo));
+        }
+        camera.validate()?;
+
+        let (image, stats) = scene.render_timed();
+        let (image, exposure) = scene.finish_image(image, None);
+        let source = match (&self.scene, &self.scene_file) {
+            (Some(name), _) => name.clone(),
+            (_, Some(path)) => path.display().to_string(),
+            _ => unreachable!("the scene was built from one of them"),
+        };
+        let mut metadata = RenderMetadata::new(source, &scene.camera, &stats);
+        metadata.exposure = exposure;
+        for path in std::iter::once(&self.output).chain(&self.sidecar) {
+            if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                fs::create_dir_all(dir).map_err(|source| Error::Io {
+                    path: dir.to_path_buf(),
+                    source,
+                })?;
+            }
+        }
+        metadata.save_image(&image, &self.output)?;
+        if let Some(sidecar) = &self.sidecar {
+            metadata.save_sidecar(sidecar)?;
+        }
+        Ok(())
+    }
+}
+
+impl RenderManifest {
+    pub fn from_path(path: &Path) -> Result<Self, SceneError> {
+        let text = fs::read_to_string(path).map_err(|source| SceneError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let parse_error = |message: String| SceneError::Parse {
+            path: path.to_path_buf(),
+            message,
+        };
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => serde_json::from_str(&text).map_err(|e| parse_error(e.to_string())),
+            Some("toml") => toml::from_str(&text).map_err(|e| parse_error(e.to_string())),
+            _ => Err(SceneError::UnsupportedFormat(path.to_path_buf())),
+        }
+    }
+
+    /// Renders every job, running up to `parallel_jobs` at once. A failed job
+    /// is reported and doesn't stop the others; reports are in manifest order.
+    pub fn run(&self, parallel_jobs: usize) -> Vec<JobReport> {
+        let next = AtomicUsize::new(0);
+        let reports = Mutex::new(Vec::with_capacity(self.jobs.len()));
+        thread::scope(|scope| {
+            for _ in 0..parallel_jobs.clamp(1, self.jobs.len().max(1)) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(job) = self.jobs.get(index) else {
+                        break;
+                    };
+                    let name = job.label(index);
+                    log(
+                        Verbosity::Normal,
+                        format_args!("rendering {} -> {}", name, job.output.display()),
+                    );
+                    let start = Instant::now();
+                    let result = job.run(&name);
+                    let report = JobReport {
+                        name,
+                        output: job.output.clone(),
+                        elapsed: start.elapsed(),
+                        result,
+                    };
+                    reports.lock().unwrap().push((index, report));
+                });
+            }
+        });
+
+        let mut reports = reports.into_inner().unwrap();
+        reports.sort_by_key(|(index, _)| *index);
+        reports.into_iter().map(|(_, report)| report).collect()
+    }
+}
+
+/// Formats the reports as a table of job names, times, and outcomes.
+pub fn summary_table(reports: &[JobReport]) -> String {
+    let width = reports
+        .iter()
+        .map(|report| report.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("job".len());
+    let mut text = format!("{:<width$}  {:>9}  result", "job", "time");
+    for report in reports {
+        let outcome = match &report.result {
+            Ok(()) => format!("ok ({})", report.output.display()),
+            Err(e) => format!("failed: {}", e),
+        };
+        text += &format!(
+            "\n{:<width$}  {:>8.2}s  {}",
+            report.name,
+            report.elapsed.as_secs_f64(),
+            outcome
+        );
+    }
+    let failed = reports
+        .iter()
+        .filter(|report| report.result.is_err())
+        .count();
+    text += &format!(
+        "\n{} of {} jobs succeeded",
+        reports.len() - failed,
+        reports.len()
+    );
+    text
+}
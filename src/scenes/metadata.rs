@@ -0,0 +1,137 @@
+use std::{fmt, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{read_image_metadata, scenes::CameraBuilder, Camera, Error, ImageBuffer, RenderStats};
+
+/// The settings that produced a render, saved in the image and optionally
+/// beside it as JSON, so that it can be told apart and reproduced later.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RenderMetadata {
+    /// The built-in scene's name or the path of the scene file; empty for
+    /// scenes rendered from code.
+    pub scene: String,
+    pub width: usize,
+    pub height: usize,
+    /// The size the image was rendered at before it was shrunk to `width`
+    /// by `height`, if it was supersampled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub internal_size: Option<(usize, usize)>,
+    /// Samples per pixel.
+    pub samples: i32,
+    pub max_depth: i32,
+    /// The base seed, if the render was seeded.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// The whole camera, as a scene file would describe it.
+    pub camera: CameraBuilder,
+    /// The multiplier the image was exposed with, if it was brightened or
+    /// darkened: the one an automatic exposure chose, to repeat it by hand.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exposure: Option<f64>,
+    /// The crate name and version that rendered the image.
+    pub software: String,
+    /// Seconds the render took.
+    pub wall_time: f64,
+}
+
+impl RenderMetadata {
+    pub fn new(scene: impl Into<String>, camera: &Camera, stats: &RenderStats) -> Self {
+        Self {
+            scene: scene.into(),
+            width: stats.width,
+            height: stats.height,
+            internal_size: (camera.supersample > 1).then(|| {
+                let factor = camera.supersample as usize;
+                (stats.width * factor, stats.height * factor)
+            }),
+            samples: camera.aa_samples,
+            max_depth: camera.max_depth,
+            seed: camera.seed,
+            camera: CameraBuilder::from(camera),
+            exposure: None,
+            software: format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+            wall_time: stats.render_time.as_secs_f64(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("render metadata is always serializable")
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(text)
+    }
+
+    /// Saves `image` to `path` with this metadata embedded; see
+    /// `ImageBuffer::save_with_metadata`.
+    pub fn save_image(&self, image: &ImageBuffer, path: &Path) -> Result<(), Error> {
+        image
+            .save_with_metadata(path, &self.to_json())
+            .map_err(|source| Error::Io {
+                path: path.to_path_buf(),
+                source,
+            })
+    }
+
+    /// Writes the metadata as a JSON sidecar file, e.g. `render.json`.
+    pub fn save_sidecar(&self, path: &Path) -> Result<(), Error> {
+        fs::write(path, self.to_json() + "\n").map_err(|source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// Reads the metadata back from a PNG or PPM saved with it, or from a
+    /// JSON sidecar.
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let io_error = |source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        let text = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => fs::read_to_string(path).map_err(io_error)?,
+            _ => read_image_metadata(path).map_err(io_error)?,
+        };
+        let decode_error = |message: String| Error::Decode {
+            path: path.to_path_buf(),
+            message,
+        };
+        if text.trim().is_empty() {
+            return Err(decode_error("it has no render metadata".to_string()));
+        }
+        Self::from_json(&text).map_err(|e| decode_error(format!("bad render metadata: {}", e)))
+    }
+}
+
+impl fmt::Display for RenderMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.scene.as_str() {
+            "" => writeln!(f, "scene:     built in code")?,
+            scene => writeln!(f, "scene:     {}", scene)?,
+        }
+        writeln!(f, "size:      {} by {}", self.width, self.height)?;
+        if let Some((width, height)) = self.internal_size {
+            writeln!(f, "rendered:  {} by {}, then shrunk", width, height)?;
+        }
+        writeln!(
+            f,
+            "samples:   {} per pixel, max depth {}",
+            self.samples, self.max_depth
+        )?;
+        match self.seed {
+            Some(seed) => writeln!(f, "seed:      {}", seed)?,
+            None => writeln!(f, "seed:      none")?,
+        }
+        writeln!(
+            f,
+            "camera:    from {} toward {}, up {}, {}° field of view",
+            self.camera.look_from, self.camera.look_at, self.camera.up, self.camera.vfov
+        )?;
+        if let Some(exposure) = self.exposure {
+            writeln!(f, "exposure:  {}", exposure)?;
+        }
+        writeln!(f, "software:  {}", self.software)?;
+        write!(f, "wall time: {:.2} s", self.wall_time)
+    }
+}
@@ -0,0 +1,107 @@
+use std::{f64::consts::PI, fmt, sync::Arc};
+
+use crate::{core::*, models::*};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Places `nx` by `nz` objects on a grid in the XZ plane, calling `f` with the
+/// grid indices and the corner of each cell, starting at `origin`.
+pub fn grid_place(
+    origin: Point,
+    nx: usize,
+    nz: usize,
+    spacing: f64,
+    mut f: impl FnMut(usize, usize, Point) -> Arc<dyn Hittable>,
+) -> HittableList {
+    let mut list = HittableList::new();
+    for i in 0..nx {
+        for j in 0..nz {
+            let corner = origin + Vec3(i as f64 * spacing, 0., j as f64 * spacing);
+            list.add_arc(f(i, j, corner));
+        }
+    }
+    list
+}
+
+#[derive(Debug)]
+pub enum PlacementError {
+    /// A region that is empty, inverted, or not finite on some axis.
+    InvalidRegion(BoundingBox),
+}
+
+impl fmt::Display for PlacementError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlacementError::InvalidRegion(region) => {
+                let [x, y, z] = region.intervals;
+                write!(
+                    f,
+                    "can't scatter objects through the region [{}, {}] x [{}, {}] x [{}, {}]",
+                    x.start, x.end, y.start, y.end, z.start, z.end
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for PlacementError {}
+
+/// Scatters up to `count` objects through `region`, keeping their positions at
+/// least `min_distance` apart by rejection. Crowded regions may yield fewer
+/// objects; the result depends only on the arguments and `seed`.
+pub fn scatter_on_box(
+    region: BoundingBox,
+    count: usize,
+    seed: u64,
+    min_distance: f64,
+    mut f: impl FnMut(Point, &mut StdRng) -> Arc<dyn Hittable>,
+) -> Result<HittableList, PlacementError> {
+    const ATTEMPTS_PER_OBJECT: usize = 30;
+
+    let [x, y, z] = region.intervals;
+    if [x, y, z]
+        .iter()
+        .any(|axis| !(axis.start.is_finite() && axis.end.is_finite() && axis.start <= axis.end))
+    {
+        return Err(PlacementError::InvalidRegion(region));
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut placed: Vec<Point> = Vec::with_capacity(count);
+    let mut list = HittableList::new();
+    for _ in 0..count * ATTEMPTS_PER_OBJECT {
+        if placed.len() == count {
+            break;
+        }
+        let candidate = point(
+            rng.gen_range(x.start..=x.end),
+            rng.gen_range(y.start..=y.end),
+            rng.gen_range(z.start..=z.end),
+        );
+        if placed
+            .iter()
+            .any(|&p| (p - candidate).length() < min_distance)
+        {
+            continue;
+        }
+        placed.push(candidate);
+        list.add_arc(f(candidate, &mut rng));
+    }
+    Ok(list)
+}
+
+/// Places `count` objects evenly around a horizontal circle, calling `f` with
+/// each object's index and position.
+pub fn orbit_ring(
+    center: Point,
+    radius: f64,
+    count: usize,
+    mut f: impl FnMut(usize, Point) -> Arc<dyn Hittable>,
+) -> HittableList {
+    let mut list = HittableList::new();
+    for i in 0..count {
+        let angle = 2.0 * PI * i as f64 / count as f64;
+        let position = center + Vec3(radius * angle.cos(), 0., radius * angle.sin());
+        list.add_arc(f(i, position));
+    }
+    list
+}
@@ -0,0 +1,134 @@
+use std::{cmp::Reverse, collections::HashMap, fmt, sync::Arc};
+
+use crate::{models::*, scenes::*};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found in a scene. `object` is a path into the world like the
+/// entries of scene files, e.g. "objects[3].object".
+#[derive(Debug, Clone)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    pub object: String,
+    pub message: String,
+}
+
+impl ValidationIssue {
+    pub fn warning(object: &str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            object: object.to_string(),
+            message: message.into(),
+        }
+    }
+    pub fn error(object: &str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            object: object.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.severity, self.object, self.message)
+    }
+}
+
+/// Checks a bounding box for NaNs and inverted or empty intervals.
+pub fn validate_bounds(bounds: &BoundingBox, object: &str, issues: &mut Vec<ValidationIssue>) {
+    let intervals = bounds.intervals;
+    if intervals.iter().any(|i| i.start.is_nan() || i.end.is_nan()) {
+        issues.push(ValidationIssue::error(object, "bounding box contains NaN"));
+    } else if intervals
+        .iter()
+        .all(|i| i.start == f64::INFINITY && i.end == f64::NEG_INFINITY)
+    {
+        issues.push(ValidationIssue::warning(
+            object,
+            "bounding box is empty, so the object can never be hit",
+        ));
+    } else if intervals.iter().any(|i| i.start > i.end) {
+        issues.push(ValidationIssue::error(object, "bounding box is inverted"));
+    }
+}
+
+/// Reports objects that appear more than once in `objects`, either as the same
+/// shared object or as separate objects with identical descriptions.
+pub fn validate_duplicates(
+    objects: &[(String, Arc<dyn Hittable>)],
+    issues: &mut Vec<ValidationIssue>,
+) {
+    let mut describer = Describer::new();
+    let mut seen: HashMap<String, &str> = HashMap::new();
+    for (path, object) in objects {
+        let key = match describer.object(object.as_ref()) {
+            Some(desc) => serde_json::to_string(&desc).unwrap_or_default(),
+            None => format!("{:p}", Arc::as_ptr(object) as *const ()),
+        };
+        match seen.get(&key) {
+            Some(first) => issues.push(ValidationIssue::warning(
+                path,
+                format!("duplicate of {}", first),
+            )),
+            None => {
+                seen.insert(key, path);
+            }
+        }
+    }
+}
+
+impl Scene {
+    /// Checks the camera and the world for anything that would render incorrectly, most
+    /// severe issues first.
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        if let Err(e) = self.camera.validate() {
+            issues.push(ValidationIssue::error("camera", e.to_string()));
+        }
+        if self.world.objects.is_empty() {
+            issues.push(ValidationIssue::error(
+                "objects",
+                "the world is empty, so the image will only show the background",
+            ));
+        }
+        let objects: Vec<(String, Arc<dyn Hittable>)> = self
+            .world
+            .objects
+            .iter()
+            .enumerate()
+            .map(|(i, object)| (format!("objects[{}]", i), object.clone()))
+            .collect();
+        for (path, object) in &objects {
+            object.validate(path, &mut issues);
+        }
+        validate_duplicates(&objects, &mut issues);
+        for (i, light) in self.lights.delta.iter().enumerate() {
+            light.validate(&format!("lights[{}]", i), &mut issues);
+        }
+        for (i, portal) in self.lights.portals.iter().enumerate() {
+            if !(portal.area() > 0.0 && portal.corner.is_finite()) {
+                issues.push(ValidationIssue::error(
+                    &format!("portals[{}]", i),
+                    "a portal needs a finite corner and sides spanning some area",
+                ));
+            }
+        }
+        issues.sort_by_key(|issue| Reverse(issue.severity));
+        issues
+    }
+}
@@ -1,5 +1,7 @@
+pub mod decoders;
 pub mod materials;
 pub mod textures;
 
+pub use decoders::*;
 pub use materials::*;
 pub use textures::*;
@@ -0,0 +1,57 @@
+use crate::ColorTexture;
+
+#[cfg(feature = "image")]
+use crate::color;
+
+/// Turns the bytes of an encoded image file into a texture. The `image`
+/// feature provides `ImageCrateDecoder` and the `macroquad` feature
+/// `MacroquadDecoder`; without either, only procedural textures are available.
+pub trait ImageDecoder: Send + Sync {
+    /// Decodes `bytes`, guessing the format from them.
+    fn decode(&self, bytes: &[u8]) -> Result<ColorTexture, String>;
+}
+
+/// The decoder image textures are loaded with: the `image` crate's if the
+/// feature is on, otherwise macroquad's, otherwise none.
+pub fn default_decoder() -> Option<&'static dyn ImageDecoder> {
+    DEFAULT_DECODER
+}
+
+#[cfg(feature = "image")]
+const DEFAULT_DECODER: Option<&dyn ImageDecoder> = Some(&ImageCrateDecoder);
+#[cfg(all(feature = "macroquad", not(feature = "image")))]
+const DEFAULT_DECODER: Option<&dyn ImageDecoder> = Some(&MacroquadDecoder);
+#[cfg(not(any(feature = "image", feature = "macroquad")))]
+const DEFAULT_DECODER: Option<&dyn ImageDecoder> = None;
+
+/// Decodes PNG and JPEG files with the `image` crate.
+#[cfg(feature = "image")]
+pub struct ImageCrateDecoder;
+
+#[cfg(feature = "image")]
+impl ImageDecoder for ImageCrateDecoder {
+    fn decode(&self, bytes: &[u8]) -> Result<ColorTexture, String> {
+        let image = image::load_from_memory(bytes)
+            .map_err(|e| e.to_string())?
+            .into_rgb8();
+        let (width, height) = image.dimensions();
+        let data = image
+            .pixels()
+            .map(|p| color(p[0] as f64 / 255., p[1] as f64 / 255., p[2] as f64 / 255.))
+            .collect();
+        Ok(ColorTexture::new(data, width as usize, height as usize))
+    }
+}
+
+/// Decodes PNG files with macroquad, for programs already using it for a window.
+#[cfg(feature = "macroquad")]
+pub struct MacroquadDecoder;
+
+#[cfg(feature = "macroquad")]
+impl ImageDecoder for MacroquadDecoder {
+    fn decode(&self, bytes: &[u8]) -> Result<ColorTexture, String> {
+        let image = macroquad::texture::Image::from_file_with_format(bytes, None)
+            .map_err(|e| e.to_string())?;
+        Ok(ColorTexture::from_image(image))
+    }
+}
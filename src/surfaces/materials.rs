@@ -1,14 +1,90 @@
-use std::sync::Arc;
+use std::{f64::consts::PI, fmt, sync::Arc};
 
-use crate::{color, Color, HitRecord, Ray, SolidColor, Texture, Vec3};
+use rand::Rng;
 
-pub trait Material {
-    fn scatter(&self, _ray: &Ray, _hit: &HitRecord) -> Option<(Ray, Color)> {
+use crate::{
+    color,
+    hittable::short_type_name,
+    scenes::{Describer, MaterialDesc},
+    Color, HitRecord, Ray, Sampler, SolidColor, Texture, Vec3,
+};
+
+/// The kinds of bounce a path can take, each with its own depth limit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BounceKind {
+    /// Off a rough surface, in any direction.
+    Diffuse,
+    /// Off a blurry reflector, near the mirror direction.
+    Glossy,
+    /// Off a perfect mirror.
+    Specular,
+    /// Through a refracting surface.
+    Transmission,
+}
+
+impl fmt::Display for BounceKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BounceKind::Diffuse => write!(f, "diffuse"),
+            BounceKind::Glossy => write!(f, "glossy"),
+            BounceKind::Specular => write!(f, "specular"),
+            BounceKind::Transmission => write!(f, "transmission"),
+        }
+    }
+}
+
+/// A ray leaving a surface, the fraction of the light it brings back that the
+/// surface passes on, and what kind of bounce it took.
+pub struct Scatter {
+    pub ray: Ray,
+    pub attenuation: Color,
+    pub kind: BounceKind,
+}
+
+pub trait Material: Send + Sync {
+    fn scatter(&self, _ray: &Ray, _hit: &HitRecord, _rng: &mut dyn Sampler) -> Option<Scatter> {
         None
     }
     fn emitted(&self, _u: f64, _v: f64, _p: &Vec3) -> Color {
         color(0., 0., 0.)
     }
+    /// The fraction of light arriving along unit `direction` that leaves along
+    /// `ray` back toward its origin, cosine term included. Used to shade delta
+    /// lights, so perfectly specular materials, which could never reflect a
+    /// point into the eye, return black.
+    fn evaluate(&self, _ray: &Ray, _hit: &HitRecord, _direction: Vec3) -> Color {
+        color(0., 0., 0.)
+    }
+    /// The density, per steradian, of `scatter` sending a diffuse bounce
+    /// along unit `direction`, which lets light found by a bounce be weighed
+    /// against the same light sampled directly. `None` for materials that
+    /// `evaluate` can't shade, which are never sampled directly.
+    fn scatter_pdf(&self, _ray: &Ray, _hit: &HitRecord, _direction: Vec3) -> Option<f64> {
+        None
+    }
+    /// The surface's own color at `hit`, apart from its lighting, as a
+    /// denoiser's albedo pass wants it: white for clear glass, and black
+    /// unless a material says otherwise.
+    fn albedo(&self, _hit: &HitRecord) -> Color {
+        color(0., 0., 0.)
+    }
+    /// Whether `emitted` can be nonzero, making surfaces with this material lights.
+    fn is_emissive(&self) -> bool {
+        false
+    }
+    /// Whether the camera sees the surface only by the shadows on it, see
+    /// `ShadowCatcher`.
+    fn is_shadow_catcher(&self) -> bool {
+        false
+    }
+    /// Describes the material for scene export, or `None` if it has no description.
+    fn describe(&self, _describer: &mut Describer) -> Option<MaterialDesc> {
+        None
+    }
+    /// The material's type, for `Hierarchy`.
+    fn kind(&self) -> &'static str {
+        short_type_name::<Self>()
+    }
 }
 
 pub struct Lambertian {
@@ -27,17 +103,42 @@ impl Lambertian {
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _ray: &Ray, hit: &HitRecord) -> Option<(Ray, Color)> {
-        let mut scatter_direction = hit.normal + Vec3::random_unit();
+    fn scatter(&self, _ray: &Ray, hit: &HitRecord, rng: &mut dyn Sampler) -> Option<Scatter> {
+        let mut scatter_direction = hit.normal + Vec3::random_unit(rng);
         if scatter_direction.near_zero() {
             scatter_direction = hit.normal;
         }
-        let scattered = Ray {
-            origin: hit.point,
-            direction: scatter_direction,
-        };
-        let attenuation = self.texture.value(hit.u, hit.v, &hit.point);
-        Some((scattered, attenuation))
+        Some(Scatter {
+            ray: hit.spawn_ray(scatter_direction),
+            attenuation: self.texture.value_at(hit),
+            kind: BounceKind::Diffuse,
+        })
+    }
+    fn evaluate(&self, _ray: &Ray, hit: &HitRecord, direction: Vec3) -> Color {
+        let cosine = Vec3::dot(&hit.normal, &direction);
+        if cosine <= 0.0 {
+            return color(0., 0., 0.);
+        }
+        self.texture.value_at(hit) * (cosine / PI)
+    }
+    fn scatter_pdf(&self, _ray: &Ray, hit: &HitRecord, direction: Vec3) -> Option<f64> {
+        Some(Vec3::dot(&hit.normal, &direction).max(0.0) / PI)
+    }
+    fn albedo(&self, hit: &HitRecord) -> Color {
+        self.texture.value_at(hit)
+    }
+    fn describe(&self, describer: &mut Describer) -> Option<MaterialDesc> {
+        let (albedo, texture) = describer.color_or_texture(&self.texture)?;
+        Some(MaterialDesc::Lambertian { albedo, texture })
+    }
+}
+
+/// A reflection blurred by `fuzz` is glossy, and a sharp one specular.
+fn reflection_kind(fuzz: f64) -> BounceKind {
+    if fuzz > 0.0 {
+        BounceKind::Glossy
+    } else {
+        BounceKind::Specular
     }
 }
 
@@ -57,20 +158,94 @@ impl Metal {
 }
 
 impl Material for Metal {
-    fn scatter(&self, ray: &Ray, hit: &HitRecord) -> Option<(Ray, Color)> {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut dyn Sampler) -> Option<Scatter> {
         let reflected =
-            Vec3::reflect(&ray.direction, &hit.normal).unit() + Vec3::random_unit() * self.fuzz;
-        let scattered = Ray {
-            origin: hit.point,
-            direction: reflected,
-        };
-        let attenuation = self.albedo;
+            Vec3::reflect(&ray.direction, &hit.normal).unit() + Vec3::random_unit(rng) * self.fuzz;
         // if Vec3::dot(&scattered.direction, &hit.normal) > 0.0 {
-        Some((scattered, attenuation))
+        Some(Scatter {
+            ray: hit.spawn_ray(reflected),
+            attenuation: self.albedo,
+            kind: reflection_kind(self.fuzz),
+        })
         // } else {
         // 	None
         // }
     }
+    fn albedo(&self, _hit: &HitRecord) -> Color {
+        self.albedo
+    }
+    fn describe(&self, _describer: &mut Describer) -> Option<MaterialDesc> {
+        Some(MaterialDesc::Metal {
+            albedo: self.albedo,
+            fuzz: self.fuzz,
+        })
+    }
+}
+
+/// A diffuse base under a glossy clear coat, as described by the Kd/Ks/Ns
+/// terms of an MTL file.
+pub struct Plastic {
+    pub texture: Arc<dyn Texture>,
+    // Probability of a bounce off the coat rather than the diffuse base.
+    pub specular: f64,
+    pub roughness: f64,
+}
+
+impl Plastic {
+    pub fn new(texture: Arc<dyn Texture>, specular: f64, roughness: f64) -> Self {
+        Self {
+            texture,
+            specular: specular.clamp(0.0, 1.0),
+            roughness: roughness.clamp(0.0, 1.0),
+        }
+    }
+}
+
+impl Material for Plastic {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut dyn Sampler) -> Option<Scatter> {
+        if rng.gen::<f64>() < self.specular {
+            let reflected = Vec3::reflect(&ray.direction, &hit.normal).unit()
+                + Vec3::random_unit(rng) * self.roughness;
+            Some(Scatter {
+                ray: hit.spawn_ray(reflected),
+                attenuation: color(1.0, 1.0, 1.0),
+                kind: reflection_kind(self.roughness),
+            })
+        } else {
+            let mut scatter_direction = hit.normal + Vec3::random_unit(rng);
+            if scatter_direction.near_zero() {
+                scatter_direction = hit.normal;
+            }
+            Some(Scatter {
+                ray: hit.spawn_ray(scatter_direction),
+                attenuation: self.texture.value_at(hit),
+                kind: BounceKind::Diffuse,
+            })
+        }
+    }
+    /// Only the diffuse base; the coat's highlight would be a firefly.
+    fn evaluate(&self, _ray: &Ray, hit: &HitRecord, direction: Vec3) -> Color {
+        let cosine = Vec3::dot(&hit.normal, &direction);
+        if cosine <= 0.0 {
+            return color(0., 0., 0.);
+        }
+        self.texture.value_at(hit) * ((1.0 - self.specular) * cosine / PI)
+    }
+    fn scatter_pdf(&self, _ray: &Ray, hit: &HitRecord, direction: Vec3) -> Option<f64> {
+        Some((1.0 - self.specular) * Vec3::dot(&hit.normal, &direction).max(0.0) / PI)
+    }
+    fn albedo(&self, hit: &HitRecord) -> Color {
+        self.texture.value_at(hit)
+    }
+    fn describe(&self, describer: &mut Describer) -> Option<MaterialDesc> {
+        let (albedo, texture) = describer.color_or_texture(&self.texture)?;
+        Some(MaterialDesc::Plastic {
+            albedo,
+            texture,
+            specular: self.specular,
+            roughness: self.roughness,
+        })
+    }
 }
 
 pub struct Dielectric {
@@ -91,7 +266,7 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray: &Ray, hit: &HitRecord) -> Option<(Ray, Color)> {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut dyn Sampler) -> Option<Scatter> {
         let attenuation = color(1.0, 1.0, 1.0);
         let refraction_ratio = if hit.front_face {
             1.0 / self.refraction_index
@@ -103,28 +278,81 @@ impl Material for Dielectric {
         let sin_theta = f64::sqrt(1.0 - cos_theta * cos_theta);
 
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
-        if cannot_refract || Dielectric::reflectance(cos_theta, refraction_ratio) > rand::random() {
+        if cannot_refract || Dielectric::reflectance(cos_theta, refraction_ratio) > rng.gen::<f64>()
+        {
             let reflected = Vec3::reflect(&ray.direction.unit(), &hit.normal);
-            let scattered = Ray {
-                origin: hit.point,
-                direction: reflected,
-            };
-            Some((scattered, attenuation))
+            Some(Scatter {
+                ray: hit.spawn_ray(reflected),
+                attenuation,
+                kind: BounceKind::Specular,
+            })
         } else {
             let refracted = Vec3::refract(&ray.direction.unit(), &hit.normal, refraction_ratio);
-            let scattered = Ray {
-                origin: hit.point,
-                direction: refracted,
-            };
-            Some((scattered, attenuation))
+            Some(Scatter {
+                ray: hit.spawn_ray(refracted),
+                attenuation,
+                kind: BounceKind::Transmission,
+            })
         }
     }
+    fn albedo(&self, _hit: &HitRecord) -> Color {
+        color(1.0, 1.0, 1.0)
+    }
+    fn describe(&self, _describer: &mut Describer) -> Option<MaterialDesc> {
+        Some(MaterialDesc::Dielectric {
+            refraction_index: self.refraction_index,
+        })
+    }
+}
+
+/// A stand-in for the ground of a photograph the scene is composited over:
+/// seen from the camera, only the shadows falling on it show, darkening
+/// whatever is behind it, see `Ray::send_from_camera`. Everything else
+/// sees a diffuse surface of `texture`, so it still lights the scene from
+/// below and shows in reflections.
+pub struct ShadowCatcher {
+    pub surface: Lambertian,
+}
+
+impl ShadowCatcher {
+    pub fn new(texture: Arc<dyn Texture>) -> Self {
+        Self {
+            surface: Lambertian::new(texture),
+        }
+    }
+    pub fn from(albedo: Color) -> Self {
+        Self {
+            surface: Lambertian::from(albedo),
+        }
+    }
+}
+
+impl Material for ShadowCatcher {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut dyn Sampler) -> Option<Scatter> {
+        self.surface.scatter(ray, hit, rng)
+    }
+    fn evaluate(&self, ray: &Ray, hit: &HitRecord, direction: Vec3) -> Color {
+        self.surface.evaluate(ray, hit, direction)
+    }
+    fn scatter_pdf(&self, ray: &Ray, hit: &HitRecord, direction: Vec3) -> Option<f64> {
+        self.surface.scatter_pdf(ray, hit, direction)
+    }
+    fn albedo(&self, hit: &HitRecord) -> Color {
+        self.surface.albedo(hit)
+    }
+    fn is_shadow_catcher(&self) -> bool {
+        true
+    }
+    fn describe(&self, describer: &mut Describer) -> Option<MaterialDesc> {
+        let (albedo, texture) = describer.color_or_texture(&self.surface.texture)?;
+        Some(MaterialDesc::ShadowCatcher { albedo, texture })
+    }
 }
 
 pub struct Invisible;
 
 impl Material for Invisible {
-    fn scatter(&self, _ray: &Ray, _hit: &HitRecord) -> Option<(Ray, Color)> {
+    fn scatter(&self, _ray: &Ray, _hit: &HitRecord, _rng: &mut dyn Sampler) -> Option<Scatter> {
         None
     }
 }
@@ -145,9 +373,16 @@ impl DiffuseLight {
 }
 
 impl Material for DiffuseLight {
+    fn is_emissive(&self) -> bool {
+        true
+    }
     fn emitted(&self, u: f64, v: f64, p: &Vec3) -> Color {
         self.texture.value(u, v, p)
     }
+    fn describe(&self, describer: &mut Describer) -> Option<MaterialDesc> {
+        let (color, texture) = describer.color_or_texture(&self.texture)?;
+        Some(MaterialDesc::DiffuseLight { color, texture })
+    }
 }
 
 pub struct Isotropic {
@@ -166,12 +401,24 @@ impl Isotropic {
 }
 
 impl Material for Isotropic {
-    fn scatter(&self, _ray: &Ray, hit: &HitRecord) -> Option<(Ray, Color)> {
-        let scattered = Ray {
-            origin: hit.point,
-            direction: Vec3::random_unit(),
-        };
-        let attenuation = self.texture.value(hit.u, hit.v, &hit.point);
-        Some((scattered, attenuation))
+    fn scatter(&self, _ray: &Ray, hit: &HitRecord, rng: &mut dyn Sampler) -> Option<Scatter> {
+        Some(Scatter {
+            ray: hit.spawn_ray(Vec3::random_unit(rng)),
+            attenuation: self.texture.value_at(hit),
+            kind: BounceKind::Diffuse,
+        })
+    }
+    fn evaluate(&self, _ray: &Ray, hit: &HitRecord, _direction: Vec3) -> Color {
+        self.texture.value_at(hit) / (4.0 * PI)
+    }
+    fn scatter_pdf(&self, _ray: &Ray, _hit: &HitRecord, _direction: Vec3) -> Option<f64> {
+        Some(1.0 / (4.0 * PI))
+    }
+    fn albedo(&self, hit: &HitRecord) -> Color {
+        self.texture.value_at(hit)
+    }
+    fn describe(&self, describer: &mut Describer) -> Option<MaterialDesc> {
+        let (color, texture) = describer.color_or_texture(&self.texture)?;
+        Some(MaterialDesc::Isotropic { color, texture })
     }
 }
@@ -1,11 +1,31 @@
-use macroquad::{prelude::ImageFormat, texture::Image};
+#[cfg(feature = "macroquad")]
+use macroquad::texture::Image;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
 
-use crate::{color, Color, Interval, Point};
+use crate::{
+    color, default_decoder, scenes::TextureDesc, AssetResolver, Color, Error, HitRecord, Interval,
+    Point, Stage, Vec3,
+};
 
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock},
+};
 
-pub trait Texture {
+pub trait Texture: Send + Sync {
     fn value(&self, u: f64, v: f64, p: &Point) -> Color;
+
+    /// Looks the texture up at a hit; textures that need more than (u, v, p) override this.
+    fn value_at(&self, hit: &HitRecord) -> Color {
+        self.value(hit.u, hit.v, &hit.point)
+    }
+
+    /// Describes the texture for scene export, or `None` if it has no description.
+    fn describe(&self) -> Option<TextureDesc> {
+        None
+    }
 }
 
 pub struct SolidColor {
@@ -22,6 +42,34 @@ impl Texture for SolidColor {
     fn value(&self, _u: f64, _v: f64, _p: &Point) -> Color {
         self.color
     }
+    fn describe(&self) -> Option<TextureDesc> {
+        Some(TextureDesc::Solid { color: self.color })
+    }
+}
+
+/// Uses the interpolated vertex colors of a mesh, or `fallback` on surfaces without them.
+pub struct VertexColorTexture {
+    pub fallback: Color,
+}
+
+impl VertexColorTexture {
+    pub fn new(fallback: Color) -> Self {
+        Self { fallback }
+    }
+}
+
+impl Texture for VertexColorTexture {
+    fn value(&self, _u: f64, _v: f64, _p: &Point) -> Color {
+        self.fallback
+    }
+    fn value_at(&self, hit: &HitRecord) -> Color {
+        hit.vertex_color.unwrap_or(self.fallback)
+    }
+    fn describe(&self) -> Option<TextureDesc> {
+        Some(TextureDesc::VertexColor {
+            fallback: self.fallback,
+        })
+    }
 }
 
 pub struct CheckerTexture {
@@ -60,6 +108,19 @@ impl Texture for CheckerTexture {
             self.odd.value(u, v, p)
         }
     }
+    fn describe(&self) -> Option<TextureDesc> {
+        // Only checkers of two solid colors can be written out.
+        match (self.odd.describe()?, self.even.describe()?) {
+            (TextureDesc::Solid { color: odd }, TextureDesc::Solid { color: even }) => {
+                Some(TextureDesc::Checker {
+                    scale: 1.0 / self.inv_scale,
+                    odd,
+                    even,
+                })
+            }
+            _ => None,
+        }
+    }
 }
 
 pub struct ColorTexture {
@@ -76,6 +137,7 @@ impl ColorTexture {
             height,
         }
     }
+    #[cfg(feature = "macroquad")]
     pub fn from_image(image: Image) -> Self {
         Self {
             width: image.width as usize,
@@ -87,9 +149,12 @@ impl ColorTexture {
                 .collect(),
         }
     }
-    pub fn from_file(file: &[u8], format: Option<ImageFormat>) -> Self {
-        let image = Image::from_file_with_format(file, format).unwrap();
-        Self::from_image(image)
+    /// Decodes an encoded image with the `default_decoder`, guessing its format.
+    pub fn from_file(file: &[u8]) -> Result<Self, String> {
+        let decoder = default_decoder().ok_or_else(|| {
+            "this build can't decode images; enable the `image` or `macroquad` feature".to_string()
+        })?;
+        decoder.decode(file)
     }
 }
 
@@ -103,21 +168,24 @@ impl Texture for ColorTexture {
     }
 }
 
+#[cfg(feature = "macroquad")]
 pub struct ImageTexture {
     pub image: Image,
 }
 
+#[cfg(feature = "macroquad")]
 impl ImageTexture {
     pub fn new(image: Image) -> Self {
         Self { image }
     }
-    pub fn from_file(file: &[u8], format: Option<ImageFormat>) -> Self {
-        Self {
-            image: Image::from_file_with_format(file, format).unwrap(),
-        }
+    pub fn from_file(file: &[u8]) -> Result<Self, String> {
+        Ok(Self {
+            image: Image::from_file_with_format(file, None).map_err(|e| e.to_string())?,
+        })
     }
 }
 
+#[cfg(feature = "macroquad")]
 impl Texture for ImageTexture {
     fn value(&self, u: f64, v: f64, _p: &Point) -> Color {
         let u = Interval::new(0., 1.).clamp(u);
@@ -132,3 +200,193 @@ impl Texture for ImageTexture {
         color(c.r as f64, c.g as f64, c.b as f64)
     }
 }
+
+pub struct Perlin {
+    gradients: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Perlin {
+    const POINT_COUNT: usize = 256;
+
+    pub fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let gradients = (0..Self::POINT_COUNT)
+            .map(|_| {
+                Vec3(
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                )
+                .unit()
+            })
+            .collect();
+        let permutation = |rng: &mut StdRng| {
+            let mut p: Vec<usize> = (0..Self::POINT_COUNT).collect();
+            p.shuffle(rng);
+            p
+        };
+        Self {
+            gradients,
+            perm_x: permutation(&mut rng),
+            perm_y: permutation(&mut rng),
+            perm_z: permutation(&mut rng),
+        }
+    }
+
+    pub fn noise(&self, p: &Point) -> f64 {
+        let u = p.x() - p.x().floor();
+        let v = p.y() - p.y().floor();
+        let w = p.z() - p.z().floor();
+        let i = p.x().floor() as i64;
+        let j = p.y().floor() as i64;
+        let k = p.z().floor() as i64;
+
+        // Hermite smoothing of the trilinear weights.
+        let uu = u * u * (3.0 - 2.0 * u);
+        let vv = v * v * (3.0 - 2.0 * v);
+        let ww = w * w * (3.0 - 2.0 * w);
+
+        let mask = Self::POINT_COUNT as i64 - 1;
+        let mut accumulated = 0.0;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let index = self.perm_x[((i + di) & mask) as usize]
+                        ^ self.perm_y[((j + dj) & mask) as usize]
+                        ^ self.perm_z[((k + dk) & mask) as usize];
+                    let (fi, fj, fk) = (di as f64, dj as f64, dk as f64);
+                    let weight = Vec3(u - fi, v - fj, w - fk);
+                    accumulated += (fi * uu + (1.0 - fi) * (1.0 - uu))
+                        * (fj * vv + (1.0 - fj) * (1.0 - vv))
+                        * (fk * ww + (1.0 - fk) * (1.0 - ww))
+                        * Vec3::dot(&self.gradients[index], &weight);
+                }
+            }
+        }
+        accumulated
+    }
+
+    pub fn turbulence(&self, p: &Point, depth: usize) -> f64 {
+        let mut accumulated = 0.0;
+        let mut p = *p;
+        let mut weight = 1.0;
+        for _ in 0..depth {
+            accumulated += weight * self.noise(&p);
+            weight *= 0.5;
+            p = p * 2.0;
+        }
+        accumulated.abs()
+    }
+}
+
+pub struct NoiseTexture {
+    pub noise: Perlin,
+    pub scale: f64,
+}
+
+impl NoiseTexture {
+    pub fn new(scale: f64) -> Self {
+        Self {
+            noise: Perlin::new(0),
+            scale,
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f64, _v: f64, p: &Point) -> Color {
+        // Marble-like veins: a sine along z phase-shifted by turbulence.
+        let phase = self.scale * p.z() + 10.0 * self.noise.turbulence(p, 7);
+        color(0.5, 0.5, 0.5) * (1.0 + phase.sin())
+    }
+    fn describe(&self) -> Option<TextureDesc> {
+        Some(TextureDesc::Noise { scale: self.scale })
+    }
+}
+
+/// The path a texture was first registered under, and the texture.
+type RegistryEntry = (PathBuf, Arc<dyn Texture>);
+
+/// Shares image textures by path so each file is decoded once per process.
+#[derive(Default)]
+pub struct TextureRegistry {
+    // Keyed by canonical path.
+    textures: Mutex<HashMap<PathBuf, RegistryEntry>>,
+}
+
+impl TextureRegistry {
+    pub fn global() -> &'static TextureRegistry {
+        static REGISTRY: OnceLock<TextureRegistry> = OnceLock::new();
+        REGISTRY.get_or_init(TextureRegistry::default)
+    }
+
+    fn key(path: &Path) -> PathBuf {
+        path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+    }
+
+    /// Returns the texture registered for `path`, creating it with `create` on first use.
+    pub fn get_or_insert_with(
+        &self,
+        path: &Path,
+        create: impl FnOnce() -> Arc<dyn Texture>,
+    ) -> Arc<dyn Texture> {
+        let mut textures = self.textures.lock().unwrap();
+        textures
+            .entry(Self::key(path))
+            .or_insert_with(|| (path.to_path_buf(), create()))
+            .1
+            .clone()
+    }
+
+    /// Like `get_or_insert_with`, for loaders that can fail.
+    pub fn try_get_or_insert_with<E>(
+        &self,
+        path: &Path,
+        load: impl FnOnce() -> Result<Arc<dyn Texture>, E>,
+    ) -> Result<Arc<dyn Texture>, E> {
+        let key = Self::key(path);
+        if let Some((_, texture)) = self.textures.lock().unwrap().get(&key) {
+            return Ok(texture.clone());
+        }
+        let texture = load()?;
+        self.textures
+            .lock()
+            .unwrap()
+            .insert(key, (path.to_path_buf(), texture.clone()));
+        Ok(texture)
+    }
+
+    /// Finds the path a registered texture was loaded from.
+    pub fn path_of(&self, texture: &Arc<dyn Texture>) -> Option<PathBuf> {
+        self.textures
+            .lock()
+            .unwrap()
+            .values()
+            .find(|(_, registered)| Arc::ptr_eq(registered, texture))
+            .map(|(path, _)| path.clone())
+    }
+
+    /// Loads the image `reference` points to, found with `resolver`.
+    pub fn load(
+        &self,
+        reference: &Path,
+        resolver: &AssetResolver,
+    ) -> Result<Arc<dyn Texture>, Error> {
+        let path = resolver.resolve(reference)?;
+        self.try_get_or_insert_with(&path, || {
+            let _stage = Stage::start("load assets");
+            let bytes = fs::read(&path).map_err(|source| Error::Io {
+                path: path.clone(),
+                source,
+            })?;
+            let texture = ColorTexture::from_file(&bytes).map_err(|message| Error::Decode {
+                path: path.clone(),
+                message,
+            })?;
+            Ok(Arc::new(texture) as Arc<dyn Texture>)
+        })
+    }
+}
@@ -0,0 +1,90 @@
+//! JavaScript bindings for rendering in the browser, built with
+//! `wasm-pack`. Everything renders on the calling thread into memory; run it
+//! in a worker to keep the page responsive. See examples/wasm.
+
+use js_sys::Function;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    color,
+    scenes::{self, Scene, SceneDesc},
+};
+
+/// A scene to render from JavaScript.
+#[wasm_bindgen]
+pub struct WebScene {
+    scene: Scene,
+}
+
+#[wasm_bindgen]
+impl WebScene {
+    /// Builds the built-in scene called `name`, e.g. "material_spheres".
+    #[wasm_bindgen(constructor)]
+    pub fn new(name: &str) -> Result<WebScene, JsError> {
+        let scene = scenes::build(name).map_err(|e| JsError::new(&e.to_string()))?;
+        Ok(Self { scene })
+    }
+
+    /// Builds a scene from the text of a TOML scene file. Asset paths can't
+    /// be loaded, as there is no filesystem to load them from.
+    #[wasm_bindgen(js_name = fromToml)]
+    pub fn from_toml(text: &str) -> Result<WebScene, JsError> {
+        let desc: SceneDesc = toml::from_str(text).map_err(|e| JsError::new(&e.to_string()))?;
+        let scene = desc.build().map_err(|e| JsError::new(&e.to_string()))?;
+        Ok(Self { scene })
+    }
+
+    /// The names of the built-in scenes.
+    #[wasm_bindgen(js_name = builtIn)]
+    pub fn built_in() -> Vec<String> {
+        scenes::list()
+            .iter()
+            .map(|entry| entry.name.to_string())
+            .collect()
+    }
+
+    /// Sets the image width, keeping the aspect ratio, and the samples per pixel.
+    #[wasm_bindgen(js_name = setSize)]
+    pub fn set_size(&mut self, width: i32, samples: i32) {
+        self.scene
+            .camera
+            .set_image_width(width.max(1))
+            .set_aa_samples(samples.max(1));
+    }
+
+    /// Sets the color of rays that escape the scene, e.g. a sky for scenes
+    /// without lights of their own.
+    #[wasm_bindgen(js_name = setBackground)]
+    pub fn set_background(&mut self, r: f64, g: f64, b: f64) {
+        self.scene.camera.set_background(color(r, g, b));
+    }
+
+    /// Fixes the random seed so that renders repeat exactly.
+    #[wasm_bindgen(js_name = setSeed)]
+    pub fn set_seed(&mut self, seed: u32) {
+        self.scene.camera.set_seed(Some(seed as u64));
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> usize {
+        self.scene.camera.image_width as usize
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> usize {
+        self.scene.camera.image_height() as usize
+    }
+
+    /// Renders the scene as RGBA bytes for an `ImageData`, calling
+    /// `progress(rowsDone, rows)` after each row if given.
+    #[wasm_bindgen(js_name = renderRgba)]
+    pub fn render_rgba(&self, progress: Option<Function>) -> Vec<u8> {
+        let image = self.scene.render_image_with_progress(&mut |done, rows| {
+            if let Some(progress) = &progress {
+                // A throwing callback shouldn't stop the render.
+                let _ = progress.call2(&JsValue::NULL, &done.into(), &rows.into());
+            }
+        });
+        image.to_rgba8()
+    }
+}
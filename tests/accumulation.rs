@@ -0,0 +1,129 @@
+//! Accumulating samples across renders: the file format, fingerprints that
+//! keep different scenes apart, and merges that match one longer render.
+
+use ray_tracer::{
+    color,
+    scenes::{self, Scene},
+    Accumulation, AccumulationError, ImageBuffer, StereoMode, StereoSettings,
+};
+
+fn tiny(samples: i32, seed: u64) -> Scene {
+    let mut scene = scenes::cornell_box();
+    scene
+        .camera
+        .set_image_width(12)
+        .set_aa_samples(samples)
+        .set_max_depth(4)
+        .set_seed(Some(seed));
+    scene
+}
+
+fn accumulate(scene: &Scene) -> Accumulation {
+    let mut accumulation = scene.new_accumulation();
+    scene.accumulate(&mut accumulation).unwrap();
+    accumulation
+}
+
+fn mean_squared_error(image: &ImageBuffer, reference: &ImageBuffer) -> f64 {
+    let report = image.diff(reference).unwrap();
+    report.rms_error * report.rms_error
+}
+
+#[test]
+fn files_round_trip_exactly() {
+    let accumulation = accumulate(&tiny(2, 5));
+    let mut bytes = Vec::new();
+    accumulation.write(&mut bytes).unwrap();
+    assert_eq!(bytes.len(), 8 + 4 + 4 + 8 + 12 * 12 * (6 * 8 + 4));
+    let read = Accumulation::read(bytes.as_slice()).unwrap();
+    assert_eq!(
+        (read.width, read.height, read.fingerprint),
+        (12, 12, accumulation.fingerprint)
+    );
+    assert_eq!(read.samples, accumulation.samples);
+    assert!(read
+        .sums
+        .iter()
+        .zip(&accumulation.sums)
+        .all(|(a, b)| (a.0, a.1, a.2) == (b.0, b.1, b.2)));
+
+    let error = Accumulation::read(&b"P3\n1 1\n255\n0 0 0\n"[..]).expect_err("a PPM");
+    assert!(matches!(error, AccumulationError::NotAnAccumulation));
+}
+
+#[test]
+fn only_renders_of_the_same_scene_merge() {
+    let a = accumulate(&tiny(1, 1));
+    // The seed and sample count don't change what the render converges to.
+    assert_eq!(a.fingerprint, tiny(3, 2).fingerprint());
+
+    let mut lit = tiny(1, 1);
+    lit.camera.set_background(color(0.1, 0.1, 0.1));
+    let mut wider = tiny(1, 1);
+    wider.camera.set_image_width(13);
+    for other in [lit, wider, scenes::cornell_glass()] {
+        assert_ne!(a.fingerprint, other.fingerprint());
+    }
+
+    let mut lit = tiny(1, 1);
+    lit.camera.set_background(color(0.1, 0.1, 0.1));
+    let error = Accumulation::merge(&[a.clone(), accumulate(&lit)]).expect_err("lit differently");
+    assert!(matches!(
+        error,
+        AccumulationError::FingerprintMismatch { .. }
+    ));
+    assert!(error
+        .to_string()
+        .starts_with("the renders are of different"));
+    assert!(matches!(
+        Accumulation::merge(&[]),
+        Err(AccumulationError::NothingToMerge)
+    ));
+}
+
+#[test]
+fn continuing_with_one_seed_is_one_longer_render() {
+    let scene = tiny(3, 9);
+    let mut accumulation = accumulate(&scene);
+    scene.accumulate(&mut accumulation).unwrap();
+    assert_eq!(accumulation.min_samples(), 6);
+
+    let whole = tiny(6, 9).render_image();
+    let report = accumulation.image().diff(&whole).unwrap();
+    assert!(report.max_error < 1e-12, "{}", report);
+}
+
+#[test]
+fn two_merged_halves_are_as_good_as_one_render() {
+    let reference = tiny(512, 100).render_image();
+    let n = 8;
+    let (mut merged, mut single, mut half) = (0.0, 0.0, 0.0);
+    for trial in 0..8 {
+        let (a, b, c) = (3 * trial + 1, 3 * trial + 2, 3 * trial + 3);
+        let both =
+            Accumulation::merge(&[accumulate(&tiny(n, a)), accumulate(&tiny(n, b))]).unwrap();
+        assert_eq!(both.min_samples(), 2 * n as u32);
+        merged += mean_squared_error(&both.image(), &reference);
+        single += mean_squared_error(&tiny(2 * n, c).render_image(), &reference);
+        half += mean_squared_error(&tiny(n, c).render_image(), &reference);
+    }
+    // The error falls as one over the samples, however they were split up.
+    let ratio = merged / single;
+    assert!((0.75..1.33).contains(&ratio), "merged / single: {}", ratio);
+    let ratio = merged / half;
+    assert!((0.35..0.7).contains(&ratio), "merged / half: {}", ratio);
+}
+
+#[test]
+fn stereo_renders_are_refused() {
+    let mut scene = tiny(1, 1);
+    let mut accumulation = scene.new_accumulation();
+    scene.camera.set_stereo(Some(StereoSettings {
+        ipd: 0.1,
+        mode: StereoMode::Anaglyph,
+        converge: false,
+    }));
+    let error = scene.accumulate(&mut accumulation).expect_err("stereo");
+    assert!(matches!(error, AccumulationError::Stereo));
+    assert_eq!(accumulation.min_samples(), 0);
+}
@@ -0,0 +1,80 @@
+//! Adaptive sampling: pixels stop once they converge, between the fewest
+//! and most samples allowed, and every way of rendering agrees on it.
+
+use ray_tracer::{
+    scenes::{self, Scene},
+    AdaptiveSampling, CameraError,
+};
+
+fn cornell(adaptive: Option<AdaptiveSampling>, threads: usize) -> Scene {
+    let mut scene = scenes::cornell_box();
+    scene
+        .camera
+        .set_image_width(24)
+        .set_aa_samples(8)
+        .set_max_depth(4)
+        .set_seed(Some(3))
+        .set_threads(threads)
+        .set_adaptive(adaptive);
+    scene
+}
+
+#[test]
+fn pixels_take_between_the_fewest_and_most_samples() {
+    let adaptive = AdaptiveSampling::new(0.2, 4, 64);
+    let scene = cornell(Some(adaptive), 1);
+    let accumulation = scene.render_accumulation().unwrap();
+    let fewest = *accumulation.samples.iter().min().unwrap();
+    let most = *accumulation.samples.iter().max().unwrap();
+    assert!(fewest >= 4, "{}", fewest);
+    assert_eq!(most, 64);
+    // The black outside of the box converges at once; the lit walls don't.
+    assert!(fewest < most);
+
+    let (image, stats) = scene.render_timed();
+    assert_eq!(stats.samples, 64);
+    let mean = stats.mean_samples.unwrap();
+    assert!(mean > 4.0 && mean < 64.0, "{}", mean);
+    assert!(stats
+        .to_string()
+        .contains(&format!("{:.1} samples per pixel on average", mean)));
+
+    // The image is the same however it is rendered.
+    let same = |a: &ray_tracer::ImageBuffer, b: &ray_tracer::ImageBuffer| {
+        a.pixels
+            .iter()
+            .zip(&b.pixels)
+            .all(|(a, b)| (a.0, a.1, a.2) == (b.0, b.1, b.2))
+    };
+    assert!(same(&image, &scene.render_image()));
+    assert!(same(&image, &cornell(Some(adaptive), 3).render_image()));
+
+    assert_eq!(cornell(None, 1).render_timed().1.mean_samples, None);
+}
+
+#[test]
+fn a_tighter_tolerance_takes_more_samples() {
+    let mean = |tolerance| {
+        let scene = cornell(Some(AdaptiveSampling::new(tolerance, 4, 256)), 1);
+        scene.render_accumulation().unwrap().mean_samples()
+    };
+    assert!(mean(0.05) > mean(0.5));
+}
+
+#[test]
+fn impossible_settings_are_rejected() {
+    for adaptive in [
+        AdaptiveSampling::new(0.0, 4, 16),
+        AdaptiveSampling::new(f64::NAN, 4, 16),
+        AdaptiveSampling::new(0.1, 1, 16),
+        AdaptiveSampling::new(0.1, 32, 16),
+    ] {
+        let scene = cornell(Some(adaptive), 1);
+        match scene.camera.validate() {
+            Err(CameraError::InvalidAdaptive(rejected)) => {
+                assert_eq!(format!("{:?}", rejected), format!("{:?}", adaptive))
+            }
+            other => panic!("expected InvalidAdaptive, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,96 @@
+//! Ray tests on planar shapes run for every ray and every bounce, so they
+//! must not touch the heap.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    sync::Arc,
+};
+
+use ray_tracer::{
+    color, point, Hittable, IndependentSampler, Interval, Lambertian, Parallelogram, Plane, Ray,
+    Triangle, Vec3,
+};
+
+/// Counts allocations made on the current thread, so tests running in
+/// parallel don't see each other's.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations_during(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+fn rays() -> Vec<Ray> {
+    [
+        Vec3(0.2, 0.3, -1.),
+        Vec3(3., 0., -1.),
+        Vec3(1., 0., 0.),
+        Vec3(0., 0., 1.),
+    ]
+    .into_iter()
+    .map(|direction| Ray {
+        origin: point(0., 0., 5.),
+        direction,
+        time: 0.0,
+    })
+    .collect()
+}
+
+fn assert_no_allocations(shape: &dyn Hittable) {
+    let rays = rays();
+    let mut rng = IndependentSampler::new(0);
+    // The first hit may initialise shared state; only steady state counts.
+    for ray in &rays {
+        shape.hit(ray, Interval::new(0.001, f64::INFINITY), &mut rng);
+    }
+    let count = allocations_during(|| {
+        for _ in 0..1000 {
+            for ray in &rays {
+                std::hint::black_box(shape.hit(ray, Interval::new(0.001, f64::INFINITY), &mut rng));
+            }
+        }
+    });
+    assert_eq!(count, 0, "{} allocations during ray tests", count);
+}
+
+#[test]
+fn parallelogram_hit_does_not_allocate() {
+    assert_no_allocations(&Parallelogram::new(
+        point(-1., -1., 0.),
+        (Vec3(2., 0., 0.), Vec3(0., 2., 0.)),
+        Arc::new(Lambertian::from(color(0.5, 0.5, 0.5))),
+    ));
+}
+
+#[test]
+fn triangle_hit_does_not_allocate() {
+    assert_no_allocations(&Triangle::new(
+        (point(-1., -1., 0.), point(1., -1., 0.), point(0., 1., 0.)),
+        Arc::new(Lambertian::from(color(0.5, 0.5, 0.5))),
+    ));
+}
+
+#[test]
+fn plane_hit_does_not_allocate() {
+    assert_no_allocations(&Plane::new(point(0., 0., 0.), Vec3(0., 0., 1.)));
+}
@@ -0,0 +1,283 @@
+//! Transparent renders: an alpha channel where the scene doesn't cover the
+//! image, shadow catchers that show only the shadows on them, and
+//! compositing over a backplate.
+
+use std::{fs, sync::Arc};
+
+use ray_tracer::{
+    color, point,
+    scenes::{self, Scene},
+    Accumulation, Camera, DirectionalLight, Environment, HittableList, ImageBuffer,
+    IndependentSampler, Lambertian, LightList, Parallelogram, Point, Ray, RaySample, ShadowCatcher,
+    SolidBackground, Sphere, Vec3,
+};
+
+const EPSILON: f64 = 1e-9;
+
+/// A unit sphere resting on a shadow-catching floor at y = 0.
+fn world() -> HittableList {
+    let mut world = HittableList::new();
+    world.add(Parallelogram::new(
+        point(-50., 0., -50.),
+        (Vec3(100., 0., 0.), Vec3(0., 0., 100.)),
+        Arc::new(ShadowCatcher::from(color(0.5, 0.5, 0.5))),
+    ));
+    world.add(Sphere::new(
+        point(0., 1., 0.),
+        1.,
+        Arc::new(Lambertian::from(color(0.5, 0.5, 0.5))),
+    ));
+    world
+}
+
+/// A white sky, the same in every direction.
+fn white_sky() -> Environment {
+    let mut sky = ImageBuffer::new(1, 1);
+    sky.set(0, 0, color(1., 1., 1.));
+    Environment::new(sky)
+}
+
+/// What a camera low in front of the floor at `at` sees there.
+fn look(
+    world: &HittableList,
+    lights: &LightList,
+    at: Point,
+    background: Vec3,
+    transparent: bool,
+    seed: u64,
+) -> RaySample {
+    let ray = Ray {
+        origin: at + Vec3(0., 0.5, 5.),
+        direction: Vec3(0., -0.5, -5.),
+        time: 0.0,
+    };
+    let mut rng = IndependentSampler::new(seed);
+    ray.send_from_camera(
+        world,
+        lights,
+        4,
+        &SolidBackground(background),
+        EPSILON,
+        transparent,
+        &mut rng,
+    )
+}
+
+/// The sphere scene seen from the front, small enough to render quickly.
+fn tiny(transparent: bool) -> Scene {
+    let mut camera = Camera::new(
+        1.0,
+        12,
+        40.,
+        point(0., 3., 7.),
+        point(0., 0.8, 0.),
+        Vec3(0., 1., 0.),
+        4,
+        4,
+    );
+    camera.set_seed(Some(2)).set_transparent(transparent);
+    let mut scene = Scene::new(camera, world());
+    scene.set_environment(white_sky());
+    scene
+}
+
+#[test]
+fn only_what_the_scene_covers_is_opaque() {
+    let mut lights = LightList::new();
+    lights.environment = Some(Arc::new(white_sky()));
+    let sphere = Sphere::new(
+        point(0., 0., 0.),
+        1.,
+        Arc::new(Lambertian::from(color(0.5, 0.5, 0.5))),
+    );
+    let mut world = HittableList::new();
+    world.add(sphere);
+    let send = |direction: Vec3, transparent: bool| {
+        let ray = Ray {
+            origin: point(0., 0., 5.),
+            direction,
+            time: 0.0,
+        };
+        let mut rng = IndependentSampler::new(4);
+        let sky = color(0.2, 0.3, 0.4);
+        ray.send_from_camera(
+            &world,
+            &lights,
+            8,
+            &SolidBackground(sky),
+            EPSILON,
+            transparent,
+            &mut rng,
+        )
+    };
+
+    // A miss is nothing at all, unless the render is opaque.
+    let miss = send(Vec3(0., 1., -1.), true);
+    assert_eq!(
+        (miss.color.0, miss.color.1, miss.color.2, miss.alpha),
+        (0., 0., 0., 0.)
+    );
+    let opaque = send(Vec3(0., 1., -1.), false);
+    assert_eq!((opaque.color.0, opaque.color.1, opaque.alpha), (1., 1., 1.));
+
+    // A hit is fully covered, and lit the same either way: only the sky
+    // seen directly is left out.
+    let (hit, lit) = (
+        send(Vec3(0., 0., -1.), true),
+        send(Vec3(0., 0., -1.), false),
+    );
+    assert_eq!((hit.alpha, lit.alpha), (1., 1.));
+    assert_eq!(hit.color.0, lit.color.0);
+    assert!(hit.color.0 > 0.0, "{:?}", hit);
+}
+
+#[test]
+fn shadow_catchers_show_only_their_shadows() {
+    let world = world();
+    let mut lights = LightList::new();
+    lights.delta.push(Arc::new(DirectionalLight::new(
+        Vec3(0., -1., 0.),
+        color(2., 2., 2.),
+    )));
+    let background = color(0.2, 0.3, 0.4);
+
+    // Under the sphere, the shadow is black and opaque; out from under it,
+    // the floor is clear.
+    let shadow = look(&world, &lights, point(0., 0., 0.9), background, true, 0);
+    assert_eq!((shadow.color.0, shadow.color.1, shadow.alpha), (0., 0., 1.));
+    let clear = look(&world, &lights, point(3., 0., 0.), background, true, 0);
+    assert_eq!((clear.color.0, clear.alpha), (0., 0.));
+
+    // Rendered opaque, the catcher shows the background, darkened by the
+    // same shadows.
+    let shadow = look(&world, &lights, point(0., 0., 0.9), background, false, 0);
+    assert_eq!((shadow.color.2, shadow.alpha), (0., 1.));
+    let clear = look(&world, &lights, point(3., 0., 0.), background, false, 0);
+    assert_eq!(
+        (clear.color.0, clear.color.1, clear.color.2),
+        (0.2, 0.3, 0.4)
+    );
+
+    // A catcher under no light at all casts no shadow either.
+    let dark = look(
+        &world,
+        &LightList::new(),
+        point(0., 0., 0.9),
+        background,
+        true,
+        0,
+    );
+    assert_eq!(dark.alpha, 0.);
+}
+
+#[test]
+fn soft_shadows_gather_where_objects_meet_the_catcher() {
+    let world = world();
+    let mut lights = LightList::new();
+    lights.environment = Some(Arc::new(white_sky()));
+    let background = color(0., 0., 0.);
+    let alpha = |at: Point| {
+        (0..2000)
+            .map(|seed| look(&world, &lights, at, background, true, seed).alpha)
+            .sum::<f64>()
+            / 2000.0
+    };
+    let contact = alpha(point(0., 0., 0.5));
+    let near = alpha(point(0., 0., 2.5));
+    let far = alpha(point(8., 0., 0.));
+    assert!(contact > 0.3, "{}", contact);
+    assert!(near < contact && near > far, "{} {} {}", contact, near, far);
+    assert!(far < 0.02, "{}", far);
+}
+
+#[test]
+fn renders_composite_over_a_backplate() {
+    let scene = tiny(true);
+    let image = scene.render_image();
+    let alpha = image.alpha.clone().expect("transparent renders have alpha");
+    assert!(alpha.contains(&1.0));
+    assert!(alpha.iter().all(|&a| (0.0..=1.0).contains(&a)));
+
+    // Every way of rendering finds the same alpha.
+    let accumulation = scene.render_accumulation().unwrap();
+    assert_eq!(accumulation.image().alpha.as_ref(), Some(&alpha));
+    let progressive = scene.render_progressive(&mut |_| {});
+    assert_eq!(progressive.alpha.as_ref(), Some(&alpha));
+    assert!(tiny(false).render_image().alpha.is_none());
+
+    // Over a plate of any size, stretched to fit: the plate shows through
+    // as much as the alpha leaves uncovered.
+    let mut plate = ImageBuffer::new(3, 2);
+    for pixel in &mut plate.pixels {
+        *pixel = color(0.25, 0.5, 1.0);
+    }
+    let composite = image.over(&plate);
+    assert!(composite.alpha.is_none());
+    for (i, pixel) in composite.pixels.iter().enumerate() {
+        let expected = image.pixels[i].2 + (1.0 - alpha[i]);
+        assert!(
+            (pixel.2 - expected).abs() < 1e-12,
+            "{} {}",
+            pixel.2,
+            expected
+        );
+    }
+    let opaque = tiny(false).render_image();
+    assert_eq!(opaque.over(&plate).pixels[5].0, opaque.pixels[5].0);
+
+    // The demo scene brings its own plate.
+    let demo = scenes::build("backplate").unwrap();
+    assert!(demo.camera.transparent);
+    #[cfg(feature = "image")]
+    assert_eq!(demo.backplate.map(|plate| plate.width), Some(1024));
+}
+
+#[test]
+fn alpha_survives_accumulation_and_scene_files() {
+    let scene = tiny(true);
+    let accumulation = scene.render_accumulation().unwrap();
+    let mut bytes = Vec::new();
+    accumulation.write(&mut bytes).unwrap();
+    let read = Accumulation::read(bytes.as_slice()).unwrap();
+    assert_eq!(read.alphas, accumulation.alphas);
+    let mut merged = read.clone();
+    merged.add(&accumulation).unwrap();
+    assert_eq!(merged.image().alpha, accumulation.image().alpha);
+
+    // Transparency is part of the camera, and catchers part of the world,
+    // so a saved scene renders the same.
+    let scene = Scene::new(scene.camera.clone(), world());
+    let dir = std::env::temp_dir().join(format!("ray-tracer-alpha-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("scene.json");
+    scene.describe().unwrap().save(&path).unwrap();
+    let loaded = Scene::from_path(&path);
+    fs::remove_dir_all(&dir).unwrap();
+    let loaded = loaded.unwrap();
+    assert!(loaded.camera.transparent);
+    assert_eq!(loaded.fingerprint(), scene.fingerprint());
+    let mut opaque = scene.camera.clone();
+    opaque.set_transparent(false);
+    assert_ne!(
+        Scene::new(opaque, world()).fingerprint(),
+        scene.fingerprint()
+    );
+
+    #[cfg(feature = "image")]
+    {
+        let image = scene.render_image();
+        let mut png = Vec::new();
+        image.write_png(&mut png, "").unwrap();
+        let mut reader = png::Decoder::new(std::io::Cursor::new(png))
+            .read_info()
+            .unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!(info.color_type, png::ColorType::Rgba);
+        assert_eq!(&pixels[..info.buffer_size()], image.to_rgba8().as_slice());
+        let alpha = image.alpha.unwrap();
+        for (i, &a) in alpha.iter().enumerate() {
+            assert_eq!(pixels[4 * i + 3], (a * 255.0).round() as u8);
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! AOV passes: the normal, distance, and albedo of what each pixel sees
+//! first, rendered along with the image from the same rays.
+
+use std::{fs, sync::Arc};
+
+use ray_tracer::{
+    color, point, scenes::Scene, Camera, Dielectric, HittableList, Lambertian, Sphere, Vec3,
+};
+
+/// A red ball 5 units in front of a camera looking down -z, with a glass
+/// ball beside it, filling the middle of a 15 by 15 image.
+fn scene() -> Scene {
+    let mut camera = Camera::new(
+        1.0,
+        15,
+        40.,
+        point(0., 0., 0.),
+        point(0., 0., -1.),
+        Vec3(0., 1., 0.),
+        4,
+        4,
+    );
+    camera
+        .set_seed(Some(9))
+        .set_background(color(0.5, 0.7, 1.0));
+    let mut world = HittableList::new();
+    world.add(Sphere::new(
+        point(0., 0., -6.),
+        1.,
+        Arc::new(Lambertian::from(color(0.8, 0.1, 0.1))),
+    ));
+    world.add(Sphere::new(
+        point(1.6, 0., -6.),
+        0.5,
+        Arc::new(Dielectric::new(1.5)),
+    ));
+    Scene::new(camera, world)
+}
+
+fn close(a: Vec3, b: Vec3) -> bool {
+    (a - b).length() < 0.05
+}
+
+#[test]
+fn passes_describe_the_first_hits() {
+    let scene = scene();
+    let (image, aovs) = scene.render_with_aovs();
+    let at = |x: usize, y: usize| y * aovs.width + x;
+
+    // The middle of the red ball faces the camera, 5 units away.
+    let middle = at(7, 7);
+    assert!(close(aovs.normal[middle], Vec3(0., 0., 1.)));
+    assert!((aovs.depth[middle] - 5.).abs() < 0.05);
+    assert_eq!(aovs.coverage[middle], 1.0);
+    assert!(close(aovs.albedo[middle], color(0.8, 0.1, 0.1)));
+
+    // Glass is white, and the corners see nothing.
+    let glass = at(12, 7);
+    assert!(close(aovs.albedo[glass], color(1., 1., 1.)));
+    let corner = at(0, 0);
+    assert_eq!(aovs.coverage[corner], 0.0);
+    assert!(close(aovs.normal[corner], Vec3(0., 0., 0.)));
+    assert_eq!(aovs.depth[corner], f64::INFINITY);
+    assert!(close(aovs.albedo[corner], color(0., 0., 0.)));
+
+    // The image is the one `render_image` makes.
+    let plain = scene.render_image();
+    assert!(image
+        .pixels
+        .iter()
+        .zip(&plain.pixels)
+        .all(|(a, b)| (a.0, a.1, a.2) == (b.0, b.1, b.2)));
+
+    // Depth maps near to black and far, and nothing, to white.
+    let depth = aovs.depth_image(Some(4.0..6.0));
+    assert!((depth.get(7, 7).0 - 0.25).abs() < 0.05);
+    assert!(close(depth.get(0, 0), Vec3(1., 1., 1.)));
+    let normal = aovs.normal_image();
+    assert!(close(normal.get(7, 7), Vec3(0.25, 0.25, 1.)));
+}
+
+#[test]
+fn depth_is_mapped_between_the_clipping_planes() {
+    let mut scene = scene();
+    scene.camera.set_clip(4.0, 100.0);
+    let (_, mut aovs) = scene.render_with_aovs();
+    let farthest = aovs
+        .depth
+        .iter()
+        .zip(&aovs.coverage)
+        .filter(|(_, &coverage)| coverage > 0.0)
+        .map(|(&depth, _)| depth)
+        .fold(0.0, f64::max);
+    assert!(farthest > 5.0 && farthest < 100.0);
+
+    // From the near plane to the farthest hit, which is nearer than the far
+    // plane.
+    let near = (5.0 - 4.0) / (farthest - 4.0);
+    let depth = aovs.depth_image(None);
+    assert!((depth.get(7, 7).0 - near * near).abs() < 0.02);
+
+    // To the far plane, when it's nearer than the farthest hit.
+    aovs.far_clip = 6.0;
+    let depth = aovs.depth_image(None);
+    assert!((depth.get(7, 7).0 - 0.25).abs() < 0.02);
+}
+
+#[test]
+fn passes_are_saved_beside_the_image() {
+    let dir = std::env::temp_dir().join(format!("ray-tracer-aovs-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let (_, aovs) = scene().render_with_aovs();
+    // PPMs, which every build can write.
+    let paths = aovs.save(&dir.join("render.ppm"), None).unwrap();
+    let names: Vec<_> = paths
+        .iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(
+        names,
+        ["render.normal.ppm", "render.depth.ppm", "render.albedo.ppm"]
+    );
+    assert!(paths.iter().all(|path| path.exists()));
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,299 @@
+//! Defocus blur: uniform disk, polygon, and image-mask lens samples, rays
+//! that meet again on the focus plane, and the aperture settings in scene
+//! files.
+
+use std::sync::Arc;
+
+use rand::{rngs::SmallRng, SeedableRng};
+use ray_tracer::{
+    color, point, scenes, ApertureShape, Camera, CameraError, ColorTexture, IndependentSampler,
+    Ray, RegularPolygon, Sampler, Vec3,
+};
+
+/// Bins `count` points drawn by `sample` into a grid over [-1, 1]² and checks
+/// every cell wholly inside `inside` holds its share of the `area`.
+fn assert_uniform(
+    sample: impl Fn(&mut SmallRng) -> Vec3,
+    inside: impl Fn(Vec3) -> bool,
+    area: f64,
+) {
+    const CELLS: usize = 16;
+    const COUNT: usize = 400_000;
+    let cell = 2.0 / CELLS as f64;
+    let mut rng = SmallRng::seed_from_u64(11);
+    let mut bins = vec![0u32; CELLS * CELLS];
+    for _ in 0..COUNT {
+        let p = sample(&mut rng);
+        assert!(inside(p), "{:?} fell outside", p);
+        let x = (((p.0 + 1.0) / cell) as usize).min(CELLS - 1);
+        let y = (((p.1 + 1.0) / cell) as usize).min(CELLS - 1);
+        bins[y * CELLS + x] += 1;
+    }
+
+    let expected = COUNT as f64 * cell * cell / area;
+    let mut checked = 0;
+    for y in 0..CELLS {
+        for x in 0..CELLS {
+            let corner = |dx: usize, dy: usize| {
+                Vec3(
+                    -1.0 + (x + dx) as f64 * cell,
+                    -1.0 + (y + dy) as f64 * cell,
+                    0.0,
+                )
+            };
+            if ![(0, 0), (1, 0), (0, 1), (1, 1)]
+                .iter()
+                .all(|&(dx, dy)| inside(corner(dx, dy)))
+            {
+                continue;
+            }
+            checked += 1;
+            let got = bins[y * CELLS + x] as f64;
+            assert!(
+                (got - expected).abs() < 5.0 * expected.sqrt(),
+                "cell ({}, {}) holds {} points, expected about {:.0}",
+                x,
+                y,
+                got,
+                expected
+            );
+        }
+    }
+    assert!(
+        checked as f64 > 0.5 * area / (cell * cell),
+        "only {} cells were inside",
+        checked
+    );
+}
+
+#[test]
+fn disk_samples_are_uniform() {
+    assert_uniform(
+        Vec3::random_in_unit_disk,
+        |p| p.length() <= 1.0 + 1e-12,
+        std::f64::consts::PI,
+    );
+}
+
+#[test]
+fn round_apertures_draw_the_unit_disk() {
+    let (mut a, mut b) = (SmallRng::seed_from_u64(4), SmallRng::seed_from_u64(4));
+    for _ in 0..100 {
+        let (lens, disk) = (
+            ApertureShape::Circle.random(&mut a),
+            Vec3::random_in_unit_disk(&mut b),
+        );
+        assert!((lens - disk).length() < 1e-12, "{:?} {:?}", lens, disk);
+    }
+}
+
+#[test]
+fn polygon_samples_are_uniform() {
+    for (sides, rotation) in [(3, 90.0), (5, 0.0), (6, 15.0), (9, 7.0)] {
+        let polygon = RegularPolygon::new(sides, rotation);
+        assert_uniform(
+            |rng| polygon.random(rng),
+            |p| polygon.contains(p),
+            polygon.area(),
+        );
+    }
+}
+
+/// Whether `p` is on the cross that `cross_mask` leaves open.
+fn on_cross(p: Vec3) -> bool {
+    p.0.abs() <= 0.25 || p.1.abs() <= 0.25
+}
+
+/// A 16 by 16 mask, open on a cross of bars a quarter of the lens wide
+/// through its center.
+fn cross_mask() -> ApertureShape {
+    let data = (0..16 * 16)
+        .map(|i| {
+            let bar = |n: usize| (6..10).contains(&n);
+            let open = if bar(i % 16) || bar(i / 16) { 1.0 } else { 0.0 };
+            color(open, open, open)
+        })
+        .collect();
+    ApertureShape::image(Arc::new(ColorTexture::new(data, 16, 16)))
+}
+
+#[test]
+fn mask_samples_stay_inside_the_mask_and_are_uniform() {
+    let cross = cross_mask();
+    assert_uniform(|rng| cross.random(rng), on_cross, 1.75);
+}
+
+#[test]
+fn pinhole_masks_are_drawn_from_without_falling_back() {
+    // One open texel in 4096, the top row's last: too few for rejection to
+    // find, but drawn straight from the mask's distribution.
+    let mut data = vec![color(0., 0., 0.); 64 * 64];
+    data[63] = color(1., 1., 1.);
+    let pinhole = ApertureShape::image(Arc::new(ColorTexture::new(data, 64, 64)));
+    let mut rng = SmallRng::seed_from_u64(2);
+    for _ in 0..1000 {
+        let p = pinhole.random(&mut rng);
+        assert!(
+            p.0 >= 1.0 - 2.0 / 64.0 && p.1 >= 1.0 - 2.0 / 64.0,
+            "{:?}",
+            p
+        );
+    }
+}
+
+#[test]
+fn closed_masks_fall_back_to_the_center() {
+    let closed = ApertureShape::image(Arc::new(ColorTexture::new(vec![color(0., 0., 0.)], 1, 1)));
+    assert!(closed.sample(0.3, 0.7).length() < 1e-12);
+}
+
+#[test]
+fn image_apertures_shape_the_lens() {
+    let (focus_dist, angle): (f64, f64) = (3.0, 10.0);
+    let radius = focus_dist * (angle / 2.0).to_radians().tan();
+    let mut blurred = camera();
+    blurred
+        .set_defocus(angle, Some(focus_dist))
+        .set_aperture_shape(cross_mask());
+    assert!(blurred.validate().is_ok());
+    for sample in 0..200 {
+        let mut sampler = IndependentSampler::for_sample(3, 8, 8, sample);
+        let ray = blurred.sample_ray(8, 8, &mut sampler);
+        let lens = (ray.origin - point(0., 0., 5.)) / radius;
+        assert!(on_cross(lens), "{:?} is off the lens", lens);
+        // However many points the mask tried, the lens took two draws.
+        assert_eq!(sampler.dimension(), 5);
+    }
+
+    let mut scene = scenes::build("bokeh").unwrap();
+    scene.camera.set_aperture_shape(cross_mask());
+    assert!(scene.describe().is_err());
+}
+
+#[test]
+fn polygon_corners_are_reached() {
+    let polygon = RegularPolygon::new(6, 15.0);
+    assert!(polygon.sample(0.0, 0.0).length() < 1e-12);
+    let corner = polygon.sample(1.0 / 6.0 - 1e-12, 1.0 - 1e-12);
+    assert!((corner - polygon.vertex(1)).length() < 1e-5);
+    assert!(
+        (polygon.vertex(0) - Vec3(15f64.to_radians().cos(), 15f64.to_radians().sin(), 0.0))
+            .length()
+            < 1e-12
+    );
+    assert!((polygon.area() - 1.5 * 3f64.sqrt()).abs() < 1e-12);
+}
+
+fn camera() -> Camera {
+    Camera::new(
+        1.0,
+        32,
+        40.,
+        point(0., 0., 5.),
+        point(0., 0., 0.),
+        Vec3(0., 1., 0.),
+        1,
+        10,
+    )
+}
+
+/// Where `ray` crosses the plane z = `z`.
+fn crossing(ray: &Ray, z: f64) -> Vec3 {
+    ray.at((z - ray.origin.2) / ray.direction.2)
+}
+
+#[test]
+fn zero_angle_is_a_pinhole() {
+    let pinhole = camera();
+    let mut focused = camera();
+    focused
+        .set_defocus(0.0, Some(3.0))
+        .set_aperture(Some(6), 0.0);
+    for sample in 0..16 {
+        let a = pinhole.sample_ray(5, 9, &mut IndependentSampler::for_sample(1, 5, 9, sample));
+        let b = focused.sample_ray(5, 9, &mut IndependentSampler::for_sample(1, 5, 9, sample));
+        assert!((b.origin - point(0., 0., 5.)).length() < 1e-12);
+        assert!((a.direction.unit() - b.direction.unit()).length() < 1e-12);
+    }
+}
+
+#[test]
+fn lens_rays_meet_on_the_focus_plane() {
+    let pinhole = camera();
+    let focus_dist = 3.0;
+    let angle: f64 = 10.0;
+    let radius = focus_dist * (angle / 2.0).to_radians().tan();
+    let polygon = RegularPolygon::new(5, 30.0);
+    let mut blurred = camera();
+    blurred
+        .set_defocus(angle, Some(focus_dist))
+        .set_aperture(Some(polygon.sides), polygon.rotation);
+    assert!(blurred.validate().is_ok());
+
+    let plane = 5.0 - focus_dist;
+    let mut spread: f64 = 0.0;
+    for sample in 0..200 {
+        let a = pinhole.sample_ray(20, 3, &mut IndependentSampler::for_sample(2, 20, 3, sample));
+        let b = blurred.sample_ray(20, 3, &mut IndependentSampler::for_sample(2, 20, 3, sample));
+
+        // The camera looks down -z with +x right and +y up, so the lens
+        // offset is the polygon point scaled by the lens radius.
+        let lens = (b.origin - point(0., 0., 5.)) / radius;
+        assert!(lens.2.abs() < 1e-12);
+        assert!(polygon.contains(lens), "{:?} is off the lens", lens);
+        spread = spread.max(lens.length());
+
+        assert!((crossing(&a, plane) - crossing(&b, plane)).length() < 1e-9);
+        assert!((crossing(&a, -5.0) - crossing(&b, -5.0)).length() > 0.0);
+    }
+    assert!(spread > 0.8, "lens samples stayed near the center");
+}
+
+#[test]
+fn invalid_defocus_settings_are_rejected() {
+    let mut camera = camera();
+    camera.set_aperture(Some(2), 0.0);
+    assert!(matches!(
+        camera.validate(),
+        Err(CameraError::InvalidApertureBlades(2))
+    ));
+
+    let mut camera = self::camera();
+    camera.set_defocus(180.0, None);
+    assert!(matches!(
+        camera.validate(),
+        Err(CameraError::InvalidDefocusAngle(_))
+    ));
+
+    let mut camera = self::camera();
+    camera.set_defocus(2.0, Some(0.0));
+    assert!(matches!(
+        camera.validate(),
+        Err(CameraError::InvalidFocusDistance(_))
+    ));
+}
+
+#[test]
+fn aperture_is_saved_with_the_camera() {
+    let scene = scenes::build("bokeh").unwrap();
+    let ApertureShape::Polygon { blades, rotation } = scene.camera.aperture else {
+        panic!("expected a polygon, got {:?}", scene.camera.aperture);
+    };
+    assert_eq!(blades, 6);
+    let desc = scene.describe().unwrap();
+    let text = toml::to_string(&desc).unwrap();
+    assert!(text.contains("aperture_blades = 6"), "{}", text);
+    let loaded = desc.build().unwrap().camera;
+    assert_eq!(loaded.defocus_angle, scene.camera.defocus_angle);
+    assert_eq!(loaded.focus_dist, scene.camera.focus_dist);
+    assert!(
+        matches!(loaded.aperture, ApertureShape::Polygon { blades: 6, rotation: r } if r == rotation),
+        "{:?}",
+        loaded.aperture
+    );
+
+    // Pinhole cameras don't mention the lens.
+    let plain = scenes::build("cornell_box").unwrap().describe().unwrap();
+    let text = toml::to_string(&plain).unwrap();
+    assert!(!text.contains("defocus") && !text.contains("aperture"));
+}
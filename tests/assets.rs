@@ -0,0 +1,53 @@
+//! Scene files find their assets relative to their own directory, then along
+//! the search path, and say where they looked when an asset is missing.
+
+use std::path::{Path, PathBuf};
+
+use ray_tracer::{scenes::Scene, AssetResolver, Hittable};
+
+const SCENE: &str = "tests/fixtures/scenes/tetrahedron.toml";
+const MESH: &str = "assets/tetrahedron.obj";
+
+#[test]
+fn assets_resolve_relative_to_the_scene_file() {
+    // Neither the working directory nor the crate root has the mesh.
+    assert!(!Path::new(MESH).exists());
+    let scene = Scene::from_path(Path::new(SCENE)).unwrap_or_else(|e| panic!("{}", e));
+    assert_eq!(scene.world.primitive_count(), 4);
+
+    let resolver = AssetResolver::for_scene_file(Path::new(SCENE));
+    assert_eq!(
+        resolver.resolve(Path::new(MESH)).unwrap(),
+        Path::new("tests/fixtures/scenes").join(MESH)
+    );
+}
+
+#[test]
+fn assets_resolve_along_the_search_path() {
+    let mut resolver = AssetResolver::new();
+    resolver
+        .search_path
+        .insert(0, PathBuf::from("tests/fixtures/scenes"));
+    assert_eq!(
+        resolver.resolve(Path::new(MESH)).unwrap(),
+        Path::new("tests/fixtures/scenes").join(MESH)
+    );
+}
+
+#[test]
+fn missing_assets_list_every_location_tried() {
+    let resolver = AssetResolver::for_scene_file(Path::new(SCENE));
+    let error = resolver
+        .resolve(Path::new("assets/missing.obj"))
+        .unwrap_err();
+    assert_eq!(
+        error.tried[0],
+        Path::new("tests/fixtures/scenes/assets/missing.obj")
+    );
+    let crate_root = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/missing.obj");
+    assert_eq!(error.tried.last(), Some(&crate_root));
+    let message = error.to_string();
+    for path in &error.tried {
+        assert!(message.contains(&path.display().to_string()), "{}", message);
+    }
+}
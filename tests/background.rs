@@ -0,0 +1,111 @@
+//! Backgrounds: the light rays that leave the scene see, evaluated per
+//! direction, and how scene files give them.
+
+use ray_tracer::{
+    color,
+    scenes::{BackgroundDesc, Scene, SceneDesc},
+    Background, Camera, Color, GradientSky, Ray, Vec3,
+};
+
+fn looking(direction: Vec3) -> Ray {
+    Ray {
+        origin: Vec3(0.0, 0.0, 0.0),
+        direction,
+        time: 0.0,
+    }
+}
+
+fn near(a: Color, b: Color) -> bool {
+    (a - b).length() < 1e-9
+}
+
+#[test]
+fn gradient_skies_blend_by_height() {
+    let (bottom, top) = (color(1.0, 1.0, 1.0), color(0.5, 0.7, 1.0));
+    let sky = GradientSky::new(bottom, top);
+    assert!(near(sky.value(&looking(Vec3(0.0, 3.0, 0.0))), top));
+    assert!(near(sky.value(&looking(Vec3(0.0, -1.0, 0.0))), bottom));
+    let horizon = (bottom + top) * 0.5;
+    for direction in [Vec3(1.0, 0.0, 0.0), Vec3(0.0, 0.0, -1.0)] {
+        assert!(near(sky.value(&looking(direction)), horizon));
+    }
+    // Evaluated per ray rather than baked into rows, so nearby directions
+    // still differ.
+    let (a, b) = (
+        sky.value(&looking(Vec3(1.0, 0.3, 0.0))),
+        sky.value(&looking(Vec3(1.0, 0.3001, 0.0))),
+    );
+    assert!(a.0 != b.0);
+}
+
+#[test]
+fn cameras_default_to_a_black_background() {
+    let background = Camera::default().background;
+    assert!(near(
+        background.value(&looking(Vec3(0.0, 1.0, 0.0))),
+        color(0.0, 0.0, 0.0)
+    ));
+    assert!(matches!(
+        background.describe(),
+        Some(BackgroundDesc::Solid(c)) if near(c, color(0.0, 0.0, 0.0))
+    ));
+}
+
+/// The scene `background` describes, with a square camera.
+fn load(background: &str) -> Scene {
+    let text = format!(
+        "background = {}\nobjects = []\n[camera]\naspect_ratio = 1.0\n",
+        background
+    );
+    toml::from_str::<SceneDesc>(&text).unwrap().build().unwrap()
+}
+
+#[test]
+fn scene_files_give_solid_and_gradient_backgrounds() {
+    let solid = load("[0.2, 0.3, 0.4]");
+    assert!(near(
+        solid
+            .camera
+            .background
+            .value(&looking(Vec3(0.0, -1.0, 0.0))),
+        color(0.2, 0.3, 0.4)
+    ));
+    let gradient = load("{ bottom = [1, 1, 1], top = [0.5, 0.7, 1] }");
+    let up = gradient
+        .camera
+        .background
+        .value(&looking(Vec3(0.0, 1.0, 0.0)));
+    assert!(near(up, color(0.5, 0.7, 1.0)));
+    // And they are described back as given.
+    let again = gradient.describe().unwrap().build().unwrap();
+    assert!(near(
+        again
+            .camera
+            .background
+            .value(&looking(Vec3(0.0, -1.0, 0.0))),
+        color(1.0, 1.0, 1.0)
+    ));
+}
+
+struct Checkered;
+
+impl Background for Checkered {
+    fn value(&self, ray: &Ray) -> Color {
+        if ray.direction.x() > 0.0 {
+            color(1.0, 1.0, 1.0)
+        } else {
+            color(0.0, 0.0, 0.0)
+        }
+    }
+}
+
+#[test]
+fn backgrounds_without_a_description_cannot_be_exported() {
+    let mut scene = load("[0, 0, 0]");
+    scene
+        .camera
+        .set_background_arc(std::sync::Arc::new(Checkered));
+    assert!(scene.describe().is_err());
+    scene.camera.set_background(color(0.1, 0.1, 0.1));
+    assert!(scene.describe().is_ok());
+}
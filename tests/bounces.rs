@@ -0,0 +1,257 @@
+//! Per-kind bounce limits: which kind of bounce each material reports, and how
+//! the limits end paths without changing anything while they're out of reach.
+
+use std::sync::Arc;
+
+use ray_tracer::{
+    color, point, scenes, BounceKind, BounceLimits, Camera, CameraError, Color, Dielectric,
+    HitRecord, Hittable, HittableList, IndependentSampler, Interval, Isotropic, Lambertian,
+    LightList, Material, Metal, Parallelogram, PathDepth, Plastic, Ray, Sampler, SolidBackground,
+    SolidColor, Sphere, Vec3,
+};
+
+const EPSILON: f64 = 1e-9;
+
+/// The kinds of bounce `material` reports for a ray arriving head on at a
+/// surface facing +y, over many draws.
+fn kinds(material: &dyn Material, front_face: bool) -> Vec<BounceKind> {
+    let ray = Ray {
+        origin: point(0.3, 1., 0.),
+        direction: Vec3(-0.3, -1., 0.),
+        time: 0.0,
+    };
+    let normal = if front_face {
+        Vec3(0., 1., 0.)
+    } else {
+        Vec3(0., -1., 0.)
+    };
+    let hit = HitRecord::new(&ray, 1., point(0., 0., 0.), normal, material);
+    let mut kinds = Vec::new();
+    for seed in 0..200 {
+        let mut sampler = IndependentSampler::new(seed);
+        let kind = material.scatter(&ray, &hit, &mut sampler).unwrap().kind;
+        if !kinds.contains(&kind) {
+            kinds.push(kind);
+        }
+    }
+    kinds
+}
+
+#[test]
+fn materials_report_their_bounce_kind() {
+    let gray = color(0.5, 0.5, 0.5);
+    assert_eq!(kinds(&Lambertian::from(gray), true), [BounceKind::Diffuse]);
+    assert_eq!(kinds(&Isotropic::from(gray), true), [BounceKind::Diffuse]);
+    assert_eq!(kinds(&Metal::new(gray, 0.), true), [BounceKind::Specular]);
+    assert_eq!(kinds(&Metal::new(gray, 0.3), true), [BounceKind::Glossy]);
+
+    let plastic = Plastic::new(Arc::new(SolidColor::new(gray)), 0.5, 0.2);
+    let mut plastic = kinds(&plastic, true);
+    plastic.sort_by_key(|kind| *kind as u8);
+    assert_eq!(plastic, [BounceKind::Diffuse, BounceKind::Glossy]);
+
+    // Mostly refracted from outside, with the odd Fresnel reflection.
+    let mut glass = kinds(&Dielectric::new(1.5), true);
+    glass.sort_by_key(|kind| *kind as u8);
+    assert_eq!(glass, [BounceKind::Specular, BounceKind::Transmission]);
+}
+
+/// A diffuse floor with a glass ball on it, under a sky.
+fn world() -> HittableList {
+    let mut world = HittableList::new();
+    world.add(Parallelogram::new(
+        point(-50., 0., -50.),
+        (Vec3(100., 0., 0.), Vec3(0., 0., 100.)),
+        Arc::new(Lambertian::from(color(0.7, 0.6, 0.5))),
+    ));
+    world.add(Sphere::new(
+        point(0., 1., 0.),
+        1.,
+        Arc::new(Dielectric::new(1.5)),
+    ));
+    world.add(Sphere::new(
+        point(2.5, 1., 0.),
+        1.,
+        Arc::new(Metal::new(color(0.8, 0.8, 0.8), 0.2)),
+    ));
+    world
+}
+
+fn sky() -> Color {
+    color(0.7, 0.8, 1.0)
+}
+
+fn toward(target: Vec3) -> Ray {
+    let origin = point(0., 1., 6.);
+    Ray {
+        origin,
+        direction: target - origin,
+        time: 0.0,
+    }
+}
+
+fn send(ray: &Ray, depth: impl Into<PathDepth>, seed: u64) -> Color {
+    let mut sampler = IndependentSampler::new(seed);
+    ray.send(
+        &world(),
+        &LightList::new(),
+        depth,
+        &SolidBackground(sky()),
+        EPSILON,
+        &mut sampler,
+    )
+}
+
+/// The path tracer as it used to be written, one call per bounce.
+fn send_recursively(
+    ray: &Ray,
+    world: &HittableList,
+    depth: i32,
+    sampler: &mut dyn Sampler,
+) -> Color {
+    if depth <= 0 {
+        return color(0., 0., 0.);
+    }
+    let Some(record) = world.hit(ray, Interval::new(EPSILON, f64::INFINITY), sampler) else {
+        return sky();
+    };
+    let emitted = record.material.emitted(record.u, record.v, &record.point);
+    match record.material.scatter(ray, &record, sampler) {
+        Some(scatter) => {
+            emitted
+                + scatter.attenuation * send_recursively(&scatter.ray, world, depth - 1, sampler)
+        }
+        None => emitted,
+    }
+}
+
+fn assert_close(a: Color, b: Color) {
+    assert!((a - b).length() < 1e-12, "{:?} != {:?}", a, b);
+}
+
+#[test]
+fn iterative_paths_match_recursive_ones() {
+    let world = world();
+    for seed in 0..100 {
+        let ray = toward(point(seed as f64 / 25. - 1., 0.5, 0.));
+        let mut sampler = IndependentSampler::new(seed);
+        let expected = send_recursively(&ray, &world, 8, &mut sampler);
+        assert_close(send(&ray, 8, seed), expected);
+    }
+}
+
+#[test]
+fn limits_out_of_reach_change_nothing() {
+    let reachable = PathDepth {
+        max_depth: 6,
+        bounces: BounceLimits {
+            diffuse: Some(6),
+            glossy: Some(10),
+            specular: Some(6),
+            transmission: Some(6),
+        },
+        roulette_depth: None,
+    };
+    for seed in 0..100 {
+        let ray = toward(point(seed as f64 / 25. - 1., 0.5, 0.));
+        assert_close(send(&ray, reachable, seed), send(&ray, 6, seed));
+    }
+}
+
+fn limited(bounces: BounceLimits) -> PathDepth {
+    PathDepth {
+        max_depth: 20,
+        bounces,
+        roulette_depth: None,
+    }
+}
+
+#[test]
+fn diffuse_limit_ends_paths_at_rough_surfaces() {
+    // Straight down at the floor beside the ball: its only light is the sky
+    // it scatters toward.
+    let floor = Ray {
+        origin: point(-3., 5., 3.),
+        direction: Vec3(0., -1., 0.),
+        time: 0.0,
+    };
+    let no_diffuse = limited(BounceLimits {
+        diffuse: Some(0),
+        ..BounceLimits::default()
+    });
+    for seed in 0..20 {
+        assert_close(send(&floor, no_diffuse, seed), color(0., 0., 0.));
+        assert!(send(&floor, 20, seed).length() > 0.);
+    }
+}
+
+#[test]
+fn glass_stays_clear_with_diffuse_bounces_capped() {
+    // Through the middle of the ball and out the far side to the sky.
+    let through = toward(point(0., 1., 0.));
+    let capped = limited(BounceLimits {
+        diffuse: Some(0),
+        ..BounceLimits::default()
+    });
+    let one_refraction = limited(BounceLimits {
+        transmission: Some(1),
+        ..BounceLimits::default()
+    });
+    let (mut clear, mut blocked) = (0, 0);
+    for seed in 0..50 {
+        if (send(&through, capped, seed) - sky()).length() < 1e-9 {
+            clear += 1;
+        }
+        // Leaving the ball takes a second refraction; only paths reflected
+        // off its front still reach the sky.
+        if send(&through, one_refraction, seed).near_zero() {
+            blocked += 1;
+        }
+    }
+    assert!(clear > 45, "only {} of 50 paths passed through", clear);
+    assert!(blocked > 45, "{} of 50 paths left the ball", 50 - blocked);
+}
+
+fn camera() -> Camera {
+    Camera::new(
+        1.0,
+        16,
+        40.,
+        point(0., 1., 6.),
+        point(0., 1., 0.),
+        Vec3(0., 1., 0.),
+        1,
+        10,
+    )
+}
+
+#[test]
+fn negative_limits_are_rejected() {
+    let mut camera = camera();
+    camera.set_bounce_limits(BounceLimits {
+        glossy: Some(-1),
+        ..BounceLimits::default()
+    });
+    match camera.validate() {
+        Err(CameraError::InvalidBounceLimit { kind, limit }) => {
+            assert_eq!(kind, BounceKind::Glossy);
+            assert_eq!(limit, -1);
+        }
+        other => panic!("expected a bounce limit error, got {:?}", other),
+    }
+}
+
+#[test]
+fn limits_are_saved_with_the_camera() {
+    let scene = scenes::build("cornell_glass").unwrap();
+    assert_eq!(scene.camera.bounce_limits.diffuse, Some(4));
+    let desc = scene.describe().unwrap();
+    let text = toml::to_string(&desc).unwrap();
+    assert!(text.contains("[camera.bounces]"), "{}", text);
+    let loaded = desc.build().unwrap();
+    assert_eq!(loaded.camera.bounce_limits, scene.camera.bounce_limits);
+
+    // Scenes without limits don't mention them.
+    let plain = scenes::build("cornell_box").unwrap().describe().unwrap();
+    assert!(!toml::to_string(&plain).unwrap().contains("bounces"));
+}
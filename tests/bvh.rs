@@ -0,0 +1,190 @@
+//! The BVH must return exactly the hit a brute-force search of the same
+//! objects finds. Scenes and rays are generated from a seed; set
+//! `BVH_FUZZ_SEED` to rerun a single failing case.
+
+use std::sync::Arc;
+
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use ray_tracer::{
+    color, parallelepiped, point, BoundNode, HitRecord, Hittable, HittableList, IndependentSampler,
+    Interval, Lambertian, Material, Parallelogram, Point, Ray, RotateY, Sphere, Translation,
+    TraversalStats, Triangle, Vec3,
+};
+
+const SEEDS: [u64; 6] = [1, 2, 3, 42, 1234, 98765];
+const RAYS_PER_SCENE: usize = 2000;
+const EPSILON: f64 = 1e-9;
+
+fn seeds() -> Vec<u64> {
+    match std::env::var("BVH_FUZZ_SEED") {
+        Ok(seed) => vec![seed.parse().expect("BVH_FUZZ_SEED must be an integer")],
+        Err(_) => SEEDS.to_vec(),
+    }
+}
+
+fn vec3(rng: &mut SmallRng, extent: f64) -> Vec3 {
+    Vec3(
+        rng.gen_range(-extent..extent),
+        rng.gen_range(-extent..extent),
+        rng.gen_range(-extent..extent),
+    )
+}
+
+fn unit_vector(rng: &mut SmallRng) -> Vec3 {
+    loop {
+        let v = vec3(rng, 1.);
+        if v.length() > 1e-3 && v.length() <= 1. {
+            return v.unit();
+        }
+    }
+}
+
+/// A random primitive, sometimes moved and turned. Every object gets its own
+/// material so hits can be traced back to the object that produced them.
+fn object(rng: &mut SmallRng) -> Arc<dyn Hittable> {
+    let material: Arc<dyn Material> = Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)));
+    let at = vec3(rng, 10.);
+    let size = rng.gen_range(0.1..3.);
+    let shape: Arc<dyn Hittable> = match rng.gen_range(0..5) {
+        0 => Arc::new(Sphere::new(at, size, material)),
+        1 => Arc::new(Parallelogram::new(
+            at,
+            (vec3(rng, size), vec3(rng, size)),
+            material,
+        )),
+        // Axis-aligned quads have flat bounds that only the padding keeps hittable.
+        2 => Arc::new(Parallelogram::new(
+            at,
+            (Vec3(size, 0., 0.), Vec3(0., 0., size)),
+            material,
+        )),
+        3 => Arc::new(Triangle::new(
+            (at, at + vec3(rng, size), at + vec3(rng, size)),
+            material,
+        )),
+        _ => parallelepiped(at, at + vec3(rng, size), material),
+    };
+    match rng.gen_range(0..4) {
+        0 => Arc::new(RotateY::new(shape, rng.gen_range(-180.0..180.0))),
+        1 => Arc::new(Translation::new(shape, vec3(rng, 5.))),
+        2 => Arc::new(Translation::new(
+            Arc::new(RotateY::new(shape, rng.gen_range(-180.0..180.0))),
+            vec3(rng, 5.),
+        )),
+        _ => shape,
+    }
+}
+
+/// Rays from around the scene: half aimed at an object, half in any direction.
+fn ray(rng: &mut SmallRng, objects: &[Arc<dyn Hittable>]) -> (Ray, Interval) {
+    let origin = vec3(rng, 20.);
+    let direction = if rng.gen_bool(0.5) {
+        let bounds = objects[rng.gen_range(0..objects.len())].bound();
+        let target: Point = point(
+            rng.gen_range(bounds.intervals[0].start..=bounds.intervals[0].end),
+            rng.gen_range(bounds.intervals[1].start..=bounds.intervals[1].end),
+            rng.gen_range(bounds.intervals[2].start..=bounds.intervals[2].end),
+        );
+        target - origin
+    } else {
+        unit_vector(rng) * rng.gen_range(0.1..10.)
+    };
+    let end = if rng.gen_bool(0.2) {
+        rng.gen_range(1.0..20.)
+    } else {
+        f64::INFINITY
+    };
+    (
+        Ray {
+            origin,
+            direction,
+            time: 0.0,
+        },
+        Interval::new(0.001, end),
+    )
+}
+
+fn same_object(a: &HitRecord, b: &HitRecord) -> bool {
+    std::ptr::addr_eq(a.material, b.material)
+}
+
+fn compare(seed: u64, count: usize) {
+    let mut rng = SmallRng::seed_from_u64(seed);
+    let objects: Vec<Arc<dyn Hittable>> = (0..count).map(|_| object(&mut rng)).collect();
+    let mut list = HittableList::new();
+    for object in &objects {
+        list.add_arc(object.clone());
+    }
+    let bvh = BoundNode::from_objects(&objects, 0..objects.len());
+    let mut sampler = IndependentSampler::new(seed);
+
+    for i in 0..RAYS_PER_SCENE {
+        let (r, t) = ray(&mut rng, &objects);
+        let case = format!(
+            "seed {}, {} objects, ray {}: {:?} over {:?}",
+            seed, count, i, r, t
+        );
+        assert_eq!(
+            list.hit(&r, t, &mut sampler).is_some(),
+            bvh.hit_any(&r, t, &mut sampler),
+            "{}: hit_any disagrees with a full search",
+            case
+        );
+        let mut stats = TraversalStats::default();
+        assert_eq!(
+            bvh.hit(&r, t, &mut sampler).map(|hit| hit.t),
+            bvh.hit_with_stats(&r, t, &mut sampler, &mut stats)
+                .map(|hit| hit.t),
+            "{}: counting the traversal changed its result",
+            case
+        );
+        match (list.hit(&r, t, &mut sampler), bvh.hit(&r, t, &mut sampler)) {
+            (None, None) => {}
+            (Some(expected), Some(actual)) => {
+                assert!(
+                    (expected.t - actual.t).abs() <= EPSILON * expected.t.max(1.),
+                    "{}: expected t = {}, BVH gave {}",
+                    case,
+                    expected.t,
+                    actual.t
+                );
+                assert!(
+                    same_object(&expected, &actual),
+                    "{}: BVH hit another object",
+                    case
+                );
+            }
+            (Some(expected), None) => {
+                panic!("{}: BVH missed a hit at t = {}", case, expected.t)
+            }
+            (None, Some(actual)) => {
+                panic!(
+                    "{}: BVH found a hit at t = {} the list missed",
+                    case, actual.t
+                )
+            }
+        }
+    }
+}
+
+#[test]
+fn single_object_matches_brute_force() {
+    for seed in seeds() {
+        compare(seed, 1);
+    }
+}
+
+#[test]
+fn two_objects_match_brute_force() {
+    for seed in seeds() {
+        compare(seed, 2);
+    }
+}
+
+#[test]
+fn mixed_scenes_match_brute_force() {
+    for seed in seeds() {
+        let count = SmallRng::seed_from_u64(seed).gen_range(3..80);
+        compare(seed, count);
+    }
+}
@@ -0,0 +1,491 @@
+//! Degenerate camera settings are rejected with a specific error instead of
+//! rendering NaN or black images.
+
+use std::fs;
+
+use ray_tracer::{
+    point,
+    scenes::{self, CameraBuilder, RenderMetadata, Scene, SceneError},
+    BounceLimits, Camera, CameraError, Error, Exposure, ImageBuffer, IndependentSampler,
+    Projection, SamplerKind, Vec3,
+};
+
+fn builder() -> CameraBuilder {
+    CameraBuilder {
+        aspect_ratio: Some(1.0),
+        image_height: None,
+        image_width: 32,
+        vfov: 40.,
+        look_from: point(0., 0., 5.),
+        look_at: point(0., 0., 0.),
+        up: Vec3(0., 1., 0.),
+        projection: Projection::Perspective,
+        defocus_angle: None,
+        focus_dist: None,
+        aperture_blades: None,
+        aperture_rotation: None,
+        shutter: None,
+        aa_samples: 4,
+        supersample: None,
+        downscale_filter: None,
+        max_depth: 8,
+        bounces: BounceLimits::default(),
+        russian_roulette: None,
+        ray_epsilon: None,
+        near_clip: None,
+        far_clip: None,
+        sampler: SamplerKind::Independent,
+        blue_noise: false,
+        adaptive: None,
+        transparent: false,
+        fog: None,
+        exposure: Exposure::default(),
+        stereo: None,
+    }
+}
+
+fn build(change: impl FnOnce(&mut CameraBuilder)) -> Result<Camera, CameraError> {
+    let mut builder = builder();
+    change(&mut builder);
+    builder.build()
+}
+
+#[test]
+fn valid_settings_build() {
+    assert!(build(|_| {}).is_ok());
+}
+
+#[test]
+fn zero_samples() {
+    let error = build(|c| c.aa_samples = 0).err();
+    assert!(matches!(error, Some(CameraError::InvalidSamples(0))));
+}
+
+#[test]
+fn zero_width() {
+    let error = build(|c| c.image_width = 0).err();
+    assert!(matches!(error, Some(CameraError::InvalidWidth(0))));
+}
+
+#[test]
+fn non_positive_aspect_ratio() {
+    let error = build(|c| c.aspect_ratio = Some(0.)).err();
+    assert!(matches!(error, Some(CameraError::InvalidAspectRatio(_))));
+}
+
+#[test]
+fn explicit_dimensions_are_exact() {
+    let camera = |width, height| {
+        Camera::try_with_dimensions(
+            width,
+            height,
+            40.,
+            point(0., 0., 5.),
+            point(0., 0., 0.),
+            Vec3(0., 1., 0.),
+            4,
+            8,
+        )
+    };
+    let full_hd = camera(1920, 1080).unwrap();
+    assert_eq!((full_hd.image_width, full_hd.image_height()), (1920, 1080));
+    assert_eq!(full_hd.aspect_ratio, 16. / 9.);
+    assert_eq!(full_hd.output_size(), (1920, 1080));
+    assert!(matches!(
+        camera(1920, 0),
+        Err(CameraError::InvalidHeight(0))
+    ));
+    assert!(matches!(camera(-4, 3), Err(CameraError::InvalidWidth(-4))));
+
+    let mut camera = builder().build().unwrap();
+    camera.set_image_size(30, 20);
+    assert_eq!((camera.aspect_ratio, camera.image_height()), (1.5, 20));
+    camera.set_image_size(30, -1);
+    assert!(matches!(
+        camera.validate(),
+        Err(CameraError::InvalidHeight(-1))
+    ));
+}
+
+#[test]
+fn heights_from_aspect_ratios_round() {
+    // 35 / (21 / 9) comes out a hair under 15.
+    let camera = build(|c| {
+        c.aspect_ratio = Some(21. / 9.);
+        c.image_width = 35;
+    })
+    .unwrap();
+    assert_eq!(camera.image_height(), 15);
+    let mut camera = builder().build().unwrap();
+    camera.aspect_ratio = 21. / 9.;
+    camera.set_image_width(35);
+    assert_eq!(camera.image_height(), 15);
+}
+
+#[test]
+fn scene_files_give_an_aspect_ratio_or_a_height() {
+    let parse = |text: &str| toml::from_str::<CameraBuilder>(text).unwrap().build();
+    let camera = parse("image_width = 1920\nimage_height = 1080").unwrap();
+    assert_eq!((camera.image_width, camera.image_height()), (1920, 1080));
+    let camera = parse("image_width = 400\naspect_ratio = 2.0").unwrap();
+    assert_eq!((camera.aspect_ratio, camera.image_height()), (2., 200));
+    assert!(matches!(
+        parse("image_width = 400"),
+        Err(CameraError::MissingAspectRatioOrHeight)
+    ));
+    assert!(matches!(
+        parse("image_width = 400\naspect_ratio = 2.0\nimage_height = 100"),
+        Err(CameraError::AspectRatioAndHeight { .. })
+    ));
+
+    // In code, the last one given wins.
+    let camera = CameraBuilder::default()
+        .aspect_ratio(2.)
+        .image_width(64)
+        .image_height(48)
+        .build()
+        .unwrap();
+    assert_eq!((camera.aspect_ratio, camera.image_height()), (4. / 3., 48));
+}
+
+#[test]
+fn negative_fov() {
+    let error = build(|c| c.vfov = -30.).err();
+    assert!(matches!(error, Some(CameraError::InvalidFov(fov)) if fov == -30.));
+}
+
+#[test]
+fn straight_fov() {
+    let error = build(|c| c.vfov = 180.).err();
+    assert!(matches!(error, Some(CameraError::InvalidFov(_))));
+}
+
+#[test]
+fn zero_max_depth() {
+    let error = build(|c| c.max_depth = 0).err();
+    assert!(matches!(error, Some(CameraError::InvalidMaxDepth(0))));
+}
+
+#[test]
+fn look_from_equals_look_at() {
+    let error = build(|c| c.look_at = c.look_from).err();
+    assert!(matches!(error, Some(CameraError::LookFromIsLookAt(_))));
+}
+
+#[test]
+fn up_parallel_to_view() {
+    let error = build(|c| c.up = Vec3(0., 0., -2.)).err();
+    assert!(matches!(error, Some(CameraError::UpParallelToView { .. })));
+}
+
+#[test]
+fn zero_up() {
+    let error = build(|c| c.up = Vec3(0., 0., 0.)).err();
+    assert!(matches!(error, Some(CameraError::UpParallelToView { .. })));
+}
+
+#[test]
+fn non_finite_view() {
+    let error = build(|c| c.look_from = point(f64::NAN, 0., 5.)).err();
+    assert!(matches!(error, Some(CameraError::NonFiniteView)));
+}
+
+#[test]
+#[should_panic(expected = "invalid camera")]
+fn new_panics_on_degenerate_settings() {
+    Camera::new(
+        1.0,
+        32,
+        40.,
+        point(0., 0., 5.),
+        point(0., 0., 5.),
+        Vec3(0., 1., 0.),
+        4,
+        8,
+    );
+}
+
+#[test]
+fn move_camera_rejects_degenerate_views() {
+    let mut camera = builder().build().unwrap();
+    let error = camera
+        .move_camera(point(1., 2., 3.), point(1., 2., 3.), Vec3(0., 1., 0.))
+        .err();
+    assert!(matches!(error, Some(CameraError::LookFromIsLookAt(_))));
+    let error = camera
+        .move_camera(point(0., 5., 0.), point(0., 0., 0.), Vec3(0., 1., 0.))
+        .err();
+    assert!(matches!(error, Some(CameraError::UpParallelToView { .. })));
+
+    // A rejected move leaves the camera where it was.
+    assert_eq!(camera.look_from.z(), 5.);
+    assert!(camera.validate().is_ok());
+    assert!(camera
+        .move_camera(point(0., 5., 5.), point(0., 0., 0.), Vec3(0., 1., 0.))
+        .is_ok());
+}
+
+#[test]
+fn setters_are_checked_by_validate() {
+    let mut camera = builder().build().unwrap();
+    camera.set_aa_samples(0);
+    assert!(matches!(
+        camera.validate(),
+        Err(CameraError::InvalidSamples(0))
+    ));
+}
+
+#[test]
+fn scene_validation_reports_the_camera() {
+    let mut scene = scenes::build("quads").unwrap();
+    scene.camera.set_image_width(0);
+    let issues = scene.validate();
+    assert_eq!(issues[0].object, "camera");
+    assert_eq!(issues[0].message, "image width must be positive, got 0");
+}
+
+#[test]
+fn scene_file_with_bad_camera_is_an_error() {
+    let dir = std::env::temp_dir().join(format!("ray-tracer-camera-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("scene.json");
+    let mut desc = scenes::build("quads").unwrap().describe().unwrap();
+    desc.camera.aa_samples = 0;
+    desc.save(&path).unwrap();
+
+    let result = Scene::from_path(&path);
+    fs::remove_dir_all(&dir).unwrap();
+    match result {
+        Err(SceneError::Camera(CameraError::InvalidSamples(0))) => {}
+        Err(other) => panic!("expected SceneError::Camera, got {:?}", other),
+        Ok(_) => panic!("expected an error"),
+    }
+    assert_eq!(
+        Error::from(CameraError::InvalidSamples(0)).to_string(),
+        "camera: samples per pixel must be positive, got 0"
+    );
+}
+
+#[test]
+fn render_to_file_creates_missing_directories() {
+    let mut scene = scenes::build("quads").unwrap();
+    scene
+        .camera
+        .set_image_width(12)
+        .set_aa_samples(1)
+        .set_seed(Some(3));
+    let dir = std::env::temp_dir().join(format!("ray-tracer-output-{}", std::process::id()));
+    let path = dir.join("renders/today/quads.ppm");
+    let stats = scene.render_to_file(&path);
+    let loaded = ImageBuffer::load(&path);
+    let unsupported = scene.render_to_file(&dir.join("quads.bmp"));
+    fs::remove_dir_all(&dir).unwrap();
+
+    let stats = stats.unwrap();
+    let loaded = loaded.unwrap();
+    assert_eq!((loaded.width, loaded.height), (stats.width, stats.height));
+    // The file holds the same gamma-corrected bytes as a render to stdout.
+    let mut expected = Vec::new();
+    scene.render_image().write_ppm(&mut expected).unwrap();
+    let mut written = Vec::new();
+    loaded.write_ppm(&mut written).unwrap();
+    assert_eq!(written, expected);
+    match unsupported {
+        Err(Error::Io { source, .. }) => {
+            assert_eq!(source.kind(), std::io::ErrorKind::Unsupported)
+        }
+        other => panic!(
+            "expected an unsupported format, got {:?}",
+            other.map(|_| ())
+        ),
+    }
+}
+
+#[test]
+fn render_to_file_writes_what_the_command_line_does() {
+    let dir = std::env::temp_dir().join(format!("ray-tracer-cli-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut scene = scenes::build("quads").unwrap();
+    scene
+        .camera
+        .set_image_width(12)
+        .set_aa_samples(2)
+        .set_exposure(Exposure::Auto {
+            key: 0.3,
+            exclude: 0.0,
+        });
+    let scene_file = dir.join("quads.toml");
+    scene.describe().unwrap().save(&scene_file).unwrap();
+
+    let status = std::process::Command::new(env!("CARGO_BIN_EXE_Ray-Tracer_Rust"))
+        .arg("--scene-file")
+        .arg(&scene_file)
+        .args(["--seed", "3", "--quiet", "-o"])
+        .arg(dir.join("cli.ppm"))
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let mut loaded = Scene::from_path(&scene_file).unwrap();
+    loaded.camera.set_seed(Some(3));
+    loaded.render_to_file(&dir.join("library.ppm")).unwrap();
+
+    let cli = ImageBuffer::load(&dir.join("cli.ppm"));
+    let library = ImageBuffer::load(&dir.join("library.ppm"));
+    let cli_metadata = RenderMetadata::read(&dir.join("cli.ppm"));
+    let library_metadata = RenderMetadata::read(&dir.join("library.ppm"));
+    fs::remove_dir_all(&dir).unwrap();
+
+    let (cli, library) = (cli.unwrap(), library.unwrap());
+    assert_eq!((cli.width, cli.height), (library.width, library.height));
+    assert!(cli
+        .pixels
+        .iter()
+        .zip(&library.pixels)
+        .all(|(a, b)| (a.0, a.1, a.2) == (b.0, b.1, b.2)));
+    // Both were exposed, by the same multiplier.
+    let (cli_metadata, library_metadata) = (cli_metadata.unwrap(), library_metadata.unwrap());
+    assert!(cli_metadata.exposure.is_some());
+    assert_eq!(cli_metadata.exposure, library_metadata.exposure);
+    assert_eq!(library_metadata.seed, Some(3));
+}
+
+#[test]
+fn chained_builders_match_camera_new() {
+    let built = CameraBuilder::default()
+        .aspect_ratio(3.0 / 2.0)
+        .image_width(60)
+        .vfov(20.)
+        .look_from(point(13., 2., 3.))
+        .look_at(point(0., 0., 0.))
+        .samples(7)
+        .max_depth(5)
+        .build()
+        .unwrap();
+    let new = Camera::new(
+        3.0 / 2.0,
+        60,
+        20.,
+        point(13., 2., 3.),
+        point(0., 0., 0.),
+        Vec3(0., 1., 0.),
+        7,
+        5,
+    );
+    assert_eq!(
+        serde_json::to_string(&CameraBuilder::from(&built)).unwrap(),
+        serde_json::to_string(&CameraBuilder::from(&new)).unwrap()
+    );
+
+    // Unset settings are the default camera's.
+    let default = CameraBuilder::default().build().unwrap();
+    assert_eq!((default.image_width, default.aa_samples), (400, 100));
+
+    assert!(matches!(
+        CameraBuilder::default().image_width(0).build(),
+        Err(CameraError::InvalidWidth(0))
+    ));
+    assert!(matches!(
+        CameraBuilder::default().up(Vec3(0., 0., 1.)).build(),
+        Err(CameraError::UpParallelToView { .. })
+    ));
+}
+
+#[test]
+fn orthographic_rays_are_parallel_and_start_on_the_view_plane() {
+    let camera = CameraBuilder::default()
+        .aspect_ratio(2.0)
+        .image_width(40)
+        .look_from(point(0., 0., 5.))
+        .look_at(point(0., 0., 0.))
+        .projection(Projection::Orthographic { width: 8. })
+        .build()
+        .unwrap();
+    let mut rng = IndependentSampler::new(1);
+    let ray = |x, y, rng: &mut IndependentSampler| camera.sample_ray(x, y, rng);
+    let (corner, far_corner) = (ray(0, 0, &mut rng), ray(39, 19, &mut rng));
+    for ray in [&corner, &far_corner] {
+        assert!((ray.direction.unit() - Vec3(0., 0., -1.)).length() < 1e-12);
+        assert!((ray.origin.z() - 5.).abs() < 1e-12);
+    }
+    // 8 units across and, at 2:1, 4 up and down, in pixels 0.2 wide:
+    // samples of the corner pixels land within 0.1 of their centers.
+    let near = |a: f64, b: f64| (a - b).abs() <= 0.1 + 1e-9;
+    assert!(near(corner.origin.x(), -3.9) && near(corner.origin.y(), 1.9));
+    assert!(near(far_corner.origin.x(), 3.9) && near(far_corner.origin.y(), -1.9));
+
+    // The projection survives a scene file, and a bad width is refused.
+    let builder = CameraBuilder::from(&camera);
+    let text = serde_json::to_string(&builder).unwrap();
+    assert!(text.contains(r#""projection":{"orthographic":{"width":8.0}}"#));
+    let parsed: CameraBuilder = serde_json::from_str(&text).unwrap();
+    assert_eq!(parsed.projection, Projection::Orthographic { width: 8. });
+    let perspective = serde_json::to_string(&CameraBuilder::default()).unwrap();
+    assert!(!perspective.contains("projection"));
+    assert!(matches!(
+        CameraBuilder::default()
+            .projection(Projection::Orthographic { width: 0. })
+            .build(),
+        Err(CameraError::InvalidOrthographicWidth(_))
+    ));
+}
+
+#[test]
+fn panoramic_rays_are_aimed_by_angle() {
+    let looking_down_z = |projection| {
+        CameraBuilder::default()
+            .aspect_ratio(1.0)
+            .image_width(40)
+            .look_from(point(0., 0., 5.))
+            .look_at(point(0., 0., 0.))
+            .projection(projection)
+            .build()
+            .unwrap()
+    };
+    // Samples of a pixel `size` degrees across stay within half a diagonal
+    // of its center's direction, and reach the focus distance at 1.
+    let mut rng = IndependentSampler::new(1);
+    let mut aims_at = |camera: &Camera, x, y, size: f64, center: Vec3| {
+        for _ in 0..16 {
+            let ray = camera.sample_ray(x, y, &mut rng);
+            assert!((ray.direction.length() - 5.).abs() < 1e-9);
+            let cos = Vec3::dot(&ray.direction.unit(), &center.unit());
+            let angle = cos.clamp(-1., 1.).acos().to_degrees();
+            assert!(angle <= size / 2f64.sqrt() + 1e-9, "{}", angle);
+        }
+    };
+
+    // Equirectangular images are made 2:1, across them 360° starting
+    // behind the camera, and down them 180° from straight up: 9° a pixel.
+    let camera = looking_down_z(Projection::Equirectangular);
+    assert_eq!((camera.aspect_ratio, camera.image_height()), (2.0, 20));
+    for (x, y) in [(19, 9), (30, 10), (0, 10), (39, 0), (20, 19)] {
+        let azimuth = ((x as f64 + 0.5) * 9. - 180.).to_radians();
+        let polar = ((y as f64 + 0.5) * 9.).to_radians();
+        let center = Vec3(
+            polar.sin() * azimuth.sin(),
+            polar.cos(),
+            -polar.sin() * azimuth.cos(),
+        );
+        aims_at(&camera, x, y, 9., center);
+    }
+
+    // A 180° fisheye 40 pixels across turns 4.5° a pixel from its center,
+    // seeing straight sideways at the edges of the image.
+    let camera = looking_down_z(Projection::Fisheye { fov: 180. });
+    for (x, y) in [(20, 20), (39, 19), (5, 30), (19, 0), (2, 2)] {
+        let (dx, dy) = ((x as f64 + 0.5 - 20.) / 20., (20. - y as f64 - 0.5) / 20.);
+        let r = (dx * dx + dy * dy).sqrt();
+        let angle = (r * 90.).to_radians();
+        let center = Vec3(dx / r * angle.sin(), dy / r * angle.sin(), -angle.cos());
+        aims_at(&camera, x, y, 4.5, center);
+    }
+
+    for fov in [0., -90., 400., f64::NAN] {
+        assert!(matches!(
+            CameraBuilder::default()
+                .projection(Projection::Fisheye { fov })
+                .build(),
+            Err(CameraError::InvalidFisheyeFov(_))
+        ));
+    }
+}
@@ -0,0 +1,131 @@
+//! Near and far clipping planes: the camera seeing only what lies between
+//! two distances along its view direction.
+
+use std::sync::Arc;
+
+use ray_tracer::{
+    color, point,
+    scenes::{self, CameraBuilder},
+    Camera, CameraError, Color, DiffuseLight, HittableList, Lambertian, LightList, Parallelogram,
+    Planar, Sphere, Vec3,
+};
+
+/// A camera at the origin looking down -z, seeded, against a blue sky.
+fn camera() -> Camera {
+    let mut camera = Camera::new(
+        1.0,
+        9,
+        20.,
+        point(0., 0., 0.),
+        point(0., 0., -1.),
+        Vec3(0., 1., 0.),
+        16,
+        4,
+    );
+    camera.set_seed(Some(2)).set_background(color(0., 0., 1.));
+    camera
+}
+
+fn glowing(center: Vec3, radius: f64, light: Color) -> Sphere {
+    Sphere::new(center, radius, Arc::new(DiffuseLight::from(light)))
+}
+
+/// What the camera sees at the center of the image.
+fn center(camera: &Camera, world: &HittableList) -> Color {
+    camera.pixel_color(world, &LightList::new(), 4, 4, 0)
+}
+
+#[test]
+fn the_near_plane_cuts_away_what_is_in_front() {
+    let mut world = HittableList::new();
+    world.add(glowing(point(0., 0., -3.), 0.5, color(1., 0., 0.)));
+    world.add(glowing(point(0., 0., -8.), 0.5, color(0., 1., 0.)));
+    let mut camera = camera();
+    let seen = center(&camera, &world);
+    assert_eq!((seen.0, seen.1), (1., 0.));
+    camera.set_clip(5.0, f64::INFINITY);
+    let seen = center(&camera, &world);
+    assert_eq!((seen.0, seen.1), (0., 1.));
+
+    // A plane cutting through the sphere shows its inside.
+    camera.set_clip(3.0, f64::INFINITY);
+    let seen = center(&camera, &world);
+    assert_eq!((seen.0, seen.1), (1., 0.));
+}
+
+#[test]
+fn beyond_the_far_plane_is_the_background() {
+    let mut world = HittableList::new();
+    world.add(glowing(point(0., 0., -8.), 0.5, color(0., 1., 0.)));
+    let mut camera = camera();
+    camera.set_clip(0.0, 5.0);
+    let seen = center(&camera, &world);
+    assert_eq!((seen.0, seen.1, seen.2), (0., 0., 1.));
+    camera.set_clip(0.0, 7.6);
+    assert_eq!(center(&camera, &world).1, 1.);
+}
+
+#[test]
+fn light_bouncing_around_the_scene_is_not_clipped() {
+    // A light between the camera and a wall, cut away from view, still
+    // lights the wall.
+    let mut world = HittableList::new();
+    world.add(glowing(point(0., 0., -2.), 1.0, color(4., 4., 4.)));
+    world.add(Planar::Parallelogram(Parallelogram::new(
+        point(-10., -10., -4.),
+        (Vec3(20., 0., 0.), Vec3(0., 20., 0.)),
+        Arc::new(Lambertian::from(color(0.8, 0.8, 0.8))),
+    )));
+    let mut camera = camera();
+    camera
+        .set_background(color(0., 0., 0.))
+        .set_clip(3.5, f64::INFINITY);
+    let seen = center(&camera, &world);
+    assert!(seen.0 > 0.0 && seen.0 < 4.0, "{:?}", seen);
+}
+
+#[test]
+fn the_cutaway_sees_out_of_the_tall_block() {
+    let brightness = |clip: bool| {
+        let mut scene = scenes::build("cornell_cutaway").unwrap();
+        scene
+            .camera
+            .set_image_width(16)
+            .set_aa_samples(16)
+            .set_max_depth(8)
+            .set_seed(Some(1));
+        if !clip {
+            scene.camera.set_clip(0.0, f64::INFINITY);
+        }
+        let image = scene.render_image();
+        image.pixels.iter().map(|p| p.luminance()).sum::<f64>()
+    };
+    // Shut inside the block, the camera sees nothing lit.
+    assert_eq!(brightness(false), 0.0);
+    assert!(brightness(true) > 0.0);
+}
+
+#[test]
+fn clipping_planes_are_checked_and_kept_in_scene_files() {
+    for (near, far) in [(-1.0, 10.0), (5.0, 5.0), (5.0, 1.0), (f64::NAN, 10.0)] {
+        let mut camera = camera();
+        camera.set_clip(near, far);
+        assert!(
+            matches!(camera.validate(), Err(CameraError::InvalidClip { .. })),
+            "{} {}",
+            near,
+            far
+        );
+    }
+
+    let mut camera = camera();
+    camera.set_clip(2.0, 50.0);
+    let json = serde_json::to_string(&CameraBuilder::from(&camera)).unwrap();
+    let built = serde_json::from_str::<CameraBuilder>(&json)
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!((built.near_clip, built.far_clip), (2.0, 50.0));
+    let plain = serde_json::to_string(&CameraBuilder::from(&Camera::default())).unwrap();
+    assert!(!plain.contains("clip"), "{}", plain);
+}
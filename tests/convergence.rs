@@ -0,0 +1,138 @@
+//! Per-pixel variance and how converged a render is: what the accumulated
+//! sums of squares say, and that noisier scenes measure as noisier.
+
+use std::sync::Arc;
+
+use ray_tracer::{
+    color, point, scenes::Scene, Accumulation, Camera, Dielectric, DiffuseLight, HittableList,
+    Lambertian, Parallelogram, Sphere, Vec3,
+};
+
+fn camera(samples: i32) -> Camera {
+    let mut camera = Camera::new(
+        1.0,
+        8,
+        40.,
+        point(0., 0., 10.),
+        point(0., 0., 0.),
+        Vec3(0., 1., 0.),
+        samples,
+        6,
+    );
+    camera.set_seed(Some(1));
+    camera
+}
+
+fn light(size: f64) -> Parallelogram {
+    Parallelogram::new(
+        point(-size / 2., -size / 2., 0.),
+        (Vec3(size, 0., 0.), Vec3(0., size, 0.)),
+        Arc::new(DiffuseLight::from(color(0.8, 0.6, 0.4))),
+    )
+}
+
+/// A camera filled by one evenly lit quad, which every sample agrees on.
+fn flat_light(samples: i32) -> Scene {
+    let mut world = HittableList::new();
+    world.add(light(20.));
+    Scene::new(camera(samples), world)
+}
+
+/// A glass ball in front of a small light over a white floor: caustics,
+/// reflections, and diffuse bounces.
+fn glass(samples: i32) -> Scene {
+    let mut world = HittableList::new();
+    world.add(light(3.));
+    world.add(Sphere::new(
+        point(0., 0., 3.),
+        1.5,
+        Arc::new(Dielectric::new(1.5)),
+    ));
+    world.add(Parallelogram::new(
+        point(-20., -2., -20.),
+        (Vec3(0., 0., 40.), Vec3(40., 0., 0.)),
+        Arc::new(Lambertian::from(color(0.73, 0.73, 0.73))),
+    ));
+    Scene::new(camera(samples), world)
+}
+
+#[test]
+fn a_flat_light_has_no_variance_and_glass_plenty() {
+    let flat = flat_light(4).render_accumulation().unwrap();
+    assert!(flat
+        .variance()
+        .pixels
+        .iter()
+        .all(|v| (v.0, v.1, v.2) == (0.0, 0.0, 0.0)));
+    let flat = flat.convergence();
+    assert_eq!(flat.max_relative_error, 0.0);
+
+    let noisy = glass(4).render_accumulation().unwrap().convergence();
+    assert!(noisy.p95_relative_error > 0.1, "{:?}", noisy);
+    assert!(noisy.mean_relative_error <= noisy.p95_relative_error.max(noisy.max_relative_error));
+    assert!(noisy.p95_relative_error <= noisy.max_relative_error);
+}
+
+#[test]
+fn variance_is_that_of_the_pixels_samples() {
+    let scene = glass(6);
+    let accumulation = scene.render_accumulation().unwrap();
+    let (x, y) = (3, 5);
+    let samples: Vec<_> = (0..6)
+        .map(|sample| {
+            scene
+                .camera
+                .pixel_sample(&scene.world, &scene.lights, x, y, 1, sample)
+        })
+        .collect();
+    let mean = samples.iter().fold(Vec3(0., 0., 0.), |sum, &s| sum + s) / 6.0;
+    let variance = samples.iter().fold(Vec3(0., 0., 0.), |sum, &s| {
+        let d = s - mean;
+        sum + d * d
+    }) / 5.0;
+    let found = accumulation.variance().get(x as usize, y as usize);
+    assert!((found - variance).length() < 1e-9 * (1.0 + variance.length()));
+    let error = accumulation.standard_error().get(x as usize, y as usize);
+    assert!((error.0 * error.0 * 6.0 - variance.0).abs() < 1e-9);
+}
+
+#[test]
+fn timed_renders_report_convergence_that_more_samples_improve() {
+    let (image, stats) = glass(4).render_timed();
+    assert_eq!(image.to_rgba8(), glass(4).render_image().to_rgba8());
+    let few = stats.convergence.expect("mono renders measure convergence");
+    assert!(stats.to_string().contains("95th percentile"));
+
+    let many = glass(64).render_timed().1.convergence.unwrap();
+    // Sixteen times the samples, about a quarter of the error.
+    let ratio = many.mean_relative_error / few.mean_relative_error;
+    assert!((0.1..0.5).contains(&ratio), "{}", ratio);
+}
+
+#[test]
+fn merged_variance_is_one_longer_renders() {
+    let scene = glass(3);
+    let mut halves = scene.render_accumulation().unwrap();
+    scene.accumulate(&mut halves).unwrap();
+    let whole = glass(6).render_accumulation().unwrap();
+    let report = halves.variance().diff(&whole.variance()).unwrap();
+    assert!(report.max_error < 1e-9, "{}", report);
+
+    let mut bytes = Vec::new();
+    halves.write(&mut bytes).unwrap();
+    let read = Accumulation::read(bytes.as_slice()).unwrap();
+    assert!(read
+        .squares
+        .iter()
+        .zip(&halves.squares)
+        .all(|(a, b)| (a.0, a.1, a.2) == (b.0, b.1, b.2)));
+}
+
+#[test]
+fn one_sample_says_nothing_of_the_variance() {
+    let one = flat_light(1).render_accumulation().unwrap();
+    assert!(one.variance().pixels.iter().all(|v| v.0.is_infinite()));
+    assert!(one.convergence().p95_relative_error.is_infinite());
+    let empty = Accumulation::new(2, 2, 0);
+    assert!(empty.variance().pixels.iter().all(|v| v.0.is_infinite()));
+}
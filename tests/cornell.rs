@@ -0,0 +1,123 @@
+//! The Cornell scenes built from the `cornell` helpers render exactly as the
+//! spelled-out constructors they replaced did.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use ray_tracer::{
+    color, parallelepiped, point,
+    scenes::{self, Scene},
+    Camera, ConstantMedium, DiffuseLight, Hittable, HittableList, Lambertian, Parallelogram,
+    Planar, RotateY, Translation, Vec3,
+};
+
+/// A hash of the exact bits of a small seeded render.
+fn checksum(mut scene: Scene) -> u64 {
+    scene
+        .camera
+        .set_image_width(24)
+        .set_aa_samples(4)
+        .set_max_depth(6)
+        .set_seed(Some(7));
+    let image = scene.render_image();
+    let mut hasher = DefaultHasher::new();
+    for pixel in &image.pixels {
+        [pixel.0, pixel.1, pixel.2]
+            .map(f64::to_bits)
+            .hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// The walls, the light of `intensity`, and the two blocks passed through
+/// `wrap`, added one by one as the scenes did before the helpers.
+fn cornell_by_hand(
+    intensity: f64,
+    wrap: impl Fn(Arc<dyn Hittable>, usize) -> Arc<dyn Hittable>,
+) -> HittableList {
+    let mut world = HittableList::new();
+
+    let red = Arc::new(Lambertian::from(color(0.65, 0.05, 0.05)));
+    let white = Arc::new(Lambertian::from(color(0.73, 0.73, 0.73)));
+    let green = Arc::new(Lambertian::from(color(0.12, 0.45, 0.15)));
+    let light = Arc::new(DiffuseLight::from(color(intensity, intensity, intensity)));
+
+    world.add(Planar::Parallelogram(Parallelogram::new(
+        point(555., 0., 0.),
+        (Vec3(0., 555., 0.), Vec3(0., 0., 555.)),
+        green,
+    )));
+    world.add(Planar::Parallelogram(Parallelogram::new(
+        point(0., 0., 0.),
+        (Vec3(555., 0., 0.), Vec3(0., 0., 555.)),
+        red,
+    )));
+    world.add(Planar::Parallelogram(Parallelogram::new(
+        point(343., 554., 332.),
+        (Vec3(-130., 0., 0.), Vec3(0., 0., -105.)),
+        light,
+    )));
+    world.add(Planar::Parallelogram(Parallelogram::new(
+        point(0., 0., 0.),
+        (Vec3(555., 0., 0.), Vec3(0., 0., 555.)),
+        white.clone(),
+    )));
+    world.add(Planar::Parallelogram(Parallelogram::new(
+        point(555., 555., 555.),
+        (Vec3(555., 0., 0.), Vec3(0., 0., 555.)),
+        white.clone(),
+    )));
+    world.add(Planar::Parallelogram(Parallelogram::new(
+        point(0., 0., 555.),
+        (Vec3(555., 0., 0.), Vec3(0., 555., 0.)),
+        white.clone(),
+    )));
+
+    let box1 = parallelepiped(Vec3(0., 0., 0.), Vec3(165., 330., 165.), white.clone());
+    let box1 = Arc::new(RotateY::new(box1, 15.));
+    let box1 = Arc::new(Translation::new(box1, Vec3(265., 0., 295.)));
+    world.add_arc(wrap(box1, 0));
+
+    let box2 = parallelepiped(Vec3(0., 0., 0.), Vec3(165., 165., 165.), white);
+    let box2 = Arc::new(RotateY::new(box2, -18.));
+    let box2 = Arc::new(Translation::new(box2, Vec3(130., 0., 65.)));
+    world.add_arc(wrap(box2, 1));
+
+    world
+}
+
+fn camera(image_width: i32, samples: i32, max_depth: i32) -> Camera {
+    Camera::new(
+        1.0,
+        image_width,
+        40.0,
+        point(278., 278., -800.),
+        point(278., 278., 0.),
+        Vec3(0., 1., 0.),
+        samples,
+        max_depth,
+    )
+}
+
+#[test]
+fn the_cornell_box_is_unchanged() {
+    let by_hand = Scene::new(camera(600, 50, 20), cornell_by_hand(15., |block, _| block));
+    assert_eq!(checksum(scenes::cornell_box()), checksum(by_hand));
+}
+
+#[test]
+fn the_cornell_smoke_is_unchanged() {
+    let smoke = |block, i| -> Arc<dyn Hittable> {
+        let albedo = if i == 0 { 0. } else { 1. };
+        Arc::new(ConstantMedium::from_color(
+            block,
+            0.01,
+            color(albedo, albedo, albedo),
+        ))
+    };
+    let by_hand = Scene::new(camera(900, 150, 75), cornell_by_hand(20., smoke));
+    assert_eq!(checksum(scenes::cornell_smoke()), checksum(by_hand));
+}
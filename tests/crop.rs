@@ -0,0 +1,66 @@
+//! Crop windows: the pixels of a region render exactly as in the full
+//! image, cropped or in place, and windows past the edge are clamped.
+
+use ray_tracer::{
+    scenes::{self, Scene},
+    ImageBuffer, RegionOutput,
+};
+
+fn tiny() -> Scene {
+    let mut scene = scenes::cornell_box();
+    scene
+        .camera
+        .set_image_width(20)
+        .set_aa_samples(3)
+        .set_max_depth(4)
+        .set_seed(Some(7));
+    scene
+}
+
+fn same(a: ray_tracer::Color, b: ray_tracer::Color) -> bool {
+    (a.0, a.1, a.2) == (b.0, b.1, b.2)
+}
+
+#[test]
+fn crops_line_up_with_the_full_render() {
+    let scene = tiny();
+    let full = scene.render_image();
+    let crop = scene.render_region(5..12, 3..9, RegionOutput::Cropped);
+    assert_eq!((crop.width, crop.height), (7, 6));
+    for y in 0..6 {
+        for x in 0..7 {
+            assert!(
+                same(crop.get(x, y), full.get(x + 5, y + 3)),
+                "({}, {})",
+                x,
+                y
+            );
+        }
+    }
+
+    let framed = scene.render_region(5..12, 3..9, RegionOutput::FullFrame);
+    assert_eq!((framed.width, framed.height), (20, 20));
+    for y in 0..20 {
+        for x in 0..20 {
+            let inside = (5..12).contains(&x) && (3..9).contains(&y);
+            let expected = if inside {
+                full.get(x, y)
+            } else {
+                ImageBuffer::new(1, 1).get(0, 0)
+            };
+            assert!(same(framed.get(x, y), expected), "({}, {})", x, y);
+        }
+    }
+}
+
+#[test]
+fn windows_past_the_image_are_clamped() {
+    let scene = tiny();
+    let full = scene.render_image();
+    let crop = scene.render_region(15..40, 18..25, RegionOutput::Cropped);
+    assert_eq!((crop.width, crop.height), (5, 2));
+    assert!(same(crop.get(4, 1), full.get(19, 19)));
+
+    let empty = scene.render_region(30..40, 25..30, RegionOutput::Cropped);
+    assert_eq!((empty.width, empty.height), (0, 0));
+}
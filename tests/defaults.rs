@@ -0,0 +1,92 @@
+//! Pins the values of the `Default` implementations, so changing one is a
+//! deliberate edit here rather than a silent drift.
+
+use ray_tracer::{
+    point,
+    scenes::{BackgroundDesc, CameraBuilder, Scene, SceneDesc},
+    BounceLimits, Camera, Hittable, HittableList, Interval, SamplerKind, Vec3,
+};
+
+fn same(a: Vec3, b: Vec3) -> bool {
+    (a.0, a.1, a.2) == (b.0, b.1, b.2)
+}
+
+fn assert_default_camera(camera: &Camera) {
+    assert_eq!(camera.aspect_ratio, 16.0 / 9.0);
+    assert_eq!(camera.image_width, 400);
+    assert_eq!(camera.image_height(), 225);
+    assert_eq!(camera.vfov, 90.0);
+    assert!(same(camera.look_from, point(0., 0., 0.)));
+    assert!(same(camera.look_at, point(0., 0., -1.)));
+    assert!(same(camera.up, Vec3(0., 1., 0.)));
+    assert_eq!(camera.aa_samples, 100);
+    assert_eq!(camera.max_depth, 50);
+    assert_eq!(camera.defocus_angle, 0.0);
+    assert_eq!(camera.focus_dist, None);
+    assert_eq!((camera.shutter_open, camera.shutter_close), (0.0, 0.0));
+    assert_eq!(camera.bounce_limits, BounceLimits::default());
+    assert_eq!(camera.sampler, SamplerKind::default());
+    assert!(!camera.blue_noise);
+    assert!(camera.stereo.is_none());
+    assert_eq!(camera.seed, None);
+    assert!(matches!(
+        camera.background.describe(),
+        Some(BackgroundDesc::Solid(c)) if same(c, Vec3(0., 0., 0.))
+    ));
+}
+
+#[test]
+fn the_default_camera_is_the_first_books() {
+    let camera = Camera::default();
+    assert_default_camera(&camera);
+    assert!(camera.validate().is_ok());
+}
+
+#[test]
+fn the_default_camera_builder_builds_the_default_camera() {
+    let builder = CameraBuilder::default();
+    assert_eq!(builder.image_width, 400);
+    assert_eq!(builder.aa_samples, 100);
+    assert_eq!(builder.defocus_angle, None);
+    assert_eq!(builder.shutter, None);
+    assert_eq!(builder.ray_epsilon, None);
+    assert_default_camera(&builder.build().unwrap());
+
+    // Scene files fill in whatever they leave out, including the whole camera.
+    let partial: SceneDesc = toml::from_str(
+        r#"
+        objects = []
+
+        [camera]
+        image_width = 64
+        look_from = [0.0, 1.0, 3.0]
+    "#,
+    )
+    .unwrap();
+    assert_eq!(partial.camera.image_width, 64);
+    assert_eq!(partial.camera.vfov, 90.0);
+    assert_eq!(partial.camera.max_depth, 50);
+    let bare: SceneDesc = toml::from_str("objects = []").unwrap();
+    assert_default_camera(&bare.build().unwrap().camera);
+}
+
+#[test]
+fn the_default_interval_is_empty() {
+    let interval = Interval::default();
+    assert_eq!(interval.start, f64::INFINITY);
+    assert_eq!(interval.end, f64::NEG_INFINITY);
+    assert!(!interval.contains(0.0));
+    assert!(interval.size() < 0.0);
+}
+
+#[test]
+fn the_default_list_and_scene_are_empty() {
+    let list = HittableList::default();
+    assert!(list.objects.is_empty());
+    assert!(list.bound().intervals.iter().all(|i| i.start > i.end));
+
+    let scene = Scene::default();
+    assert!(scene.world.objects.is_empty());
+    assert!(scene.lights.is_empty());
+    assert_default_camera(&scene.camera);
+}
@@ -0,0 +1,337 @@
+//! Point, directional, and spot lights: falloff, shadow rays, which materials
+//! respond to them, and how they're saved with a scene.
+
+use std::{f64::consts::PI, fs, sync::Arc};
+
+use ray_tracer::{
+    color, parallelepiped, point, scenes, ConstantMedium, DeltaLight, DirectionalLight,
+    HittableList, IndependentSampler, Lambertian, LightList, Material, Metal, Parallelogram, Point,
+    PointLight, Ray, SolidBackground, Sphere, SpotLight, Vec3,
+};
+
+const EPSILON: f64 = 1e-9;
+
+/// A large floor at y = 0 and, optionally, a sphere hovering over the origin.
+fn world(floor: Arc<dyn Material>, blocker: bool) -> HittableList {
+    let mut world = HittableList::new();
+    world.add(Parallelogram::new(
+        point(-50., 0., -50.),
+        (Vec3(100., 0., 0.), Vec3(0., 0., 100.)),
+        floor,
+    ));
+    if blocker {
+        world.add(Sphere::new(
+            point(0., 3., 0.),
+            1.,
+            Arc::new(Lambertian::from(color(0.5, 0.5, 0.5))),
+        ));
+    }
+    world
+}
+
+fn gray() -> Arc<dyn Material> {
+    Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)))
+}
+
+fn lights(light: Arc<dyn DeltaLight>) -> LightList {
+    let mut lights = LightList::new();
+    lights.delta.push(light);
+    lights
+}
+
+/// The light leaving the floor at `at` toward a viewer low in front of it, under
+/// any blocker, after a single bounce so only direct light contributes.
+fn shade(world: &HittableList, lights: &LightList, at: Point) -> Vec3 {
+    let ray = Ray {
+        origin: at + Vec3(0., 0.5, 5.),
+        direction: Vec3(0., -0.5, -5.),
+        time: 0.0,
+    };
+    let mut rng = IndependentSampler::new(0);
+    ray.send(
+        world,
+        lights,
+        1,
+        &SolidBackground::default(),
+        EPSILON,
+        &mut rng,
+    )
+}
+
+fn assert_close(actual: Vec3, expected: Vec3) {
+    for c in 0..3 {
+        assert!(
+            (actual[c] - expected[c]).abs() < 1e-9,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+}
+
+#[test]
+fn point_lights_fall_off_with_the_square_of_distance() {
+    let light = PointLight::new(point(0., 0., 0.), color(8., 8., 8.));
+    let near = light.sample(point(2., 0., 0.)).unwrap();
+    let far = light.sample(point(0., 4., 0.)).unwrap();
+    assert_close(near.radiance, color(2., 2., 2.));
+    assert_close(far.radiance, color(0.5, 0.5, 0.5));
+    assert_close(near.direction, Vec3(-1., 0., 0.));
+    assert!((far.distance - 4.).abs() < EPSILON);
+}
+
+#[test]
+fn directional_lights_are_the_same_everywhere() {
+    let sun = DirectionalLight::new(Vec3(0., -2., 0.), color(1., 0.9, 0.7));
+    for at in [point(0., 0., 0.), point(100., -30., 7.)] {
+        let sample = sun.sample(at).unwrap();
+        assert_close(sample.direction, Vec3(0., 1., 0.));
+        assert_close(sample.radiance, color(1., 0.9, 0.7));
+        assert_eq!(sample.distance, f64::INFINITY);
+    }
+}
+
+/// A spot 4 units above the origin pointing straight down, full within 20°
+/// of its axis and dark beyond 40°.
+fn spot() -> SpotLight {
+    SpotLight::new(
+        point(0., 4., 0.),
+        Vec3(0., -1., 0.),
+        20.,
+        40.,
+        color(16., 16., 16.),
+    )
+}
+
+/// The point on the floor seen from the spot at `angle` degrees off its axis.
+fn floor_at(angle: f64) -> Point {
+    point(4. * angle.to_radians().tan(), 0., 0.)
+}
+
+#[test]
+fn spot_lights_are_full_inside_the_inner_cone() {
+    let spot = spot();
+    let below = spot.sample(point(0., 0., 0.)).unwrap();
+    assert_close(below.radiance, color(1., 1., 1.));
+    let at = floor_at(15.);
+    let sample = spot.sample(at).unwrap();
+    let distance_squared = (point(0., 4., 0.) - at).length_squared();
+    assert_close(sample.radiance, color(16., 16., 16.) / distance_squared);
+}
+
+#[test]
+fn spot_lights_fade_smoothly_between_the_cones() {
+    let spot = spot();
+    // Halfway between the cosines of the two cone angles, smoothstep gives half.
+    let cosine = (20f64.to_radians().cos() + 40f64.to_radians().cos()) / 2.;
+    assert!((spot.falloff(Vec3(cosine.acos().sin(), -cosine, 0.)) - 0.5).abs() < 1e-12);
+
+    let falloffs: Vec<f64> = [20., 25., 30., 35., 40.]
+        .iter()
+        .map(|angle: &f64| {
+            let radians = angle.to_radians();
+            spot.falloff(Vec3(radians.sin(), -radians.cos(), 0.))
+        })
+        .collect();
+    assert!((falloffs[0] - 1.).abs() < 1e-12);
+    assert!(falloffs.windows(2).all(|pair| pair[1] < pair[0]));
+    assert!(falloffs[4].abs() < 1e-12);
+}
+
+#[test]
+fn spot_lights_are_dark_outside_the_outer_cone() {
+    let spot = spot();
+    assert!(spot.sample(floor_at(45.)).is_none());
+    assert!(spot.sample(point(0., 8., 0.)).is_none());
+}
+
+#[test]
+fn spot_light_pools_are_shadowed() {
+    let spot = lights(Arc::new(SpotLight::new(
+        point(0., 6., 0.),
+        Vec3(0., -1., 0.),
+        20.,
+        40.,
+        color(16., 16., 16.),
+    )));
+    let open = world(gray(), false);
+    let blocked = world(gray(), true);
+    assert!(shade(&open, &spot, point(0., 0., 0.)).0 > 0.);
+    assert_close(shade(&blocked, &spot, point(0., 0., 0.)), color(0., 0., 0.));
+    assert_close(shade(&open, &spot, point(10., 0., 0.)), color(0., 0., 0.));
+}
+
+#[test]
+fn fog_scatters_light_from_inside_the_cone_only() {
+    let mut world = HittableList::new();
+    world.add(ConstantMedium::from_color(
+        parallelepiped(point(-10., -10., -10.), point(10., 10., 10.), gray()),
+        0.2,
+        color(1., 1., 1.),
+    ));
+    let spot = lights(Arc::new(spot()));
+    // Single scattering: each path scatters once in the fog and sees the light.
+    let through = |x: f64| {
+        let ray = Ray {
+            origin: point(x, 2., 9.),
+            direction: Vec3(0., 0., -1.),
+            time: 0.0,
+        };
+        let mut total = 0.;
+        for seed in 0..200 {
+            let mut rng = IndependentSampler::new(seed);
+            total += ray
+                .send(
+                    &world,
+                    &spot,
+                    1,
+                    &SolidBackground::default(),
+                    EPSILON,
+                    &mut rng,
+                )
+                .0;
+        }
+        total
+    };
+    assert!(through(0.) > 0.);
+    assert_eq!(through(5.), 0.);
+}
+
+#[test]
+fn sunlit_diffuse_floor_is_lit_by_irradiance_times_albedo() {
+    let sun = lights(Arc::new(DirectionalLight::new(
+        Vec3(0., -1., 0.),
+        color(PI, PI, PI),
+    )));
+    let shaded = shade(&world(gray(), false), &sun, point(0., 0., 0.));
+    assert_close(shaded, color(0.5, 0.5, 0.5));
+}
+
+#[test]
+fn point_light_on_a_floor_follows_inverse_square_and_cosine() {
+    let lamp = lights(Arc::new(PointLight::new(
+        point(0., 2., 0.),
+        color(PI, PI, PI),
+    )));
+    let world = world(gray(), false);
+    assert_close(
+        shade(&world, &lamp, point(0., 0., 0.)),
+        color(0.125, 0.125, 0.125),
+    );
+    // Two units to the side: distance √8, and the light arrives at 45°.
+    let expected = 0.5 / 8. * (0.5f64).sqrt();
+    assert_close(
+        shade(&world, &lamp, point(2., 0., 0.)),
+        color(expected, expected, expected),
+    );
+}
+
+#[test]
+fn shadows_from_a_sun_are_parallel() {
+    let sun = lights(Arc::new(DirectionalLight::new(
+        Vec3(0., -1., 0.),
+        color(1., 1., 1.),
+    )));
+    let world = world(gray(), true);
+    // Straight under the sphere, and still under its edge: both in shadow.
+    assert_close(shade(&world, &sun, point(0., 0., 0.)), color(0., 0., 0.));
+    assert_close(shade(&world, &sun, point(0.95, 0., 0.)), color(0., 0., 0.));
+    // Just past the edge, fully lit: the shadow has no penumbra.
+    assert!(shade(&world, &sun, point(1.05, 0., 0.)).0 > 0.1);
+}
+
+#[test]
+fn point_lights_are_not_blocked_by_objects_behind_them() {
+    let lamp = lights(Arc::new(PointLight::new(
+        point(0., 1.5, 0.),
+        color(1., 1., 1.),
+    )));
+    // The sphere sits above the lamp, between it and nothing.
+    let shaded = shade(&world(gray(), true), &lamp, point(0., 0., 0.));
+    assert!(shaded.0 > 0.);
+}
+
+#[test]
+fn specular_surfaces_ignore_delta_lights() {
+    let sun = lights(Arc::new(DirectionalLight::new(
+        Vec3(0., -1., 0.),
+        color(10., 10., 10.),
+    )));
+    let mirror: Arc<dyn Material> = Arc::new(Metal::new(color(1., 1., 1.), 0.));
+    assert_close(
+        shade(&world(mirror, false), &sun, point(0., 0., 0.)),
+        color(0., 0., 0.),
+    );
+}
+
+#[test]
+fn rays_never_hit_delta_lights() {
+    let lamp = lights(Arc::new(PointLight::new(
+        point(0., 0., -5.),
+        color(100., 100., 100.),
+    )));
+    let ray = Ray {
+        origin: point(0., 0., 0.),
+        direction: Vec3(0., 0., -1.),
+        time: 0.0,
+    };
+    let mut rng = IndependentSampler::new(0);
+    let seen = ray.send(
+        &HittableList::new(),
+        &lamp,
+        4,
+        &SolidBackground(color(0.1, 0.2, 0.3)),
+        EPSILON,
+        &mut rng,
+    );
+    assert_close(seen, color(0.1, 0.2, 0.3));
+}
+
+#[test]
+fn delta_lights_survive_a_scene_file_round_trip() {
+    let dir = std::env::temp_dir().join(format!("ray-tracer-lights-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("scene.toml");
+    let mut scene = scenes::build("sunlit_spheres").unwrap();
+    scene.add_delta_light(Arc::new(PointLight::new(
+        point(1., 2., 3.),
+        color(4., 5., 6.),
+    )));
+    scene.add_delta_light(Arc::new(spot()));
+    let saved = scene.describe().unwrap();
+    saved.save(&path).unwrap();
+
+    let loaded = scenes::Scene::from_path(&path);
+    fs::remove_dir_all(&dir).unwrap();
+    let loaded = loaded.unwrap();
+    assert_eq!(loaded.lights.delta.len(), 3);
+    assert_eq!(
+        serde_json::to_string(&loaded.describe().unwrap().lights).unwrap(),
+        serde_json::to_string(&saved.lights).unwrap()
+    );
+}
+
+#[test]
+fn degenerate_lights_are_reported() {
+    let mut scene = scenes::build("sunlit_spheres").unwrap();
+    scene.add_delta_light(Arc::new(DirectionalLight::new(
+        Vec3(0., 0., 0.),
+        color(1., 1., 1.),
+    )));
+    scene.add_delta_light(Arc::new(PointLight::new(
+        point(0., 1., 0.),
+        color(-1., 1., 1.),
+    )));
+    scene.add_delta_light(Arc::new(SpotLight::new(
+        point(0., 1., 0.),
+        Vec3(0., -1., 0.),
+        30.,
+        20.,
+        color(1., 1., 1.),
+    )));
+    let issues = scene.validate();
+    assert!(issues.iter().any(|issue| issue.object == "lights[1]"));
+    assert!(issues.iter().any(|issue| issue.object == "lights[2]"));
+    assert!(issues.iter().any(|issue| issue.object == "lights[3]"));
+    assert!(!issues.iter().any(|issue| issue.object == "lights[0]"));
+}
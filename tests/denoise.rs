@@ -0,0 +1,168 @@
+//! Post-processing: cameras run their filters in order, and the bilateral
+//! denoiser smooths noise without reading past the image or across the
+//! edges the normal and albedo passes show.
+
+use std::sync::Arc;
+
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use ray_tracer::{color, scenes, Aovs, BilateralFilter, ImageBuffer, PostProcess, Vec3};
+
+/// An image `width` by `height` of `value` give or take up to `noise`.
+fn noisy(width: usize, height: usize, value: impl Fn(usize) -> f64, noise: f64) -> ImageBuffer {
+    let mut rng = SmallRng::seed_from_u64(7);
+    let mut image = ImageBuffer::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let v = value(x) + rng.gen_range(-noise..=noise);
+            image.set(x, y, color(v, v, v));
+        }
+    }
+    image
+}
+
+fn variance(values: impl Iterator<Item = f64> + Clone) -> f64 {
+    let n = values.clone().count() as f64;
+    let mean = values.clone().sum::<f64>() / n;
+    values.map(|v| (v - mean) * (v - mean)).sum::<f64>() / n
+}
+
+#[test]
+fn flat_noise_is_smoothed_and_every_pixel_stays_in_range() {
+    let image = noisy(24, 16, |_| 0.5, 0.2);
+    let filtered = BilateralFilter::default().apply(&image, None);
+    assert_eq!((filtered.width, filtered.height), (24, 16));
+    let red = |image: &ImageBuffer| image.pixels.iter().map(|p| p.0).collect::<Vec<_>>();
+    let before = variance(red(&image).into_iter());
+    let after = variance(red(&filtered).into_iter());
+    assert!(after < before / 4.0, "{} -> {}", before, after);
+    // Averages never leave the range of what they average, corners included.
+    assert!(filtered.pixels.iter().all(|p| (0.3..=0.7).contains(&p.0)));
+}
+
+#[test]
+fn tiny_images_and_zero_sigmas_are_left_alone() {
+    let image = noisy(1, 1, |_| 0.5, 0.2);
+    let filtered = BilateralFilter::default().apply(&image, None);
+    assert_eq!(filtered.pixels[0].0, image.pixels[0].0);
+
+    let image = noisy(5, 3, |_| 0.5, 0.2);
+    for filter in [
+        BilateralFilter::new(0.0, 1.0),
+        BilateralFilter::new(2.0, 0.0),
+    ] {
+        let filtered = filter.apply(&image, None);
+        assert!(filtered
+            .pixels
+            .iter()
+            .zip(&image.pixels)
+            .all(|(a, b)| a.0 == b.0));
+    }
+    assert!(!BilateralFilter::new(-1.0, 1.0).is_valid());
+    assert!(!BilateralFilter::new(2.0, f64::NAN).is_valid());
+    assert!(BilateralFilter::new(2.0, f64::INFINITY).is_valid());
+}
+
+#[test]
+fn normals_keep_an_edge_that_color_alone_would_blur() {
+    // Two walls meeting down the middle, dark and bright, both noisy.
+    let (width, height) = (16, 8);
+    let image = noisy(width, height, |x| if x < 8 { 0.3 } else { 0.7 }, 0.2);
+    let mut aovs = Aovs::new(width, height);
+    for (i, normal) in aovs.normal.iter_mut().enumerate() {
+        *normal = if i % width < 8 {
+            Vec3(1.0, 0.0, 0.0)
+        } else {
+            Vec3(0.0, 0.0, 1.0)
+        };
+    }
+    // Colors alone can't tell the walls apart.
+    let filter = BilateralFilter::new(2.0, f64::INFINITY);
+    let edge = |image: &ImageBuffer| {
+        let column = |x| (0..height).map(|y| image.get(x, y).0).sum::<f64>() / height as f64;
+        column(8) - column(7)
+    };
+    let blurred = filter.apply(&image, None);
+    let guided = filter.apply(&image, Some(&aovs));
+    assert!(edge(&blurred) < 0.25, "{}", edge(&blurred));
+    assert!(edge(&guided) > 0.3, "{}", edge(&guided));
+
+    // Passes of another size are ignored rather than misread.
+    let other = Aovs::new(width / 2, height / 2);
+    let unguided = filter.apply(&image, Some(&other));
+    assert!(unguided
+        .pixels
+        .iter()
+        .zip(&blurred.pixels)
+        .all(|(a, b)| a.0 == b.0));
+}
+
+#[test]
+fn alpha_is_filtered_with_the_color() {
+    let mut image = noisy(6, 6, |_| 0.5, 0.0);
+    image.set_alpha(0, 0, 0.0);
+    let filtered = BilateralFilter::new(1.0, f64::INFINITY).apply(&image, None);
+    let alpha = filtered.alpha.as_ref().unwrap();
+    assert!(alpha[0] > 0.0 && alpha[0] < 1.0);
+    assert!(alpha[35] == 1.0);
+}
+
+struct Add(f64);
+
+impl PostProcess for Add {
+    fn apply(&self, image: &ImageBuffer, _: Option<&Aovs>) -> ImageBuffer {
+        let mut image = image.clone();
+        for pixel in &mut image.pixels {
+            *pixel = *pixel * 2.0 + color(self.0, self.0, self.0);
+        }
+        image
+    }
+}
+
+#[test]
+fn cameras_run_their_filters_in_order() {
+    let mut scene = scenes::cornell_box();
+    let image = noisy(2, 2, |_| 1.0, 0.0);
+    assert_eq!(
+        scene.camera.post_process(image.clone(), None).pixels[0].0,
+        1.0
+    );
+    scene
+        .camera
+        .add_post_process(Arc::new(Add(1.0)))
+        .add_post_process(Arc::new(Add(0.5)));
+    // (1 * 2 + 1) * 2 + 0.5, not (1 * 2 + 0.5) * 2 + 1.
+    assert_eq!(scene.camera.post_process(image, None).pixels[0].0, 6.5);
+}
+
+#[test]
+fn denoising_a_low_sample_render_brings_it_closer_to_a_converged_one() {
+    let render = |samples| {
+        let mut scene = scenes::cornell_box();
+        scene
+            .camera
+            .set_image_width(32)
+            .set_aa_samples(samples)
+            .set_max_depth(8)
+            .set_seed(Some(2));
+        scene.render_with_aovs()
+    };
+    let (reference, _) = render(256);
+    let (noisy, aovs) = render(4);
+    let denoised = BilateralFilter::default().apply(&noisy, Some(&aovs));
+    // Compared as displayed, past which the light and fireflies are white.
+    let shown = |c: Vec3| Vec3(c.0.min(1.0), c.1.min(1.0), c.2.min(1.0));
+    let error = |image: &ImageBuffer| {
+        image
+            .pixels
+            .iter()
+            .zip(&reference.pixels)
+            .map(|(a, b)| (shown(*a) - shown(*b)).length_squared())
+            .sum::<f64>()
+    };
+    assert!(
+        error(&denoised) < error(&noisy) / 2.0,
+        "{} -> {}",
+        error(&noisy),
+        error(&denoised)
+    );
+}
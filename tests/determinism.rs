@@ -0,0 +1,94 @@
+//! A seeded render is a pure function of its seed: pixels computed on many
+//! threads in any order come out bit-for-bit the same as a sequential render.
+
+use std::{sync::Mutex, thread};
+
+use ray_tracer::{
+    scenes::{self, Scene},
+    ImageBuffer,
+};
+
+const SEED: u64 = 17;
+const THREADS: usize = 16;
+
+/// Smoke exercises medium sampling; the glass sphere exercises reflect-or-refract.
+fn scene(name: &str) -> Scene {
+    let mut scene = scenes::build(name).unwrap();
+    scene
+        .camera
+        .set_image_width(24)
+        .set_aa_samples(4)
+        .set_max_depth(8)
+        .set_seed(Some(SEED));
+    scene
+}
+
+fn ppm(image: &ImageBuffer) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    image.write_ppm(&mut bytes).unwrap();
+    bytes
+}
+
+/// Renders with every thread taking rows round-robin, last row first.
+fn render_threaded(scene: &Scene) -> ImageBuffer {
+    let reference = scene.render_image();
+    let (width, height) = (reference.width, reference.height);
+    let image = Mutex::new(ImageBuffer::new(width, height));
+    thread::scope(|s| {
+        for worker in 0..THREADS {
+            let image = &image;
+            s.spawn(move || {
+                for y in (0..height).rev().skip(worker).step_by(THREADS) {
+                    for x in (0..width).rev() {
+                        let pixel = scene.camera.pixel_color(
+                            &scene.world,
+                            &scene.lights,
+                            x as i32,
+                            y as i32,
+                            SEED,
+                        );
+                        image.lock().unwrap().set(x, y, pixel);
+                    }
+                }
+            });
+        }
+    });
+    image.into_inner().unwrap()
+}
+
+fn assert_scheduling_independent(name: &str) {
+    let scene = scene(name);
+    let sequential = scene.render_image();
+    let threaded = render_threaded(&scene);
+    assert!(
+        ppm(&sequential) == ppm(&threaded),
+        "{}: threaded render differs from the sequential one",
+        name
+    );
+    for (a, b) in sequential.pixels.iter().zip(&threaded.pixels) {
+        for c in 0..3 {
+            assert_eq!(a[c].to_bits(), b[c].to_bits(), "{}", name);
+        }
+    }
+}
+
+#[test]
+fn smoke_is_independent_of_thread_count() {
+    assert_scheduling_independent("cornell_smoke");
+}
+
+#[test]
+fn glass_is_independent_of_thread_count() {
+    assert_scheduling_independent("material_spheres");
+}
+
+#[test]
+fn sunlight_is_independent_of_thread_count() {
+    assert_scheduling_independent("sunlit_spheres");
+}
+
+#[test]
+fn same_seed_renders_identically() {
+    let scene = scene("cornell_smoke");
+    assert!(ppm(&scene.render_image()) == ppm(&scene.render_image()));
+}
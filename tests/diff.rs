@@ -0,0 +1,121 @@
+//! Comparing images: the error metrics of `ImageBuffer::diff`, its false
+//! color image, and loading saved renders back to compare them.
+
+use std::fs;
+
+use ray_tracer::{color, heat_color, scenes, DiffError, ImageBuffer};
+
+fn image(pixels: &[(f64, f64, f64)], width: usize) -> ImageBuffer {
+    let mut image = ImageBuffer::new(width, pixels.len() / width);
+    for (i, &(r, g, b)) in pixels.iter().enumerate() {
+        image.set(i % width, i / width, color(r, g, b));
+    }
+    image
+}
+
+#[test]
+fn identical_images_have_no_error() {
+    let a = image(&[(0.1, 0.2, 0.3), (1.0, 0.5, 0.0)], 2);
+    let report = a.diff(&a.clone()).unwrap();
+    assert_eq!(
+        (report.mean_error, report.rms_error, report.max_error),
+        (0.0, 0.0, 0.0)
+    );
+    assert_eq!(report.pixels_over(0.0), 0);
+    assert!(report.image(None).pixels.iter().all(|p| p.length() == 0.0));
+}
+
+#[test]
+fn metrics_average_over_every_channel() {
+    let a = image(
+        &[
+            (0.0, 0.0, 0.0),
+            (0.5, 0.5, 0.5),
+            (0.2, 0.2, 0.2),
+            (0.0, 0.0, 0.0),
+        ],
+        2,
+    );
+    let b = image(
+        &[
+            (0.0, 0.0, 0.0),
+            (0.5, 0.5, 0.5),
+            (0.2, 0.8, 0.2),
+            (0.3, 0.0, 0.0),
+        ],
+        2,
+    );
+    let report = a.diff(&b).unwrap();
+    // 0.6 and 0.3 across twelve channels.
+    assert!((report.mean_error - 0.9 / 12.0).abs() < 1e-12);
+    assert!((report.rms_error - (0.45_f64 / 12.0).sqrt()).abs() < 1e-12);
+    assert!((report.max_error - 0.6).abs() < 1e-12);
+    assert_eq!(report.max_at, (0, 1));
+    assert_eq!(report.errors.len(), 4);
+    assert_eq!(report.pixels_over(0.1), 2);
+    assert_eq!(report.pixels_over(0.5), 1);
+
+    // The largest difference is red, the others on the way there.
+    let false_color = report.image(None);
+    assert_eq!(false_color.get(0, 1).0, 1.0);
+    assert_eq!(false_color.get(0, 1).1, 0.0);
+    assert_eq!(false_color.get(1, 1).to_gamma().to_bytes(), [0, 255, 0]);
+    assert_eq!(report.image(Some(1.2)).get(0, 1).1, heat_color(0.5).1);
+}
+
+#[test]
+fn sizes_must_match() {
+    let error = ImageBuffer::new(4, 3)
+        .diff(&ImageBuffer::new(3, 4))
+        .expect_err("the sizes differ");
+    assert_eq!(
+        error,
+        DiffError::SizeMismatch {
+            left: (4, 3),
+            right: (3, 4)
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "can't compare a 4 by 3 image with a 3 by 4 one"
+    );
+}
+
+#[test]
+fn hdr_buffers_compare_in_linear_color() {
+    let bright = image(&[(4.0, 4.0, 4.0)], 1);
+    let white = image(&[(1.0, 1.0, 1.0)], 1);
+    assert_eq!(bright.diff(&white).unwrap().max_error, 3.0);
+    // Written out, both are as white as a file gets.
+    assert_eq!(
+        bright.encoded().diff(&white.encoded()).unwrap().max_error,
+        0.0
+    );
+    assert_eq!(white.encoded().get(0, 0).0, 1.0);
+}
+
+#[test]
+fn saved_renders_load_back_to_the_same_bytes() {
+    let mut scene = scenes::build("cornell_box").unwrap();
+    scene
+        .camera
+        .set_image_width(8)
+        .set_aa_samples(2)
+        .set_seed(Some(4));
+    let render = scene.render_image();
+    let dir = std::env::temp_dir().join(format!("ray-tracer-diff-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut formats = vec!["ppm"];
+    if cfg!(feature = "image") {
+        formats.push("png");
+    }
+    for format in formats {
+        let path = dir.join(format!("box.{}", format));
+        render.save_with_metadata(&path, "seed 4").unwrap();
+        let loaded = ImageBuffer::load(&path).unwrap();
+        let report = render.encoded().diff(&loaded.encoded()).unwrap();
+        assert_eq!(report.max_error, 0.0, "{}", format);
+        assert_eq!(loaded.to_rgba8(), render.to_rgba8(), "{}", format);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
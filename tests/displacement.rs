@@ -0,0 +1,185 @@
+//! Displacement: meshes are subdivided and moved along their normals by a
+//! height texture, changing their outline and not only their shading.
+
+use std::sync::Arc;
+
+use ray_tracer::{
+    color, point, scenes::SceneDesc, Color, Hittable, IndependentSampler, Interval, Lambertian,
+    Material, MeshData, NoiseTexture, Point, Ray, Texture, TriangleMesh, Vec3,
+};
+
+fn gray() -> Arc<dyn Material> {
+    Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)))
+}
+
+/// A 4 by 4 quad on the XZ plane, facing up.
+fn floor() -> TriangleMesh {
+    TriangleMesh::new(
+        MeshData::quad(point(0., 0., 0.), (Vec3(0., 0., 4.), Vec3(4., 0., 0.))),
+        gray(),
+    )
+}
+
+/// Raised rows of bricks with mortar between them, from the UVs alone.
+struct Bricks;
+
+impl Texture for Bricks {
+    fn value(&self, u: f64, v: f64, _p: &Point) -> Color {
+        let row = (v * 8.0).floor();
+        let offset = if row as i64 % 2 == 0 { 0.0 } else { 0.5 };
+        let (x, y) = ((u * 4.0 + offset).fract(), (v * 8.0).fract());
+        let mortar = x < 0.1 || y < 0.2;
+        let h = if mortar { 0.0 } else { 1.0 };
+        color(h, h, h)
+    }
+}
+
+/// A strip 4 long in X and a quarter deep in Z, facing up.
+fn strip() -> TriangleMesh {
+    TriangleMesh::new(
+        MeshData::quad(point(0., 0., 0.), (Vec3(0., 0., 0.25), Vec3(4., 0., 0.))),
+        gray(),
+    )
+}
+
+/// The highest of `levels` heights up to `top` at which a ray along +Z, at
+/// `x`, still hits the mesh: its outline seen edge on.
+fn outline(mesh: &TriangleMesh, x: f64, levels: usize, top: f64) -> usize {
+    let mut rng = IndependentSampler::new(0);
+    (0..levels)
+        .rev()
+        .find(|&level| {
+            let ray = Ray {
+                origin: point(x, top * (level as f64 + 0.5) / levels as f64, -1.),
+                direction: Vec3(0., 0., 1.),
+                time: 0.0,
+            };
+            mesh.hit_any(&ray, Interval::new(0.001, f64::INFINITY), &mut rng)
+        })
+        .map_or(0, |level| level + 1)
+}
+
+#[test]
+fn displaced_quads_have_bumpy_outlines() {
+    let flat = strip();
+    let mut bumpy = strip();
+    let report = bumpy.displace(&NoiseTexture::new(2.0), 0.5, 6);
+    assert_eq!(report.triangles_before, 2);
+    assert_eq!(report.subdivisions, 6);
+    assert_eq!(report.triangles_after, 2 * 4usize.pow(6));
+    assert_eq!(bumpy.triangle_count(), report.triangles_after);
+
+    let xs: Vec<f64> = (0..40).map(|i| 0.05 + i as f64 * 0.1).collect();
+    assert!(
+        xs.iter().all(|&x| outline(&flat, x, 20, 0.5) == 0),
+        "a flat quad has no outline seen edge on"
+    );
+    let profile: Vec<usize> = xs.iter().map(|&x| outline(&bumpy, x, 20, 0.5)).collect();
+    let (lowest, highest) = (
+        *profile.iter().min().unwrap(),
+        *profile.iter().max().unwrap(),
+    );
+    assert!(
+        highest >= lowest + 5,
+        "outline {:?} is nearly flat",
+        profile
+    );
+    let mut levels = profile.clone();
+    levels.sort();
+    levels.dedup();
+    assert!(levels.len() >= 5, "outline {:?} has few heights", profile);
+}
+
+#[test]
+fn bricks_raise_the_faces_and_leave_the_mortar() {
+    let mut wall = floor();
+    wall.displace(&Bricks, 0.2, 6);
+    let data = wall.data();
+    let uvs = data.uvs.as_ref().unwrap();
+    let mut raised = 0;
+    for (p, &(u, v)) in data.positions.iter().zip(uvs) {
+        // The quad's UVs run along Z and X.
+        assert!((p.2 - u * 4.0).abs() < 1e-9 && (p.0 - v * 4.0).abs() < 1e-9);
+        let expected = Bricks.value(u, v, p).0 * 0.2;
+        assert!((p.1 - expected).abs() < 1e-9, "{:?} at ({}, {})", p, u, v);
+        raised += (p.1 > 0.1) as usize;
+    }
+    assert!(raised > data.positions.len() / 3 && raised < data.positions.len());
+}
+
+#[test]
+fn subdivision_shares_edge_midpoints() {
+    let mut quad = floor();
+    let report = quad.displace(&Bricks, 0.0, 4);
+    assert_eq!(report.subdivisions, 4);
+    // A watertight grid of 17 by 17 vertices, all still on the plane.
+    assert_eq!(quad.vertex_count(), 17 * 17);
+    assert!(quad.data().positions.iter().all(|p| p.1 == 0.0));
+    let normals = quad.data().normals.as_ref().unwrap();
+    assert!(normals.iter().all(|n| (n.1 - 1.0).abs() < 1e-9));
+}
+
+#[test]
+fn fine_meshes_are_not_subdivided_again() {
+    let mut quad = floor();
+    assert_eq!(quad.displace(&Bricks, 0.0, 0).triangles_after, 2);
+    let first = quad.displace(&Bricks, 0.0, 3);
+    assert_eq!(first.triangles_after, 2 * 64);
+    let second = quad.displace(&Bricks, 0.0, 3);
+    assert_eq!(second.subdivisions, 0);
+    assert_eq!(second.triangles_before, second.triangles_after);
+}
+
+#[test]
+fn face_materials_follow_their_pieces() {
+    let red: Arc<dyn Material> = Arc::new(Lambertian::from(color(0.9, 0.1, 0.1)));
+    let mut quad = TriangleMesh::with_materials(
+        MeshData::quad(point(0., 0., 0.), (Vec3(0., 0., 4.), Vec3(4., 0., 0.))),
+        gray(),
+        vec![gray(), red],
+        vec![0, 1],
+    );
+    quad.displace(&Bricks, 0.0, 2);
+    let ids = quad.material_ids().unwrap();
+    assert_eq!(ids.len(), 32);
+    assert!(ids[..16].iter().all(|&id| id == 0));
+    assert!(ids[16..].iter().all(|&id| id == 1));
+}
+
+#[test]
+fn scene_files_displace_loaded_meshes() {
+    let text = r#"
+        [camera]
+        aspect_ratio = 1.0
+        image_width = 8
+        vfov = 40.0
+        look_from = [0.0, 3.0, -4.0]
+        look_at = [0.0, 0.0, 0.0]
+        up = [0.0, 1.0, 0.0]
+        aa_samples = 1
+        max_depth = 2
+
+        [textures]
+        marble = { type = "noise", scale = 4.0 }
+
+        [materials]
+        gray = { type = "lambertian", albedo = [0.5, 0.5, 0.5] }
+
+        [[objects]]
+        type = "mesh"
+        path = "resources/models/gradient_cube.ply"
+        material = "gray"
+        displace = { texture = "marble", scale = 0.1, max_subdivision = 3 }
+    "#;
+    let desc: SceneDesc = toml::from_str(text).unwrap();
+    let scene = desc.build().unwrap();
+    let saved = toml::to_string(&scene.describe().unwrap()).unwrap();
+    assert!(saved.contains("max_subdivision = 3"), "{}", saved);
+    let again: SceneDesc = toml::from_str(&saved).unwrap();
+    let rebuilt = again.build().unwrap();
+    let (a, b) = (scene.world.bound(), rebuilt.world.bound());
+    for axis in 0..3 {
+        assert!((a.intervals[axis].start - b.intervals[axis].start).abs() < 1e-9);
+        assert!((a.intervals[axis].end - b.intervals[axis].end).abs() < 1e-9);
+    }
+}
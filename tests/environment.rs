@@ -0,0 +1,229 @@
+//! Environment lighting: the distribution shadow rays toward an HDR image
+//! are drawn from, reading and writing Radiance HDR files, and how much
+//! sampling a small sun directly cuts the noise.
+
+use std::{f64::consts::PI, fs, sync::Arc};
+
+use ray_tracer::{
+    color, direction_to_uv, point, read_image_metadata, scenes::Scene, uv_to_direction, Camera,
+    Color, Environment, HittableList, ImageBuffer, IndependentSampler, Lambertian, Parallelogram,
+    Pdf, Point, Sphere, Texture, Vec3,
+};
+
+/// A dim blue sky, `width` by half as high, with one texel of sun at
+/// (`width` / 3, `width` / 8).
+fn sky(width: usize, sun: f64) -> ImageBuffer {
+    let mut image = ImageBuffer::new(width, width / 2);
+    image.pixels.fill(color(0.15, 0.2, 0.3));
+    image.set(width / 3, width / 8, color(sun, sun * 0.9, sun * 0.8));
+    image
+}
+
+#[test]
+fn the_pdf_integrates_to_one_over_the_sphere() {
+    let mut image = sky(32, 200.0);
+    for x in 0..32 {
+        image.set(x, 15, color(0.0, 0.0, 0.0));
+    }
+    let environment = Environment::new(image);
+    let pdf = environment.pdf().unwrap();
+    // Four points a texel, at their centers in u and v.
+    let (columns, rows) = (128, 64);
+    let mut total = 0.0;
+    for row in 0..rows {
+        let v = (row as f64 + 0.5) / rows as f64;
+        let solid_angle = 2.0 * PI * PI * (v * PI).sin() / (columns * rows) as f64;
+        for column in 0..columns {
+            let u = (column as f64 + 0.5) / columns as f64;
+            total += pdf.value(uv_to_direction(u, v)) * solid_angle;
+        }
+    }
+    assert!((total - 1.0).abs() < 0.01, "{}", total);
+
+    for (u, v) in [(0.1, 0.2), (0.5, 0.5), (0.9, 0.95), (0.3, 0.01)] {
+        let (found_u, found_v) = direction_to_uv(uv_to_direction(u, v));
+        assert!((found_u - u).abs() < 1e-9 && (found_v - v).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn samples_gather_where_the_sun_is() {
+    let environment = Environment::new(sky(64, 500.0));
+    let pdf = environment.pdf().unwrap();
+    let mut rng = IndependentSampler::new(7);
+    let draws = 2000;
+    let on_sun = (0..draws)
+        .filter(|_| {
+            let direction = pdf.generate(&mut rng);
+            assert!((direction.length() - 1.0).abs() < 1e-9);
+            environment.radiance(direction).0 > 100.0
+        })
+        .count();
+    // A 2048th of the image, the sun holds 57% of its weight.
+    let share = on_sun as f64 / draws as f64;
+    assert!((0.54..0.60).contains(&share), "{}", share);
+
+    let mut uniform = Environment::new(sky(64, 500.0));
+    uniform.importance_sampled = false;
+    assert!(uniform.pdf().is_none());
+    assert!(Environment::new(ImageBuffer::new(8, 4)).pdf().is_none());
+}
+
+#[test]
+fn black_texels_are_never_drawn() {
+    // Only the top half is lit.
+    let mut image = ImageBuffer::new(16, 8);
+    for y in 0..4 {
+        for x in 0..16 {
+            image.set(x, y, color(1.0, 1.0, 1.0));
+        }
+    }
+    let environment = Environment::new(image);
+    let pdf = environment.pdf().unwrap();
+    assert_eq!(pdf.value(Vec3(0.0, -1.0, 0.0)), 0.0);
+    assert_eq!(pdf.value(Vec3(0.6, -0.8, 0.0)), 0.0);
+    // Even lighting over the upper hemisphere: 1 / 2π everywhere in it.
+    assert!((pdf.value(Vec3(0.0, 0.6, 0.8)) - 0.5 / PI).abs() < 0.05);
+    let mut rng = IndependentSampler::new(3);
+    for _ in 0..500 {
+        let direction = pdf.generate(&mut rng);
+        assert!(direction.y() >= 0.0);
+        assert!(pdf.value(direction) > 0.0);
+        assert_eq!(environment.radiance(direction).0, 1.0);
+    }
+}
+
+#[test]
+fn hdr_images_round_trip() {
+    let mut image = ImageBuffer::new(20, 3);
+    for (i, pixel) in image.pixels.iter_mut().enumerate() {
+        let i = i as f64;
+        *pixel = color(0.01 * i, 1.5 + i, 300.0 / (1.0 + i));
+    }
+    let mut bytes = Vec::new();
+    image.write_hdr(&mut bytes, "seed: 4\nscene: sky").unwrap();
+    let read = ImageBuffer::read_hdr(bytes.as_slice()).unwrap();
+    assert_eq!((read.width, read.height), (20, 3));
+    for (a, b) in read.pixels.iter().zip(&image.pixels) {
+        // Each channel to within 1% of the brightest.
+        let brightest = b.0.max(b.1).max(b.2);
+        assert!((*a - *b).length() < 0.01 * brightest, "{:?} {:?}", a, b);
+    }
+
+    // Run-length encoded, as most HDR files are: a run of 8 and 8 literals.
+    let mut encoded = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 16\n".to_vec();
+    encoded.extend([2, 2, 0, 16]);
+    for channel in [64u8, 128, 192, 129] {
+        encoded.extend([128 + 8, channel, 8, 0, 0, 0, 0, 0, 0, 0, 0]);
+    }
+    let read = ImageBuffer::read_hdr(encoded.as_slice()).unwrap();
+    assert!((read.get(0, 0) - color(0.5, 1.0, 1.5)).length() < 0.02);
+    assert_eq!(read.get(15, 0).length(), 0.0);
+
+    let path = std::env::temp_dir().join(format!("ray-tracer-env-{}.hdr", std::process::id()));
+    image.save_with_metadata(&path, "seed: 4").unwrap();
+    assert_eq!(read_image_metadata(&path).unwrap(), "seed: 4\n");
+    assert_eq!(ImageBuffer::load(&path).unwrap().width, 20);
+    fs::remove_file(&path).unwrap();
+}
+
+/// A white ball on a grey floor under a small sun, as a 16 by 10 image.
+fn sunlit_ball(importance_sampled: bool, samples: i32, seed: u64) -> ImageBuffer {
+    let mut world = HittableList::new();
+    let white = Arc::new(Lambertian::from(color(0.8, 0.8, 0.8)));
+    world.add(Sphere::new(point(0., 1., 0.), 1., white));
+    world.add(Parallelogram::new(
+        point(-10., 0., -10.),
+        (Vec3(0., 0., 20.), Vec3(20., 0., 0.)),
+        Arc::new(Lambertian::from(color(0.5, 0.5, 0.5))),
+    ));
+    let mut camera = Camera::new(
+        1.6,
+        16,
+        40.,
+        point(0., 3., -8.),
+        point(0., 0.5, 0.),
+        Vec3(0., 1., 0.),
+        samples,
+        4,
+    );
+    camera.set_seed(Some(seed));
+    let mut scene = Scene::new(camera, world);
+    let mut environment = Environment::new(sky(64, 500.0));
+    environment.importance_sampled = importance_sampled;
+    scene.set_environment(environment);
+    scene.render_accumulation().unwrap().image()
+}
+
+fn mean(image: &ImageBuffer) -> f64 {
+    image.pixels.iter().map(Color::luminance).sum::<f64>() / image.pixels.len() as f64
+}
+
+fn mean_squared_error(image: &ImageBuffer, reference: &ImageBuffer) -> f64 {
+    image
+        .pixels
+        .iter()
+        .zip(&reference.pixels)
+        .map(|(a, b)| (*a - *b).length_squared())
+        .sum::<f64>()
+        / image.pixels.len() as f64
+}
+
+#[test]
+fn sampling_the_sun_cuts_the_noise_without_changing_the_image() {
+    let reference = sunlit_ball(true, 512, 1);
+    let sampled = sunlit_ball(true, 64, 2);
+    let uniform = sunlit_ball(false, 64, 2);
+    let (sampled_error, uniform_error) = (
+        mean_squared_error(&sampled, &reference),
+        mean_squared_error(&uniform, &reference),
+    );
+    assert!(
+        sampled_error * 100.0 < uniform_error,
+        "{} {}",
+        sampled_error,
+        uniform_error
+    );
+    // Both converge to the same image, though uniform sampling takes so long
+    // finding the sun that it needs many more samples to show it.
+    let many_uniform = sunlit_ball(false, 4096, 3);
+    let ratio = mean(&many_uniform) / mean(&reference);
+    assert!((0.9..1.1).contains(&ratio), "{}", ratio);
+}
+
+/// (u, v) as red and green, to see where a texture lands.
+struct UvTexture;
+
+impl Texture for UvTexture {
+    fn value(&self, u: f64, v: f64, _p: &Point) -> Color {
+        color(u, v, 0.0)
+    }
+}
+
+#[test]
+fn textures_wrap_around_the_scene_as_around_a_sphere() {
+    let environment = Environment::from_texture(&UvTexture, 256);
+    assert_eq!(
+        (environment.image.width, environment.image.height),
+        (256, 128)
+    );
+    let sphere = Sphere::new(
+        point(0., 0., 0.),
+        1.,
+        Arc::new(Lambertian::from(color(1., 1., 1.))),
+    );
+    for direction in [
+        Vec3(1.0, 0.2, 0.3),
+        Vec3(-0.4, -0.8, 0.1),
+        Vec3(0.1, 0.5, -1.0),
+    ] {
+        let direction = direction.unit();
+        let (u, v) = sphere.get_uv(&direction);
+        let seen = environment.radiance(direction);
+        assert!(
+            (seen.0 - u).abs() < 0.01 && (seen.1 - v).abs() < 0.01,
+            "{:?}",
+            direction
+        );
+    }
+}
@@ -0,0 +1,75 @@
+//! Loading failures surface as errors naming the file, not panics.
+
+use std::{fs, path::Path, sync::Arc};
+
+use ray_tracer::{
+    color, load_model, scenes, AssetResolver, Error, Lambertian, LoadError, TextureRegistry,
+};
+
+#[test]
+fn missing_texture_reports_the_locations_tried() {
+    let resolver = AssetResolver {
+        base_dir: Some("scenes".into()),
+        search_path: vec!["assets".into()],
+    };
+    let error = TextureRegistry::global()
+        .load(Path::new("textures/missing.png"), &resolver)
+        .err()
+        .expect("loading a missing texture should fail");
+
+    match &error {
+        Error::MissingAsset(e) => {
+            assert_eq!(e.reference, Path::new("textures/missing.png"));
+            assert_eq!(
+                e.tried,
+                [
+                    Path::new("scenes/textures/missing.png"),
+                    Path::new("assets/textures/missing.png")
+                ]
+            );
+        }
+        other => panic!("expected Error::MissingAsset, got {:?}", other),
+    }
+    assert_eq!(
+        error.to_string(),
+        "file not found: textures/missing.png \
+         (tried scenes/textures/missing.png, assets/textures/missing.png)"
+    );
+}
+
+#[test]
+fn obj_with_point_geometry_is_rejected() {
+    let dir = std::env::temp_dir().join(format!("ray-tracer-errors-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("points.obj");
+    fs::write(&path, "v 0 0 0\nv 1 0 0\nv 0 1 0\np 1 2 3\n").unwrap();
+
+    let material = Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)));
+    let error = load_model(&path, &AssetResolver::new(), material, None)
+        .expect_err("point geometry should not load as a mesh");
+    fs::remove_dir_all(&dir).unwrap();
+
+    match &error {
+        LoadError::UnsupportedGeometry { path: p, kind } => {
+            assert_eq!(p, &path);
+            assert_eq!(*kind, "point");
+        }
+        other => panic!("expected LoadError::UnsupportedGeometry, got {:?}", other),
+    }
+    assert_eq!(
+        Error::from(error).to_string(),
+        format!(
+            "{} contains point geometry, expected a triangle mesh",
+            path.display()
+        )
+    );
+}
+
+#[test]
+fn unknown_scene_is_an_error() {
+    match scenes::build("no_such_scene") {
+        Err(Error::UnknownScene(name)) => assert_eq!(name, "no_such_scene"),
+        Err(other) => panic!("expected Error::UnknownScene, got {:?}", other),
+        Ok(_) => panic!("expected an error"),
+    }
+}
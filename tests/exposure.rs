@@ -0,0 +1,149 @@
+//! Exposure: brightening or darkening a render before it is written, by hand
+//! or to bring its log-average luminance to middle gray.
+
+use ray_tracer::{
+    color, log_average_luminance, point,
+    scenes::{CameraBuilder, RenderMetadata, Scene},
+    Camera, CameraError, Exposure, HittableList, ImageBuffer, Vec3, MIDDLE_GRAY,
+};
+
+/// A one-row image of gray pixels of the given luminances.
+fn grays(luminances: &[f64]) -> ImageBuffer {
+    let mut image = ImageBuffer::new(luminances.len(), 1);
+    for (pixel, &l) in image.pixels.iter_mut().zip(luminances) {
+        *pixel = color(l, l, l);
+    }
+    image
+}
+
+fn camera() -> Camera {
+    Camera::new(
+        1.0,
+        4,
+        40.,
+        point(0., 0., 1.),
+        point(0., 0., 0.),
+        Vec3(0., 1., 0.),
+        1,
+        2,
+    )
+}
+
+#[test]
+fn manual_exposure_multiplies_every_pixel() {
+    let mut image = grays(&[0.1, 0.2, 0.4]);
+    assert_eq!(image.expose(Exposure::Manual(2.0)), 2.0);
+    let values: Vec<f64> = image.pixels.iter().map(|p| p.1).collect();
+    assert_eq!(values, [0.2, 0.4, 0.8]);
+
+    // By default, nothing changes and nothing is recorded.
+    let scene = Scene::new(camera(), HittableList::new());
+    assert!(scene.camera.exposure.is_identity());
+    assert_eq!(scene.expose(&mut image), None);
+    assert_eq!(image.pixels[2].1, 0.8);
+}
+
+#[test]
+fn auto_exposure_brings_the_log_average_to_the_key() {
+    let luminances: Vec<f64> = (1..=50).map(|i| 0.002 * i as f64).collect();
+    let key = 0.05;
+    let mut image = grays(&luminances);
+    let multiplier = image.expose(Exposure::Auto { key, exclude: 0.0 });
+    assert!(multiplier > 1.0);
+    let average = log_average_luminance(&image, 0.0).unwrap();
+    assert!((average - key).abs() < 0.01 * key, "{}", average);
+
+    // The same image ten times darker comes out nearly the same; only the
+    // floor that keeps black pixels finite counts for a little more.
+    let mut darker = grays(&luminances);
+    for pixel in &mut darker.pixels {
+        *pixel = *pixel * 0.1;
+    }
+    let auto = Exposure::Auto { key, exclude: 0.0 };
+    darker.expose(auto);
+    let average = log_average_luminance(&darker, 0.0).unwrap();
+    assert!((average - key).abs() < 0.05 * key, "{}", average);
+    assert_eq!(grays(&[0.0; 4]).expose(Exposure::auto()), 1.0);
+}
+
+#[test]
+fn auto_exposure_sets_the_brightest_pixels_aside() {
+    // A dim room with a light seen directly in one pixel of a hundred.
+    let mut luminances = vec![0.01; 99];
+    luminances.push(1000.0);
+    let image = grays(&luminances);
+
+    // Leaving the light out, the room comes up to middle gray...
+    let multiplier = Exposure::auto().multiplier(&image);
+    assert!(
+        (multiplier * 0.01 - MIDDLE_GRAY).abs() < 0.01,
+        "{}",
+        multiplier
+    );
+
+    // ...but counting it, the room may only brighten until the light clips.
+    let counted = Exposure::Auto {
+        key: MIDDLE_GRAY,
+        exclude: 0.0,
+    };
+    assert_eq!(counted.multiplier(&image), 1.0 / 1000.0);
+
+    // Pixels an alpha channel leaves uncovered don't count at all.
+    let mut cutout = grays(&[0.01, 0.01, 5.0]);
+    cutout.set_alpha(2, 0, 0.0);
+    assert!((log_average_luminance(&cutout, 0.0).unwrap() - 0.0101).abs() < 1e-9);
+}
+
+#[test]
+fn exposures_parse_from_the_command_line() {
+    assert_eq!("2.5".parse(), Ok(Exposure::Manual(2.5)));
+    assert_eq!("auto".parse(), Ok(Exposure::auto()));
+    assert_eq!(
+        "auto:0.25".parse(),
+        Ok(Exposure::Auto {
+            key: 0.25,
+            exclude: 0.01
+        })
+    );
+    for bad in [
+        "0", "-1", "inf", "auto:", "auto:0", "auto:x", "autox", "bright",
+    ] {
+        assert!(bad.parse::<Exposure>().is_err(), "{}", bad);
+    }
+}
+
+#[test]
+fn exposure_is_kept_with_the_camera_and_the_render() {
+    let mut camera = camera();
+    camera.set_exposure(Exposure::Manual(0.0));
+    assert!(matches!(
+        camera.validate(),
+        Err(CameraError::InvalidExposure(_))
+    ));
+    camera.set_exposure(Exposure::Auto {
+        key: MIDDLE_GRAY,
+        exclude: 0.5,
+    });
+    assert!(camera.validate().is_err());
+
+    camera.set_exposure(Exposure::auto());
+    let json = serde_json::to_string(&CameraBuilder::from(&camera)).unwrap();
+    assert!(json.contains("\"exposure\":{\"auto\""), "{}", json);
+    let built = serde_json::from_str::<CameraBuilder>(&json)
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(built.exposure, Exposure::auto());
+    let plain = serde_json::to_string(&CameraBuilder::from(&Camera::default())).unwrap();
+    assert!(!plain.contains("exposure"), "{}", plain);
+
+    // The render records the multiplier chosen, to repeat it by hand.
+    let scene = Scene::new(camera.clone(), HittableList::new());
+    let (mut image, stats) = scene.render_timed();
+    let multiplier = scene.expose(&mut image).unwrap();
+    let mut metadata = RenderMetadata::new("test", &camera, &stats);
+    metadata.exposure = Some(multiplier);
+    let read = RenderMetadata::from_json(&metadata.to_json()).unwrap();
+    assert_eq!(read.exposure, Some(multiplier));
+    assert!(metadata.to_string().contains("exposure:"));
+}
@@ -0,0 +1,163 @@
+//! What each feature combination can do. CI runs this suite with
+//! `--no-default-features`, the defaults, and with `macroquad` and `obj`.
+
+use std::{fs, sync::Arc};
+
+use ray_tracer::{
+    color, default_decoder, load_model, point,
+    scenes::{self, WorldBuilder},
+    AssetResolver, Camera, Lambertian, Material,
+};
+
+fn gray() -> Arc<dyn Material> {
+    Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)))
+}
+
+fn fixture(name: &str, files: &[(&str, &str)]) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "ray-tracer-features-{}-{}",
+        name,
+        std::process::id()
+    ));
+    fs::create_dir_all(&dir).unwrap();
+    for (file, text) in files {
+        fs::write(dir.join(file), text).unwrap();
+    }
+    dir
+}
+
+#[test]
+fn procedural_scenes_render_in_every_build() {
+    let mut camera = Camera::default();
+    camera
+        .set_image_width(16)
+        .set_aa_samples(2)
+        .set_max_depth(3)
+        .set_seed(Some(3));
+    camera.set_background(color(0.7, 0.8, 1.0));
+    let checker = scenes::checker(0.5, color(0.1, 0.1, 0.1), color(0.9, 0.9, 0.9));
+    let scene = WorldBuilder::new()
+        .sphere(point(0., 0., -2.), 0.5, scenes::textured(scenes::noise(4.)))
+        .sphere(point(0., -100.5, -2.), 100., scenes::textured(checker))
+        .scene(camera);
+    let image = scene.render_image();
+    assert_eq!((image.width, image.height), (16, 9));
+    assert!(image
+        .pixels
+        .iter()
+        .all(|p| p.0.is_finite() && p.1.is_finite()));
+    assert!(image.pixels.iter().any(|p| p.0 > 0.0));
+}
+
+#[test]
+fn objs_load_in_every_build() {
+    let dir = fixture(
+        "quad",
+        &[(
+            "quad.obj",
+            "o quad\nv 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n\
+             vn 0 0 1\nf 1/1/1 2/2/1 3/3/1 4/4/1\n",
+        )],
+    );
+    let mesh = load_model(&dir.join("quad.obj"), &AssetResolver::new(), gray(), None).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(mesh.triangle_count(), 2);
+    let data = mesh.data();
+    let uvs = data.uvs.as_ref().unwrap();
+    // V runs up the image in every loader.
+    for (p, uv) in data.positions.iter().zip(uvs) {
+        assert!((p.0 - uv.0).abs() < 1e-6 && (p.1 - uv.1).abs() < 1e-6);
+    }
+    assert!(data.normals.as_ref().unwrap().iter().all(|n| n.2 > 0.99));
+}
+
+#[cfg(not(feature = "obj"))]
+#[test]
+fn the_built_in_obj_parser_reads_relative_indices_and_glass() {
+    let dir = fixture(
+        "glass",
+        &[
+            (
+                "pane.mtl",
+                "newmtl glass\nKd 1 1 1\nTr 0.9\nNi 1.33\n\nnewmtl paint\nKd 0.2 0.4 0.6\nKs 0.5 0.5 0.5\nNs 100\n",
+            ),
+            (
+                "pane.obj",
+                "mtllib pane.mtl\nv 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nusemtl glass\nf -4 -3 -2 -1\n\
+                 v 0 0 1\nv 1 0 1\nv 0 1 1\nusemtl paint\nf -3 -2 -1\nusemtl missing\nf 5 6 7\n",
+            ),
+        ],
+    );
+    let mesh = load_model(&dir.join("pane.obj"), &AssetResolver::new(), gray(), None).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(mesh.triangle_count(), 4);
+    // Corners with the same indices share a vertex.
+    assert_eq!(mesh.vertex_count(), 7);
+    // Both library materials, then the default for the unknown name.
+    assert_eq!(mesh.materials().len(), 3);
+    assert_eq!(mesh.material_ids().unwrap(), [0, 0, 1, 2]);
+    assert_eq!(mesh.materials()[0].kind(), "Dielectric");
+    assert_eq!(mesh.materials()[1].kind(), "Plastic");
+    assert!(Arc::ptr_eq(&mesh.materials()[2], mesh.face_material(3)));
+}
+
+#[cfg(any(feature = "image", feature = "macroquad"))]
+#[test]
+fn image_textures_decode_with_the_default_decoder() {
+    use ray_tracer::ColorTexture;
+    assert!(default_decoder().is_some());
+    let png = ColorTexture::from_file(&fs::read("resources/earthmap.png").unwrap()).unwrap();
+    assert!(png.width > 0 && png.height > 0);
+    assert_eq!(png.data.len(), png.width * png.height);
+    assert!(ColorTexture::from_file(b"not an image").is_err());
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn the_image_crate_also_decodes_jpegs() {
+    use ray_tracer::ColorTexture;
+    let png = ColorTexture::from_file(&fs::read("resources/earthmap.png").unwrap()).unwrap();
+    let jpg = ColorTexture::from_file(&fs::read("resources/earthmap.jpg").unwrap()).unwrap();
+    assert_eq!((png.width, png.height), (jpg.width, jpg.height));
+    // Lossy compression moves the colors a little, not the picture.
+    let mean_difference: f64 = png
+        .data
+        .iter()
+        .zip(&jpg.data)
+        .map(|(a, b)| (*a - *b).length())
+        .sum::<f64>()
+        / png.data.len() as f64;
+    assert!(mean_difference < 0.05, "{}", mean_difference);
+}
+
+#[cfg(all(feature = "image", feature = "macroquad"))]
+#[test]
+fn the_image_and_macroquad_decoders_agree() {
+    use ray_tracer::{ImageCrateDecoder, ImageDecoder, MacroquadDecoder};
+    let bytes = fs::read("resources/earthmap.png").unwrap();
+    let a = ImageCrateDecoder.decode(&bytes).unwrap();
+    let b = MacroquadDecoder.decode(&bytes).unwrap();
+    assert_eq!((a.width, a.height), (b.width, b.height));
+    assert!(a
+        .data
+        .iter()
+        .zip(&b.data)
+        .all(|(a, b)| (*a - *b).length() == 0.0));
+}
+
+#[cfg(not(any(feature = "image", feature = "macroquad")))]
+#[test]
+fn builds_without_a_decoder_explain_and_fall_back() {
+    use ray_tracer::{Error, TextureRegistry};
+    use std::path::Path;
+    assert!(default_decoder().is_none());
+    let error = TextureRegistry::global()
+        .load(Path::new("resources/earthmap.png"), &AssetResolver::new())
+        .err()
+        .expect("no decoder to load the texture with");
+    assert!(matches!(error, Error::Decode { .. }));
+    assert!(error.to_string().contains("`image`"), "{}", error);
+    // The built-in earth scenes still build, drawing the earth in one color.
+    let scene = scenes::build("earthmap").unwrap();
+    assert_eq!(scene.world.objects.len(), 1);
+}
@@ -0,0 +1,100 @@
+//! Focusing helpers: on a point, on what the camera looks at, and on what a
+//! pixel shows, with the lens rays meeting where focused.
+
+use std::sync::Arc;
+
+use ray_tracer::{
+    color, point, Camera, HittableList, IndependentSampler, Lambertian, Parallelogram, Ray, Sphere,
+    Vec3,
+};
+
+/// A camera at z = 10 looking down -z, with a wide lens.
+fn camera() -> Camera {
+    let mut camera = Camera::new(
+        1.0,
+        32,
+        40.,
+        point(0., 0., 10.),
+        point(0., 0., 0.),
+        Vec3(0., 1., 0.),
+        1,
+        10,
+    );
+    camera.set_defocus(8.0, None);
+    camera
+}
+
+fn grey() -> Arc<Lambertian> {
+    Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)))
+}
+
+/// The depth along the line of sight at which the lens rays through pixel
+/// (x, y) meet.
+fn sharpest_depth(camera: &Camera, x: i32, y: i32) -> f64 {
+    let rays: Vec<Ray> = (0..8)
+        .map(|sample| camera.sample_ray(x, y, &mut IndependentSampler::for_sample(4, x, y, sample)))
+        .collect();
+    // Each lens ray is aimed at its pixel sample on the plane in focus.
+    let depths: Vec<f64> = rays.iter().map(|ray| 10.0 - ray.at(1.0).2).collect();
+    assert!(depths.windows(2).all(|w| (w[0] - w[1]).abs() < 1e-9));
+    depths[0]
+}
+
+#[test]
+fn focus_on_finds_a_sphere_at_its_distance() {
+    let mut world = HittableList::new();
+    world.add(Sphere::new(point(0., 0., -2.), 2., grey()));
+    let mut camera = camera();
+    assert_eq!(camera.focus_on(&world), Some(10.0));
+    assert_eq!(camera.focus_dist, Some(10.0));
+    assert!((sharpest_depth(&camera, 16, 16) - 10.0).abs() < 1e-9);
+
+    // A nearer sphere in the way takes over.
+    world.add(Sphere::new(point(0., 0., 4.), 1., grey()));
+    assert_eq!(camera.focus_on(&world), Some(5.0));
+    assert!((sharpest_depth(&camera, 3, 27) - 5.0).abs() < 1e-9);
+}
+
+#[test]
+fn nothing_to_focus_on_leaves_the_focus_alone() {
+    let mut world = HittableList::new();
+    world.add(Sphere::new(point(6., 0., 0.), 1., grey()));
+    let mut camera = camera();
+    camera.set_defocus(8.0, Some(7.5));
+    assert_eq!(camera.focus_on(&world), None);
+    assert_eq!(camera.focus_on(&HittableList::new()), None);
+    assert_eq!(camera.focus_dist, Some(7.5));
+    assert!((sharpest_depth(&camera, 10, 10) - 7.5).abs() < 1e-9);
+}
+
+#[test]
+fn focus_on_point_takes_the_distance_to_it() {
+    let mut camera = camera();
+    camera.focus_on_point(point(0., 0., 7.));
+    assert_eq!(camera.focus_dist, Some(3.0));
+    assert!((sharpest_depth(&camera, 16, 16) - 3.0).abs() < 1e-9);
+    camera.focus_on_point(point(3., 0., 6.));
+    assert!((camera.focus_dist.unwrap() - 5.0).abs() < 1e-12);
+    // The camera's own position has no distance to focus at.
+    camera.focus_on_point(point(0., 0., 10.));
+    assert!((camera.focus_dist.unwrap() - 5.0).abs() < 1e-12);
+}
+
+#[test]
+fn a_pixel_focuses_on_its_depth_along_the_line_of_sight() {
+    // A wall square to the view, 10 away, and a ball in the top left corner.
+    let mut world = HittableList::new();
+    world.add(Parallelogram::new(
+        point(-20., -20., 0.),
+        (Vec3(40., 0., 0.), Vec3(0., 40., 0.)),
+        grey(),
+    ));
+    world.add(Sphere::new(point(-1.6, 1.6, 5.), 0.5, grey()));
+    let mut camera = camera();
+    // The corner pixel's ray is longer, but the wall is all in focus at 10.
+    let depth = camera.focus_on_pixel(&world, 30, 30).unwrap();
+    assert!((depth - 10.0).abs() < 1e-9, "{}", depth);
+    let depth = camera.focus_on_pixel(&world, 4, 4).unwrap();
+    assert!((4.0..5.0).contains(&depth), "{}", depth);
+    assert!((sharpest_depth(&camera, 4, 4) - depth).abs() < 1e-9);
+}
@@ -0,0 +1,130 @@
+//! Distance fog: what the camera sees fading toward a color with distance.
+
+use std::{fs, sync::Arc};
+
+use ray_tracer::{
+    color, point,
+    scenes::{self, Scene},
+    Camera, CameraError, Color, DiffuseLight, FogSettings, HittableList, LightList, Sphere, Vec3,
+};
+
+/// A camera at the origin looking down -z, one sample per pixel.
+fn camera(fog: Option<FogSettings>) -> Camera {
+    let mut camera = Camera::new(
+        1.0,
+        8,
+        10.,
+        point(0., 0., 0.),
+        point(0., 0., -1.),
+        Vec3(0., 1., 0.),
+        1,
+        4,
+    );
+    camera
+        .set_seed(Some(5))
+        .set_background(color(0., 0., 1.))
+        .set_fog(fog);
+    camera
+}
+
+/// A glowing white sphere whose near side is `distance` in front of the camera.
+fn glowing_sphere_at(distance: f64) -> HittableList {
+    let mut world = HittableList::new();
+    world.add(Sphere::new(
+        point(0., 0., -distance - 1.),
+        1.,
+        Arc::new(DiffuseLight::from(color(1., 1., 1.))),
+    ));
+    world
+}
+
+/// What the camera sees at the center of the image.
+fn center(camera: &Camera, world: &HittableList) -> Color {
+    camera.pixel_color(world, &LightList::new(), 4, 4, 0)
+}
+
+#[test]
+fn fog_thickens_with_distance_past_its_start() {
+    let fog = FogSettings {
+        start: 2.0,
+        ..FogSettings::new(color(0.5, 0.5, 0.5), 10.0)
+    };
+    assert_eq!(fog.transmittance(0.0), 1.0);
+    assert_eq!(fog.transmittance(2.0), 1.0);
+    assert!((fog.transmittance(12.0) - (-1.0f64).exp()).abs() < 1e-12);
+    assert_eq!(fog.transmittance(f64::INFINITY), 0.0);
+    let mut last = 1.0;
+    for distance in [3.0, 5.0, 10.0, 50.0] {
+        let transmittance = fog.transmittance(distance);
+        assert!(transmittance < last, "{} at {}", transmittance, distance);
+        last = transmittance;
+    }
+    let clear = FogSettings::new(color(0.5, 0.5, 0.5), f64::INFINITY);
+    assert_eq!(clear.transmittance(f64::INFINITY), 1.0);
+}
+
+#[test]
+fn receding_objects_fade_smoothly_into_the_fog() {
+    // Black fog over white spheres leaves just the transmittance.
+    let camera = camera(Some(FogSettings::new(color(0., 0., 0.), 20.0)));
+    let mut last = 1.0;
+    for distance in [2.0, 5.0, 10.0, 20.0, 40.0] {
+        let seen = center(&camera, &glowing_sphere_at(distance)).0;
+        let expected = (-distance / 20.0f64).exp();
+        assert!((seen - expected).abs() < 1e-3, "{} {}", seen, expected);
+        assert!(seen < last);
+        last = seen;
+    }
+}
+
+#[test]
+fn infinite_falloff_leaves_the_render_as_it_was() {
+    let scene = |fog| Scene::new(camera(fog), glowing_sphere_at(5.0));
+    let clear = scene(None).render_image();
+    let fogged = scene(Some(FogSettings::new(color(1., 0., 0.), f64::INFINITY))).render_image();
+    assert_eq!(clear.pixels.len(), fogged.pixels.len());
+    for (a, b) in clear.pixels.iter().zip(&fogged.pixels) {
+        assert_eq!((a.0, a.1, a.2), (b.0, b.1, b.2));
+    }
+}
+
+#[test]
+fn rays_that_miss_are_lost_in_the_fog_unless_the_background_is_kept() {
+    let empty = HittableList::new();
+    let fog = FogSettings::new(color(1., 0., 0.), 20.0);
+    let lost = center(&camera(Some(fog)), &empty);
+    assert_eq!((lost.0, lost.1, lost.2), (1., 0., 0.));
+    let kept = FogSettings {
+        keep_background: true,
+        ..fog
+    };
+    let sky = center(&camera(Some(kept)), &empty);
+    assert_eq!((sky.0, sky.1, sky.2), (0., 0., 1.));
+
+    // Nothing is there to fog in a transparent render.
+    let mut transparent = camera(Some(fog));
+    transparent.set_transparent(true);
+    let seen = transparent.pixel_average(&empty, &LightList::new(), 4, 4, 0);
+    assert_eq!((seen.color.0, seen.alpha), (0., 0.));
+}
+
+#[test]
+fn fog_is_checked_and_kept_in_scene_files() {
+    for falloff in [0.0, -1.0, f64::NAN] {
+        let mut camera = camera(None);
+        camera.set_fog(Some(FogSettings::new(color(1., 1., 1.), falloff)));
+        assert!(matches!(camera.validate(), Err(CameraError::InvalidFog(_))));
+    }
+
+    let scene = scenes::build("distance_fog").unwrap();
+    let dir = std::env::temp_dir().join(format!("ray-tracer-fog-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("scene.json");
+    scene.describe().unwrap().save(&path).unwrap();
+    let loaded = Scene::from_path(&path);
+    fs::remove_dir_all(&dir).unwrap();
+    let loaded = loaded.unwrap();
+    let fog = loaded.camera.fog.expect("the fog is saved with the camera");
+    assert_eq!((fog.falloff, fog.start), (15.0, 4.0));
+    assert_eq!(loaded.fingerprint(), scene.fingerprint());
+}
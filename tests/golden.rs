@@ -0,0 +1,114 @@
+//! Golden-image regression tests: small, seeded renders of representative
+//! scenes compared against the references in `tests/golden/`.
+//!
+//! After an intentional change to how images look, regenerate the references
+//! and review them before committing:
+//!
+//!     UPDATE_GOLDENS=1 cargo test --release --test golden
+//!
+//! Changing the order or number of random draws (a sampling routine that
+//! takes a different number of draws, a new light-sampling step) moves every
+//! pixel's noise without changing the image it converges to, and fails these
+//! tests as surely as a real change. Before regenerating for that reason,
+//! render the scene with many samples before and after the change, and check
+//! that the per-channel means agree well within the tolerances below.
+//!
+//! On a mismatch the failing test writes `target/golden-diff/<scene>.ppm`,
+//! showing the per-pixel difference in false color. The comparison is
+//! `ImageBuffer::diff`, the same as `ray-tracer diff`.
+
+use std::{env, path::PathBuf};
+
+use ray_tracer::{scenes, Color, ImageBuffer, Vec3};
+
+const WIDTH: i32 = 64;
+const SAMPLES: i32 = 12;
+const MAX_DEPTH: i32 = 10;
+const SEED: u64 = 1;
+/// Scenes without lights are black against the default background, so they
+/// are rendered under a flat sky instead.
+const SKY: Color = Vec3(0.7, 0.8, 1.0);
+
+/// Largest mean absolute difference per channel, on a 0-255 scale.
+const MAX_MEAN_ERROR: f64 = 1.5;
+/// Largest difference of any single channel, on a 0-255 scale.
+const MAX_PIXEL_ERROR: f64 = 64.0;
+
+fn render(name: &str, background: Option<Color>) -> ImageBuffer {
+    let mut scene = scenes::build(name).unwrap_or_else(|e| panic!("{}", e));
+    scene
+        .camera
+        .set_image_width(WIDTH)
+        .set_aa_samples(SAMPLES)
+        .set_max_depth(MAX_DEPTH)
+        .set_seed(Some(SEED));
+    if let Some(background) = background {
+        scene.camera.set_background(background);
+    }
+    scene.render_image()
+}
+
+fn check_golden(name: &str, background: Option<Color>) {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let golden_path = root.join("tests/golden").join(format!("{}.ppm", name));
+    let actual = render(name, background);
+
+    if env::var_os("UPDATE_GOLDENS").is_some() {
+        actual.save(&golden_path).unwrap();
+        return;
+    }
+
+    let expected = ImageBuffer::load(&golden_path).unwrap_or_else(|e| {
+        panic!(
+            "could not read {} ({}); run with UPDATE_GOLDENS=1 to create it",
+            golden_path.display(),
+            e
+        )
+    });
+    // Compared as written out, so the references' 8 bits lose nothing.
+    let report = actual
+        .encoded()
+        .diff(&expected.encoded())
+        .unwrap_or_else(|e| panic!("{}: {}", name, e));
+    let (mean, worst) = (report.mean_error * 255.0, report.max_error * 255.0);
+
+    if mean > MAX_MEAN_ERROR || worst > MAX_PIXEL_ERROR {
+        let diff_path = root
+            .join("target/golden-diff")
+            .join(format!("{}.ppm", name));
+        std::fs::create_dir_all(diff_path.parent().unwrap()).unwrap();
+        report.image(None).save(&diff_path).unwrap();
+        panic!(
+            "{}: image differs from {}\n  mean error {:.3} (limit {})\n  worst pixel ({}, {}) off by {:.0} (limit {})\n  diff written to {}",
+            name,
+            golden_path.display(),
+            mean,
+            MAX_MEAN_ERROR,
+            report.max_at.0,
+            report.max_at.1,
+            worst,
+            MAX_PIXEL_ERROR,
+            diff_path.display()
+        );
+    }
+}
+
+#[test]
+fn quads() {
+    check_golden("quads", Some(SKY));
+}
+
+#[test]
+fn cornell_box() {
+    check_golden("cornell_box", None);
+}
+
+#[test]
+fn material_spheres() {
+    check_golden("material_spheres", None);
+}
+
+#[test]
+fn checkered_spheres() {
+    check_golden("checkered_spheres", Some(SKY));
+}
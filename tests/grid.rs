@@ -0,0 +1,299 @@
+//! The uniform grid must return exactly the hit a brute-force search of the
+//! same objects finds. Scenes and rays are generated from a seed; set
+//! `GRID_FUZZ_SEED` to rerun a single failing case.
+
+use std::sync::Arc;
+
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use ray_tracer::{
+    color, parallelepiped, point, scenes, scenes::SceneDesc, GridAccel, Heightfield, HitRecord,
+    Hittable, HittableList, IndependentSampler, Interval, Lambertian, Material, MeshAccel,
+    Parallelogram, Ray, RotateY, Sphere, Translation, TraversalStats, Triangle, TriangleMesh, Vec3,
+    PRIMITIVES_PER_CELL,
+};
+
+const SEEDS: [u64; 6] = [1, 2, 3, 42, 1234, 98765];
+const RAYS_PER_SCENE: usize = 2000;
+const EPSILON: f64 = 1e-9;
+
+fn seeds() -> Vec<u64> {
+    match std::env::var("GRID_FUZZ_SEED") {
+        Ok(seed) => vec![seed.parse().expect("GRID_FUZZ_SEED must be an integer")],
+        Err(_) => SEEDS.to_vec(),
+    }
+}
+
+fn vec3(rng: &mut SmallRng, extent: f64) -> Vec3 {
+    Vec3(
+        rng.gen_range(-extent..extent),
+        rng.gen_range(-extent..extent),
+        rng.gen_range(-extent..extent),
+    )
+}
+
+fn gray() -> Arc<dyn Material> {
+    Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)))
+}
+
+/// A random primitive, sometimes moved and turned, with its own material so
+/// hits can be traced back to it.
+fn object(rng: &mut SmallRng) -> Arc<dyn Hittable> {
+    let at = vec3(rng, 10.);
+    let size = rng.gen_range(0.1..3.);
+    let shape: Arc<dyn Hittable> = match rng.gen_range(0..5) {
+        0 => Arc::new(Sphere::new(at, size, gray())),
+        1 => Arc::new(Parallelogram::new(
+            at,
+            (vec3(rng, size), vec3(rng, size)),
+            gray(),
+        )),
+        // Flat in Y: only the padding gives it any thickness.
+        2 => Arc::new(Parallelogram::new(
+            at,
+            (Vec3(size, 0., 0.), Vec3(0., 0., size)),
+            gray(),
+        )),
+        3 => Arc::new(Triangle::new(
+            (at, at + vec3(rng, size), at + vec3(rng, size)),
+            gray(),
+        )),
+        _ => parallelepiped(at, at + vec3(rng, size), gray()),
+    };
+    match rng.gen_range(0..3) {
+        0 => Arc::new(RotateY::new(shape, rng.gen_range(-180.0..180.0))),
+        1 => Arc::new(Translation::new(shape, vec3(rng, 5.))),
+        _ => shape,
+    }
+}
+
+/// Rays from around the scene: half aimed at an object, some along an axis,
+/// the rest in any direction.
+fn ray(rng: &mut SmallRng, objects: &[Arc<dyn Hittable>]) -> (Ray, Interval) {
+    let origin = vec3(rng, 20.);
+    let direction = match rng.gen_range(0..4) {
+        0 | 1 => {
+            let bounds = objects[rng.gen_range(0..objects.len())].bound();
+            let target = point(
+                rng.gen_range(bounds.intervals[0].start..=bounds.intervals[0].end),
+                rng.gen_range(bounds.intervals[1].start..=bounds.intervals[1].end),
+                rng.gen_range(bounds.intervals[2].start..=bounds.intervals[2].end),
+            );
+            target - origin
+        }
+        2 => {
+            let sign = if rng.gen_bool(0.5) { 1. } else { -1. };
+            [Vec3(sign, 0., 0.), Vec3(0., sign, 0.), Vec3(0., 0., sign)][rng.gen_range(0..3)]
+        }
+        _ => vec3(rng, 1.),
+    };
+    let end = if rng.gen_bool(0.2) {
+        rng.gen_range(1.0..20.)
+    } else {
+        f64::INFINITY
+    };
+    (
+        Ray {
+            origin,
+            direction,
+            time: 0.0,
+        },
+        Interval::new(0.001, end),
+    )
+}
+
+fn same_object(a: &HitRecord, b: &HitRecord) -> bool {
+    std::ptr::addr_eq(a.material, b.material)
+}
+
+fn compare(case: &str, expected: Option<HitRecord>, actual: Option<HitRecord>) {
+    match (expected, actual) {
+        (None, None) => {}
+        (Some(expected), Some(actual)) => {
+            assert!(
+                (expected.t - actual.t).abs() <= EPSILON * expected.t.max(1.),
+                "{}: expected t = {}, the grid gave {}",
+                case,
+                expected.t,
+                actual.t
+            );
+            assert!(
+                same_object(&expected, &actual),
+                "{}: the grid hit another object",
+                case
+            );
+        }
+        (Some(expected), None) => panic!("{}: the grid missed a hit at t = {}", case, expected.t),
+        (None, Some(actual)) => panic!(
+            "{}: the grid found a hit at t = {} the list missed",
+            case, actual.t
+        ),
+    }
+}
+
+fn fuzz(seed: u64, count: usize) {
+    let mut rng = SmallRng::seed_from_u64(seed);
+    let objects: Vec<Arc<dyn Hittable>> = (0..count).map(|_| object(&mut rng)).collect();
+    let mut list = HittableList::new();
+    for object in &objects {
+        list.add_arc(object.clone());
+    }
+    let grid = GridAccel::new(objects.clone());
+    let mut sampler = IndependentSampler::new(seed);
+
+    for i in 0..RAYS_PER_SCENE {
+        let (r, t) = ray(&mut rng, &objects);
+        let case = format!(
+            "seed {}, {} objects, ray {}: {:?} over {:?}",
+            seed, count, i, r, t
+        );
+        assert_eq!(
+            list.hit(&r, t, &mut sampler).is_some(),
+            grid.hit_any(&r, t, &mut sampler),
+            "{}: hit_any disagrees with a full search",
+            case
+        );
+        let mut stats = TraversalStats::default();
+        assert_eq!(
+            grid.hit(&r, t, &mut sampler).map(|hit| hit.t),
+            grid.hit_with_stats(&r, t, &mut sampler, &mut stats)
+                .map(|hit| hit.t),
+            "{}: counting the traversal changed its result",
+            case
+        );
+        compare(
+            &case,
+            list.hit(&r, t, &mut sampler),
+            grid.hit(&r, t, &mut sampler),
+        );
+    }
+}
+
+#[test]
+fn single_object_matches_brute_force() {
+    for seed in seeds() {
+        fuzz(seed, 1);
+    }
+}
+
+#[test]
+fn mixed_scenes_match_brute_force() {
+    for seed in seeds() {
+        let count = SmallRng::seed_from_u64(seed).gen_range(3..200);
+        fuzz(seed, count);
+    }
+}
+
+#[test]
+fn grid_and_bvh_meshes_agree_on_terrain() {
+    let field = scenes::hills(41, gray());
+    assert_eq!(field.mesh().accel(), MeshAccel::Grid);
+    let bvh = TriangleMesh::new(field.mesh().data().clone(), gray());
+    let mut rng = SmallRng::seed_from_u64(5);
+    let mut sampler = IndependentSampler::new(5);
+    let mut hits = 0;
+    for i in 0..RAYS_PER_SCENE {
+        let origin = point(
+            rng.gen_range(-5.0..25.),
+            rng.gen_range(-3.0..8.),
+            rng.gen_range(-5.0..25.),
+        );
+        let target = point(rng.gen_range(0.0..20.), 0., rng.gen_range(0.0..20.));
+        let r = Ray {
+            origin,
+            direction: target - origin,
+            time: 0.0,
+        };
+        let t = Interval::new(0.001, f64::INFINITY);
+        let (expected, actual) = (bvh.hit(&r, t, &mut sampler), field.hit(&r, t, &mut sampler));
+        hits += expected.is_some() as usize;
+        match (expected, actual) {
+            (Some(expected), Some(actual)) => {
+                assert!((expected.t - actual.t).abs() <= EPSILON * expected.t.max(1.));
+                assert!((expected.point - actual.point).length() < 1e-9);
+            }
+            (None, None) => {}
+            (expected, actual) => panic!(
+                "ray {}: BVH hit: {}, grid hit: {}",
+                i,
+                expected.is_some(),
+                actual.is_some()
+            ),
+        }
+    }
+    assert!(hits > RAYS_PER_SCENE / 2, "only {} rays hit", hits);
+}
+
+#[test]
+fn flat_geometry_gets_a_single_layer_of_cells() {
+    let tiles: Vec<Arc<dyn Hittable>> = (0..100)
+        .flat_map(|i| {
+            (0..100).map(move |j| {
+                let at = point(i as f64, 0., j as f64);
+                Arc::new(Parallelogram::new(
+                    at,
+                    (Vec3(1., 0., 0.), Vec3(0., 0., 1.)),
+                    gray(),
+                )) as Arc<dyn Hittable>
+            })
+        })
+        .collect();
+    let grid = GridAccel::new(tiles);
+    let [x, y, z] = grid.resolution();
+    assert_eq!(y, 1);
+    assert_eq!(x, z);
+    let per_cell = 10_000.0 / (x * y * z) as f64;
+    assert!(
+        (per_cell / PRIMITIVES_PER_CELL - 1.0).abs() < 0.1,
+        "{} per cell",
+        per_cell
+    );
+}
+
+#[test]
+fn scene_files_build_heightfields_and_grid_meshes() {
+    let text = r#"
+        [camera]
+        aspect_ratio = 1.0
+        image_width = 8
+        vfov = 40.0
+        look_from = [1.0, 3.0, -2.0]
+        look_at = [1.0, 0.0, 1.0]
+        up = [0.0, 1.0, 0.0]
+        aa_samples = 1
+        max_depth = 2
+
+        [materials]
+        grass = { type = "lambertian", albedo = [0.4, 0.6, 0.3] }
+
+        [[objects]]
+        type = "heightfield"
+        columns = 3
+        spacing = 1.0
+        heights = [0.0, 0.5, 0.0, 0.2, 1.0, 0.2, 0.0, 0.3, 0.0]
+        material = "grass"
+
+        [[objects]]
+        type = "mesh"
+        path = "resources/models/gradient_cube.ply"
+        material = "grass"
+        accel = "grid"
+    "#;
+    let desc: SceneDesc = toml::from_str(text).unwrap();
+    let scene = desc.build().unwrap();
+    let saved = toml::to_string(&scene.describe().unwrap()).unwrap();
+    assert!(saved.contains("type = \"heightfield\""), "{}", saved);
+    assert!(saved.contains("accel = \"grid\""), "{}", saved);
+    let again: SceneDesc = toml::from_str(&saved).unwrap();
+    let rebuilt = again.build().unwrap();
+    let (a, b) = (scene.world.bound(), rebuilt.world.bound());
+    for axis in 0..3 {
+        assert!((a.intervals[axis].start - b.intervals[axis].start).abs() < 1e-9);
+        assert!((a.intervals[axis].end - b.intervals[axis].end).abs() < 1e-9);
+    }
+
+    let ragged = Heightfield::new(3, 1.0, vec![0.0; 7], gray());
+    let mut issues = Vec::new();
+    ragged.validate("objects[0]", &mut issues);
+    assert_eq!(issues.len(), 1, "{:?}", issues.len());
+    assert_eq!(ragged.mesh().triangle_count(), 0);
+}
@@ -0,0 +1,161 @@
+//! Counting BVH traversal work per ray, and the heat map drawn from the counts.
+
+use std::sync::Arc;
+
+use ray_tracer::{
+    color, point, BoundNode, Camera, HeatMap, Hittable, HittableList, IndependentSampler, Interval,
+    Lambertian, Material, Ray, RotateY, Sphere, Translation, TraversalStats, Vec3,
+};
+
+fn gray() -> Arc<dyn Material> {
+    Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)))
+}
+
+/// A row of `count` unit spheres along the x axis, two units apart.
+fn row(count: usize) -> Vec<Arc<dyn Hittable>> {
+    (0..count)
+        .map(|i| {
+            Arc::new(Sphere::new(point(i as f64 * 2., 0., 0.), 1., gray())) as Arc<dyn Hittable>
+        })
+        .collect()
+}
+
+fn traverse(object: &dyn Hittable, ray: &Ray) -> (Option<f64>, TraversalStats) {
+    let mut sampler = IndependentSampler::new(0);
+    let mut stats = TraversalStats::default();
+    let t = object
+        .hit_with_stats(
+            ray,
+            Interval::new(0.001, f64::INFINITY),
+            &mut sampler,
+            &mut stats,
+        )
+        .map(|hit| hit.t);
+    (t, stats)
+}
+
+fn toward_sphere(i: usize) -> Ray {
+    Ray {
+        origin: point(i as f64 * 2., 0., 10.),
+        direction: Vec3(0., 0., -1.),
+        time: 0.0,
+    }
+}
+
+#[test]
+fn a_lone_primitive_is_one_test_and_no_nodes() {
+    let (t, stats) = traverse(row(1)[0].as_ref(), &toward_sphere(0));
+    assert_eq!(t, Some(9.));
+    assert_eq!(stats.node_visits, 0);
+    assert_eq!(stats.primitive_tests, 1);
+}
+
+#[test]
+fn rays_missing_the_root_stop_there() {
+    let objects = row(16);
+    let bvh = BoundNode::from_objects(&objects, 0..objects.len());
+    let away = Ray {
+        origin: point(0., 10., 10.),
+        direction: Vec3(0., 1., 0.),
+        time: 0.0,
+    };
+    let (t, stats) = traverse(&bvh, &away);
+    assert_eq!(t, None);
+    assert_eq!(stats.node_visits, 1);
+    assert_eq!(stats.primitive_tests, 0);
+}
+
+#[test]
+fn a_hit_visits_a_small_part_of_the_tree() {
+    let objects = row(64);
+    let bvh = BoundNode::from_objects(&objects, 0..objects.len());
+    let (t, stats) = traverse(&bvh, &toward_sphere(37));
+    assert_eq!(t, Some(9.));
+    // 63 internal nodes over 64 spheres; one ray should need a single path.
+    assert!(stats.node_visits < 20, "{:?}", stats);
+    assert!(stats.primitive_tests < 4, "{:?}", stats);
+}
+
+#[test]
+fn transforms_and_groups_count_what_they_contain() {
+    let objects = row(8);
+    let bvh: Arc<dyn Hittable> = Arc::new(BoundNode::from_objects(&objects, 0..objects.len()));
+    let direct = traverse(bvh.as_ref(), &toward_sphere(3)).1;
+
+    let moved = Translation::new(Arc::new(RotateY::new(bvh.clone(), 0.)), Vec3(0., 0., 0.));
+    let wrapped = traverse(&moved, &toward_sphere(3)).1;
+    assert_eq!(wrapped.node_visits, direct.node_visits);
+    assert_eq!(wrapped.primitive_tests, direct.primitive_tests);
+
+    let mut list = HittableList::new();
+    list.add_arc(bvh);
+    list.add(Sphere::new(point(0., 5., 0.), 1., gray()));
+    let grouped = traverse(&list, &toward_sphere(3)).1;
+    assert_eq!(grouped.node_visits, direct.node_visits);
+    assert_eq!(grouped.primitive_tests, direct.primitive_tests + 1);
+}
+
+fn assert_red(pixel: Vec3) {
+    assert!(
+        (pixel.0 - 1.).abs() < 1e-12 && pixel.1.abs() < 1e-12 && pixel.2.abs() < 1e-12,
+        "{:?} is not red",
+        pixel
+    );
+}
+
+fn camera() -> Camera {
+    Camera::new(
+        1.0,
+        24,
+        40.,
+        point(7., 0., 30.),
+        point(7., 0., 0.),
+        Vec3(0., 1., 0.),
+        1,
+        4,
+    )
+}
+
+fn world() -> HittableList {
+    let objects = row(8);
+    HittableList::from(Arc::new(BoundNode::from_objects(
+        &objects,
+        0..objects.len(),
+    )))
+}
+
+#[test]
+fn heat_maps_scale_to_the_busiest_pixel_by_default() {
+    let heat_map = camera().render_heat_map(&world(), None);
+    assert_eq!(heat_map.stats.len(), 24 * 24);
+    assert_eq!(heat_map.image.pixels.len(), 24 * 24);
+    let busiest = heat_map.stats.iter().map(|s| s.node_visits).max().unwrap();
+    assert_eq!(heat_map.max, busiest);
+    assert!(heat_map.mean_node_visits() >= 1.);
+
+    // The busiest pixel is drawn red, and rays that miss everything stay dark.
+    let hottest = heat_map
+        .stats
+        .iter()
+        .position(|s| s.node_visits == busiest)
+        .unwrap();
+    assert_red(heat_map.image.pixels[hottest]);
+    let coldest = heat_map
+        .stats
+        .iter()
+        .position(|s| s.node_visits == 1)
+        .unwrap();
+    assert!(heat_map.image.pixels[coldest].0 < 0.1);
+}
+
+#[test]
+fn heat_maps_can_use_a_fixed_scale() {
+    let fixed = camera().render_heat_map(&world(), Some(2));
+    assert_eq!(fixed.max, 2);
+    let over = fixed.stats.iter().position(|s| s.node_visits > 2).unwrap();
+    assert_red(fixed.image.pixels[over]);
+
+    let auto = camera().render_heat_map(&world(), None);
+    let total = |h: &HeatMap| h.image.pixels.iter().map(|p| p.0 + p.1 + p.2).sum::<f64>();
+    assert!(total(&fixed) > total(&auto));
+}
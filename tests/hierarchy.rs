@@ -0,0 +1,135 @@
+//! The hierarchy dump: one indented line per object with its settings and
+//! bounds, meshes summarized, and long groups cut short.
+
+use std::sync::Arc;
+
+use ray_tracer::{
+    color, point,
+    scenes::{self, Place, WorldBuilder},
+    BoundNode, Camera, ConstantMedium, Hierarchy, Hittable, HittableList, MeshData, TriangleMesh,
+    Vec3,
+};
+
+fn small_scene() -> ray_tracer::scenes::Scene {
+    let white = scenes::lambertian(color(0.73, 0.73, 0.73));
+    let smoke: Arc<dyn Hittable> = Arc::new(ConstantMedium::from_color(
+        scenes::sphere(point(0., 5., 0.), 1., white.clone()),
+        0.5,
+        color(0., 0., 0.),
+    ));
+    let mesh: Arc<dyn Hittable> = Arc::new(TriangleMesh::new(
+        MeshData::quad(point(-4., 0., -4.), (Vec3(8., 0., 0.), Vec3(0., 0., 8.))),
+        white.clone(),
+    ));
+    WorldBuilder::new()
+        .sphere(
+            point(0., 1., 0.),
+            1.,
+            scenes::metal(color(0.8, 0.8, 0.8), 0.),
+        )
+        .object(
+            scenes::cuboid(point(0., 0., 0.), point(1., 2., 1.), white)
+                .rotate_y(30.)
+                .translate(Vec3(2., 0., 0.)),
+        )
+        .object(smoke)
+        .object(mesh)
+        .scene(Camera::default())
+}
+
+#[test]
+fn small_scenes_print_one_indented_line_per_object() {
+    let text = small_scene().hierarchy();
+    let lines: Vec<&str> = text.lines().collect();
+    assert!(
+        lines[0].starts_with("camera: 400 by 225, 100 samples"),
+        "{}",
+        text
+    );
+    assert_eq!(lines[1], "lights: 0 area, 0 delta");
+    assert!(
+        lines[2].starts_with("HittableList (4 objects)  ["),
+        "{}",
+        text
+    );
+    for expected in [
+        "  Sphere at (0, 1, 0), radius 1 (Metal)  [-1.000, 1.000] x [0.000, 2.000] x [-1.000, 1.000]",
+        "  Translation by (2, 0, 0)  [",
+        "    RotateY by 30°  [",
+        "      HittableList (6 objects)  [",
+        "        Parallelogram at ",
+        "  ConstantMedium, density 0.5 (Isotropic)  [",
+        "    Sphere at (0, 5, 0), radius 1 (Lambertian)  [",
+    ] {
+        assert!(
+            lines.iter().any(|line| line.starts_with(expected)),
+            "no line starting {:?} in\n{}",
+            expected,
+            text
+        );
+    }
+}
+
+#[test]
+fn meshes_are_summarized_not_listed() {
+    let text = small_scene().hierarchy();
+    let mesh = text
+        .lines()
+        .find(|line| line.contains("TriangleMesh"))
+        .unwrap();
+    assert!(
+        mesh.starts_with("  TriangleMesh (2 tris, 4 vertices, BVH, Lambertian)  [-4.000, 4.000]"),
+        "{}",
+        mesh
+    );
+    assert!(!text.contains("MeshTriangle"), "{}", text);
+}
+
+#[test]
+fn bvh_roots_list_their_objects() {
+    let white = scenes::lambertian(color(0.5, 0.5, 0.5));
+    let mut list = HittableList::new();
+    for i in 0..5 {
+        list.add_arc(scenes::sphere(
+            point(i as f64 * 3., 0., 0.),
+            1.,
+            white.clone(),
+        ));
+    }
+    let text = Hierarchy(&BoundNode::from_list(list)).to_string();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 6, "{}", text);
+    assert!(lines[0].starts_with("BVH over 5 objects  [-1.000, 13.000]"));
+    assert!(lines[1..]
+        .iter()
+        .all(|line| line.starts_with("  Sphere at ")));
+}
+
+#[test]
+fn long_groups_are_cut_short() {
+    let white = scenes::lambertian(color(0.5, 0.5, 0.5));
+    let mut list = HittableList::new();
+    for i in 0..25 {
+        list.add_arc(scenes::sphere(point(i as f64, 0., 0.), 0.4, white.clone()));
+    }
+    let text = Hierarchy(&list).to_string();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 22, "{}", text);
+    assert_eq!(lines[21], "  ... 5 more");
+}
+
+#[test]
+fn debug_prints_the_hierarchy() {
+    let scene = small_scene();
+    let object: &dyn Hittable = &scene.world;
+    assert_eq!(format!("{:?}", object), Hierarchy(&scene.world).to_string());
+    assert_eq!(
+        format!("{:?}", scene.world),
+        Hierarchy(&scene.world).to_string()
+    );
+    let sphere = scene.world.objects[0].clone();
+    assert_eq!(
+        format!("{:?}", sphere),
+        "Sphere at (0, 1, 0), radius 1 (Metal)  [-1.000, 1.000] x [0.000, 2.000] x [-1.000, 1.000]\n"
+    );
+}
@@ -0,0 +1,99 @@
+//! Rendering into memory, as the wasm bindings do: progress by row, and
+//! the image as RGBA bytes.
+
+use ray_tracer::{
+    color,
+    scenes::{self, Scene},
+    ImageBuffer, StereoMode, StereoSettings,
+};
+
+fn tiny(mut scene: Scene) -> Scene {
+    scene
+        .camera
+        .set_image_width(8)
+        .set_aa_samples(2)
+        .set_max_depth(3)
+        .set_seed(Some(9));
+    scene
+}
+
+#[test]
+fn progress_counts_every_row_once() {
+    let scene = tiny(scenes::material_spheres());
+    let mut calls = Vec::new();
+    let image = scene.render_image_with_progress(&mut |done, rows| calls.push((done, rows)));
+    let rows = image.height;
+    assert_eq!(
+        calls,
+        (1..=rows).map(|done| (done, rows)).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn progress_does_not_change_the_image() {
+    let scene = tiny(scenes::material_spheres());
+    let plain = scene.render_image();
+    let reported = scene.render_image_with_progress(&mut |_, _| {});
+    assert_eq!(plain.to_rgba8(), reported.to_rgba8());
+}
+
+#[test]
+fn stereo_progress_runs_through_both_eyes() {
+    let mut scene = tiny(scenes::material_spheres());
+    scene.camera.set_stereo(Some(StereoSettings {
+        ipd: 0.06,
+        mode: StereoMode::SideBySide,
+        converge: false,
+    }));
+    let mut calls = Vec::new();
+    scene.render_image_with_progress(&mut |done, rows| calls.push((done, rows)));
+    let rows = calls[0].1;
+    assert_eq!(rows, 2 * scene.camera.image_height() as usize);
+    assert_eq!(calls.len(), rows);
+    assert_eq!(calls.last(), Some(&(rows, rows)));
+    assert!(calls.windows(2).all(|pair| pair[1].0 == pair[0].0 + 1));
+}
+
+#[test]
+fn rgba_bytes_are_gamma_corrected_and_opaque() {
+    let mut image = ImageBuffer::new(2, 1);
+    image.set(0, 0, color(0.25, 1.0, 0.0));
+    image.set(1, 0, color(4.0, -1.0, 0.01));
+    assert_eq!(image.to_rgba8(), [128, 255, 0, 255, 255, 0, 25, 255]);
+}
+
+#[test]
+fn rgba_bytes_match_the_ppm() {
+    let image = tiny(scenes::material_spheres()).render_image();
+    let rgba = image.to_rgba8();
+    assert_eq!(rgba.len(), image.width * image.height * 4);
+    let mut ppm = Vec::new();
+    image.write_ppm(&mut ppm).unwrap();
+    let ppm: Vec<u8> = String::from_utf8(ppm)
+        .unwrap()
+        .lines()
+        .skip(3)
+        .flat_map(|line| line.split(' ').map(|n| n.parse::<u8>().unwrap()))
+        .collect::<Vec<_>>();
+    let without_alpha: Vec<u8> = rgba
+        .chunks(4)
+        .flat_map(|pixel| pixel[..3].to_vec())
+        .collect();
+    assert_eq!(without_alpha, ppm);
+}
+
+#[test]
+fn render_to_writes_the_ppm_of_render_image() {
+    let scene = tiny(scenes::material_spheres());
+    let mut written = Vec::new();
+    let stats = scene.render_to(&mut written).unwrap();
+    // 8 pixels at 16:9 is 4.5 tall, rounded up.
+    assert_eq!((stats.width, stats.height), (8, 5));
+    let mut expected = Vec::new();
+    scene.render_image().write_ppm(&mut expected).unwrap();
+    assert_eq!(written, expected);
+
+    let mut line = Vec::new();
+    color(0.5, 2.0, -1.0).write_color(&mut line).unwrap();
+    assert_eq!(line, b"128 255 0\n");
+}
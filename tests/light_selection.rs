@@ -0,0 +1,185 @@
+//! Picking one of many delta lights per shadow ray: the odds of each pick,
+//! and estimates that agree with a shadow ray toward every light while
+//! being far less noisy when picked by power or from a light tree.
+
+use std::sync::Arc;
+
+use ray_tracer::{
+    color, point, scenes, DeltaLight, DirectionalLight, HittableList, IndependentSampler,
+    Lambertian, LightList, LightSampling, LightSelector, Parallelogram, Point, PointLight, Ray,
+    SolidBackground, SpotLight, Vec3,
+};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const EPSILON: f64 = 1e-9;
+
+/// A large gray floor at y = 0.
+fn floor() -> HittableList {
+    let mut world = HittableList::new();
+    world.add(Parallelogram::new(
+        point(-50., 0., -50.),
+        (Vec3(100., 0., 0.), Vec3(0., 0., 100.)),
+        Arc::new(Lambertian::from(color(0.5, 0.5, 0.5))),
+    ));
+    world
+}
+
+/// 200 small lights a unit over the floor, from a hundredth to a hundred
+/// times as bright as each other.
+fn many_lights() -> Vec<Arc<dyn DeltaLight>> {
+    let mut rng = StdRng::seed_from_u64(11);
+    (0..200)
+        .map(|i| {
+            let position = point(-10. + (i % 20) as f64, 1., -5. + (i / 20) as f64);
+            let intensity = 10f64.powf(rng.gen_range(-2.0..2.0));
+            Arc::new(PointLight::new(position, color(1., 1., 1.) * intensity))
+                as Arc<dyn DeltaLight>
+        })
+        .collect()
+}
+
+fn lights(delta: Vec<Arc<dyn DeltaLight>>, sampling: LightSampling) -> LightList {
+    let mut lights = LightList::new();
+    lights.delta = delta;
+    lights.set_sampling(sampling);
+    lights
+}
+
+/// The light leaving the floor at `at` toward a viewer above it, after a
+/// single bounce so only direct light contributes.
+fn shade(world: &HittableList, lights: &LightList, at: Point, seed: u64) -> f64 {
+    let ray = Ray {
+        origin: at + Vec3(0., 2., 1.),
+        direction: Vec3(0., -2., -1.),
+        time: 0.0,
+    };
+    let mut rng = IndependentSampler::new(seed);
+    ray.send(
+        world,
+        lights,
+        1,
+        &SolidBackground::default(),
+        EPSILON,
+        &mut rng,
+    )
+    .luminance()
+}
+
+/// The mean and variance of `n` single-sample estimates at `at`.
+fn estimate(world: &HittableList, lights: &LightList, at: Point, n: u64) -> (f64, f64) {
+    let samples: Vec<f64> = (0..n).map(|seed| shade(world, lights, at, seed)).collect();
+    let mean = samples.iter().sum::<f64>() / n as f64;
+    let variance = samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / n as f64;
+    (mean, variance)
+}
+
+#[test]
+fn the_odds_of_every_pick_add_up_to_one() {
+    let mut delta = many_lights();
+    delta.push(Arc::new(DirectionalLight::new(
+        Vec3(0., -1., 0.),
+        color(0.3, 0.3, 0.3),
+    )));
+    delta.push(Arc::new(SpotLight::new(
+        point(0., 4., 0.),
+        Vec3(0., -1., 0.),
+        20.,
+        40.,
+        color(50., 50., 50.),
+    )));
+    for sampling in [
+        LightSampling::Uniform,
+        LightSampling::Power,
+        LightSampling::Tree,
+    ] {
+        let selector = LightSelector::new(sampling, &delta, 20.).unwrap();
+        assert_eq!(selector.len(), delta.len());
+        for at in [point(0., 0., 0.), point(-8., 0., 3.), point(30., 2., -40.)] {
+            let total: f64 = (0..delta.len()).map(|i| selector.probability(at, i)).sum();
+            assert!(
+                (total - 1.0).abs() < 1e-9,
+                "{} {:?}: {}",
+                sampling,
+                at,
+                total
+            );
+            // Each pick reports the odds `probability` gives it.
+            for k in 0..50 {
+                let xi = (k as f64 + 0.5) / 50.;
+                let (i, probability) = selector.pick(at, xi).unwrap();
+                let expected = selector.probability(at, i);
+                assert!(
+                    (probability - expected).abs() < 1e-9 * expected,
+                    "{}",
+                    sampling
+                );
+            }
+        }
+    }
+    assert!(LightSelector::new(LightSampling::All, &delta, 20.).is_none());
+}
+
+#[test]
+fn trees_favor_the_lights_nearby() {
+    let delta: Vec<Arc<dyn DeltaLight>> = vec![
+        Arc::new(PointLight::new(point(-10., 1., 0.), color(1., 1., 1.))),
+        Arc::new(PointLight::new(point(10., 1., 0.), color(1., 1., 1.))),
+    ];
+    let selector = LightSelector::new(LightSampling::Tree, &delta, 20.).unwrap();
+    assert!(selector.probability(point(-10., 0., 0.), 0) > 0.9);
+    assert!(selector.probability(point(10., 0., 0.), 1) > 0.9);
+    let power = LightSelector::new(LightSampling::Power, &delta, 20.).unwrap();
+    assert_eq!(power.probability(point(-10., 0., 0.), 0), 0.5);
+}
+
+#[test]
+fn every_strategy_converges_to_the_light_of_all_of_them() {
+    let world = floor();
+    for at in [point(0., 0., 0.), point(-6., 0., -3.)] {
+        let exact = shade(&world, &lights(many_lights(), LightSampling::All), at, 0);
+        for sampling in [
+            LightSampling::Uniform,
+            LightSampling::Power,
+            LightSampling::Tree,
+        ] {
+            let (mean, variance) = estimate(&world, &lights(many_lights(), sampling), at, 4000);
+            // Within four standard errors.
+            let error = 4.0 * (variance / 4000.).sqrt();
+            assert!(
+                (mean - exact).abs() < error.max(1e-3 * exact),
+                "{} at {:?}: {} vs {} (± {})",
+                sampling,
+                at,
+                mean,
+                exact,
+                error
+            );
+        }
+    }
+}
+
+#[test]
+fn picking_by_contribution_is_far_less_noisy() {
+    let world = floor();
+    let at = point(-3., 0., 1.);
+    let variance = |sampling| estimate(&world, &lights(many_lights(), sampling), at, 2000).1;
+    let uniform = variance(LightSampling::Uniform);
+    let power = variance(LightSampling::Power);
+    let tree = variance(LightSampling::Tree);
+    assert!(power < uniform / 2.0, "power {} uniform {}", power, uniform);
+    assert!(tree < power / 2.0, "tree {} power {}", tree, power);
+}
+
+#[test]
+fn scene_files_keep_the_strategy() {
+    let scene = scenes::build("ceiling_lights").unwrap();
+    assert_eq!(scene.lights.sampling(), LightSampling::Tree);
+    assert_eq!(scene.lights.delta.len(), 200);
+    let desc = scene.describe().unwrap();
+    let saved = toml::to_string(&desc).unwrap();
+    assert!(saved.contains("light_sampling = \"tree\""));
+    let loaded: ray_tracer::scenes::SceneDesc = toml::from_str(&saved).unwrap();
+    assert_eq!(loaded.light_sampling, LightSampling::Tree);
+    assert!("sideways".parse::<LightSampling>().is_err());
+}
@@ -0,0 +1,127 @@
+//! Scenes find their lights on their own, through groups, BVHs, and transforms.
+
+use std::sync::Arc;
+
+use ray_tracer::{
+    color, point,
+    scenes::{self, Scene},
+    BoundNode, BoundingBox, Camera, DiffuseLight, Hittable, HittableList, Lambertian, Material,
+    Parallelogram, RotateY, Sphere, Translation, Vec3,
+};
+
+fn camera() -> Camera {
+    Camera::new(
+        1.0,
+        16,
+        40.,
+        point(0., 0., 10.),
+        point(0., 0., 0.),
+        Vec3(0., 1., 0.),
+        1,
+        4,
+    )
+}
+
+fn light() -> Arc<dyn Material> {
+    Arc::new(DiffuseLight::from(color(4., 4., 4.)))
+}
+
+fn white() -> Arc<dyn Material> {
+    Arc::new(Lambertian::from(color(0.73, 0.73, 0.73)))
+}
+
+fn panel() -> Arc<dyn Hittable> {
+    Arc::new(Parallelogram::new(
+        point(0., 0., 0.),
+        (Vec3(1., 0., 0.), Vec3(0., 0., 1.)),
+        light(),
+    ))
+}
+
+fn assert_close(a: BoundingBox, b: BoundingBox) {
+    for axis in 0..3 {
+        assert!(
+            (a.intervals[axis].start - b.intervals[axis].start).abs() < 1e-9
+                && (a.intervals[axis].end - b.intervals[axis].end).abs() < 1e-9,
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+}
+
+#[test]
+fn cornell_scenes_register_the_ceiling_light() {
+    for name in ["cornell_box", "cornell_smoke"] {
+        let scene = scenes::build(name).unwrap();
+        assert_eq!(scene.lights.area.objects.len(), 1, "{}", name);
+        let bounds = scene.lights.area.objects[0].bound();
+        assert!(
+            bounds.intervals[1].contains(554.),
+            "{}: light at {:?}",
+            name,
+            bounds
+        );
+    }
+}
+
+#[test]
+fn scenes_without_emitters_have_no_lights() {
+    assert!(scenes::build("quads")
+        .unwrap()
+        .lights
+        .area
+        .objects
+        .is_empty());
+}
+
+#[test]
+fn transformed_lights_are_registered_in_place() {
+    let moved: Arc<dyn Hittable> = Arc::new(Translation::new(
+        Arc::new(RotateY::new(panel(), 30.)),
+        Vec3(2., 3., 4.),
+    ));
+    let mut scene = Scene::new(camera(), HittableList::new());
+    scene.add(moved.clone());
+    assert_eq!(scene.lights.area.objects.len(), 1);
+    assert!(Arc::ptr_eq(&scene.lights.area.objects[0], &moved));
+}
+
+#[test]
+fn lights_inside_transformed_groups_are_moved_with_them() {
+    let mut group = HittableList::new();
+    group.add_arc(panel());
+    group.add(Sphere::new(point(5., 0., 0.), 1., white()));
+    let offset = Vec3(0., 10., 0.);
+    let mut world = HittableList::new();
+    world.add(Translation::new(Arc::new(group), offset));
+
+    let scene = Scene::new(camera(), world);
+    assert_eq!(scene.lights.area.objects.len(), 1);
+    assert_close(
+        scene.lights.area.objects[0].bound(),
+        panel().bound() + offset,
+    );
+}
+
+#[test]
+fn lights_are_found_through_a_bvh() {
+    let mut objects = HittableList::new();
+    for i in 0..5 {
+        objects.add(Sphere::new(point(i as f64 * 3., 0., 0.), 1., white()));
+    }
+    objects.add(Sphere::new(point(0., 5., 0.), 1., light()));
+    objects.add_arc(panel());
+    let world = HittableList::from(Arc::new(BoundNode::from_list(objects)));
+
+    let scene = Scene::new(camera(), world);
+    assert_eq!(scene.lights.area.objects.len(), 2);
+}
+
+#[test]
+fn lights_can_be_added_explicitly() {
+    let mut scene = Scene::new(camera(), HittableList::new());
+    scene.add_light(panel());
+    assert_eq!(scene.lights.area.objects.len(), 1);
+    assert!(scene.world.objects.is_empty());
+}
@@ -0,0 +1,166 @@
+//! Mesh load reports: what a file held, what loading it cost, and the
+//! progress callbacks sent while it was read.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use ray_tracer::{
+    color, load_model, load_model_normalized, set_load_listener, AssetResolver, Lambertian,
+    LoadListener, LoadReport, TriangleMesh, PROGRESS_INTERVAL,
+};
+
+fn fixture(name: &str, contents: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ray-tracer-load-report-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+fn load(path: &Path) -> TriangleMesh {
+    let material = Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)));
+    load_model(path, &AssetResolver::new(), material, None).unwrap()
+}
+
+/// A flat `n` by `n` grid of quads on the unit square, with normals and
+/// texture coordinates when asked for.
+fn grid_obj(n: usize, attributes: bool) -> String {
+    let mut obj = String::from("o grid\n");
+    for j in 0..=n {
+        for i in 0..=n {
+            let (u, v) = (i as f64 / n as f64, j as f64 / n as f64);
+            obj += &format!("v {} {} 0\n", u, v);
+            if attributes {
+                obj += &format!("vt {} {}\nvn 0 0 1\n", u, v);
+            }
+        }
+    }
+    let index = |i: usize, j: usize| j * (n + 1) + i + 1;
+    for j in 0..n {
+        for i in 0..n {
+            let corners = [
+                index(i, j),
+                index(i + 1, j),
+                index(i + 1, j + 1),
+                index(i, j + 1),
+            ];
+            obj += "f";
+            for c in corners {
+                if attributes {
+                    obj += &format!(" {}/{}/{}", c, c, c);
+                } else {
+                    obj += &format!(" {}", c);
+                }
+            }
+            obj += "\n";
+        }
+    }
+    obj
+}
+
+fn report(mesh: &TriangleMesh) -> &LoadReport {
+    mesh.report.as_ref().expect("the loader left no report")
+}
+
+#[test]
+fn reports_describe_what_the_file_held() {
+    let path = fixture("textured.obj", &grid_obj(4, true));
+    let mesh = load(&path);
+    let report = report(&mesh);
+    assert_eq!(report.path, path);
+    // The parser keeps a vertex per distinct position, uv, and normal triple.
+    assert_eq!(report.triangles, 32);
+    assert_eq!(report.vertices, mesh.vertex_count());
+    assert!(report.normals && report.uvs && !report.colors);
+    assert_eq!(report.materials, 0);
+    let [x, y, z] = report.bounds.intervals;
+    for (interval, (start, end)) in [(x, (0.0, 1.0)), (y, (0.0, 1.0)), (z, (0.0, 0.0))] {
+        assert!((interval.start - start).abs() < 1e-3 && (interval.end - end).abs() < 1e-3);
+    }
+    assert!(report.bytes > 0);
+
+    let text = report.to_string();
+    let counts = format!("32 triangles, {} vertices", report.vertices);
+    assert!(text.contains(&counts), "{}", text);
+    assert!(text.contains("normals: file, uvs: yes"), "{}", text);
+}
+
+#[test]
+fn computed_normals_and_colors_are_reported() {
+    let mesh = load(Path::new("resources/models/gradient_cube.ply"));
+    let report = report(&mesh);
+    assert!(!report.normals && !report.uvs && report.colors);
+    assert_eq!(report.triangles, 12);
+    assert!(report.to_string().contains("normals: computed"));
+}
+
+#[test]
+fn estimated_memory_grows_with_the_mesh() {
+    let small = load(&fixture("small.obj", &grid_obj(4, false)));
+    let large = load(&fixture("large.obj", &grid_obj(16, false)));
+    let (small, large) = (report(&small).bytes, report(&large).bytes);
+    // 16 times the triangles, and the per-triangle cost dominates.
+    assert!(
+        large > 12 * small && large < 20 * small,
+        "{} vs {}",
+        small,
+        large
+    );
+}
+
+#[test]
+fn normalized_loads_report_the_normalized_bounds() {
+    let path = fixture("normalized.obj", &grid_obj(2, false));
+    let material = Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)));
+    let (mesh, _) =
+        load_model_normalized(&path, &AssetResolver::new(), material, 4.0, None).unwrap();
+    let [x, y, _] = report(&mesh).bounds.intervals;
+    for end in [x.start, y.start, -x.end, -y.end] {
+        assert!((end + 2.0).abs() < 1e-3, "{:?}", report(&mesh).bounds);
+    }
+}
+
+/// Records the progress callbacks and reports for one file.
+struct Recorder {
+    path: PathBuf,
+    progress: Mutex<Vec<(usize, usize)>>,
+    loaded: Mutex<Vec<usize>>,
+}
+
+impl LoadListener for Recorder {
+    fn progress(&self, path: &Path, faces: usize, total: usize) {
+        if path == self.path {
+            self.progress.lock().unwrap().push((faces, total));
+        }
+    }
+    fn loaded(&self, report: &LoadReport) {
+        if report.path == self.path {
+            self.loaded.lock().unwrap().push(report.triangles);
+        }
+    }
+}
+
+#[test]
+fn listeners_hear_progress_and_the_final_report() {
+    // 75 by 75 quads: 11250 triangles.
+    let path = fixture("progress.obj", &grid_obj(75, false));
+    let recorder = Arc::new(Recorder {
+        path: path.clone(),
+        progress: Mutex::new(Vec::new()),
+        loaded: Mutex::new(Vec::new()),
+    });
+    set_load_listener(Some(recorder.clone()));
+    load(&path);
+    set_load_listener(None);
+
+    let total = 11250;
+    let expected: Vec<_> = (1..=total / PROGRESS_INTERVAL)
+        .map(|i| (i * PROGRESS_INTERVAL, total))
+        .chain([(total, total)])
+        .collect();
+    assert_eq!(*recorder.progress.lock().unwrap(), expected);
+    assert_eq!(*recorder.loaded.lock().unwrap(), vec![total]);
+}
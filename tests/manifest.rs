@@ -0,0 +1,86 @@
+//! Render manifests run every job, writing each image at the size asked for,
+//! and a failed job doesn't stop the rest.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use ray_tracer::{scenes::RenderManifest, ImageBuffer};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "ray-tracer-manifest-{}-{}",
+        name,
+        std::process::id()
+    ));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Two tiny renders of scenes with different aspect ratios, one by name and
+/// one from a scene file, and a job naming a scene that doesn't exist.
+fn manifest(dir: &Path) -> RenderManifest {
+    let text = format!(
+        r#"
+        [[jobs]]
+        name = "box"
+        scene = "cornell_box"
+        width = 16
+        samples = 1
+        max_depth = 2
+        seed = 1
+        output = "{dir}/box.ppm"
+
+        [[jobs]]
+        name = "missing"
+        scene = "no_such_scene"
+        output = "{dir}/missing.ppm"
+
+        [[jobs]]
+        name = "spheres"
+        scene_file = "resources/scenes/material_spheres.json"
+        width = 32
+        samples = 1
+        max_depth = 2
+        seed = 1
+        output = "{dir}/nested/spheres.ppm"
+        "#,
+        dir = dir.display()
+    );
+    let path = dir.join("jobs.toml");
+    fs::write(&path, text).unwrap();
+    RenderManifest::from_path(&path).unwrap()
+}
+
+fn dimensions(path: PathBuf) -> (usize, usize) {
+    let image = ImageBuffer::load(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+    (image.width, image.height)
+}
+
+fn run(parallel_jobs: usize) {
+    let dir = temp_dir(&format!("{}-at-once", parallel_jobs));
+    let reports = manifest(&dir).run(parallel_jobs);
+
+    let names: Vec<_> = reports.iter().map(|report| report.name.as_str()).collect();
+    assert_eq!(names, ["box", "missing", "spheres"]);
+    assert!(reports[0].result.is_ok());
+    assert!(reports[1].result.is_err());
+    assert!(reports[2].result.is_ok());
+
+    assert_eq!(dimensions(dir.join("box.ppm")), (16, 16));
+    // The scene file's camera is 16:9.
+    assert_eq!(dimensions(dir.join("nested/spheres.ppm")), (32, 18));
+    assert!(!dir.join("missing.ppm").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn every_job_writes_its_image() {
+    run(1);
+}
+
+#[test]
+fn parallel_jobs_write_the_same_images() {
+    run(3);
+}
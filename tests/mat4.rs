@@ -0,0 +1,192 @@
+//! The 4×4 matrix type: products, inverses, determinants, normal transforms,
+//! and the conversions to and from the simpler transform types.
+
+use proptest::prelude::*;
+use ray_tracer::{point, Mat4, Quat, Transform, Vec3};
+
+const EPSILON: f64 = 1e-9;
+
+fn close(a: Vec3, b: Vec3, tolerance: f64) -> bool {
+    (a - b).length() < tolerance
+}
+
+fn max_difference(a: &Mat4, b: &Mat4) -> f64 {
+    a.m.iter()
+        .flatten()
+        .zip(b.m.iter().flatten())
+        .fold(0.0, |max, (x, y)| f64::max(max, (x - y).abs()))
+}
+
+fn entries() -> impl Strategy<Value = Mat4> {
+    prop::array::uniform4(prop::array::uniform4(-10.0..10.0)).prop_map(Mat4::new)
+}
+
+/// Matrices far enough from singular that their inverses are accurate.
+fn invertible() -> impl Strategy<Value = Mat4> {
+    entries().prop_filter("matrix is nearly singular", |m| m.determinant().abs() > 1.0)
+}
+
+fn vec3(range: std::ops::Range<f64>) -> impl Strategy<Value = Vec3> {
+    (range.clone(), range.clone(), range).prop_map(|(x, y, z)| Vec3(x, y, z))
+}
+
+fn axis() -> impl Strategy<Value = Vec3> {
+    vec3(-1.0..1.0).prop_filter("axis must be nonzero", |v| v.length() > 1e-3)
+}
+
+/// Nonzero scale factors of either sign.
+fn scale() -> impl Strategy<Value = Vec3> {
+    let factor = || prop_oneof![-4.0..-0.25, 0.25..4.0];
+    (factor(), factor(), factor()).prop_map(|(x, y, z)| Vec3(x, y, z))
+}
+
+proptest! {
+    #[test]
+    fn inverse_undoes_the_matrix(m in invertible()) {
+        let inverse = m.inverse().unwrap();
+        prop_assert!(max_difference(&(inverse * m), &Mat4::identity()) < 1e-8);
+        prop_assert!(max_difference(&(m * inverse), &Mat4::identity()) < 1e-8);
+        prop_assert!((inverse.determinant() * m.determinant() - 1.0).abs() < 1e-8);
+    }
+
+    #[test]
+    fn determinants_multiply(a in entries(), b in entries()) {
+        let expected = a.determinant() * b.determinant();
+        let tolerance = 1e-9 * expected.abs().max(1.0) * 1e3;
+        prop_assert!(((a * b).determinant() - expected).abs() < tolerance);
+        prop_assert!((a.transpose().determinant() - a.determinant()).abs() < tolerance);
+    }
+
+    #[test]
+    fn matrices_with_dependent_rows_are_singular(
+        m in entries(),
+        (from, to) in (0..4usize, 0..4usize).prop_filter("rows must differ", |(a, b)| a != b),
+        factor in -3.0..3.0,
+    ) {
+        let mut singular = m;
+        singular.m[to] = singular.m[from].map(|v| v * factor);
+        prop_assert!(singular.inverse().is_none());
+        prop_assert!(singular.determinant().abs() < 1e-9);
+    }
+
+    #[test]
+    fn normals_stay_perpendicular_under_any_scale(
+        scale in scale(),
+        axis in axis(),
+        angle in -180.0..180.0,
+        tangent in axis(),
+        other in axis(),
+    ) {
+        let m = Mat4::rotation(axis, angle) * Mat4::scale(scale);
+        let normal = Vec3::cross(&tangent, &other);
+        prop_assume!(normal.length() > 1e-3);
+        // Both tangents of the surface stay perpendicular to its normal.
+        let transformed = m.transform_normal(normal);
+        for t in [tangent, other] {
+            let t = m.transform_vector(t).unit();
+            prop_assert!(Vec3::dot(&transformed, &t).abs() < 1e-9);
+        }
+        prop_assert!((transformed.length() - 1.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn decomposing_recovers_the_parts(
+        translation in vec3(-50.0..50.0),
+        axis in axis(),
+        angle in -180.0..180.0,
+        scale in scale(),
+    ) {
+        let rotation = Quat::from_axis_angle(axis, angle);
+        let m = Mat4::translation(translation) * Mat4::from(rotation) * Mat4::scale(scale);
+        let (t, r, s) = m.decompose().unwrap();
+        let rebuilt = Mat4::translation(t) * Mat4::from(r) * Mat4::scale(s);
+        prop_assert!(max_difference(&m, &rebuilt) < 1e-9);
+        prop_assert!(close(t, translation, 1e-9));
+        for (got, want) in [(s.0, scale.0), (s.1, scale.1), (s.2, scale.2)] {
+            prop_assert!((got.abs() - want.abs()).abs() < 1e-9);
+        }
+    }
+}
+
+#[test]
+fn singular_matrices_have_no_inverse() {
+    assert!(Mat4::new([[0.; 4]; 4]).inverse().is_none());
+    assert!(Mat4::scale(Vec3(1., 0., 1.)).inverse().is_none());
+    let mut projection = Mat4::identity();
+    projection.m[3] = [0., 0., 0., 0.];
+    assert!(projection.inverse().is_none());
+    assert!(Mat4::scale(Vec3(1e-3, 1e-3, 1e-3)).inverse().is_some());
+}
+
+#[test]
+fn basic_constructors_move_points_and_vectors() {
+    let m = Mat4::translation(Vec3(1., 2., 3.)) * Mat4::scale(Vec3(2., 2., 2.));
+    assert!(close(
+        m.transform_point(point(1., 1., 1.)),
+        point(3., 4., 5.),
+        EPSILON
+    ));
+    assert!(close(
+        m.transform_vector(Vec3(1., 1., 1.)),
+        Vec3(2., 2., 2.),
+        EPSILON
+    ));
+    assert!((m.determinant() - 8.0).abs() < EPSILON);
+
+    // Positive angles turn counterclockwise looking down each axis.
+    let turned = |m: Mat4, v: Vec3| m.transform_vector(v);
+    assert!(close(
+        turned(Mat4::rotation_x(90.), Vec3(0., 1., 0.)),
+        Vec3(0., 0., 1.),
+        EPSILON
+    ));
+    assert!(close(
+        turned(Mat4::rotation_y(90.), Vec3(0., 0., 1.)),
+        Vec3(1., 0., 0.),
+        EPSILON
+    ));
+    assert!(close(
+        turned(Mat4::rotation_z(90.), Vec3(1., 0., 0.)),
+        Vec3(0., 1., 0.),
+        EPSILON
+    ));
+    assert_eq!(Mat4::identity().transpose(), Mat4::identity());
+}
+
+#[test]
+fn look_at_points_the_camera_down_its_negative_z() {
+    let from = point(3., 2., 5.);
+    let at = point(0., 1., 0.);
+    let m = Mat4::look_at(from, at, Vec3(0., 1., 0.));
+    assert!(close(m.transform_point(point(0., 0., 0.)), from, EPSILON));
+    let forward = m.transform_vector(Vec3(0., 0., -1.));
+    assert!(close(forward, (at - from).unit(), EPSILON));
+    // The camera's right stays level and its up leans toward world up.
+    assert!(m.transform_vector(Vec3(1., 0., 0.)).1.abs() < EPSILON);
+    assert!(m.transform_vector(Vec3(0., 1., 0.)).1 > 0.0);
+    assert!((m.determinant() - 1.0).abs() < EPSILON);
+}
+
+#[test]
+fn transforms_and_quaternions_become_matrices() {
+    let transform = Transform {
+        translation: Vec3(1., -2., 0.5),
+        rotation_y: 35.,
+        scale: Vec3(2., 0.5, 3.),
+    };
+    let m = Mat4::from(transform);
+    for p in [point(0., 0., 0.), point(1., 2., 3.), point(-4., 0.5, 1.)] {
+        assert!(close(m.transform_point(p), transform.point(p), EPSILON));
+    }
+    for n in [Vec3(0., 1., 0.), Vec3(1., 2., 3.).unit()] {
+        assert!(close(m.transform_normal(n), transform.normal(n), EPSILON));
+    }
+
+    let q = Quat::from_euler(10., 20., 30.);
+    let v = Vec3(0.3, -1., 2.);
+    assert!(close(
+        Mat4::from(q).transform_vector(v),
+        q.rotate(v),
+        EPSILON
+    ));
+}
@@ -0,0 +1,163 @@
+//! ID mattes: objects tagged with a name, and how much of each pixel they
+//! cover.
+
+use std::{fs, sync::Arc};
+
+use ray_tracer::{
+    color, point,
+    scenes::{self, Scene},
+    Camera, Dielectric, Hittable, HittableList, IndependentSampler, Interval, Lambertian, Matte,
+    Ray, Sphere, Vec3,
+};
+
+/// The Cornell box, small and seeded so it renders quickly.
+fn tiny_cornell() -> Scene {
+    let mut scene = scenes::build("cornell_box").unwrap();
+    scene
+        .camera
+        .set_image_width(24)
+        .set_aa_samples(8)
+        .set_max_depth(4)
+        .set_seed(Some(3));
+    scene
+}
+
+fn sphere(center: f64) -> Arc<dyn Hittable> {
+    Arc::new(Sphere::new(
+        point(center, 0., 0.),
+        1.,
+        Arc::new(Lambertian::from(color(0.5, 0.5, 0.5))),
+    ))
+}
+
+#[test]
+fn cornell_mattes_and_the_background_cover_every_pixel() {
+    let mattes = tiny_cornell().render_mattes(&["tall_box", "short_box"]);
+    assert_eq!((mattes.width, mattes.height), (24, 24));
+    let tall = mattes.coverage("tall_box").unwrap();
+    let short = mattes.coverage("short_box").unwrap();
+    for i in 0..mattes.width * mattes.height {
+        let sum = tall[i] + short[i] + mattes.background[i];
+        assert!((sum - 1.0).abs() < 1e-9, "pixel {}: {}", i, sum);
+    }
+
+    // Each box fills some pixels whole, and its edges are antialiased.
+    for coverage in [tall, short, &mattes.background] {
+        assert!(coverage.contains(&1.0));
+        assert!(coverage.iter().any(|&c| c > 0.0 && c < 1.0));
+    }
+    assert!(mattes.coverage("light").is_none());
+}
+
+#[test]
+fn inner_tags_win_over_the_ones_around_them() {
+    let mut group = HittableList::new();
+    group.add(Matte::new("inner", sphere(-2.)));
+    group.add_arc(sphere(2.));
+    let mut world = HittableList::new();
+    world.add(Matte::new("outer", Arc::new(group)));
+    world.add_arc(sphere(6.));
+
+    let matte = |x: f64| {
+        let ray = Ray {
+            origin: point(x, 0., 5.),
+            direction: Vec3(0., 0., -1.),
+            time: 0.0,
+        };
+        let mut rng = IndependentSampler::new(0);
+        let t = Interval::from_range(1e-9..f64::INFINITY);
+        world
+            .hit(&ray, t, &mut rng)
+            .map(|record| record.matte.map(str::to_string))
+    };
+    assert_eq!(matte(-2.), Some(Some("inner".to_string())));
+    assert_eq!(matte(2.), Some(Some("outer".to_string())));
+    assert_eq!(matte(6.), Some(None));
+    assert_eq!(matte(10.), None);
+}
+
+#[test]
+fn mattes_trace_the_rays_of_the_render() {
+    // Glass covers what is behind it as much as anything else does.
+    let mut world = HittableList::new();
+    world.add(Matte::new(
+        "glass",
+        Arc::new(Sphere::new(
+            point(0., 0., 0.),
+            1.,
+            Arc::new(Dielectric::new(1.5)),
+        )),
+    ));
+    let mut camera = Camera::new(
+        1.0,
+        10,
+        30.,
+        point(0., 0., 5.),
+        point(0., 0., 0.),
+        Vec3(0., 1., 0.),
+        6,
+        4,
+    );
+    camera.set_seed(Some(9)).set_transparent(true);
+    let scene = Scene::new(camera, world);
+
+    // So a transparent render's alpha is the matte, edges and all.
+    let alpha = scene.render_image().alpha.unwrap();
+    let mattes = scene.render_mattes(&["glass"]);
+    let glass = mattes.coverage("glass").unwrap();
+    assert!(glass.iter().any(|&c| c > 0.0 && c < 1.0));
+    for (a, c) in alpha.iter().zip(glass) {
+        assert!((a - c).abs() < 1e-12, "{} {}", a, c);
+    }
+}
+
+#[test]
+fn mattes_become_grayscale_or_packed_images() {
+    let mattes = tiny_cornell().render_mattes(&["tall_box", "short_box"]);
+    let tall = mattes.coverage("tall_box").unwrap();
+
+    // Coverage is squared, so the gamma-corrected pixels are the coverage.
+    let image = mattes.image(Some("tall_box")).unwrap();
+    for (pixel, &c) in image.pixels.iter().zip(tall) {
+        assert_eq!((pixel.0, pixel.1, pixel.2), (c * c, c * c, c * c));
+    }
+    let background = mattes.image(None).unwrap();
+    assert_eq!(background.pixels[0].0, mattes.background[0].powi(2));
+    assert!(mattes.image(Some("light")).is_none());
+
+    let packed = mattes.packed(&["short_box", "tall_box"]).unwrap();
+    let short = mattes.coverage("short_box").unwrap();
+    for (i, pixel) in packed.pixels.iter().enumerate() {
+        assert_eq!(
+            (pixel.0, pixel.1, pixel.2),
+            (short[i].powi(2), tall[i].powi(2), 0.)
+        );
+    }
+    assert!(mattes.packed(&["tall_box", "light"]).is_none());
+    assert!(mattes
+        .packed(&["tall_box", "short_box", "tall_box", "short_box"])
+        .is_none());
+}
+
+#[test]
+fn tags_survive_scene_files() {
+    let scene = tiny_cornell();
+    let dir = std::env::temp_dir().join(format!("ray-tracer-mattes-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("scene.json");
+    scene.describe().unwrap().save(&path).unwrap();
+    let text = fs::read_to_string(&path).unwrap();
+    let loaded = Scene::from_path(&path);
+    fs::remove_dir_all(&dir).unwrap();
+    let mut loaded = loaded.unwrap();
+    assert!(text.contains("\"matte\""), "{}", text);
+    assert_eq!(loaded.fingerprint(), scene.fingerprint());
+
+    // The seed is the render's, not the scene's.
+    loaded.camera.set_seed(scene.camera.seed);
+
+    let names = ["tall_box", "short_box"];
+    let (mattes, reloaded) = (scene.render_mattes(&names), loaded.render_mattes(&names));
+    assert_eq!(reloaded.mattes, mattes.mattes);
+    assert_eq!(reloaded.background, mattes.background);
+}
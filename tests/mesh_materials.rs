@@ -0,0 +1,150 @@
+//! Per-face materials: OBJ `usemtl` switches become indices into one mesh's
+//! palette instead of separate meshes.
+
+use std::{fs, sync::Arc};
+
+use ray_tracer::{
+    color, load_model, point, AssetResolver, Color, Hittable, IndependentSampler, Interval,
+    Lambertian, Material, MeshData, Ray, TriangleMesh, Vec3,
+};
+
+/// A unit cube around the origin whose +x, +y, and +z sides are red and
+/// whose -x, -y, and -z sides are blue, switching material on every side.
+const CUBE_OBJ: &str = "mtllib cube.mtl
+o cube
+v -0.5 -0.5 -0.5
+v 0.5 -0.5 -0.5
+v -0.5 0.5 -0.5
+v 0.5 0.5 -0.5
+v -0.5 -0.5 0.5
+v 0.5 -0.5 0.5
+v -0.5 0.5 0.5
+v 0.5 0.5 0.5
+usemtl red
+f 2 4 8 6
+usemtl blue
+f 1 5 7 3
+usemtl red
+f 3 7 8 4
+usemtl blue
+f 1 2 6 5
+usemtl red
+f 5 6 8 7
+usemtl blue
+f 1 3 4 2
+";
+
+/// The OBJ parser only reads materials with every field Blender writes.
+const CUBE_MTL: &str = "newmtl red
+Ns 0.0
+Ka 1.0 1.0 1.0
+Kd 0.9 0.1 0.1
+Ks 0.0 0.0 0.0
+d 1.0
+illum 1
+
+newmtl blue
+Ns 0.0
+Ka 1.0 1.0 1.0
+Kd 0.1 0.1 0.9
+Ks 0.0 0.0 0.0
+d 1.0
+illum 1
+";
+
+fn gray() -> Arc<dyn Material> {
+    Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)))
+}
+
+fn load_cube() -> TriangleMesh {
+    let dir = std::env::temp_dir().join(format!("ray-tracer-materials-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("cube.obj"), CUBE_OBJ).unwrap();
+    fs::write(dir.join("cube.mtl"), CUBE_MTL).unwrap();
+    let mesh = load_model(&dir.join("cube.obj"), &AssetResolver::new(), gray(), None).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    mesh
+}
+
+/// The albedo of the material `mesh` shows where a ray from `from` toward
+/// the origin first meets it.
+fn albedo_seen_from(mesh: &TriangleMesh, from: Vec3) -> Color {
+    let ray = Ray {
+        origin: from,
+        direction: -from,
+        time: 0.0,
+    };
+    let mut rng = IndependentSampler::new(1);
+    let record = mesh
+        .hit(&ray, Interval::from_range(0.001..f64::INFINITY), &mut rng)
+        .expect("the ray missed the cube");
+    record
+        .material
+        .scatter(&ray, &record, &mut rng)
+        .expect("the material absorbed the ray")
+        .attenuation
+}
+
+#[test]
+fn each_side_of_the_cube_gets_its_material() {
+    let mesh = load_cube();
+    assert_eq!(mesh.materials().len(), 2);
+    assert_eq!(mesh.material_ids().unwrap().len(), mesh.triangle_count());
+
+    let red = color(0.9, 0.1, 0.1);
+    let blue = color(0.1, 0.1, 0.9);
+    let sides = [
+        (Vec3(3., 0.1, 0.2), red),
+        (Vec3(-3., 0.1, 0.2), blue),
+        (Vec3(0.1, 3., 0.2), red),
+        (Vec3(0.1, -3., 0.2), blue),
+        (Vec3(0.1, 0.2, 3.), red),
+        (Vec3(0.1, 0.2, -3.), blue),
+    ];
+    for (from, expected) in sides {
+        let albedo = albedo_seen_from(&mesh, from);
+        // MTL colors are stored as bytes.
+        assert!(
+            (albedo - expected).length() < 2.0 / 255.0,
+            "seen from {:?}: {:?}, expected {:?}",
+            from,
+            albedo,
+            expected
+        );
+    }
+}
+
+#[test]
+fn faces_without_a_valid_index_use_the_default() {
+    let data = MeshData {
+        positions: vec![
+            point(0., 0., 0.),
+            point(1., 0., 0.),
+            point(0., 1., 0.),
+            point(0., 0., 1.),
+        ],
+        faces: vec![[0, 1, 2], [0, 2, 3]],
+        ..MeshData::default()
+    };
+    let green: Arc<dyn Material> = Arc::new(Lambertian::from(color(0.1, 0.9, 0.1)));
+    let mesh = TriangleMesh::with_materials(data, gray(), vec![green.clone()], vec![0, 7]);
+    assert!(Arc::ptr_eq(mesh.face_material(0), &green));
+    assert!(!Arc::ptr_eq(mesh.face_material(1), &green));
+
+    let mut issues = Vec::new();
+    mesh.validate("mesh", &mut issues);
+    assert_eq!(issues.len(), 1);
+    assert!(issues[0].to_string().contains("1 triangles index past"));
+}
+
+#[test]
+fn objs_without_materials_have_no_palette() {
+    let dir = std::env::temp_dir().join(format!("ray-tracer-plain-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("plain.obj");
+    fs::write(&path, "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+    let mesh = load_model(&path, &AssetResolver::new(), gray(), None).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(mesh.materials().is_empty());
+    assert!(mesh.material_ids().is_none());
+}
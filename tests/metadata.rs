@@ -0,0 +1,146 @@
+//! Render metadata: the settings a render was made with, saved in the image
+//! and beside it, and read back.
+
+use std::{fs, path::PathBuf};
+
+use ray_tracer::{
+    read_image_metadata,
+    scenes::{self, RenderManifest, RenderMetadata},
+    Error, ImageBuffer,
+};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "ray-tracer-metadata-{}-{}",
+        name,
+        std::process::id()
+    ));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn render(scene_name: &str) -> (ImageBuffer, RenderMetadata) {
+    let mut scene = scenes::build(scene_name).unwrap();
+    scene
+        .camera
+        .set_image_width(8)
+        .set_aa_samples(2)
+        .set_max_depth(3)
+        .set_seed(Some(42));
+    let (image, stats) = scene.render_timed();
+    let metadata = RenderMetadata::new(scene_name, &scene.camera, &stats);
+    (image, metadata)
+}
+
+fn assert_same(read: &RenderMetadata, written: &RenderMetadata) {
+    assert_eq!(read.to_json(), written.to_json());
+    assert_eq!(read.scene, "cornell_box");
+    assert_eq!((read.width, read.height), (8, 8));
+    assert_eq!((read.samples, read.max_depth), (2, 3));
+    assert_eq!(read.seed, Some(42));
+    assert_eq!(read.camera.look_from.2, written.camera.look_from.2);
+    assert!(read.software.ends_with(env!("CARGO_PKG_VERSION")));
+}
+
+#[test]
+fn ppm_comments_round_trip() {
+    let dir = temp_dir("ppm");
+    let (image, metadata) = render("cornell_box");
+    let path = dir.join("box.ppm");
+    metadata.save_image(&image, &path).unwrap();
+    let read = RenderMetadata::read(&path);
+    let text = fs::read_to_string(&path).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_same(&read.unwrap(), &metadata);
+    assert!(text.starts_with("P3\n# {\n#   \"scene\": \"cornell_box\",\n"));
+    // The pixels are the same as without the comments.
+    let mut plain = Vec::new();
+    image.write_ppm(&mut plain).unwrap();
+    let plain = String::from_utf8(plain).unwrap();
+    assert!(text
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .eq(plain.lines()));
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn png_text_chunks_round_trip() {
+    let dir = temp_dir("png");
+    let (image, mut metadata) = render("cornell_box");
+    let path = dir.join("box.png");
+    metadata.save_image(&image, &path).unwrap();
+    let read = RenderMetadata::read(&path).unwrap();
+    assert_same(&read, &metadata);
+
+    // The PNG decodes to the same pixels the PPM holds.
+    let decoded = ray_tracer::ColorTexture::from_file(&fs::read(&path).unwrap()).unwrap();
+    assert_eq!((decoded.width, decoded.height), (8, 8));
+    let bytes = image.to_rgba8();
+    for (i, pixel) in decoded.data.iter().enumerate() {
+        assert_eq!((pixel.0 * 255.).round() as u8, bytes[4 * i]);
+    }
+
+    // Text beyond Latin-1 survives too.
+    metadata.scene = "scènes/立方体.toml".to_string();
+    metadata.save_image(&image, &path).unwrap();
+    let read = RenderMetadata::read(&path);
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(read.unwrap().scene, "scènes/立方体.toml");
+}
+
+#[test]
+fn sidecars_hold_the_same_json() {
+    let dir = temp_dir("sidecar");
+    let (_, metadata) = render("cornell_box");
+    let path = dir.join("render.json");
+    metadata.save_sidecar(&path).unwrap();
+    let read = RenderMetadata::read(&path);
+    fs::remove_dir_all(&dir).unwrap();
+    assert_same(&read.unwrap(), &metadata);
+}
+
+#[test]
+fn images_without_metadata_say_so() {
+    let dir = temp_dir("none");
+    let (image, _) = render("cornell_box");
+    let path = dir.join("plain.ppm");
+    image.save(&path).unwrap();
+    let text = read_image_metadata(&path).unwrap();
+    let error = RenderMetadata::read(&path).expect_err("the image has no metadata");
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(text, "");
+    assert!(matches!(error, Error::Decode { .. }));
+    assert!(
+        error.to_string().contains("no render metadata"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn manifest_jobs_embed_metadata_and_write_sidecars() {
+    let dir = temp_dir("manifest");
+    let manifest_path = dir.join("jobs.toml");
+    fs::write(
+        &manifest_path,
+        format!(
+            "[[jobs]]\nscene = \"cornell_box\"\nwidth = 8\nsamples = 1\nseed = 7\n\
+             output = '{0}/out/box.ppm'\nsidecar = '{0}/out/box.json'\n",
+            dir.display()
+        ),
+    )
+    .unwrap();
+    let reports = RenderManifest::from_path(&manifest_path).unwrap().run(1);
+    assert!(reports[0].result.is_ok());
+    let embedded = RenderMetadata::read(&dir.join("out/box.ppm"));
+    let sidecar = RenderMetadata::read(&dir.join("out/box.json"));
+    fs::remove_dir_all(&dir).unwrap();
+
+    let (embedded, sidecar) = (embedded.unwrap(), sidecar.unwrap());
+    assert_eq!(embedded.to_json(), sidecar.to_json());
+    assert_eq!(embedded.seed, Some(7));
+    assert_eq!((embedded.width, embedded.samples), (8, 1));
+    assert!(embedded.to_string().contains("seed:      7\n"));
+}
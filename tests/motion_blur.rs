@@ -0,0 +1,224 @@
+//! Object motion blur: animated transforms, ray times, and the camera shutter.
+
+use std::sync::Arc;
+
+use ray_tracer::{
+    color, point, scenes::SceneDesc, AnimatedTransform, Camera, CameraError, Hittable,
+    IndependentSampler, Interval, Lambertian, Mat4, Pose, Quat, Ray, Sphere, Transformed, Vec3,
+};
+
+const EPSILON: f64 = 1e-9;
+
+fn close(a: Vec3, b: Vec3, tolerance: f64) -> bool {
+    (a - b).length() < tolerance
+}
+
+fn ball() -> Arc<dyn Hittable> {
+    Arc::new(Sphere::new(
+        point(0., 0., 0.),
+        0.5,
+        Arc::new(Lambertian::from(color(0.5, 0.5, 0.5))),
+    ))
+}
+
+fn pose(translation: Vec3, angle: f64) -> Pose {
+    Pose {
+        translation,
+        rotation: Quat::from_axis_angle(Vec3(0., 1., 0.), angle),
+        scale: Vec3(1., 1., 1.),
+    }
+}
+
+/// A ray down the -Z axis through `(x, y)` at `time`.
+fn ray_at(x: f64, y: f64, time: f64) -> Ray {
+    Ray {
+        origin: point(x, y, 5.),
+        direction: Vec3(0., 0., -1.),
+        time,
+    }
+}
+
+#[test]
+fn still_poses_match_the_static_transform_exactly() {
+    let still = Pose {
+        translation: Vec3(0.3, -0.2, 0.1),
+        rotation: Quat::from_euler(10., 20., 30.),
+        scale: Vec3(1.5, 0.5, 1.),
+    };
+    let animated = AnimatedTransform::new(ball(), still, still);
+    let reference = Transformed::new(ball(), still.matrix());
+    let (a, b) = (animated.bound(), reference.bound());
+    for axis in 0..3 {
+        assert_eq!(a.intervals[axis].start, b.intervals[axis].start);
+        assert_eq!(a.intervals[axis].end, b.intervals[axis].end);
+    }
+
+    let t = Interval::new(0.001, f64::INFINITY);
+    for (x, y, time) in [(0.3, -0.2, 0.0), (0.5, -0.1, 0.5), (0.1, -0.3, 1.0)] {
+        let ray = ray_at(x, y, time);
+        let mut rng = IndependentSampler::new(0);
+        let expected = reference.hit(&ray, t, &mut rng).unwrap();
+        let actual = animated.hit(&ray, t, &mut rng).unwrap();
+        assert_eq!(expected.t, actual.t);
+        assert_eq!(
+            (expected.point.0, expected.point.1, expected.point.2),
+            (actual.point.0, actual.point.1, actual.point.2)
+        );
+        assert_eq!(
+            (expected.normal.0, expected.normal.1, expected.normal.2),
+            (actual.normal.0, actual.normal.1, actual.normal.2)
+        );
+    }
+}
+
+#[test]
+fn rays_see_the_pose_at_their_time() {
+    let moving = AnimatedTransform::new(
+        ball(),
+        pose(Vec3(-2., 0., 0.), 0.),
+        pose(Vec3(2., 0., 0.), 0.),
+    );
+    let t = Interval::new(0.001, f64::INFINITY);
+    let mut rng = IndependentSampler::new(0);
+    for (x, time) in [(-2.0, 0.0), (0.0, 0.5), (2.0, 1.0)] {
+        let record = moving.hit(&ray_at(x, 0., time), t, &mut rng).unwrap();
+        assert!(close(record.point, point(x, 0., 0.5), EPSILON));
+        assert_eq!(record.time, time);
+        // Half a unit either side, the ball has moved on.
+        assert!(!moving.hit_any(&ray_at(x + 1.0, 0., time), t, &mut rng));
+    }
+    // Outside the shutter the object holds its end poses.
+    assert!(close(
+        moving.pose(-1.0).translation,
+        Vec3(-2., 0., 0.),
+        EPSILON
+    ));
+    assert!(close(
+        moving.pose(3.0).translation,
+        Vec3(2., 0., 0.),
+        EPSILON
+    ));
+}
+
+#[test]
+fn swept_bounds_hold_every_pose() {
+    let panel: Arc<dyn Hittable> = Arc::new(Sphere::new(
+        point(3., 0., 0.),
+        0.25,
+        Arc::new(Lambertian::from(color(0.5, 0.5, 0.5))),
+    ));
+    let spinning = AnimatedTransform::new(
+        panel.clone(),
+        pose(Vec3(0., 0., 0.), 0.),
+        pose(Vec3(0., 1., 0.), 170.),
+    );
+    let bounds = spinning.bound();
+    for i in 0..=100 {
+        let time = i as f64 / 100.0;
+        let placed = Transformed::new(panel.clone(), spinning.pose(time).matrix()).bound();
+        for axis in 0..3 {
+            let (outer, inner) = (bounds.intervals[axis], placed.intervals[axis]);
+            assert!(
+                outer.start <= inner.start + EPSILON && inner.end <= outer.end + EPSILON,
+                "axis {} at time {}: {:?} outside {:?}",
+                axis,
+                time,
+                inner,
+                outer
+            );
+        }
+    }
+}
+
+#[test]
+fn singular_matrices_hide_the_object() {
+    let flat = Transformed::new(ball(), Mat4::scale(Vec3(1., 0., 1.)));
+    let t = Interval::new(0.001, f64::INFINITY);
+    assert!(flat
+        .hit(&ray_at(0., 0., 0.), t, &mut IndependentSampler::new(0))
+        .is_none());
+    let mut issues = Vec::new();
+    flat.validate("objects[0]", &mut issues);
+    assert_eq!(issues.len(), 1);
+}
+
+fn camera() -> Camera {
+    Camera::new(
+        1.0,
+        8,
+        40.0,
+        point(0., 0., 5.),
+        point(0., 0., 0.),
+        Vec3(0., 1., 0.),
+        1,
+        2,
+    )
+}
+
+#[test]
+fn the_shutter_sets_the_times_rays_are_sent() {
+    let mut rng = IndependentSampler::new(3);
+    let still = camera();
+    assert!((0..32).all(|i| still.sample_ray(i % 8, i / 8, &mut rng).time == 0.0));
+
+    let mut open = camera();
+    open.set_shutter(0.25, 0.75);
+    let times: Vec<f64> = (0..64)
+        .map(|i| open.sample_ray(i % 8, i / 8, &mut rng).time)
+        .collect();
+    assert!(times.iter().all(|t| (0.25..0.75).contains(t)));
+    assert!(times.iter().any(|&t| t != times[0]));
+
+    for (open, close) in [(-0.1, 0.5), (0.6, 0.4), (0.0, 1.5), (f64::NAN, 1.0)] {
+        let mut bad = camera();
+        bad.set_shutter(open, close);
+        assert!(
+            matches!(bad.validate(), Err(CameraError::InvalidShutter { .. })),
+            "{} to {} was accepted",
+            open,
+            close
+        );
+    }
+}
+
+#[test]
+fn scene_files_animate_objects() {
+    let text = r#"
+        [camera]
+        aspect_ratio = 1.0
+        image_width = 8
+        vfov = 40.0
+        look_from = [0.0, 0.0, 5.0]
+        look_at = [0.0, 0.0, 0.0]
+        up = [0.0, 1.0, 0.0]
+        aa_samples = 1
+        max_depth = 2
+        shutter = [0.0, 1.0]
+
+        [materials]
+        gray = { type = "lambertian", albedo = [0.5, 0.5, 0.5] }
+
+        [[objects]]
+        type = "animate"
+        start = { translation = [-1.0, 0.0, 0.0] }
+        end = { translation = [1.0, 0.0, 0.0], axis = [0.0, 0.0, 1.0], angle = 90.0 }
+        object = { type = "sphere", center = [0.0, 0.0, 0.0], radius = 0.5, material = "gray" }
+    "#;
+    let desc: SceneDesc = toml::from_str(text).unwrap();
+    let scene = desc.build().unwrap();
+    assert_eq!(
+        (scene.camera.shutter_open, scene.camera.shutter_close),
+        (0.0, 1.0)
+    );
+
+    let saved = toml::to_string(&scene.describe().unwrap()).unwrap();
+    assert!(saved.contains("type = \"animate\""), "{}", saved);
+    let again: SceneDesc = toml::from_str(&saved).unwrap();
+    let rebuilt = again.build().unwrap();
+    assert_eq!(rebuilt.camera.shutter_close, 1.0);
+    let (a, b) = (scene.world.bound(), rebuilt.world.bound());
+    for axis in 0..3 {
+        assert!((a.intervals[axis].start - b.intervals[axis].start).abs() < 1e-9);
+        assert!((a.intervals[axis].end - b.intervals[axis].end).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,97 @@
+//! Normalizing a mesh centers it on the origin and scales its longest side
+//! to the target, keeping its proportions.
+
+use std::{path::Path, sync::Arc};
+
+use ray_tracer::{
+    color, load_model_normalized, point, AssetResolver, BoundingBox, Lambertian, MeshData,
+    TriangleMesh,
+};
+
+/// Bounds are padded by this much around the vertices.
+const TOLERANCE: f64 = 1e-3;
+
+fn gray() -> Arc<Lambertian> {
+    Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)))
+}
+
+/// A box-shaped mesh 200 by 50 by 10, far from the origin.
+fn far_slab() -> TriangleMesh {
+    let (lo, hi) = (point(100., 10., -5.), point(300., 60., 5.));
+    let positions = (0..8)
+        .map(|i| {
+            point(
+                if i & 1 == 0 { lo.x() } else { hi.x() },
+                if i & 2 == 0 { lo.y() } else { hi.y() },
+                if i & 4 == 0 { lo.z() } else { hi.z() },
+            )
+        })
+        .collect();
+    let faces = vec![
+        [0, 2, 3],
+        [0, 3, 1],
+        [4, 5, 7],
+        [4, 7, 6],
+        [0, 1, 5],
+        [0, 5, 4],
+        [2, 6, 7],
+        [2, 7, 3],
+        [0, 4, 6],
+        [0, 6, 2],
+        [1, 3, 7],
+        [1, 7, 5],
+    ];
+    let data = MeshData {
+        positions,
+        faces,
+        ..MeshData::default()
+    };
+    TriangleMesh::new(data, gray())
+}
+
+/// Asserts `bounds` runs from minus to plus half of `size` on each axis.
+fn assert_centered(bounds: BoundingBox, size: [f64; 3]) {
+    for (axis, (interval, size)) in bounds.intervals.iter().zip(size).enumerate() {
+        let half = size / 2.0;
+        assert!(
+            (interval.start + half).abs() < TOLERANCE && (interval.end - half).abs() < TOLERANCE,
+            "axis {}: {:?}, expected ±{}",
+            axis,
+            interval,
+            half
+        );
+    }
+}
+
+#[test]
+fn normalizing_centers_and_scales_to_the_target() {
+    let mut mesh = far_slab();
+    mesh.normalize(1.0);
+    assert_centered(mesh.bounds(), [1.0, 0.25, 0.05]);
+    assert!(mesh.centroid().length() < TOLERANCE);
+}
+
+#[test]
+fn the_returned_transform_is_the_one_applied() {
+    let mut mesh = far_slab();
+    let before = mesh.centroid();
+    let transform = mesh.normalize(4.0);
+    assert!((transform.scale.x() - 0.02).abs() < 1e-4);
+    assert!(transform.point(before).length() < TOLERANCE);
+    let corner = transform.point(point(300., 60., 5.));
+    assert!((corner - point(2.0, 0.5, 0.1)).length() < TOLERANCE);
+}
+
+#[test]
+fn normalized_loads_are_unit_sized_and_centered() {
+    let (mesh, _) = load_model_normalized(
+        Path::new("tests/fixtures/cube.obj"),
+        &AssetResolver::new(),
+        gray(),
+        1.0,
+        None,
+    )
+    .unwrap();
+    assert_eq!(mesh.triangle_count(), 12);
+    assert_centered(mesh.bounds(), [1.0, 1.0, 1.0]);
+}
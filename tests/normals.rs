@@ -0,0 +1,201 @@
+//! Smooth vertex normals for meshes loaded without them: curved surfaces
+//! shade smoothly while creases sharper than the threshold stay hard.
+
+use std::{f64::consts::PI, fs, path::PathBuf, sync::Arc};
+
+use ray_tracer::{
+    color, load_model, point, AssetResolver, Hittable, IndependentSampler, Interval, Lambertian,
+    MeshData, Ray, TriangleMesh, Vec3, DEFAULT_SMOOTHING_ANGLE,
+};
+
+const SEGMENTS: usize = 16;
+
+fn fixture(name: &str, contents: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ray-tracer-normals-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+fn load(path: &PathBuf) -> TriangleMesh {
+    let material = Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)));
+    let mesh = load_model(path, &AssetResolver::new(), material, None).unwrap();
+    fs::remove_file(path).unwrap();
+    mesh
+}
+
+/// A closed cylinder of radius 1 from y = -1 to 1 around the y axis: the
+/// bottom ring, the top ring, then the cap centers.
+fn cylinder_data() -> MeshData {
+    let mut data = MeshData::default();
+    for y in [-1.0, 1.0] {
+        for i in 0..SEGMENTS {
+            let theta = 2.0 * PI * i as f64 / SEGMENTS as f64;
+            data.positions.push(point(theta.cos(), y, theta.sin()));
+        }
+    }
+    data.positions.push(point(0., -1., 0.));
+    data.positions.push(point(0., 1., 0.));
+    let (bottom_center, top_center) = (2 * SEGMENTS, 2 * SEGMENTS + 1);
+    for i in 0..SEGMENTS {
+        let (b0, b1) = (i, (i + 1) % SEGMENTS);
+        let (t0, t1) = (b0 + SEGMENTS, b1 + SEGMENTS);
+        data.faces.push([b0, t1, b1]);
+        data.faces.push([b0, t0, t1]);
+        data.faces.push([bottom_center, b0, b1]);
+        data.faces.push([top_center, t1, t0]);
+    }
+    data
+}
+
+/// The cylinder as an OBJ file without normals.
+fn cylinder_obj() -> String {
+    let data = cylinder_data();
+    let mut obj = String::new();
+    for p in &data.positions {
+        obj += &format!("v {} {} {}\n", p.0, p.1, p.2);
+    }
+    for [a, b, c] in &data.faces {
+        obj += &format!("f {} {} {}\n", a + 1, b + 1, c + 1);
+    }
+    obj
+}
+
+fn cylinder(angle_threshold: f64) -> MeshData {
+    let mut data = cylinder_data();
+    data.compute_vertex_normals(angle_threshold);
+    data
+}
+
+fn face_normal(data: &MeshData, face: usize) -> Vec3 {
+    let (a, b, c) = data.vertices(face);
+    Vec3::cross(&(b - a), &(c - a)).unit()
+}
+
+fn is_cap(data: &MeshData, face: usize) -> bool {
+    face_normal(data, face).1.abs() > 0.99
+}
+
+fn radial(p: Vec3) -> Vec3 {
+    Vec3(p.0, 0.0, p.2).unit()
+}
+
+#[test]
+fn loaders_fill_in_missing_normals() {
+    let mesh = load(&fixture("cylinder.obj", &cylinder_obj()));
+    let data = mesh.data();
+    let normals = data.normals.as_ref().expect("normals were not computed");
+    assert_eq!(normals.len(), data.positions.len());
+    // Each rim vertex is split between the barrel and its cap.
+    assert_eq!(data.positions.len(), 4 * SEGMENTS + 2);
+    assert!(DEFAULT_SMOOTHING_ANGLE > 360.0 / SEGMENTS as f64 && DEFAULT_SMOOTHING_ANGLE < 90.0);
+}
+
+#[test]
+fn cylinder_is_smooth_around_the_barrel_and_sharp_at_the_rims() {
+    let data = cylinder(DEFAULT_SMOOTHING_ANGLE);
+    let normals = data.normals.as_ref().unwrap();
+    for (face, corners) in data.faces.iter().enumerate() {
+        for &vertex in corners {
+            let normal = normals[vertex];
+            if is_cap(&data, face) {
+                assert!((normal - face_normal(&data, face)).length() < 1e-9);
+            } else {
+                // Area weighting leans toward the quad with more triangles at
+                // the vertex, but stays well inside the two facets around it,
+                // whose normals are half a segment off the radial direction.
+                let quarter_segment = PI / (2 * SEGMENTS) as f64;
+                assert!(normal.1.abs() < 1e-9, "{:?} tilts off the barrel", normal);
+                assert!(
+                    Vec3::dot(&normal, &radial(data.positions[vertex])) > quarter_segment.cos(),
+                    "{:?} at {:?}",
+                    normal,
+                    data.positions[vertex]
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn a_low_threshold_keeps_every_facet_flat() {
+    let data = cylinder(10.0);
+    let normals = data.normals.as_ref().unwrap();
+    for (face, corners) in data.faces.iter().enumerate() {
+        for &vertex in corners {
+            assert!((normals[vertex] - face_normal(&data, face)).length() < 1e-9);
+        }
+    }
+}
+
+#[test]
+fn a_straight_threshold_smooths_everything_without_splitting() {
+    let data = cylinder(180.0);
+    assert_eq!(data.positions.len(), 2 * SEGMENTS + 2);
+    let normals = data.normals.as_ref().unwrap();
+    // Rim normals lean between the barrel and the cap.
+    let rim = normals[SEGMENTS];
+    assert!(rim.1 > 0.3 && Vec3::dot(&radial(data.positions[SEGMENTS]), &rim) > 0.3);
+}
+
+#[test]
+fn smooth_normals_follow_the_curve_between_vertices() {
+    let angle = 2.0 * PI / (4.0 * SEGMENTS as f64);
+    let toward_axis = Ray {
+        origin: point(3.0 * angle.cos(), 0.2, 3.0 * angle.sin()),
+        direction: Vec3(-angle.cos(), 0.0, -angle.sin()),
+        time: 0.0,
+    };
+    let surface = radial(toward_axis.direction * -1.0);
+    let shade = |threshold: f64| {
+        let mut mesh = load(&fixture("cylinder.obj", &cylinder_obj()));
+        mesh.compute_vertex_normals(threshold);
+        let record = mesh
+            .hit(
+                &toward_axis,
+                Interval::from_range(0.001..f64::INFINITY),
+                &mut IndependentSampler::new(1),
+            )
+            .expect("the ray missed the barrel");
+        Vec3::dot(&record.normal, &surface)
+    };
+    assert!(shade(DEFAULT_SMOOTHING_ANGLE) > 1f64.to_radians().cos());
+    assert!(shade(10.0) < 3f64.to_radians().cos());
+}
+
+#[test]
+fn stl_cubes_keep_their_corners_crisp() {
+    let mut stl = String::from("solid cube\n");
+    let corner = |i: usize| Vec3((i & 1) as f64, ((i >> 1) & 1) as f64, ((i >> 2) & 1) as f64);
+    // Two triangles per side, wound outward, in corner bit indices.
+    let sides = [
+        [0, 2, 3, 1],
+        [4, 5, 7, 6],
+        [0, 1, 5, 4],
+        [2, 6, 7, 3],
+        [0, 4, 6, 2],
+        [1, 3, 7, 5],
+    ];
+    for [a, b, c, d] in sides {
+        for [p, q, r] in [[a, b, c], [a, c, d]] {
+            stl += "facet normal 0 0 0\nouter loop\n";
+            for v in [p, q, r] {
+                let v = corner(v);
+                stl += &format!("vertex {} {} {}\n", v.0, v.1, v.2);
+            }
+            stl += "endloop\nendfacet\n";
+        }
+    }
+    stl += "endsolid cube\n";
+
+    let mesh = load(&fixture("cube.stl", &stl));
+    let data = mesh.data();
+    assert_eq!(data.positions.len(), 24);
+    let normals = data.normals.as_ref().unwrap();
+    for (face, corners) in data.faces.iter().enumerate() {
+        for &vertex in corners {
+            assert!((normals[vertex] - face_normal(data, face)).length() < 1e-9);
+        }
+    }
+}
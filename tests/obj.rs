@@ -0,0 +1,91 @@
+//! Loading OBJ files with `load_obj`: every object in the file goes into one
+//! mesh, placed by the optional transform, with the normals and uvs it gives.
+
+use std::{path::Path, sync::Arc};
+
+use ray_tracer::{
+    color, load_obj, point, BoundingBox, Hittable, IndependentSampler, Interval, Lambertian,
+    LoadError, Ray, Transform, Vec3,
+};
+
+const CUBE: &str = "tests/fixtures/cube.obj";
+
+fn gray() -> Arc<Lambertian> {
+    Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)))
+}
+
+/// Asserts `bounds` spans `lo` to `hi` on every axis.
+fn assert_bounds(bounds: BoundingBox, lo: Vec3, hi: Vec3) {
+    for axis in 0..3 {
+        let interval = bounds.intervals[axis];
+        assert!(
+            (interval.start - lo[axis]).abs() < 1e-3 && (interval.end - hi[axis]).abs() < 1e-3,
+            "axis {}: {:?} is not [{}, {}]",
+            axis,
+            interval,
+            lo[axis],
+            hi[axis]
+        );
+    }
+}
+
+#[test]
+fn the_cube_fixture_loads_as_twelve_triangles() {
+    let mesh = load_obj(Path::new(CUBE), gray(), None).unwrap();
+    assert_eq!(mesh.triangle_count(), 12);
+    assert_bounds(mesh.bounds(), Vec3(-1.0, -1.0, -1.0), Vec3(1.0, 1.0, 1.0));
+}
+
+#[test]
+fn the_transform_places_the_positions() {
+    let transform = Transform {
+        translation: Vec3(2.0, 0.0, -3.0),
+        scale: Vec3(0.5, 0.5, 0.5),
+        ..Transform::identity()
+    };
+    let mesh = load_obj(Path::new(CUBE), gray(), Some(transform)).unwrap();
+    assert_eq!(mesh.triangle_count(), 12);
+    assert_bounds(mesh.bounds(), Vec3(1.5, -0.5, -3.5), Vec3(2.5, 0.5, -2.5));
+}
+
+#[test]
+fn missing_files_are_reported() {
+    let path = Path::new("tests/fixtures/no_such_model.obj");
+    match load_obj(path, gray(), None) {
+        Err(LoadError::NotFound(missing)) => assert_eq!(missing, path),
+        Err(e) => panic!("expected NotFound, got {}", e),
+        Ok(_) => panic!("loaded a file that doesn't exist"),
+    }
+}
+
+#[test]
+fn normals_and_uvs_are_interpolated_from_the_file() {
+    // A unit square whose normals lean toward -x on its left edge and +x on
+    // its right, and whose uvs run across half the texture.
+    let mesh = load_obj(Path::new("tests/fixtures/shaded_quad.obj"), gray(), None).unwrap();
+    let ray = Ray {
+        origin: point(0.25, 0.75, 5.0),
+        direction: Vec3(0.0, 0.0, -1.0),
+        time: 0.0,
+    };
+    let record = mesh
+        .hit(
+            &ray,
+            Interval::from_range(0.001..f64::INFINITY),
+            &mut IndependentSampler::new(0),
+        )
+        .expect("the ray missed the quad");
+
+    let normal = Vec3(-0.5, 0.0, 1.0).unit();
+    assert!(
+        (record.normal - normal).length() < 1e-9,
+        "{:?}",
+        record.normal
+    );
+    assert!(
+        (record.u - 0.125).abs() < 1e-6 && (record.v - 0.75).abs() < 1e-6,
+        "({}, {})",
+        record.u,
+        record.v
+    );
+}
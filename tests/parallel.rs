@@ -0,0 +1,95 @@
+//! Rendering on several threads: the same image, sums, and ray counts as on
+//! one, whichever thread renders which row.
+
+use ray_tracer::{
+    rays_traced,
+    scenes::{self, Scene},
+    DownscaleFilter,
+};
+
+fn tiny(threads: usize) -> Scene {
+    let mut scene = scenes::cornell_box();
+    scene
+        .camera
+        .set_image_width(23)
+        .set_aa_samples(3)
+        .set_max_depth(4)
+        .set_seed(Some(5))
+        .set_threads(threads);
+    scene
+}
+
+fn same_pixels(a: &ray_tracer::ImageBuffer, b: &ray_tracer::ImageBuffer) -> bool {
+    a.pixels
+        .iter()
+        .zip(&b.pixels)
+        .all(|(a, b)| (a.0, a.1, a.2) == (b.0, b.1, b.2))
+}
+
+#[test]
+fn threads_render_the_same_image() {
+    let one = tiny(1).render_image();
+    for threads in [2, 4, 64] {
+        assert!(
+            same_pixels(&tiny(threads).render_image(), &one),
+            "{}",
+            threads
+        );
+    }
+
+    let mut large = tiny(1);
+    large.camera.set_supersample(2, DownscaleFilter::Lanczos3);
+    let mut parallel = tiny(4);
+    parallel
+        .camera
+        .set_supersample(2, DownscaleFilter::Lanczos3);
+    assert!(same_pixels(&parallel.render_image(), &large.render_image()));
+}
+
+#[test]
+fn zero_threads_is_one_per_core() {
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    assert_eq!(tiny(0).camera.thread_count(), cores);
+    assert_eq!(tiny(3).camera.thread_count(), 3);
+    assert!(same_pixels(
+        &tiny(0).render_image(),
+        &tiny(1).render_image()
+    ));
+}
+
+#[test]
+fn threads_accumulate_the_same_sums() {
+    let (one, four) = (tiny(1), tiny(4));
+    let mut a = one.render_accumulation().unwrap();
+    let mut b = four.render_accumulation().unwrap();
+    // Continuing an accumulation adds onto what it already holds.
+    one.accumulate(&mut a).unwrap();
+    four.accumulate(&mut b).unwrap();
+    assert_eq!(a.samples, b.samples);
+    assert!(a
+        .sums
+        .iter()
+        .zip(&b.sums)
+        .all(|(a, b)| (a.0, a.1, a.2) == (b.0, b.1, b.2)));
+    assert!(a
+        .squares
+        .iter()
+        .zip(&b.squares)
+        .all(|(a, b)| (a.0, a.1, a.2) == (b.0, b.1, b.2)));
+}
+
+#[test]
+fn rays_traced_on_other_threads_are_counted() {
+    let count = |threads| {
+        let before = rays_traced();
+        tiny(threads).render_image();
+        rays_traced() - before
+    };
+    let one = count(1);
+    assert!(one > 0);
+    assert_eq!(count(4), one);
+
+    let (_, stats) = tiny(3).render_timed();
+    assert_eq!(stats.threads, 3);
+    assert_eq!(stats.rays, one);
+}
@@ -0,0 +1,146 @@
+//! The placement helpers lay objects out the same way every time, and refuse
+//! regions they can't scatter through.
+
+use std::sync::Arc;
+
+use rand::Rng;
+use ray_tracer::{
+    color, point,
+    scenes::{grid_place, orbit_ring, scatter_on_box, PlacementError},
+    BoundingBox, Hittable, HittableList, Interval, Lambertian, Point, Sphere,
+};
+
+/// Each object's bounds, as exact bits.
+fn layout(list: &HittableList) -> Vec<[u64; 6]> {
+    list.objects
+        .iter()
+        .map(|object| {
+            let [x, y, z] = object.bound().intervals;
+            [x.start, x.end, y.start, y.end, z.start, z.end].map(f64::to_bits)
+        })
+        .collect()
+}
+
+fn center(object: &dyn Hittable) -> Point {
+    let [x, y, z] = object.bound().intervals;
+    point(
+        (x.start + x.end) / 2.,
+        (y.start + y.end) / 2.,
+        (z.start + z.end) / 2.,
+    )
+}
+
+/// Scatters spheres of random radii through a 20 unit cube.
+fn asteroids(seed: u64) -> (HittableList, Vec<Point>) {
+    let material = Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)));
+    let region = BoundingBox::from_points(point(0., 0., 0.), point(20., 20., 20.));
+    let mut positions = Vec::new();
+    let list = scatter_on_box(region, 60, seed, 2.0, |position, rng| {
+        positions.push(position);
+        Arc::new(Sphere::new(
+            position,
+            rng.gen_range(0.2..0.9),
+            material.clone(),
+        ))
+    })
+    .unwrap();
+    (list, positions)
+}
+
+fn scatter(region: BoundingBox) -> Result<usize, PlacementError> {
+    let material = Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)));
+    scatter_on_box(region, 10, 1, 0.5, |position, _| {
+        Arc::new(Sphere::new(position, 0.1, material.clone()))
+    })
+    .map(|list| list.objects.len())
+}
+
+#[test]
+fn bad_regions_are_errors() {
+    let unit = Interval::new(0.0, 1.0);
+    let regions = [
+        BoundingBox::empty(),
+        BoundingBox {
+            intervals: [unit, Interval::new(2.0, -2.0), unit],
+        },
+        BoundingBox {
+            intervals: [unit, unit, Interval::new(0.0, f64::INFINITY)],
+        },
+        BoundingBox {
+            intervals: [Interval::new(f64::NAN, 1.0), unit, unit],
+        },
+    ];
+    for region in regions {
+        match scatter(region) {
+            Err(PlacementError::InvalidRegion(_)) => {}
+            Ok(count) => panic!("scattered {} objects through {:?}", count, region),
+        }
+    }
+}
+
+#[test]
+fn flat_regions_are_fine() {
+    let region = BoundingBox {
+        intervals: [
+            Interval::new(0.0, 10.0),
+            Interval::new(0.0, 0.0),
+            Interval::new(0.0, 10.0),
+        ],
+    };
+    assert_eq!(scatter(region).unwrap(), 10);
+}
+
+#[test]
+fn scattering_depends_only_on_the_seed() {
+    let (first, positions) = asteroids(11);
+    let (second, _) = asteroids(11);
+    assert_eq!(first.objects.len(), 60);
+    assert_eq!(layout(&first), layout(&second));
+    assert_ne!(layout(&first), layout(&asteroids(12).0));
+
+    for (i, a) in positions.iter().enumerate() {
+        for b in &positions[i + 1..] {
+            assert!((*a - *b).length() >= 2.0, "{:?} and {:?}", a, b);
+        }
+    }
+}
+
+#[test]
+fn grids_are_placed_the_same_every_time() {
+    let material = Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)));
+    let grid = || {
+        grid_place(point(-5., 0., -5.), 4, 3, 2.5, |i, j, corner| {
+            let radius = 0.1 * (1 + i + j) as f64;
+            Arc::new(Sphere::new(corner, radius, material.clone()))
+        })
+    };
+    let (first, second) = (grid(), grid());
+    assert_eq!(first.objects.len(), 12);
+    assert_eq!(layout(&first), layout(&second));
+    // Rows run along x, then along z.
+    let centers: Vec<_> = first
+        .objects
+        .iter()
+        .map(|object| center(object.as_ref()))
+        .collect();
+    assert!((centers[0] - point(-5., 0., -5.)).length() < 1e-9);
+    assert!((centers[1] - point(-5., 0., -2.5)).length() < 1e-9);
+    assert!((centers[3] - point(-2.5, 0., -5.)).length() < 1e-9);
+}
+
+#[test]
+fn rings_are_placed_the_same_every_time() {
+    let material = Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)));
+    let ring = || {
+        orbit_ring(point(1., 2., 3.), 10., 36, |_, position| {
+            Arc::new(Sphere::new(position, 0.5, material.clone()))
+        })
+    };
+    let (first, second) = (ring(), ring());
+    assert_eq!(first.objects.len(), 36);
+    assert_eq!(layout(&first), layout(&second));
+    for object in &first.objects {
+        let offset = center(object.as_ref()) - point(1., 2., 3.);
+        assert!((offset.length() - 10.).abs() < 1e-9 && offset.y().abs() < 1e-9);
+    }
+}
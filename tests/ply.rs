@@ -0,0 +1,149 @@
+//! Loading PLY files with `load_ply`, in ASCII and binary, and the errors
+//! for files that are malformed or cut short.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use ray_tracer::{color, load_ply, Lambertian, LoadError, TriangleMesh};
+
+fn fixture(name: &str, contents: &[u8]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ray-tracer-ply-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+fn load(path: &Path) -> Result<TriangleMesh, LoadError> {
+    load_ply(path, Arc::new(Lambertian::from(color(0.5, 0.5, 0.5))), None)
+}
+
+/// The message of the `Decode` error loading `path` fails with.
+fn decode_error(path: &Path) -> String {
+    match load(path) {
+        Err(LoadError::Decode { path: at, message }) => {
+            assert_eq!(at, path);
+            message
+        }
+        Err(e) => panic!("expected a decode error, got {}", e),
+        Ok(_) => panic!("{} loaded", path.display()),
+    }
+}
+
+/// The header of a single triangle with colored vertices, in `format`.
+fn triangle_header(format: &str) -> String {
+    format!(
+        "ply\nformat {} 1.0\nelement vertex 3\nproperty float x\nproperty float y\n\
+         property float z\nproperty uchar red\nproperty uchar green\nproperty uchar blue\n\
+         element face 1\nproperty list uchar int vertex_indices\nend_header\n",
+        format
+    )
+}
+
+const TRIANGLE_BODY: &str = "0 0 0 255 0 0\n1 0 0 0 255 0\n0 1 0 0 0 255\n3 0 1 2\n";
+
+/// The triangle's body in binary little-endian.
+fn binary_triangle_body() -> Vec<u8> {
+    let mut body = Vec::new();
+    for (position, rgb) in [
+        ([0.0f32, 0.0, 0.0], [255u8, 0, 0]),
+        ([1.0, 0.0, 0.0], [0, 255, 0]),
+        ([0.0, 1.0, 0.0], [0, 0, 255]),
+    ] {
+        for coordinate in position {
+            body.extend(coordinate.to_le_bytes());
+        }
+        body.extend(rgb);
+    }
+    body.push(3);
+    for index in [0i32, 1, 2] {
+        body.extend(index.to_le_bytes());
+    }
+    body
+}
+
+#[test]
+fn the_colored_cube_loads_with_its_colors() {
+    let mesh = load(Path::new("resources/models/gradient_cube.ply")).unwrap();
+    assert_eq!(mesh.triangle_count(), 12);
+    let colors = mesh
+        .data()
+        .colors
+        .as_ref()
+        .expect("the colors were dropped");
+    assert_eq!(colors.len(), mesh.data().positions.len());
+    for axis in mesh.bounds().intervals {
+        assert!((axis.start + 0.5).abs() < 1e-3 && (axis.end - 0.5).abs() < 1e-3);
+    }
+}
+
+#[test]
+fn ascii_and_binary_files_load_alike() {
+    let ascii = fixture(
+        "ascii.ply",
+        (triangle_header("ascii") + TRIANGLE_BODY).as_bytes(),
+    );
+    let mut binary = triangle_header("binary_little_endian").into_bytes();
+    binary.extend(binary_triangle_body());
+    let binary = fixture("binary.ply", &binary);
+
+    let (ascii, binary) = (load(&ascii).unwrap(), load(&binary).unwrap());
+    for mesh in [&ascii, &binary] {
+        assert_eq!(mesh.triangle_count(), 1);
+        assert_eq!(mesh.data().faces, vec![[0, 1, 2]]);
+    }
+    let (ascii, binary) = (ascii.data(), binary.data());
+    let pairs = ascii.positions.iter().zip(&binary.positions).chain(
+        ascii
+            .colors
+            .as_ref()
+            .unwrap()
+            .iter()
+            .zip(binary.colors.as_ref().unwrap()),
+    );
+    for (a, b) in pairs {
+        assert!((*a - *b).length() < 1e-9, "{:?} vs {:?}", a, b);
+    }
+}
+
+#[test]
+fn malformed_headers_are_decode_errors() {
+    let cases = [
+        ("no_magic.ply", "format ascii 1.0\nend_header\n", "magic"),
+        (
+            "unknown_format.ply",
+            "ply\nformat binary_middle_endian 1.0\nend_header\n",
+            "unknown format",
+        ),
+        (
+            "unknown_type.ply",
+            "ply\nformat ascii 1.0\nelement vertex 1\nproperty quad x\nend_header\n",
+            "unknown type",
+        ),
+        (
+            "no_end.ply",
+            "ply\nformat ascii 1.0\nelement vertex 1\n",
+            "end_header",
+        ),
+    ];
+    for (name, text, expected) in cases {
+        let message = decode_error(&fixture(name, text.as_bytes()));
+        assert!(message.contains(expected), "{}: {}", name, message);
+    }
+}
+
+#[test]
+fn truncated_files_are_decode_errors() {
+    let ascii = triangle_header("ascii") + &TRIANGLE_BODY[..20];
+    let message = decode_error(&fixture("truncated_ascii.ply", ascii.as_bytes()));
+    assert!(message.contains("unexpected end of file"), "{}", message);
+
+    let mut binary = triangle_header("binary_little_endian").into_bytes();
+    let body = binary_triangle_body();
+    binary.extend(&body[..body.len() - 3]);
+    let message = decode_error(&fixture("truncated_binary.ply", &binary));
+    assert!(message.contains("unexpected end of file"), "{}", message);
+}
@@ -0,0 +1,168 @@
+//! Light portals: directions through openings drawn with the right density,
+//! and interiors lit through them converging far faster, to the same image.
+
+use ray_tracer::{
+    color, point, rays_traced,
+    scenes::{self, Scene},
+    Color, Environment, EnvironmentSampling, ImageBuffer, IndependentSampler, LightList, Pdf,
+    Portal, PortalPdf, Vec3,
+};
+
+/// A window 2 wide and 1 high in the plane z = 0, with its inside toward +z.
+fn window() -> Portal {
+    Portal::new(point(-1., -0.5, 0.), (Vec3(2., 0., 0.), Vec3(0., 1., 0.)))
+}
+
+#[test]
+fn portals_face_their_inside() {
+    let portal = window();
+    assert!((portal.normal().z() - 1.0).abs() < 1e-12);
+    assert!((portal.area() - 2.0).abs() < 1e-12);
+    assert!(portal.faces(point(0., 0., 1.)));
+    assert!(portal.faces(point(30., -7., 0.1)));
+    assert!(!portal.faces(point(0., 0., -1.)));
+    assert!(!portal.faces(point(5., 0., 0.)));
+    assert!(PortalPdf::new(&[window()], point(0., 0., -1.)).is_none());
+
+    // Points outside every portal still sample the whole environment.
+    let mut lights = LightList::new();
+    assert!(lights.environment_pdf(point(0., 0., 1.)).is_none());
+    lights.environment = Some(std::sync::Arc::new(Environment::new(sky(1.0))));
+    lights.portals.push(window());
+    assert!(matches!(
+        lights.environment_pdf(point(0., 0., 1.)),
+        Some(EnvironmentSampling::Portals(_))
+    ));
+    assert!(matches!(
+        lights.environment_pdf(point(0., 0., -1.)),
+        Some(EnvironmentSampling::Image(_))
+    ));
+}
+
+#[test]
+fn directions_through_a_portal_have_its_solid_angle() {
+    // The solid angle of a 2 by 1 rectangle seen square on from 1 away.
+    let (a, b, d) = (2.0f64, 1.0f64, 1.0f64);
+    let solid_angle = 4.0 * (a * b / ((a * a + 4. * d * d) * (b * b + 4. * d * d)).sqrt()).asin();
+
+    let portals = [window()];
+    let origin = point(0., 0., 1.);
+    let pdf = PortalPdf::new(&portals, origin).unwrap();
+    let mut rng = IndependentSampler::new(7);
+    let n = 20000;
+    let mut total = 0.0;
+    for _ in 0..n {
+        let direction = pdf.generate(&mut rng);
+        assert!((direction.length() - 1.0).abs() < 1e-9);
+        // Every direction drawn leaves through the window.
+        let t = -origin.z() / direction.z();
+        let crossing = origin + direction * t;
+        assert!(crossing.x().abs() <= 1.0 + 1e-9 && crossing.y().abs() <= 0.5 + 1e-9);
+        total += 1.0 / pdf.value(direction);
+    }
+    let estimate = total / n as f64;
+    assert!(
+        (estimate / solid_angle - 1.0).abs() < 0.02,
+        "{} {}",
+        estimate,
+        solid_angle
+    );
+
+    // Away from the window, and back into the room, there is nothing.
+    assert_eq!(pdf.value(Vec3(1., 0., -0.1).unit()), 0.0);
+    assert_eq!(pdf.value(Vec3(0., 0., 1.)), 0.0);
+}
+
+#[test]
+fn overlapping_portals_share_the_samples() {
+    // Two windows one behind the other, both seen from the origin.
+    let near = window();
+    let far = Portal::new(point(-4., -2., -2.), (Vec3(8., 0., 0.), Vec3(0., 4., 0.)));
+    let portals = [near, far];
+    let origin = point(0., 0., 1.);
+    let pdf = PortalPdf::new(&portals, origin).unwrap();
+    let alone = PortalPdf::new(&portals[..1], origin).unwrap();
+    let straight = Vec3(0., 0., -1.);
+    // Straight out, the mixture is the average of the two densities.
+    let far_alone = PortalPdf::new(&portals[1..], origin).unwrap();
+    let expected = 0.5 * (alone.value(straight) + far_alone.value(straight));
+    assert!((pdf.value(straight) - expected).abs() < 1e-12);
+    // Through the far window only, half as likely as with it alone.
+    let wide = Vec3(3.5, 0., -3.).unit();
+    assert_eq!(alone.value(wide), 0.0);
+    assert!((pdf.value(wide) - 0.5 * far_alone.value(wide)).abs() < 1e-12);
+}
+
+/// A uniformly bright sky.
+fn sky(brightness: f64) -> ImageBuffer {
+    let mut image = ImageBuffer::new(8, 4);
+    for pixel in image.pixels.iter_mut() {
+        *pixel = color(0.6, 0.8, 1.0) * brightness;
+    }
+    image
+}
+
+/// The window room lit only by a bright, even sky, seen from beside the
+/// window, and tiny.
+fn room(portal: bool, max_depth: i32) -> Scene {
+    let mut scene = scenes::window_room();
+    let up = scene.camera.up;
+    scene
+        .camera
+        .move_camera(point(0.3, 1.6, 3.7), point(4., 0.8, 1.), up)
+        .unwrap();
+    scene.lights.delta.clear();
+    scene.set_environment(Environment::new(sky(4.0)));
+    if !portal {
+        scene.lights.portals.clear();
+    }
+    scene
+        .camera
+        .set_image_width(12)
+        .set_aa_samples(64)
+        .set_max_depth(max_depth)
+        .set_seed(Some(4));
+    scene
+}
+
+fn mean(image: &ImageBuffer) -> Color {
+    image
+        .pixels
+        .iter()
+        .fold(Vec3(0., 0., 0.), |sum, &p| sum + p)
+        / image.pixels.len() as f64
+}
+
+#[test]
+fn portals_light_rooms_with_far_less_noise_for_the_work() {
+    // Noise for equal work: mean variance times rays traced.
+    let noise = |scene: &Scene| {
+        let before = rays_traced();
+        let accumulation = scene.render_accumulation().unwrap();
+        let rays = rays_traced() - before;
+        mean(&accumulation.variance()).luminance() * rays as f64
+    };
+    // The light straight through the window converges an order of magnitude
+    // faster; light bounced around the room, which portals can't help,
+    // dilutes that.
+    let direct = noise(&room(false, 1)) / noise(&room(true, 1));
+    assert!(direct > 10.0, "{}", direct);
+    let bounced = noise(&room(false, 3)) / noise(&room(true, 3));
+    assert!(bounced > 3.0, "{}", bounced);
+}
+
+#[test]
+fn portals_change_the_noise_but_not_the_image() {
+    for max_depth in [1, 2] {
+        let mut means = Vec::new();
+        for portal in [true, false] {
+            let mut scene = room(portal, max_depth);
+            scene.camera.set_image_width(6).set_aa_samples(2048);
+            means.push(mean(&scene.render_image()).luminance());
+        }
+        let ratio = means[0] / means[1];
+        assert!((ratio - 1.0).abs() < 0.03, "{} {:?}", max_depth, means);
+    }
+    // So renders with and without them can be merged.
+    assert_eq!(room(true, 3).fingerprint(), room(false, 3).fingerprint());
+}
@@ -0,0 +1,190 @@
+//! Enum-dispatched BVH leaves: built-in shapes stored in place, hit just as
+//! they are behind an `Arc`, in a tree that still lists its objects in the
+//! order they were given.
+
+use std::{mem, sync::Arc};
+
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use ray_tracer::{
+    color, parallelepiped, point, scenes::Describer, BoundNode, ConstantMedium, Hittable,
+    HittableList, IndependentSampler, Interval, Lambertian, Material, MeshData, MeshTriangle,
+    Parallelogram, Primitive, Ray, Sphere, TraversalStats, Triangle, TriangleMesh, Vec3,
+};
+
+fn gray() -> Arc<dyn Material> {
+    Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)))
+}
+
+fn vec3(rng: &mut SmallRng, extent: f64) -> Vec3 {
+    Vec3(
+        rng.gen_range(-extent..extent),
+        rng.gen_range(-extent..extent),
+        rng.gen_range(-extent..extent),
+    )
+}
+
+/// Random shapes of every kind, built-in or not, scattered through a box.
+fn shapes(rng: &mut SmallRng, count: usize) -> Vec<Arc<dyn Hittable>> {
+    (0..count)
+        .map(|i| {
+            let at = vec3(rng, 10.);
+            let size = rng.gen_range(0.2..2.);
+            match i % 4 {
+                0 => Arc::new(Sphere::new(at, size, gray())) as Arc<dyn Hittable>,
+                1 => Arc::new(Parallelogram::new(
+                    at,
+                    (vec3(rng, size), vec3(rng, size)),
+                    gray(),
+                )),
+                2 => Arc::new(Triangle::new(
+                    (at, at + vec3(rng, size), at + vec3(rng, size)),
+                    gray(),
+                )),
+                _ => parallelepiped(at, at + vec3(rng, size), gray()),
+            }
+        })
+        .collect()
+}
+
+fn ray(rng: &mut SmallRng) -> Ray {
+    let origin = vec3(rng, 15.);
+    Ray {
+        origin,
+        direction: vec3(rng, 10.) - origin,
+        time: 0.0,
+    }
+}
+
+#[test]
+fn built_in_shapes_are_stored_in_place() {
+    let sphere: Arc<dyn Hittable> = Arc::new(Sphere::new(point(0., 0., 0.), 1., gray()));
+    assert!(matches!(Primitive::from_arc(sphere), Primitive::Sphere(_)));
+    let quad: Arc<dyn Hittable> = Arc::new(Parallelogram::new(
+        point(0., 0., 0.),
+        (Vec3(1., 0., 0.), Vec3(0., 1., 0.)),
+        gray(),
+    ));
+    assert!(matches!(
+        Primitive::from_arc(quad),
+        Primitive::Parallelogram(_)
+    ));
+
+    // Anything else stays behind its Arc, and comes back out the same one.
+    let boxes = parallelepiped(point(0., 0., 0.), point(1., 1., 1.), gray());
+    let fog: Arc<dyn Hittable> = Arc::new(ConstantMedium::from_color(
+        boxes.clone(),
+        0.5,
+        color(1., 1., 1.),
+    ));
+    for object in [boxes as Arc<dyn Hittable>, fog] {
+        let primitive = Primitive::from_arc(object.clone());
+        assert!(matches!(primitive, Primitive::Other(_)));
+        assert!(Arc::ptr_eq(&primitive.to_arc(), &object));
+    }
+}
+
+#[test]
+fn primitives_hit_as_their_shapes_do() {
+    let mut rng = SmallRng::seed_from_u64(3);
+    let objects = shapes(&mut rng, 40);
+    let mut sampler = IndependentSampler::new(0);
+    let t = Interval::from_range(0.001..f64::INFINITY);
+    let mut hits = 0;
+    for object in &objects {
+        let primitive = Primitive::from_arc(object.clone());
+        assert_eq!(primitive.label(), object.label());
+        let [x, y, z] = object.bound().intervals;
+        let middle = |i: Interval| 0.5 * (i.start + i.end);
+        let center = point(middle(x), middle(y), middle(z));
+        for _ in 0..200 {
+            // Aimed near the shape, so most rays hit it.
+            let mut ray = ray(&mut rng);
+            ray.direction = center + vec3(&mut rng, 0.5) - ray.origin;
+            let expected = object.hit(&ray, t, &mut sampler);
+            let found = primitive.hit(&ray, t, &mut sampler);
+            assert_eq!(found.is_some(), expected.is_some());
+            if let (Some(a), Some(b)) = (found, expected) {
+                assert_eq!((a.t, a.front_face), (b.t, b.front_face));
+                assert!((a.normal - b.normal).length() < 1e-12);
+                hits += 1;
+            }
+        }
+    }
+    assert!(hits > 2000, "{}", hits);
+}
+
+#[test]
+fn inline_and_dynamic_leaves_trace_the_same() {
+    let mut rng = SmallRng::seed_from_u64(8);
+    let objects = shapes(&mut rng, 150);
+    let inline = BoundNode::from_objects(&objects, 0..objects.len());
+    let dynamic = BoundNode::from_primitives(
+        objects
+            .iter()
+            .map(|object| Primitive::Other(object.clone()))
+            .collect(),
+    );
+    let mut sampler = IndependentSampler::new(0);
+    let t = Interval::from_range(0.001..f64::INFINITY);
+    for _ in 0..2000 {
+        let ray = ray(&mut rng);
+        let (mut a, mut b) = (TraversalStats::default(), TraversalStats::default());
+        let found = inline.hit_with_stats(&ray, t, &mut sampler, &mut a);
+        let expected = dynamic.hit_with_stats(&ray, t, &mut sampler, &mut b);
+        assert_eq!(found.map(|r| r.t), expected.map(|r| r.t));
+        assert_eq!(
+            (a.node_visits, a.primitive_tests),
+            (b.node_visits, b.primitive_tests)
+        );
+        assert_eq!(
+            inline.hit_any(&ray, t, &mut sampler),
+            dynamic.hit_any(&ray, t, &mut sampler)
+        );
+    }
+}
+
+#[test]
+fn bvhs_list_their_objects_in_the_order_given() {
+    let mut rng = SmallRng::seed_from_u64(5);
+    let objects = shapes(&mut rng, 30);
+    let bvh = BoundNode::from_objects(&objects, 0..objects.len());
+    let labels: Vec<String> = bvh.children().iter().map(|child| child.label()).collect();
+    let given: Vec<String> = objects.iter().map(|object| object.label()).collect();
+    assert_eq!(labels, given);
+
+    // As the objects described one by one, and again once regrouped.
+    let describe = |object: &dyn Hittable| {
+        serde_json::to_string(&Describer::new().object(object).unwrap()).unwrap()
+    };
+    let mut list = HittableList::new();
+    for object in &objects {
+        list.add_arc(object.clone());
+    }
+    let leaves = bvh.bvh_leaves().unwrap();
+    let rebuilt = BoundNode::from_objects(&leaves, 0..leaves.len());
+    assert_eq!(describe(&bvh), describe(&rebuilt));
+    assert_eq!(
+        describe(&bvh).replacen("true", "false", 1),
+        describe(&list),
+        "a BVH describes as the group it was built from"
+    );
+}
+
+#[test]
+fn leaves_are_no_bigger_than_a_sphere() {
+    assert!(mem::size_of::<Primitive>() <= mem::size_of::<Sphere>() + 8);
+    assert!(mem::size_of::<Primitive>() <= mem::size_of::<MeshTriangle>() + 16);
+
+    // A mesh triangle costs less than it did behind an Arc and a fat
+    // pointer, before counting any nodes.
+    let mut data = MeshData::quad(point(0., 0., 0.), (Vec3(1., 0., 0.), Vec3(0., 0., 1.)));
+    for _ in 0..5 {
+        data.subdivide();
+    }
+    let mesh = TriangleMesh::new(data, gray());
+    let boxed = mem::size_of::<MeshTriangle>()
+        + 2 * mem::size_of::<usize>()
+        + mem::size_of::<Arc<dyn Hittable>>();
+    let per_triangle = mesh.estimated_bytes() as f64 / mesh.triangle_count() as f64;
+    assert!(per_triangle < (boxed * 2) as f64, "{}", per_triangle);
+}
@@ -0,0 +1,145 @@
+//! Progressive rendering: coarse previews first, then full resolution with
+//! the samples accumulating, ending on exactly the image `render_image` makes.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use ray_tracer::{
+    rays_traced,
+    scenes::{self, Scene},
+    SamplerKind, StereoMode, StereoSettings,
+};
+
+fn tiny(mut scene: Scene) -> Scene {
+    // Not a multiple of 8, so the last blocks are partial.
+    scene
+        .camera
+        .set_image_width(21)
+        .set_aa_samples(5)
+        .set_max_depth(4)
+        .set_seed(Some(11));
+    scene
+}
+
+#[test]
+fn the_finished_image_is_the_plain_render() {
+    for (sampler, blue_noise) in [
+        (SamplerKind::Independent, false),
+        (SamplerKind::Halton, true),
+    ] {
+        let mut scene = tiny(scenes::cornell_box());
+        scene.camera.set_sampler(sampler).set_blue_noise(blue_noise);
+        let progressive = scene.render_progressive(&mut |_| {});
+        let plain = scene.render_image();
+        assert!(progressive
+            .pixels
+            .iter()
+            .zip(&plain.pixels)
+            .all(|(a, b)| (a.0, a.1, a.2) == (b.0, b.1, b.2)));
+    }
+}
+
+#[test]
+fn passes_go_coarse_to_fine_then_double_the_samples() {
+    let scene = tiny(scenes::cornell_box());
+    let mut passes = Vec::new();
+    scene.render_progressive(&mut |preview| {
+        assert_eq!((preview.image.width, preview.image.height), (21, 21));
+        passes.push((preview.block, preview.samples, preview.done));
+    });
+    assert_eq!(
+        passes,
+        [
+            (8, 1, false),
+            (4, 1, false),
+            (2, 1, false),
+            (1, 2, false),
+            (1, 4, false),
+            (1, 5, true)
+        ]
+    );
+}
+
+#[test]
+fn coarse_passes_fill_each_block_with_one_pixel() {
+    let scene = tiny(scenes::cornell_box());
+    let mut first = None;
+    scene.render_progressive(&mut |preview| {
+        if first.is_none() {
+            first = Some(preview.image.clone());
+        }
+    });
+    let first = first.unwrap();
+    for y in 0..21 {
+        for x in 0..21 {
+            let (a, b) = (first.get(x, y), first.get(x - x % 8, y - y % 8));
+            assert_eq!((a.0, a.1, a.2), (b.0, b.1, b.2), "({}, {})", x, y);
+        }
+    }
+    // One sample of the corner pixel.
+    let mut one = tiny(scenes::cornell_box());
+    one.camera.set_aa_samples(1);
+    let corner = one.render_image().get(8, 16);
+    assert_eq!(
+        (corner.0, corner.1, corner.2),
+        (first.get(9, 20).0, first.get(9, 20).1, first.get(9, 20).2)
+    );
+}
+
+#[test]
+fn the_first_preview_costs_a_small_part_of_the_render() {
+    let mut scene = scenes::cornell_box();
+    scene
+        .camera
+        .set_image_width(64)
+        .set_aa_samples(16)
+        .set_seed(Some(2));
+    let start = rays_traced();
+    let mut first = None;
+    scene.render_progressive(&mut |_| {
+        first.get_or_insert(rays_traced() - start);
+    });
+    let (first, total) = (first.unwrap(), rays_traced() - start);
+    // 64 of 4096 pixels, one of 16 samples each.
+    assert!(first * 500 < total, "{} of {} rays", first, total);
+}
+
+#[test]
+fn stereo_renders_skip_to_the_finished_image() {
+    let mut scene = tiny(scenes::cornell_box());
+    scene.camera.set_stereo(Some(StereoSettings {
+        ipd: 0.1,
+        mode: StereoMode::SideBySide,
+        converge: false,
+    }));
+    let mut passes = Vec::new();
+    let image = scene.render_progressive(&mut |preview| {
+        passes.push((preview.block, preview.samples, preview.done))
+    });
+    assert_eq!(passes, [(1, 5, true)]);
+    assert_eq!(image.to_rgba8(), scene.render_image().to_rgba8());
+}
+
+#[test]
+fn a_render_stops_when_told() {
+    let scene = tiny(scenes::cornell_box());
+    let stop = AtomicBool::new(false);
+    let mut passes = 0;
+    let image =
+        scene
+            .camera
+            .render_progressive_until(&scene.world, &scene.lights, &stop, &mut |_| {
+                passes += 1;
+                if passes == 2 {
+                    stop.store(true, Ordering::Relaxed);
+                }
+            });
+    assert!(image.is_none());
+    assert_eq!(passes, 2);
+
+    let stop = AtomicBool::new(false);
+    let image = scene
+        .camera
+        .render_progressive_until(&scene.world, &scene.lights, &stop, &mut |_| {})
+        .expect("never stopped");
+    assert_eq!(image.to_rgba8(), scene.render_image().to_rgba8());
+}
@@ -0,0 +1,190 @@
+//! Property-based tests for the invariants of the core math types.
+
+use std::sync::Arc;
+
+use proptest::prelude::*;
+use ray_tracer::{
+    color, BoundingBox, Bounds, Hittable, IndependentSampler, Interval, Lambertian, Point, Quat,
+    Ray, Rotate, RotateY, Sphere, Vec3,
+};
+
+const EPSILON: f64 = 1e-9;
+
+fn coordinate() -> impl Strategy<Value = f64> {
+    prop_oneof![1 => Just(0.0), 9 => -100.0..100.0]
+}
+
+fn vec3() -> impl Strategy<Value = Vec3> {
+    (coordinate(), coordinate(), coordinate()).prop_map(|(x, y, z)| Vec3(x, y, z))
+}
+
+fn direction() -> impl Strategy<Value = Vec3> {
+    vec3().prop_filter("direction must be nonzero", |v| v.length() > 1e-3)
+}
+
+fn unit_vector() -> impl Strategy<Value = Vec3> {
+    direction().prop_map(|v| v.unit())
+}
+
+fn interval() -> impl Strategy<Value = Interval> {
+    (-100.0..100.0, 0.0..50.0)
+        .prop_map(|(start, size): (f64, f64)| Interval::new(start, start + size))
+}
+
+fn bounding_box() -> impl Strategy<Value = BoundingBox> {
+    (interval(), interval(), interval()).prop_map(|(x, y, z)| BoundingBox::new(x, y, z))
+}
+
+fn box_contains(outer: &BoundingBox, inner: &BoundingBox, tolerance: f64) -> bool {
+    (0..3).all(|axis| {
+        outer.intervals[axis].start <= inner.intervals[axis].start + tolerance
+            && inner.intervals[axis].end <= outer.intervals[axis].end + tolerance
+    })
+}
+
+fn rng() -> IndependentSampler {
+    IndependentSampler::new(0)
+}
+
+fn sphere(center: Point, radius: f64) -> Arc<dyn Hittable> {
+    Arc::new(Sphere::new(
+        center,
+        radius,
+        Arc::new(Lambertian::from(color(0.5, 0.5, 0.5))),
+    ))
+}
+
+proptest! {
+    /* === Vec3 === */
+
+    #[test]
+    fn reflect_preserves_length_and_flips_normal_component(v in vec3(), n in unit_vector()) {
+        let r = v.reflect(&n);
+        prop_assert!((r.length() - v.length()).abs() < EPSILON * (1.0 + v.length()));
+        prop_assert!((Vec3::dot(&r, &n) + Vec3::dot(&v, &n)).abs() < EPSILON * (1.0 + v.length()));
+    }
+
+    #[test]
+    fn refract_and_reflect_stay_finite(
+        v in unit_vector(),
+        n in unit_vector(),
+        eta in 0.2..5.0,
+    ) {
+        // Materials only refract rays arriving against the normal.
+        let n = if Vec3::dot(&v, &n) > 0.0 { -n } else { n };
+        prop_assert!(v.reflect(&n).is_finite());
+        prop_assert!(v.refract(&n, eta).is_finite());
+    }
+
+    /* === Interval === */
+
+    #[test]
+    fn interval_from_pair_contains_both(a in interval(), b in interval()) {
+        let joined = Interval::from_pair(a, b);
+        for x in [a.start, a.end, b.start, b.end] {
+            prop_assert!(joined.contains(x));
+        }
+    }
+
+    #[test]
+    fn interval_from_pair_ignores_empty(a in interval()) {
+        let joined = Interval::from_pair(Interval::empty(), a);
+        prop_assert_eq!((joined.start, joined.end), (a.start, a.end));
+    }
+
+    /* === BoundingBox === */
+
+    #[test]
+    fn union_contains_both_boxes(a in bounding_box(), b in bounding_box()) {
+        let union = BoundingBox::from_boxes(a, b);
+        prop_assert!(box_contains(&union, &a, 0.0));
+        prop_assert!(box_contains(&union, &b, 0.0));
+    }
+
+    #[test]
+    fn ray_through_interior_hits_box(
+        bounds in bounding_box(),
+        weights in (0.01..0.99, 0.01..0.99, 0.01..0.99),
+        direction in direction(),
+        distance in 0.0..100.0,
+    ) {
+        let [x, y, z] = bounds.intervals;
+        let interior = Vec3(
+            x.start + x.size() * weights.0,
+            y.start + y.size() * weights.1,
+            z.start + z.size() * weights.2,
+        );
+        let ray = Ray {
+            origin: interior - direction.unit() * distance,
+            direction: direction.unit(),
+            time: 0.0,
+        };
+        prop_assert!(bounds.hit(&ray, Interval::new(0.0, f64::INFINITY)));
+    }
+
+    #[test]
+    fn rotating_back_restores_the_object(
+        center in vec3(),
+        radius in 0.1..10.0,
+        angle in -360.0..360.0,
+        from in unit_vector(),
+    ) {
+        let original = sphere(center, radius);
+        let inner: Arc<dyn Hittable> = Arc::new(RotateY::new(original.clone(), angle));
+        let restored = RotateY::new(inner, -angle);
+
+        // Each rotation re-boxes its child, so the bounds may only grow.
+        prop_assert!(box_contains(&restored.bound(), &original.bound(), 1e-9));
+
+        let ray = Ray {
+            origin: center + from * (radius + 50.0),
+            direction: -from,
+            time: 0.0,
+        };
+        let t = Interval::new(0.001, f64::INFINITY);
+        let expected = original.hit(&ray, t, &mut rng()).unwrap();
+        let actual = restored.hit(&ray, t, &mut rng()).unwrap();
+        prop_assert!((expected.t - actual.t).abs() < 1e-6);
+        prop_assert!((expected.point - actual.point).length() < 1e-6);
+        prop_assert!((expected.normal - actual.normal).length() < 1e-6);
+    }
+
+    #[test]
+    fn rotating_by_the_inverse_restores_the_object(
+        center in vec3(),
+        radius in 0.1..10.0,
+        axis in unit_vector(),
+        angle in -360.0..360.0,
+        from in unit_vector(),
+    ) {
+        let original = sphere(center, radius);
+        let q = Quat::from_axis_angle(axis, angle);
+        let inner: Arc<dyn Hittable> = Arc::new(Rotate::new(original.clone(), q));
+        let restored = Rotate::new(inner, q.inverse());
+        prop_assert!(box_contains(&restored.bound(), &original.bound(), 1e-9));
+
+        let ray = Ray {
+            origin: center + from * (radius + 50.0),
+            direction: -from,
+            time: 0.0,
+        };
+        let t = Interval::new(0.001, f64::INFINITY);
+        let expected = original.hit(&ray, t, &mut rng()).unwrap();
+        let actual = restored.hit(&ray, t, &mut rng()).unwrap();
+        prop_assert!((expected.t - actual.t).abs() < 1e-6);
+        prop_assert!((expected.point - actual.point).length() < 1e-6);
+        prop_assert!((expected.normal - actual.normal).length() < 1e-6);
+        prop_assert_eq!(expected.front_face, actual.front_face);
+    }
+
+    #[test]
+    fn quarter_turns_keep_bounds(center in vec3(), radius in 0.1..10.0, turns in -4i32..4) {
+        let original = sphere(center, radius);
+        let inner: Arc<dyn Hittable> = Arc::new(RotateY::new(original.clone(), 90.0 * turns as f64));
+        let restored = RotateY::new(inner, -90.0 * turns as f64);
+        for axis in 0..3 {
+            let (a, b) = (original.bound().intervals[axis], restored.bound().intervals[axis]);
+            prop_assert!((a.start - b.start).abs() < 1e-6 && (a.end - b.end).abs() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,72 @@
+//! The same contact scene rendered at very different scales must look the
+//! same: no light leaking under the sphere when tiny, no acne when huge.
+
+use std::sync::Arc;
+
+use ray_tracer::{
+    color, point, Camera, HittableList, ImageBuffer, Lambertian, LightList, Parallelogram, Planar,
+    Sphere, Vec3,
+};
+
+/// A diffuse sphere resting on a diffuse floor under a white sky, `scale` units across.
+fn contact_scene(scale: f64) -> ImageBuffer {
+    let gray = Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)));
+    let mut world = HittableList::new();
+    world.add(Planar::Parallelogram(Parallelogram::new(
+        point(-50., 0., -50.) * scale,
+        (Vec3(100., 0., 0.) * scale, Vec3(0., 0., 100.) * scale),
+        gray.clone(),
+    )));
+    world.add(Sphere::new(point(0., 1., 0.) * scale, scale, gray));
+
+    let mut camera = Camera::new(
+        1.0,
+        48,
+        30.,
+        point(0., 1.5, 6.) * scale,
+        point(0., 0.8, 0.) * scale,
+        Vec3(0., 1., 0.),
+        16,
+        8,
+    );
+    camera.set_background(color(1., 1., 1.)).set_seed(Some(11));
+    camera.render_image(&world, &LightList::new())
+}
+
+fn mean_difference(a: &ImageBuffer, b: &ImageBuffer) -> f64 {
+    let total: f64 = a
+        .pixels
+        .iter()
+        .zip(&b.pixels)
+        .map(|(p, q)| (0..3).map(|c| (p[c] - q[c]).abs()).sum::<f64>())
+        .sum();
+    total / (a.pixels.len() * 3) as f64
+}
+
+/// The darkest pixel of the floor right where the sphere touches it.
+fn contact_shadow(image: &ImageBuffer) -> f64 {
+    let (x, y) = (image.width / 2, image.height * 3 / 4);
+    let p = image.get(x, y);
+    (p[0] + p[1] + p[2]) / 3.
+}
+
+#[test]
+fn tiny_scene_matches_unit_scene() {
+    let (unit, tiny) = (contact_scene(1.), contact_scene(0.001));
+    let difference = mean_difference(&unit, &tiny);
+    assert!(difference < 1e-3, "mean difference {}", difference);
+    let (expected, actual) = (contact_shadow(&unit), contact_shadow(&tiny));
+    assert!(
+        (actual - expected).abs() < 0.01,
+        "contact shadow {} leaks light (expected {})",
+        actual,
+        expected
+    );
+}
+
+#[test]
+fn huge_scene_matches_unit_scene() {
+    let (unit, huge) = (contact_scene(1.), contact_scene(1000.));
+    let difference = mean_difference(&unit, &huge);
+    assert!(difference < 1e-3, "mean difference {}", difference);
+}
@@ -0,0 +1,211 @@
+//! Rendering for embedding: tiles, passes, and progress reported as they
+//! finish, and cancelling from the callback or another thread, leaving the
+//! image as far as it got.
+
+use std::{
+    ops::ControlFlow,
+    sync::{atomic::Ordering, mpsc, Arc},
+    thread,
+    time::{Duration, Instant},
+};
+
+use ray_tracer::{
+    color,
+    scenes::{self, Scene},
+    RenderEvent, RenderSettings,
+};
+
+fn tiny(mut scene: Scene) -> Scene {
+    // Not a multiple of the tile size, so the last tiles are partial.
+    scene
+        .camera
+        .set_image_width(21)
+        .set_aa_samples(5)
+        .set_max_depth(4)
+        .set_seed(Some(11));
+    scene
+}
+
+/// Each event in brief, to compare their order.
+fn describe(event: &RenderEvent) -> String {
+    match event {
+        RenderEvent::Tile(tile) => {
+            assert_eq!(tile.pixels.len(), tile.width * tile.height);
+            format!("tile {} {} {}", tile.x, tile.y, tile.samples)
+        }
+        RenderEvent::Progress { done, total } => format!("progress {}/{}", done, total),
+        RenderEvent::Pass { pass, samples, .. } => format!("pass {} {}", pass, samples),
+        RenderEvent::Finished(stats) => format!("finished {}", stats.samples),
+    }
+}
+
+#[test]
+fn the_finished_image_is_the_plain_render() {
+    // One thread, so the tiles finish in order.
+    let mut scene = tiny(scenes::cornell_box());
+    scene.camera.set_threads(1);
+    let settings = RenderSettings {
+        tile_size: 8,
+        passes: 2,
+        ..RenderSettings::default()
+    };
+    let mut events = Vec::new();
+    let outcome = scene.render_with(&settings, |event| {
+        events.push(describe(&event));
+        ControlFlow::Continue(())
+    });
+    assert!(!outcome.cancelled);
+    let plain = scene.render_image();
+    assert!(outcome
+        .image
+        .pixels
+        .iter()
+        .zip(&plain.pixels)
+        .all(|(a, b)| (a.0, a.1, a.2) == (b.0, b.1, b.2)));
+
+    // Nine tiles a pass, the first pass taking two samples of the five.
+    assert_eq!(events.len(), 2 * (9 * 2 + 1) + 1);
+    assert_eq!(events[0], "tile 0 0 2");
+    assert_eq!(events[1], "progress 1/18");
+    assert_eq!(events[18], "pass 0 2");
+    assert_eq!(events[35], "tile 16 16 5");
+    assert_eq!(events[37], "pass 1 5");
+    assert_eq!(events[38], "finished 5");
+}
+
+#[test]
+fn tiles_are_shared_across_threads() {
+    let mut scene = tiny(scenes::cornell_box());
+    scene.camera.set_threads(4);
+    let settings = RenderSettings {
+        tile_size: 8,
+        passes: 2,
+        ..RenderSettings::default()
+    };
+    let mut events = Vec::new();
+    let outcome = scene.render_with(&settings, |event| {
+        events.push(describe(&event));
+        ControlFlow::Continue(())
+    });
+    assert!(!outcome.cancelled);
+    assert_eq!(outcome.stats.threads, 4);
+    let plain = scene.render_image();
+    assert!(outcome
+        .image
+        .pixels
+        .iter()
+        .zip(&plain.pixels)
+        .all(|(a, b)| (a.0, a.1, a.2) == (b.0, b.1, b.2)));
+
+    // Every tile of each pass, in whatever order they finish, and then the
+    // pass.
+    assert_eq!(events.len(), 2 * (9 * 2 + 1) + 1);
+    for (pass, samples) in [(0, 2), (1, 5)] {
+        let events = &events[pass * 19..(pass + 1) * 19];
+        let mut tiles: Vec<_> = events.iter().filter(|e| e.starts_with("tile")).collect();
+        tiles.sort();
+        tiles.dedup();
+        assert_eq!(tiles.len(), 9);
+        assert!(tiles.iter().all(|t| t.ends_with(&format!(" {}", samples))));
+        assert_eq!(events[17], format!("progress {}/18", 9 * (pass + 1)));
+        assert_eq!(events[18], format!("pass {} {}", pass, samples));
+    }
+    assert_eq!(events[38], "finished 5");
+}
+
+#[test]
+fn breaking_from_the_last_progress_cancels() {
+    let mut scene = tiny(scenes::cornell_box());
+    scene.camera.set_threads(1);
+    let settings = RenderSettings {
+        tile_size: 8,
+        ..RenderSettings::default()
+    };
+    let mut events = Vec::new();
+    let outcome = scene.render_with(&settings, |event| {
+        events.push(describe(&event));
+        match event {
+            RenderEvent::Progress { done, total } if done == total => ControlFlow::Break(()),
+            _ => ControlFlow::Continue(()),
+        }
+    });
+    // Every tile was done, but the break still cancels the render, with
+    // neither the pass nor the finish reported.
+    assert!(outcome.cancelled);
+    assert_eq!(events.last().unwrap(), "progress 9/9");
+    assert!(!events
+        .iter()
+        .any(|e| e.starts_with("pass") || e.starts_with("finished")));
+}
+
+#[test]
+fn breaking_from_the_callback_stops_promptly_with_a_partial_image() {
+    // Far too big to finish in a test: 600 by 600 with 100 samples, with a
+    // white background around the box so no pixel rendered stays black.
+    // One thread, so no other tile is under way when it breaks.
+    let mut scene = scenes::cornell_box();
+    scene
+        .camera
+        .set_background(color(1., 1., 1.))
+        .set_threads(1);
+    let scene = Arc::new(scene);
+    let (sender, receiver) = mpsc::channel();
+    let started = Instant::now();
+    let worker = thread::spawn(move || {
+        let settings = RenderSettings {
+            tile_size: 4,
+            ..RenderSettings::default()
+        };
+        let mut tiles = 0;
+        scene.render_with(&settings, |event| {
+            if let RenderEvent::Tile(tile) = event {
+                sender.send((tile.x, tile.y)).unwrap();
+                tiles += 1;
+            }
+            if tiles == 3 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        })
+    });
+    let outcome = worker.join().unwrap();
+    assert!(started.elapsed() < Duration::from_secs(60));
+    assert!(outcome.cancelled);
+    assert_eq!(
+        receiver.iter().collect::<Vec<_>>(),
+        [(0, 0), (4, 0), (8, 0)]
+    );
+
+    // The three tiles are filled in and the rest left black.
+    let image = &outcome.image;
+    assert_eq!((image.width, image.height), (600, 600));
+    let lit = |x: usize, y: usize| image.get(x, y).length_squared() > 0.0;
+    assert!((0..12).all(|x| (0..4).all(|y| lit(x, y))));
+    assert!((12..600).all(|x| (0..4).all(|y| !lit(x, y))));
+    assert!((0..600).all(|x| (4..600).all(|y| !lit(x, y))));
+}
+
+#[test]
+fn another_thread_can_cancel_through_the_settings() {
+    let scene = scenes::cornell_box();
+    let settings = RenderSettings {
+        tile_size: 4,
+        ..RenderSettings::default()
+    };
+    let cancel = settings.cancel.clone();
+    let mut events = 0;
+    let outcome = thread::scope(|scope| {
+        let render = scope.spawn(|| {
+            scene.render_with(&settings, |_| {
+                events += 1;
+                ControlFlow::Continue(())
+            })
+        });
+        thread::sleep(Duration::from_millis(50));
+        cancel.store(true, Ordering::Relaxed);
+        render.join().unwrap()
+    });
+    assert!(outcome.cancelled);
+    assert!(events < 2 * 150 * 150);
+}
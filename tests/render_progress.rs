@@ -0,0 +1,50 @@
+//! Progress hooks: called once a row, counting up to every row, however
+//! many threads render them.
+
+use std::sync::{Arc, Mutex};
+
+use ray_tracer::{scenes, DownscaleFilter, RenderProgress};
+
+fn reports(threads: usize, supersample: u32) -> Vec<RenderProgress> {
+    let mut scene = scenes::cornell_box();
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let hook = seen.clone();
+    scene
+        .camera
+        .set_image_width(16)
+        .set_aa_samples(2)
+        .set_max_depth(3)
+        .set_seed(Some(1))
+        .set_threads(threads)
+        .set_supersample(supersample, DownscaleFilter::Box)
+        .set_progress(move |progress| hook.lock().unwrap().push(progress));
+    scene.render_timed();
+    let seen = seen.lock().unwrap().clone();
+    seen
+}
+
+#[test]
+fn every_row_is_reported_in_order_of_finishing() {
+    for threads in [1, 3] {
+        let seen = reports(threads, 1);
+        assert_eq!(seen.len(), 16);
+        for (i, progress) in seen.iter().enumerate() {
+            assert_eq!(progress.rows_done, i + 1);
+            assert_eq!(progress.rows, 16);
+            assert!(progress.samples_per_second > 0.0);
+        }
+        assert_eq!(seen.last().unwrap().rows_remaining(), 0);
+        assert!(seen.windows(2).all(|w| w[0].elapsed <= w[1].elapsed));
+    }
+}
+
+#[test]
+fn supersampled_renders_report_the_larger_rows() {
+    let seen = reports(2, 2);
+    assert_eq!(seen.len(), 32);
+    assert_eq!(seen.last().unwrap().rows, 32);
+    assert_eq!(
+        seen[0].to_string().split(' ').take(3).collect::<Vec<_>>(),
+        ["Scanlines", "remaining:", "31"]
+    );
+}
@@ -0,0 +1,243 @@
+//! Render statistics and stage timing: what `Scene::render_timed` reports
+//! about the render and the work that led up to it.
+
+use std::{sync::Arc, thread, time::Duration};
+
+use ray_tracer::{
+    color, point,
+    scenes::{self, Place, Scene, SceneDesc},
+    stage_times, take_stage_times, BoundNode, Hittable, HittableList, Lambertian, MeshData, Stage,
+    StageTime, TriangleMesh, Vec3, Verbosity,
+};
+
+/// Runs `f` on a fresh thread, so stages and rays from other tests don't leak in.
+fn isolated<T: Send>(f: impl FnOnce() -> T + Send) -> T {
+    thread::scope(|scope| scope.spawn(f).join().unwrap())
+}
+
+fn tiny(mut scene: Scene) -> Scene {
+    scene
+        .camera
+        .set_image_width(12)
+        .set_aa_samples(3)
+        .set_max_depth(4)
+        .set_seed(Some(5));
+    scene
+}
+
+#[test]
+fn renders_report_their_size_rays_and_primitives() {
+    let (image, stats) = isolated(|| tiny(scenes::cornell_box()).render_timed());
+    assert_eq!((stats.width, stats.height), (12, 12));
+    assert_eq!((image.width, image.height), (12, 12));
+    assert_eq!(stats.samples, 3);
+    assert_eq!(stats.threads, 1);
+    // At least one ray per sample, and at most one per bounce.
+    assert!(stats.rays >= 12 * 12 * 3, "{}", stats.rays);
+    assert!(stats.rays <= 12 * 12 * 3 * 4, "{}", stats.rays);
+    assert!(stats.rays_per_second() > 0.0);
+    // Five walls, a light, and two boxes of six sides.
+    assert_eq!(stats.primitives, 5 + 1 + 12);
+}
+
+#[test]
+fn counted_renders_report_what_the_paths_did() {
+    let (timed, counted) = isolated(|| {
+        let scene = tiny(scenes::cornell_box());
+        (scene.render_timed().1, scene.render_with_stats().1)
+    });
+    assert!(timed.trace.is_none());
+    assert!(!timed.to_string().contains("primary rays"));
+
+    let trace = counted.trace.as_ref().unwrap();
+    assert_eq!(trace.primary_rays, 12 * 12 * 3);
+    assert!(trace.path_rays <= counted.rays);
+    assert!(trace.mean_path_depth() >= 1.0 && trace.mean_path_depth() <= 5.0);
+    // The walls and the light are parallelograms, and the boxes are made of
+    // them.
+    assert!(trace.hit_tests["Parallelogram"] > 0, "{:?}", trace);
+    assert_eq!(
+        trace.total_hit_tests(),
+        trace.hit_tests.values().sum::<u64>()
+    );
+    // The same seeded render, counted or not.
+    assert_eq!(counted.rays, timed.rays);
+
+    let summary = counted.to_string();
+    assert!(
+        summary.contains(&format!("\n  {} primary rays, ", trace.primary_rays)),
+        "{}",
+        summary
+    );
+    assert!(summary.contains("\n    Parallelogram "), "{}", summary);
+}
+
+#[test]
+fn counts_add_up_across_threads_and_through_the_bvh() {
+    let counted = |threads| {
+        isolated(move || {
+            let mut scene = tiny(scenes::cornell_box());
+            let gray = Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)));
+            let spheres: Vec<Arc<dyn Hittable>> = (0..8)
+                .map(|i| {
+                    scenes::sphere(point(100. + 50. * i as f64, 100., 300.), 20., gray.clone())
+                })
+                .collect();
+            scene.add(Arc::new(BoundNode::from_objects(
+                &spheres,
+                0..spheres.len(),
+            )));
+            scene.camera.set_threads(threads);
+            scene.render_with_stats().1.trace.unwrap()
+        })
+    };
+    let one = counted(1);
+    assert_eq!(counted(3), one);
+    // Every path ray is tested against the root of the BVH at least.
+    assert!(one.node_visits >= one.path_rays, "{:?}", one);
+    assert!(one.hit_tests["Sphere"] > 0, "{:?}", one);
+}
+
+#[test]
+fn stages_nest_and_include_the_scene_build() {
+    let text = r#"
+        [camera]
+        aspect_ratio = 1.0
+        image_width = 8
+        vfov = 40.0
+        look_from = [0.0, 3.0, -4.0]
+        look_at = [0.0, 0.0, 0.0]
+        up = [0.0, 1.0, 0.0]
+        aa_samples = 1
+        max_depth = 2
+
+        [materials]
+        gray = { type = "lambertian", albedo = [0.5, 0.5, 0.5] }
+
+        [[objects]]
+        type = "mesh"
+        path = "resources/models/gradient_cube.ply"
+        material = "gray"
+    "#;
+    let stats = isolated(|| {
+        let desc: SceneDesc = toml::from_str(text).unwrap();
+        desc.build().unwrap().render_timed().1
+    });
+    let stages: Vec<(&str, usize)> = stats
+        .stages
+        .iter()
+        .map(|stage| (stage.name, stage.depth))
+        .collect();
+    assert_eq!(
+        stages,
+        [
+            ("build scene", 0),
+            ("load assets", 1),
+            ("build BVH", 2),
+            ("render", 0)
+        ]
+    );
+    assert!(stats.stages.iter().all(|stage| stage.count == 1));
+    assert_eq!(stats.render_time, stats.stages[3].elapsed);
+    assert_eq!(stats.stage_time("build BVH"), Some(stats.stages[2].elapsed));
+    assert_eq!(stats.stage_time("build TLAS"), None);
+    assert!(stats.stages[0].elapsed >= stats.stages[1].elapsed);
+    assert_eq!(stats.primitives, 12);
+
+    let summary = stats.to_string();
+    assert!(
+        summary.starts_with("render summary:\n  8 by 8 pixels, 1 samples per pixel, 1 thread\n")
+    );
+    assert!(
+        summary.contains("\n  12 primitives\n  build scene "),
+        "{}",
+        summary
+    );
+    assert!(summary.contains("\n      build BVH "), "{}", summary);
+}
+
+#[test]
+fn deeply_nested_stages_still_print() {
+    let mut stats = isolated(|| tiny(scenes::build("cornell_box").unwrap()).render_timed().1);
+    stats.stages.push(StageTime {
+        name: "deep",
+        depth: 12,
+        count: 1,
+        elapsed: Duration::from_millis(3),
+    });
+    let summary = stats.to_string();
+    assert!(
+        summary.contains(&format!("\n  {}deep ", " ".repeat(24))),
+        "{}",
+        summary
+    );
+}
+
+#[test]
+fn each_render_starts_the_stages_over() {
+    let (first, second) = isolated(|| {
+        let scene = tiny(scenes::build("cornell_box").unwrap());
+        (scene.render_timed().1, scene.render_timed().1)
+    });
+    assert_eq!(first.stages[0].name, "build scene");
+    assert_eq!(second.stages.len(), 1);
+    assert_eq!(second.stages[0].name, "render");
+    // The same seeded render traces the same rays.
+    assert_eq!(first.rays, second.rays);
+}
+
+#[test]
+fn repeated_stages_add_up() {
+    let stages = isolated(|| {
+        for _ in 0..3 {
+            let _outer = Stage::start("outer");
+            let _inner = Stage::start("inner");
+        }
+        let running = Stage::start("running");
+        let seen = stage_times();
+        assert_eq!(seen[2].name, "running");
+        assert_eq!(seen[2].count, 0);
+        drop(running);
+        take_stage_times()
+    });
+    assert_eq!(stages.len(), 3);
+    assert_eq!(
+        (stages[0].name, stages[0].depth, stages[0].count),
+        ("outer", 0, 3)
+    );
+    assert_eq!(
+        (stages[1].name, stages[1].depth, stages[1].count),
+        ("inner", 1, 3)
+    );
+    assert_eq!(
+        (stages[2].name, stages[2].depth, stages[2].count),
+        ("running", 0, 1)
+    );
+    assert!(stages[0].elapsed >= stages[1].elapsed);
+}
+
+#[test]
+fn instances_count_their_primitives_each() {
+    let gray = Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)));
+    let mesh: Arc<dyn Hittable> = Arc::new(TriangleMesh::new(
+        MeshData::quad(point(0., 0., 0.), (Vec3(1., 0., 0.), Vec3(0., 0., 1.))),
+        gray.clone(),
+    ));
+    let mut world = HittableList::new();
+    world.add_arc(mesh.clone());
+    world.add_arc(mesh.translate(Vec3(3., 0., 0.)));
+    world.add_arc(scenes::sphere(point(0., 2., 0.), 1., gray));
+    assert_eq!(world.primitive_count(), 2 + 2 + 1);
+}
+
+#[test]
+fn verbosity_levels_parse_and_order() {
+    assert_eq!("quiet".parse(), Ok(Verbosity::Quiet));
+    assert_eq!(
+        "verbose".parse::<Verbosity>().unwrap().to_string(),
+        "verbose"
+    );
+    assert!("loud".parse::<Verbosity>().is_err());
+    assert_eq!(Verbosity::default(), Verbosity::Normal);
+    assert!(Verbosity::Quiet < Verbosity::Normal && Verbosity::Normal < Verbosity::Verbose);
+}
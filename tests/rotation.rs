@@ -0,0 +1,153 @@
+//! Quaternion rotations: agreement with `RotateY`, Euler angles, slerp, and
+//! the conversions to and from `Transform`.
+
+use std::sync::Arc;
+
+use ray_tracer::{
+    color, point, scenes::SceneDesc, Hittable, IndependentSampler, Interval, Lambertian,
+    Parallelogram, Quat, Ray, Rotate, RotateY, Transform, Vec3,
+};
+
+const EPSILON: f64 = 1e-9;
+
+fn close(a: Vec3, b: Vec3) -> bool {
+    (a - b).length() < EPSILON
+}
+
+/// An off-center panel, so rotations move it as well as turn it.
+fn panel() -> Arc<dyn Hittable> {
+    Arc::new(Parallelogram::new(
+        point(1., -0.5, 2.),
+        (Vec3(1.5, 0., 0.), Vec3(0., 1., 0.5)),
+        Arc::new(Lambertian::from(color(0.5, 0.5, 0.5))),
+    ))
+}
+
+#[test]
+fn quarter_turn_about_y_matches_rotate_y() {
+    let turned = Rotate::from_axis_angle(panel(), Vec3(0., 1., 0.), 90.);
+    let reference = RotateY::new(panel(), 90.);
+    for axis in 0..3 {
+        let (a, b) = (
+            turned.bound().intervals[axis],
+            reference.bound().intervals[axis],
+        );
+        assert!((a.start - b.start).abs() < EPSILON && (a.end - b.end).abs() < EPSILON);
+    }
+
+    let t = Interval::new(0.001, f64::INFINITY);
+    for (x, y) in [(0.0, 0.0), (0.3, 0.1), (-0.4, 0.3), (0.9, -0.2)] {
+        let ray = Ray {
+            origin: point(0., 0., 0.),
+            direction: Vec3(2.5 + x, y, -1.8),
+            time: 0.0,
+        };
+        let mut rng = IndependentSampler::new(0);
+        let expected = reference.hit(&ray, t, &mut rng);
+        let actual = turned.hit(&ray, t, &mut rng);
+        match (expected, actual) {
+            (Some(expected), Some(actual)) => {
+                assert!((expected.t - actual.t).abs() < EPSILON);
+                assert!(close(expected.point, actual.point));
+                assert!(close(expected.normal, actual.normal));
+                assert!((expected.u - actual.u).abs() < EPSILON);
+            }
+            (None, None) => {}
+            (expected, actual) => panic!(
+                "RotateY hit: {}, Rotate hit: {}",
+                expected.is_some(),
+                actual.is_some()
+            ),
+        }
+    }
+}
+
+#[test]
+fn euler_angles_apply_x_then_y_then_z() {
+    let q = Quat::from_euler(90., 90., 0.);
+    // X takes +y to +z, then Y takes +z to +x.
+    assert!(close(q.rotate(Vec3(0., 1., 0.)), Vec3(1., 0., 0.)));
+    let q = Quat::from_euler(0., 90., 90.);
+    // Y takes +x to -z, which Z leaves alone.
+    assert!(close(q.rotate(Vec3(1., 0., 0.)), Vec3(0., 0., -1.)));
+}
+
+#[test]
+fn axis_angle_round_trips() {
+    let axis = Vec3(1., 2., -2.).unit();
+    let (back, angle) = Quat::from_axis_angle(axis * 3.0, 130.).to_axis_angle();
+    assert!(close(back, axis) && (angle - 130.).abs() < EPSILON);
+    let (axis, angle) = Quat::identity().to_axis_angle();
+    assert!(close(axis, Vec3(0., 1., 0.)) && angle == 0.0);
+}
+
+#[test]
+fn slerp_turns_at_a_constant_rate_along_the_short_way() {
+    let axis = Vec3(0., 0., 1.);
+    let a = Quat::from_axis_angle(axis, 10.);
+    let b = Quat::from_axis_angle(axis, 130.);
+    for i in 0..=4 {
+        let t = i as f64 / 4.0;
+        let (_, angle) = a.slerp(&b, t).to_axis_angle();
+        assert!(
+            (angle - (10. + 120. * t)).abs() < 1e-6,
+            "{} at {}",
+            angle,
+            t
+        );
+    }
+
+    // 350° is -10°, so the short way from 10° passes through 0°.
+    let c = Quat::from_axis_angle(axis, 350.);
+    let halfway = a.slerp(&c, 0.5);
+    assert!(close(halfway.rotate(Vec3(1., 0., 0.)), Vec3(1., 0., 0.)));
+}
+
+#[test]
+fn transforms_convert_to_and_from_quaternions() {
+    let transform = Transform::rotate_y(35.);
+    let q = transform.rotation();
+    let v = Vec3(0.3, -1., 2.);
+    assert!(close(q.rotate(v), transform.vector(v)));
+
+    let back = Transform::from_rotation(q).unwrap();
+    assert!((back.rotation_y - 35.).abs() < EPSILON);
+    let flipped = Transform::from_rotation(Quat::from_axis_angle(Vec3(0., -1., 0.), 35.)).unwrap();
+    assert!((flipped.rotation_y + 35.).abs() < EPSILON);
+    assert!(Transform::from_rotation(Quat::from_euler(20., 35., 0.)).is_none());
+}
+
+#[test]
+fn scene_files_rotate_about_any_axis() {
+    let text = r#"
+        [camera]
+        aspect_ratio = 1.0
+        image_width = 8
+        vfov = 40.0
+        look_from = [0.0, 0.0, 5.0]
+        look_at = [0.0, 0.0, 0.0]
+        up = [0.0, 1.0, 0.0]
+        aa_samples = 1
+        max_depth = 2
+
+        [materials]
+        gray = { type = "lambertian", albedo = [0.5, 0.5, 0.5] }
+
+        [[objects]]
+        type = "rotate"
+        axis = [1.0, 1.0, 0.0]
+        angle = 45.0
+        object = { type = "sphere", center = [1.0, 0.0, 0.0], radius = 0.5, material = "gray" }
+    "#;
+    let desc: SceneDesc = toml::from_str(text).unwrap();
+    let scene = desc.build().unwrap();
+    let saved = toml::to_string(&scene.describe().unwrap()).unwrap();
+    assert!(saved.contains("type = \"rotate\""), "{}", saved);
+    let again: SceneDesc = toml::from_str(&saved).unwrap();
+    let rebuilt = again.build().unwrap();
+    let (a, b) = (scene.world.bound(), rebuilt.world.bound());
+    for axis in 0..3 {
+        assert!((a.intervals[axis].start - b.intervals[axis].start).abs() < 1e-9);
+        assert!((a.intervals[axis].end - b.intervals[axis].end).abs() < 1e-9);
+    }
+}
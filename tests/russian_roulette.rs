@@ -0,0 +1,63 @@
+//! Russian roulette: paths ended at random past the start depth leave the
+//! image the same on average, in fewer rays.
+
+use ray_tracer::{
+    scenes::{self, CameraBuilder, Scene},
+    CameraError, Color, ImageBuffer,
+};
+
+fn cornell(roulette: Option<i32>) -> Scene {
+    let mut scene = scenes::cornell_box();
+    scene
+        .camera
+        .set_image_width(16)
+        .set_aa_samples(64)
+        .set_max_depth(50)
+        .set_seed(Some(5))
+        .set_russian_roulette(roulette.is_some(), roulette.unwrap_or(3));
+    scene
+}
+
+fn mean(image: &ImageBuffer) -> f64 {
+    image.pixels.iter().map(Color::luminance).sum::<f64>() / image.pixels.len() as f64
+}
+
+#[test]
+fn roulette_keeps_the_image_in_fewer_rays() {
+    let (full, full_stats) = cornell(None).render_timed();
+    let (cut, cut_stats) = cornell(Some(3)).render_timed();
+    assert!(
+        cut_stats.rays < full_stats.rays,
+        "{} rays with roulette, {} without",
+        cut_stats.rays,
+        full_stats.rays
+    );
+    let (full, cut) = (mean(&full), mean(&cut));
+    assert!((cut - full).abs() < 0.05 * full, "{} against {}", cut, full);
+
+    // Starting later ends fewer paths.
+    let (_, later_stats) = cornell(Some(10)).render_timed();
+    assert!(later_stats.rays > cut_stats.rays);
+}
+
+#[test]
+fn roulette_is_off_unless_asked_for_and_survives_a_scene_file() {
+    let camera = CameraBuilder::default().build().unwrap();
+    assert!(!camera.russian_roulette);
+    let text = serde_json::to_string(&CameraBuilder::from(&camera)).unwrap();
+    assert!(!text.contains("russian_roulette"));
+
+    let camera = CameraBuilder::default()
+        .russian_roulette(Some(4))
+        .build()
+        .unwrap();
+    assert!(camera.russian_roulette);
+    assert_eq!(camera.roulette_depth, 4);
+    let text = serde_json::to_string(&CameraBuilder::from(&camera)).unwrap();
+    assert!(text.contains(r#""russian_roulette":4"#));
+
+    assert!(matches!(
+        CameraBuilder::default().russian_roulette(Some(-1)).build(),
+        Err(CameraError::InvalidRouletteDepth(-1))
+    ));
+}
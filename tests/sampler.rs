@@ -0,0 +1,415 @@
+//! Samplers: the Halton and Sobol sequences, dimension tracking, the blue-noise mask, and
+//! whether the QMC sampler and blue-noise dithering actually pay off.
+
+use std::sync::Arc;
+
+use rand::{Rng, RngCore};
+use ray_tracer::{
+    blue_noise, color, point, radical_inverse, random, scenes, sobol, Camera, HaltonSampler,
+    HittableList, ImageBuffer, IndependentSampler, Lambertian, LightList, Parallelogram, Sampler,
+    SamplerKind, SobolSampler, Sphere, Vec3, BLUE_NOISE_SIZE,
+};
+
+#[test]
+fn radical_inverse_mirrors_digits() {
+    assert_eq!(radical_inverse(2, 0), 0.);
+    assert_eq!(radical_inverse(2, 1), 0.5);
+    assert_eq!(radical_inverse(2, 2), 0.25);
+    assert_eq!(radical_inverse(2, 3), 0.75);
+    assert_eq!(radical_inverse(2, 6), 0.375);
+    assert!((radical_inverse(3, 1) - 1. / 3.).abs() < 1e-15);
+    assert!((radical_inverse(3, 5) - 7. / 9.).abs() < 1e-15);
+}
+
+/// The values dimension `dimension` of a pixel's first `count` samples takes.
+fn halton_values(dimension: u32, count: u32) -> Vec<f64> {
+    (0..count)
+        .map(|sample| {
+            let mut sampler = HaltonSampler::for_sample(5, 3, 4, sample);
+            for _ in 0..dimension {
+                sampler.next_1d();
+            }
+            sampler.next_1d()
+        })
+        .collect()
+}
+
+fn assert_stratified(values: &[f64]) {
+    let n = values.len();
+    let mut strata = vec![0; n];
+    for &v in values {
+        assert!((0.0..1.0).contains(&v), "{} out of range", v);
+        strata[(v * n as f64) as usize] += 1;
+    }
+    assert!(strata.iter().all(|&count| count == 1), "{:?}", strata);
+}
+
+#[test]
+fn halton_samples_of_a_pixel_fill_every_stratum() {
+    assert_stratified(&halton_values(0, 16));
+    assert_stratified(&halton_values(1, 9));
+    assert_stratified(&halton_values(2, 25));
+}
+
+#[test]
+fn halton_pixels_are_rotated_differently() {
+    let a = HaltonSampler::for_sample(5, 0, 0, 0).next_1d();
+    let b = HaltonSampler::for_sample(5, 1, 0, 0).next_1d();
+    assert_ne!(a, b);
+}
+
+/// The first 16 points of the first three dimensions, in index order rather
+/// than the Gray-code order some tables list them in.
+#[test]
+fn sobol_points_match_the_reference_sequence() {
+    let expected = [
+        [
+            0., 0.5, 0.25, 0.75, 0.125, 0.625, 0.375, 0.875, 0.0625, 0.5625, 0.3125, 0.8125,
+            0.1875, 0.6875, 0.4375, 0.9375,
+        ],
+        [
+            0., 0.5, 0.75, 0.25, 0.625, 0.125, 0.375, 0.875, 0.9375, 0.4375, 0.1875, 0.6875,
+            0.3125, 0.8125, 0.5625, 0.0625,
+        ],
+        [
+            0., 0.5, 0.75, 0.25, 0.375, 0.875, 0.625, 0.125, 0.5625, 0.0625, 0.3125, 0.8125,
+            0.9375, 0.4375, 0.1875, 0.6875,
+        ],
+    ];
+    for (dimension, points) in expected.iter().enumerate() {
+        for (index, &point) in points.iter().enumerate() {
+            assert_eq!(
+                sobol(dimension, index as u32),
+                point,
+                "{} {}",
+                dimension,
+                index
+            );
+        }
+    }
+}
+
+fn sobol_values(x: i32, dimension: u32, count: u32) -> Vec<f64> {
+    (0..count)
+        .map(|sample| {
+            let mut sampler = SobolSampler::for_sample(5, x, 4, sample);
+            for _ in 0..dimension {
+                sampler.next_1d();
+            }
+            sampler.next_1d()
+        })
+        .collect()
+}
+
+#[test]
+fn scrambled_sobol_samples_of_a_pixel_fill_every_stratum() {
+    for dimension in 0..8 {
+        assert_stratified(&sobol_values(3, dimension, 16));
+        assert_stratified(&sobol_values(3, dimension, 64));
+    }
+    // Each pixel is scrambled its own way.
+    assert_ne!(sobol_values(3, 0, 4), sobol_values(4, 0, 4));
+}
+
+#[test]
+fn samplers_count_dimensions() {
+    let mut samplers: [Box<dyn Sampler>; 3] = [
+        Box::new(IndependentSampler::new(1)),
+        Box::new(HaltonSampler::for_sample(1, 0, 0, 3)),
+        Box::new(SobolSampler::for_sample(1, 0, 0, 3)),
+    ];
+    for sampler in &mut samplers {
+        assert_eq!(sampler.dimension(), 0);
+        sampler.next_1d();
+        assert_eq!(sampler.dimension(), 1);
+        sampler.next_2d();
+        assert_eq!(sampler.dimension(), 3);
+        let _: f64 = sampler.gen();
+        let _ = sampler.gen_range(-0.5..0.5);
+        assert_eq!(sampler.dimension(), 5);
+        // Past the Halton and Sobol dimensions, draws continue independently.
+        for _ in 0..20 {
+            assert!((0.0..1.0).contains(&sampler.next_1d()));
+        }
+        assert_eq!(sampler.dimension(), 25);
+    }
+}
+
+#[test]
+fn camera_rays_use_the_same_dimensions_with_or_without_a_lens_or_shutter() {
+    let still = Camera::default();
+    let mut blurred = Camera::default();
+    blurred.set_defocus(2.0, Some(3.0)).set_shutter(0.0, 1.0);
+    for kind in [
+        SamplerKind::Independent,
+        SamplerKind::Halton,
+        SamplerKind::Sobol,
+    ] {
+        let dimensions: Vec<_> = [&still, &blurred]
+            .map(|camera| {
+                let mut sampler: Box<dyn Sampler> = match kind {
+                    SamplerKind::Independent => Box::new(IndependentSampler::dithered(1, 2, 3, 4)),
+                    SamplerKind::Halton => Box::new(HaltonSampler::for_sample(1, 2, 3, 4)),
+                    SamplerKind::Sobol => Box::new(SobolSampler::for_sample(1, 2, 3, 4)),
+                };
+                camera.sample_ray(2, 3, sampler.as_mut());
+                (sampler.dimension(), sampler.next_1d())
+            })
+            .into();
+        // The first scatter decision draws the same number either way.
+        assert_eq!(dimensions[0], dimensions[1], "{}", kind);
+        assert_eq!(dimensions[0].0, 5);
+    }
+}
+
+#[test]
+fn halton_draws_through_rng_match_next_1d() {
+    let mut direct = HaltonSampler::for_sample(9, 2, 7, 11);
+    let mut through_rng = HaltonSampler::for_sample(9, 2, 7, 11);
+    for _ in 0..12 {
+        let expected = direct.next_1d();
+        let actual: f64 = through_rng.gen();
+        assert!(
+            (expected - actual).abs() < 1e-15,
+            "{} != {}",
+            expected,
+            actual
+        );
+    }
+}
+
+#[test]
+fn independent_sampler_draws_the_sample_stream() {
+    let mut sampler = IndependentSampler::for_sample(3, 10, 20, 4);
+    let mut rng = random::sample_rng(3, 10, 20, 4);
+    for _ in 0..8 {
+        assert_eq!(sampler.next_u64(), rng.next_u64());
+    }
+}
+
+#[test]
+fn sampler_is_parsed_and_saved_with_the_camera() {
+    assert_eq!("halton".parse::<SamplerKind>(), Ok(SamplerKind::Halton));
+    assert_eq!("sobol".parse::<SamplerKind>(), Ok(SamplerKind::Sobol));
+    assert!("stratified".parse::<SamplerKind>().is_err());
+    assert_eq!(SamplerKind::default(), SamplerKind::Independent);
+
+    let mut scene = scenes::build("quads").unwrap();
+    assert!(!serde_json::to_string(&scene.describe().unwrap())
+        .unwrap()
+        .contains("sampler"));
+    scene.camera.set_sampler(SamplerKind::Halton);
+    let desc = scene.describe().unwrap();
+    assert_eq!(desc.camera.sampler, SamplerKind::Halton);
+    assert_eq!(desc.build().unwrap().camera.sampler, SamplerKind::Halton);
+    assert!(!desc.camera.blue_noise);
+
+    scene.camera.set_blue_noise(true);
+    let desc = scene.describe().unwrap();
+    assert!(desc.camera.blue_noise);
+    assert!(desc.build().unwrap().camera.blue_noise);
+}
+
+/* === Blue Noise === */
+
+#[test]
+fn blue_noise_mask_holds_every_rank_once() {
+    let size = BLUE_NOISE_SIZE as i32;
+    let count = (size * size) as f64;
+    let mut values: Vec<f64> = (0..size)
+        .flat_map(|y| (0..size).map(move |x| blue_noise(x, y)))
+        .collect();
+    values.sort_by(f64::total_cmp);
+    for (rank, value) in values.into_iter().enumerate() {
+        assert_eq!(value, (rank as f64 + 0.5) / count);
+    }
+}
+
+#[test]
+fn blue_noise_mask_tiles() {
+    let size = BLUE_NOISE_SIZE as i32;
+    for (x, y) in [(0, 0), (5, 17), (63, 1)] {
+        assert_eq!(blue_noise(x, y), blue_noise(x + size, y - 3 * size));
+    }
+}
+
+/// The mean distance around [0, 1) between the first dimension drawn by
+/// horizontally neighboring pixels, which is 1/4 for independent values.
+fn neighbor_difference(first: impl Fn(i32, i32) -> f64) -> f64 {
+    let size = BLUE_NOISE_SIZE as i32;
+    let mut total = 0.;
+    for y in 0..size {
+        for x in 0..size {
+            let d = (first(x, y) - first(x + 1, y)).abs();
+            total += d.min(1. - d);
+        }
+    }
+    total / (size * size) as f64
+}
+
+#[test]
+fn dithered_pixels_draw_values_far_from_their_neighbors() {
+    let white = [
+        neighbor_difference(|x, y| IndependentSampler::for_sample(7, x, y, 0).next_1d()),
+        neighbor_difference(|x, y| HaltonSampler::for_sample(7, x, y, 0).next_1d()),
+    ];
+    let blue = [
+        neighbor_difference(|x, y| IndependentSampler::dithered(7, x, y, 0).next_1d()),
+        neighbor_difference(|x, y| HaltonSampler::dithered(7, x, y, 0).next_1d()),
+    ];
+    for (white, blue) in white.into_iter().zip(blue) {
+        assert!(white < 0.265, "white noise differs by {}", white);
+        assert!(blue > 0.285, "blue noise differs by {}", blue);
+    }
+}
+
+#[test]
+fn blue_noise_renders_are_deterministic() {
+    for sampler in [
+        SamplerKind::Independent,
+        SamplerKind::Halton,
+        SamplerKind::Sobol,
+    ] {
+        let first = render(sampler, true, 12, 4, 3);
+        let second = render(sampler, true, 12, 4, 3);
+        assert_eq!(rms_error(&first, &second), 0.);
+        assert!(rms_error(&first, &render(sampler, false, 12, 4, 3)) > 0.);
+    }
+}
+
+/// How much of the difference between two renders survives a 3×3 box blur,
+/// which is about 1/3 for independent noise and less for blue noise.
+fn blurred_fraction(a: &ImageBuffer, b: &ImageBuffer) -> f64 {
+    let (w, h) = (a.width, a.height);
+    let difference: Vec<Vec3> = a
+        .pixels
+        .iter()
+        .zip(&b.pixels)
+        .map(|(a, b)| *a - *b)
+        .collect();
+    let mut blurred = 0.;
+    let mut raw = 0.;
+    for y in 1..h - 1 {
+        for x in 1..w - 1 {
+            let mut sum = Vec3(0., 0., 0.);
+            for (dx, dy) in (0..3).flat_map(|dy| (0..3).map(move |dx| (dx, dy))) {
+                sum += difference[(y + dy - 1) * w + x + dx - 1];
+            }
+            blurred += (sum / 9.).length_squared();
+            raw += difference[y * w + x].length_squared();
+        }
+    }
+    (blurred / raw).sqrt()
+}
+
+#[test]
+fn blue_noise_leaves_less_low_frequency_noise() {
+    for sampler in [
+        SamplerKind::Independent,
+        SamplerKind::Halton,
+        SamplerKind::Sobol,
+    ] {
+        let fraction = |blue_noise| -> f64 {
+            [(1, 2), (3, 4)]
+                .iter()
+                .map(|&(a, b)| {
+                    blurred_fraction(
+                        &render(sampler, blue_noise, 48, 1, a),
+                        &render(sampler, blue_noise, 48, 1, b),
+                    )
+                })
+                .sum()
+        };
+        let (white, blue) = (fraction(false), fraction(true));
+        assert!(
+            blue < 0.9 * white,
+            "{}: blue noise keeps {} after blurring vs {}",
+            sampler,
+            blue,
+            white
+        );
+    }
+}
+
+/* === Convergence === */
+
+/// A diffuse sphere on a floor under a white sky: one or two bounces, where
+/// well-spread samples pay off most.
+fn render(
+    sampler: SamplerKind,
+    blue_noise: bool,
+    width: i32,
+    samples: i32,
+    seed: u64,
+) -> ImageBuffer {
+    let gray = Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)));
+    let mut world = HittableList::new();
+    world.add(Parallelogram::new(
+        point(-5., 0., -5.),
+        (Vec3(10., 0., 0.), Vec3(0., 0., 10.)),
+        gray.clone(),
+    ));
+    world.add(Sphere::new(point(0., 1., 0.), 1., gray));
+    let mut camera = Camera::new(
+        1.0,
+        width,
+        40.,
+        point(0., 2., 5.),
+        point(0., 0.8, 0.),
+        Vec3(0., 1., 0.),
+        samples,
+        4,
+    );
+    camera
+        .set_background(color(1., 1., 1.))
+        .set_sampler(sampler)
+        .set_blue_noise(blue_noise)
+        .set_seed(Some(seed));
+    camera.render_image(&world, &LightList::new())
+}
+
+fn rms_error(image: &ImageBuffer, reference: &ImageBuffer) -> f64 {
+    let total: f64 = image
+        .pixels
+        .iter()
+        .zip(&reference.pixels)
+        .map(|(a, b)| (*a - *b).length_squared())
+        .sum();
+    (total / image.pixels.len() as f64).sqrt()
+}
+
+#[test]
+fn halton_converges_faster_at_equal_samples() {
+    let reference = render(SamplerKind::Independent, false, 12, 1024, 1000);
+    let error = |sampler| -> f64 {
+        (1..=3)
+            .map(|seed| rms_error(&render(sampler, false, 12, 16, seed), &reference))
+            .sum()
+    };
+    let independent = error(SamplerKind::Independent);
+    let halton = error(SamplerKind::Halton);
+    assert!(
+        halton < 0.8 * independent,
+        "halton error {} vs independent {}",
+        halton,
+        independent
+    );
+}
+
+#[test]
+fn sobol_converges_faster_at_equal_samples() {
+    let reference = render(SamplerKind::Independent, false, 12, 1024, 1000);
+    let error = |sampler| -> f64 {
+        (1..=3)
+            .map(|seed| rms_error(&render(sampler, false, 12, 16, seed), &reference))
+            .sum()
+    };
+    let independent = error(SamplerKind::Independent);
+    let sobol = error(SamplerKind::Sobol);
+    assert!(
+        sobol < 0.8 * independent,
+        "sobol error {} vs independent {}",
+        sobol,
+        independent
+    );
+}
@@ -0,0 +1,112 @@
+//! The example scene files describe the same scenes as the built-in
+//! constructors they were written from, and scenes saved back to files load
+//! as the scenes they were saved from.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use ray_tracer::{
+    scenes::{self, Scene},
+    BoundingBox, Hittable,
+};
+
+fn temp_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ray-tracer-scene-files-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+/// A hash of the exact bits of a small seeded render.
+fn checksum(scene: &mut Scene) -> u64 {
+    scene
+        .camera
+        .set_image_width(24)
+        .set_aa_samples(4)
+        .set_max_depth(6)
+        .set_seed(Some(7));
+    let image = scene.render_image();
+    let mut hasher = DefaultHasher::new();
+    for pixel in &image.pixels {
+        [pixel.0, pixel.1, pixel.2]
+            .map(f64::to_bits)
+            .hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Asserts `a` and `b` agree to within `tolerance` on every axis.
+fn assert_same_bounds(a: BoundingBox, b: BoundingBox, tolerance: f64) {
+    for axis in 0..3 {
+        let (a, b) = (a.intervals[axis], b.intervals[axis]);
+        assert!(
+            (a.start - b.start).abs() < tolerance && (a.end - b.end).abs() < tolerance,
+            "axis {}: {:?} vs {:?}",
+            axis,
+            a,
+            b
+        );
+    }
+}
+
+fn assert_matches(path: &str, built: Scene) {
+    let loaded = Scene::from_path(Path::new(path)).unwrap_or_else(|e| panic!("{}", e));
+    assert_eq!(
+        loaded.world.primitive_count(),
+        built.world.primitive_count(),
+        "{} holds a different number of objects",
+        path
+    );
+    assert_eq!(
+        loaded.lights.area.objects.len(),
+        built.lights.area.objects.len()
+    );
+    assert_same_bounds(loaded.bounds(), built.bounds(), 1e-6);
+    assert_eq!(loaded.camera.image_width, built.camera.image_width);
+    assert_eq!(loaded.camera.image_height(), built.camera.image_height());
+    assert!((loaded.camera.look_from - built.camera.look_from).length() < 1e-9);
+    assert!((loaded.camera.look_at - built.camera.look_at).length() < 1e-9);
+}
+
+#[test]
+fn the_cornell_box_file_matches_the_built_in_scene() {
+    assert_matches("resources/scenes/cornell_box.toml", scenes::cornell_box());
+}
+
+#[test]
+fn the_material_spheres_file_matches_the_built_in_scene() {
+    assert_matches(
+        "resources/scenes/material_spheres.json",
+        scenes::material_spheres(),
+    );
+}
+
+/// Describes `scene`, saves it as `name`, and checks the scene loaded back
+/// holds the same objects and renders the same image.
+fn assert_round_trips(mut scene: Scene, name: &str) {
+    let path = temp_path(name);
+    scene.describe().unwrap().save(&path).unwrap();
+    let mut loaded = Scene::from_path(&path).unwrap_or_else(|e| panic!("{}", e));
+    assert_eq!(
+        loaded.world.primitive_count(),
+        scene.world.primitive_count()
+    );
+    assert_same_bounds(loaded.bounds(), scene.bounds(), 1e-9);
+    assert_eq!(checksum(&mut loaded), checksum(&mut scene), "{}", name);
+}
+
+#[test]
+fn a_loaded_scene_round_trips_through_a_file() {
+    let path = Path::new("resources/scenes/cornell_box.toml");
+    assert_round_trips(Scene::from_path(path).unwrap(), "cornell_box.json");
+    assert_round_trips(Scene::from_path(path).unwrap(), "cornell_box.toml");
+}
+
+#[test]
+fn a_generated_scene_round_trips_through_a_file() {
+    assert_round_trips(scenes::random_spheres(3), "random_spheres.json");
+    assert_round_trips(scenes::random_spheres(3), "random_spheres.toml");
+}
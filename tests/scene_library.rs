@@ -0,0 +1,179 @@
+//! Scene files' named textures and materials: built once and shared by every
+//! object naming them, alongside one-offs defined in place.
+
+use std::sync::Arc;
+
+use ray_tracer::{
+    scenes::{SceneDesc, SceneError},
+    AssetResolver,
+};
+
+/// A scene of ten spheres in a row, all of the material "white".
+fn ten_white_spheres() -> SceneDesc {
+    let mut text = String::from(
+        r#"
+        [textures]
+        stripes = { type = "checker", scale = 0.5, odd = [0.1, 0.1, 0.1], even = [0.9, 0.9, 0.9] }
+
+        [materials]
+        white = { type = "lambertian", albedo = [0.8, 0.8, 0.8] }
+        striped = { type = "plastic", texture = "stripes", specular = 0.5, roughness = 0.2 }
+        "#,
+    );
+    for i in 0..10 {
+        text += &format!(
+            "[[objects]]\ntype = \"sphere\"\ncenter = [{}.0, 0.0, 0.0]\nradius = 0.4\nmaterial = \"white\"\n",
+            i
+        );
+    }
+    toml::from_str(&text).unwrap()
+}
+
+fn build_error(text: &str) -> SceneError {
+    let desc: SceneDesc = toml::from_str(text).unwrap();
+    desc.build().err().expect("the scene file should not build")
+}
+
+#[test]
+fn objects_naming_a_material_share_one() {
+    let desc = ten_white_spheres();
+    let resolver = AssetResolver::new();
+    let library = desc.library(&resolver).unwrap();
+    assert_eq!(Arc::strong_count(&library.materials["white"]), 1);
+    let objects: Vec<_> = desc
+        .objects
+        .iter()
+        .map(|object| library.build_object("object", object).unwrap())
+        .collect();
+    assert_eq!(objects.len(), 10);
+    assert_eq!(Arc::strong_count(&library.materials["white"]), 11);
+
+    // Saving the scene keeps the one material one.
+    let scene = desc.build().unwrap();
+    let saved = scene.describe().unwrap();
+    assert_eq!(saved.materials.len(), 1);
+}
+
+#[test]
+fn materials_share_the_textures_they_name() {
+    let desc = ten_white_spheres();
+    let resolver = AssetResolver::new();
+    let library = desc.library(&resolver).unwrap();
+    // The library's own, and the striped plastic's.
+    assert_eq!(Arc::strong_count(&library.textures["stripes"]), 2);
+}
+
+#[test]
+fn one_offs_are_defined_in_place() {
+    let desc: SceneDesc = toml::from_str(
+        r#"
+        [[objects]]
+        type = "sphere"
+        center = [0.0, 0.0, 0.0]
+        radius = 1.0
+        material = { type = "lambertian", texture = { type = "noise", scale = 4.0 } }
+
+        [[objects]]
+        type = "quad"
+        corner = [0.0, 0.0, -2.0]
+        u = [1.0, 0.0, 0.0]
+        v = [0.0, 1.0, 0.0]
+        material = { type = "metal", albedo = [0.9, 0.9, 0.9] }
+        "#,
+    )
+    .unwrap();
+    let scene = desc.build().unwrap();
+    assert_eq!(scene.world.objects.len(), 2);
+
+    // A one-off that is wrong says why, not just that it isn't a name.
+    let error = toml::from_str::<SceneDesc>(
+        r#"
+        [[objects]]
+        type = "sphere"
+        center = [0.0, 0.0, 0.0]
+        radius = 1.0
+        material = { type = "velvet" }
+        "#,
+    )
+    .err()
+    .expect("an unknown material type should not parse");
+    assert!(error.to_string().contains("velvet"), "{}", error);
+}
+
+#[test]
+fn undefined_names_are_errors() {
+    let error = build_error(
+        r#"
+        [[objects]]
+        type = "sphere"
+        center = [0.0, 0.0, 0.0]
+        radius = 1.0
+        material = "missing"
+        "#,
+    );
+    assert_eq!(error.to_string(), "objects[0]: unknown material 'missing'");
+
+    let error = build_error(
+        r#"
+        objects = []
+        [materials]
+        red = { type = "lambertian", texture = "paint" }
+        "#,
+    );
+    assert_eq!(error.to_string(), "materials.red: unknown texture 'paint'");
+
+    let error = build_error(
+        r#"
+        objects = []
+        [materials]
+        crimson = "red"
+        "#,
+    );
+    assert!(
+        matches!(error, SceneError::UnknownMaterial { .. }),
+        "{}",
+        error
+    );
+    assert_eq!(
+        error.to_string(),
+        "materials.crimson: unknown material 'red'"
+    );
+}
+
+#[test]
+fn names_may_alias_but_not_in_a_cycle() {
+    let desc: SceneDesc = toml::from_str(
+        r#"
+        objects = []
+        [materials]
+        base = { type = "lambertian", albedo = [0.5, 0.5, 0.5] }
+        floor = "base"
+        walls = "floor"
+        "#,
+    )
+    .unwrap();
+    let resolver = AssetResolver::new();
+    let library = desc.library(&resolver).unwrap();
+    assert!(Arc::ptr_eq(
+        &library.materials["base"],
+        &library.materials["walls"]
+    ));
+
+    let error = build_error(
+        r#"
+        objects = []
+        [textures]
+        a = "b"
+        b = "c"
+        c = "b"
+        "#,
+    );
+    match &error {
+        SceneError::CyclicReference { names, .. } => assert_eq!(names, &["b", "c"]),
+        other => panic!("expected a cycle, got {}", other),
+    }
+    assert_eq!(
+        error.to_string(),
+        "textures.c: the names refer to each other in a cycle, b -> c -> b"
+    );
+}
@@ -0,0 +1,73 @@
+//! Every registered scene builds, holding the objects it is meant to and
+//! bounds that contain them.
+
+use ray_tracer::{scenes, Hittable};
+
+/// Each registered scene and the primitives in its world; see
+/// `Hittable::primitive_count`.
+const EXPECTED: &[(&str, usize)] = &[
+    ("material_spheres", 5),
+    ("book2_final", 3409),
+    ("random_spheres", 480),
+    ("asteroid_field", 242),
+    ("checkered_spheres", 2),
+    ("earthmap", 1),
+    ("earth_environment", 3),
+    ("backplate", 2),
+    ("quads", 5),
+    ("quads_orthographic", 5),
+    ("planars", 5),
+    ("obj_mesh", 7645),
+    ("simple_light", 3),
+    ("sunlit_spheres", 4),
+    ("terrain", 51200),
+    ("sky_boxes", 19),
+    ("window_room", 22),
+    ("spotlight_fog", 9),
+    ("ceiling_lights", 7),
+    ("distance_fog", 25),
+    ("bokeh", 25),
+    ("motion_blur", 18),
+    ("cornell_box", 18),
+    ("cornell_cutaway", 18),
+    ("cornell_panorama", 18),
+    ("cornell_glass", 8),
+    ("cornell_plume", 7),
+    ("cornell_smoke", 18),
+];
+
+#[test]
+fn the_registry_lists_every_scene_once() {
+    let names: Vec<_> = scenes::list().iter().map(|entry| entry.name).collect();
+    let expected: Vec<_> = EXPECTED.iter().map(|(name, _)| *name).collect();
+    assert_eq!(names, expected);
+    for name in names {
+        assert_eq!(scenes::by_name(name).map(|entry| entry.name), Some(name));
+    }
+    assert!(scenes::by_name("no_such_scene").is_none());
+}
+
+#[test]
+fn every_scene_builds_with_its_objects() {
+    for &(name, primitives) in EXPECTED {
+        let scene = scenes::build(name).unwrap_or_else(|e| panic!("{}: {}", name, e));
+        assert_eq!(scene.world.primitive_count(), primitives, "{}", name);
+        for axis in scene.bounds().intervals {
+            assert!(
+                axis.start.is_finite() && axis.end.is_finite() && axis.start < axis.end,
+                "{}: {:?}",
+                name,
+                scene.bounds()
+            );
+        }
+        let issues = scene.validate();
+        assert!(
+            issues
+                .iter()
+                .all(|issue| issue.severity != scenes::Severity::Error),
+            "{}: {:?}",
+            name,
+            issues
+        );
+    }
+}
@@ -0,0 +1,651 @@
+//! Intersection tests for every shape: exact hits and misses, `t_range`
+//! bounds, front-face flipping, UVs, and bounds containing the surface.
+
+use std::sync::Arc;
+
+use ray_tracer::{
+    color, parallelepiped, point, BoundingBox, HitRecord, Hittable, IndependentSampler, Interval,
+    Lambertian, Material, Planar, Plane, Point, Ray, Vec3,
+};
+
+const EPSILON: f64 = 1e-9;
+
+fn material() -> Arc<dyn Material> {
+    Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)))
+}
+
+fn ray(origin: Point, direction: Vec3) -> Ray {
+    Ray {
+        origin,
+        direction,
+        time: 0.0,
+    }
+}
+
+/// Only media draw random numbers; everything else ignores the generator.
+fn rng() -> IndependentSampler {
+    IndependentSampler::new(0)
+}
+
+fn forward() -> Interval {
+    Interval::new(0.001, f64::INFINITY)
+}
+
+fn assert_close(actual: Vec3, expected: Vec3) {
+    assert!(
+        (actual - expected).length() < EPSILON,
+        "expected {:?}, got {:?}",
+        expected,
+        actual
+    );
+}
+
+/// Checks t, point, normal, and facing of a hit.
+fn assert_hit(record: Option<HitRecord>, t: f64, point: Point, normal: Vec3, front_face: bool) {
+    let record = record.expect("expected a hit");
+    assert!(
+        (record.t - t).abs() < EPSILON,
+        "expected t = {}, got {}",
+        t,
+        record.t
+    );
+    assert_close(record.point, point);
+    assert_close(record.normal, normal);
+    assert_eq!(record.front_face, front_face);
+}
+
+fn assert_uv(record: Option<HitRecord>, u: f64, v: f64) {
+    let record = record.expect("expected a hit");
+    assert!(
+        (record.u - u).abs() < EPSILON && (record.v - v).abs() < EPSILON,
+        "expected uv ({}, {}), got ({}, {})",
+        u,
+        v,
+        record.u,
+        record.v
+    );
+}
+
+/// Checks that `bounds` contains every point, up to rounding.
+fn assert_bounds_contain(bounds: BoundingBox, points: impl IntoIterator<Item = Point>) {
+    for p in points {
+        for axis in 0..3 {
+            assert!(
+                bounds.intervals[axis].expand(EPSILON).contains(p[axis]),
+                "{:?} is outside {:?}",
+                p,
+                bounds
+            );
+        }
+    }
+}
+
+/// Points spread over the unit sphere.
+fn sphere_directions() -> Vec<Vec3> {
+    let mut directions = Vec::new();
+    for i in 0..12 {
+        for j in 0..=6 {
+            let (phi, theta) = (
+                i as f64 * std::f64::consts::PI / 6.0,
+                j as f64 * std::f64::consts::PI / 6.0,
+            );
+            directions.push(Vec3(
+                theta.sin() * phi.cos(),
+                theta.cos(),
+                theta.sin() * phi.sin(),
+            ));
+        }
+    }
+    directions
+}
+
+mod sphere {
+    use super::*;
+    use ray_tracer::Sphere;
+
+    fn sphere() -> Sphere {
+        Sphere::new(point(0., 0., -5.), 1., material())
+    }
+
+    #[test]
+    fn hits_the_near_side() {
+        let sphere = sphere();
+        let hit = sphere.hit(
+            &ray(point(0., 0., 0.), Vec3(0., 0., -1.)),
+            forward(),
+            &mut rng(),
+        );
+        assert_hit(hit, 4., point(0., 0., -4.), Vec3(0., 0., 1.), true);
+    }
+
+    #[test]
+    fn flips_the_normal_from_inside() {
+        let sphere = sphere();
+        let hit = sphere.hit(
+            &ray(point(0., 0., -5.), Vec3(0., 0., -1.)),
+            forward(),
+            &mut rng(),
+        );
+        assert_hit(hit, 1., point(0., 0., -6.), Vec3(0., 0., 1.), false);
+    }
+
+    #[test]
+    fn misses() {
+        let sphere = sphere();
+        let hit = sphere.hit(
+            &ray(point(0., 0., 0.), Vec3(0., 1., 0.)),
+            forward(),
+            &mut rng(),
+        );
+        assert!(hit.is_none());
+        let hit = sphere.hit(
+            &ray(point(1.5, 0., 0.), Vec3(0., 0., -1.)),
+            forward(),
+            &mut rng(),
+        );
+        assert!(hit.is_none());
+    }
+
+    #[test]
+    fn respects_t_range() {
+        let sphere = sphere();
+        let r = ray(point(0., 0., 0.), Vec3(0., 0., -1.));
+        assert!(sphere
+            .hit(&r, Interval::new(0.001, 3.999), &mut rng())
+            .is_none());
+        assert!(sphere
+            .hit(&r, Interval::new(6.001, 10.), &mut rng())
+            .is_none());
+        // Past the near root, the far side is the next hit.
+        let hit = sphere.hit(&r, Interval::new(4.5, 10.), &mut rng());
+        assert_hit(hit, 6., point(0., 0., -6.), Vec3(0., 0., 1.), false);
+    }
+
+    #[test]
+    fn uv_at_poles_and_equator() {
+        let unit = Sphere::new(point(0., 0., 0.), 1., material());
+        let toward = |from: Point| unit.hit(&ray(from, -from), forward(), &mut rng());
+        assert!((toward(point(0., 5., 0.)).unwrap().v - 1.).abs() < EPSILON);
+        assert!(toward(point(0., -5., 0.)).unwrap().v.abs() < EPSILON);
+        assert_uv(toward(point(5., 0., 0.)), 0.5, 0.5);
+        assert_uv(toward(point(0., 0., 5.)), 0.25, 0.5);
+        assert_uv(toward(point(0., 0., -5.)), 0.75, 0.5);
+    }
+
+    #[test]
+    fn bounds_contain_surface() {
+        let s = sphere();
+        assert_bounds_contain(
+            s.bound(),
+            sphere_directions().into_iter().map(|d| s.center + d),
+        );
+    }
+}
+
+mod parallelogram {
+    use super::*;
+    use ray_tracer::Parallelogram;
+
+    fn quad() -> Parallelogram {
+        Parallelogram::new(
+            point(0., 0., 0.),
+            (Vec3(2., 0., 0.), Vec3(0., 3., 0.)),
+            material(),
+        )
+    }
+
+    #[test]
+    fn hits_the_front() {
+        let quad = quad();
+        let hit = quad.hit(
+            &ray(point(1., 1., 5.), Vec3(0., 0., -1.)),
+            forward(),
+            &mut rng(),
+        );
+        assert_hit(hit, 5., point(1., 1., 0.), Vec3(0., 0., 1.), true);
+    }
+
+    #[test]
+    fn flips_the_normal_from_behind() {
+        let quad = quad();
+        let hit = quad.hit(
+            &ray(point(1., 1., -5.), Vec3(0., 0., 1.)),
+            forward(),
+            &mut rng(),
+        );
+        assert_hit(hit, 5., point(1., 1., 0.), Vec3(0., 0., -1.), false);
+    }
+
+    #[test]
+    fn misses() {
+        let down = Vec3(0., 0., -1.);
+        assert!(quad()
+            .hit(&ray(point(2.5, 1., 5.), down), forward(), &mut rng())
+            .is_none());
+        assert!(quad()
+            .hit(&ray(point(1., -0.5, 5.), down), forward(), &mut rng())
+            .is_none());
+        assert!(quad()
+            .hit(
+                &ray(point(1., 1., 5.), Vec3(1., 0., 0.)),
+                forward(),
+                &mut rng()
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn respects_t_range() {
+        let quad = quad();
+        let r = ray(point(1., 1., 5.), Vec3(0., 0., -1.));
+        assert!(quad
+            .hit(&r, Interval::new(0.001, 4.999), &mut rng())
+            .is_none());
+        assert!(quad
+            .hit(&r, Interval::new(5.001, 10.), &mut rng())
+            .is_none());
+    }
+
+    #[test]
+    fn uv_at_corners() {
+        let quad = quad();
+        let down = Vec3(0., 0., -1.);
+        let at = |x: f64, y: f64| quad.hit(&ray(point(x, y, 5.), down), forward(), &mut rng());
+        assert_uv(at(0., 0.), 0., 0.);
+        assert_uv(at(2., 0.), 1., 0.);
+        assert_uv(at(0., 3.), 0., 1.);
+        assert_uv(at(2., 3.), 1., 1.);
+        assert_uv(at(1., 1.5), 0.5, 0.5);
+    }
+
+    #[test]
+    fn bounds_contain_surface() {
+        let q = quad();
+        let points = (0..=4).flat_map(|i| {
+            (0..=4)
+                .map(move |j| point(0., 0., 0.) + Vec3(2. * i as f64 / 4., 3. * j as f64 / 4., 0.))
+        });
+        assert_bounds_contain(q.bound(), points);
+    }
+}
+
+mod triangle {
+    use super::*;
+    use ray_tracer::Triangle;
+
+    fn triangle() -> Triangle {
+        Triangle::new(
+            (point(0., 0., 0.), point(2., 0., 0.), point(0., 2., 0.)),
+            material(),
+        )
+    }
+
+    #[test]
+    fn hits_the_front() {
+        let triangle = triangle();
+        let hit = triangle.hit(
+            &ray(point(0.5, 0.5, 5.), Vec3(0., 0., -1.)),
+            forward(),
+            &mut rng(),
+        );
+        assert_hit(hit, 5., point(0.5, 0.5, 0.), Vec3(0., 0., 1.), true);
+    }
+
+    #[test]
+    fn flips_the_normal_from_behind() {
+        let triangle = triangle();
+        let hit = triangle.hit(
+            &ray(point(0.5, 0.5, -5.), Vec3(0., 0., 1.)),
+            forward(),
+            &mut rng(),
+        );
+        assert_hit(hit, 5., point(0.5, 0.5, 0.), Vec3(0., 0., -1.), false);
+    }
+
+    #[test]
+    fn misses_outside_the_hypotenuse() {
+        let triangle = triangle();
+        let hit = triangle.hit(
+            &ray(point(1.5, 1.5, 5.), Vec3(0., 0., -1.)),
+            forward(),
+            &mut rng(),
+        );
+        assert!(hit.is_none());
+    }
+
+    #[test]
+    fn respects_t_range() {
+        let triangle = triangle();
+        let r = ray(point(0.5, 0.5, 5.), Vec3(0., 0., -1.));
+        assert!(triangle
+            .hit(&r, Interval::new(0.001, 4.999), &mut rng())
+            .is_none());
+        assert!(triangle
+            .hit(&r, Interval::new(5.001, 10.), &mut rng())
+            .is_none());
+    }
+
+    #[test]
+    fn uv_are_barycentric() {
+        let triangle = triangle();
+        let down = Vec3(0., 0., -1.);
+        let at = |x: f64, y: f64| triangle.hit(&ray(point(x, y, 5.), down), forward(), &mut rng());
+        assert_uv(at(0., 0.), 0., 0.);
+        assert_uv(at(2., 0.), 1., 0.);
+        assert_uv(at(0., 2.), 0., 1.);
+        assert_uv(at(0.5, 0.5), 0.25, 0.25);
+    }
+
+    #[test]
+    fn bounds_contain_vertices() {
+        let t = triangle();
+        assert_bounds_contain(t.bound(), [t.vertex.0, t.vertex.1, t.vertex.2]);
+    }
+}
+
+mod planar {
+    use super::*;
+    use ray_tracer::{Parallelogram, Triangle};
+
+    #[test]
+    fn matches_the_wrapped_shapes() {
+        let r = ray(point(0.5, 0.5, 5.), Vec3(0., 0., -1.));
+        let triangle = Planar::Triangle(Triangle::new(
+            (point(0., 0., 0.), point(2., 0., 0.), point(0., 2., 0.)),
+            material(),
+        ));
+        let quad = Planar::Parallelogram(Parallelogram::new(
+            point(0., 0., 0.),
+            (Vec3(2., 0., 0.), Vec3(0., 2., 0.)),
+            material(),
+        ));
+        for shape in [triangle, quad] {
+            assert_hit(
+                shape.hit(&r, forward(), &mut rng()),
+                5.,
+                point(0.5, 0.5, 0.),
+                Vec3(0., 0., 1.),
+                true,
+            );
+            assert!(shape
+                .hit(&r, Interval::new(0.001, 4.), &mut rng())
+                .is_none());
+            assert_bounds_contain(shape.bound(), [point(0., 0., 0.), point(2., 0., 0.)]);
+        }
+    }
+}
+
+mod plane {
+    use super::*;
+
+    fn plane() -> Plane {
+        Plane::new(point(0., 0., 0.), Vec3(0., 1., 0.))
+    }
+
+    #[test]
+    fn hits_from_both_sides() {
+        let plane = plane();
+        let hit = plane.hit(
+            &ray(point(3., 5., 1.), Vec3(0., -1., 0.)),
+            forward(),
+            &mut rng(),
+        );
+        assert_hit(hit, 5., point(3., 0., 1.), Vec3(0., 1., 0.), true);
+        let hit = plane.hit(
+            &ray(point(3., -2., 1.), Vec3(0., 1., 0.)),
+            forward(),
+            &mut rng(),
+        );
+        assert_hit(hit, 2., point(3., 0., 1.), Vec3(0., -1., 0.), false);
+    }
+
+    #[test]
+    fn misses_parallel_and_receding_rays() {
+        assert!(plane()
+            .hit(
+                &ray(point(0., 1., 0.), Vec3(1., 0., 0.)),
+                forward(),
+                &mut rng()
+            )
+            .is_none());
+        assert!(plane()
+            .hit(
+                &ray(point(0., 1., 0.), Vec3(0., 1., 0.)),
+                forward(),
+                &mut rng()
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn respects_t_range() {
+        let plane = plane();
+        let r = ray(point(0., 5., 0.), Vec3(0., -1., 0.));
+        assert!(plane
+            .hit(&r, Interval::new(0.001, 4.999), &mut rng())
+            .is_none());
+    }
+}
+
+mod parallelepiped {
+    use super::*;
+
+    fn cuboid() -> Arc<dyn Hittable> {
+        parallelepiped(point(0., 0., 0.), point(1., 2., 3.), material())
+    }
+
+    #[test]
+    fn hits_the_nearest_face() {
+        let cuboid = cuboid();
+        let hit = cuboid.hit(
+            &ray(point(0.5, 1., 10.), Vec3(0., 0., -1.)),
+            forward(),
+            &mut rng(),
+        );
+        assert_hit(hit, 7., point(0.5, 1., 3.), Vec3(0., 0., 1.), true);
+        let hit = cuboid.hit(
+            &ray(point(-4., 1., 1.), Vec3(1., 0., 0.)),
+            forward(),
+            &mut rng(),
+        );
+        assert_hit(hit, 4., point(0., 1., 1.), Vec3(-1., 0., 0.), true);
+    }
+
+    #[test]
+    fn hits_the_inside_from_within() {
+        let cuboid = cuboid();
+        let hit = cuboid.hit(
+            &ray(point(0.5, 1., 1.5), Vec3(1., 0., 0.)),
+            forward(),
+            &mut rng(),
+        );
+        assert_hit(hit, 0.5, point(1., 1., 1.5), Vec3(-1., 0., 0.), false);
+    }
+
+    #[test]
+    fn misses_and_respects_t_range() {
+        let cuboid = cuboid();
+        let r = ray(point(0.5, 1., 10.), Vec3(0., 0., -1.));
+        assert!(cuboid
+            .hit(&r, Interval::new(0.001, 6.9), &mut rng())
+            .is_none());
+        assert!(cuboid
+            .hit(
+                &ray(point(2., 1., 10.), Vec3(0., 0., -1.)),
+                forward(),
+                &mut rng()
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn bounds_contain_corners() {
+        let corners = (0..8).map(|i| {
+            point(
+                (i & 1) as f64,
+                2. * ((i >> 1) & 1) as f64,
+                3. * ((i >> 2) & 1) as f64,
+            )
+        });
+        assert_bounds_contain(cuboid().bound(), corners);
+    }
+}
+
+mod translation {
+    use super::*;
+    use ray_tracer::{Sphere, Translation};
+
+    fn moved() -> Translation {
+        let sphere = Arc::new(Sphere::new(point(0., 0., 0.), 1., material()));
+        Translation::new(sphere, Vec3(0., 0., -5.))
+    }
+
+    #[test]
+    fn hits_the_moved_object() {
+        let moved = moved();
+        let hit = moved.hit(
+            &ray(point(0., 0., 0.), Vec3(0., 0., -1.)),
+            forward(),
+            &mut rng(),
+        );
+        assert_hit(hit, 4., point(0., 0., -4.), Vec3(0., 0., 1.), true);
+        let hit = moved.hit(
+            &ray(point(0., 0., -5.), Vec3(0., 0., -1.)),
+            forward(),
+            &mut rng(),
+        );
+        assert_hit(hit, 1., point(0., 0., -6.), Vec3(0., 0., 1.), false);
+    }
+
+    #[test]
+    fn misses_where_the_object_was() {
+        let moved = moved();
+        let hit = moved.hit(
+            &ray(point(0., 5., 0.), Vec3(0., -1., 0.)),
+            forward(),
+            &mut rng(),
+        );
+        assert!(hit.is_none());
+    }
+
+    #[test]
+    fn respects_t_range() {
+        let moved = moved();
+        let r = ray(point(0., 0., 0.), Vec3(0., 0., -1.));
+        assert!(moved
+            .hit(&r, Interval::new(0.001, 3.9), &mut rng())
+            .is_none());
+    }
+
+    #[test]
+    fn bounds_contain_surface() {
+        let center = point(0., 0., -5.);
+        assert_bounds_contain(
+            moved().bound(),
+            sphere_directions().into_iter().map(|d| center + d),
+        );
+    }
+}
+
+mod rotate_y {
+    use super::*;
+    use ray_tracer::{RotateY, Sphere};
+
+    /// A sphere at +x, turned a quarter turn so it ends up at -z.
+    fn turned() -> RotateY {
+        let sphere = Arc::new(Sphere::new(point(3., 0., 0.), 1., material()));
+        RotateY::new(sphere, 90.)
+    }
+
+    #[test]
+    fn hits_the_rotated_object() {
+        let turned = turned();
+        let hit = turned.hit(
+            &ray(point(0., 0., 0.), Vec3(0., 0., -1.)),
+            forward(),
+            &mut rng(),
+        );
+        assert_hit(hit, 2., point(0., 0., -2.), Vec3(0., 0., 1.), true);
+    }
+
+    #[test]
+    fn misses_where_the_object_was() {
+        let turned = turned();
+        let hit = turned.hit(
+            &ray(point(0., 0., 0.), Vec3(1., 0., 0.)),
+            forward(),
+            &mut rng(),
+        );
+        assert!(hit.is_none());
+    }
+
+    #[test]
+    fn respects_t_range() {
+        let turned = turned();
+        let r = ray(point(0., 0., 0.), Vec3(0., 0., -1.));
+        assert!(turned
+            .hit(&r, Interval::new(0.001, 1.9), &mut rng())
+            .is_none());
+    }
+
+    #[test]
+    fn bounds_contain_surface() {
+        let center = point(0., 0., -3.);
+        assert_bounds_contain(
+            turned().bound(),
+            sphere_directions().into_iter().map(|d| center + d),
+        );
+    }
+}
+
+mod constant_medium {
+    use super::*;
+    use ray_tracer::ConstantMedium;
+
+    const DENSITY: f64 = 0.5;
+
+    /// A medium filling the cube from -1 to 1.
+    fn fog() -> ConstantMedium {
+        let boundary = parallelepiped(point(-1., -1., -1.), point(1., 1., 1.), material());
+        ConstantMedium::from_color(boundary, DENSITY, color(1., 1., 1.))
+    }
+
+    #[test]
+    fn scatters_at_the_expected_rate() {
+        let mut rng = IndependentSampler::new(7);
+        let medium = fog();
+        let r = ray(point(0., 0., 5.), Vec3(0., 0., -1.));
+        let samples = 20_000;
+        let mut hits = 0;
+        for _ in 0..samples {
+            if let Some(record) = medium.hit(&r, forward(), &mut rng) {
+                hits += 1;
+                assert!(
+                    (4.0..=6.0).contains(&record.t),
+                    "t = {} is outside the cube",
+                    record.t
+                );
+            }
+        }
+        // Two units of fog at density 0.5 scatter 1 - e^-1 of the rays.
+        let expected = 1. - (-2. * DENSITY).exp();
+        let rate = hits as f64 / samples as f64;
+        assert!(
+            (rate - expected).abs() < 0.02,
+            "scatter rate {} != {}",
+            rate,
+            expected
+        );
+    }
+
+    #[test]
+    fn misses_outside_and_respects_t_range() {
+        let medium = fog();
+        let beside = ray(point(2., 0., 5.), Vec3(0., 0., -1.));
+        assert!((0..100).all(|_| medium.hit(&beside, forward(), &mut rng()).is_none()));
+        let through = ray(point(0., 0., 5.), Vec3(0., 0., -1.));
+        assert!((0..100).all(|_| medium
+            .hit(&through, Interval::new(0.001, 3.9), &mut rng())
+            .is_none()));
+    }
+}
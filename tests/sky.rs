@@ -0,0 +1,118 @@
+//! The procedural sun and sky: where the sun is at each hour, the colors of
+//! the sky and sunlight, and lighting scenes with them.
+
+use ray_tracer::{
+    scenes::{self, Scene},
+    Color, ImageBuffer, Sky, Vec3,
+};
+
+fn blueness(color: Color) -> f64 {
+    color.2 / color.0
+}
+
+fn mean(image: &ImageBuffer) -> Color {
+    image
+        .pixels
+        .iter()
+        .fold(Vec3(0., 0., 0.), |sum, &p| sum + p)
+        / image.pixels.len() as f64
+}
+
+#[test]
+fn the_sun_crosses_from_east_to_west() {
+    let morning = Sky::from_time_of_day(8.0, 3.0).sun_direction();
+    let noon = Sky::from_time_of_day(12.0, 3.0).sun_direction();
+    let afternoon = Sky::from_time_of_day(16.0, 3.0).sun_direction();
+    assert!(morning.x() > 0.5 && afternoon.x() < -0.5);
+    assert!(noon.x().abs() < 1e-9 && noon.z() > 0.0);
+    assert!((noon.y() - 60f64.to_radians().sin()).abs() < 1e-9);
+    assert!(morning.y() > 0.0 && morning.y() < noon.y());
+    assert!((morning.y() - afternoon.y()).abs() < 1e-9);
+    for hour in [0.0, 5.0, 7.0, 12.0, 19.0] {
+        let direction = Sky::from_time_of_day(hour, 3.0).sun_direction();
+        assert!((direction.length() - 1.0).abs() < 1e-9);
+    }
+    assert!(Sky::from_time_of_day(3.0, 3.0).sun_direction().y() < 0.0);
+}
+
+#[test]
+fn the_noon_sky_is_blue_above_and_paler_at_the_horizon() {
+    let sky = Sky::from_time_of_day(12.0, 3.0);
+    let zenith = sky.radiance(Vec3(0., 1., 0.));
+    let horizon = sky.radiance(Vec3(0., 0.05, -1.).unit());
+    assert!(blueness(zenith) > 2.0, "{:?}", zenith);
+    assert!(blueness(horizon) < blueness(zenith), "{:?}", horizon);
+    assert!(horizon.length() > 0.0);
+    // Toward the sun it is brighter than away from it.
+    let near_sun = sky.radiance((sky.sun_direction() + Vec3(0., 0.2, 0.)).unit());
+    assert!(near_sun.luminance() > zenith.luminance());
+    // Below the horizon is left to the scene.
+    assert_eq!(sky.radiance(Vec3(0., -0.2, 1.).unit()).length(), 0.0);
+    // And by midnight the sky is dark.
+    let night = Sky::from_time_of_day(0.0, 3.0);
+    assert_eq!(night.radiance(Vec3(0., 1., 0.)).length(), 0.0);
+}
+
+#[test]
+fn sunlight_reddens_and_dims_toward_sunset() {
+    let noon = Sky::from_time_of_day(12.0, 3.0).sun().unwrap();
+    let evening = Sky::from_time_of_day(17.5, 3.0).sun().unwrap();
+    assert!(blueness(evening.irradiance) < 0.5 * blueness(noon.irradiance));
+    assert!(evening.irradiance.luminance() < 0.5 * noon.irradiance.luminance());
+    // A white surface facing the noon sun is lit to about 1.
+    let lit = noon.irradiance.luminance() / std::f64::consts::PI;
+    assert!((0.7..1.3).contains(&lit), "{}", lit);
+    // The light travels away from the sun.
+    let sky = Sky::from_time_of_day(12.0, 3.0);
+    assert!((noon.direction + sky.sun_direction()).length() < 1e-9);
+    assert!(Sky::from_time_of_day(20.0, 3.0).sun().is_none());
+}
+
+#[test]
+fn hazier_air_pales_the_sky_and_dims_the_sun() {
+    let clear = Sky::from_time_of_day(10.0, 2.0);
+    let hazy = Sky::from_time_of_day(10.0, 8.0);
+    let up = Vec3(0., 1., 0.);
+    assert!(blueness(hazy.radiance(up)) < blueness(clear.radiance(up)));
+    let (clear_sun, hazy_sun) = (clear.sun().unwrap(), hazy.sun().unwrap());
+    assert!(hazy_sun.irradiance.luminance() < clear_sun.irradiance.luminance());
+
+    let mut dim = clear;
+    dim.strength = 0.5;
+    let ratio = dim.radiance(up).luminance() / clear.radiance(up).luminance();
+    assert!((ratio - 0.5).abs() < 1e-9);
+}
+
+fn tiny_boxes(hour: f64) -> Scene {
+    let mut scene = scenes::sky_boxes(hour);
+    scene
+        .camera
+        .set_image_width(16)
+        .set_aa_samples(4)
+        .set_max_depth(3)
+        .set_seed(Some(5));
+    scene
+}
+
+#[test]
+fn skies_light_scenes_and_replace_each_other() {
+    let mut scene = tiny_boxes(8.0);
+    assert_eq!(scene.lights.delta.len(), 1);
+    assert!(scene.lights.environment.is_some());
+    let morning = scene.fingerprint();
+    scene.set_sky(&Sky::from_time_of_day(12.0, 3.0));
+    assert_eq!(scene.lights.delta.len(), 1);
+    assert_ne!(scene.fingerprint(), morning);
+    scene.set_sky(&Sky::from_time_of_day(22.0, 3.0));
+    assert!(scene.lights.delta.is_empty());
+
+    let noon = mean(&tiny_boxes(12.0).render_image());
+    let evening = mean(&tiny_boxes(17.5).render_image());
+    assert!(
+        blueness(evening) < blueness(noon),
+        "{:?} {:?}",
+        evening,
+        noon
+    );
+    assert!(evening.luminance() < noon.luminance());
+}
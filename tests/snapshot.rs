@@ -0,0 +1,112 @@
+//! Snapshots of long renders: when they are taken, what they hold, and
+//! writing them so a viewer never sees half a file.
+
+use std::{fs, time::Duration};
+
+use ray_tracer::{
+    scenes::{self, Scene},
+    ImageBuffer, SamplerKind, SnapshotEvery,
+};
+
+fn tiny(samples: i32) -> Scene {
+    let mut scene = scenes::cornell_box();
+    scene
+        .camera
+        .set_image_width(10)
+        .set_aa_samples(samples)
+        .set_max_depth(4)
+        .set_seed(Some(3));
+    scene
+}
+
+#[test]
+fn the_finished_image_is_the_plain_render() {
+    for (sampler, blue_noise) in [
+        (SamplerKind::Independent, false),
+        (SamplerKind::Halton, true),
+    ] {
+        let mut scene = tiny(5);
+        scene.camera.set_sampler(sampler).set_blue_noise(blue_noise);
+        let image = scene.render_with_snapshots(SnapshotEvery::Passes(2), &mut |_| {});
+        assert_eq!(image.to_rgba8(), scene.render_image().to_rgba8());
+        assert_eq!(image.diff(&scene.render_image()).unwrap().max_error, 0.0);
+    }
+}
+
+#[test]
+fn snapshots_come_every_so_many_passes_but_not_at_the_end() {
+    let mut passes = Vec::new();
+    tiny(8).render_with_snapshots(SnapshotEvery::Passes(3), &mut |snapshot| {
+        assert_eq!(snapshot.accumulation.min_samples(), snapshot.passes);
+        passes.push(snapshot.passes);
+    });
+    assert_eq!(passes, [3, 6]);
+
+    passes.clear();
+    tiny(6).render_with_snapshots(SnapshotEvery::Passes(3), &mut |snapshot| {
+        passes.push(snapshot.passes)
+    });
+    assert_eq!(passes, [3]);
+
+    // A time already up is checked after every row.
+    let mut count = 0;
+    tiny(2).render_with_snapshots(
+        SnapshotEvery::Interval(Duration::from_nanos(1)),
+        &mut |_| count += 1,
+    );
+    assert_eq!(count, 2 * 10 - 1);
+}
+
+#[test]
+fn a_snapshot_is_the_render_with_fewer_samples() {
+    let mut snapshot = None;
+    tiny(6).render_with_snapshots(SnapshotEvery::Passes(4), &mut |s| {
+        snapshot = Some(s.accumulation.clone())
+    });
+    let report = snapshot
+        .unwrap()
+        .image()
+        .diff(&tiny(4).render_image())
+        .unwrap();
+    assert!(report.max_error < 1e-12, "{}", report);
+}
+
+#[test]
+fn intervals_parse_as_times_or_passes() {
+    for (text, every) in [
+        ("60s", SnapshotEvery::Interval(Duration::from_secs(60))),
+        ("500ms", SnapshotEvery::Interval(Duration::from_millis(500))),
+        ("5m", SnapshotEvery::Interval(Duration::from_secs(300))),
+        ("1h", SnapshotEvery::Interval(Duration::from_secs(3600))),
+        ("8passes", SnapshotEvery::Passes(8)),
+        ("1pass", SnapshotEvery::Passes(1)),
+    ] {
+        assert_eq!(text.parse::<SnapshotEvery>(), Ok(every), "{}", text);
+    }
+    for text in ["", "60", "s", "0s", "0passes", "1.5s", "-3s", "10x"] {
+        assert!(text.parse::<SnapshotEvery>().is_err(), "{}", text);
+    }
+}
+
+#[test]
+fn atomic_saves_leave_no_temporary_file() {
+    let dir = std::env::temp_dir().join(format!("ray-tracer-snapshot-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let image = tiny(1).render_image();
+    let path = dir.join("progress.ppm");
+    image.save_atomically(&path).unwrap();
+    image.save_atomically(&path).unwrap();
+    assert_eq!(
+        ImageBuffer::load(&path).unwrap().to_rgba8(),
+        image.to_rgba8()
+    );
+    image
+        .save_atomically(&dir.join("progress.bmp"))
+        .expect_err("not a format we write");
+    let names: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(names, ["progress.ppm"]);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,167 @@
+//! Stereo renders: where the eyes sit, how their views are composed, and the
+//! disparity that makes near objects stand out from far ones.
+
+use std::sync::Arc;
+
+use ray_tracer::{
+    color, point,
+    scenes::{self, Scene},
+    Camera, CameraError, DiffuseLight, Eye, HittableList, ImageBuffer, Sphere, StereoMode,
+    StereoSettings, Vec3,
+};
+
+const IPD: f64 = 0.2;
+
+fn camera(mode: StereoMode, converge: bool) -> Camera {
+    let mut camera = Camera::new(
+        2.0,
+        64,
+        40.,
+        point(0., 0., 5.),
+        point(0., 0., 0.),
+        Vec3(0., 1., 0.),
+        4,
+        4,
+    );
+    camera.set_seed(Some(3)).set_stereo(Some(StereoSettings {
+        ipd: IPD,
+        mode,
+        converge,
+    }));
+    camera
+}
+
+/// A small light close to the camera in the top half of the view and a large
+/// one far away in the bottom half, on black.
+fn scene(camera: Camera) -> Scene {
+    let light = Arc::new(DiffuseLight::from(color(1., 1., 1.)));
+    let mut world = HittableList::new();
+    world.add(Sphere::new(point(0., 0.4, 3.), 0.15, light.clone()));
+    world.add(Sphere::new(point(0., -3., -15.), 1.2, light));
+    Scene::new(camera, world)
+}
+
+/// The brightness-weighted mean x of columns `x0..x0 + width` and rows
+/// `rows`, in one channel.
+fn centroid(
+    image: &ImageBuffer,
+    x0: usize,
+    width: usize,
+    rows: std::ops::Range<usize>,
+    channel: fn(Vec3) -> f64,
+) -> f64 {
+    let (mut sum, mut total) = (0.0, 0.0);
+    for y in rows {
+        for x in 0..width {
+            let value = channel(image.get(x0 + x, y));
+            sum += value * x as f64;
+            total += value;
+        }
+    }
+    assert!(total > 0.0, "nothing bright in the region");
+    sum / total
+}
+
+#[test]
+fn eyes_sit_half_the_separation_apart() {
+    let parallel = camera(StereoMode::SideBySide, false);
+    let left = parallel.eye(Eye::Left);
+    let right = parallel.eye(Eye::Right);
+    assert!((left.look_from - point(-IPD / 2., 0., 5.)).length() < 1e-12);
+    assert!((right.look_from - point(IPD / 2., 0., 5.)).length() < 1e-12);
+    assert!((left.look_at - point(-IPD / 2., 0., 0.)).length() < 1e-12);
+    assert!(left.stereo.is_none() && right.stereo.is_none());
+
+    let converged = camera(StereoMode::SideBySide, true);
+    assert!((converged.eye(Eye::Left).look_at - point(0., 0., 0.)).length() < 1e-12);
+    assert!((converged.eye(Eye::Right).look_at - point(0., 0., 0.)).length() < 1e-12);
+}
+
+#[test]
+fn side_by_side_puts_the_eyes_next_to_each_other() {
+    let scene = scene(camera(StereoMode::SideBySide, false));
+    let image = scene.render_image();
+    let left = scene
+        .camera
+        .eye(Eye::Left)
+        .render_image(&scene.world, &scene.lights);
+    let right = scene
+        .camera
+        .eye(Eye::Right)
+        .render_image(&scene.world, &scene.lights);
+    assert_eq!((image.width, image.height), (128, 32));
+    for y in 0..32 {
+        for x in 0..64 {
+            assert!((image.get(x, y) - left.get(x, y)).length() == 0.0);
+            assert!((image.get(64 + x, y) - right.get(x, y)).length() == 0.0);
+        }
+    }
+}
+
+#[test]
+fn anaglyph_splits_the_channels_between_the_eyes() {
+    let scene = scene(camera(StereoMode::Anaglyph, false));
+    let image = scene.render_image();
+    let left = scene
+        .camera
+        .eye(Eye::Left)
+        .render_image(&scene.world, &scene.lights);
+    let right = scene
+        .camera
+        .eye(Eye::Right)
+        .render_image(&scene.world, &scene.lights);
+    assert_eq!((image.width, image.height), (64, 32));
+    for ((pixel, l), r) in image.pixels.iter().zip(&left.pixels).zip(&right.pixels) {
+        assert_eq!((pixel.0, pixel.1, pixel.2), (l.0, r.1, r.2));
+    }
+}
+
+#[test]
+fn near_objects_shift_more_between_the_eyes() {
+    let image = scene(camera(StereoMode::SideBySide, false)).render_image();
+    let red = |c: Vec3| c.0;
+    let near = centroid(&image, 0, 64, 0..16, red) - centroid(&image, 64, 64, 0..16, red);
+    let far = centroid(&image, 0, 64, 16..32, red) - centroid(&image, 64, 64, 16..32, red);
+    // The left eye sees everything further right, and near things most.
+    assert!(near > 3.0, "near disparity {}", near);
+    assert!(far > 0.0 && far < 1.0, "far disparity {}", far);
+
+    // Anaglyphs carry the same shift between red and cyan.
+    let image = scene(camera(StereoMode::Anaglyph, false)).render_image();
+    let shift = centroid(&image, 0, 64, 0..16, red) - centroid(&image, 0, 64, 0..16, |c| c.2);
+    assert!((shift - near).abs() < 0.5, "{} vs {}", shift, near);
+}
+
+#[test]
+fn eye_separation_must_be_positive() {
+    let mut camera = camera(StereoMode::Anaglyph, false);
+    for ipd in [0.0, -1.0, f64::NAN] {
+        camera.set_stereo(Some(StereoSettings {
+            ipd,
+            mode: StereoMode::Anaglyph,
+            converge: false,
+        }));
+        assert!(matches!(
+            camera.validate(),
+            Err(CameraError::InvalidEyeSeparation(_))
+        ));
+    }
+}
+
+#[test]
+fn stereo_is_saved_with_the_camera() {
+    let mut scene = scenes::build("cornell_box").unwrap();
+    let stereo = StereoSettings {
+        ipd: 40.0,
+        mode: StereoMode::Anaglyph,
+        converge: true,
+    };
+    scene.camera.set_stereo(Some(stereo));
+    let text = toml::to_string(&scene.describe().unwrap()).unwrap();
+    assert!(text.contains("[camera.stereo]"), "{}", text);
+    let loaded: scenes::SceneDesc = toml::from_str(&text).unwrap();
+    assert_eq!(loaded.build().unwrap().camera.stereo, Some(stereo));
+
+    let plain = scenes::build("cornell_box").unwrap().describe().unwrap();
+    assert!(!toml::to_string(&plain).unwrap().contains("stereo"));
+}
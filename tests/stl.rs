@@ -0,0 +1,61 @@
+//! Loading STL files with `load_stl`: the facets' corners are welded back
+//! into shared vertices.
+
+use std::{path::Path, sync::Arc};
+
+use ray_tracer::{color, load_model, load_stl, AssetResolver, Lambertian, TriangleMesh};
+
+/// A binary unit cube of 12 facets whose header starts with "solid", as some
+/// exporters write it. Every other facet's corners are off by a few
+/// millionths, so they only meet the others' once welded.
+const CUBE: &str = "tests/fixtures/cube.stl";
+
+fn load() -> TriangleMesh {
+    load_stl(
+        Path::new(CUBE),
+        Arc::new(Lambertian::from(color(0.5, 0.5, 0.5))),
+        None,
+    )
+    .unwrap()
+}
+
+#[test]
+fn the_binary_cube_loads_as_twelve_triangles() {
+    let mesh = load();
+    assert_eq!(mesh.triangle_count(), 12);
+    for axis in mesh.bounds().intervals {
+        assert!(
+            axis.start.abs() < 1e-3 && (axis.end - 1.0).abs() < 1e-3,
+            "{:?}",
+            axis
+        );
+    }
+}
+
+#[test]
+fn the_cube_welds_to_its_eight_corners() {
+    let mesh = load();
+    let positions = &mesh.data().positions;
+    let mut corners: Vec<(u64, u64, u64)> = positions
+        .iter()
+        .map(|p| (p.0.to_bits(), p.1.to_bits(), p.2.to_bits()))
+        .collect();
+    corners.sort();
+    corners.dedup();
+    assert_eq!(corners.len(), 8);
+    // Unwelded, the 36 facet corners would each be a vertex. Welded, each
+    // corner is split once per side meeting there to keep the edges hard.
+    assert_eq!(mesh.vertex_count(), 24);
+}
+
+#[test]
+fn load_model_picks_the_stl_loader() {
+    let mesh = load_model(
+        Path::new(CUBE),
+        &AssetResolver::new(),
+        Arc::new(Lambertian::from(color(0.5, 0.5, 0.5))),
+        None,
+    )
+    .unwrap();
+    assert_eq!(mesh.triangle_count(), 12);
+}
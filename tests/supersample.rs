@@ -0,0 +1,164 @@
+//! Supersampling: rendering at a multiple of the output size and shrinking
+//! the image back down with a box or Lanczos filter.
+
+use ray_tracer::{
+    color, point,
+    scenes::{self, CameraBuilder, RenderMetadata, Scene},
+    Accumulation, AccumulationError, Camera, CameraError, DownscaleFilter, HittableList,
+    ImageBuffer, LightList, Vec3,
+};
+
+/// The sum of squared differences between neighboring pixels.
+fn gradient_energy(image: &ImageBuffer) -> f64 {
+    let mut energy = 0.0;
+    for y in 0..image.height {
+        for x in 0..image.width {
+            let pixel = image.pixels[y * image.width + x];
+            if x + 1 < image.width {
+                energy += (image.pixels[y * image.width + x + 1] - pixel).length_squared();
+            }
+            if y + 1 < image.height {
+                energy += (image.pixels[(y + 1) * image.width + x] - pixel).length_squared();
+            }
+        }
+    }
+    energy
+}
+
+fn camera() -> Camera {
+    let mut camera = Camera::new(
+        1.0,
+        4,
+        40.,
+        point(0., 0., 1.),
+        point(0., 0., 0.),
+        Vec3(0., 1., 0.),
+        1,
+        2,
+    );
+    camera.set_seed(Some(1));
+    camera
+}
+
+#[test]
+fn supersampling_smooths_the_quads_scene_at_equal_samples() {
+    let render = |factor, samples, filter| {
+        let mut scene = scenes::build("quads").unwrap();
+        scene
+            .camera
+            .set_image_width(64)
+            .set_aa_samples(samples)
+            .set_seed(Some(3))
+            .set_background(color(0.7, 0.8, 1.0))
+            .set_supersample(factor, filter);
+        scene.render_image()
+    };
+    // Four times the pixels at a quarter of the samples each.
+    let direct = gradient_energy(&render(1, 8, DownscaleFilter::Box));
+    for filter in [DownscaleFilter::Box, DownscaleFilter::Lanczos3] {
+        let image = render(2, 2, filter);
+        assert_eq!((image.width, image.height), (64, 64));
+        let supersampled = gradient_energy(&image);
+        assert!(
+            supersampled < direct,
+            "{:?}: {} {}",
+            filter,
+            supersampled,
+            direct
+        );
+    }
+}
+
+#[test]
+fn downscaling_averages_and_keeps_flat_images_flat() {
+    let mut image = ImageBuffer::new(4, 2);
+    for (i, pixel) in image.pixels.iter_mut().enumerate() {
+        *pixel = color(i as f64, 1.0, 0.5);
+    }
+    let boxed = image.downscale(2, DownscaleFilter::Box);
+    assert_eq!((boxed.width, boxed.height), (2, 1));
+    let reds: Vec<f64> = boxed.pixels.iter().map(|p| p.0).collect();
+    assert_eq!(reds, [(0. + 1. + 4. + 5.) / 4., (2. + 3. + 6. + 7.) / 4.]);
+
+    // However the filter's lobes fall near the edges, a flat image stays flat.
+    let flat = ImageBuffer::new(9, 7).downscale(3, DownscaleFilter::Lanczos3);
+    assert_eq!((flat.width, flat.height), (3, 2));
+    let mut gray = ImageBuffer::new(12, 12);
+    gray.pixels.fill(color(0.25, 0.25, 0.25));
+    for pixel in gray.downscale(4, DownscaleFilter::Lanczos3).pixels {
+        assert!((pixel.1 - 0.25).abs() < 1e-12, "{}", pixel.1);
+    }
+}
+
+#[test]
+fn lanczos_ringing_stays_within_black_and_full_coverage() {
+    // A bright square on black with a cutout beside it rings both ways.
+    let mut image = ImageBuffer::new(12, 12);
+    for y in 0..12 {
+        for x in 0..12 {
+            if (4..8).contains(&x) {
+                image.set(x, y, color(10., 10., 10.));
+            }
+            image.set_alpha(x, y, if x < 6 { 1.0 } else { 0.0 });
+        }
+    }
+    let small = image.downscale(2, DownscaleFilter::Lanczos3);
+    let alpha = small
+        .alpha
+        .as_ref()
+        .expect("alpha is shrunk with the color");
+    assert!(alpha.iter().all(|a| (0.0..=1.0).contains(a)));
+    assert!(small.pixels.iter().all(|p| p.0 >= 0.0));
+    assert_eq!("lanczos3".parse(), Ok(DownscaleFilter::Lanczos3));
+    assert!("bicubic".parse::<DownscaleFilter>().is_err());
+}
+
+#[test]
+fn supersampled_renders_report_both_sizes() {
+    let mut camera = camera();
+    camera.set_supersample(0, DownscaleFilter::Box);
+    assert!(matches!(
+        camera.validate(),
+        Err(CameraError::InvalidSupersample(0))
+    ));
+
+    camera.set_supersample(3, DownscaleFilter::Box);
+    let scene = Scene::new(camera.clone(), HittableList::new());
+    let (image, stats) = scene.render_timed();
+    assert_eq!((image.width, image.height), (4, 4));
+    let metadata = RenderMetadata::new("test", &camera, &stats);
+    assert_eq!(
+        (metadata.width, metadata.internal_size),
+        (4, Some((12, 12)))
+    );
+    let read = RenderMetadata::from_json(&metadata.to_json()).unwrap();
+    assert_eq!(read.internal_size, Some((12, 12)));
+    assert!(metadata.to_string().contains("12 by 12"));
+    let plain = RenderMetadata::new("test", &self::camera(), &stats);
+    assert_eq!(plain.internal_size, None);
+}
+
+#[test]
+fn supersampling_is_kept_with_the_camera_but_not_accumulated() {
+    let mut camera = camera();
+    camera.set_supersample(2, DownscaleFilter::Box);
+    let json = serde_json::to_string(&CameraBuilder::from(&camera)).unwrap();
+    assert!(json.contains("\"supersample\":2"), "{}", json);
+    assert!(json.contains("\"downscale_filter\":\"box\""), "{}", json);
+    let built = serde_json::from_str::<CameraBuilder>(&json)
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(
+        (built.supersample, built.downscale_filter),
+        (2, DownscaleFilter::Box)
+    );
+    let plain = serde_json::to_string(&CameraBuilder::from(&Camera::default())).unwrap();
+    assert!(!plain.contains("supersample"), "{}", plain);
+
+    let mut accumulation = Accumulation::new(4, 4, 0);
+    let error = camera
+        .accumulate(&HittableList::new(), &LightList::new(), &mut accumulation)
+        .unwrap_err();
+    assert!(matches!(error, AccumulationError::Supersampled));
+}
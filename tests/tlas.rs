@@ -0,0 +1,238 @@
+//! Two-level BVHs: instanced worlds rendering as built, instances moved by
+//! refitting the top-level tree or rebuilding it, and dragging objects
+//! about a scene without touching the large mesh beside them.
+
+use std::{sync::Arc, time::Instant};
+
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use ray_tracer::{
+    color, parallelepiped, point,
+    scenes::{self, Scene},
+    BoundNode, Camera, DiffuseLight, Hittable, HittableList, IndependentSampler, Instance,
+    Interval, Lambertian, Mat4, Material, Ray, Sphere, Tlas, TlasUpdate, Transformed, Vec3,
+};
+
+fn gray() -> Arc<dyn Material> {
+    Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)))
+}
+
+/// Unit spheres in a row along x, 3 apart.
+fn row(count: usize) -> Vec<Arc<dyn Hittable>> {
+    (0..count)
+        .map(|i| Arc::new(Sphere::new(point(3. * i as f64, 0., 0.), 1., gray())) as _)
+        .collect()
+}
+
+/// A few boxes and spheres and a lamp on a floor, under a BVH as scenes
+/// build them, seen from above at a slant.
+fn room() -> Scene {
+    let mut objects = HittableList::new();
+    objects.add_arc(parallelepiped(
+        point(-10., -1., -10.),
+        point(10., 0., 10.),
+        gray(),
+    ));
+    objects.add_arc(parallelepiped(
+        point(-2., 0., -1.),
+        point(-1., 1., 0.),
+        gray(),
+    ));
+    objects.add(Sphere::new(point(1.5, 0.7, 0.), 0.7, gray()));
+    objects.add(Sphere::new(point(0., 0.5, 2.), 0.5, gray()));
+    let lamp = Arc::new(DiffuseLight::from(color(4., 4., 4.)));
+    objects.add(Sphere::new(point(0., 4., 0.), 1., lamp));
+    let world = HittableList::from(Arc::new(BoundNode::from_list(objects)));
+    let camera = Camera::new(
+        1.0,
+        16,
+        50.,
+        point(0., 6., 8.),
+        point(0., 0., 0.),
+        Vec3(0., 1., 0.),
+        4,
+        4,
+    );
+    let mut scene = Scene::new(camera, world);
+    scene.camera.set_seed(Some(3));
+    scene
+}
+
+#[test]
+fn instanced_worlds_render_as_built() {
+    let mut scene = room();
+    let image = scene.render_image();
+    let fingerprint = scene.fingerprint();
+    scene.instance_world();
+    assert_eq!(scene.instances().unwrap().len(), 5);
+    // Instancing twice changes nothing.
+    scene.instance_world();
+    assert_eq!(scene.instances().unwrap().len(), 5);
+    let instanced = scene.render_image();
+    assert!(image
+        .pixels
+        .iter()
+        .zip(&instanced.pixels)
+        .all(|(p, q)| (p.0, p.1, p.2) == (q.0, q.1, q.2)));
+    assert_eq!(scene.fingerprint(), fingerprint);
+}
+
+#[test]
+fn moved_instances_refit_until_the_tree_grows_too_loose() {
+    let mut tlas = Tlas::from_objects(row(16));
+    let built = tlas.cost();
+    assert_eq!(tlas.update(), TlasUpdate::Unchanged);
+
+    // A small move keeps the tree, a little looser.
+    tlas.set_transform(3, Mat4::translation(Vec3(0., 1., 0.)));
+    assert_eq!(tlas.update(), TlasUpdate::Refit);
+    assert!(tlas.cost() > built, "{} {}", tlas.cost(), built);
+
+    // Swapping the ends of the row spans the whole tree with both halves.
+    tlas.set_transform(0, Mat4::translation(Vec3(45., 0., 0.)));
+    tlas.set_transform(15, Mat4::translation(Vec3(-45., 0., 0.)));
+    assert_eq!(tlas.update(), TlasUpdate::Rebuilt);
+    assert!(tlas.cost() < built * 1.5);
+
+    // Unless that's allowed.
+    tlas.rebuild_threshold = f64::INFINITY;
+    tlas.set_transform(0, Mat4::identity());
+    tlas.set_transform(15, Mat4::identity());
+    tlas.set_transform(7, Mat4::translation(Vec3(0., 0., 100.)));
+    assert_eq!(tlas.update(), TlasUpdate::Refit);
+}
+
+#[test]
+fn moved_instances_are_hit_where_they_are() {
+    let mut rng = SmallRng::seed_from_u64(11);
+    let objects = row(12);
+    let mut tlas = Tlas::from_objects(objects.clone());
+    let mut moved: Vec<Arc<dyn Hittable>> = objects.clone();
+    for _ in 0..20 {
+        let index = rng.gen_range(0..objects.len());
+        let transform = Mat4::translation(Vec3(
+            rng.gen_range(-6.0..6.),
+            rng.gen_range(-6.0..6.),
+            rng.gen_range(-6.0..6.),
+        )) * Mat4::rotation_y(rng.gen_range(0.0..3.));
+        tlas.set_transform(index, transform);
+        tlas.update();
+        moved[index] = Arc::new(Transformed::new(objects[index].clone(), transform));
+    }
+    let fresh = Tlas::new(tlas.instances().to_vec());
+    let mut brute = HittableList::new();
+    for object in moved {
+        brute.add_arc(object);
+    }
+
+    let mut sampler = IndependentSampler::new(0);
+    let mut hits = 0;
+    for _ in 0..2000 {
+        let origin = point(
+            rng.gen_range(-10.0..40.),
+            rng.gen_range(-12.0..12.),
+            rng.gen_range(-12.0..12.),
+        );
+        let target = point(rng.gen_range(-3.0..36.), 0., 0.);
+        let ray = Ray {
+            origin,
+            direction: target - origin,
+            time: 0.0,
+        };
+        let t = Interval::from_range(0.001..f64::INFINITY);
+        let expected = brute.hit(&ray, t, &mut sampler).map(|record| record.t);
+        for tree in [&tlas, &fresh] {
+            let found = tree.hit(&ray, t, &mut sampler).map(|record| record.t);
+            match (found, expected) {
+                (Some(a), Some(b)) => assert!((a - b).abs() < 1e-9, "{} {}", a, b),
+                (a, b) => assert_eq!(a.is_some(), b.is_some(), "{:?}", ray),
+            }
+            assert_eq!(tree.hit_any(&ray, t, &mut sampler), expected.is_some());
+        }
+        hits += expected.is_some() as usize;
+    }
+    assert!(hits > 200, "{}", hits);
+}
+
+#[test]
+fn dragged_objects_follow_the_cursor() {
+    let mut scene = room();
+    let unedited = scene.fingerprint();
+    // Without instances there is nothing to move.
+    assert!(scene.grab_instance(8, 8).is_none());
+    scene.instance_world();
+    let lights = scene.lights.area.objects.len();
+
+    // The sphere at the origin's right, then the lamp.
+    let grab = scene.grab_instance(10, 6).expect("the sphere is there");
+    let sphere = grab.instance;
+    assert!(scene
+        .grab_instance(8, 1)
+        .is_some_and(|lamp| lamp.instance != sphere));
+    assert!(scene.drag_instance(&grab, 3, 11).is_some());
+
+    // It's under the cursor now, slid over the floor, and gone from where
+    // it was.
+    let again = scene.grab_instance(3, 11).expect("the sphere moved there");
+    assert_eq!(again.instance, sphere);
+    let center = scene.instances().unwrap().instances()[sphere]
+        .transform
+        .transform_point(point(1.5, 0.7, 0.));
+    assert!((center.y() - 0.7).abs() < 1e-9, "{:?}", center);
+    assert!(scene
+        .grab_instance(10, 6)
+        .is_none_or(|below| below.instance != sphere));
+
+    // Moving the lamp moves its light, and moving everything back brings
+    // back the scene as it was.
+    let lamp = scene.grab_instance(8, 1).unwrap();
+    scene.drag_instance(&lamp, 6, 1).unwrap();
+    assert_eq!(scene.lights.area.objects.len(), lights);
+    assert_ne!(scene.fingerprint(), unedited);
+    scene.set_instance_transform(sphere, Mat4::identity());
+    scene.set_instance_transform(lamp.instance, Mat4::identity());
+    assert_eq!(scene.fingerprint(), unedited);
+    assert!(scene.set_instance_transform(99, Mat4::identity()).is_none());
+}
+
+#[test]
+fn dragging_beside_a_large_mesh_leaves_it_alone() {
+    // Over 100,000 triangles, one instance.
+    let terrain: Arc<dyn Hittable> = Arc::new(scenes::hills(225, gray()));
+    assert!(terrain.primitive_count() > 100_000);
+    let mut world = HittableList::new();
+    world.add_arc(terrain.clone());
+    world.add_arc(parallelepiped(
+        point(9., 3., 9.),
+        point(11., 5., 11.),
+        gray(),
+    ));
+    let camera = Camera::new(
+        1.0,
+        16,
+        60.,
+        point(10., 12., 25.),
+        point(10., 4., 10.),
+        Vec3(0., 1., 0.),
+        1,
+        2,
+    );
+    let mut scene = Scene::new(camera, world);
+    scene.instance_world();
+    assert_eq!(scene.instances().unwrap().len(), 2);
+
+    let grab = scene.grab_instance(8, 8).expect("the box is in the middle");
+    assert_eq!(grab.instance, 1);
+    let start = Instant::now();
+    for step in 0..100 {
+        let update = scene.drag_instance(&grab, 4 + step % 8, 8).unwrap();
+        assert_ne!(update, TlasUpdate::Unchanged);
+    }
+    // A move costs a refit of a two-instance tree, nothing like the
+    // terrain's BVH; this bound holds with plenty to spare in debug builds.
+    let elapsed = start.elapsed();
+    assert!(elapsed.as_millis() < 500, "{:?}", elapsed);
+    let instances = scene.instances().unwrap().instances();
+    assert!(Arc::ptr_eq(instances[0].placed(), &terrain));
+    assert_eq!(instances[0].transform, Mat4::identity());
+    let _: &Instance = &instances[1];
+}
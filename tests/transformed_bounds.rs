@@ -0,0 +1,250 @@
+//! Bounds of transformed objects come from the placed geometry, not from
+//! the placed corners of the object's own box, so tilted objects stay tight.
+
+use std::sync::Arc;
+
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use ray_tracer::{
+    color, parallelepiped, point, BoundNode, BoundingBox, HitRecord, Hittable, IndependentSampler,
+    Interval, Lambertian, Mat4, Material, MeshData, Point, Quat, Ray, Rotate, RotateY, Sampler,
+    Sphere, Transformed, TraversalStats, Triangle, TriangleMesh, Vec3,
+};
+
+const EPSILON: f64 = 1e-9;
+
+fn gray() -> Arc<dyn Material> {
+    Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)))
+}
+
+/// Asserts `bounds` is the box around `points`, allowing for the padding
+/// given to flat boxes.
+fn assert_tight(bounds: BoundingBox, points: &[Point]) {
+    for axis in 0..3 {
+        let lo = points.iter().map(|p| p[axis]).fold(f64::INFINITY, f64::min);
+        let hi = points
+            .iter()
+            .map(|p| p[axis])
+            .fold(f64::NEG_INFINITY, f64::max);
+        let interval = bounds.intervals[axis];
+        assert!(
+            (interval.start - lo).abs() < 1e-4 && (interval.end - hi).abs() < 1e-4,
+            "axis {}: {:?} is not [{}, {}]",
+            axis,
+            interval,
+            lo,
+            hi
+        );
+    }
+}
+
+fn volume(bounds: &BoundingBox) -> f64 {
+    bounds.intervals.iter().map(|i| i.size()).product()
+}
+
+#[test]
+fn rotated_spheres_keep_their_radius() {
+    let sphere: Arc<dyn Hittable> = Arc::new(Sphere::new(point(2., -1., 0.5), 1.5, gray()));
+    let turned = Rotate::new(sphere.clone(), Quat::from_euler(30., 45., 60.));
+    let center = Quat::from_euler(30., 45., 60.).rotate(point(2., -1., 0.5));
+    let r = Vec3(1.5, 1.5, 1.5);
+    assert_tight(turned.bound(), &[center - r, center + r]);
+
+    // Stretched, it becomes an ellipsoid: check the box against points on it.
+    let m = Mat4::rotation_z(35.) * Mat4::scale(Vec3(3., 1., 0.5));
+    let stretched = Transformed::new(sphere, m);
+    let bounds = stretched.bound();
+    let mut points = Vec::new();
+    for i in 0..=200 {
+        for j in 0..=100 {
+            let (phi, theta) = (i as f64 / 200.0 * 360f64, j as f64 / 100.0 * 180f64);
+            let (phi, theta) = (phi.to_radians(), theta.to_radians());
+            let on = Vec3(
+                theta.sin() * phi.cos(),
+                theta.cos(),
+                theta.sin() * phi.sin(),
+            );
+            points.push(m.transform_point(point(2., -1., 0.5) + on * 1.5));
+        }
+    }
+    for axis in 0..3 {
+        let interval = bounds.intervals[axis];
+        let lo = points.iter().map(|p| p[axis]).fold(f64::INFINITY, f64::min);
+        let hi = points
+            .iter()
+            .map(|p| p[axis])
+            .fold(f64::NEG_INFINITY, f64::max);
+        assert!(interval.start <= lo + EPSILON && hi <= interval.end + EPSILON);
+        // Sampling misses the exact extremes, but only by a little.
+        assert!(lo - interval.start < 1e-2 && interval.end - hi < 1e-2);
+    }
+}
+
+#[test]
+fn triangles_and_meshes_box_their_placed_vertices() {
+    let vertices = [point(0., 0., 0.), point(4., 4., 0.), point(0., 1., 3.)];
+    let triangle: Arc<dyn Hittable> = Arc::new(Triangle::new(
+        (vertices[0], vertices[1], vertices[2]),
+        gray(),
+    ));
+    let rotation = Quat::from_axis_angle(Vec3(1., 0., 1.), 50.);
+    let placed = vertices.map(|p| rotation.rotate(p));
+    assert_tight(Rotate::new(triangle, rotation).bound(), &placed);
+
+    // An octahedron: its box rotated 45° about Y would grow by √2 in x and z.
+    let positions = vec![
+        point(1., 0., 0.),
+        point(-1., 0., 0.),
+        point(0., 1., 0.),
+        point(0., -1., 0.),
+        point(0., 0., 1.),
+        point(0., 0., -1.),
+    ];
+    let faces = vec![
+        [0, 2, 4],
+        [4, 2, 1],
+        [1, 2, 5],
+        [5, 2, 0],
+        [0, 4, 3],
+        [4, 1, 3],
+        [1, 5, 3],
+        [5, 0, 3],
+    ];
+    let mesh = TriangleMesh::new(
+        MeshData {
+            positions: positions.clone(),
+            faces,
+            normals: None,
+            uvs: None,
+            colors: None,
+        },
+        gray(),
+    );
+    let turned = RotateY::new(Arc::new(mesh), 45.);
+    let placed: Vec<Point> = positions
+        .iter()
+        .map(|&p| Mat4::rotation_y(45.).transform_point(p))
+        .collect();
+    assert_tight(turned.bound(), &placed);
+}
+
+#[test]
+fn nested_rotations_compose_before_boxing() {
+    // The tall Cornell block, turned about Y and then tipped over.
+    let block = parallelepiped(point(0., 0., 0.), point(165., 330., 165.), gray());
+    let tip = Quat::from_euler(40., 0., 30.);
+    let tipped = Rotate::new(Arc::new(RotateY::new(block, 45.)), tip);
+    let m = Mat4::from(tip) * Mat4::rotation_y(45.);
+    let box_corners = BoundingBox::around([point(0., 0., 0.), point(165., 330., 165.)]).corners();
+    let placed = box_corners.map(|p| m.transform_point(p));
+    assert_tight(tipped.bound(), &placed);
+
+    // Boxing after each step, as a wrapper without the hook does, is looser.
+    let corners =
+        BoundingBox::around(box_corners.map(|p| Mat4::rotation_y(45.).transform_point(p)))
+            .transformed(&Mat4::from(tip));
+    assert!(volume(&tipped.bound()) < 0.7 * volume(&corners));
+}
+
+#[test]
+fn singular_and_empty_objects_have_empty_bounds() {
+    let sphere: Arc<dyn Hittable> = Arc::new(Sphere::new(point(0., 0., 0.), 1., gray()));
+    let flat = Transformed::new(sphere.clone(), Mat4::scale(Vec3(1., 0., 1.)));
+    assert!(flat.bound().intervals.iter().all(|i| i.start > i.end));
+    assert!(BoundingBox::around([])
+        .intervals
+        .iter()
+        .all(|i| i.start > i.end));
+    assert!(BoundingBox::empty()
+        .transformed(&Mat4::rotation_y(30.))
+        .intervals
+        .iter()
+        .all(|i| i.start > i.end));
+}
+
+/// Hides the geometry of the object inside, so placing it can only box the
+/// placed corners of its bounds: the behavior before the hook existed.
+struct CornerBounded(Arc<dyn Hittable>);
+
+impl Hittable for CornerBounded {
+    fn hit(&self, ray: &Ray, t: Interval, rng: &mut dyn Sampler) -> Option<HitRecord<'_>> {
+        self.0.hit(ray, t, rng)
+    }
+    fn hit_with_stats(
+        &self,
+        ray: &Ray,
+        t: Interval,
+        rng: &mut dyn Sampler,
+        stats: &mut TraversalStats,
+    ) -> Option<HitRecord<'_>> {
+        self.0.hit_with_stats(ray, t, rng, stats)
+    }
+    fn bound(&self) -> BoundingBox {
+        self.0.bound()
+    }
+}
+
+/// Long thin beams at random heading and tilt, like a pile of pick-up sticks.
+fn beams(hidden: bool) -> Vec<Arc<dyn Hittable>> {
+    let mut rng = SmallRng::seed_from_u64(7);
+    (0..200)
+        .map(|_| {
+            let beam = parallelepiped(point(-4., -0.2, -0.2), point(4., 0.2, 0.2), gray());
+            let mut turned: Arc<dyn Hittable> =
+                Arc::new(RotateY::new(beam, rng.gen_range(0.0..360.)));
+            if hidden {
+                turned = Arc::new(CornerBounded(turned));
+            }
+            let tilt = Mat4::from(Quat::from_euler(
+                rng.gen_range(-60.0..60.),
+                0.,
+                rng.gen_range(-60.0..60.),
+            ));
+            let at = Vec3(
+                rng.gen_range(-20.0..20.),
+                rng.gen_range(-20.0..20.),
+                rng.gen_range(-20.0..20.),
+            );
+            Arc::new(Transformed::new(turned, Mat4::translation(at) * tilt)) as Arc<dyn Hittable>
+        })
+        .collect()
+}
+
+#[test]
+fn tighter_bounds_cut_bvh_traversal() {
+    let count = |objects: Vec<Arc<dyn Hittable>>| {
+        let volume: f64 = objects.iter().map(|o| volume(&o.bound())).sum();
+        let bvh = BoundNode::from_objects(&objects, 0..objects.len());
+        let mut stats = TraversalStats::default();
+        let mut hits = Vec::new();
+        let mut rng = SmallRng::seed_from_u64(11);
+        let mut sampler = IndependentSampler::new(0);
+        for _ in 0..2000 {
+            let ray = Ray {
+                origin: Vec3(rng.gen_range(-25.0..25.), rng.gen_range(-25.0..25.), 60.),
+                direction: Vec3(rng.gen_range(-0.3..0.3), rng.gen_range(-0.3..0.3), -1.),
+                time: 0.0,
+            };
+            let t = Interval::new(0.001, f64::INFINITY);
+            hits.push(
+                bvh.hit_with_stats(&ray, t, &mut sampler, &mut stats)
+                    .map(|record| record.t),
+            );
+        }
+        (volume, stats.node_visits + stats.primitive_tests, hits)
+    };
+    let (tight_volume, tight_work, tight_hits) = count(beams(false));
+    let (loose_volume, loose_work, loose_hits) = count(beams(true));
+    assert_eq!(tight_hits, loose_hits);
+    assert!(
+        tight_volume < 0.7 * loose_volume,
+        "{} vs {}",
+        tight_volume,
+        loose_volume
+    );
+    assert!(
+        tight_work < loose_work,
+        "{} vs {} steps",
+        tight_work,
+        loose_work
+    );
+}
@@ -0,0 +1,221 @@
+//! Scene validation reports each kind of broken scene with an issue naming
+//! the object at fault.
+
+use std::sync::Arc;
+
+use ray_tracer::{
+    color, point,
+    scenes::{Scene, Severity, ValidationIssue},
+    BoundNode, BoundingBox, Camera, HitRecord, Hittable, HittableList, Interval, Lambertian,
+    Material, MeshData, Parallelogram, Plane, Ray, Sampler, Sphere, TriangleMesh, Vec3,
+};
+
+fn gray() -> Arc<dyn Material> {
+    Arc::new(Lambertian::from(color(0.5, 0.5, 0.5)))
+}
+
+/// An object that is never hit and reports whatever bounds it is given.
+struct Bounded(BoundingBox);
+
+impl Hittable for Bounded {
+    fn hit(&self, _ray: &Ray, _t: Interval, _rng: &mut dyn Sampler) -> Option<HitRecord<'_>> {
+        None
+    }
+    fn bound(&self) -> BoundingBox {
+        self.0
+    }
+}
+
+fn validate(objects: Vec<Arc<dyn Hittable>>) -> Vec<ValidationIssue> {
+    let mut world = HittableList::new();
+    for object in objects {
+        world.add_arc(object);
+    }
+    Scene::new(Camera::default(), world).validate()
+}
+
+/// Asserts `issues` is exactly one issue of `severity` about `object`, whose
+/// message contains `message`.
+fn assert_one(issues: &[ValidationIssue], severity: Severity, object: &str, message: &str) {
+    match issues {
+        [issue] => {
+            assert_eq!(issue.severity, severity, "{}", issue);
+            assert_eq!(issue.object, object, "{}", issue);
+            assert!(issue.message.contains(message), "{}", issue);
+        }
+        _ => panic!("expected one issue, got {:?}", issues),
+    }
+}
+
+fn mesh(positions: Vec<Vec3>) -> Arc<dyn Hittable> {
+    let data = MeshData {
+        positions,
+        faces: vec![[0, 1, 2]],
+        ..MeshData::default()
+    };
+    Arc::new(TriangleMesh::new(data, gray()))
+}
+
+#[test]
+fn a_sound_scene_has_no_issues() {
+    let issues = validate(vec![Arc::new(Sphere::new(point(0., 0., -1.), 0.5, gray()))]);
+    assert!(issues.is_empty(), "{:?}", issues);
+}
+
+#[test]
+fn an_empty_world_is_an_error() {
+    assert_one(&validate(Vec::new()), Severity::Error, "objects", "empty");
+}
+
+#[test]
+fn a_bad_camera_is_an_error() {
+    let mut scene = Scene::new(Camera::default(), HittableList::new());
+    scene
+        .world
+        .add(Sphere::new(point(0., 0., -1.), 0.5, gray()));
+    scene.camera.set_aa_samples(0);
+    assert_one(&scene.validate(), Severity::Error, "camera", "sample");
+}
+
+#[test]
+fn zero_area_planars_are_errors() {
+    let flat = Parallelogram::new(
+        point(0., 0., 0.),
+        (Vec3(1., 0., 0.), Vec3(2., 0., 0.)),
+        gray(),
+    );
+    assert_one(
+        &validate(vec![Arc::new(flat)]),
+        Severity::Error,
+        "objects[0]",
+        "zero area",
+    );
+}
+
+#[test]
+fn non_finite_planars_are_errors() {
+    let broken = Parallelogram::new(
+        point(0., f64::NAN, 0.),
+        (Vec3(1., 0., 0.), Vec3(0., 1., 0.)),
+        gray(),
+    );
+    assert_one(
+        &validate(vec![Arc::new(broken)]),
+        Severity::Error,
+        "objects[0]",
+        "not finite",
+    );
+}
+
+#[test]
+fn non_finite_spheres_are_errors() {
+    let issues = validate(vec![Arc::new(Sphere::new(
+        point(f64::INFINITY, 0., 0.),
+        1.0,
+        gray(),
+    ))]);
+    assert_one(
+        &issues,
+        Severity::Error,
+        "objects[0]",
+        "center is not finite",
+    );
+}
+
+#[test]
+fn non_finite_mesh_vertices_are_errors() {
+    let issues = validate(vec![mesh(vec![
+        point(0., 0., 0.),
+        point(1., f64::NAN, 0.),
+        point(0., 1., 0.),
+    ])]);
+    assert!(issues.iter().any(|issue| issue.severity == Severity::Error
+        && issue.object == "objects[0]"
+        && issue.message.contains("1 vertices have non-finite")));
+}
+
+#[test]
+fn degenerate_mesh_triangles_are_warnings() {
+    let issues = validate(vec![mesh(vec![
+        point(0., 0., 0.),
+        point(1., 1., 1.),
+        point(2., 2., 2.),
+    ])]);
+    assert_one(&issues, Severity::Warning, "objects[0]", "zero area");
+}
+
+#[test]
+fn nan_bounds_are_errors() {
+    let nan = Interval::new(f64::NAN, 1.0);
+    let issues = validate(vec![Arc::new(Bounded(BoundingBox {
+        intervals: [nan, Interval::new(0., 1.), Interval::new(0., 1.)],
+    }))]);
+    assert_one(&issues, Severity::Error, "objects[0]", "NaN");
+}
+
+#[test]
+fn inverted_bounds_are_errors() {
+    let issues = validate(vec![Arc::new(Bounded(BoundingBox {
+        intervals: [
+            Interval::new(0., 1.),
+            Interval::new(1., -1.),
+            Interval::new(0., 1.),
+        ],
+    }))]);
+    assert_one(&issues, Severity::Error, "objects[0]", "inverted");
+}
+
+#[test]
+fn empty_bounds_are_warnings() {
+    let issues = validate(vec![Arc::new(Bounded(BoundingBox::empty()))]);
+    assert_one(&issues, Severity::Warning, "objects[0]", "empty");
+}
+
+#[test]
+fn duplicate_objects_are_warnings() {
+    // The same object twice, then two separate but identical ones.
+    let shared = Arc::new(Sphere::new(point(0., 0., -1.), 0.5, gray()));
+    let issues = validate(vec![shared.clone(), shared]);
+    assert_one(
+        &issues,
+        Severity::Warning,
+        "objects[1]",
+        "duplicate of objects[0]",
+    );
+
+    let material = gray();
+    let issues = validate(vec![
+        Arc::new(Sphere::new(point(0., 0., -1.), 0.5, material.clone())),
+        Arc::new(Sphere::new(point(0., 0., -1.), 0.5, material)),
+    ]);
+    assert_one(
+        &issues,
+        Severity::Warning,
+        "objects[1]",
+        "duplicate of objects[0]",
+    );
+}
+
+#[test]
+fn unbounded_objects_in_a_bvh_are_errors() {
+    let mut list = HittableList::new();
+    list.add(Sphere::new(point(0., 0., -1.), 0.5, gray()));
+    list.add(Plane::new(point(0., -1., 0.), Vec3(0., 1., 0.)));
+    let issues = validate(vec![Arc::new(BoundNode::from_list(list))]);
+    assert_one(
+        &issues,
+        Severity::Error,
+        "objects[0].objects[1]",
+        "unbounded",
+    );
+}
+
+#[test]
+fn errors_come_before_warnings() {
+    let issues = validate(vec![
+        Arc::new(Bounded(BoundingBox::empty())),
+        Arc::new(Sphere::new(point(0., 0., 0.), -1.0, gray())),
+    ]);
+    let severities: Vec<_> = issues.iter().map(|issue| issue.severity).collect();
+    assert_eq!(severities, vec![Severity::Error, Severity::Warning]);
+}
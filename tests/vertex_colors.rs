@@ -0,0 +1,154 @@
+//! Vertex colors: loaded from PLY and OBJ files, blended across each triangle,
+//! and used as albedo through a vertex-color texture.
+
+use std::{fs, path::Path, sync::Arc};
+
+use rand::{Rng, SeedableRng};
+use ray_tracer::{
+    color, load_model, scenes::Scene, AssetResolver, Color, Hittable, IndependentSampler, Interval,
+    Lambertian, Material, Ray, TriangleMesh, Vec3, VertexColorTexture,
+};
+
+fn vertex_colored() -> Arc<dyn Material> {
+    Arc::new(Lambertian::new(Arc::new(VertexColorTexture::new(color(
+        0.5, 0.5, 0.5,
+    )))))
+}
+
+fn load(path: &Path) -> TriangleMesh {
+    load_model(path, &AssetResolver::new(), vertex_colored(), None).unwrap()
+}
+
+/// Shoots rays from all around at the unit cube centered on the origin and
+/// checks the albedo at every hit against `expected` at the hit point.
+fn assert_albedo(mesh: &TriangleMesh, expected: impl Fn(Vec3) -> Color) {
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(4);
+    let mut sampler = IndependentSampler::new(4);
+    for _ in 0..200 {
+        let from = Vec3::random_unit(&mut rng) * 3.0;
+        let target = Vec3(
+            rng.gen_range(-0.3..0.3),
+            rng.gen_range(-0.3..0.3),
+            rng.gen_range(-0.3..0.3),
+        );
+        let ray = Ray {
+            origin: from,
+            direction: target - from,
+            time: 0.0,
+        };
+        let record = mesh
+            .hit(
+                &ray,
+                Interval::from_range(0.001..f64::INFINITY),
+                &mut sampler,
+            )
+            .expect("the ray missed the cube");
+        let albedo = record
+            .material
+            .scatter(&ray, &record, &mut sampler)
+            .unwrap()
+            .attenuation;
+        let want = expected(record.point);
+        assert!(
+            (albedo - want).length() < 1e-9,
+            "at {:?}: {:?}, expected {:?}",
+            record.point,
+            albedo,
+            want
+        );
+    }
+}
+
+#[test]
+fn ply_colors_blend_smoothly_across_faces() {
+    // The fixture's corner colors are their positions shifted into [0, 1], so
+    // blending them anywhere on the surface gives the shifted hit point.
+    let mesh = load(Path::new("resources/models/gradient_cube.ply"));
+    assert!(mesh.data().colors.is_some());
+    assert_albedo(&mesh, |p| p + Vec3(0.5, 0.5, 0.5));
+}
+
+#[test]
+fn obj_vertex_colors_are_read_after_the_position() {
+    let mut obj = String::from("o cube\n");
+    for i in 0..8 {
+        let (x, y, z) = ((i & 1) as f64, ((i >> 1) & 1) as f64, ((i >> 2) & 1) as f64);
+        // Colors run the other way from the PLY fixture's.
+        obj += &format!(
+            "v {} {} {} {} {} {}\n",
+            x - 0.5,
+            y - 0.5,
+            z - 0.5,
+            1. - x,
+            1. - y,
+            1. - z
+        );
+    }
+    for [a, b, c, d] in [
+        [1, 3, 4, 2],
+        [5, 6, 8, 7],
+        [1, 2, 6, 5],
+        [3, 7, 8, 4],
+        [1, 5, 7, 3],
+        [2, 4, 8, 6],
+    ] {
+        obj += &format!("f {} {} {} {}\n", a, b, c, d);
+    }
+    let dir = std::env::temp_dir().join(format!("ray-tracer-vertex-colors-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("cube.obj");
+    fs::write(&path, obj).unwrap();
+    let mesh = load(&path);
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_albedo(&mesh, |p| Vec3(0.5, 0.5, 0.5) - p);
+}
+
+#[test]
+fn meshes_without_colors_use_the_fallback() {
+    let dir = std::env::temp_dir().join(format!("ray-tracer-no-colors-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("cube.obj");
+    let mut obj = String::new();
+    for i in 0..8 {
+        let (x, y, z) = ((i & 1) as f64, ((i >> 1) & 1) as f64, ((i >> 2) & 1) as f64);
+        obj += &format!("v {} {} {}\n", x - 0.5, y - 0.5, z - 0.5);
+    }
+    obj += "f 1 3 4 2\nf 5 6 8 7\nf 1 2 6 5\nf 3 7 8 4\nf 1 5 7 3\nf 2 4 8 6\n";
+    fs::write(&path, obj).unwrap();
+    let mesh = load(&path);
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(mesh.data().colors.is_none());
+    assert_albedo(&mesh, |_| color(0.5, 0.5, 0.5));
+}
+
+#[test]
+fn gradient_cube_scene_loads() {
+    let scene = Scene::from_path(Path::new("resources/scenes/vertex_colors.toml")).unwrap();
+    assert!(scene.validate().is_empty());
+}
+
+#[test]
+fn colored_objs_keep_their_material_library() {
+    let dir = std::env::temp_dir().join(format!("ray-tracer-colored-mtl-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("tri.mtl"),
+        "newmtl paint\nNs 0.0\nKa 1.0 1.0 1.0\nKd 0.2 0.4 0.6\nKs 0.0 0.0 0.0\nd 1.0\nillum 1\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("tri.obj"),
+        "mtllib tri.mtl\no tri\nv 0 0 0 1 0 0\nv 1 0 0 0 1 0\nv 0 1 0 0 0 1\nusemtl paint\nf 1 2 3\n",
+    )
+    .unwrap();
+    let mesh = load(&dir.join("tri.obj"));
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(mesh.materials().len(), 1);
+    let colors = mesh.data().colors.as_ref().unwrap();
+    assert_eq!(colors.len(), 3);
+    let total = colors.iter().fold(Vec3(0., 0., 0.), |sum, &c| sum + c);
+    assert!((total - Vec3(1., 1., 1.)).length() < 1e-12);
+}
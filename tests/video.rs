@@ -0,0 +1,258 @@
+//! Animations: turntable cameras, frames rendered in parallel written in
+//! order, and the GIF and ffmpeg video sinks.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{Arc, Mutex},
+};
+
+use ray_tracer::{
+    color, open_video, point,
+    scenes::{self, frame_time, Scene, Turntable},
+    FfmpegSettings, FfmpegSink, ImageBuffer, ReorderBuffer, VideoError, VideoSink,
+};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("ray-tracer-video-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// The material spheres from further back, small and quick, with a sky to
+/// see them against.
+fn spheres() -> Scene {
+    let mut scene = scenes::material_spheres();
+    let camera = &mut scene.camera;
+    camera
+        .move_camera(point(0., 1., 2.), point(0., 0., -1.), camera.up)
+        .unwrap();
+    camera
+        .set_image_width(17)
+        .set_aa_samples(2)
+        .set_max_depth(3)
+        .set_seed(Some(9))
+        .set_background(color(0.7, 0.8, 1.0));
+    scene
+}
+
+/// Keeps the frames written to it, in the order they came.
+#[derive(Clone, Default)]
+struct Frames(Arc<Mutex<Vec<ImageBuffer>>>);
+
+impl VideoSink for Frames {
+    fn write_frame(&mut self, frame: &ImageBuffer) -> Result<(), VideoError> {
+        let mut frames = self.0.lock().unwrap();
+        if let Some(first) = frames.first() {
+            if (first.width, first.height) != (frame.width, frame.height) {
+                return Err(VideoError::FrameSize {
+                    expected: (first.width, first.height),
+                    found: (frame.width, frame.height),
+                });
+            }
+        }
+        frames.push(frame.clone());
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), VideoError> {
+        Ok(())
+    }
+}
+
+fn same(a: &ImageBuffer, b: &ImageBuffer) -> bool {
+    a.pixels
+        .iter()
+        .zip(&b.pixels)
+        .all(|(p, q)| (p.0, p.1, p.2) == (q.0, q.1, q.2))
+}
+
+#[test]
+fn the_reorder_buffer_releases_items_in_order() {
+    let mut buffer = ReorderBuffer::new();
+    assert!(buffer.push(2, 'c').is_empty());
+    assert!(buffer.push(1, 'b').is_empty());
+    assert_eq!(buffer.held(), 2);
+    assert_eq!(buffer.push(0, 'a'), vec!['a', 'b', 'c']);
+    assert_eq!(buffer.push(3, 'd'), vec!['d']);
+    // Late or repeated items are dropped.
+    assert!(buffer.push(1, 'x').is_empty());
+    assert!(buffer.push(5, 'f').is_empty());
+    assert!(buffer.push(5, 'y').is_empty());
+    assert_eq!(buffer.push(4, 'e'), vec!['e', 'f']);
+    assert_eq!((buffer.next_index(), buffer.held()), (6, 0));
+}
+
+#[test]
+fn parallel_frames_arrive_in_order_as_rendered_alone() {
+    let scene = spheres();
+    let turntable = Turntable::new(5);
+    let frames = Frames::default();
+    scene
+        .render_animation(
+            5,
+            3,
+            &|frame| turntable.camera_at(&scene.camera, frame),
+            Box::new(frames.clone()),
+        )
+        .unwrap();
+    let frames = frames.0.lock().unwrap();
+    assert_eq!(frames.len(), 5);
+    for (index, frame) in frames.iter().enumerate() {
+        let alone = turntable
+            .camera_at(&scene.camera, index)
+            .render_image(&scene.world, &scene.lights);
+        assert!(same(frame, &alone), "frame {}", index);
+    }
+    // The turntable starts where the camera is and moves from there.
+    assert!(same(&frames[0], &scene.render_image()));
+    assert!(!same(&frames[0], &frames[1]));
+}
+
+#[test]
+fn turntables_circle_the_look_at_point() {
+    let scene = spheres();
+    let camera = &scene.camera;
+    let turntable = Turntable::new(4);
+    let radius = |c: &ray_tracer::Camera| (c.look_from - c.look_at).length();
+    let quarter = turntable.camera_at(camera, 1);
+    assert!((radius(&quarter) - radius(camera)).abs() < 1e-9);
+    assert!((quarter.look_from.y() - camera.look_from.y()).abs() < 1e-9);
+    // A quarter turn counterclockwise seen from above takes +z to +x.
+    assert!(
+        (quarter.look_from.x() - 3.0).abs() < 1e-9,
+        "{:?}",
+        quarter.look_from
+    );
+    let around = turntable.camera_at(camera, 4);
+    assert!((around.look_from - camera.look_from).length() < 1e-9);
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn turntables_write_animated_gifs() {
+    let dir = temp_dir("gif");
+    let path = dir.join("spheres.gif");
+    let scene = spheres();
+    let (width, height) = scene.camera.output_size();
+    let settings = FfmpegSettings {
+        fps: 10,
+        ..FfmpegSettings::default()
+    };
+    let sink = open_video(&path, width, height, &settings).unwrap();
+    let turntable = Turntable::new(6);
+    scene
+        .render_animation(
+            6,
+            2,
+            &|frame| turntable.camera_at(&scene.camera, frame),
+            sink,
+        )
+        .unwrap();
+
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(fs::File::open(&path).unwrap()).unwrap();
+    assert_eq!((decoder.width(), decoder.height()), (17, 10));
+    let mut delays = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        delays.push(frame.delay);
+    }
+    assert_eq!(delays, vec![10; 6]);
+
+    // And frames of another size are refused.
+    let mut sink = open_video(&path, width, height, &settings).unwrap();
+    let error = sink
+        .write_frame(&ImageBuffer::new(4, 4))
+        .expect_err("wrong size");
+    assert!(matches!(error, VideoError::FrameSize { .. }), "{}", error);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+fn has_ffmpeg() -> bool {
+    Command::new("ffmpeg")
+        .arg("-version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+#[test]
+fn videos_go_through_ffmpeg_when_there_is_one() {
+    let dir = temp_dir("mp4");
+    let path = dir.join("spheres.mp4");
+    let missing = FfmpegSettings {
+        program: Path::new("/nonexistent/ffmpeg").to_path_buf(),
+        ..FfmpegSettings::default()
+    };
+    let error = FfmpegSink::spawn(&missing, &path, 17, 9)
+        .err()
+        .expect("no such program");
+    assert!(matches!(error, VideoError::FfmpegMissing { .. }));
+    assert!(error.to_string().contains("install ffmpeg"), "{}", error);
+
+    if has_ffmpeg() {
+        let scene = spheres();
+        let sink = open_video(&path, 17, 9, &FfmpegSettings::default()).unwrap();
+        let turntable = Turntable::new(24);
+        scene
+            .render_animation(
+                24,
+                2,
+                &|frame| turntable.camera_at(&scene.camera, frame),
+                sink,
+            )
+            .unwrap();
+        assert!(fs::metadata(&path).unwrap().len() > 0);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn frame_times_run_from_zero_to_one() {
+    assert_eq!(frame_time(0, 5), 0.0);
+    assert_eq!(frame_time(2, 5), 0.5);
+    assert_eq!(frame_time(4, 5), 1.0);
+    assert_eq!(frame_time(0, 1), 0.0);
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn changing_scenes_render_into_numbered_frames() {
+    use ray_tracer::{scenes::render_sequence, Lambertian, Sphere};
+
+    let dir = temp_dir("sequence");
+    let frames_dir = dir.join("frames");
+    // A red ball rolls from left to right over the spheres.
+    let scene_at = |t: f64| {
+        let mut scene = spheres();
+        let red = Arc::new(Lambertian::from(color(0.9, 0.2, 0.2)));
+        scene.add(Arc::new(Sphere::new(
+            point(2. * t - 1., 0.8, -1.),
+            0.3,
+            red,
+        )));
+        scene
+    };
+    let sink = open_video(&frames_dir, 17, 9, &FfmpegSettings::default()).unwrap();
+    let times = render_sequence(3, 2, &scene_at, sink).unwrap();
+    assert_eq!(times.len(), 3);
+
+    let names: Vec<_> = (1..=3)
+        .map(|i| frames_dir.join(format!("frame_{:04}.png", i)))
+        .collect();
+    assert!(names.iter().all(|name| name.exists()));
+    assert!(!frames_dir.join("frame_0004.png").exists());
+    let first = image::open(&names[0]).unwrap().to_rgb8();
+    let expected = scene_at(0.0).render_image().to_rgba8();
+    assert!(first
+        .pixels()
+        .zip(expected.chunks(4))
+        .all(|(a, b)| a.0 == b[..3]));
+    let last = image::open(&names[2]).unwrap().to_rgb8();
+    assert_ne!(first, last);
+    fs::remove_dir_all(&dir).unwrap();
+}
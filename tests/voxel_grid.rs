@@ -0,0 +1,259 @@
+//! Voxel-grid media: densities read from a grid file, interpolated between
+//! samples, and tracked through to find where rays scatter.
+
+use std::{fs, path::Path, sync::Arc};
+
+use ray_tracer::{
+    color, load_vol, load_volume, point,
+    scenes::{self, Scene},
+    AssetResolver, BoundingBox, Hittable, IndependentSampler, Interval, LoadError, Mat4, Ray, Vec3,
+    VoxelGrid, VoxelGridMedium,
+};
+
+/// A 3 by 2 by 2 grid over the box from (0, 0, 0) to (2, 1, 1), each
+/// sample its index.
+fn counting_grid() -> VoxelGrid {
+    let bounds = BoundingBox::new(
+        Interval::new(0., 2.),
+        Interval::new(0., 1.),
+        Interval::new(0., 1.),
+    );
+    VoxelGrid::new([3, 2, 2], bounds, (0..12).map(|i| i as f32).collect()).unwrap()
+}
+
+/// A grid of one density throughout the unit cube.
+fn uniform_grid(density: f32) -> VoxelGrid {
+    let unit = Interval::new(0., 1.);
+    VoxelGrid::new(
+        [2, 2, 2],
+        BoundingBox::new(unit, unit, unit),
+        vec![density; 8],
+    )
+    .unwrap()
+}
+
+fn ray(origin: ray_tracer::Point, direction: Vec3) -> Ray {
+    Ray {
+        origin,
+        direction,
+        time: 0.0,
+    }
+}
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("ray-tracer-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn densities_interpolate_between_grid_points() {
+    let grid = counting_grid();
+    assert_eq!(grid.max_density(), 11.0);
+    // At the grid points, exactly the samples.
+    for z in 0..2 {
+        for y in 0..2 {
+            for x in 0..3 {
+                let at = point(x as f64, y as f64, z as f64);
+                assert_eq!(grid.density_at(at), grid.voxel(x, y, z), "{:?}", at);
+            }
+        }
+    }
+    assert_eq!(grid.voxel(2, 1, 1), 11.0);
+    // Halfway along each axis, the average of the neighbors.
+    assert_eq!(grid.density_at(point(0.5, 0., 0.)), 0.5);
+    assert_eq!(grid.density_at(point(0., 0.5, 0.)), 1.5);
+    assert_eq!(grid.density_at(point(0., 0., 0.5)), 3.0);
+    assert!((grid.density_at(point(1.5, 0.5, 0.5)) - 5.5 - 1.5 + 1.0).abs() < 1e-12);
+    // Outside the grid, nothing.
+    for outside in [
+        point(-0.01, 0.5, 0.5),
+        point(1., 1.01, 0.5),
+        point(1., 0.5, 2.),
+    ] {
+        assert_eq!(grid.density_at(outside), 0.0);
+    }
+}
+
+#[test]
+fn grids_round_trip_through_vol_files() {
+    let grid = counting_grid();
+    let dir = temp_dir("vol");
+    let path = dir.join("counting.vol");
+    fs::write(&path, grid.to_vol_bytes()).unwrap();
+    let loaded = load_volume(&path, &AssetResolver::new());
+
+    let truncated = dir.join("truncated.vol");
+    fs::write(&truncated, &grid.to_vol_bytes()[..60]).unwrap();
+    let error = load_vol(&truncated).err();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let loaded = loaded.unwrap();
+    assert_eq!(loaded.resolution, [3, 2, 2]);
+    assert_eq!(loaded.densities, grid.densities);
+    assert_eq!(loaded.bounds.intervals[0].end, 2.0);
+    assert_eq!(loaded.path.as_deref(), Some(path.as_path()));
+    match error {
+        Some(LoadError::Decode { message, .. }) => {
+            assert!(message.contains("bytes"), "{}", message)
+        }
+        other => panic!("expected a decode error, got {:?}", other),
+    }
+    assert!(matches!(
+        load_volume(Path::new("grid.vdb"), &AssetResolver::new()),
+        Err(LoadError::Missing(_))
+    ));
+}
+
+#[test]
+fn huge_resolutions_are_decode_errors() {
+    let mut bytes = counting_grid().to_vol_bytes();
+    for axis in 0..3 {
+        let at = 8 + 4 * axis;
+        bytes[at..at + 4].copy_from_slice(&i32::MAX.to_le_bytes());
+    }
+    let dir = temp_dir("huge");
+    let path = dir.join("huge.vol");
+    fs::write(&path, &bytes).unwrap();
+    let error = load_vol(&path).err();
+    fs::remove_dir_all(&dir).unwrap();
+    match error {
+        Some(LoadError::Decode { message, .. }) => {
+            assert!(message.contains("too large"), "{}", message)
+        }
+        other => panic!("expected a decode error, got {:?}", other),
+    }
+}
+
+#[test]
+fn empty_axes_are_errors_and_single_samples_fill_the_grid() {
+    let unit = Interval::new(0., 1.);
+    let bounds = BoundingBox::new(unit, unit, unit);
+    for resolution in [[0, 2, 2], [2, 0, 2], [2, 2, 0], [0, 0, 0]] {
+        let error = VoxelGrid::new(resolution, bounds, Vec::new()).err();
+        assert!(
+            error
+                .as_deref()
+                .is_some_and(|e| e.contains("at least one sample")),
+            "{:?}: {:?}",
+            resolution,
+            error
+        );
+    }
+    let single = VoxelGrid::new([1, 1, 1], bounds, vec![0.75]).unwrap();
+    assert_eq!(single.density_at(point(0.5, 0.5, 0.5)), 0.75);
+    assert_eq!(single.density_at(point(1.0, 0.0, 1.0)), 0.75);
+}
+
+#[test]
+fn delta_tracking_matches_the_transmittance_of_a_uniform_grid() {
+    // A density of 0.5 scaled by 4 through a box squashed to 0.5 deep is
+    // an optical depth of one, so exp(-1) of the light gets through.
+    let placement = Mat4::scale(Vec3(1., 1., 0.5));
+    let medium = VoxelGridMedium::from_color(
+        Arc::new(uniform_grid(0.5)),
+        4.0,
+        color(1., 1., 1.),
+        placement,
+    )
+    .unwrap();
+    let mut rng = IndependentSampler::new(7);
+    let rays = 20_000;
+    let mut through = 0;
+    for _ in 0..rays {
+        let ray = ray(point(0.5, 0.5, -1.), Vec3(0., 0., 2.));
+        match medium.hit(&ray, Interval::new(0.0, f64::INFINITY), &mut rng) {
+            Some(record) => assert!((0.0..=0.5).contains(&record.point.2)),
+            None => through += 1,
+        }
+    }
+    let transmittance = through as f64 / rays as f64;
+    assert!(
+        (transmittance - (-1.0f64).exp()).abs() < 0.01,
+        "{}",
+        transmittance
+    );
+}
+
+#[test]
+fn placed_grids_are_bounded_and_missed_outside() {
+    let placement = Mat4::translation(Vec3(10., 0., 0.)) * Mat4::scale(Vec3(2., 2., 2.));
+    let medium = VoxelGridMedium::from_color(
+        Arc::new(uniform_grid(100.)),
+        1.0,
+        color(1., 1., 1.),
+        placement,
+    )
+    .unwrap();
+    let bound = medium.bound();
+    assert_eq!(
+        (bound.intervals[0].start, bound.intervals[0].end),
+        (10.0, 12.0)
+    );
+    let mut rng = IndependentSampler::new(1);
+    let through = ray(point(11., 1., -5.), Vec3(0., 0., 1.));
+    let record = medium
+        .hit(&through, Interval::new(0.0, f64::INFINITY), &mut rng)
+        .expect("so dense a grid stops the ray");
+    assert!(
+        record.point.2 >= 0.0 && record.point.2 < 0.1,
+        "{:?}",
+        record.point
+    );
+    let beside = ray(point(9., 1., -5.), Vec3(0., 0., 1.));
+    assert!(medium
+        .hit(&beside, Interval::new(0.0, f64::INFINITY), &mut rng)
+        .is_none());
+    assert!(VoxelGridMedium::from_color(
+        Arc::new(uniform_grid(1.)),
+        1.0,
+        color(1., 1., 1.),
+        Mat4::scale(Vec3(1., 0., 1.)),
+    )
+    .is_none());
+}
+
+#[test]
+fn scene_files_place_grids_from_files_beside_them() {
+    let dir = temp_dir("voxel-scene");
+    fs::write(dir.join("smoke.vol"), uniform_grid(0.5).to_vol_bytes()).unwrap();
+    let path = dir.join("scene.toml");
+    fs::write(
+        &path,
+        r#"
+        [[objects]]
+        type = "voxel_medium"
+        path = "smoke.vol"
+        density = 0.2
+        color = [0.8, 0.8, 0.8]
+        transform = { translation = [0.0, 1.0, 0.0], scale = [3.0, 3.0, 3.0] }
+        "#,
+    )
+    .unwrap();
+    let scene = Scene::from_path(&path);
+    let missing = dir.join("missing.toml");
+    fs::write(
+        &missing,
+        "[[objects]]\ntype = \"voxel_medium\"\npath = \"nowhere.vol\"\ncolor = [1.0, 1.0, 1.0]\n",
+    )
+    .unwrap();
+    let error = Scene::from_path(&missing).err();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let scene = scene.unwrap();
+    let bound = scene.world.bound();
+    assert_eq!(
+        (bound.intervals[1].start, bound.intervals[1].end),
+        (1.0, 4.0)
+    );
+    let desc = scene.describe().unwrap();
+    let saved = toml::to_string(&desc).unwrap();
+    assert!(saved.contains("type = \"voxel_medium\""), "{}", saved);
+    assert!(saved.contains("density = 0.2"), "{}", saved);
+    assert!(error.unwrap().to_string().contains("nowhere.vol"));
+
+    // The bundled plume loads and fits inside the box.
+    let plume = scenes::build("cornell_plume").unwrap();
+    let box_bound = plume.world.bound();
+    assert!(box_bound.intervals[1].end <= 556.0, "{:?}", box_bound);
+}
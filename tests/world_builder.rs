@@ -0,0 +1,170 @@
+//! The world builder and short constructors must build exactly what the
+//! spelled-out constructors do.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use ray_tracer::{
+    color, point,
+    scenes::{self, cornell, Place, Scene, WorldBuilder},
+    BoundingBox, Camera, Hittable, HittableList, IndependentSampler, Interval, Lambertian, Ray,
+    RotateY, Sphere, Translation, Vec3,
+};
+
+fn same_bounds(a: BoundingBox, b: BoundingBox) {
+    for axis in 0..3 {
+        assert_eq!(a.intervals[axis].start, b.intervals[axis].start);
+        assert_eq!(a.intervals[axis].end, b.intervals[axis].end);
+    }
+}
+
+/// A hash of the exact bits of a small seeded render.
+fn checksum(mut scene: Scene) -> u64 {
+    scene
+        .camera
+        .set_image_width(24)
+        .set_aa_samples(4)
+        .set_max_depth(6)
+        .set_seed(Some(7));
+    let image = scene.render_image();
+    let mut hasher = DefaultHasher::new();
+    for pixel in &image.pixels {
+        [pixel.0, pixel.1, pixel.2]
+            .map(f64::to_bits)
+            .hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// The Cornell box as it was built before the builder existed.
+fn cornell_by_hand() -> Scene {
+    let mut world = cornell::empty_box(cornell::STANDARD_SIZE);
+    world.add_arc(cornell::ceiling_light(15., cornell::STANDARD_SIZE));
+    for block in cornell::standard_boxes() {
+        world.add_arc(block);
+    }
+    Scene::new(
+        Camera::new(
+            1.0,
+            600,
+            40.0,
+            point(278., 278., -800.),
+            point(278., 278., 0.),
+            Vec3(0., 1., 0.),
+            50,
+            20,
+        ),
+        world,
+    )
+}
+
+#[test]
+fn the_built_cornell_box_matches_the_hand_built_one() {
+    let (built, by_hand) = (scenes::cornell_box(), cornell_by_hand());
+    assert_eq!(built.world.objects.len(), by_hand.world.objects.len());
+    assert_eq!(
+        built.lights.area.objects.len(),
+        by_hand.lights.area.objects.len()
+    );
+    same_bounds(built.bounds(), by_hand.bounds());
+    for (a, b) in built.world.objects.iter().zip(&by_hand.world.objects) {
+        same_bounds(a.bound(), b.bound());
+    }
+    assert_eq!(checksum(built), checksum(by_hand));
+}
+
+#[test]
+fn placement_chains_wrap_innermost_first() {
+    let white = scenes::lambertian(color(0.73, 0.73, 0.73));
+    let chained = scenes::cuboid(point(0., 0., 0.), point(1., 2., 1.), white.clone())
+        .rotate_y(30.)
+        .translate(Vec3(5., 0., -2.));
+    let block = ray_tracer::parallelepiped(point(0., 0., 0.), point(1., 2., 1.), white);
+    let nested = Translation::new(Arc::new(RotateY::new(block, 30.)), Vec3(5., 0., -2.));
+    same_bounds(chained.bound(), nested.bound());
+
+    let t = Interval::new(0.001, f64::INFINITY);
+    let mut rng = IndependentSampler::new(0);
+    for x in [4.6, 5.0, 5.4, 5.9] {
+        let ray = Ray {
+            origin: point(x, 1., -10.),
+            direction: Vec3(0., 0., 1.),
+            time: 0.0,
+        };
+        assert_eq!(
+            chained.hit(&ray, t, &mut rng).map(|hit| hit.t),
+            nested.hit(&ray, t, &mut rng).map(|hit| hit.t)
+        );
+    }
+}
+
+#[test]
+fn builders_keep_objects_in_order_and_register_lights() {
+    let lamp = scenes::diffuse_light(color(4., 4., 4.));
+    let scene = WorldBuilder::new()
+        .sphere(point(0., 0., 0.), 1., scenes::dielectric(1.5))
+        .quad(
+            point(-1., 3., -1.),
+            Vec3(2., 0., 0.),
+            Vec3(0., 0., 2.),
+            lamp,
+        )
+        .extend([scenes::sphere(
+            point(3., 0., 0.),
+            0.5,
+            scenes::metal(color(0.8, 0.8, 0.8), 0.1),
+        )])
+        .cuboid(
+            point(-5., -1., -5.),
+            point(5., -0.5, 5.),
+            scenes::textured(scenes::noise(4.)),
+        )
+        .scene(Camera::new(
+            1.0,
+            8,
+            40.0,
+            point(0., 1., -6.),
+            point(0., 0., 0.),
+            Vec3(0., 1., 0.),
+            1,
+            2,
+        ));
+    assert_eq!(scene.world.objects.len(), 4);
+    assert_eq!(scene.lights.area.objects.len(), 1);
+    let first = scene.world.objects[0].bound();
+    same_bounds(
+        first,
+        Sphere::new(
+            point(0., 0., 0.),
+            1.,
+            Arc::new(Lambertian::from(color(0., 0., 0.))),
+        )
+        .bound(),
+    );
+    assert!(scene.world.objects[0]
+        .describe(&mut Default::default())
+        .is_some());
+}
+
+#[test]
+fn built_worlds_are_plain_lists() {
+    let white = scenes::lambertian(color(0.73, 0.73, 0.73));
+    let world: HittableList = WorldBuilder::new()
+        .triangle(
+            point(0., 0., 0.),
+            point(1., 0., 0.),
+            point(0., 1., 0.),
+            white.clone(),
+        )
+        .object(scenes::sphere(point(0., 0., 5.), 1., white))
+        .build();
+    assert_eq!(world.objects.len(), 2);
+    same_bounds(
+        world.bound(),
+        BoundingBox::from_boxes(world.objects[0].bound(), world.objects[1].bound()),
+    );
+    assert!(WorldBuilder::new().build().objects.is_empty());
+}